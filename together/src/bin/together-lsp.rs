@@ -0,0 +1,10 @@
+use std::io;
+use std::process;
+
+// Language Server für Oberon-Quelltexte, spricht LSP über stdin/stdout
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let code = together::lsp::run(&mut stdin.lock(), &mut stdout.lock());
+    process::exit(code);
+}
@@ -0,0 +1,158 @@
+use super::{BytecodeModule, Function, Instruction, TypeDescKind};
+use std::fmt::Write;
+
+// ============================================================================
+// Disassembler
+// ============================================================================
+//
+// Lesbare Auflistung eines Moduls: Typtabelle, globale Variablen und je
+// Prozedur die Befehle mit Befehlsindex und Quelltextzeile. Operanden, die auf
+// Tabellen verweisen, werden mit dem Namen kommentiert.
+
+pub fn disassemble(module: &BytecodeModule) -> String {
+    let mut out = String::new();
+    writeln!(out, "MODULE {}", module.name).unwrap();
+    if !module.imports.is_empty() {
+        writeln!(out, "IMPORT {}", module.imports.join(", ")).unwrap();
+    }
+
+    if !module.types.is_empty() {
+        writeln!(out, "\nTYPE").unwrap();
+        for (index, desc) in module.types.iter().enumerate() {
+            let kind = match &desc.kind {
+                TypeDescKind::Reference => "Verweis".to_string(),
+                TypeDescKind::Array { length, element } => format!("ARRAY {} OF {}", length, type_name(module, *element)),
+                TypeDescKind::Record { base, fields } => {
+                    let fields: Vec<String> = fields.iter().map(|&field| type_name(module, field)).collect();
+                    match base {
+                        Some(base) => format!("RECORD ({}) {} END", type_name(module, *base), fields.join(", ")),
+                        None => format!("RECORD {} END", fields.join(", ")),
+                    }
+                }
+                basic => basic_name(basic).unwrap_or_default().to_string(),
+            };
+            match &desc.name {
+                Some(name) => writeln!(out, "  {:>3}  {} = {}", index, name, kind).unwrap(),
+                None => writeln!(out, "  {:>3}  {}", index, kind).unwrap(),
+            }
+        }
+    }
+
+    if !module.globals.is_empty() {
+        writeln!(out, "\nVAR").unwrap();
+        for (index, global) in module.globals.iter().enumerate() {
+            let mark = if global.exported { "*" } else { "" };
+            writeln!(out, "  {:>3}  {}{}: {}", index, global.name, mark, type_name(module, global.ty)).unwrap();
+        }
+    }
+
+    for (index, function) in module.functions.iter().enumerate() {
+        let mark = if function.exported { "*" } else { "" };
+        writeln!(out, "\nPROCEDURE {} {}{}", index, function.name, mark).unwrap();
+        function_listing(&mut out, module, function);
+    }
+    writeln!(out, "\nBEGIN").unwrap();
+    function_listing(&mut out, module, &module.body);
+    out
+}
+
+fn function_listing(out: &mut String, module: &BytecodeModule, function: &Function) {
+    if !function.params.is_empty() {
+        let params: Vec<&str> = function
+            .params
+            .iter()
+            .map(|&is_var| if is_var { "VAR" } else { "Wert" })
+            .collect();
+        writeln!(out, "  Parameter: {}", params.join(", ")).unwrap();
+    }
+    if !function.locals.is_empty() {
+        let locals: Vec<String> = function.locals.iter().map(|&ty| type_name(module, ty)).collect();
+        writeln!(out, "  Lokal: {}", locals.join(", ")).unwrap();
+    }
+
+    let mut lines = function.lines.iter().peekable();
+    for (pc, instruction) in function.code.iter().enumerate() {
+        // Zeilennummer nur dort, wo eine neue Zeile beginnt
+        let line = match lines.peek() {
+            Some(&&(start, line)) if start as usize == pc => {
+                lines.next();
+                line.to_string()
+            }
+            _ => String::new(),
+        };
+        writeln!(out, "  {:>4} {:>5}  {}", pc, line, listing(module, instruction)).unwrap();
+    }
+}
+
+// Name aus der Typdeklaration, Grundtypen beim Namen, sonst T<Index>
+fn type_name(module: &BytecodeModule, ty: u32) -> String {
+    let name = module
+        .types
+        .get(ty as usize)
+        .and_then(|desc| desc.name.clone().or_else(|| basic_name(&desc.kind).map(str::to_string)));
+    name.unwrap_or_else(|| format!("T{}", ty))
+}
+
+fn basic_name(kind: &TypeDescKind) -> Option<&'static str> {
+    match kind {
+        TypeDescKind::Integer => Some("INTEGER"),
+        TypeDescKind::Real => Some("REAL"),
+        TypeDescKind::Boolean => Some("BOOLEAN"),
+        TypeDescKind::Char => Some("CHAR"),
+        TypeDescKind::Set => Some("SET"),
+        TypeDescKind::String => Some("STRING"),
+        _ => None,
+    }
+}
+
+fn listing(module: &BytecodeModule, instruction: &Instruction) -> String {
+    use Instruction::*;
+    let function_name = |index: &u32| match module.functions.get(*index as usize) {
+        Some(function) => function.name.clone(),
+        None => "?".to_string(),
+    };
+    let import_name = |index: &u32| match module.imports.get(*index as usize) {
+        Some(name) => name.clone(),
+        None => "?".to_string(),
+    };
+
+    match instruction {
+        PushInt(value) => format!("PushInt {}", value),
+        PushReal(value) => format!("PushReal {:?}", value),
+        PushBool(value) => format!("PushBool {}", if *value { "TRUE" } else { "FALSE" }),
+        PushChar(ch) => format!("PushChar {:X}X", *ch as u32),
+        PushSet(bits) => format!("PushSet {:#x}", bits),
+        PushString(text) => format!("PushString {:?}", text),
+        PushProc(index) => format!("PushProc {}  ; {}", index, function_name(index)),
+        PushExternProc { module: import, function } => {
+            format!("PushExternProc {} {}  ; {}", import, function, import_name(import))
+        }
+        GlobalAddr(index) => match module.globals.get(*index as usize) {
+            Some(global) => format!("GlobalAddr {}  ; {}", index, global.name),
+            None => format!("GlobalAddr {}", index),
+        },
+        LocalAddr(slot) => format!("LocalAddr {}", slot),
+        OuterAddr { function, slot } => format!("OuterAddr {} {}  ; {}", function, slot, function_name(function)),
+        ExternGlobalAddr { module: import, slot } => {
+            format!("ExternGlobalAddr {} {}  ; {}", import, slot, import_name(import))
+        }
+        Field(index) => format!("Field {}", index),
+        Index(length) => format!("Index {}", length),
+        Project(ty) => format!("Project {}", type_name(module, *ty)),
+        New(ty) => format!("New {}", type_name(module, *ty)),
+        Is(ty) => format!("Is {}", type_name(module, *ty)),
+        InRange(start, end) => format!("InRange {} {}", start, end),
+        Jump(to) => format!("Jump {}", to),
+        JumpIfFalse(to) => format!("JumpIfFalse {}", to),
+        JumpIfTrue(to) => format!("JumpIfTrue {}", to),
+        Call(index) => format!("Call {}  ; {}", index, function_name(index)),
+        CallExtern { module: import, function } => {
+            format!("CallExtern {} {}  ; {}", import, function, import_name(import))
+        }
+        CallIndirect(count) => format!("CallIndirect {}", count),
+        Library(procedure) => format!("Library {}", procedure),
+        Trap(kind) => format!("Trap {:?}", kind),
+        // Befehle ohne Operanden heißen wie ihre Variante
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,537 @@
+use super::{BytecodeModule, Function, Global, Instruction, TrapKind, TypeDesc, TypeDescKind};
+use crate::semantic::library::LibraryProcedure;
+
+// ============================================================================
+// Binärformat
+// ============================================================================
+//
+// Kennung "OBC", Versionsbyte, danach Name, Importe, Typen, globale
+// Variablen, Prozeduren und Modulrumpf. Zahlen sind little-endian mit fester
+// Breite (u32, i64, f64 als Bitmuster), Zeichenketten haben ein u32-Längenpräfix
+// und sind UTF-8, Listen ebenso ein u32-Längenpräfix. Jeder Befehl ist ein
+// Opcode-Byte mit seinen Operanden.
+
+const MAGIC: &[u8; 3] = b"OBC";
+const VERSION: u8 = 1;
+
+pub fn encode(module: &BytecodeModule) -> Vec<u8> {
+    let mut w = Writer { bytes: Vec::new() };
+    w.bytes.extend_from_slice(MAGIC);
+    w.u8(VERSION);
+
+    w.string(&module.name);
+    w.u32(module.imports.len() as u32);
+    for import in &module.imports {
+        w.string(import);
+    }
+
+    w.u32(module.types.len() as u32);
+    for desc in &module.types {
+        w.type_desc(desc);
+    }
+
+    w.u32(module.globals.len() as u32);
+    for global in &module.globals {
+        w.string(&global.name);
+        w.u32(global.ty);
+        w.bool(global.exported);
+    }
+
+    w.u32(module.functions.len() as u32);
+    for function in &module.functions {
+        w.function(function);
+    }
+    w.function(&module.body);
+    w.bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<BytecodeModule, String> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(MAGIC.len())? != MAGIC {
+        return Err("Keine Bytecode-Datei (Kennung OBC fehlt)".to_string());
+    }
+    let version = r.u8()?;
+    if version != VERSION {
+        return Err(format!("Bytecode-Version {} wird nicht unterstützt (erwartet {})", version, VERSION));
+    }
+
+    let name = r.string()?;
+    let imports = r.list(|r| r.string())?;
+    let types = r.list(|r| r.type_desc())?;
+    let globals = r.list(|r| {
+        Ok(Global {
+            name: r.string()?,
+            ty: r.u32()?,
+            exported: r.bool()?,
+        })
+    })?;
+    let functions = r.list(|r| r.function())?;
+    let body = r.function()?;
+
+    if r.pos != bytes.len() {
+        return Err(format!("{} überzählige Bytes am Dateiende", bytes.len() - r.pos));
+    }
+    let module = BytecodeModule { name, imports, types, globals, functions, body };
+    validate(&module)?;
+    Ok(module)
+}
+
+// Verweise auf Typen, Variablen, Prozeduren und Sprungziele müssen im Modul
+// liegen, damit die VM sie ohne weitere Prüfung benutzen kann
+fn validate(module: &BytecodeModule) -> Result<(), String> {
+    let types = module.types.len() as u32;
+    let type_ok = |ty: u32| {
+        if ty < types {
+            Ok(())
+        } else {
+            Err(format!("Typindex {} außerhalb der Typtabelle", ty))
+        }
+    };
+
+    for desc in &module.types {
+        match &desc.kind {
+            TypeDescKind::Array { element, .. } => type_ok(*element)?,
+            TypeDescKind::Record { base, fields } => {
+                base.iter().chain(fields).try_for_each(|&ty| type_ok(ty))?;
+            }
+            _ => {}
+        }
+    }
+    for global in &module.globals {
+        type_ok(global.ty)?;
+    }
+
+    for function in module.functions.iter().chain([&module.body]) {
+        function.locals.iter().try_for_each(|&ty| type_ok(ty))?;
+        let slots = (function.params.len() + function.locals.len()) as u32;
+        let length = function.code.len() as u32;
+        let fail = |pc: usize, what: &str| Err(format!("{}, Befehl {}: {}", function.name, pc, what));
+
+        for (pc, instruction) in function.code.iter().enumerate() {
+            match instruction {
+                Instruction::GlobalAddr(index) if *index as usize >= module.globals.len() => {
+                    return fail(pc, "unbekannte globale Variable");
+                }
+                Instruction::LocalAddr(slot) if *slot >= slots => return fail(pc, "unbekannte lokale Variable"),
+                Instruction::OuterAddr { function: outer, slot } => match module.functions.get(*outer as usize) {
+                    Some(outer) if (*slot as usize) < outer.params.len() + outer.locals.len() => {}
+                    _ => return fail(pc, "unbekannte Variable einer umschließenden Prozedur"),
+                },
+                Instruction::Jump(to) | Instruction::JumpIfFalse(to) | Instruction::JumpIfTrue(to) if *to >= length => {
+                    return fail(pc, "Sprungziel außerhalb des Codes");
+                }
+                Instruction::PushProc(index) | Instruction::Call(index) if *index as usize >= module.functions.len() => {
+                    return fail(pc, "unbekannte Prozedur");
+                }
+                // Prozeduren und Variablen importierter Module prüft erst die VM beim Laden
+                Instruction::PushExternProc { module: import, .. }
+                | Instruction::ExternGlobalAddr { module: import, .. }
+                | Instruction::CallExtern { module: import, .. }
+                    if *import as usize >= module.imports.len() =>
+                {
+                    return fail(pc, "unbekanntes importiertes Modul");
+                }
+                Instruction::Project(ty) | Instruction::New(ty) | Instruction::Is(ty) => type_ok(*ty)?,
+                _ => {}
+            }
+        }
+        if !matches!(function.code.last(), Some(Instruction::Return | Instruction::ReturnValue)) {
+            return Err(format!("{}: Code endet ohne RETURN", function.name));
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Schreiben
+// ============================================================================
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn type_desc(&mut self, desc: &TypeDesc) {
+        match &desc.name {
+            Some(name) => {
+                self.bool(true);
+                self.string(name);
+            }
+            None => self.bool(false),
+        }
+        match &desc.kind {
+            TypeDescKind::Integer => self.u8(0),
+            TypeDescKind::Real => self.u8(1),
+            TypeDescKind::Boolean => self.u8(2),
+            TypeDescKind::Char => self.u8(3),
+            TypeDescKind::Set => self.u8(4),
+            TypeDescKind::String => self.u8(5),
+            TypeDescKind::Reference => self.u8(6),
+            TypeDescKind::Array { length, element } => {
+                self.u8(7);
+                self.u32(*length);
+                self.u32(*element);
+            }
+            TypeDescKind::Record { base, fields } => {
+                self.u8(8);
+                // Ohne Basistyp: u32::MAX
+                self.u32(base.unwrap_or(u32::MAX));
+                self.u32(fields.len() as u32);
+                for field in fields {
+                    self.u32(*field);
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.string(&function.name);
+        self.bool(function.exported);
+        self.u32(function.params.len() as u32);
+        for is_var in &function.params {
+            self.bool(*is_var);
+        }
+        self.u32(function.locals.len() as u32);
+        for ty in &function.locals {
+            self.u32(*ty);
+        }
+        self.u32(function.code.len() as u32);
+        for instruction in &function.code {
+            self.instruction(instruction);
+        }
+        self.u32(function.lines.len() as u32);
+        for (pc, line) in &function.lines {
+            self.u32(*pc);
+            self.u32(*line);
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        use Instruction::*;
+        self.u8(opcode(instruction));
+        match instruction {
+            PushInt(value) => self.i64(*value),
+            PushReal(value) => self.bytes.extend_from_slice(&value.to_bits().to_le_bytes()),
+            PushBool(value) => self.bool(*value),
+            PushChar(ch) => self.u32(*ch as u32),
+            PushString(text) => self.string(text),
+            PushSet(value) | PushProc(value) | GlobalAddr(value) | LocalAddr(value) | Field(value) | Index(value)
+            | Project(value) | New(value) | Is(value) | Jump(value) | JumpIfFalse(value) | JumpIfTrue(value)
+            | Call(value) | CallIndirect(value) => self.u32(*value),
+            OuterAddr { function, slot } => {
+                self.u32(*function);
+                self.u32(*slot);
+            }
+            PushExternProc { module, function } | CallExtern { module, function } => {
+                self.u32(*module);
+                self.u32(*function);
+            }
+            ExternGlobalAddr { module, slot } => {
+                self.u32(*module);
+                self.u32(*slot);
+            }
+            InRange(start, end) => {
+                self.i64(*start);
+                self.i64(*end);
+            }
+            Library(procedure) => {
+                let index = LibraryProcedure::ALL.iter().position(|p| p == procedure).unwrap_or_default();
+                self.u8(index as u8);
+            }
+            Trap(kind) => self.u8(match kind {
+                TrapKind::Assert => 0,
+                TrapKind::Case => 1,
+            }),
+            _ => {}
+        }
+    }
+}
+
+fn opcode(instruction: &Instruction) -> u8 {
+    use Instruction::*;
+    match instruction {
+        PushInt(_) => 0,
+        PushReal(_) => 1,
+        PushBool(_) => 2,
+        PushChar(_) => 3,
+        PushSet(_) => 4,
+        PushString(_) => 5,
+        PushNil => 6,
+        PushProc(_) => 7,
+        GlobalAddr(_) => 8,
+        LocalAddr(_) => 9,
+        OuterAddr { .. } => 10,
+        Field(_) => 11,
+        Index(_) => 12,
+        Deref => 13,
+        Load => 14,
+        Store => 15,
+        Dup => 16,
+        Pop => 17,
+        Project(_) => 18,
+        New(_) => 19,
+        Add => 20,
+        Sub => 21,
+        Mul => 22,
+        Div => 23,
+        IntDiv => 24,
+        Mod => 25,
+        Neg => 26,
+        Not => 27,
+        ToReal => 28,
+        Eq => 29,
+        Ne => 30,
+        Lt => 31,
+        Le => 32,
+        Gt => 33,
+        Ge => 34,
+        In => 35,
+        Is(_) => 36,
+        InRange(..) => 37,
+        Incl => 38,
+        Excl => 39,
+        InclRange => 40,
+        Abs => 41,
+        Odd => 42,
+        Lsl => 43,
+        Asr => 44,
+        Ror => 45,
+        Floor => 46,
+        Ord => 47,
+        Chr => 48,
+        Jump(_) => 49,
+        JumpIfFalse(_) => 50,
+        JumpIfTrue(_) => 51,
+        Call(_) => 52,
+        CallIndirect(_) => 53,
+        Return => 54,
+        ReturnValue => 55,
+        Library(_) => 56,
+        Trap(_) => 57,
+        Chars => 58,
+        PushExternProc { .. } => 59,
+        ExternGlobalAddr { .. } => 60,
+        CallExtern { .. } => 61,
+        Inc => 62,
+        Dec => 63,
+    }
+}
+
+// ============================================================================
+// Lesen
+// ============================================================================
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, count: usize) -> Result<&'b [u8], String> {
+        match self.bytes.get(self.pos..self.pos + count) {
+            Some(slice) => {
+                self.pos += count;
+                Ok(slice)
+            }
+            None => Err(format!("Unerwartetes Dateiende bei Byte {}", self.pos)),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, String> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(format!("Ungültiger Wahrheitswert {} bei Byte {}", other, self.pos - 1)),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        let start = self.pos;
+        let bytes = self.take(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| format!("Ungültiges UTF-8 bei Byte {}", start))
+    }
+
+    // Liste mit Längenpräfix; die Länge wird nicht zum Reservieren benutzt,
+    // damit beschädigte Dateien keinen riesigen Speicher anfordern
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let count = self.u32()?;
+        let mut items = Vec::new();
+        for _ in 0..count {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn type_desc(&mut self) -> Result<TypeDesc, String> {
+        let name = match self.bool()? {
+            true => Some(self.string()?),
+            false => None,
+        };
+        let kind = match self.u8()? {
+            0 => TypeDescKind::Integer,
+            1 => TypeDescKind::Real,
+            2 => TypeDescKind::Boolean,
+            3 => TypeDescKind::Char,
+            4 => TypeDescKind::Set,
+            5 => TypeDescKind::String,
+            6 => TypeDescKind::Reference,
+            7 => TypeDescKind::Array {
+                length: self.u32()?,
+                element: self.u32()?,
+            },
+            8 => {
+                let base = match self.u32()? {
+                    u32::MAX => None,
+                    base => Some(base),
+                };
+                TypeDescKind::Record { base, fields: self.list(|r| r.u32())? }
+            }
+            tag => return Err(format!("Unbekannte Typart {} bei Byte {}", tag, self.pos - 1)),
+        };
+        Ok(TypeDesc { name, kind })
+    }
+
+    fn function(&mut self) -> Result<Function, String> {
+        Ok(Function {
+            name: self.string()?,
+            exported: self.bool()?,
+            params: self.list(|r| r.bool())?,
+            locals: self.list(|r| r.u32())?,
+            code: self.list(|r| r.instruction())?,
+            lines: self.list(|r| Ok((r.u32()?, r.u32()?)))?,
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, String> {
+        use Instruction::*;
+        let opcode = self.u8()?;
+        let instruction = match opcode {
+            0 => PushInt(self.i64()?),
+            1 => PushReal(f64::from_bits(self.i64()? as u64)),
+            2 => PushBool(self.bool()?),
+            3 => {
+                let code = self.u32()?;
+                PushChar(char::from_u32(code).ok_or_else(|| format!("Ungültiges Zeichen {:#x}", code))?)
+            }
+            4 => PushSet(self.u32()?),
+            5 => PushString(self.string()?),
+            6 => PushNil,
+            7 => PushProc(self.u32()?),
+            8 => GlobalAddr(self.u32()?),
+            9 => LocalAddr(self.u32()?),
+            10 => OuterAddr {
+                function: self.u32()?,
+                slot: self.u32()?,
+            },
+            11 => Field(self.u32()?),
+            12 => Index(self.u32()?),
+            13 => Deref,
+            14 => Load,
+            15 => Store,
+            16 => Dup,
+            17 => Pop,
+            18 => Project(self.u32()?),
+            19 => New(self.u32()?),
+            20 => Add,
+            21 => Sub,
+            22 => Mul,
+            23 => Div,
+            24 => IntDiv,
+            25 => Mod,
+            26 => Neg,
+            27 => Not,
+            28 => ToReal,
+            29 => Eq,
+            30 => Ne,
+            31 => Lt,
+            32 => Le,
+            33 => Gt,
+            34 => Ge,
+            35 => In,
+            36 => Is(self.u32()?),
+            37 => InRange(self.i64()?, self.i64()?),
+            38 => Incl,
+            39 => Excl,
+            40 => InclRange,
+            41 => Abs,
+            42 => Odd,
+            43 => Lsl,
+            44 => Asr,
+            45 => Ror,
+            46 => Floor,
+            47 => Ord,
+            48 => Chr,
+            49 => Jump(self.u32()?),
+            50 => JumpIfFalse(self.u32()?),
+            51 => JumpIfTrue(self.u32()?),
+            52 => Call(self.u32()?),
+            53 => CallIndirect(self.u32()?),
+            54 => Return,
+            55 => ReturnValue,
+            56 => {
+                let index = self.u8()?;
+                Library(
+                    *LibraryProcedure::ALL
+                        .get(index as usize)
+                        .ok_or_else(|| format!("Unbekannte Bibliotheksprozedur {}", index))?,
+                )
+            }
+            57 => Trap(match self.u8()? {
+                0 => TrapKind::Assert,
+                1 => TrapKind::Case,
+                kind => return Err(format!("Unbekannte Trap-Art {}", kind)),
+            }),
+            58 => Chars,
+            59 => PushExternProc {
+                module: self.u32()?,
+                function: self.u32()?,
+            },
+            60 => ExternGlobalAddr {
+                module: self.u32()?,
+                slot: self.u32()?,
+            },
+            61 => CallExtern {
+                module: self.u32()?,
+                function: self.u32()?,
+            },
+            62 => Inc,
+            63 => Dec,
+            _ => return Err(format!("Unbekannter Opcode {} bei Byte {}", opcode, self.pos - 1)),
+        };
+        Ok(instruction)
+    }
+}
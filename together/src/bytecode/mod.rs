@@ -0,0 +1,277 @@
+use crate::semantic::library::LibraryProcedure;
+
+mod disassembler;
+mod format;
+
+pub use disassembler::disassemble;
+pub use format::{decode, encode};
+
+// ============================================================================
+// Bytecode
+// ============================================================================
+//
+// Kompaktes Format für die Stack-VM (crate::vm). Ein Modul enthält seine
+// Typbeschreibungen, globalen Variablen und Prozeduren; die Befehle arbeiten
+// auf einem Operandenstapel. Adressen (Orte von Variablen, Feldern und
+// Array-Elementen) liegen ebenfalls auf dem Stapel, so dass VAR-Parameter und
+// Zuweisungen ohne eigene Befehle für jede Variablenart auskommen.
+
+// Dateiendung übersetzter Module
+pub const EXTENSION: &str = "obc";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BytecodeModule {
+    pub name: String,
+    // Importierte Module außer den eingebauten (Out)
+    pub imports: Vec<String>,
+    pub types: Vec<TypeDesc>,
+    pub globals: Vec<Global>,
+    pub functions: Vec<Function>,
+    // Modulrumpf, läuft einmal beim Laden
+    pub body: Function,
+}
+
+// ============================================================================
+// Typen
+// ============================================================================
+
+// Typbeschreibung für Anfangswerte, NEW und Typtests; Verweise auf andere
+// Typen sind Indizes in BytecodeModule::types
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDesc {
+    pub name: Option<String>,
+    pub kind: TypeDescKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeDescKind {
+    Integer,
+    Real,
+    Boolean,
+    Char,
+    Set,
+    String,
+    // Zeiger- und Prozedurtypen; Anfangswert NIL
+    Reference,
+    Array { length: u32, element: u32 },
+    // Alle Felder einschließlich der geerbten, Basistyp zuerst
+    Record { base: Option<u32>, fields: Vec<u32> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    pub name: String,
+    pub ty: u32,
+    pub exported: bool,
+}
+
+// ============================================================================
+// Prozeduren
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    // Qualifizierter Name ("P", "P.Q")
+    pub name: String,
+    pub exported: bool,
+    // Je Parameter: VAR-Parameter?
+    pub params: Vec<bool>,
+    // Typen der lokalen Variablen; ihre Plätze folgen auf die Parameter
+    pub locals: Vec<u32>,
+    pub code: Vec<Instruction>,
+    // (Befehlsindex, Zeile), aufsteigend nach Befehlsindex
+    pub lines: Vec<(u32, u32)>,
+}
+
+impl Function {
+    pub fn new(name: &str) -> Self {
+        Function {
+            name: name.to_string(),
+            exported: false,
+            params: Vec::new(),
+            locals: Vec::new(),
+            code: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    // Zeile des Befehls an `pc` (0, wenn unbekannt)
+    pub fn line(&self, pc: usize) -> u32 {
+        let index = self.lines.partition_point(|&(start, _)| start as usize <= pc);
+        match index {
+            0 => 0,
+            _ => self.lines[index - 1].1,
+        }
+    }
+}
+
+// ============================================================================
+// Befehle
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    // Konstanten
+    PushInt(i64),
+    PushReal(f64),
+    PushBool(bool),
+    PushChar(char),
+    PushSet(u32),
+    PushString(String),
+    PushNil,
+    PushProc(u32),
+    // Prozedur eines importierten Moduls: Index in BytecodeModule::imports
+    // und in dessen Prozeduren
+    PushExternProc { module: u32, function: u32 },
+
+    // Adressen: globale Variable, Platz im eigenen Rahmen, Platz im jüngsten
+    // Rahmen einer umschließenden Prozedur
+    GlobalAddr(u32),
+    LocalAddr(u32),
+    OuterAddr { function: u32, slot: u32 },
+    // Globale Variable eines importierten Moduls
+    ExternGlobalAddr { module: u32, slot: u32 },
+    // Adresse -> Adresse des Felds (Position in allen Feldern des Records)
+    Field(u32),
+    // Adresse, Index -> Adresse des Elements; Operand ist die Array-Länge
+    Index(u32),
+    // Adresse eines Zeigers -> Adresse des Objekts
+    Deref,
+
+    // Speicher und Stapel
+    Load,
+    // Adresse, Wert -> ; Records behalten ihren dynamischen Typ
+    Store,
+    Dup,
+    Pop,
+    // Record -> Record des Basistyps (Typindex)
+    Project(u32),
+    // Adresse eines Zeigers -> ; legt ein Objekt des Typs an
+    New(u32),
+
+    // Arithmetik (INTEGER, REAL, SET)
+    Add,
+    Sub,
+    Mul,
+    Div,
+    IntDiv,
+    Mod,
+    Neg,
+    Not,
+    ToReal,
+    // String -> ARRAY OF CHAR mit den Zeichen des Strings
+    Chars,
+
+    // Vergleiche
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    // Element, Menge -> BOOLEAN
+    In,
+    // Zeiger oder Record -> BOOLEAN (Typindex)
+    Is(u32),
+    // INTEGER -> liegt im Bereich?
+    InRange(i64, i64),
+
+    // Mengen: Menge, Element -> Menge bzw. Menge, von, bis -> Menge
+    Incl,
+    Excl,
+    InclRange,
+
+    // INC und DEC: Wert, Schritt -> Wert wie Add und Sub; ein Überlauf
+    // nennt die Prozedur
+    Inc,
+    Dec,
+
+    // Standardfunktionen
+    Abs,
+    Odd,
+    Lsl,
+    Asr,
+    Ror,
+    Floor,
+    Ord,
+    Chr,
+
+    // Sprünge (Befehlsindex)
+    Jump(u32),
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+
+    // Aufrufe: Argumente liegen auf dem Stapel, bei VAR-Parametern Adressen
+    Call(u32),
+    // Aufruf einer Prozedur eines importierten Moduls
+    CallExtern { module: u32, function: u32 },
+    // Argumente, Prozedurwert -> ; Operand ist die Anzahl der Argumente
+    CallIndirect(u32),
+    Return,
+    ReturnValue,
+
+    Library(LibraryProcedure),
+    Trap(TrapKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapKind {
+    Assert,
+    // Nimmt den Wert ohne passendes Label vom Stapel
+    Case,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::BytecodeGenerator;
+    use crate::testing::checked;
+
+    fn compile(source: &str) -> BytecodeModule {
+        BytecodeGenerator::new().generate(&checked(source)).unwrap()
+    }
+
+    const SOURCE: &str = "MODULE M;
+        IMPORT Out, Lib;
+        TYPE P = RECORD x: INTEGER; r: REAL END;
+        VAR v*: P; s: SET;
+        PROCEDURE Max*(a, b: INTEGER): INTEGER;
+          VAR m: INTEGER;
+        BEGIN
+          IF a > b THEN m := a ELSE m := b END
+          RETURN m
+        END Max;
+        BEGIN
+          v.x := Max(2, 3); v.r := 1.5; s := {1..3};
+          Out.String(\"x\"); Out.Char(\"y\")
+        END M.";
+
+    #[test]
+    fn test_round_trip() {
+        let module = compile(SOURCE);
+        assert_eq!(module.imports, ["Lib"]);
+        assert_eq!(decode(&encode(&module)), Ok(module.clone()));
+
+        // Jede Kürzung und ein fremder Opcode werden erkannt, ohne Panik
+        let bytes = encode(&module);
+        for length in 0..bytes.len() {
+            assert!(decode(&bytes[..length]).is_err());
+        }
+        let mut broken = module.clone();
+        broken.functions[0].code.insert(0, Instruction::Jump(1000));
+        assert!(decode(&encode(&broken)).unwrap_err().contains("Sprungziel"));
+        assert!(decode(b"MZ\x90\x00").unwrap_err().contains("Kennung"));
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let listing = disassemble(&compile(SOURCE));
+        assert!(listing.starts_with("MODULE M\nIMPORT Lib\n"));
+        assert!(listing.contains("P = RECORD INTEGER, REAL END"));
+        assert!(listing.contains("v*: P"));
+        assert!(listing.contains("PROCEDURE 0 Max*\n  Parameter: Wert, Wert\n  Lokal: INTEGER\n"));
+        assert!(listing.contains("     8  LocalAddr 0"));
+        assert!(listing.contains("Call 0  ; Max"));
+        assert!(listing.contains("Library Out.Char"));
+    }
+}
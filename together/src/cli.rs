@@ -0,0 +1,1263 @@
+use crate::bytecode;
+use crate::codegen::{c_generator, x86_generator, BytecodeGenerator, CGenerator, RiscvGenerator, WasmGenerator, X86Generator};
+use crate::doc::{self, ModuleDoc};
+use crate::fmt::{self, FormatOptions, KeywordCase};
+use crate::interpreter::{self, Interpreter};
+use crate::ir::{self, opt::OptLevel, opt::Remark};
+#[cfg(feature = "llvm")]
+use crate::codegen::llvm_generator::{self, Emit, LLVMGenerator};
+#[cfg(feature = "llvm")]
+use inkwell::context::Context;
+use crate::lint::{LintConfig, Linter, Severity};
+use crate::parser::ast::Module;
+use crate::parser::{Parser, PrettyPrinter};
+use crate::repl;
+use crate::scanner::{self, Comment, Lexer};
+use crate::semantic::{hir, ModuleLoader, Warning, WarningKind, WarningOptions};
+use crate::vm::{Loader, Vm};
+use crate::riscv;
+use crate::wasm;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Unterbefehle der Kommandozeile
+// ============================================================================
+
+// Liest, scannt und parst eine Quelldatei
+fn parse_file(path: &Path) -> Result<(Module, Vec<Comment>), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Kann Datei nicht lesen: {}", e))?;
+    let tokens = Lexer::new(&source).tokenize()?;
+    let comments = scanner::comments(&tokens);
+    let module = Parser::new(tokens).parse()?;
+    Ok((module, comments))
+}
+
+// Lädt und prüft die Datei samt der Module, die sie importiert (ModuleLoader,
+// gesucht im Verzeichnis der Datei); meldet Fehler und Warnungen mit Datei
+// und Zeile und liefert das Modul der Datei
+fn load_module(path: &Path, warnings: &WarningOptions) -> Option<hir::Module> {
+    let mut loader = ModuleLoader::with_warnings(warnings.clone());
+    loader.add_path(source_dir(path));
+    match loader.load_file(path) {
+        Ok(modules) => {
+            for loaded in &modules {
+                print_warnings(&loaded.path, &loaded.warnings);
+            }
+            modules.into_iter().last().map(|loaded| loaded.module)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+// Verzeichnis der Quelldatei, "." für einen Dateinamen ohne Verzeichnis
+fn source_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn print_warnings(path: &Path, warnings: &[Warning]) {
+    for warning in warnings {
+        eprintln!("{}:{}: {}", path.display(), warning.line, warning);
+    }
+}
+
+// -W<name> schaltet eine Kategorie von Warnungen ein, -Wno-<name> aus;
+// -Wall bzw. -Wno-all gelten für alle
+fn warning_switch(flag: &str, warnings: &mut WarningOptions) -> Result<(), String> {
+    let (name, enabled) = match flag.strip_prefix("-Wno-") {
+        Some(name) => (name, false),
+        None => (&flag[2..], true),
+    };
+    if name == "all" {
+        *warnings = if enabled { WarningOptions::all() } else { WarningOptions::none() };
+        return Ok(());
+    }
+    match WarningKind::from_name(name) {
+        Some(kind) => {
+            warnings.set(kind, enabled);
+            Ok(())
+        }
+        None => {
+            let names: Vec<&str> = WarningKind::ALL.iter().map(|kind| kind.name()).collect();
+            Err(format!("Unbekannte Warnung: {} (bekannt: all, {})", name, names.join(", ")))
+        }
+    }
+}
+
+// together lint [--config <datei>] [--list-rules] <datei>...
+//
+// Exit-Code 0 ohne Befunde der Schwere error, 1 mit solchen Befunden,
+// 2 bei Lese-, Syntax- oder Konfigurationsfehlern.
+pub fn lint(args: &[String]) -> i32 {
+    let mut config_path: Option<PathBuf> = None;
+    let mut list_rules = false;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => match args.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--config erwartet einen Dateinamen");
+                    return 2;
+                }
+            },
+            "--list-rules" => list_rules = true,
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    if list_rules {
+        let config = config_path.or_else(|| LintConfig::find(Path::new(".")));
+        return match load_linter(config.as_deref()) {
+            Ok(linter) => {
+                for (rule, severity) in linter.rules() {
+                    println!("{:<18} {:<8} {}", rule.name(), severity.name(), rule.description());
+                }
+                0
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                2
+            }
+        };
+    }
+
+    if files.is_empty() {
+        eprintln!("Verwendung: together lint [--config <datei>] [--list-rules] <datei>...");
+        return 2;
+    }
+
+    let mut status = 0;
+    for file in &files {
+        // Ohne --config gilt die nächstgelegene together.lint der Quelldatei
+        let found;
+        let config = match &config_path {
+            Some(path) => Some(path.as_path()),
+            None => {
+                found = LintConfig::find(file.parent().unwrap_or(Path::new(".")));
+                found.as_deref()
+            }
+        };
+
+        let result = load_linter(config).and_then(|linter| {
+            let (module, comments) = parse_file(file)?;
+            Ok(linter.lint(&module, &comments))
+        });
+
+        match result {
+            Ok(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    println!("{}:{}", file.display(), diagnostic);
+                    if diagnostic.severity == Severity::Error {
+                        status = status.max(1);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                status = 2;
+            }
+        }
+    }
+    status
+}
+
+fn load_linter(config_path: Option<&Path>) -> Result<Linter, String> {
+    let config = match config_path {
+        Some(path) => LintConfig::load(path)?,
+        None => LintConfig::new(),
+    };
+    Linter::new(&config)
+}
+
+// together fmt [--check] [--indent <n>] [--width <n>] [--keyword-case upper|lower] [--no-align] <datei>...
+//
+// Formatiert die Dateien an Ort und Stelle. Mit --check wird nichts
+// geschrieben; Exit-Code 1, wenn eine Datei nicht formatiert ist.
+// 2 bei Lese-, Schreib- oder Syntaxfehlern. --keyword-case lower schreibt
+// Schlüsselwörter klein; das Ergebnis übersetzt der Compiler nicht mehr.
+pub fn fmt(args: &[String]) -> i32 {
+    let mut options = FormatOptions::new();
+    let mut check = false;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let result = match arg.as_str() {
+            "--check" => {
+                check = true;
+                Ok(())
+            }
+            "--no-align" => {
+                options.align = false;
+                Ok(())
+            }
+            "--indent" => number_argument(arg, args.next()).map(|n| options.indent = n),
+            "--width" => number_argument(arg, args.next()).map(|n| options.max_width = n),
+            "--keyword-case" => match args.next().map(String::as_str) {
+                Some("upper") => Ok(KeywordCase::Upper),
+                Some("lower") => Ok(KeywordCase::Lower),
+                _ => Err("--keyword-case erwartet upper oder lower".to_string()),
+            }
+            .map(|case| options.keyword_case = case),
+            _ => {
+                files.push(PathBuf::from(arg));
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            return 2;
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!(
+            "Verwendung: together fmt [--check] [--indent <n>] [--width <n>] [--keyword-case upper|lower] [--no-align] <datei>..."
+        );
+        return 2;
+    }
+
+    let mut status = 0;
+    for file in &files {
+        let result = fs::read_to_string(file)
+            .map_err(|e| format!("Kann Datei nicht lesen: {}", e))
+            .and_then(|source| Ok((fmt::format(&source, &options)?, source)));
+
+        match result {
+            Ok((formatted, source)) if formatted != source => {
+                if check {
+                    println!("{}: nicht formatiert", file.display());
+                    status = status.max(1);
+                } else if let Err(e) = fs::write(file, formatted) {
+                    eprintln!("{}: Kann Datei nicht schreiben: {}", file.display(), e);
+                    status = 2;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                status = 2;
+            }
+        }
+    }
+    status
+}
+
+fn number_argument(option: &str, value: Option<&String>) -> Result<usize, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("{} erwartet eine positive Zahl", option))
+}
+
+// together doc [--format html|markdown] [--out <verzeichnis>] <datei>...
+//
+// Schreibt je Modul eine Seite und eine Übersicht ins Ausgabeverzeichnis
+// (Standard: doc). Verweise zwischen den angegebenen Modulen werden verlinkt.
+// Exit-Code 2 bei Lese-, Schreib- oder Syntaxfehlern.
+pub fn doc(args: &[String]) -> i32 {
+    let mut markdown = false;
+    let mut out_dir = PathBuf::from("doc");
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some("html") => markdown = false,
+                Some("markdown") => markdown = true,
+                _ => {
+                    eprintln!("--format erwartet html oder markdown");
+                    return 2;
+                }
+            },
+            "--out" => match args.next() {
+                Some(path) => out_dir = PathBuf::from(path),
+                None => {
+                    eprintln!("--out erwartet ein Verzeichnis");
+                    return 2;
+                }
+            },
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!("Verwendung: together doc [--format html|markdown] [--out <verzeichnis>] <datei>...");
+        return 2;
+    }
+
+    let mut status = 0;
+    let mut docs = Vec::new();
+    for file in &files {
+        let result = fs::read_to_string(file)
+            .map_err(|e| format!("Kann Datei nicht lesen: {}", e))
+            .and_then(|source| ModuleDoc::from_source(&source));
+        match result {
+            Ok(module_doc) => docs.push(module_doc),
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                status = 2;
+            }
+        }
+    }
+
+    let modules: Vec<String> = docs.iter().map(|module_doc| module_doc.name.clone()).collect();
+    let mut pages = Vec::new();
+    for module_doc in &docs {
+        let page = if markdown {
+            (format!("{}.md", module_doc.name), doc::render_markdown(module_doc, &modules))
+        } else {
+            (format!("{}.html", module_doc.name), doc::render_html(module_doc, &modules))
+        };
+        pages.push(page);
+    }
+    if markdown {
+        pages.push(("index.md".to_string(), doc::render_index_markdown(&docs)));
+    } else {
+        pages.push(("index.html".to_string(), doc::render_index_html(&docs)));
+    }
+
+    if let Err(e) = fs::create_dir_all(&out_dir) {
+        eprintln!("{}: Kann Verzeichnis nicht anlegen: {}", out_dir.display(), e);
+        return 2;
+    }
+    for (name, content) in pages {
+        let path = out_dir.join(name);
+        if let Err(e) = fs::write(&path, content) {
+            eprintln!("{}: Kann Datei nicht schreiben: {}", path.display(), e);
+            status = 2;
+        }
+    }
+    status
+}
+
+// Schnittstellen (DEFINITION) der Module; mit --out als <Modul>.Def, sonst auf
+// die Standardausgabe, etwa um zwei Versionen per diff zu vergleichen
+pub fn def(args: &[String]) -> i32 {
+    let mut out_dir = None;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => match args.next() {
+                Some(path) => out_dir = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--out erwartet ein Verzeichnis");
+                    return 2;
+                }
+            },
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!("Verwendung: together def [--out <verzeichnis>] <datei>...");
+        return 2;
+    }
+
+    if let Some(dir) = &out_dir {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("{}: Kann Verzeichnis nicht anlegen: {}", dir.display(), e);
+            return 2;
+        }
+    }
+
+    let mut status = 0;
+    let mut printer = PrettyPrinter::new();
+    for file in &files {
+        let module = match parse_file(file) {
+            Ok((module, _)) => module,
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                status = 2;
+                continue;
+            }
+        };
+        let definition = printer.print_definition(&module);
+
+        match &out_dir {
+            Some(dir) => {
+                let path = dir.join(format!("{}.Def", module.name));
+                if let Err(e) = fs::write(&path, definition) {
+                    eprintln!("{}: Kann Datei nicht schreiben: {}", path.display(), e);
+                    status = 2;
+                }
+            }
+            None => print!("{}", definition),
+        }
+    }
+    status
+}
+
+// together run [-W[no-]<warnung>]... <datei>
+//
+// Führt das Modul mit dem Interpreter aus, ohne C-Compiler. Importierte
+// Module werden wie bei bc gesucht und geprüft, ausführen kann der
+// Interpreter aber nur einzelne Module. Exit-Code 0 nach normalem Ende, 1 bei
+// einem Laufzeitfehler, 2 bei Lese-, Syntax- oder Typfehlern.
+pub fn run(args: &[String]) -> i32 {
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            flag if flag.starts_with("-W") => {
+                if let Err(e) = warning_switch(flag, &mut warnings) {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    let file = match files.as_slice() {
+        [file] => file.clone(),
+        _ => {
+            eprintln!("Verwendung: together run [-W[no-]<warnung>]... <datei>");
+            return 2;
+        }
+    };
+
+    let checked = match load_module(&file, &warnings) {
+        Some(checked) => checked,
+        None => return 2,
+    };
+    if let Some(name) = c_generator::imported_modules(&checked).first() {
+        eprintln!(
+            "{}: Import {}: der Interpreter führt nur einzelne Module aus (und Out), mehrere Module mit bc und vm",
+            file.display(),
+            name
+        );
+        return 2;
+    }
+
+    let interpreter = std::thread::Builder::new().stack_size(interpreter::STACK_SIZE).spawn(move || {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        Interpreter::new(&checked, &mut out).run()
+    });
+
+    match interpreter.map(|thread| thread.join()) {
+        Ok(Ok(Ok(()))) => 0,
+        Ok(Ok(Err(trap))) => {
+            eprintln!("{}:{}", file.display(), trap);
+            1
+        }
+        Ok(Err(_)) => 2,
+        Err(e) => {
+            eprintln!("Kann Interpreter nicht starten: {}", e);
+            2
+        }
+    }
+}
+
+// together repl
+//
+// Liest Deklarationen, Ausdrücke und Anweisungen von der Standardeingabe; die
+// Eingabeaufforderung erscheint nur an einem Terminal. Exit-Code 0 nach :quit
+// oder dem Ende der Eingabe, 2 bei Ein- oder Ausgabefehlern.
+pub fn repl(args: &[String]) -> i32 {
+    if !args.is_empty() {
+        eprintln!("Verwendung: together repl");
+        return 2;
+    }
+
+    // Wie bei run: tiefe Rekursion braucht einen großen Stapel
+    let session = std::thread::Builder::new().stack_size(interpreter::STACK_SIZE).spawn(|| {
+        let stdin = io::stdin();
+        let prompt = stdin.is_terminal();
+        let stdout = io::stdout();
+        repl::run(&mut stdin.lock(), &mut stdout.lock(), prompt)
+    });
+
+    match session.map(|thread| thread.join()) {
+        Ok(Ok(Ok(()))) => 0,
+        Ok(Ok(Err(e))) => {
+            eprintln!("Ein-/Ausgabefehler: {}", e);
+            2
+        }
+        Ok(Err(_)) => 2,
+        Err(e) => {
+            eprintln!("Kann REPL nicht starten: {}", e);
+            2
+        }
+    }
+}
+
+// together bc [-o <datei>] [-I <verzeichnis>]... [-W[no-]<warnung>]... <datei>
+//
+// Übersetzt das Modul samt Importen in Bytecode für die VM. Importe werden
+// als <Name>.Mod im Verzeichnis der Datei und in den -I-Verzeichnissen
+// gesucht und nach <Name>.obc neben ihrer Quelldatei übersetzt, das Modul
+// selbst ohne -o nach <Modul>.obc neben der Quelldatei. Exit-Code 0 bei
+// Erfolg, 2 bei Fehlern.
+pub fn bc(args: &[String]) -> i32 {
+    let mut output = None;
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("-o erwartet eine Datei");
+                    return 2;
+                }
+            },
+            "-I" => match args.next() {
+                Some(dir) => dirs.push(PathBuf::from(dir)),
+                None => {
+                    eprintln!("-I erwartet ein Verzeichnis");
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("-W") => {
+                if let Err(e) = warning_switch(flag, &mut warnings) {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!("Verwendung: together bc [-o <datei>] [-I <verzeichnis>]... [-W[no-]<warnung>]... <datei>");
+            return 2;
+        }
+    };
+
+    let mut loader = ModuleLoader::with_warnings(warnings);
+    loader.add_path(source_dir(file));
+    for dir in dirs {
+        loader.add_path(dir);
+    }
+    let modules = match loader.load_file(file) {
+        Ok(modules) => modules,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    // Jedes Modul nach seinen Importen, deren Bytecode die Verweise auflöst
+    let mut generator = BytecodeGenerator::new();
+    let mut files = Vec::new();
+    for loaded in &modules {
+        print_warnings(&loaded.path, &loaded.warnings);
+        let code = match generator.generate(&loaded.module) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("{}: {}", loaded.path.display(), e);
+                return 2;
+            }
+        };
+        generator.add_interface(&code);
+        let path = loaded.path.with_file_name(format!("{}.{}", code.name, bytecode::EXTENSION));
+        files.push((path, bytecode::encode(&code)));
+    }
+
+    if let (Some(output), Some(main)) = (output, files.last_mut()) {
+        main.0 = output;
+    }
+    for (path, bytes) in files {
+        if let Err(e) = fs::write(&path, bytes) {
+            eprintln!("{}: Kann Datei nicht schreiben: {}", path.display(), e);
+            return 2;
+        }
+    }
+    0
+}
+
+// together vm [-I <verzeichnis>]... [--steps <n>] <datei.obc>
+//
+// Lädt das Modul samt Importen und führt die Modulrümpfe aus. Importe werden
+// im Verzeichnis der Datei, dann in den -I-Verzeichnissen gesucht. --steps
+// bricht nach n Befehlen mit einem Laufzeitfehler ab. Exit-Code 0 nach
+// normalem Ende, 1 bei einem Laufzeitfehler, 2 bei Ladefehlern.
+pub fn vm(args: &[String]) -> i32 {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut steps = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-I" => match args.next() {
+                Some(dir) => dirs.push(PathBuf::from(dir)),
+                None => {
+                    eprintln!("-I erwartet ein Verzeichnis");
+                    return 2;
+                }
+            },
+            "--steps" => match number_argument(arg, args.next()) {
+                Ok(n) => steps = Some(n as u64),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            },
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!("Verwendung: together vm [-I <verzeichnis>]... [--steps <n>] <datei.obc>");
+            return 2;
+        }
+    };
+
+    let mut loader = Loader::new();
+    loader.add_path(source_dir(file));
+    for dir in dirs {
+        loader.add_path(dir);
+    }
+    let modules = match loader.load_file(file) {
+        Ok(modules) => modules,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut vm = Vm::new(&mut out);
+    if let Some(steps) = steps {
+        vm.set_step_limit(steps);
+    }
+    for module in modules {
+        // Traps in Prozeduren anderer Module nennen die Prozedur samt Modul
+        let name = module.name.clone();
+        if let Err(trap) = vm.load(module) {
+            eprintln!("{}:{}", name, trap);
+            return 1;
+        }
+    }
+    0
+}
+
+// together disasm <datei.obc>...
+pub fn disasm(args: &[String]) -> i32 {
+    if args.is_empty() {
+        eprintln!("Verwendung: together disasm <datei.obc>...");
+        return 2;
+    }
+
+    let mut status = 0;
+    for file in args {
+        let decoded = fs::read(file)
+            .map_err(|e| format!("Kann Datei nicht lesen: {}", e))
+            .and_then(|bytes| bytecode::decode(&bytes));
+        match decoded {
+            Ok(module) => print!("{}", bytecode::disassemble(&module)),
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                status = 2;
+            }
+        }
+    }
+    status
+}
+
+// together wasm [-O0|-O1|-O2] [--remarks] [-o <datei>] [--wat] [-W[no-]<warnung>]... <datei>
+//
+// Übersetzt das Modul über die IR nach WebAssembly, binär oder mit --wat im
+// Textformat; ohne -o nach <Modul>.wasm bzw. <Modul>.wat neben der
+// Quelldatei. -O und --remarks wie bei ir. Exit-Code 0 bei Erfolg, 2 bei
+// Fehlern.
+pub fn wasm(args: &[String]) -> i32 {
+    let mut level = OptLevel::O0;
+    let mut show_remarks = false;
+    let mut output = None;
+    let mut text = false;
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("-o erwartet eine Datei");
+                    return 2;
+                }
+            },
+            "--wat" => text = true,
+            "--remarks" => show_remarks = true,
+            flag if flag.starts_with("-O") => match OptLevel::from_flag(flag) {
+                Some(parsed) => level = parsed,
+                None => {
+                    eprintln!("Unbekannte Optimierungsstufe: {}", flag);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("-W") => {
+                if let Err(e) = warning_switch(flag, &mut warnings) {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!("Verwendung: together wasm [-O0|-O1|-O2] [--remarks] [-o <datei>] [--wat] [-W[no-]<warnung>]... <datei>");
+            return 2;
+        }
+    };
+
+    let checked = match load_module(file, &warnings) {
+        Some(checked) => checked,
+        None => return 2,
+    };
+    let mut generator = WasmGenerator::with_optimization(level);
+    let generated = generator.generate(&checked);
+    if show_remarks {
+        print_remarks(file, generator.remarks());
+    }
+    let code = match generated {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}: {}", file.display(), e);
+            return 2;
+        }
+    };
+
+    let (extension, bytes) = if text {
+        (wasm::TEXT_EXTENSION, wasm::to_wat(&code).into_bytes())
+    } else {
+        (wasm::EXTENSION, wasm::encode(&code))
+    };
+    let path = output.unwrap_or_else(|| file.with_file_name(format!("{}.{}", checked.name, extension)));
+    match fs::write(&path, bytes) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: Kann Datei nicht schreiben: {}", path.display(), e);
+            2
+        }
+    }
+}
+
+// together asm [-O0|-O1|-O2] [--remarks] [-o <datei>] [-W[no-]<warnung>]... <datei>
+//
+// Übersetzt das Modul über die IR in x86-64-Assembler; ohne -o nach <Modul>.s
+// neben der Quelldatei. -O und --remarks wie bei ir. Gebunden wird mit dem C-Compiler des Systems. Exit-Code 0 bei
+// Erfolg, 2 bei Fehlern.
+pub fn asm(args: &[String]) -> i32 {
+    let mut level = OptLevel::O0;
+    let mut show_remarks = false;
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("-o erwartet eine Datei");
+                    return 2;
+                }
+            },
+            "--remarks" => show_remarks = true,
+            flag if flag.starts_with("-O") => match OptLevel::from_flag(flag) {
+                Some(parsed) => level = parsed,
+                None => {
+                    eprintln!("Unbekannte Optimierungsstufe: {}", flag);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("-W") => {
+                if let Err(e) = warning_switch(flag, &mut warnings) {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!("Verwendung: together asm [-O0|-O1|-O2] [--remarks] [-o <datei>] [-W[no-]<warnung>]... <datei>");
+            return 2;
+        }
+    };
+
+    let checked = match load_module(file, &warnings) {
+        Some(checked) => checked,
+        None => return 2,
+    };
+    let mut generator = X86Generator::with_optimization(level);
+    let generated = generator.generate(&checked);
+    if show_remarks {
+        print_remarks(file, generator.remarks());
+    }
+    let code = match generated {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}: {}", file.display(), e);
+            return 2;
+        }
+    };
+
+    let path = output.unwrap_or_else(|| file.with_file_name(format!("{}.{}", checked.name, x86_generator::EXTENSION)));
+    match fs::write(&path, code) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: Kann Datei nicht schreiben: {}", path.display(), e);
+            2
+        }
+    }
+}
+
+// together riscv [-O0|-O1|-O2] [--remarks] [-o <datei>] [-W[no-]<warnung>]... <datei>
+//
+// Übersetzt das Modul über die IR in RV64-Assembler; ohne -o nach <Modul>.s
+// neben der Quelldatei. -O und --remarks wie bei ir. Gebunden wird mit einem RISC-V-C-Compiler. Exit-Code 0 bei
+// Erfolg, 2 bei Fehlern.
+pub fn riscv(args: &[String]) -> i32 {
+    let mut level = OptLevel::O0;
+    let mut show_remarks = false;
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("-o erwartet eine Datei");
+                    return 2;
+                }
+            },
+            "--remarks" => show_remarks = true,
+            flag if flag.starts_with("-O") => match OptLevel::from_flag(flag) {
+                Some(parsed) => level = parsed,
+                None => {
+                    eprintln!("Unbekannte Optimierungsstufe: {}", flag);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("-W") => {
+                if let Err(e) = warning_switch(flag, &mut warnings) {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!("Verwendung: together riscv [-O0|-O1|-O2] [--remarks] [-o <datei>] [-W[no-]<warnung>]... <datei>");
+            return 2;
+        }
+    };
+
+    let checked = match load_module(file, &warnings) {
+        Some(checked) => checked,
+        None => return 2,
+    };
+    let mut generator = RiscvGenerator::with_optimization(level);
+    let generated = generator.generate(&checked);
+    if show_remarks {
+        print_remarks(file, generator.remarks());
+    }
+    let code = match generated {
+        Ok(program) => riscv::to_asm(&program),
+        Err(e) => {
+            eprintln!("{}: {}", file.display(), e);
+            return 2;
+        }
+    };
+
+    let path = output.unwrap_or_else(|| file.with_file_name(format!("{}.{}", checked.name, riscv::EXTENSION)));
+    match fs::write(&path, code) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: Kann Datei nicht schreiben: {}", path.display(), e);
+            2
+        }
+    }
+}
+
+// together ir [-O0|-O1|-O2] [--remarks] [-W[no-]<warnung>]... <datei>
+//
+// Gibt die Zwischendarstellung in SSA-Form aus, aus der die Backends
+// übersetzen, auf Wunsch optimiert; --remarks meldet die Optimierungen auf
+// stderr.
+// Exit-Code 0 bei Erfolg, 2 bei Fehlern.
+pub fn ir(args: &[String]) -> i32 {
+    let mut level = OptLevel::O0;
+    let mut show_remarks = false;
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--remarks" => show_remarks = true,
+            flag if flag.starts_with("-O") => match OptLevel::from_flag(flag) {
+                Some(parsed) => level = parsed,
+                None => {
+                    eprintln!("Unbekannte Optimierungsstufe: {}", flag);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("-W") => {
+                if let Err(e) = warning_switch(flag, &mut warnings) {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!("Verwendung: together ir [-O0|-O1|-O2] [--remarks] [-W[no-]<warnung>]... <datei>");
+            return 2;
+        }
+    };
+
+    let checked = match load_module(file, &warnings) {
+        Some(checked) => checked,
+        None => return 2,
+    };
+    match ir::build(&checked) {
+        Ok(mut program) => {
+            let remarks = ir::opt::optimize(&mut program, level);
+            if show_remarks {
+                print_remarks(file, &remarks);
+            }
+            print!("{}", program);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}: {}", file.display(), e);
+            2
+        }
+    }
+}
+
+// together c [-O0|-O1|-O2] [--remarks] [--line-directives] [--source-map]
+//           [--modules [-I <verzeichnis>]...] [-W[no-]<warnung>]... [-o <datei>] <datei>
+//
+// Übersetzt das Modul nach C; ohne -o nach <Modul>.c neben der Quelldatei.
+// --line-directives setzt #line-Direktiven, damit Meldungen des C-Compilers
+// und Debugger auf die .Mod-Datei zeigen; --source-map schreibt zusätzlich
+// die Zuordnung der C-Zeilen zu Oberon-Zeilen und -Spalten als JSON nach
+// <datei>.c.map.
+// --modules übersetzt das Modul samt Importen getrennt, siehe c_modules.
+// Exit-Code 0 bei Erfolg, 2 bei Fehlern.
+pub fn c(args: &[String]) -> i32 {
+    let mut level = OptLevel::O0;
+    let mut show_remarks = false;
+    let mut line_directives = false;
+    let mut source_map = false;
+    let mut modules = false;
+    let mut dirs = Vec::new();
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("-o erwartet eine Datei");
+                    return 2;
+                }
+            },
+            "-I" => match args.next() {
+                Some(dir) => dirs.push(PathBuf::from(dir)),
+                None => {
+                    eprintln!("-I erwartet ein Verzeichnis");
+                    return 2;
+                }
+            },
+            "--remarks" => show_remarks = true,
+            "--line-directives" => line_directives = true,
+            "--source-map" => source_map = true,
+            "--modules" => modules = true,
+            flag if flag.starts_with("-O") => match OptLevel::from_flag(flag) {
+                Some(parsed) => level = parsed,
+                None => {
+                    eprintln!("Unbekannte Optimierungsstufe: {}", flag);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("-W") => {
+                if let Err(e) = warning_switch(flag, &mut warnings) {
+                    eprintln!("{}", e);
+                    return 2;
+                }
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!(
+                "Verwendung: together c [-O0|-O1|-O2] [--remarks] [--line-directives] [--source-map] \
+                 [--modules [-I <verzeichnis>]...] [-W[no-]<warnung>]... [-o <datei>] <datei>"
+            );
+            return 2;
+        }
+    };
+    if modules {
+        let options = COptions { level, show_remarks, line_directives, source_map, warnings };
+        return c_modules(file, &dirs, output, &options);
+    }
+
+    let checked = match load_module(file, &warnings) {
+        Some(checked) => checked,
+        None => return 2,
+    };
+    let path = output.unwrap_or_else(|| file.with_file_name(format!("{}.c", checked.name)));
+    let source = file.display().to_string();
+    let target = path.display().to_string();
+
+    let mut generator = CGenerator::with_optimization(level);
+    if line_directives {
+        generator.set_line_directives(&source, &target);
+    }
+    let code = match generator.generate(&checked) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}: {}", file.display(), e);
+            return 2;
+        }
+    };
+    if show_remarks {
+        print_remarks(file, generator.remarks());
+    }
+
+    let mut files = vec![(path.clone(), code)];
+    if source_map {
+        let map = PathBuf::from(format!("{}.map", target));
+        files.push((map, generator.source_map(&source, &target)));
+    }
+    write_files(files)
+}
+
+struct COptions {
+    level: OptLevel,
+    show_remarks: bool,
+    line_directives: bool,
+    source_map: bool,
+    warnings: WarningOptions,
+}
+
+// together c --modules: das Modul und alle Module, die es (mittelbar)
+// importiert, gesucht als <Name>.Mod im Verzeichnis der Datei und in den
+// -I-Verzeichnissen. Ins Ausgabeverzeichnis (-o, sonst das der Datei)
+// kommen <Modul>.h und <Modul>.c für jedes Modul, die Laufzeit oberon.h,
+// das Hauptprogramm oberon_main.c und ein Makefile, das das Programm
+// <Modul> baut.
+fn c_modules(file: &Path, dirs: &[PathBuf], output: Option<PathBuf>, options: &COptions) -> i32 {
+    let mut loader = ModuleLoader::with_warnings(options.warnings.clone());
+    let parent = source_dir(file);
+    loader.add_path(parent);
+    for dir in dirs {
+        loader.add_path(dir);
+    }
+    let modules = match loader.load_file(file) {
+        Ok(modules) => modules,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let dir = output.unwrap_or_else(|| parent.to_path_buf());
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("{}: Kann Verzeichnis nicht anlegen: {}", dir.display(), e);
+        return 2;
+    }
+
+    let mut files = vec![(dir.join(c_generator::RUNTIME_HEADER), c_generator::runtime_header())];
+    let mut order = Vec::new();
+    for loaded in &modules {
+        let name = &loaded.module.name;
+        let path = dir.join(format!("{}.c", name));
+        let source = loaded.path.display().to_string();
+        let target = path.display().to_string();
+        print_warnings(&loaded.path, &loaded.warnings);
+
+        let mut generator = CGenerator::with_optimization(options.level);
+        if options.line_directives {
+            generator.set_line_directives(&source, &target);
+        }
+        let unit = match generator.generate_unit(&loaded.module) {
+            Ok(unit) => unit,
+            Err(e) => {
+                eprintln!("{}: {}", loaded.path.display(), e);
+                return 2;
+            }
+        };
+        if options.show_remarks {
+            print_remarks(&loaded.path, generator.remarks());
+        }
+
+        files.push((dir.join(format!("{}.h", name)), unit.header));
+        files.push((path, unit.source));
+        if options.source_map {
+            files.push((PathBuf::from(format!("{}.map", target)), generator.source_map(&source, &target)));
+        }
+        order.push((name.clone(), c_generator::imported_modules(&loaded.module)));
+    }
+
+    // Das Hauptmodul steht zuletzt und gibt dem Programm den Namen
+    let names: Vec<String> = order.iter().map(|(name, _)| name.clone()).collect();
+    let program = names.last().cloned().unwrap_or_default();
+    files.push((dir.join(c_generator::MAIN_FILE), c_generator::main_stub(&names)));
+    files.push((dir.join("Makefile"), c_generator::makefile(&program, &order)));
+    write_files(files)
+}
+
+fn write_files(files: Vec<(PathBuf, String)>) -> i32 {
+    for (path, content) in files {
+        if let Err(e) = fs::write(&path, content) {
+            eprintln!("{}: Kann Datei nicht schreiben: {}", path.display(), e);
+            return 2;
+        }
+    }
+    0
+}
+
+fn print_remarks(file: &Path, remarks: &[Remark]) {
+    for remark in remarks {
+        eprintln!("{}:{}", file.display(), remark);
+    }
+}
+
+// together llvm [-g] [--target <triple>] [--opt-level 0..3] [--emit obj|asm|llvm-ir|bc] [-o <datei>] [-W[no-]<warnung>]... <datei>
+//
+// Übersetzt das Modul mit LLVM für den Host oder das angegebene Ziel und
+// optimiert es mit der Pipeline von LLVM. Ohne --emit entsteht ein Programm
+// <Modul> neben der Quelldatei, gebunden mit dem C-Compiler des Systems;
+// sonst die gewünschte Datei (<Modul>.o, .s, .ll oder .bc). Optionen mit
+// Wert auch als --emit=obj usw. -g erzeugt DWARF-Debuginformationen für
+// gdb/lldb. Exit-Code 0 bei Erfolg, 2 bei Fehlern.
+#[cfg(feature = "llvm")]
+pub fn llvm(args: &[String]) -> i32 {
+    let mut output = None;
+    let mut triple: Option<String> = None;
+    let mut opt_level = 0;
+    let mut emit = None;
+    let mut debug = false;
+    let mut files = Vec::new();
+    let mut warnings = WarningOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (option, inline) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => (option, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next().cloned());
+        let result = match option {
+            "-g" => {
+                debug = true;
+                Ok(())
+            }
+            "-o" => value().map(|path| output = Some(PathBuf::from(path))).ok_or("-o erwartet eine Datei".to_string()),
+            "--target" => value().map(|name| triple = Some(name)).ok_or("--target erwartet ein Triple".to_string()),
+            "--opt-level" => value()
+                .and_then(|level| level.parse::<u8>().ok())
+                .filter(|&level| level <= 3)
+                .map(|level| opt_level = level)
+                .ok_or("--opt-level erwartet 0, 1, 2 oder 3".to_string()),
+            "--emit" => value()
+                .and_then(|kind| Emit::parse(&kind))
+                .map(|kind| emit = Some(kind))
+                .ok_or("--emit erwartet obj, asm, llvm-ir oder bc".to_string()),
+            flag if flag.starts_with("-W") => warning_switch(flag, &mut warnings),
+            _ => {
+                files.push(PathBuf::from(arg));
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            return 2;
+        }
+    }
+
+    let file = match files.as_slice() {
+        [file] => file,
+        _ => {
+            eprintln!("Verwendung: together llvm [-g] [--target <triple>] [--opt-level 0..3] [--emit obj|asm|llvm-ir|bc] [-o <datei>] [-W[no-]<warnung>]... <datei>");
+            return 2;
+        }
+    };
+
+    let checked = match load_module(file, &warnings) {
+        Some(checked) => checked,
+        None => return 2,
+    };
+
+    let context = Context::create();
+    let source = debug.then_some(file.as_path());
+    let mut generator = LLVMGenerator::with_debug_info(&context, &checked.name, source);
+    let result = generator
+        .generate(&checked)
+        .and_then(|_| llvm_generator::target_machine(triple.as_deref(), opt_level))
+        .and_then(|machine| generator.optimize(&machine, opt_level).map(|_| machine))
+        .and_then(|machine| match emit {
+            Some(emit) => {
+                let path =
+                    output.unwrap_or_else(|| file.with_file_name(format!("{}.{}", checked.name, emit.extension())));
+                generator.emit(&machine, emit, &path)
+            }
+            None => {
+                let path = output.unwrap_or_else(|| file.with_file_name(&checked.name));
+                let object = std::env::temp_dir().join(format!("together-{}-{}.o", std::process::id(), checked.name));
+                let linked = generator
+                    .emit(&machine, Emit::Object, &object)
+                    .and_then(|_| llvm_generator::link(&[&object], &path, triple.as_deref()));
+                let _ = fs::remove_file(&object);
+                linked
+            }
+        });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: {}", file.display(), e);
+            2
+        }
+    }
+}
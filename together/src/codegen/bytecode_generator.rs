@@ -0,0 +1,784 @@
+use crate::bytecode::{BytecodeModule, Function, Global, Instruction, TrapKind, TypeDesc, TypeDescKind};
+use crate::parser::ast::{BinaryOp, ExportMark, UnaryOp};
+use crate::semantic::hir::*;
+use crate::semantic::library::LibraryProcedure;
+use crate::semantic::symbol_table::Builtin;
+use crate::semantic::{ConstValue, TypeArena, TypeId, TypeKind};
+use std::collections::HashMap;
+
+// Übersetzt ein geprüftes Modul in Bytecode für die Stack-VM. Konstanten
+// werden eingesetzt, jede Prozedur (auch geschachtelte) wird zu einer
+// Function; Variablen umschließender Prozeduren erreicht OuterAddr.
+// Prozeduren und Variablen importierter Module werden über den Bytecode des
+// Imports (add_interface) nummeriert und mit CallExtern, ExternGlobalAddr
+// bzw. PushExternProc erreicht.
+
+// Ort einer lokalen Variablen bzw. eines Parameters
+#[derive(Clone, Copy)]
+struct Slot {
+    function: u32,
+    slot: u32,
+    ty: TypeId,
+}
+
+pub struct BytecodeGenerator {
+    types: TypeArena,
+    type_table: Vec<TypeDesc>,
+    type_indices: HashMap<TypeId, u32>,
+    // Globale Variablen: Index und Typ
+    globals: HashMap<String, (u32, TypeId)>,
+    // Übersetzte Importe nach Modulname
+    interfaces: HashMap<String, BytecodeModule>,
+    // Importe außer den eingebauten; Index für die Extern-Befehle
+    imports: Vec<String>,
+    // Typen importierter Variablen ("Modul.Name")
+    imported_variables: HashMap<String, TypeId>,
+    // Lokale Variablen nach qualifiziertem Namen ("P.x")
+    locals: HashMap<String, Slot>,
+    // Prozeduren nach qualifiziertem Namen
+    function_indices: HashMap<String, u32>,
+    functions: Vec<Function>,
+    // Prozedur, deren Code gerade erzeugt wird (None: Modulrumpf)
+    current: Option<u32>,
+    code: Function,
+}
+
+impl BytecodeGenerator {
+    pub fn new() -> Self {
+        BytecodeGenerator {
+            types: TypeArena::new(),
+            type_table: Vec::new(),
+            type_indices: HashMap::new(),
+            globals: HashMap::new(),
+            interfaces: HashMap::new(),
+            imports: Vec::new(),
+            imported_variables: HashMap::new(),
+            locals: HashMap::new(),
+            function_indices: HashMap::new(),
+            functions: Vec::new(),
+            current: None,
+            code: Function::new(""),
+        }
+    }
+
+    // Bytecode eines importierten Moduls; nötig, sobald das Modul auf dessen
+    // Prozeduren oder Variablen verweist
+    pub fn add_interface(&mut self, module: &BytecodeModule) {
+        self.interfaces.insert(module.name.clone(), module.clone());
+    }
+
+    pub fn generate(&mut self, module: &Module) -> Result<BytecodeModule, String> {
+        let interfaces = std::mem::take(&mut self.interfaces);
+        *self = BytecodeGenerator::new();
+        self.interfaces = interfaces;
+        self.types = module.types.clone();
+        self.imports = module
+            .imports
+            .iter()
+            .map(|import| import.module_name.clone())
+            .filter(|name| !LibraryProcedure::is_library_module(name))
+            .collect();
+        for variable in &module.imported_variables {
+            self.imported_variables.insert(variable.name.clone(), variable.var_type);
+        }
+
+        let mut globals = Vec::new();
+        for variable in &module.declarations.variables {
+            let slot = globals.len() as u32;
+            self.globals.insert(variable.name.clone(), (slot, variable.var_type));
+            globals.push(Global {
+                name: variable.name.clone(),
+                ty: self.type_index(variable.var_type),
+                exported: variable.exported != ExportMark::None,
+            });
+        }
+
+        // Erst alle Prozeduren nummerieren, damit Aufrufe vor der Deklaration
+        // (und Rekursion) ihr Ziel kennen
+        self.register_functions("", &module.declarations);
+        for procedure in &module.declarations.procedures {
+            self.generate_procedure("", procedure)?;
+        }
+
+        self.current = None;
+        self.code = Function::new(&module.name);
+        self.generate_statements(&module.body)?;
+        self.emit(Instruction::Return);
+        let body = std::mem::replace(&mut self.code, Function::new(""));
+
+        Ok(BytecodeModule {
+            name: module.name.clone(),
+            imports: std::mem::take(&mut self.imports),
+            types: std::mem::take(&mut self.type_table),
+            globals,
+            functions: std::mem::take(&mut self.functions),
+            body,
+        })
+    }
+
+    // ========================================================================
+    // Typen
+    // ========================================================================
+
+    fn type_index(&mut self, ty: TypeId) -> u32 {
+        if let Some(&index) = self.type_indices.get(&ty) {
+            return index;
+        }
+
+        // Platz zuerst reservieren: Records können über ihre Felder wieder
+        // auf Typen verweisen, die gerade beschrieben werden
+        let index = self.type_table.len() as u32;
+        self.type_indices.insert(ty, index);
+        self.type_table.push(TypeDesc {
+            name: self.types.name(ty).map(str::to_string),
+            kind: TypeDescKind::Reference,
+        });
+
+        let kind = match self.types.kind(ty).clone() {
+            TypeKind::Integer => TypeDescKind::Integer,
+            TypeKind::Real => TypeDescKind::Real,
+            TypeKind::Boolean => TypeDescKind::Boolean,
+            TypeKind::Char => TypeDescKind::Char,
+            TypeKind::Set => TypeDescKind::Set,
+            TypeKind::String => TypeDescKind::String,
+            TypeKind::Array { length, element } => TypeDescKind::Array {
+                length: length as u32,
+                element: self.type_index(element),
+            },
+            TypeKind::Record { base, .. } => {
+                let base = base.map(|base| self.type_index(base));
+                let field_types: Vec<TypeId> = self.types.fields(ty).iter().map(|field| field.ty).collect();
+                let fields = field_types.into_iter().map(|field| self.type_index(field)).collect();
+                TypeDescKind::Record { base, fields }
+            }
+            TypeKind::Nil
+            | TypeKind::Pointer { .. }
+            | TypeKind::Procedure { .. }
+            | TypeKind::Imported(_)
+            | TypeKind::Forward(_) => TypeDescKind::Reference,
+        };
+        self.type_table[index as usize].kind = kind;
+        index
+    }
+
+    fn is_record(&self, ty: TypeId) -> bool {
+        matches!(self.types.kind(ty), TypeKind::Record { .. })
+    }
+
+    // ========================================================================
+    // Prozeduren
+    // ========================================================================
+
+    fn register_functions(&mut self, prefix: &str, decls: &Declarations) {
+        for procedure in &decls.procedures {
+            let name = qualify(prefix, &procedure.name);
+            self.function_indices.insert(name.clone(), self.functions.len() as u32);
+            self.functions.push(Function::new(&name));
+            self.register_functions(&name, &procedure.declarations);
+        }
+    }
+
+    fn generate_procedure(&mut self, prefix: &str, procedure: &Procedure) -> Result<(), String> {
+        let name = qualify(prefix, &procedure.name);
+        let index = self.function_indices[&name];
+
+        let mut function = Function::new(&name);
+        function.exported = procedure.exported != ExportMark::None;
+        for (slot, param) in procedure.params.iter().enumerate() {
+            function.params.push(param.is_var);
+            self.locals.insert(
+                qualify(&name, &param.name),
+                Slot { function: index, slot: slot as u32, ty: param.param_type },
+            );
+        }
+        for (offset, variable) in procedure.declarations.variables.iter().enumerate() {
+            function.locals.push(self.type_index(variable.var_type));
+            self.locals.insert(
+                qualify(&name, &variable.name),
+                Slot { function: index, slot: (procedure.params.len() + offset) as u32, ty: variable.var_type },
+            );
+        }
+
+        // Geschachtelte Prozeduren zuerst; sie sehen die Variablen von oben
+        for nested in &procedure.declarations.procedures {
+            self.generate_procedure(&name, nested)?;
+        }
+
+        self.current = Some(index);
+        self.code = function;
+        self.generate_statements(&procedure.body)?;
+        match &procedure.return_expr {
+            Some(expr) => {
+                self.mark_line(procedure.return_line);
+                self.generate_value(expr, procedure.return_type)?;
+                self.emit(Instruction::ReturnValue);
+            }
+            None => {
+                self.emit(Instruction::Return);
+            }
+        }
+        self.functions[index as usize] = std::mem::replace(&mut self.code, Function::new(""));
+        Ok(())
+    }
+
+    // ========================================================================
+    // Code-Puffer
+    // ========================================================================
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.code.push(instruction);
+        self.code.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.code.code.len() as u32
+    }
+
+    // Setzt das Sprungziel eines zuvor erzeugten Sprungs
+    fn patch(&mut self, at: usize, target: u32) {
+        match &mut self.code.code[at] {
+            Instruction::Jump(to) | Instruction::JumpIfFalse(to) | Instruction::JumpIfTrue(to) => *to = target,
+            _ => unreachable!("patch auf Nicht-Sprung"),
+        }
+    }
+
+    fn mark_line(&mut self, line: usize) {
+        let (pc, line) = (self.here(), line as u32);
+        match self.code.lines.last_mut() {
+            Some(last) if last.0 == pc => last.1 = line,
+            Some(last) if last.1 == line => {}
+            _ => self.code.lines.push((pc, line)),
+        }
+    }
+
+    // ========================================================================
+    // Anweisungen
+    // ========================================================================
+
+    fn generate_statements(&mut self, statements: &[Statement]) -> Result<(), String> {
+        for statement in statements {
+            self.generate_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn generate_statement(&mut self, statement: &Statement) -> Result<(), String> {
+        self.mark_line(statement.line());
+
+        match statement {
+            Statement::Assignment { target, value, .. } => {
+                self.generate_address(target)?;
+                self.generate_value(value, Some(target.ty))?;
+                self.emit(Instruction::Store);
+            }
+            Statement::ProcedureCall { procedure, arguments, .. } => {
+                self.generate_call(procedure, arguments)?;
+                if let TypeKind::Procedure { result: Some(_), .. } = self.types.kind(procedure.ty) {
+                    self.emit(Instruction::Pop);
+                }
+            }
+            Statement::BuiltinCall { builtin, arguments, .. } => self.generate_builtin_procedure(*builtin, arguments)?,
+            Statement::LibraryCall { procedure, arguments, .. } => {
+                for argument in arguments {
+                    self.generate_expression(argument)?;
+                }
+                self.emit(Instruction::Library(*procedure));
+            }
+            Statement::If { branches, else_body, .. } => {
+                let mut exits = Vec::new();
+                for (condition, body) in branches {
+                    self.generate_expression(condition)?;
+                    let skip = self.emit(Instruction::JumpIfFalse(0));
+                    self.generate_statements(body)?;
+                    exits.push(self.emit(Instruction::Jump(0)));
+                    let next = self.here();
+                    self.patch(skip, next);
+                }
+                if let Some(body) = else_body {
+                    self.generate_statements(body)?;
+                }
+                let end = self.here();
+                for exit in exits {
+                    self.patch(exit, end);
+                }
+            }
+            Statement::Case { line, expr, clauses, else_body, .. } => {
+                self.generate_expression(expr)?;
+                if expr.ty == TypeId::CHAR {
+                    self.emit(Instruction::Ord);
+                }
+
+                // Der Ordinalwert bleibt für die Label-Tests auf dem Stapel
+                let mut entries = Vec::new();
+                for clause in clauses {
+                    let mut jumps = Vec::new();
+                    for &(start, end) in &clause.labels {
+                        self.emit(Instruction::Dup);
+                        self.emit(Instruction::InRange(start, end));
+                        jumps.push(self.emit(Instruction::JumpIfTrue(0)));
+                    }
+                    entries.push(jumps);
+                }
+                let mut exits = Vec::new();
+                match else_body {
+                    Some(body) => {
+                        self.emit(Instruction::Pop);
+                        self.generate_statements(body)?;
+                        exits.push(self.emit(Instruction::Jump(0)));
+                    }
+                    None => {
+                        self.emit(Instruction::Trap(TrapKind::Case));
+                    }
+                }
+                for (clause, jumps) in clauses.iter().zip(entries) {
+                    let entry = self.here();
+                    for jump in jumps {
+                        self.patch(jump, entry);
+                    }
+                    self.mark_line(*line);
+                    self.emit(Instruction::Pop);
+                    self.generate_statements(&clause.body)?;
+                    exits.push(self.emit(Instruction::Jump(0)));
+                }
+                let end = self.here();
+                for exit in exits {
+                    self.patch(exit, end);
+                }
+            }
+            Statement::While { line, branches, .. } => {
+                let start = self.here();
+                for (condition, body) in branches {
+                    self.mark_line(*line);
+                    self.generate_expression(condition)?;
+                    let skip = self.emit(Instruction::JumpIfFalse(0));
+                    self.generate_statements(body)?;
+                    self.emit(Instruction::Jump(start));
+                    let next = self.here();
+                    self.patch(skip, next);
+                }
+            }
+            Statement::Repeat { line, body, condition, .. } => {
+                let start = self.here();
+                self.generate_statements(body)?;
+                self.mark_line(*line);
+                self.generate_expression(condition)?;
+                self.emit(Instruction::JumpIfFalse(start));
+            }
+            // Kopf prüft die Grenze vor jedem Durchlauf, am Ende springt die
+            // Erhöhung um den Schritt zurück zum Kopf
+            Statement::For { line, variable, start, end, step, body, .. } => {
+                self.generate_variable_address(variable)?;
+                self.generate_expression(start)?;
+                self.emit(Instruction::Store);
+
+                let head = self.here();
+                self.mark_line(*line);
+                self.generate_variable_address(variable)?;
+                self.emit(Instruction::Load);
+                self.generate_expression(end)?;
+                self.emit(if *step > 0 { Instruction::Le } else { Instruction::Ge });
+                let exit = self.emit(Instruction::JumpIfFalse(0));
+                self.generate_statements(body)?;
+
+                self.mark_line(*line);
+                self.generate_variable_address(variable)?;
+                self.emit(Instruction::Dup);
+                self.emit(Instruction::Load);
+                self.emit(Instruction::PushInt(*step));
+                self.emit(Instruction::Add);
+                self.emit(Instruction::Store);
+                self.emit(Instruction::Jump(head));
+                let after = self.here();
+                self.patch(exit, after);
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_builtin_procedure(&mut self, builtin: Builtin, arguments: &[Expr]) -> Result<(), String> {
+        if builtin == Builtin::Assert {
+            let condition = arguments.first().ok_or("ASSERT ohne Argument")?;
+            self.generate_expression(condition)?;
+            let ok = self.emit(Instruction::JumpIfTrue(0));
+            self.emit(Instruction::Trap(TrapKind::Assert));
+            let after = self.here();
+            self.patch(ok, after);
+            return Ok(());
+        }
+
+        // Die übrigen Prozeduren schreiben über die Adresse ihrer Variablen
+        let target = match arguments.first() {
+            Some(Expr { kind: ExprKind::Designator(designator), .. }) => designator,
+            _ => return Err(format!("{} erwartet eine Variable", builtin.name())),
+        };
+        self.generate_address(target)?;
+
+        if builtin == Builtin::New {
+            let record = match self.types.kind(target.ty) {
+                TypeKind::Pointer { target } => *target,
+                _ => return Err("NEW erwartet einen Zeiger".to_string()),
+            };
+            let index = self.type_index(record);
+            self.emit(Instruction::New(index));
+            return Ok(());
+        }
+
+        self.emit(Instruction::Dup);
+        self.emit(Instruction::Load);
+        match arguments.get(1) {
+            Some(operand) => self.generate_expression(operand)?,
+            None => {
+                self.emit(Instruction::PushInt(1));
+            }
+        }
+        self.emit(match builtin {
+            Builtin::Inc => Instruction::Inc,
+            Builtin::Dec => Instruction::Dec,
+            Builtin::Incl => Instruction::Incl,
+            Builtin::Excl => Instruction::Excl,
+            _ => return Err(format!("{} ist keine eigentliche Prozedur", builtin.name())),
+        });
+        self.emit(Instruction::Store);
+        Ok(())
+    }
+
+    // Argumente auf den Stapel legen und aufrufen; direkt oder über eine
+    // Prozedurvariable
+    fn generate_call(&mut self, procedure: &Designator, arguments: &[Expr]) -> Result<(), String> {
+        let params = match self.types.kind(procedure.ty) {
+            TypeKind::Procedure { params, .. } => params.clone(),
+            _ => return Err(format!("{} ist keine Prozedur", procedure.symbol.name)),
+        };
+        for (param, argument) in params.iter().zip(arguments) {
+            if param.is_var {
+                match &argument.kind {
+                    ExprKind::Designator(designator) => self.generate_address(designator)?,
+                    _ => return Err(format!("VAR-Argument für {} ist keine Variable", param.name)),
+                }
+            } else {
+                self.generate_value(argument, Some(param.param_type))?;
+            }
+        }
+
+        match (&procedure.symbol.kind, procedure.selectors.is_empty()) {
+            (SymbolRefKind::Procedure, true) if procedure.symbol.module.is_some() => {
+                let (module, function) = self.extern_function(&procedure.symbol)?;
+                self.emit(Instruction::CallExtern { module, function });
+            }
+            (SymbolRefKind::Procedure, true) => {
+                let index = self.function(&procedure.symbol.qualified_name)?;
+                self.emit(Instruction::Call(index));
+            }
+            _ => {
+                self.generate_expression(&Expr::new(ExprKind::Designator(procedure.clone()), procedure.ty))?;
+                self.emit(Instruction::CallIndirect(arguments.len() as u32));
+            }
+        }
+        Ok(())
+    }
+
+    fn function(&self, name: &str) -> Result<u32, String> {
+        self.function_indices
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("Prozedur {} nicht gefunden", name))
+    }
+
+    // ========================================================================
+    // Importe
+    // ========================================================================
+
+    // Index des Imports und Bytecode des Moduls, aus dem das Symbol stammt
+    fn interface(&self, symbol: &SymbolRef) -> Result<(u32, &BytecodeModule), String> {
+        let module = symbol.module.as_deref().unwrap_or_default();
+        let index = self
+            .imports
+            .iter()
+            .position(|import| import == module)
+            .ok_or_else(|| format!("Modul {} ist nicht importiert", module))?;
+        let interface = self
+            .interfaces
+            .get(module)
+            .ok_or_else(|| format!("Bytecode von Modul {} fehlt für {}", module, symbol.qualified_name))?;
+        Ok((index as u32, interface))
+    }
+
+    fn extern_function(&self, symbol: &SymbolRef) -> Result<(u32, u32), String> {
+        let (module, interface) = self.interface(symbol)?;
+        let function = interface
+            .functions
+            .iter()
+            .position(|function| function.exported && function.name == symbol.name)
+            .ok_or_else(|| format!("Prozedur {} nicht gefunden", symbol.qualified_name))?;
+        Ok((module, function as u32))
+    }
+
+    fn extern_global(&self, symbol: &SymbolRef) -> Result<(u32, u32), String> {
+        let (module, interface) = self.interface(symbol)?;
+        let slot = interface
+            .globals
+            .iter()
+            .position(|global| global.exported && global.name == symbol.name)
+            .ok_or_else(|| format!("Variable {} nicht gefunden", symbol.qualified_name))?;
+        Ok((module, slot as u32))
+    }
+
+    // ========================================================================
+    // Adressen
+    // ========================================================================
+
+    // Legt die Adresse der Variablen ab und liefert ihren Typ
+    fn generate_variable_address(&mut self, symbol: &SymbolRef) -> Result<TypeId, String> {
+        match symbol.kind {
+            SymbolRefKind::Variable { level: 0, .. } if symbol.module.is_some() => {
+                let ty = *self
+                    .imported_variables
+                    .get(&symbol.qualified_name)
+                    .ok_or_else(|| format!("Variable {} nicht gefunden", symbol.qualified_name))?;
+                let (module, slot) = self.extern_global(symbol)?;
+                self.emit(Instruction::ExternGlobalAddr { module, slot });
+                Ok(ty)
+            }
+            SymbolRefKind::Variable { level: 0, .. } => {
+                let (index, ty) = *self
+                    .globals
+                    .get(&symbol.qualified_name)
+                    .ok_or_else(|| format!("Variable {} nicht gefunden", symbol.qualified_name))?;
+                self.emit(Instruction::GlobalAddr(index));
+                Ok(ty)
+            }
+            SymbolRefKind::Variable { is_var_param, .. } => {
+                let slot = *self
+                    .locals
+                    .get(&symbol.qualified_name)
+                    .ok_or_else(|| format!("Variable {} nicht gefunden", symbol.qualified_name))?;
+                if Some(slot.function) == self.current {
+                    self.emit(Instruction::LocalAddr(slot.slot));
+                } else {
+                    self.emit(Instruction::OuterAddr { function: slot.function, slot: slot.slot });
+                }
+                // Der Platz eines VAR-Parameters enthält die Adresse des Arguments
+                if is_var_param {
+                    self.emit(Instruction::Load);
+                }
+                Ok(slot.ty)
+            }
+            _ => Err(format!("'{}' ist keine Variable", symbol.name)),
+        }
+    }
+
+    fn generate_address(&mut self, designator: &Designator) -> Result<(), String> {
+        let mut ty = self.generate_variable_address(&designator.symbol)?;
+
+        for selector in &designator.selectors {
+            match selector {
+                Selector::Field { name, ty: next } => {
+                    let index = self
+                        .types
+                        .field(ty, name)
+                        .map(|(index, _)| index)
+                        .ok_or_else(|| format!("Feld {} nicht gefunden", name))?;
+                    self.emit(Instruction::Field(index as u32));
+                    ty = *next;
+                }
+                Selector::Index { index, ty: next } => {
+                    let length = match self.types.kind(ty) {
+                        TypeKind::Array { length, .. } => *length as u32,
+                        _ => return Err("Index auf Nicht-Array".to_string()),
+                    };
+                    self.generate_expression(index)?;
+                    self.emit(Instruction::Index(length));
+                    ty = *next;
+                }
+                Selector::Dereference { ty: next } => {
+                    self.emit(Instruction::Deref);
+                    ty = *next;
+                }
+                Selector::TypeGuard { ty: next } => ty = *next,
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Ausdrücke
+    // ========================================================================
+
+    // Wert für eine Variable bzw. einen Parameter vom Typ `target`: Records
+    // werden auf die Felder des Zieltyps projiziert
+    fn generate_value(&mut self, expr: &Expr, target: Option<TypeId>) -> Result<(), String> {
+        self.generate_expression(expr)?;
+        if let Some(target) = target.filter(|&target| self.is_record(target)) {
+            let index = self.type_index(target);
+            self.emit(Instruction::Project(index));
+        }
+        Ok(())
+    }
+
+    fn generate_expression(&mut self, expr: &Expr) -> Result<(), String> {
+        match &expr.kind {
+            ExprKind::Literal(value) => {
+                self.emit(literal(value));
+                // String-Konstante als Wert eines Zeichen-Arrays
+                if matches!(value, ConstValue::String(_)) && expr.ty != TypeId::STRING {
+                    self.emit(Instruction::Chars);
+                }
+            }
+            ExprKind::Designator(designator) => match &designator.symbol.kind {
+                SymbolRefKind::Constant(value) => {
+                    self.emit(literal(value));
+                }
+                SymbolRefKind::Procedure if designator.symbol.module.is_some() => {
+                    let (module, function) = self.extern_function(&designator.symbol)?;
+                    self.emit(Instruction::PushExternProc { module, function });
+                }
+                SymbolRefKind::Procedure => {
+                    let index = self.function(&designator.symbol.qualified_name)?;
+                    self.emit(Instruction::PushProc(index));
+                }
+                SymbolRefKind::Variable { .. } => {
+                    self.generate_address(designator)?;
+                    self.emit(Instruction::Load);
+                }
+                SymbolRefKind::Type | SymbolRefKind::Module => {
+                    return Err(format!("'{}' ist kein Wert", designator.symbol.name));
+                }
+            },
+            ExprKind::Call { procedure, arguments } => self.generate_call(procedure, arguments)?,
+            ExprKind::BuiltinCall { builtin, arguments } => self.generate_builtin_function(*builtin, arguments)?,
+            ExprKind::Unary { op, operand } => {
+                self.generate_expression(operand)?;
+                match op {
+                    UnaryOp::Plus => {}
+                    UnaryOp::Minus => {
+                        self.emit(Instruction::Neg);
+                    }
+                    UnaryOp::Not => {
+                        self.emit(Instruction::Not);
+                    }
+                }
+            }
+            ExprKind::Binary { left, op, right } => self.generate_binary(left, op, right)?,
+            ExprKind::Set(elements) => {
+                self.emit(Instruction::PushSet(0));
+                for (start, end) in elements {
+                    self.generate_expression(start)?;
+                    match end {
+                        Some(end) => {
+                            self.generate_expression(end)?;
+                            self.emit(Instruction::InclRange);
+                        }
+                        None => {
+                            self.emit(Instruction::Incl);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_binary(&mut self, left: &Expr, op: &BinaryOp, right: &Expr) -> Result<(), String> {
+        // & und OR werten den rechten Operanden nur bei Bedarf aus
+        if matches!(op, BinaryOp::And | BinaryOp::Or) {
+            let and = *op == BinaryOp::And;
+            self.generate_expression(left)?;
+            let short = self.emit(if and { Instruction::JumpIfFalse(0) } else { Instruction::JumpIfTrue(0) });
+            self.generate_expression(right)?;
+            let end = self.emit(Instruction::Jump(0));
+            let target = self.here();
+            self.patch(short, target);
+            self.emit(Instruction::PushBool(!and));
+            let after = self.here();
+            self.patch(end, after);
+            return Ok(());
+        }
+
+        if *op == BinaryOp::Is {
+            let target = match self.types.kind(right.ty) {
+                TypeKind::Pointer { target } => *target,
+                _ => right.ty,
+            };
+            self.generate_expression(left)?;
+            let index = self.type_index(target);
+            self.emit(Instruction::Is(index));
+            return Ok(());
+        }
+
+        self.generate_expression(left)?;
+        self.generate_expression(right)?;
+        self.emit(match op {
+            BinaryOp::Add => Instruction::Add,
+            BinaryOp::Sub => Instruction::Sub,
+            BinaryOp::Mul => Instruction::Mul,
+            BinaryOp::Div => Instruction::Div,
+            BinaryOp::IntDiv => Instruction::IntDiv,
+            BinaryOp::Mod => Instruction::Mod,
+            BinaryOp::Equal => Instruction::Eq,
+            BinaryOp::NotEqual => Instruction::Ne,
+            BinaryOp::Less => Instruction::Lt,
+            BinaryOp::LessEqual => Instruction::Le,
+            BinaryOp::Greater => Instruction::Gt,
+            BinaryOp::GreaterEqual => Instruction::Ge,
+            BinaryOp::In => Instruction::In,
+            BinaryOp::And | BinaryOp::Or | BinaryOp::Is => unreachable!(),
+        });
+        Ok(())
+    }
+
+    fn generate_builtin_function(&mut self, builtin: Builtin, arguments: &[Expr]) -> Result<(), String> {
+        // LEN ist statisch bekannt
+        if builtin == Builtin::Len {
+            let length = match arguments.first().map(|argument| (&argument.kind, self.types.kind(argument.ty))) {
+                Some((_, TypeKind::Array { length, .. })) => *length,
+                Some((ExprKind::Literal(ConstValue::String(text)), _)) => text.chars().count(),
+                _ => return Err("LEN erwartet ein Array".to_string()),
+            };
+            self.emit(Instruction::PushInt(length as i64));
+            return Ok(());
+        }
+
+        for argument in arguments {
+            self.generate_expression(argument)?;
+        }
+        self.emit(match builtin {
+            Builtin::Abs => Instruction::Abs,
+            Builtin::Odd => Instruction::Odd,
+            Builtin::Lsl => Instruction::Lsl,
+            Builtin::Asr => Instruction::Asr,
+            Builtin::Ror => Instruction::Ror,
+            Builtin::Floor => Instruction::Floor,
+            Builtin::Flt => Instruction::ToReal,
+            Builtin::Ord => Instruction::Ord,
+            Builtin::Chr => Instruction::Chr,
+            _ => return Err(format!("{} ist keine Funktionsprozedur", builtin.name())),
+        });
+        Ok(())
+    }
+}
+
+impl Default for BytecodeGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn qualify(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+fn literal(value: &ConstValue) -> Instruction {
+    match value {
+        ConstValue::Integer(val) => Instruction::PushInt(*val),
+        ConstValue::Real(val) => Instruction::PushReal(*val),
+        ConstValue::Boolean(val) => Instruction::PushBool(*val),
+        ConstValue::Char(ch) => Instruction::PushChar(*ch),
+        ConstValue::Set(bits) => Instruction::PushSet(*bits),
+        ConstValue::String(text) => Instruction::PushString(text.clone()),
+        ConstValue::Nil => Instruction::PushNil,
+    }
+}
@@ -1,583 +1,1146 @@
-use crate::parser::ast::*;
+use crate::ir::opt::{self, OptLevel, Remark};
+use crate::ir::{self, BinaryOp, Callee, Constant, Function, InstKind, Instruction, Program, Terminator, Type, UnaryOp, Value};
+use crate::lsp::json::Json;
+use crate::parser::ast::{self, Span};
+use crate::semantic::hir::*;
+use crate::semantic::library::LibraryProcedure;
+use crate::semantic::symbol_table::Builtin;
+use crate::semantic::{ConstValue, TypeArena, TypeId, TypeKind};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 
+// ============================================================================
+// C-Backend
+// ============================================================================
+//
+// Übersetzt die IR (crate::ir) nach C99 mit GNU-Erweiterungen (Bereichs-Labels
+// in switch, __builtin_*_overflow): jede Prozedur wird zu einer C-Funktion,
+// der Modulrumpf zu main(), jeder SSA-Wert zu einer lokalen Variablen und
+// jeder Block zu einem Label. Phi-Knoten erhalten eine Schattenvariable
+// (v7_in), die jeder Vorgänger vor dem Sprung setzt.
+//
+// Records beginnen mit einem Zeiger auf ihren Typdeskriptor, Zeiger und
+// Prozedurwerte sind void*. Prozeduren mit geschachtelten Prozeduren legen
+// ihre Slots in einer Rahmenstruktur an, deren Adresse die geschachtelten
+// als statischen Verweis `link` erhalten.
+//
+// Jede ausgegebene Zeile kennt die Stelle im Oberon-Text, aus der sie
+// entsteht: Anweisungen mit Zeile und Spalte von Anfang und Ende,
+// Deklarationen bzw. Prozeduren von Name bis END nur mit Zeilen. Daraus
+// entstehen die Quelltextzuordnung (source_map) und auf Wunsch
+// #line-Direktiven, mit denen C-Compiler, Debugger und Sanitizer auf die
+// .Mod-Datei zeigen; Laufzeitfehler nennen dann über __FILE__ auch sie.
+//
+// generate liefert ein Programm in einer Datei. Getrennt übersetzte Module
+// (generate_unit) erhalten einen Header und eine C-Datei; Namen auf
+// Modulebene beginnen dann mit dem Modulnamen statt mit oberon, der
+// Modulrumpf wird zu <Modul>__init. Ein Hauptprogramm (main_stub) ruft die
+// Initialisierungen in Importreihenfolge auf, ein Makefile (makefile) baut
+// alles zusammen.
+
 pub struct CGenerator {
     output: String,
+    types: TypeArena,
     indent_level: usize,
-    label_counter: usize,
-    temp_counter: usize,
+    // C-Name jedes Records
+    records: HashMap<TypeId, String>,
+    // Funktionen mit Rahmenstruktur
+    framed: HashSet<String>,
+    // Slots aller Funktionen für den Zugriff über statische Verweise
+    slots: HashMap<String, Vec<ir::Slot>>,
+    // Funktion in Arbeit, für Meldungen und Rahmenzugriffe
+    function: String,
+    depth: usize,
+    loop_counter: usize,
+    level: OptLevel,
+    remarks: Vec<Remark>,
+    // Quelldatei und Name der C-Datei für #line, None = ohne Direktiven
+    line_directives: Option<(String, String)>,
+    // Zeilen der Record-Deklarationen
+    record_lines: HashMap<TypeId, usize>,
+    // Ausgegebene Zeilen und Oberon-Stelle der folgenden
+    lines: usize,
+    span: Option<Span>,
+    // Zeile, die der C-Compiler der nächsten Zeile zuordnet, solange eine
+    // #line-Direktive der Quelldatei gilt
+    directive: Option<usize>,
+    mappings: Vec<Mapping>,
+    // Modul bei getrennter Übersetzung
+    unit: Option<String>,
+}
+
+// Header und C-Datei eines getrennt übersetzten Moduls
+#[derive(Debug, Clone, PartialEq)]
+pub struct CUnit {
+    pub header: String,
+    pub source: String,
+}
+
+// C-Zeilen `c` (von, bis) stammen aus der Oberon-Stelle `oberon` der
+// Prozedur bzw. des Modulrumpfs `function` (leer bei Deklarationen auf
+// Modulebene); Spalte 0 steht für eine ganze Zeile
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub c: (usize, usize),
+    pub oberon: Span,
+    pub function: String,
 }
 
 impl CGenerator {
     pub fn new() -> Self {
+        Self::with_optimization(OptLevel::O0)
+    }
+
+    // Optimiert die IR vor der Ausgabe (ir::opt)
+    pub fn with_optimization(level: OptLevel) -> Self {
         CGenerator {
             output: String::new(),
+            types: TypeArena::new(),
             indent_level: 0,
-            label_counter: 0,
-            temp_counter: 0,
+            records: HashMap::new(),
+            framed: HashSet::new(),
+            slots: HashMap::new(),
+            function: String::new(),
+            depth: 0,
+            loop_counter: 0,
+            level,
+            remarks: Vec::new(),
+            line_directives: None,
+            record_lines: HashMap::new(),
+            lines: 0,
+            span: None,
+            directive: None,
+            mappings: Vec::new(),
+            unit: None,
         }
     }
 
-    pub fn generate(&mut self, module: &Module) -> String {
-        self.output.clear();
+    // #line-Direktiven für die Quelldatei `source` (etwa "T.Mod"); `target`
+    // ist der Name der C-Datei für Zeilen ohne Oberon-Herkunft
+    pub fn set_line_directives(&mut self, source: &str, target: &str) {
+        self.line_directives = Some((source.to_string(), target.to_string()));
+    }
 
-        // C Header
-        self.emit_line("#include <stdio.h>");
-        self.emit_line("#include <stdlib.h>");
-        self.emit_line("#include <stdbool.h>");
-        self.emit_line("#include <string.h>");
-        self.emit_line("#include <stdint.h>");
-        self.emit_line("");
+    // Zuordnung aus dem letzten Aufruf von generate bzw. zur C-Datei aus
+    // generate_unit
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
 
-        // Forward-Deklarationen für Prozeduren
-        self.emit_forward_declarations(&module.declarations);
-        self.emit_line("");
+    // Zuordnung als JSON: C-Zeilen (von, bis) zu Oberon-Stellen
+    // ([Zeile, Spalte] von Anfang und Ende), je Bereich mit Funktion
+    pub fn source_map(&self, source: &str, target: &str) -> String {
+        let range = |(from, to): (usize, usize)| Json::Array(vec![Json::Number(from as f64), Json::Number(to as f64)]);
+        let mappings = self
+            .mappings
+            .iter()
+            .map(|mapping| {
+                let span = mapping.oberon;
+                Json::object(vec![
+                    ("c", range(mapping.c)),
+                    ("oberon", Json::Array(vec![range((span.line, span.column)), range((span.end_line, span.end_column))])),
+                    ("function", Json::string(&mapping.function)),
+                ])
+            })
+            .collect();
+        let map = Json::object(vec![
+            ("version", Json::Number(2.0)),
+            ("file", Json::string(target)),
+            ("source", Json::string(source)),
+            ("mappings", Json::Array(mappings)),
+        ]);
+        format!("{}\n", map)
+    }
+
+    // Hinweise der Optimierungen aus dem letzten Aufruf von generate
+    pub fn remarks(&self) -> &[Remark] {
+        &self.remarks
+    }
 
-        // Globale Konstanten
-        self.generate_constants(&module.declarations.constants);
+    pub fn generate(&mut self, module: &Module) -> Result<String, String> {
+        if let Some(name) = imported_modules(module).first() {
+            return Err(format!(
+                "Import {}: ohne --modules übersetzt das C-Backend nur einzelne Module (und Out)",
+                name
+            ));
+        }
+        let mut program = ir::build(module)?;
+        self.remarks = opt::optimize(&mut program, self.level);
+        self.unit = None;
+        self.clear_output();
+        self.prepare(&program, &[&module.declarations]);
 
-        // Type Definitions
-        self.generate_types(&module.declarations.types);
+        // C Header
+        for include in INCLUDES {
+            self.emit_line(include);
+        }
+        self.output.push_str(RUNTIME);
+        self.lines += RUNTIME.matches('\n').count();
+        self.emit_line("");
 
-        // Globale Variablen
-        self.generate_global_variables(&module.declarations.variables);
+        let records = self.record_order(&module.declarations);
+        self.generate_program(&program, &records);
+        Ok(self.output.clone())
+    }
 
-        // Prozeduren
-        for proc in &module.declarations.procedures {
-            self.generate_procedure(proc);
+    // Getrennte Übersetzung: der Header enthält alle Records des Moduls,
+    // weil exportierte auf nicht exportierte verweisen können, dazu die
+    // Deskriptoren, exportierte Variablen und Prozeduren und die
+    // Initialisierung. Die Header der Importe und die Laufzeit (oberon.h)
+    // bindet er selbst ein.
+    pub fn generate_unit(&mut self, module: &Module) -> Result<CUnit, String> {
+        let mut program = ir::build(module)?;
+        self.remarks = opt::optimize(&mut program, self.level);
+        self.unit = Some(module.name.clone());
+        self.clear_output();
+        self.prepare(&program, &[&module.declarations]);
+        let records = self.record_order(&module.declarations);
+
+        // #line-Direktiven und Zuordnung gelten nur der C-Datei
+        let line_directives = self.line_directives.take();
+        self.emit_line(&format!("#ifndef {}_H", module.name));
+        self.emit_line(&format!("#define {}_H", module.name));
+        self.emit_line("");
+        self.emit_line(&format!("#include \"{}\"", RUNTIME_HEADER));
+        for import in imported_modules(module) {
+            self.emit_line(&format!("#include \"{}.h\"", import));
+        }
+        self.emit_line("");
+        self.generate_typedefs(&records);
+        if !records.is_empty() {
+            self.emit_line("// Type descriptors");
+            for &record in &records {
+                self.emit_line(&format!("extern const oberon_desc {}_desc;", self.record_name(record)));
+            }
+            self.emit_line("");
+        }
+        let globals: Vec<&ir::Global> = program.globals.iter().filter(|global| global.exported).collect();
+        if !globals.is_empty() {
+            self.emit_line("// Exported variables");
+            for global in globals {
+                let declaration = self.declaration(global.ty, &self.global_name(&global.name));
+                self.emit_line(&format!("extern {};", declaration));
+            }
+            self.emit_line("");
+        }
+        let procedures: Vec<&Function> = program.functions.iter().filter(|function| function.exported).collect();
+        if !procedures.is_empty() {
+            self.emit_line("// Exported procedures");
+            for function in procedures {
+                let signature = self.signature(function);
+                self.emit_line(&format!("{};", signature));
+            }
             self.emit_line("");
         }
+        self.emit_line(&format!("{};", self.init_signature()));
+        self.emit_line("");
+        self.emit_line("#endif");
+        let header = std::mem::take(&mut self.output);
 
-        // Main-Funktion (Modul-Body)
-        self.emit_line("int main(void) {");
-        self.indent_level += 1;
+        self.line_directives = line_directives;
+        self.clear_output();
+        self.emit_line(&format!("#include \"{}.h\"", module.name));
+        self.emit_line("");
+        self.generate_program(&program, &records);
+        Ok(CUnit { header, source: self.output.clone() })
+    }
 
-        if let Some(body) = &module.body {
-            self.generate_statement_sequence(body);
+    // Ausschnitt ohne Header und Laufzeit, etwa für die REPL: Typen,
+    // Variablen und Prozeduren des Moduls, danach sein Rumpf. `context`
+    // enthält frühere Deklarationen, auf die das Modul verweisen darf.
+    pub fn generate_fragment(&mut self, module: &Module, context: &Declarations) -> String {
+        let mut lowering = ir::Lowering::new();
+        lowering.declare(context);
+        let mut program = match lowering.lower(module) {
+            Ok(program) => program,
+            Err(e) => return format!("/* {} */\n", e),
+        };
+        self.unit = None;
+        ir::ssa::construct(&mut program);
+        if module.body.is_empty() {
+            program.functions.retain(|function| function.depth > 0);
         }
 
-        self.emit_line("return 0;");
-        self.indent_level -= 1;
-        self.emit_line("}");
-
+        self.clear_output();
+        self.prepare(&program, &[context, &module.declarations]);
+        let records = self.record_order(&module.declarations);
+        self.generate_program(&program, &records);
         self.output.clone()
     }
 
+    // Einzelner Ausdruck ohne Laufzeitprüfungen, zur Anzeige in der REPL
+    pub fn generate_expression(&mut self, types: &TypeArena, expr: &Expr) -> String {
+        self.types = types.clone();
+        self.expression_to_c(expr)
+    }
+
     // ========================================================================
-    // Forward-Deklarationen
+    // Programm
     // ========================================================================
 
-    fn emit_forward_declarations(&mut self, decls: &DeclSequence) {
-        self.emit_line("// Forward declarations");
-        for proc in &decls.procedures {
-            if proc.is_forward {
-                continue;
-            }
-
-            let return_type = if let Some(params) = &proc.params {
-                if let Some(ret) = &params.return_type {
-                    self.map_type_name(&ret.name)
-                } else {
-                    "void".to_string()
-                }
-            } else {
-                "void".to_string()
-            };
-
-            let mut signature = format!("{} {}(", return_type, self.mangle_name(&proc.name.name));
+    fn prepare(&mut self, program: &Program, declarations: &[&Declarations]) {
+        self.types = program.types.clone();
+        self.records.clear();
+        self.record_lines.clear();
+        for decls in declarations {
+            self.name_records("", decls);
+        }
+        self.framed = program
+            .functions
+            .iter()
+            .filter(|function| function.depth > 1)
+            .map(|function| function.ancestor(function.depth - 1))
+            .collect();
+        self.slots = program
+            .functions
+            .iter()
+            .map(|function| (function.name.clone(), function.slots.clone()))
+            .collect();
+    }
 
-            if let Some(params) = &proc.params {
-                let param_strs: Vec<String> = params.sections.iter().flat_map(|section| {
-                    section.names.iter().map(|name| {
-                        let (type_str, array_suffix) = self.type_to_c_with_array(&section.param_type);
-                        let ptr = if section.is_var { "*" } else { "" };
-                        format!("{}{} {}{}", type_str, ptr, self.mangle_name(name), array_suffix)
-                    }).collect::<Vec<_>>()
-                }).collect();
+    fn generate_program(&mut self, program: &Program, records: &[TypeId]) {
+        self.function.clear();
+        self.generate_types(records);
+
+        if !program.globals.is_empty() {
+            self.emit_line("// Global variables");
+            for global in &program.globals {
+                let declaration = self.declaration(global.ty, &self.global_name(&global.name));
+                let storage = if global.exported { "" } else { "static " };
+                self.locate(global.line, global.line);
+                self.emit_line(&format!("{}{};", storage, declaration));
+            }
+            self.unlocate();
+            self.emit_line("");
+        }
 
-                if param_strs.is_empty() {
-                    signature.push_str("void");
-                } else {
-                    signature.push_str(&param_strs.join(", "));
-                }
-            } else {
-                signature.push_str("void");
+        let procedures: Vec<&Function> = program.functions.iter().filter(|function| function.depth > 0).collect();
+        for function in &procedures {
+            if self.framed.contains(&function.name) {
+                self.generate_frame(function);
             }
+        }
+        if !procedures.is_empty() {
+            self.emit_line("// Forward declarations");
+            for function in &procedures {
+                let signature = self.signature(function);
+                self.locate(function.line, function.line);
+                self.emit_line(&format!("{};", signature));
+            }
+            self.unlocate();
+            self.emit_line("");
+        }
 
-            signature.push_str(");");
-            self.emit_line(&signature);
+        for (index, function) in program.functions.iter().enumerate() {
+            if index > 0 {
+                self.emit_line("");
+            }
+            self.generate_function(function);
         }
     }
 
     // ========================================================================
-    // Konstanten
+    // Typen
     // ========================================================================
 
-    fn generate_constants(&mut self, constants: &[ConstDeclaration]) {
-        if constants.is_empty() {
-            return;
+    // Benannte Records heißen nach ihrer Deklaration, lokale mit dem Namen
+    // der Prozedur davor
+    fn name_records(&mut self, prefix: &str, decls: &Declarations) {
+        for type_decl in &decls.types {
+            let defines = self.types.name(type_decl.type_def) == Some(type_decl.name.as_str());
+            if defines && matches!(self.types.kind(type_decl.type_def), TypeKind::Record { .. }) {
+                self.records.insert(type_decl.type_def, self.global_name(&qualify(prefix, &type_decl.name)));
+                self.record_lines.insert(type_decl.type_def, type_decl.line);
+            }
         }
-
-        self.emit_line("// Constants");
-        for const_decl in constants {
-            let value = self.expression_to_c(&const_decl.value);
-            self.emit_line(&format!(
-                "#define {} {}",
-                self.mangle_name(&const_decl.name.name),
-                value
-            ));
+        for procedure in &decls.procedures {
+            self.name_records(&qualify(prefix, &procedure.name), &procedure.declarations);
         }
-        self.emit_line("");
     }
 
-    // ========================================================================
-    // Typen
-    // ========================================================================
+    // Records der Deklarationen, jeder nach den Records, die er als Basistyp
+    // oder Feld enthält; namenlose erhalten hier ihren Namen
+    fn record_order(&mut self, decls: &Declarations) -> Vec<TypeId> {
+        let mut roots = Vec::new();
+        collect_types(decls, &mut roots);
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        for ty in roots {
+            self.visit_type(ty, &mut visited, &mut order);
+        }
+        order
+    }
 
-    fn generate_types(&mut self, types: &[TypeDeclaration]) {
-        if types.is_empty() {
+    fn visit_type(&mut self, ty: TypeId, visited: &mut HashSet<TypeId>, order: &mut Vec<TypeId>) {
+        if !visited.insert(ty) {
             return;
         }
-
-        self.emit_line("// Type definitions");
-        for type_decl in types {
-            match &type_decl.type_def {
-                Type::Record { fields, .. } => {
-                    self.emit_line(&format!("typedef struct {{"));
-                    self.indent_level += 1;
-                    for field_list in fields {
-                        let (type_str, array_suffix) = self.type_to_c_with_array(&field_list.field_type);
-                        for name in &field_list.names {
-                            self.emit_line(&format!("{} {}{};", type_str, self.mangle_name(&name.name), array_suffix));
-                        }
-                    }
-                    self.indent_level -= 1;
-                    self.emit_line(&format!("}} {};", self.mangle_name(&type_decl.name.name)));
+        match self.types.kind(ty).clone() {
+            TypeKind::Array { element, .. } => self.visit_type(element, visited, order),
+            TypeKind::Pointer { target } => self.visit_type(target, visited, order),
+            TypeKind::Procedure { params, result } => {
+                for param in params {
+                    self.visit_type(param.param_type, visited, order);
+                }
+                if let Some(result) = result {
+                    self.visit_type(result, visited, order);
                 }
-                Type::Array { lengths, element_type } => {
-                    // Für Array-Typen verwenden wir jetzt eine korrekte C-Syntax
-                    let elem_type = self.type_to_c_base(element_type);
-                    self.emit_line(&format!(
-                        "typedef {} {}",
-                        elem_type,
-                        self.mangle_name(&type_decl.name.name)
-                    ));
-
-                    let dims = lengths.iter()
-                        .map(|len| {
-                            if let Expression::IntegerLiteral(val) = len {
-                                format!("[{}]", val)
-                            } else if let Expression::Designator(d) = len {
-                                // Konstanten-Referenz auflösen
-                                format!("[{}]", self.mangle_name(&d.base.name))
-                            } else {
-                                "[100]".to_string()
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                        .join("");
-
-                    self.emit_line(&format!("{};", dims));
+            }
+            // Records importierter Module stehen im Header ihres Moduls
+            TypeKind::Record { .. } if self.types.name(ty).is_some_and(|name| name.contains('.')) => {
+                let name = self.global_name(self.types.name(ty).unwrap_or_default());
+                self.records.insert(ty, name);
+            }
+            TypeKind::Record { base, fields } => {
+                if let Some(base) = base {
+                    self.visit_type(base, visited, order);
                 }
-                Type::Pointer { target_type } => {
-                    let target = self.type_to_c_base(target_type);
-                    self.emit_line(&format!(
-                        "typedef {}* {};",
-                        target,
-                        self.mangle_name(&type_decl.name.name)
-                    ));
+                for field in fields {
+                    self.visit_type(field.ty, visited, order);
                 }
-                _ => {
-                    let (type_str, _) = self.type_to_c_with_array(&type_decl.type_def);
-                    self.emit_line(&format!(
-                        "typedef {} {};",
-                        type_str,
-                        self.mangle_name(&type_decl.name.name)
-                    ));
+                if !self.records.contains_key(&ty) {
+                    let name = self.global_name(&format!("record{}", self.records.len()));
+                    self.records.insert(ty, name);
                 }
+                order.push(ty);
             }
+            _ => {}
         }
-        self.emit_line("");
     }
 
-    fn type_to_c_base(&self, type_def: &Type) -> String {
-        match type_def {
-            Type::Qualident(q) => self.map_type_name(&q.name),
-            Type::Array { element_type, .. } => {
-                self.type_to_c_base(element_type)
-            }
-            Type::Pointer { target_type } => {
-                format!("{}*", self.type_to_c_base(target_type))
-            }
-            _ => "void".to_string(),
+    // Bei getrennter Übersetzung stehen die Typdefinitionen im Header
+    fn generate_types(&mut self, records: &[TypeId]) {
+        if self.unit.is_none() {
+            self.generate_typedefs(records);
         }
+        self.generate_descriptors(records);
     }
 
-    fn type_to_c_with_array(&self, type_def: &Type) -> (String, String) {
-        match type_def {
-            Type::Array { lengths, element_type } => {
-                let base_type = self.type_to_c_base(element_type);
-                let dims = lengths.iter()
-                    .map(|len| {
-                        if let Expression::IntegerLiteral(val) = len {
-                            format!("[{}]", val)
-                        } else if let Expression::Designator(d) = len {
-                            format!("[{}]", self.mangle_name(&d.base.name))
-                        } else {
-                            "[100]".to_string()
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .join("");
-                (base_type, dims)
-            }
-            _ => (self.type_to_c_base(type_def), String::new()),
+    fn generate_typedefs(&mut self, records: &[TypeId]) {
+        if records.is_empty() {
+            return;
         }
-    }
 
-    fn map_type_name(&self, name: &str) -> String {
-        match name {
-            "INTEGER" => "int64_t".to_string(),
-            "REAL" => "double".to_string(),
-            "BOOLEAN" => "bool".to_string(),
-            "CHAR" => "char".to_string(),
-            "SET" => "uint32_t".to_string(),
-            _ => self.mangle_name(name),
+        self.emit_line("// Type definitions");
+        for &record in records {
+            let name = self.record_name(record);
+            let line = self.record_lines.get(&record).copied().unwrap_or(0);
+            self.locate(line, line);
+            self.emit_line(&format!("typedef struct {} {{", name));
+            self.unlocate();
+            self.indent_level += 1;
+            self.emit_line("const oberon_desc *tag;");
+            let fields: Vec<(String, TypeId)> =
+                self.types.fields(record).into_iter().map(|field| (field.name.clone(), field.ty)).collect();
+            for (field, ty) in fields {
+                let declaration = self.declaration(ty, &mangle(&field));
+                self.emit_line(&format!("{};", declaration));
+            }
+            self.indent_level -= 1;
+            self.emit_line(&format!("}} {};", name));
         }
+        self.emit_line("");
     }
 
-    // ========================================================================
-    // Variablen
-    // ========================================================================
-
-    fn generate_global_variables(&mut self, variables: &[VariableDeclaration]) {
-        if variables.is_empty() {
+    // Deskriptoren getrennt übersetzter Module sind für Typtests und
+    // Erweiterungen in anderen Modulen sichtbar
+    fn generate_descriptors(&mut self, records: &[TypeId]) {
+        if records.is_empty() {
             return;
         }
 
-        self.emit_line("// Global variables");
-        for var_decl in variables {
-            let (type_str, array_suffix) = self.type_to_c_with_array(&var_decl.var_type);
-            for name in &var_decl.names {
-                self.emit_line(&format!("{} {}{};", type_str, self.mangle_name(&name.name), array_suffix));
-            }
+        self.emit_line("// Type descriptors");
+        let storage = if self.unit.is_some() { "" } else { "static " };
+        for &record in records {
+            let base = match self.types.kind(record) {
+                TypeKind::Record { base: Some(base), .. } => format!("&{}_desc", self.record_name(*base)),
+                _ => "NULL".to_string(),
+            };
+            let line = self.record_lines.get(&record).copied().unwrap_or(0);
+            self.locate(line, line);
+            self.emit_line(&format!("{}const oberon_desc {}_desc = {{ {} }};", storage, self.record_name(record), base));
         }
+        self.unlocate();
         self.emit_line("");
     }
 
-    // ========================================================================
-    // Prozeduren
-    // ========================================================================
-
-    fn generate_procedure(&mut self, proc: &ProcedureDeclaration) {
-        if proc.is_forward {
-            return;
+    fn record_name(&self, record: TypeId) -> String {
+        match self.records.get(&record) {
+            Some(name) => name.clone(),
+            None => self.types.name(record).map(|name| self.global_name(name)).unwrap_or_else(|| "oberon_record".to_string()),
         }
+    }
 
-        let return_type = if let Some(params) = &proc.params {
-            if let Some(ret) = &params.return_type {
-                self.map_type_name(&ret.name)
-            } else {
-                "void".to_string()
+    // Liefert Basistyp und Array-Suffix, z.B. ("int64_t", "[10][20]")
+    fn type_to_c_with_array(&self, type_id: TypeId) -> (String, String) {
+        match self.types.kind(type_id) {
+            TypeKind::Integer => ("int64_t".to_string(), String::new()),
+            TypeKind::Real => ("double".to_string(), String::new()),
+            TypeKind::Boolean => ("bool".to_string(), String::new()),
+            TypeKind::Char => ("char".to_string(), String::new()),
+            TypeKind::Set => ("uint32_t".to_string(), String::new()),
+            TypeKind::Array { length, element } => {
+                let (base_type, inner_dims) = self.type_to_c_with_array(*element);
+                (base_type, format!("[{}]{}", length, inner_dims))
             }
-        } else {
-            "void".to_string()
-        };
+            TypeKind::Record { .. } => (self.record_name(type_id), String::new()),
+            TypeKind::String
+            | TypeKind::Nil
+            | TypeKind::Pointer { .. }
+            | TypeKind::Procedure { .. }
+            | TypeKind::Imported(_)
+            | TypeKind::Forward(_) => ("void*".to_string(), String::new()),
+        }
+    }
 
-        let mut signature = format!("{} {}(", return_type, self.mangle_name(&proc.name.name));
+    // Vollständiger Typ ohne Namen, etwa für sizeof
+    fn type_to_c(&self, type_id: TypeId) -> String {
+        let (base, suffix) = self.type_to_c_with_array(type_id);
+        format!("{}{}", base, suffix)
+    }
 
-        if let Some(params) = &proc.params {
-            let param_strs: Vec<String> = params.sections.iter().flat_map(|section| {
-                section.names.iter().map(|name| {
-                    let (type_str, array_suffix) = self.type_to_c_with_array(&section.param_type);
-                    let ptr = if section.is_var { "*" } else { "" };
-                    format!("{}{} {}{}", type_str, ptr, self.mangle_name(name), array_suffix)
-                }).collect::<Vec<_>>()
-            }).collect();
+    fn declaration(&self, type_id: TypeId, name: &str) -> String {
+        let (base, suffix) = self.type_to_c_with_array(type_id);
+        format!("{} {}{}", base, name, suffix)
+    }
 
-            if param_strs.is_empty() {
-                signature.push_str("void");
-            } else {
-                signature.push_str(&param_strs.join(", "));
-            }
-        } else {
-            signature.push_str("void");
+    // ========================================================================
+    // Funktionen
+    // ========================================================================
+
+    // Präfix der Namen auf Modulebene
+    fn prefix(&self) -> &str {
+        self.unit.as_deref().unwrap_or("oberon")
+    }
+
+    // Variable oder Record auf Modulebene; importierte ("Modul.Name")
+    // heißen wie im Header ihres Moduls
+    fn global_name(&self, name: &str) -> String {
+        match name.contains('.') {
+            true => name.replace('.', "_"),
+            false => format!("{}_{}", self.prefix(), name),
         }
+    }
 
-        signature.push_str(") {");
-        self.emit_line(&signature);
-        self.indent_level += 1;
+    // Eigene Funktion, geschachtelte mit den Namen der umgebenden davor
+    fn function_name(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix(), name.replace('.', "_"))
+    }
 
-        // Lokale Variablen
-        for var_decl in &proc.declarations.variables {
-            let (type_str, array_suffix) = self.type_to_c_with_array(&var_decl.var_type);
-            for name in &var_decl.names {
-                self.emit_line(&format!("{} {}{};", type_str, self.mangle_name(&name.name), array_suffix));
-            }
+    // Aufgerufene Prozedur auf Tiefe `depth` (ir::Callee::Direct)
+    fn procedure_name(&self, name: &str, depth: usize) -> String {
+        match depth {
+            1 => self.global_name(name),
+            _ => self.function_name(name),
         }
+    }
+
+    fn init_signature(&self) -> String {
+        format!("void {}__init(void)", self.prefix())
+    }
 
-        // Body
-        if let Some(body) = &proc.body {
-            self.generate_statement_sequence(body);
+    fn slot_name(&self, slot: &ir::Slot, index: usize) -> String {
+        match &slot.name {
+            Some(name) => mangle(name),
+            None => format!("tmp{}", index),
         }
+    }
 
-        // Return
-        if let Some(ret_expr) = &proc.return_expr {
-            self.emit_line(&format!("return {};", self.expression_to_c(ret_expr)));
+    fn slot_declaration(&self, slot: &ir::Slot, index: usize) -> String {
+        let name = self.slot_name(slot, index);
+        if slot.by_reference {
+            format!("void* {}", name)
+        } else {
+            self.declaration(slot.ty, &name)
         }
+    }
 
+    // Rahmen einer Prozedur mit geschachtelten Prozeduren
+    fn generate_frame(&mut self, function: &Function) {
+        self.locate(function.line, function.end_line);
+        self.emit_line(&format!("struct {}_frame {{", self.function_name(&function.name)));
+        self.unlocate();
+        self.indent_level += 1;
+        if function.depth > 1 {
+            let parent = self.function_name(&function.ancestor(function.depth - 1));
+            self.emit_line(&format!("struct {}_frame *link;", parent));
+        }
+        for (index, slot) in function.slots.iter().enumerate() {
+            let declaration = self.slot_declaration(slot, index);
+            self.emit_line(&format!("{};", declaration));
+        }
         self.indent_level -= 1;
-        self.emit_line("}");
+        self.emit_line("};");
+        self.emit_line("");
     }
 
-    // ========================================================================
-    // Statements
-    // ========================================================================
-
-    fn generate_statement_sequence(&mut self, statements: &[Statement]) {
-        for stmt in statements {
-            self.generate_statement(stmt);
+    fn signature(&self, function: &Function) -> String {
+        if function.depth == 0 {
+            return match self.unit {
+                Some(_) => self.init_signature(),
+                None => "int main(void)".to_string(),
+            };
+        }
+        let result = function.result.map(value_type).unwrap_or("void");
+        let mut params = Vec::new();
+        if function.depth > 1 {
+            let parent = self.function_name(&function.ancestor(function.depth - 1));
+            params.push(format!("struct {}_frame *link", parent));
+        }
+        for param in &function.params {
+            params.push(format!("{} {}", value_type(function.value_type(param.value)), param.value));
+        }
+        if params.is_empty() {
+            params.push("void".to_string());
         }
+        let storage = if function.exported { "" } else { "static " };
+        format!("{}{} {}({})", storage, result, self.function_name(&function.name), params.join(", "))
     }
 
-    fn generate_statement(&mut self, stmt: &Statement) {
-        match stmt {
-            Statement::Empty => {}
-            Statement::Assignment { target, value } => {
-                let target_str = self.designator_to_c(target);
-                let value_str = self.expression_to_c(value);
-                self.emit_line(&format!("{} = {};", target_str, value_str));
+    fn generate_function(&mut self, function: &Function) {
+        self.function = function.name.clone();
+        self.depth = function.depth;
+        self.loop_counter = 0;
+
+        // Der Kopf gehört zur ganzen Prozedur, Befehle zu ihrer Anweisung;
+        // Variablen der Übersetzung haben keine Entsprechung im Oberon-Text
+        let signature = self.signature(function);
+        self.locate(function.line, function.end_line);
+        self.emit_line(&format!("{} {{", signature));
+        self.unlocate();
+        self.indent_level += 1;
+
+        if self.framed.contains(&function.name) {
+            self.emit_line(&format!("struct {}_frame frame = {{0}};", self.function_name(&function.name)));
+            if function.depth > 1 {
+                self.emit_line("frame.link = link;");
             }
-            Statement::ProcedureCall { designator, arguments } => {
-                let proc_name = self.designator_to_c(designator);
-                let args: Vec<String> = arguments.iter()
-                    .map(|arg| self.expression_to_c(arg))
-                    .collect();
-                self.emit_line(&format!("{}({});", proc_name, args.join(", ")));
+        } else {
+            for (index, slot) in function.slots.iter().enumerate() {
+                let declaration = self.slot_declaration(slot, index);
+                let zero = if slot.value_type(&self.types).is_some() { "0" } else { "{0}" };
+                self.emit_line(&format!("{} = {};", declaration, zero));
             }
-            Statement::If { condition, then_body, elsif_parts, else_body } => {
-                let cond_str = self.expression_to_c(condition);
-                self.emit_line(&format!("if ({}) {{", cond_str));
-                self.indent_level += 1;
-                self.generate_statement_sequence(then_body);
-                self.indent_level -= 1;
-                self.emit_line("}");
+        }
 
-                for (elsif_cond, elsif_body) in elsif_parts {
-                    let elsif_str = self.expression_to_c(elsif_cond);
-                    self.emit_line(&format!("else if ({}) {{", elsif_str));
-                    self.indent_level += 1;
-                    self.generate_statement_sequence(elsif_body);
-                    self.indent_level -= 1;
-                    self.emit_line("}");
-                }
+        // Werte außer den Parametern; Phi-Knoten mit Schattenvariable
+        let mut defined: Vec<(Value, bool)> = Vec::new();
+        for block in &function.blocks {
+            defined.extend(block.phis.iter().map(|phi| (phi.dest, true)));
+            defined.extend(block.instructions.iter().filter_map(|instruction| instruction.dest).map(|dest| (dest, false)));
+        }
+        defined.sort();
+        for (value, is_phi) in defined {
+            let ty = value_type(function.value_type(value));
+            if is_phi {
+                // Getrennt deklariert: bei void* gälte der Stern nur dem ersten
+                self.emit_line(&format!("{0} {1}; {0} {1}_in;", ty, value));
+            } else {
+                self.emit_line(&format!("{} {};", ty, value));
+            }
+        }
 
-                if let Some(else_stmts) = else_body {
-                    self.emit_line("else {");
-                    self.indent_level += 1;
-                    self.generate_statement_sequence(else_stmts);
-                    self.indent_level -= 1;
-                    self.emit_line("}");
-                }
+        let predecessors = function.predecessors();
+        for id in function.block_ids() {
+            let block = function.block(id);
+            if !predecessors[id.index()].is_empty() {
+                let indent = std::mem::replace(&mut self.indent_level, 0);
+                self.emit_line(&format!("{}:;", id));
+                self.indent_level = indent;
+            }
+            for phi in &block.phis {
+                self.emit_line(&format!("{} = {}_in;", phi.dest, phi.dest));
+            }
+            for instruction in &block.instructions {
+                self.generate_instruction(function, instruction);
             }
-            Statement::Case { expr, cases, else_body } => {
-                let expr_str = self.expression_to_c(expr);
-                self.emit_line(&format!("switch ({}) {{", expr_str));
-                self.indent_level += 1;
 
-                for case in cases {
-                    for label in &case.labels {
-                        let label_str = self.expression_to_c(&label.start);
-                        self.emit_line(&format!("case {}:", label_str));
+            // Schattenvariablen der Phi-Knoten in den Nachfolgern
+            let mut seen = HashSet::new();
+            for successor in block.terminator.successors() {
+                if !seen.insert(successor) {
+                    continue;
+                }
+                for phi in &function.block(successor).phis {
+                    if let Some(&(_, value)) = phi.arguments.iter().find(|(source, _)| *source == id) {
+                        self.emit_line(&format!("{}_in = {};", phi.dest, value));
                     }
-                    self.indent_level += 1;
-                    self.generate_statement_sequence(&case.body);
-                    self.emit_line("break;");
-                    self.indent_level -= 1;
                 }
+            }
+            self.generate_terminator(function, &block.terminator);
+        }
 
-                if let Some(else_stmts) = else_body {
-                    self.emit_line("default:");
-                    self.indent_level += 1;
-                    self.generate_statement_sequence(else_stmts);
-                    self.emit_line("break;");
-                    self.indent_level -= 1;
-                }
+        self.indent_level -= 1;
+        self.locate(function.end_line, function.end_line);
+        self.emit_line("}");
+        self.unlocate();
+    }
 
-                self.indent_level -= 1;
-                self.emit_line("}");
+    fn generate_terminator(&mut self, function: &Function, terminator: &Terminator) {
+        match terminator {
+            Terminator::Jump(target) => self.emit_line(&format!("goto {};", target)),
+            Terminator::Branch { condition, then_block, else_block } => {
+                self.emit_line(&format!("if ({}) goto {};", condition, then_block));
+                self.emit_line(&format!("goto {};", else_block));
             }
-            Statement::While { condition, body, elsif_parts } => {
-                if elsif_parts.is_empty() {
-                    let cond_str = self.expression_to_c(condition);
-                    self.emit_line(&format!("while ({}) {{", cond_str));
-                    self.indent_level += 1;
-                    self.generate_statement_sequence(body);
-                    self.indent_level -= 1;
-                    self.emit_line("}");
-                } else {
-                    self.emit_line("while (1) {");
-                    self.indent_level += 1;
-
-                    let cond_str = self.expression_to_c(condition);
-                    self.emit_line(&format!("if ({}) {{", cond_str));
-                    self.indent_level += 1;
-                    self.generate_statement_sequence(body);
-                    self.indent_level -= 1;
-                    self.emit_line("}");
-
-                    for (elsif_cond, elsif_body) in elsif_parts {
-                        let elsif_str = self.expression_to_c(elsif_cond);
-                        self.emit_line(&format!("else if ({}) {{", elsif_str));
-                        self.indent_level += 1;
-                        self.generate_statement_sequence(elsif_body);
-                        self.indent_level -= 1;
-                        self.emit_line("}");
+            Terminator::Switch { value, cases, default } => {
+                self.emit_line(&format!("switch ({}) {{", value));
+                for &(start, end, target) in cases {
+                    if start == end {
+                        self.emit_line(&format!("case {}: goto {};", int_literal(start), target));
+                    } else {
+                        // Bereichs-Labels (GNU-C-Erweiterung, von gcc und clang unterstützt)
+                        self.emit_line(&format!("case {} ... {}: goto {};", int_literal(start), int_literal(end), target));
                     }
-
-                    self.emit_line("else { break; }");
-                    self.indent_level -= 1;
-                    self.emit_line("}");
                 }
+                self.emit_line(&format!("default: goto {};", default));
+                self.emit_line("}");
             }
-            Statement::Repeat { body, condition } => {
-                self.emit_line("do {");
-                self.indent_level += 1;
-                self.generate_statement_sequence(body);
-                self.indent_level -= 1;
-                let cond_str = self.expression_to_c(condition);
-                self.emit_line(&format!("}} while (!({}) );", cond_str));
+            Terminator::Return(Some(value)) => self.emit_line(&format!("return {};", value)),
+            Terminator::Return(None) if function.depth == 0 && self.unit.is_none() => self.emit_line("return 0;"),
+            Terminator::Return(None) => self.emit_line("return;"),
+            Terminator::CaseTrap { value, line } => {
+                self.locate(*line, *line);
+                let at = self.at(*line, &self.function);
+                self.emit_line(&format!("oberon_trap({}, \"Kein CASE-Label für den Wert %lld\", {}, 0);", at, value));
             }
-            Statement::For { variable, start, end, step, body } => {
-                let var_name = self.mangle_name(variable);
-                let start_str = self.expression_to_c(start);
-                let end_str = self.expression_to_c(end);
+        }
+    }
+
+    // Datei, Zeile und Prozedur für oberon_trap und die Prüffunktionen;
+    // die Datei nur mit #line-Direktiven, dann ist __FILE__ die Quelldatei
+    fn at(&self, line: usize, procedure: &str) -> String {
+        let file = if self.line_directives.is_some() { "__FILE__" } else { "NULL" };
+        format!("{}, {}, \"{}\"", file, line, procedure)
+    }
 
-                let step_str = if let Some(step_expr) = step {
-                    self.expression_to_c(step_expr)
+    // Lvalue von Slot `index` der Funktion auf Tiefe `depth`
+    fn slot_lvalue(&self, depth: usize, index: usize) -> String {
+        let owner = ancestor(&self.function, depth);
+        let name = match self.slots.get(&owner).and_then(|slots| slots.get(index)) {
+            Some(slot) => self.slot_name(slot, index),
+            None => format!("tmp{}", index),
+        };
+        if depth < self.depth {
+            format!("{}->{}", self.frame(depth), name)
+        } else if self.framed.contains(&owner) {
+            format!("frame.{}", name)
+        } else {
+            name
+        }
+    }
+
+    // Zeiger auf den Rahmen der Prozedur auf Tiefe `depth` (eigene oder umgebende)
+    fn frame(&self, depth: usize) -> String {
+        if depth == self.depth {
+            return "&frame".to_string();
+        }
+        let mut frame = "link".to_string();
+        for _ in depth + 1..self.depth {
+            frame.push_str("->link");
+        }
+        frame
+    }
+
+    // ========================================================================
+    // Befehle
+    // ========================================================================
+
+    fn generate_instruction(&mut self, function: &Function, instruction: &Instruction) {
+        if instruction.span.line > 0 {
+            self.span = Some(instruction.span);
+        } else {
+            self.locate(instruction.line, instruction.line);
+        }
+        let at = self.at(instruction.line, instruction.origin.as_deref().unwrap_or(&self.function));
+        let dest = instruction.dest.map(|dest| dest.to_string()).unwrap_or_default();
+
+        let expression = match &instruction.kind {
+            InstKind::Const(constant) => constant_to_c(constant),
+            InstKind::String(text) => format!("(void*){}", c_string_literal(text)),
+            InstKind::Global(name) => format!("(void*)&{}", self.global_name(name)),
+            InstKind::Slot { depth, index } => format!("(void*)&{}", self.slot_lvalue(*depth, *index)),
+            InstKind::Procedure(name) => format!("(void*){}", self.procedure_name(name, 1)),
+            InstKind::Field { base, record, field } => {
+                let name = self.types.fields(*record).get(*field).map(|field| mangle(&field.name)).unwrap_or_default();
+                format!("(char*){} + offsetof({}, {})", base, self.record_name(*record), name)
+            }
+            InstKind::Element { base, array, index } => {
+                let element = match self.types.kind(*array) {
+                    TypeKind::Array { element, .. } => *element,
+                    _ => *array,
+                };
+                format!("(char*){} + {} * sizeof({})", base, index, self.type_to_c(element))
+            }
+            InstKind::Load { ty, address } => format!("*({}*){}", memory_type(*ty), address),
+            InstKind::Store { ty, address, value } => {
+                self.emit_line(&format!("*({}*){} = {};", memory_type(*ty), address, value));
+                return;
+            }
+            InstKind::Copy { ty, dest, source } => {
+                // Records behalten ihren Deskriptor
+                let line = if matches!(self.types.kind(*ty), TypeKind::Record { .. }) {
+                    format!(
+                        "memcpy((char*){} + sizeof(void*), (char*){} + sizeof(void*), sizeof({}) - sizeof(void*));",
+                        dest,
+                        source,
+                        self.record_name(*ty)
+                    )
                 } else {
-                    "1".to_string()
+                    format!("memcpy({}, {}, sizeof({}));", dest, source, self.type_to_c(*ty))
                 };
-
+                self.emit_line(&line);
+                return;
+            }
+            InstKind::CopyString { dest, source, length } => {
+                self.emit_line(&format!("memcpy({}, {}, {});", dest, source, length));
+                return;
+            }
+            InstKind::InitDescriptors { ty, address } => {
+                self.init_descriptors(&address.to_string(), *ty);
+                return;
+            }
+            InstKind::New { record } => {
+                self.emit_line(&format!("{} = oberon_new(sizeof({}), {});", dest, self.record_name(*record), at));
+                self.init_descriptors(&dest, *record);
+                return;
+            }
+            InstKind::Unary { op, operand } => unary_to_c(*op, *operand, &at),
+            InstKind::Binary { op, left, right } => binary_to_c(*op, *left, *right, &at),
+            InstKind::Compare { op, left, right } => format!("{} {} {}", left, op.symbol(), right),
+            InstKind::CompareStrings { op, left, left_length, right, right_length } => format!(
+                "oberon_compare({}, {}, {}, {}) {} 0",
+                left,
+                left_length,
+                right,
+                right_length,
+                op.symbol()
+            ),
+            InstKind::TypeTest { address, record } => {
+                format!("oberon_is(*(const oberon_desc**){}, &{}_desc)", address, self.record_name(*record))
+            }
+            InstKind::Call { callee, arguments } => {
+                let mut values: Vec<String> = arguments.iter().map(Value::to_string).collect();
+                let call = match callee {
+                    Callee::Direct { name, depth } => {
+                        if *depth > 1 {
+                            values.insert(0, self.frame(depth - 1));
+                        }
+                        format!("{}({})", self.procedure_name(name, *depth), values.join(", "))
+                    }
+                    Callee::Indirect(target) => {
+                        self.emit_line(&format!(
+                            "if (!{}) oberon_trap({}, \"Aufruf einer Prozedurvariablen mit Wert NIL\", 0, 0);",
+                            target, at
+                        ));
+                        let result = instruction.dest.map(|dest| value_type(function.value_type(dest))).unwrap_or("void");
+                        let params: Vec<&str> =
+                            arguments.iter().map(|&argument| value_type(function.value_type(argument))).collect();
+                        let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+                        format!("(({} (*)({})){})({})", result, params, target, values.join(", "))
+                    }
+                };
+                if instruction.dest.is_none() {
+                    self.emit_line(&format!("{};", call));
+                    return;
+                }
+                call
+            }
+            InstKind::Output { procedure, arguments } => {
+                let argument = |index: usize| arguments.get(index).map(Value::to_string).unwrap_or_default();
+                let line = match procedure {
+                    LibraryProcedure::OutOpen => return,
+                    LibraryProcedure::OutLn => "putchar('\\n');".to_string(),
+                    LibraryProcedure::OutChar => format!("putchar({});", argument(0)),
+                    LibraryProcedure::OutString => {
+                        format!("printf(\"%.*s\", (int){}, (const char*){});", argument(1), argument(0))
+                    }
+                    LibraryProcedure::OutInt => {
+                        format!("printf(\"%*lld\", (int){}, (long long){});", argument(1), argument(0))
+                    }
+                    LibraryProcedure::OutReal => format!("printf(\"%*.6f\", (int){}, {});", argument(1), argument(0)),
+                };
+                self.emit_line(&line);
+                return;
+            }
+            InstKind::CheckIndex { index, length } => {
                 self.emit_line(&format!(
-                    "for ({} = {}; {} <= {}; {} += {}) {{",
-                    var_name, start_str, var_name, end_str, var_name, step_str
+                    "if ((uint64_t){} >= {}) oberon_trap({}, \"Index %lld außerhalb von 0..%lld\", {}, {});",
+                    index,
+                    length,
+                    at,
+                    index,
+                    *length as i64 - 1
                 ));
+                return;
+            }
+            InstKind::CheckNil(value) => {
+                self.emit_line(&format!("if (!{}) oberon_trap({}, \"Dereferenzierung von NIL\", 0, 0);", value, at));
+                return;
+            }
+            InstKind::Assert(value) => {
+                self.emit_line(&format!("if (!{}) oberon_trap({}, \"ASSERT fehlgeschlagen\", 0, 0);", value, at));
+                return;
+            }
+        };
+        self.emit_line(&format!("{} = {};", dest, expression));
+    }
+
+    // Setzt die Deskriptoren aller Records in der Variablen an `address`
+    fn init_descriptors(&mut self, address: &str, ty: TypeId) {
+        match self.types.kind(ty).clone() {
+            TypeKind::Record { .. } => {
+                let name = self.record_name(ty);
+                self.emit_line(&format!("(({}*){})->tag = &{}_desc;", name, address, name));
+                let fields: Vec<(String, TypeId)> =
+                    self.types.fields(ty).into_iter().map(|field| (field.name.clone(), field.ty)).collect();
+                for (field, field_type) in fields {
+                    if contains_record(&self.types, field_type) {
+                        let inner = format!("&(({}*){})->{}", name, address, mangle(&field));
+                        self.init_descriptors(&inner, field_type);
+                    }
+                }
+            }
+            TypeKind::Array { length, element } if contains_record(&self.types, element) => {
+                let counter = format!("i{}", self.loop_counter);
+                self.loop_counter += 1;
+                self.emit_line(&format!("for (int64_t {0} = 0; {0} < {1}; {0}++) {{", counter, length));
                 self.indent_level += 1;
-                self.generate_statement_sequence(body);
+                let inner = format!("((char*){} + {} * sizeof({}))", address, counter, self.type_to_c(element));
+                self.init_descriptors(&inner, element);
                 self.indent_level -= 1;
                 self.emit_line("}");
             }
+            _ => {}
         }
     }
 
     // ========================================================================
-    // Ausdrücke
+    // Ausdrücke (REPL)
     // ========================================================================
 
-    fn expression_to_c(&self, expr: &Expression) -> String {
-        match expr {
-            Expression::IntegerLiteral(val) => format!("{}LL", val),
-            Expression::RealLiteral(val) => format!("{}", val),
-            Expression::StringLiteral(val) => format!("\"{}\"", val),
-            Expression::BooleanLiteral(val) => format!("{}", val),
-            Expression::Nil => "NULL".to_string(),
-            Expression::Set(_) => "0".to_string(),
-            Expression::Designator(designator) => self.designator_to_c(designator),
-            Expression::FunctionCall { designator, arguments } => {
-                let func_name = self.designator_to_c(designator);
-                let args: Vec<String> = arguments.iter()
-                    .map(|arg| self.expression_to_c(arg))
-                    .collect();
-                format!("{}({})", func_name, args.join(", "))
-            }
-            Expression::Unary { op, expr } => {
-                let expr_str = self.expression_to_c(expr);
+    fn expression_to_c(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(value) => self.const_to_c(value),
+            ExprKind::Designator(designator) => self.designator_to_c(designator),
+            ExprKind::Call { procedure, arguments } => self.call_to_c(procedure, arguments),
+            ExprKind::BuiltinCall { builtin, arguments } => self.builtin_call_to_c(*builtin, arguments),
+            ExprKind::Unary { op, operand } => {
+                let operand_str = self.expression_to_c(operand);
                 match op {
-                    UnaryOp::Plus => format!("+{}", expr_str),
-                    UnaryOp::Minus => format!("-{}", expr_str),
-                    UnaryOp::Not => format!("!{}", expr_str),
+                    ast::UnaryOp::Plus => format!("(+{})", operand_str),
+                    ast::UnaryOp::Minus => format!("(-{})", operand_str),
+                    ast::UnaryOp::Not => format!("(!{})", operand_str),
                 }
             }
-            Expression::Binary { left, op, right } => {
+            ExprKind::Binary { left, op, right } => {
                 let left_str = self.expression_to_c(left);
                 let right_str = self.expression_to_c(right);
                 let op_str = match op {
-                    BinaryOp::Add => "+",
-                    BinaryOp::Sub => "-",
-                    BinaryOp::Mul => "*",
-                    BinaryOp::Div => "/",
-                    BinaryOp::IntDiv => "/",
-                    BinaryOp::Mod => "%",
-                    BinaryOp::And => "&&",
-                    BinaryOp::Or => "||",
-                    BinaryOp::Equal => "==",
-                    BinaryOp::NotEqual => "!=",
-                    BinaryOp::Less => "<",
-                    BinaryOp::LessEqual => "<=",
-                    BinaryOp::Greater => ">",
-                    BinaryOp::GreaterEqual => ">=",
-                    BinaryOp::In => "&",
-                    BinaryOp::Is => "==",
+                    ast::BinaryOp::Add => "+",
+                    ast::BinaryOp::Sub => "-",
+                    ast::BinaryOp::Mul => "*",
+                    ast::BinaryOp::Div => "/",
+                    ast::BinaryOp::IntDiv => "/",
+                    ast::BinaryOp::Mod => "%",
+                    ast::BinaryOp::And => "&&",
+                    ast::BinaryOp::Or => "||",
+                    ast::BinaryOp::Equal => "==",
+                    ast::BinaryOp::NotEqual => "!=",
+                    ast::BinaryOp::Less => "<",
+                    ast::BinaryOp::LessEqual => "<=",
+                    ast::BinaryOp::Greater => ">",
+                    ast::BinaryOp::GreaterEqual => ">=",
+                    ast::BinaryOp::In => {
+                        return format!("((({} >> {}) & 1u) != 0)", right_str, left_str);
+                    }
+                    ast::BinaryOp::Is => "==",
                 };
                 format!("({} {} {})", left_str, op_str, right_str)
             }
+            ExprKind::Set(elements) => {
+                let mut result = String::from("(0u");
+                for (start, end) in elements {
+                    let start_str = self.expression_to_c(start);
+                    match end {
+                        // Bereich lo..hi: Bits lo bis hi (Überlauf von 2u << 31 ist gewollt)
+                        Some(end) => write!(
+                            result,
+                            " | ((2u << ({})) - (1u << ({})))",
+                            self.expression_to_c(end),
+                            start_str
+                        )
+                        .unwrap(),
+                        None => write!(result, " | (1u << ({}))", start_str).unwrap(),
+                    }
+                }
+                result.push(')');
+                result
+            }
+        }
+    }
+
+    fn call_to_c(&self, procedure: &Designator, arguments: &[Expr]) -> String {
+        let params = match self.types.kind(procedure.ty) {
+            TypeKind::Procedure { params, .. } => params.as_slice(),
+            _ => &[],
+        };
+
+        let args: Vec<String> = arguments
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| match params.get(i) {
+                // VAR-Parameter werden als Zeiger übergeben
+                Some(param) if param.is_var => format!("&{}", self.expression_to_c(arg)),
+                Some(param) => self.converted_to_c(arg, param.param_type),
+                None => self.expression_to_c(arg),
+            })
+            .collect();
+        format!("{}({})", self.designator_to_c(procedure), args.join(", "))
+    }
+
+    // Wert einer Typerweiterung als Wert des Basistyps: Records werden auf den
+    // Basisteil projiziert (gleiches Layout am Anfang)
+    fn converted_to_c(&self, expr: &Expr, target_type: TypeId) -> String {
+        let value = self.expression_to_c(expr);
+        if expr.ty == target_type {
+            return value;
+        }
+
+        match (self.types.kind(expr.ty), self.types.kind(target_type)) {
+            (TypeKind::Record { .. }, TypeKind::Record { .. }) => {
+                format!("(*({}*)&{})", self.record_name(target_type), value)
+            }
+            _ => value,
+        }
+    }
+
+    fn symbol_to_c(&self, symbol: &SymbolRef) -> String {
+        if symbol.is_var_param() {
+            format!("(*{})", mangle(&symbol.name))
+        } else {
+            mangle(&symbol.name)
         }
     }
 
     fn designator_to_c(&self, designator: &Designator) -> String {
-        let mut result = self.mangle_name(&designator.base.name);
+        let mut result = self.symbol_to_c(&designator.symbol);
 
         for selector in &designator.selectors {
             match selector {
-                Selector::Field(name) => {
+                Selector::Field { name, .. } => {
                     result.push('.');
-                    result.push_str(&self.mangle_name(name));
+                    result.push_str(&mangle(name));
                 }
-                Selector::Index(indices) => {
-                    for index in indices {
-                        result.push('[');
-                        result.push_str(&self.expression_to_c(index));
-                        result.push(']');
-                    }
+                Selector::Index { index, .. } => {
+                    result.push('[');
+                    result.push_str(&self.expression_to_c(index));
+                    result.push(']');
                 }
-                Selector::Dereference => {
+                Selector::Dereference { .. } => {
                     result = format!("(*{})", result);
                 }
-                Selector::TypeGuard(_) => {}
+                Selector::TypeGuard { .. } => {}
             }
         }
 
         result
     }
 
+    fn const_to_c(&self, value: &ConstValue) -> String {
+        match value {
+            ConstValue::Integer(val) => int_literal(*val),
+            ConstValue::Real(val) => format!("{:?}", val),
+            ConstValue::Boolean(val) => format!("{}", val),
+            ConstValue::Char(ch) => c_char_literal(*ch),
+            ConstValue::Set(bits) => format!("0x{:X}u", bits),
+            ConstValue::String(val) => c_string_literal(val.as_bytes()),
+            ConstValue::Nil => "NULL".to_string(),
+        }
+    }
+
+    fn builtin_call_to_c(&self, builtin: Builtin, arguments: &[Expr]) -> String {
+        let args: Vec<String> = arguments.iter().map(|arg| self.expression_to_c(arg)).collect();
+        let arg = |i: usize| format!("({})", args.get(i).map(String::as_str).unwrap_or_default());
+
+        match builtin {
+            Builtin::Abs => format!("({0} < 0 ? -{0} : {0})", arg(0)),
+            Builtin::Odd => format!("(({} & 1) != 0)", arg(0)),
+            Builtin::Len => format!("((int64_t)(sizeof({0}) / sizeof(({0})[0])))", arg(0)),
+            Builtin::Lsl => format!("((int64_t)((uint64_t){} << {}))", arg(0), arg(1)),
+            Builtin::Asr => format!("({} >> {})", arg(0), arg(1)),
+            Builtin::Ror => format!(
+                "((int64_t)(((uint64_t){0} >> {1}) | ((uint64_t){0} << ((64 - {1}) & 63))))",
+                arg(0),
+                arg(1)
+            ),
+            Builtin::Floor => format!("((int64_t)floor({}))", arg(0)),
+            Builtin::Flt => format!("((double){})", arg(0)),
+            Builtin::Ord => match arguments.first().map(|first| first.ty) {
+                Some(TypeId::CHAR) => format!("((int64_t)(unsigned char){})", arg(0)),
+                _ => format!("((int64_t){})", arg(0)),
+            },
+            Builtin::Chr => format!("((char){})", arg(0)),
+            // Eigentliche Prozeduren kommen in Ausdrücken nicht vor
+            _ => format!("{}({})", builtin.name(), args.join(", ")),
+        }
+    }
+
     // ========================================================================
     // Hilfsfunktionen
     // ========================================================================
 
-    fn mangle_name(&self, name: &str) -> String {
-        format!("oberon_{}", name)
-    }
-
     fn emit_line(&mut self, line: &str) {
+        self.emit_directive();
         for _ in 0..self.indent_level {
             write!(self.output, "    ").unwrap();
         }
         writeln!(self.output, "{}", line).unwrap();
+        self.lines += 1;
+
+        let Some(oberon) = self.span else { return };
+        if let Some(last) = self.mappings.last_mut() {
+            if last.oberon == oberon && last.function == self.function && last.c.1 + 1 == self.lines {
+                last.c.1 = self.lines;
+                return;
+            }
+        }
+        let function = self.function.clone();
+        self.mappings.push(Mapping { c: (self.lines, self.lines), oberon, function });
+    }
+
+    // #line vor der nächsten Zeile, wenn der C-Compiler ihr sonst eine
+    // andere Zeile zuordnen würde; ohne Oberon-Herkunft zurück zur C-Datei
+    fn emit_directive(&mut self) {
+        let Some((source, target)) = &self.line_directives else { return };
+        let directive = match self.span {
+            Some(span) if self.directive == Some(span.line) => None,
+            Some(span) => Some(format!("#line {} \"{}\"", span.line, source)),
+            None if self.directive.is_some() => Some(format!("#line {} \"{}\"", self.lines + 2, target)),
+            None => None,
+        };
+        if let Some(directive) = directive {
+            writeln!(self.output, "{}", directive).unwrap();
+            self.lines += 1;
+        }
+        self.directive = self.span.map(|span| span.line + 1);
     }
 
-    #[allow(dead_code)]
-    fn new_label(&mut self) -> String {
-        let label = format!("L{}", self.label_counter);
-        self.label_counter += 1;
-        label
+    // Oberon-Zeilen der folgenden Ausgabe ohne Spalten; Zeile 0 ist unbekannt
+    fn locate(&mut self, start: usize, end: usize) {
+        self.span = (start > 0).then_some(Span { line: start, column: 0, end_line: end.max(start), end_column: 0 });
     }
 
-    #[allow(dead_code)]
-    fn new_temp(&mut self) -> String {
-        let temp = format!("_t{}", self.temp_counter);
-        self.temp_counter += 1;
-        temp
+    fn unlocate(&mut self) {
+        self.span = None;
+    }
+
+    fn clear_output(&mut self) {
+        self.output.clear();
+        self.lines = 0;
+        self.span = None;
+        self.directive = None;
+        self.mappings.clear();
     }
 }
 
@@ -585,4 +1148,738 @@ impl Default for CGenerator {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+// ============================================================================
+// Getrennte Übersetzung
+// ============================================================================
+
+// Laufzeit für getrennt übersetzte Module, von jedem Header eingebunden
+pub const RUNTIME_HEADER: &str = "oberon.h";
+
+// Datei des Hauptprogramms aus main_stub
+pub const MAIN_FILE: &str = "oberon_main.c";
+
+pub fn runtime_header() -> String {
+    let mut header = String::from("#ifndef OBERON_H\n#define OBERON_H\n\n");
+    for include in INCLUDES {
+        header.push_str(include);
+        header.push('\n');
+    }
+    header.push_str(RUNTIME);
+    header.push_str("\n#endif\n");
+    header
+}
+
+// Ruft die Initialisierungen der Module in der Reihenfolge `modules` auf;
+// jedes Modul muss nach allen stehen, die es importiert
+pub fn main_stub(modules: &[String]) -> String {
+    let mut code = String::new();
+    for module in modules {
+        writeln!(code, "void {}__init(void);", module).unwrap();
+    }
+    code.push_str("\nint main(void) {\n");
+    for module in modules {
+        writeln!(code, "    {}__init();", module).unwrap();
+    }
+    code.push_str("    return 0;\n}\n");
+    code
+}
+
+// Makefile für das Programm `program` aus den Modulen in
+// Initialisierungsreihenfolge, je mit den Modulen, die es importiert. Jede
+// C-Datei hängt von den Headern aller mittelbar importierten Module ab.
+pub fn makefile(program: &str, modules: &[(String, Vec<String>)]) -> String {
+    let mut headers: HashMap<&str, Vec<String>> = HashMap::new();
+    for (module, imports) in modules {
+        let mut needed = vec![format!("{}.h", module)];
+        for import in imports {
+            for header in headers.get(import.as_str()).into_iter().flatten() {
+                if !needed.contains(header) {
+                    needed.push(header.clone());
+                }
+            }
+        }
+        headers.insert(module, needed);
+    }
+
+    let objects: Vec<String> = modules
+        .iter()
+        .map(|(module, _)| format!("{}.o", module))
+        .chain(std::iter::once(MAIN_FILE.replace(".c", ".o")))
+        .collect();
+    let mut make = String::new();
+    writeln!(make, "# Erzeugt von together c --modules").unwrap();
+    writeln!(make, "CFLAGS ?= -std=gnu99 -O2").unwrap();
+    writeln!(make, "OBJECTS = {}", objects.join(" ")).unwrap();
+    writeln!(make).unwrap();
+    writeln!(make, "{}: $(OBJECTS)", program).unwrap();
+    writeln!(make, "\t$(CC) $(LDFLAGS) -o $@ $(OBJECTS) $(LDLIBS)").unwrap();
+    writeln!(make).unwrap();
+    for (module, _) in modules {
+        writeln!(make, "{}.o: {}.c {} {}", module, module, headers[module.as_str()].join(" "), RUNTIME_HEADER).unwrap();
+    }
+    writeln!(make, "{}: {}", objects[objects.len() - 1], MAIN_FILE).unwrap();
+    writeln!(make).unwrap();
+    writeln!(make, "clean:").unwrap();
+    writeln!(make, "\trm -f {} $(OBJECTS)", program).unwrap();
+    writeln!(make).unwrap();
+    writeln!(make, ".PHONY: clean").unwrap();
+    make
+}
+
+// Importierte Module mit eigenem Quelltext, ohne die Bibliotheksmodule
+pub fn imported_modules(module: &Module) -> Vec<String> {
+    let mut modules: Vec<String> = Vec::new();
+    for import in &module.imports {
+        if !LibraryProcedure::is_library_module(&import.module_name) && !modules.contains(&import.module_name) {
+            modules.push(import.module_name.clone());
+        }
+    }
+    modules
+}
+
+fn mangle(name: &str) -> String {
+    format!("oberon_{}", name)
+}
+
+fn qualify(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}_{}", prefix, name)
+    }
+}
+
+// Wie Function::ancestor, für den Namen der Funktion in Arbeit
+fn ancestor(name: &str, depth: usize) -> String {
+    name.split('.').take(depth).collect::<Vec<_>>().join(".")
+}
+
+fn contains_record(types: &TypeArena, ty: TypeId) -> bool {
+    match types.kind(ty) {
+        TypeKind::Record { .. } => true,
+        TypeKind::Array { element, .. } => contains_record(types, *element),
+        _ => false,
+    }
+}
+
+// Alle Typen, die in Deklarationen vorkommen
+fn collect_types(decls: &Declarations, types: &mut Vec<TypeId>) {
+    types.extend(decls.types.iter().map(|type_decl| type_decl.type_def));
+    types.extend(decls.variables.iter().map(|variable| variable.var_type));
+    for procedure in &decls.procedures {
+        types.extend(procedure.params.iter().map(|param| param.param_type));
+        types.extend(procedure.return_type);
+        collect_types(&procedure.declarations, types);
+    }
+}
+
+// C-Typ eines SSA-Werts
+fn value_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Int => "int64_t",
+        Type::Real => "double",
+        Type::Bool => "bool",
+        Type::Char => "uint8_t",
+        Type::Set => "uint32_t",
+        Type::Ptr => "void*",
+    }
+}
+
+// C-Typ im Speicher; CHAR belegt wie in den Records ein char
+fn memory_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Char => "char",
+        _ => value_type(ty),
+    }
+}
+
+fn unary_to_c(op: UnaryOp, operand: Value, at: &str) -> String {
+    match op {
+        UnaryOp::Neg => format!("oberon_neg({}, {}, \"Überlauf bei '-'\")", operand, at),
+        UnaryOp::Abs => format!("{0} < 0 ? oberon_neg({0}, {1}, \"Überlauf bei 'ABS'\") : {0}", operand, at),
+        UnaryOp::FNeg => format!("-{}", operand),
+        UnaryOp::FAbs => format!("__builtin_fabs({})", operand),
+        UnaryOp::Not => format!("!{}", operand),
+        UnaryOp::Complement => format!("~{}", operand),
+        UnaryOp::Odd => format!("({} & 1) != 0", operand),
+        UnaryOp::IntToReal => format!("(double){}", operand),
+        UnaryOp::Floor => format!("oberon_floor({}, {})", operand, at),
+        UnaryOp::Ord => format!("(int64_t){}", operand),
+        UnaryOp::Chr => format!("oberon_chr({}, {})", operand, at),
+    }
+}
+
+fn binary_to_c(op: BinaryOp, left: Value, right: Value, at: &str) -> String {
+    let overflow = format!("\"Überlauf bei '{}'\"", op.symbol());
+    match op {
+        BinaryOp::Add | BinaryOp::Inc | BinaryOp::For => format!("oberon_add({}, {}, {}, {})", left, right, at, overflow),
+        BinaryOp::Sub | BinaryOp::Dec => format!("oberon_sub({}, {}, {}, {})", left, right, at, overflow),
+        BinaryOp::Mul => format!("oberon_mul({}, {}, {}, {})", left, right, at, overflow),
+        BinaryOp::Div => format!("oberon_div({}, {}, {})", left, right, at),
+        BinaryOp::Mod => format!("oberon_mod({}, {}, {})", left, right, at),
+        BinaryOp::FAdd => format!("{} + {}", left, right),
+        BinaryOp::FSub => format!("{} - {}", left, right),
+        BinaryOp::FMul => format!("{} * {}", left, right),
+        BinaryOp::FDiv => format!("{} / {}", left, right),
+        BinaryOp::Union => format!("{} | {}", left, right),
+        BinaryOp::Difference => format!("{} & ~{}", left, right),
+        BinaryOp::Intersection => format!("{} & {}", left, right),
+        BinaryOp::SymmetricDifference => format!("{} ^ {}", left, right),
+        BinaryOp::In => format!("(uint64_t){0} < 32 && (({1} >> {0}) & 1)", left, right),
+        BinaryOp::Range => format!("oberon_range({}, {}, {})", left, right, at),
+        BinaryOp::Lsl => format!("oberon_lsl({}, {}, {})", left, right, at),
+        BinaryOp::Asr => format!("oberon_asr({}, {}, {})", left, right, at),
+        BinaryOp::Ror => format!("oberon_ror({}, {}, {})", left, right, at),
+    }
+}
+
+fn constant_to_c(constant: &Constant) -> String {
+    match constant {
+        Constant::Int(value) => int_literal(*value),
+        Constant::Real(value) => format!("{:?}", value),
+        Constant::Bool(value) => value.to_string(),
+        Constant::Char(value) => value.to_string(),
+        Constant::Set(bits) => format!("0x{:X}u", bits),
+        Constant::Nil => "NULL".to_string(),
+    }
+}
+
+fn int_literal(value: i64) -> String {
+    // i64::MIN ist als Literal in C nicht darstellbar
+    if value == i64::MIN {
+        "(-9223372036854775807LL - 1)".to_string()
+    } else {
+        format!("{}LL", value)
+    }
+}
+
+fn c_char_literal(ch: char) -> String {
+    match ch {
+        '\'' => "'\\''".to_string(),
+        '\\' => "'\\\\'".to_string(),
+        ' '..='~' => format!("'{}'", ch),
+        _ => format!("((char)0x{:X})", ch as u32),
+    }
+}
+
+fn c_string_literal(bytes: &[u8]) -> String {
+    let mut result = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' => result.push_str("\\\""),
+            b'\\' => result.push_str("\\\\"),
+            b' '..=b'~' => result.push(byte as char),
+            // Oktal-Escapes, damit nachfolgende Hex-Ziffern nicht mitgelesen werden
+            _ => write!(result, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    result.push('"');
+    result
+}
+
+const INCLUDES: [&str; 6] = [
+    "#include <stdio.h>",
+    "#include <stdlib.h>",
+    "#include <stdbool.h>",
+    "#include <stddef.h>",
+    "#include <string.h>",
+    "#include <stdint.h>",
+];
+
+// Laufzeit, in jede Ausgabe kopiert bzw. bei getrennter Übersetzung in
+// oberon.h. oberon_trap meldet einen Laufzeitfehler im Format des
+// Interpreters auf stderr, mit Datei davor, wenn eine bekannt ist, und
+// beendet das Programm mit Exit-Code 1; die Prüffunktionen erhalten Datei,
+// Zeile und Prozedur dafür. inline vermeidet
+// Warnungen für nicht benutzte Hilfsfunktionen.
+const RUNTIME: &str = r#"
+typedef struct oberon_desc {
+    const struct oberon_desc *base;
+} oberon_desc;
+
+__attribute__((noreturn)) static inline void oberon_trap(const char *file, int64_t line, const char *procedure, const char *format, int64_t a, int64_t b) {
+    fflush(stdout);
+    if (file) fprintf(stderr, "%s:", file);
+    fprintf(stderr, "%lld: Laufzeitfehler in %s: ", (long long)line, procedure);
+    fprintf(stderr, format, (long long)a, (long long)b);
+    fputc('\n', stderr);
+    exit(1);
+}
+
+static inline int64_t oberon_add(int64_t a, int64_t b, const char *file, int64_t line, const char *procedure, const char *message) {
+    int64_t r;
+    if (__builtin_add_overflow(a, b, &r)) oberon_trap(file, line, procedure, message, 0, 0);
+    return r;
+}
+
+static inline int64_t oberon_sub(int64_t a, int64_t b, const char *file, int64_t line, const char *procedure, const char *message) {
+    int64_t r;
+    if (__builtin_sub_overflow(a, b, &r)) oberon_trap(file, line, procedure, message, 0, 0);
+    return r;
+}
+
+static inline int64_t oberon_mul(int64_t a, int64_t b, const char *file, int64_t line, const char *procedure, const char *message) {
+    int64_t r;
+    if (__builtin_mul_overflow(a, b, &r)) oberon_trap(file, line, procedure, message, 0, 0);
+    return r;
+}
+
+static inline int64_t oberon_neg(int64_t a, const char *file, int64_t line, const char *procedure, const char *message) {
+    if (a == INT64_MIN) oberon_trap(file, line, procedure, message, 0, 0);
+    return -a;
+}
+
+/* DIV und MOD runden zur negativen Unendlichkeit */
+static inline int64_t oberon_div(int64_t a, int64_t b, const char *file, int64_t line, const char *procedure) {
+    if (b == 0) oberon_trap(file, line, procedure, "Division durch 0", 0, 0);
+    if (b == -1) return oberon_neg(a, file, line, procedure, "Überlauf bei 'DIV'");
+    int64_t q = a / b;
+    if (a % b != 0 && (a % b < 0) != (b < 0)) q--;
+    return q;
+}
+
+static inline int64_t oberon_mod(int64_t a, int64_t b, const char *file, int64_t line, const char *procedure) {
+    if (b == 0) oberon_trap(file, line, procedure, "Division durch 0", 0, 0);
+    if (b == -1) return 0;
+    int64_t r = a % b;
+    if (r != 0 && (r < 0) != (b < 0)) r += b;
+    return r;
+}
+
+static inline uint32_t oberon_range(int64_t low, int64_t high, const char *file, int64_t line, const char *procedure) {
+    if ((uint64_t)low > 31) oberon_trap(file, line, procedure, "Mengenelement %lld liegt außerhalb von 0..31", low, 0);
+    if ((uint64_t)high > 31) oberon_trap(file, line, procedure, "Mengenelement %lld liegt außerhalb von 0..31", high, 0);
+    if (low > high) return 0;
+    return (uint32_t)((2ull << high) - (1ull << low));
+}
+
+static inline int64_t oberon_lsl(int64_t a, int64_t n, const char *file, int64_t line, const char *procedure) {
+    if ((uint64_t)n > 63) oberon_trap(file, line, procedure, "Schiebeweite %lld für LSL außerhalb von 0..63", n, 0);
+    int64_t r = (int64_t)((uint64_t)a << n);
+    if (r >> n != a) oberon_trap(file, line, procedure, "Überlauf bei 'LSL'", 0, 0);
+    return r;
+}
+
+static inline int64_t oberon_asr(int64_t a, int64_t n, const char *file, int64_t line, const char *procedure) {
+    if ((uint64_t)n > 63) oberon_trap(file, line, procedure, "Schiebeweite %lld für ASR außerhalb von 0..63", n, 0);
+    return a >> n;
+}
+
+static inline int64_t oberon_ror(int64_t a, int64_t n, const char *file, int64_t line, const char *procedure) {
+    if ((uint64_t)n > 63) oberon_trap(file, line, procedure, "Schiebeweite %lld für ROR außerhalb von 0..63", n, 0);
+    return (int64_t)(((uint64_t)a >> n) | ((uint64_t)a << ((64 - n) & 63)));
+}
+
+static inline int64_t oberon_floor(double x, const char *file, int64_t line, const char *procedure) {
+    if (!(x > -9223372036854775808.0 && x < 9223372036854775808.0)) oberon_trap(file, line, procedure, "Überlauf bei 'FLOOR'", 0, 0);
+    int64_t i = (int64_t)x;
+    return (double)i > x ? i - 1 : i;
+}
+
+static inline uint8_t oberon_chr(int64_t x, const char *file, int64_t line, const char *procedure) {
+    if ((uint64_t)x > 255) oberon_trap(file, line, procedure, "CHR(%lld) ist kein gültiges Zeichen", x, 0);
+    return (uint8_t)x;
+}
+
+static inline void *oberon_new(size_t size, const char *file, int64_t line, const char *procedure) {
+    void *object = calloc(1, size);
+    if (!object) oberon_trap(file, line, procedure, "Kein Speicher mehr für NEW", 0, 0);
+    return object;
+}
+
+/* Ist d der Deskriptor von t oder einer Erweiterung davon? */
+static inline bool oberon_is(const oberon_desc *d, const oberon_desc *t) {
+    for (; d; d = d->base) {
+        if (d == t) return true;
+    }
+    return false;
+}
+
+/* Vergleicht zwei Zeichenketten bis zum ersten 0X, liefert -1, 0 oder 1 */
+static inline int oberon_compare(const void *a, int64_t la, const void *b, int64_t lb) {
+    for (int64_t i = 0;; i++) {
+        unsigned char x = i < la ? ((const unsigned char *)a)[i] : 0;
+        unsigned char y = i < lb ? ((const unsigned char *)b)[i] : 0;
+        if (x != y) return x < y ? -1 : 1;
+        if (x == 0) return 0;
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::TypeChecker;
+    use crate::testing::{checked, parse};
+    use std::fs;
+    use std::process::Command;
+
+    fn compile(source: &str) -> String {
+        compile_with(source, OptLevel::O0)
+    }
+
+    fn compile_with(source: &str, level: OptLevel) -> String {
+        CGenerator::with_optimization(level).generate(&checked(source)).unwrap()
+    }
+
+    // Übersetzt mit cc und liefert stdout bzw. bei Exit-Code # 0 stderr;
+    // None, wenn kein C-Compiler zur Verfügung steht. Optimiert (-O2) muss
+    // das Programm dasselbe ausgeben.
+    fn run(name: &str, source: &str) -> Option<Result<String, String>> {
+        let output = run_with(name, source, OptLevel::O0)?;
+        let optimized = run_with(name, source, OptLevel::O2)?;
+        assert_eq!(output, optimized, "-O2 weicht ab");
+        Some(output)
+    }
+
+    fn run_with(name: &str, source: &str, level: OptLevel) -> Option<Result<String, String>> {
+        if Command::new("cc").arg("--version").output().is_err() {
+            return None;
+        }
+        let dir = std::env::temp_dir().join(format!("together-c-{}-{}-{:?}", std::process::id(), name, level));
+        fs::create_dir_all(&dir).unwrap();
+        let code = dir.join("T.c");
+        let program = dir.join("T");
+        fs::write(&code, compile_with(source, level)).unwrap();
+        let compiled = Command::new("cc").arg("-std=gnu99").arg(&code).arg("-o").arg(&program).output().unwrap();
+        assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+        let output = Command::new(&program).output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        Some(match output.status.code() {
+            Some(0) => Ok(String::from_utf8(output.stdout).unwrap()),
+            _ => Err(String::from_utf8(output.stderr).unwrap()),
+        })
+    }
+
+    #[test]
+    fn test_code() {
+        let code = compile("MODULE T;
+            TYPE R* = RECORD x: INTEGER END;
+            VAR r*: R; g: INTEGER;
+            PROCEDURE P*(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END P;
+            PROCEDURE Q(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END Q;
+            BEGIN g := P(1) + Q(2)
+            END T.");
+
+        assert!(code.contains("typedef struct oberon_R {\n    const oberon_desc *tag;\n    int64_t oberon_x;\n} oberon_R;\n"), "{}", code);
+        assert!(code.contains("static const oberon_desc oberon_R_desc = { NULL };\n"), "{}", code);
+        assert!(code.contains("\noberon_R oberon_r;\nstatic int64_t oberon_g;\n"), "{}", code);
+        assert!(code.contains("\nint64_t oberon_P(int64_t v0);\nstatic int64_t oberon_Q(int64_t v0);\n"), "{}", code);
+        assert!(code.contains("->tag = &oberon_R_desc;\n"), "{}", code);
+        assert!(code.contains(", 10, \"T\", \"Überlauf bei '+'\")"), "{}", code);
+    }
+
+    #[test]
+    fn test_execution() {
+        let Some(output) = run("execution", "MODULE T;
+            IMPORT Out;
+            TYPE
+              Node = POINTER TO NodeDesc;
+              NodeDesc = RECORD key: INTEGER; next: Node END;
+              Point = RECORD x, y: INTEGER END;
+              Point3 = RECORD (Point) z: INTEGER END;
+              PPoint = POINTER TO Point;
+              PPoint3 = POINTER TO Point3;
+              Vec = ARRAY 3 OF INTEGER;
+            VAR list: Node; a, b: Vec; p: Point; q: Point3; i: INTEGER; s: SET; name: ARRAY 4 OF CHAR;
+              pp: PPoint; pq: PPoint3; f: PROCEDURE (n: INTEGER): INTEGER;
+
+            PROCEDURE Insert(VAR list: Node; key: INTEGER);
+              VAR n: Node;
+            BEGIN NEW(n); n^.key := key; n^.next := list; list := n
+            END Insert;
+
+            PROCEDURE Fib(n: INTEGER): INTEGER;
+              VAR r: INTEGER;
+            BEGIN IF n < 2 THEN r := n ELSE r := Fib(n - 1) + Fib(n - 2) END
+              RETURN r
+            END Fib;
+
+            PROCEDURE Sum(n: INTEGER): INTEGER;
+              VAR total: INTEGER;
+              PROCEDURE Add(k: INTEGER);
+              BEGIN total := total + k
+              END Add;
+            BEGIN total := 0; WHILE n > 0 DO Add(n); DEC(n) END
+              RETURN total
+            END Sum;
+
+            PROCEDURE Clear(v: Vec);
+              VAR i: INTEGER;
+            BEGIN FOR i := 0 TO 2 DO v[i] := 0 END
+            END Clear;
+
+            BEGIN
+              FOR i := 1 TO 3 DO Insert(list, i * i) END;
+              WHILE list # NIL DO Out.Int(list^.key, 3); list := list^.next END; Out.Ln;
+              f := Fib; Out.Int(f(15), 0); Out.Char(\" \"); Out.Int(Sum(10), 0); Out.Ln;
+              FOR i := 0 TO 2 DO a[i] := i + 1 END;
+              b := a; b[0] := 100; Clear(a);
+              Out.Int(a[0], 0); Out.Char(\" \"); Out.Int(b[0], 0); Out.Ln;
+              q.x := 1; q.y := 2; q.z := 3; p := q; Out.Int(p.x + p.y, 0); Out.Ln;
+              s := {1, 3..5}; INCL(s, 7); EXCL(s, 4);
+              FOR i := 31 TO 0 BY -1 DO IF i IN s THEN Out.Int(i, 2) END END; Out.Ln;
+              Out.Int((-7) DIV 2, 0); Out.Int((-7) MOD 2, 3); Out.Real(2.5 * 2.0, 9); Out.Ln;
+              name[0] := \"H\"; name[1] := \"i\"; name[2] := 0X; name[3] := \"!\"; Out.String(name); Out.Ln;
+              CASE ORD(name[0]) OF 0..64: Out.String(\"klein\") | 65..90: Out.String(\"gross\") END; Out.Ln;
+              NEW(pq); pp := pq; IF (pp IS PPoint3) & ~(p IS Point3) THEN Out.String(\"IS\") END; Out.Ln
+            END T.") else {
+            return;
+        };
+
+        assert_eq!(output.unwrap(), "  9  4  1\n610 55\n1 100\n3\n 7 5 3 1\n-4  1 5.000000\nHi\ngross\nIS\n");
+    }
+
+    // Geschachtelte Prozeduren über mehrere Ebenen, REAL- und VAR-Parameter
+    #[test]
+    fn test_frames() {
+        let Some(output) = run("frames", "MODULE T;
+            IMPORT Out;
+            VAR n: INTEGER; r: REAL;
+
+            PROCEDURE Half(x: REAL; VAR n: INTEGER): REAL;
+            BEGIN INC(n) RETURN x / 2.0
+            END Half;
+
+            PROCEDURE Outer(k: INTEGER): INTEGER;
+              VAR acc: INTEGER;
+              PROCEDURE Mid(j: INTEGER);
+                PROCEDURE Inner;
+                BEGIN acc := acc + j * k
+                END Inner;
+              BEGIN Inner; IF j > 0 THEN Mid(j - 1) END
+              END Mid;
+            BEGIN acc := 0; Mid(3) RETURN acc
+            END Outer;
+
+            BEGIN
+              n := 0; r := Half(Half(9.0, n), n); Out.Real(r, 0); Out.Int(n, 2); Out.Ln;
+              Out.Int(Outer(5), 0); Out.Int(FLOOR(-2.5), 3); Out.Ln
+            END T.") else {
+            return;
+        };
+
+        assert_eq!(output.unwrap(), "2.250000 2\n30 -3\n");
+    }
+
+    #[test]
+    fn test_traps() {
+        let trap = |name: &str, source: &str| run(name, source).map(|output| output.unwrap_err());
+        let Some(message) = trap("index", "MODULE T;
+            VAR a: ARRAY 3 OF INTEGER; i: INTEGER;
+            PROCEDURE Set(k: INTEGER);
+            BEGIN
+              a[k] := k
+            END Set;
+            BEGIN
+              FOR i := 0 TO 3 DO Set(i) END
+            END T.") else {
+            return;
+        };
+        assert_eq!(message, "5: Laufzeitfehler in Set: Index 3 außerhalb von 0..2\n");
+
+        assert_eq!(
+            trap("nil", "MODULE T; TYPE P = POINTER TO RECORD x: INTEGER END; VAR p: P; BEGIN p^.x := 1 END T.").unwrap(),
+            "1: Laufzeitfehler in T: Dereferenzierung von NIL\n"
+        );
+        assert_eq!(
+            trap("div", "MODULE T; VAR i: INTEGER; BEGIN i := 7 MOD i END T.").unwrap(),
+            "1: Laufzeitfehler in T: Division durch 0\n"
+        );
+        assert_eq!(
+            trap("case", "MODULE T; VAR i: INTEGER; BEGIN i := 4; CASE i OF 1..3: i := 0 END END T.").unwrap(),
+            "1: Laufzeitfehler in T: Kein CASE-Label für den Wert 4\n"
+        );
+        assert_eq!(
+            trap("overflow", "MODULE T; VAR i: INTEGER; BEGIN i := 1; i := LSL(i, 62) * 4 END T.").unwrap(),
+            "1: Laufzeitfehler in T: Überlauf bei '*'\n"
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let source = "MODULE T;
+            TYPE R = RECORD x: INTEGER END;
+            VAR a: ARRAY 3 OF INTEGER; r: R;
+            PROCEDURE Get(i: INTEGER): INTEGER;
+            BEGIN
+              RETURN a[i]
+            END Get;
+            BEGIN
+              r.x := 3;
+              a[0] := Get(r.x)
+            END T.";
+        let mut generator = CGenerator::new();
+        generator.set_line_directives("T.Mod", "T.c");
+        let code = generator.generate(&checked(source)).unwrap();
+
+        // Zeilen, die der C-Präprozessor jeder Zeile zuordnet
+        let mut attributed = Vec::new();
+        let (mut file, mut next) = ("T.c".to_string(), 1);
+        for line in code.lines() {
+            match line.strip_prefix("#line ") {
+                Some(directive) => {
+                    let (number, name) = directive.split_once(' ').unwrap();
+                    next = number.parse().unwrap();
+                    file = name.trim_matches('"').to_string();
+                    attributed.push(None);
+                }
+                None => {
+                    attributed.push(Some((file.clone(), next)));
+                    next += 1;
+                }
+            }
+        }
+        let mut mapped = HashSet::new();
+        for mapping in generator.mappings() {
+            for c in mapping.c.0..=mapping.c.1 {
+                assert_eq!(attributed[c - 1], Some(("T.Mod".to_string(), mapping.oberon.line)), "{}", code);
+                mapped.insert(c);
+            }
+        }
+        for (index, target) in attributed.iter().enumerate() {
+            if let (false, Some(target)) = (mapped.contains(&(index + 1)), target) {
+                assert_eq!(*target, ("T.c".to_string(), index + 1), "{}", code);
+            }
+        }
+
+        // Anweisungen, Deklarationen und Prozeduren
+        let find = |text: &str| {
+            let line = code.lines().position(|line| line.contains(text)).unwrap() + 1;
+            generator.mappings().iter().find(|mapping| mapping.c.0 <= line && line <= mapping.c.1).cloned()
+        };
+        let oberon = |text: &str| find(text).map(|mapping| (mapping.oberon, mapping.function));
+        let lines = |line: usize, end_line: usize| Span { line, column: 0, end_line, end_column: 0 };
+        let columns = |line: usize, column: usize, end_column: usize| Span { line, column, end_line: line, end_column };
+        assert_eq!(oberon("typedef struct oberon_R"), Some((lines(2, 2), String::new())));
+        assert_eq!(oberon("static int64_t oberon_a[3];"), Some((lines(3, 3), String::new())));
+        assert_eq!(oberon("static int64_t oberon_Get(int64_t v0) {"), Some((lines(4, 7), "Get".to_string())));
+        assert_eq!(oberon("oberon_trap(__FILE__, 6, \"Get\""), Some((lines(6, 6), "Get".to_string())));
+        assert_eq!(oberon("= 3LL;"), Some((columns(9, 15, 23), "T".to_string())));
+        assert_eq!(oberon("= oberon_Get("), Some((columns(10, 15, 31), "T".to_string())));
+        assert_eq!(oberon("int64_t oberon_x;"), None);
+
+        let map = Json::parse(&generator.source_map("T.Mod", "T.c")).unwrap();
+        assert_eq!(map.get("source").and_then(Json::as_str), Some("T.Mod"));
+        let mappings = map.get("mappings").and_then(Json::as_array).unwrap();
+        assert_eq!(mappings.len(), generator.mappings().len());
+        assert_eq!(map.get("version").and_then(Json::as_usize), Some(2));
+        let position = |mapping: &Json, index: usize| -> Vec<Option<usize>> {
+            let oberon = mapping.get("oberon").and_then(Json::as_array).unwrap();
+            oberon[index].as_array().unwrap().iter().map(Json::as_usize).collect()
+        };
+        assert_eq!(position(&mappings[0], 0), [Some(2), Some(0)]);
+        let assignment = mappings.iter().find(|mapping| position(mapping, 0) == [Some(10), Some(15)]).unwrap();
+        assert_eq!(position(assignment, 1), [Some(10), Some(31)]);
+
+        // Der C-Compiler akzeptiert die Direktiven
+        if Command::new("cc").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("together-c-{}-lines", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("T.c"), &code).unwrap();
+        let compiled = Command::new("cc").current_dir(&dir).args(["-std=gnu99", "T.c", "-o", "T"]).output().unwrap();
+        assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+
+        // Laufzeitfehler nennen die Quelldatei aus der #line-Direktive
+        let output = Command::new(dir.join("T")).output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "T.Mod:6: Laufzeitfehler in Get: Index 3 außerhalb von 0..2\n");
+    }
+
+    #[test]
+    fn test_modules() {
+        let check = |source: &str, interfaces: &[&Module]| {
+            let mut checker = TypeChecker::new();
+            for interface in interfaces {
+                checker.add_interface(interface);
+            }
+            checker.check_module(&parse(source)).unwrap()
+        };
+        let stack = check(
+            "MODULE Stack;
+            TYPE Item* = RECORD value*: INTEGER END;
+            VAR size*: INTEGER; items: ARRAY 8 OF Item;
+            PROCEDURE Push*(value: INTEGER);
+            BEGIN items[size].value := value; INC(size)
+            END Push;
+            PROCEDURE Top*(): INTEGER;
+            BEGIN RETURN items[size - 1].value
+            END Top;
+            BEGIN size := 0
+            END Stack.",
+            &[],
+        );
+        let main = check(
+            "MODULE Main;
+            IMPORT Out, S := Stack;
+            TYPE Named = RECORD (S.Item) name: CHAR END;
+            VAR n: Named;
+            BEGIN S.Push(3); S.Push(4); n.value := S.Top() + S.size; Out.Int(n.value, 0); Out.Ln
+            END Main.",
+            &[&stack],
+        );
+
+        let mut generator = CGenerator::new();
+        let stack_unit = generator.generate_unit(&stack).unwrap();
+        let main_unit = generator.generate_unit(&main).unwrap();
+        let header = &stack_unit.header;
+        assert!(header.starts_with("#ifndef Stack_H\n#define Stack_H\n\n#include \"oberon.h\"\n"), "{}", header);
+        assert!(header.contains("extern const oberon_desc Stack_Item_desc;\n"), "{}", header);
+        assert!(header.contains("extern int64_t Stack_size;\n"), "{}", header);
+        assert!(header.contains("void Stack_Push(int64_t v0);\n"), "{}", header);
+        assert!(header.contains("void Stack__init(void);\n"), "{}", header);
+        assert!(!header.contains("Stack_items"), "{}", header);
+        assert!(stack_unit.source.contains("static Stack_Item Stack_items[8];\n"), "{}", stack_unit.source);
+        assert!(main_unit.header.contains("#include \"Stack.h\"\n"), "{}", main_unit.header);
+        assert!(!main_unit.header.contains("typedef struct Stack_Item"), "{}", main_unit.header);
+        let source = &main_unit.source;
+        assert!(source.contains("const oberon_desc Main_Named_desc = { &Stack_Item_desc };\n"), "{}", source);
+        assert!(source.contains("Stack_Push(v"), "{}", source);
+        assert!(source.contains("(void*)&Stack_size;"), "{}", source);
+        assert!(!source.contains("int main"), "{}", source);
+
+        let modules = vec![("Stack".to_string(), Vec::new()), ("Main".to_string(), vec!["Stack".to_string()])];
+        let make = makefile("Main", &modules);
+        assert!(make.contains("Main: $(OBJECTS)\n"), "{}", make);
+        assert!(make.contains("Main.o: Main.c Main.h Stack.h oberon.h\n"), "{}", make);
+        let names: Vec<String> = modules.iter().map(|(name, _)| name.clone()).collect();
+        let stub = main_stub(&names);
+        assert!(stub.contains("    Stack__init();\n    Main__init();\n"), "{}", stub);
+
+        // Getrennt übersetzt und gebunden
+        if Command::new("cc").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("together-c-{}-modules", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = [
+            (RUNTIME_HEADER, runtime_header()),
+            ("Stack.h", stack_unit.header),
+            ("Stack.c", stack_unit.source),
+            ("Main.h", main_unit.header),
+            ("Main.c", main_unit.source),
+            (MAIN_FILE, stub),
+        ];
+        for (name, content) in &files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        for source in ["Stack.c", "Main.c", MAIN_FILE] {
+            let compiled = Command::new("cc").current_dir(&dir).args(["-std=gnu99", "-c", source]).output().unwrap();
+            assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+        }
+        let objects = ["Stack.o", "Main.o", "oberon_main.o"];
+        let linked = Command::new("cc").current_dir(&dir).args(objects).args(["-o", "Main"]).output().unwrap();
+        assert!(linked.status.success(), "{}", String::from_utf8_lossy(&linked.stderr));
+        let output = Command::new(dir.join("Main")).output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "6\n");
+    }
+}
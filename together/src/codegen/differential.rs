@@ -0,0 +1,470 @@
+use super::wasm_generator::{TrapCode, BODY_EXPORT, RUNTIME_MODULE};
+use super::{BytecodeGenerator, CGenerator, RiscvGenerator, WasmGenerator, X86Generator};
+use crate::interpreter::{Interpreter, Trap};
+use crate::ir::opt::OptLevel;
+use crate::riscv::simulator::Simulator;
+use crate::semantic::hir::Module;
+use crate::testing::checked;
+use crate::vm::Vm;
+use crate::wasm::machine::{Machine, Val};
+use crate::wasm;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Differenztest der Backends gegen den Interpreter: jedes Programm des
+// Korpus muss in jedem Backend dieselbe Ausgabe bzw. denselben
+// Laufzeitfehler liefern, die IR-Backends ohne und mit Optimierung (-O2).
+// C, x86-64, RISC-V, LLVM und die Bytecode-VM melden Traps im Format des
+// Interpreters; WebAssembly kennt nur Trap-Codes ohne Operanden, dort wird
+// die Zeile verglichen. Der Korpus enthält auch die früheren
+// Ausführungstests der einzelnen Backends; C und x86-64 laufen nur, wenn
+// ein C-Compiler zur Verfügung steht.
+
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Backend {
+    Wasm(OptLevel),
+    X86(OptLevel),
+    Riscv(OptLevel),
+    C(OptLevel),
+    Vm,
+    #[cfg(feature = "llvm")]
+    Llvm,
+}
+
+const BACKENDS: &[Backend] = &[
+    Backend::Wasm(OptLevel::O0),
+    Backend::Wasm(OptLevel::O2),
+    Backend::X86(OptLevel::O0),
+    Backend::X86(OptLevel::O2),
+    Backend::Riscv(OptLevel::O0),
+    Backend::Riscv(OptLevel::O2),
+    Backend::C(OptLevel::O0),
+    Backend::C(OptLevel::O2),
+    Backend::Vm,
+    #[cfg(feature = "llvm")]
+    Backend::Llvm,
+];
+
+impl Backend {
+    // Ausgabe des Programms bzw. die Meldung des Laufzeitfehlers; None, wenn
+    // das Zielsystem nicht zur Verfügung steht
+    pub(super) fn run(self, source: &str) -> Option<Result<String, String>> {
+        let module = checked(source);
+        match self {
+            Backend::Wasm(level) => Some(run_wasm(&module, level)),
+            Backend::X86(level) => run_x86(&module, level),
+            Backend::Riscv(level) => Some(run_riscv(&module, level)),
+            Backend::C(level) => run_c(&module, level),
+            Backend::Vm => Some(run_vm(&module)),
+            #[cfg(feature = "llvm")]
+            Backend::Llvm => run_llvm(&module),
+        }
+    }
+}
+
+fn interpret(source: &str) -> Result<String, Trap> {
+    let mut out = Vec::new();
+    Interpreter::new(&checked(source), &mut out).run()?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+// Host mit Out und Oberon.trap; eine Trap endet als Fehler "Zeile: Meldung".
+// Das Modul geht einmal durch das Binärformat.
+fn run_wasm(module: &Module, level: OptLevel) -> Result<String, String> {
+    let module = WasmGenerator::with_optimization(level).generate(module).unwrap();
+    let module = wasm::decode(&wasm::encode(&module)).unwrap();
+    let mut machine = Machine::new(&module)?;
+    let mut out = String::new();
+    let mut host = |module: &str, name: &str, args: &[Val], memory: &mut [u8]| -> Result<Option<Val>, String> {
+        match (module, name, args) {
+            (RUNTIME_MODULE, "trap", [Val::I32(code), Val::I32(line)]) => {
+                let trap = TrapCode::from_code(*code).ok_or("unbekannte Trap")?;
+                return Err(format!("{}: {}", line, trap.message()));
+            }
+            ("Out", "Char", [Val::I32(ch)]) => out.push(*ch as u8 as char),
+            ("Out", "String", [Val::I32(address), Val::I32(length)]) => {
+                let bytes = &memory[*address as usize..][..*length as usize];
+                out.extend(bytes.iter().take_while(|&&byte| byte != 0).map(|&byte| byte as char));
+            }
+            ("Out", "Int", [Val::I64(value), Val::I64(width)]) => {
+                out.push_str(&format!("{:>width$}", value, width = *width as usize))
+            }
+            ("Out", "Real", [Val::F64(value), Val::I64(width)]) => {
+                out.push_str(&format!("{:>width$.6}", value, width = *width as usize))
+            }
+            ("Out", "Ln", []) => out.push('\n'),
+            ("Out", "Open", []) => {}
+            _ => return Err(format!("unbekannter Import {}.{}", module, name)),
+        }
+        Ok(None)
+    };
+    machine.run_start(&mut host)?;
+    machine.invoke(BODY_EXPORT, &[], &mut host)?;
+    Ok(out)
+}
+
+// Bindet mit cc; nur auf x86-64-Linux mit C-Compiler
+fn run_x86(module: &Module, level: OptLevel) -> Option<Result<String, String>> {
+    if !cfg!(all(target_arch = "x86_64", target_os = "linux")) || !has_cc() {
+        return None;
+    }
+    let dir = scratch("x86");
+    let assembly = dir.join("T.s");
+    let program = dir.join("T");
+    fs::write(&assembly, X86Generator::with_optimization(level).generate(module).unwrap()).unwrap();
+    let linked = Command::new("cc").arg(&assembly).arg("-o").arg(&program).output().unwrap();
+    assert!(linked.status.success(), "{}", String::from_utf8_lossy(&linked.stderr));
+    let output = execute(&program);
+    fs::remove_dir_all(&dir).unwrap();
+    Some(output)
+}
+
+fn run_c(module: &Module, level: OptLevel) -> Option<Result<String, String>> {
+    if !has_cc() {
+        return None;
+    }
+    let dir = scratch("c");
+    let code = dir.join("T.c");
+    let program = dir.join("T");
+    fs::write(&code, CGenerator::with_optimization(level).generate(module).unwrap()).unwrap();
+    let compiled = Command::new("cc").arg("-std=gnu99").arg(&code).arg("-o").arg(&program).output().unwrap();
+    assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+    let output = execute(&program);
+    fs::remove_dir_all(&dir).unwrap();
+    Some(output)
+}
+// Optimiert (-O2) für den Host, gebunden mit cc
+#[cfg(feature = "llvm")]
+fn run_llvm(module: &Module) -> Option<Result<String, String>> {
+    use super::llvm_generator::{self, Emit, LLVMGenerator};
+    use inkwell::context::Context;
+
+    if !has_cc() {
+        return None;
+    }
+    let context = Context::create();
+    let mut generator = LLVMGenerator::new(&context, "T");
+    generator.generate(module).unwrap();
+    let machine = llvm_generator::target_machine(None, 2).unwrap();
+    generator.optimize(&machine, 2).unwrap();
+    let dir = scratch("llvm");
+    let object = dir.join("T.o");
+    let program = dir.join("T");
+    generator.emit(&machine, Emit::Object, &object).unwrap();
+    llvm_generator::link(&[&object], &program, None).unwrap();
+    let output = execute(&program);
+    fs::remove_dir_all(&dir).unwrap();
+    Some(output)
+}
+
+// Eigenes Verzeichnis je Lauf, die Tests laufen parallel
+fn scratch(backend: &str) -> PathBuf {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("together-{}-{}-{}", backend, std::process::id(), run));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn execute(program: &Path) -> Result<String, String> {
+    let output = Command::new(program).output().unwrap();
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8(output.stdout).unwrap()),
+        _ => Err(String::from_utf8(output.stderr).unwrap().trim_end().to_string()),
+    }
+}
+
+fn run_riscv(module: &Module, level: OptLevel) -> Result<String, String> {
+    let program = RiscvGenerator::with_optimization(level).generate(module).unwrap();
+    let mut simulator = Simulator::new(&program).unwrap();
+    match simulator.run("main").unwrap() {
+        0 => Ok(String::from_utf8(simulator.stdout).unwrap()),
+        _ => Err(String::from_utf8(simulator.stderr).unwrap().trim_end().to_string()),
+    }
+}
+
+// Der Bytecode geht einmal durch das Binärformat
+fn run_vm(module: &Module) -> Result<String, String> {
+    let bytecode = BytecodeGenerator::new().generate(module).unwrap();
+    let bytecode = crate::bytecode::decode(&crate::bytecode::encode(&bytecode)).unwrap();
+    let mut out = Vec::new();
+    Vm::new(&mut out).load(bytecode).map_err(|trap| trap.to_string())?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn has_cc() -> bool {
+    Command::new("cc").arg("--version").output().is_ok()
+}
+
+// Programme mit Ausgabe
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "Listen, Records, Arrays und Typtests",
+        "MODULE T;
+        IMPORT Out;
+        TYPE
+          Node = POINTER TO NodeDesc;
+          NodeDesc = RECORD key: INTEGER; next: Node END;
+          Point = RECORD x, y: INTEGER END;
+          Point3 = RECORD (Point) z: INTEGER END;
+          PPoint = POINTER TO Point;
+          PPoint3 = POINTER TO Point3;
+          Vec = ARRAY 3 OF INTEGER;
+        VAR list: Node; a, b: Vec; p: Point; q: Point3; i: INTEGER; name: ARRAY 4 OF CHAR;
+          pp: PPoint; pq: PPoint3; f: PROCEDURE (n: INTEGER): INTEGER;
+
+        PROCEDURE Insert(VAR list: Node; key: INTEGER);
+          VAR n: Node;
+        BEGIN NEW(n); n^.key := key; n^.next := list; list := n
+        END Insert;
+
+        PROCEDURE Fib(n: INTEGER): INTEGER;
+          VAR r: INTEGER;
+        BEGIN IF n < 2 THEN r := n ELSE r := Fib(n - 1) + Fib(n - 2) END
+          RETURN r
+        END Fib;
+
+        PROCEDURE Sum(n: INTEGER): INTEGER;
+          VAR total: INTEGER;
+          PROCEDURE Add(k: INTEGER);
+          BEGIN total := total + k
+          END Add;
+        BEGIN total := 0; WHILE n > 0 DO Add(n); DEC(n) END
+          RETURN total
+        END Sum;
+
+        PROCEDURE Clear(v: Vec);
+          VAR i: INTEGER;
+        BEGIN FOR i := 0 TO 2 DO v[i] := 0 END
+        END Clear;
+
+        BEGIN
+          FOR i := 1 TO 3 DO Insert(list, i * i) END;
+          WHILE list # NIL DO Out.Int(list^.key, 3); list := list^.next END; Out.Ln;
+          f := Fib; Out.Int(f(15), 0); Out.Char(\" \"); Out.Int(Sum(10), 0); Out.Ln;
+          FOR i := 0 TO 2 DO a[i] := i + 1 END;
+          b := a; b[0] := 100; Clear(a);
+          Out.Int(a[0], 0); Out.Char(\" \"); Out.Int(b[0], 0); Out.Ln;
+          q.x := 1; q.y := 2; q.z := 3; p := q; Out.Int(p.x + p.y, 0); Out.Ln;
+          Out.Real(2.5 * 2.0, 9); Out.Ln;
+          name[0] := \"H\"; name[1] := \"i\"; name[2] := 0X; name[3] := \"!\"; Out.String(name); Out.Ln;
+          NEW(pq); pp := pq; IF (pp IS PPoint3) & ~(p IS Point3) THEN Out.String(\"IS\") END; Out.Ln
+        END T.",
+    ),
+    (
+        "Mehr Zwischenwerte als Register, REAL-Parameter und Schachtelung",
+        "MODULE T;
+        IMPORT Out;
+        VAR a, b, c, d, e, f, g, n: INTEGER; r: REAL;
+
+        PROCEDURE Deep(a, b, c, d, e, f: INTEGER): INTEGER;
+        BEGIN RETURN a * (b + (c * (d + (e * (f + (a * (b + (c * (d + 1)))))))))
+        END Deep;
+
+        PROCEDURE Half(x: REAL; VAR n: INTEGER): REAL;
+        BEGIN INC(n) RETURN x / 2.0
+        END Half;
+
+        PROCEDURE Outer(k: INTEGER): INTEGER;
+          VAR acc: INTEGER;
+          PROCEDURE Mid(j: INTEGER);
+            PROCEDURE Inner;
+            BEGIN acc := acc + j * k
+            END Inner;
+          BEGIN Inner; IF j > 0 THEN Mid(j - 1) END
+          END Mid;
+        BEGIN acc := 0; Mid(3) RETURN acc
+        END Outer;
+
+        PROCEDURE Big(k: INTEGER): INTEGER;
+          VAR buffer: ARRAY 1000 OF INTEGER; last: INTEGER;
+        BEGIN buffer[999] := k; last := buffer[999] + 1
+          RETURN last
+        END Big;
+
+        BEGIN
+          a := 1; b := 2; c := 3; d := 4; e := 5; f := 6; g := 7;
+          Out.Int(a + (b * (c + (d * (e + (f * (g + (a * (b + (c * (d + (e * f))))))))))), 0); Out.Ln;
+          Out.Int(a + Deep(b, c, d, e, f, g) * (b + Deep(a, a, a, a, a, a)), 0); Out.Ln;
+          n := 0; r := Half(Half(9.0, n), n); Out.Real(r, 0); Out.Int(n, 2); Out.Ln;
+          Out.Int(Outer(5), 0); Out.Int(FLOOR(-2.5), 3); Out.Int(Big(41), 3); Out.Ln
+        END T.",
+    ),
+    (
+        "DIV und MOD mit negativen Operanden",
+        "MODULE T;
+        IMPORT Out;
+        VAR x, y, i, j: INTEGER; v: ARRAY 4 OF INTEGER;
+        BEGIN
+          v[0] := 7; v[1] := -7; v[2] := 3; v[3] := -3;
+          FOR i := 0 TO 1 DO
+            FOR j := 2 TO 3 DO
+              x := v[i]; y := v[j];
+              Out.Int(x DIV y, 3); Out.Int(x MOD y, 3); Out.Int((x DIV y) * y + x MOD y, 3); Out.Ln
+            END
+          END;
+          x := -6; Out.Int(x DIV 3, 3); Out.Int(x MOD 3, 3); Out.Int(x DIV (-3), 3); Out.Int(x MOD (-3), 3); Out.Ln;
+          Out.Int((-7) DIV 2, 0); Out.Int((-7) MOD 2, 3); Out.Int(ASR(-7, 1), 3); Out.Ln
+        END T.",
+    ),
+    (
+        "CASE mit Bereichen, mehreren Labels und ELSE",
+        "MODULE T;
+        IMPORT Out;
+        VAR i: INTEGER; ch: CHAR; name: ARRAY 4 OF CHAR;
+        BEGIN
+          FOR i := -2 TO 12 DO
+            CASE i OF
+              -2, -1: Out.Char(\"n\")
+            | 0: Out.Char(\"0\")
+            | 1..3, 5: Out.Char(\"a\")
+            | 4, 6..9: Out.Char(\"b\")
+            ELSE Out.Char(\"-\")
+            END
+          END;
+          Out.Ln;
+          name := \"Hi7\";
+          FOR i := 0 TO 2 DO
+            ch := name[i];
+            CASE ch OF
+              \"A\"..\"Z\": Out.String(\"gross\")
+            | \"a\"..\"z\": Out.String(\"klein\")
+            | \"0\"..\"9\": Out.String(\"Ziffer\")
+            END;
+            Out.Char(\" \")
+          END;
+          Out.Ln;
+          CASE ORD(name[0]) OF 0..64: Out.String(\"klein\") | 65..90: Out.String(\"gross\") END; Out.Ln
+        END T.",
+    ),
+    (
+        "SET-Operationen",
+        "MODULE T;
+        IMPORT Out;
+        VAR s, t, u: SET; i, lo, hi: INTEGER;
+
+        PROCEDURE Show(s: SET);
+          VAR i: INTEGER;
+        BEGIN Out.Char(\"{\");
+          FOR i := 0 TO 31 DO IF i IN s THEN Out.Int(i, 3) END END;
+          Out.Char(\"}\"); Out.Ln
+        END Show;
+
+        BEGIN
+          s := {1, 3..5}; INCL(s, 7); EXCL(s, 4); Show(s);
+          lo := 28; hi := 31; t := {0, 2, lo..hi}; Show(t);
+          Show(s + t); Show(s - {1, 7}); Show(s * {3, 5, 7, 9}); Show(s / {1, 2});
+          u := {}; FOR i := 0 TO 31 BY 5 DO INCL(u, i) END; Show(u);
+          IF (s = {1, 3, 5, 7}) & (s # t) & ~(2 IN s) & (31 IN t) THEN Out.String(\"ok\") END; Out.Ln;
+          hi := 2; Show({5..hi}); Show({31})
+        END T.",
+    ),
+    (
+        "Argumente auf dem Stapel",
+        "MODULE T;
+        IMPORT Out;
+        VAR r: REAL; f: PROCEDURE (a, b, c, d, e, f, g, h, i: INTEGER): INTEGER;
+
+        PROCEDURE Sum(a, b, c, d, e, f, g, h, i: INTEGER): INTEGER;
+        BEGIN RETURN a + 2 * b + 3 * c + 4 * d + 5 * e + 6 * f + 7 * g + 8 * h + 9 * i
+        END Sum;
+
+        PROCEDURE Mixed(a: INTEGER; x1, x2, x3, x4, x5, x6, x7, x8, x9, x10: REAL;
+          b, c, d, e, f, g, h: INTEGER; VAR out: REAL);
+          VAR k: INTEGER;
+          PROCEDURE Inner(p, q, s, t, u, v, w, x: INTEGER): INTEGER;
+          BEGIN RETURN p + q + s + t + u + v + w * 100 + x * 1000 + k
+          END Inner;
+        BEGIN k := 10000;
+          out := x1 + x3 * x4 - x5 * x6 + x7 * x8 + x9 * 10.0 + x10 * 100.0 + x2;
+          Out.Int(Inner(a, b, c, d, e, f, h, g), 0); Out.Ln
+        END Mixed;
+
+        BEGIN
+          f := Sum;
+          Out.Int(Sum(1, 2, 3, 4, 5, 6, 7, 8, 9), 0); Out.Char(\" \"); Out.Int(f(9, 8, 7, 6, 5, 4, 3, 2, 1), 0); Out.Ln;
+          Mixed(1, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 2, 3, 4, 5, 6, 7, 8, r);
+          Out.Real(r, 0); Out.Ln
+        END T.",
+    ),
+    (
+        "Zeichenketten",
+        "MODULE T;
+        IMPORT Out;
+        TYPE Name = ARRAY 8 OF CHAR;
+        VAR a: Name; b: ARRAY 8 OF CHAR; c: ARRAY 4 OF CHAR;
+
+        PROCEDURE Show(x: BOOLEAN);
+        BEGIN IF x THEN Out.Char(\"1\") ELSE Out.Char(\"0\") END
+        END Show;
+
+        BEGIN
+          a := \"Anna\"; b := a; c := \"Ann\"; b[6] := \"x\";
+          Out.String(b); Out.Ln;
+          Show(a = b); Show(a = \"Anna\"); Show(a # c); Show(c < a); Show(a <= \"Anna\");
+          Show(\"Bob\" > a); Show(c >= \"Anne\"); Show(\"\" < c); Out.Ln;
+          a[2] := 0X; Show(a = \"An\"); Show(a < c); b := a; Show(b = \"An\"); Out.Ln
+        END T.",
+    ),
+];
+
+// Programme, die mit einem Laufzeitfehler enden
+const TRAPS: &[&str] = &[
+    "MODULE T;
+    VAR a: ARRAY 3 OF INTEGER; i: INTEGER;
+    PROCEDURE Set(k: INTEGER);
+    BEGIN
+      a[k] := k
+    END Set;
+    BEGIN
+      FOR i := 0 TO 3 DO Set(i) END
+    END T.",
+    "MODULE T; TYPE P = POINTER TO RECORD x: INTEGER END; VAR p: P; BEGIN p^.x := 1 END T.",
+    "MODULE T; VAR i: INTEGER; BEGIN i := 7 MOD i END T.",
+    "MODULE T; VAR i, j: INTEGER; BEGIN i := -7; j := i DIV j END T.",
+    "MODULE T;
+    VAR i: INTEGER;
+    BEGIN i := 4;
+      CASE i OF 1..3: i := 0 END
+    END T.",
+    "MODULE T; VAR i: INTEGER; BEGIN ASSERT(i > 0) END T.",
+    "MODULE T; VAR f: PROCEDURE; BEGIN f END T.",
+    "MODULE T; VAR i: INTEGER; BEGIN i := 1; i := LSL(i, 62) * 4 END T.",
+    "MODULE T; VAR i: INTEGER; BEGIN i := 9223372036854775807; INC(i) END T.",
+    "MODULE T; VAR i: INTEGER; BEGIN i := -9223372036854775807 - 1; i := i DIV (-1) END T.",
+    "MODULE T; VAR s: SET; i: INTEGER; BEGIN i := 32; INCL(s, i) END T.",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_programs() {
+        for (name, source) in PROGRAMS {
+            let expected = interpret(source).unwrap_or_else(|trap| panic!("{}: {}", name, trap));
+            for &backend in BACKENDS {
+                if let Some(output) = backend.run(source) {
+                    assert_eq!(output.as_deref(), Ok(expected.as_str()), "{} ({:?})", name, backend);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_traps() {
+        for source in TRAPS {
+            let trap = interpret(source).expect_err(source);
+            for &backend in BACKENDS {
+                let Some(output) = backend.run(source) else { continue };
+                let message = output.expect_err(source);
+                match backend {
+                    Backend::Wasm(_) => assert_eq!(message.split_once(": ").unwrap().0, trap.line.to_string(), "{}", source),
+                    _ => assert_eq!(message, trap.to_string(), "{} ({:?})", source, backend),
+                }
+            }
+        }
+    }
+}
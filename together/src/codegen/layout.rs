@@ -0,0 +1,110 @@
+use crate::semantic::{TypeArena, TypeId, TypeKind};
+use std::collections::HashMap;
+
+// Speicherlayout der Backends mit linearem Speicher (WebAssembly, x86-64,
+// RISC-V). Records beginnen mit der Adresse ihres Typdeskriptors, danach
+// folgen die Felder, die des Basistyps zuerst; Erweiterungen haben daher für
+// geerbte Felder dieselben Offsets. Die Backends unterscheiden sich nur in
+// der Breite von Adressen (Zeiger und Prozedurvariablen).
+
+// Typdeskriptor am Anfang jedes Records, auf 8 Bytes aufgefüllt
+pub const RECORD_HEADER: u32 = 8;
+
+pub struct Layout {
+    // Größe und Ausrichtung von Zeigern und Prozedurvariablen
+    address: u32,
+    // Feldoffsets (alle Felder) und Größe der Records
+    records: HashMap<TypeId, (Vec<u32>, u32)>,
+}
+
+impl Layout {
+    pub fn new(address: u32) -> Self {
+        Layout { address, records: HashMap::new() }
+    }
+
+    pub fn size(&mut self, types: &TypeArena, ty: TypeId) -> u32 {
+        match types.kind(ty) {
+            TypeKind::Boolean | TypeKind::Char => 1,
+            TypeKind::Set => 4,
+            TypeKind::Integer | TypeKind::Real => 8,
+            TypeKind::Array { length, element } => *length as u32 * self.size(types, *element),
+            TypeKind::Record { .. } => self.record(types, ty).1,
+            _ => self.address,
+        }
+    }
+
+    pub fn align(&self, types: &TypeArena, ty: TypeId) -> u32 {
+        match types.kind(ty) {
+            TypeKind::Boolean | TypeKind::Char => 1,
+            TypeKind::Set => 4,
+            TypeKind::Integer | TypeKind::Real | TypeKind::Record { .. } => 8,
+            TypeKind::Array { element, .. } => self.align(types, *element),
+            _ => self.address,
+        }
+    }
+
+    // Feldoffsets und Größe eines Records
+    pub fn record(&mut self, types: &TypeArena, record: TypeId) -> (Vec<u32>, u32) {
+        if let Some(layout) = self.records.get(&record) {
+            return layout.clone();
+        }
+        let fields: Vec<TypeId> = types.fields(record).iter().map(|field| field.ty).collect();
+        let mut offsets = Vec::new();
+        let mut offset = RECORD_HEADER;
+        for field in fields {
+            offset = align_up(offset, self.align(types, field));
+            offsets.push(offset);
+            offset += self.size(types, field);
+        }
+        let layout = (offsets, align_up(offset, 8));
+        self.records.insert(record, layout.clone());
+        layout
+    }
+}
+
+pub fn is_record(types: &TypeArena, ty: TypeId) -> bool {
+    matches!(types.kind(ty), TypeKind::Record { .. })
+}
+
+pub fn contains_record(types: &TypeArena, ty: TypeId) -> bool {
+    match types.kind(ty) {
+        TypeKind::Record { .. } => true,
+        TypeKind::Array { element, .. } => contains_record(types, *element),
+        _ => false,
+    }
+}
+
+pub fn align_up(value: u32, align: u32) -> u32 {
+    value.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::types::Field;
+
+    #[test]
+    fn test_record_layout() {
+        let mut types = TypeArena::new();
+        let pointer = types.add(TypeKind::Pointer { target: TypeId::INTEGER });
+        let base = types.add(TypeKind::Record {
+            base: None,
+            fields: vec![
+                Field { name: "flag".to_string(), ty: TypeId::BOOLEAN },
+                Field { name: "next".to_string(), ty: pointer },
+            ],
+        });
+        let extension = types.add(TypeKind::Record {
+            base: Some(base),
+            fields: vec![Field { name: "s".to_string(), ty: TypeId::SET }],
+        });
+
+        // Zeiger mit 8 Bytes wie auf x86-64 und RISC-V, mit 4 wie in WebAssembly
+        let mut native = Layout::new(8);
+        assert_eq!(native.record(&types, base), (vec![8, 16], 24));
+        assert_eq!(native.record(&types, extension), (vec![8, 16, 24], 32));
+        let mut wasm = Layout::new(4);
+        assert_eq!(wasm.record(&types, base), (vec![8, 12], 16));
+        assert_eq!(wasm.record(&types, extension), (vec![8, 12, 16], 24));
+    }
+}
@@ -0,0 +1,316 @@
+use crate::semantic::{TypeArena, TypeId, TypeKind};
+use inkwell::basic_block::BasicBlock;
+use inkwell::context::Context;
+use inkwell::debug_info::{
+    AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DILocation, DIScope, DISubprogram, DIType,
+    DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::{FlagBehavior, Module as LLVMModule};
+use inkwell::values::{FunctionValue, GlobalValue, PointerValue};
+use inkwell::AddressSpace;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// ============================================================================
+// DWARF-Debuginformationen (together llvm -g)
+// ============================================================================
+//
+// Eine Übersetzungseinheit je Modul, ein Unterprogramm je Prozedur (und für
+// den Modulrumpf main). Typen tragen ihre Oberon-Namen: Records werden
+// Structs mit allen Feldern einschließlich der geerbten hinter dem Zeiger
+// auf den Typdeskriptor, geschachtelte Arrays ein Array mit allen
+// Dimensionen. Größen und Ausrichtung entsprechen den LLVM-Typen
+// des Generators auf einem 64-Bit-Ziel.
+
+// DWARF-Codierungen der Grundtypen (DW_ATE_*)
+const DW_ATE_BOOLEAN: u32 = 0x02;
+const DW_ATE_FLOAT: u32 = 0x04;
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_UNSIGNED: u32 = 0x07;
+const DW_ATE_UNSIGNED_CHAR: u32 = 0x08;
+// DW_OP_deref: VAR-Parameter liegen als Adresse im Stack
+const DW_OP_DEREF: i64 = 0x06;
+
+const POINTER_BITS: u64 = 64;
+
+pub(crate) struct DebugInfo<'ctx> {
+    builder: DebugInfoBuilder<'ctx>,
+    compile_unit: DICompileUnit<'ctx>,
+    file: DIFile<'ctx>,
+    // Unterprogramm der Funktion in Arbeit
+    subprogram: Option<DIScope<'ctx>>,
+    types: HashMap<TypeId, DIType<'ctx>>,
+    // Records, deren Beschreibung gerade entsteht (rekursive Zeiger)
+    pending: HashSet<TypeId>,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    pub(crate) fn new(context: &'ctx Context, module: &LLVMModule<'ctx>, source: &Path) -> Self {
+        let version = context.i32_type();
+        module.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version.const_int(3, false));
+        module.add_basic_value_flag("Dwarf Version", FlagBehavior::Warning, version.const_int(4, false));
+
+        let filename = source.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let directory = source
+            .parent()
+            .and_then(|parent| std::fs::canonicalize(if parent.as_os_str().is_empty() { Path::new(".") } else { parent }).ok())
+            .map(|parent| parent.to_string_lossy().into_owned())
+            .unwrap_or_else(|| ".".to_string());
+
+        // DWARF kennt Modula-2, aber kein Oberon; Debugger zeigen damit
+        // zumindest die Wertdarstellung der Pascal-Familie
+        let (builder, compile_unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::Modula2,
+            &filename,
+            &directory,
+            "together",
+            false,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+        let file = compile_unit.get_file();
+        DebugInfo { builder, compile_unit, file, subprogram: None, types: HashMap::new(), pending: HashSet::new() }
+    }
+
+    pub(crate) fn finalize(&self) {
+        self.builder.finalize();
+    }
+
+    // ========================================================================
+    // Gültigkeitsbereiche
+    // ========================================================================
+
+    // Unterprogramm für eine Funktion; Parameter- und Ergebnistypen bilden
+    // den Typ des Unterprogramms
+    pub(crate) fn begin_function(
+        &mut self,
+        types: &TypeArena,
+        function: FunctionValue<'ctx>,
+        name: &str,
+        params: &[TypeId],
+        result: Option<TypeId>,
+        line: usize,
+    ) {
+        let result = result.map(|ty| self.ty(types, ty));
+        let params: Vec<DIType<'ctx>> = params.iter().map(|&ty| self.ty(types, ty)).collect();
+        let subroutine = self.builder.create_subroutine_type(self.file, result, &params, DIFlags::ZERO);
+        let linkage = function.get_name().to_string_lossy().into_owned();
+        let subprogram: DISubprogram<'ctx> = self.builder.create_function(
+            self.compile_unit.as_debug_info_scope(),
+            name,
+            Some(&linkage),
+            self.file,
+            line as u32,
+            subroutine,
+            false,
+            true,
+            line as u32,
+            DIFlags::ZERO,
+            false,
+        );
+        function.set_subprogram(subprogram);
+        self.subprogram = Some(subprogram.as_debug_info_scope());
+    }
+
+    pub(crate) fn end_function(&mut self) {
+        self.subprogram = None;
+    }
+
+    fn scope(&self) -> DIScope<'ctx> {
+        self.subprogram.unwrap_or_else(|| self.compile_unit.as_debug_info_scope())
+    }
+
+    pub(crate) fn location(&self, context: &'ctx Context, line: usize) -> DILocation<'ctx> {
+        self.builder.create_debug_location(context, line as u32, 0, self.scope(), None)
+    }
+
+    // ========================================================================
+    // Variablen
+    // ========================================================================
+
+    pub(crate) fn global(&mut self, context: &'ctx Context, types: &TypeArena, global: GlobalValue<'ctx>, name: &str, ty: TypeId, line: usize) {
+        let ty = self.ty(types, ty);
+        let linkage = global.get_name().to_string_lossy().into_owned();
+        let expression = self.builder.create_global_variable_expression(
+            self.compile_unit.as_debug_info_scope(),
+            name,
+            &linkage,
+            self.file,
+            line as u32,
+            ty,
+            false,
+            None,
+            None,
+            0,
+        );
+        global.set_metadata(expression.as_metadata_value(context), context.get_kind_id("dbg"));
+    }
+
+    // Lokale Variable oder Parameter (`argument` ab 1) im Speicher `storage`;
+    // bei VAR-Parametern enthält der Speicher die Adresse des Werts
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn variable(
+        &mut self,
+        context: &'ctx Context,
+        types: &TypeArena,
+        storage: PointerValue<'ctx>,
+        name: &str,
+        ty: TypeId,
+        argument: Option<u32>,
+        by_reference: bool,
+        line: usize,
+        block: BasicBlock<'ctx>,
+    ) {
+        let ty = self.ty(types, ty);
+        let scope = self.scope();
+        let variable = match argument {
+            Some(number) => self.builder.create_parameter_variable(
+                scope,
+                name,
+                number,
+                self.file,
+                line as u32,
+                ty,
+                true,
+                DIFlags::ZERO,
+            ),
+            None => self.builder.create_auto_variable(scope, name, self.file, line as u32, ty, true, DIFlags::ZERO, 0),
+        };
+        let expression = self.builder.create_expression(if by_reference { vec![DW_OP_DEREF] } else { Vec::new() });
+        let location = self.location(context, line);
+        self.builder.insert_declare_at_end(storage, Some(variable), Some(expression), location, block);
+    }
+
+    // ========================================================================
+    // Typen
+    // ========================================================================
+
+    fn ty(&mut self, types: &TypeArena, id: TypeId) -> DIType<'ctx> {
+        if let Some(&ty) = self.types.get(&id) {
+            return ty;
+        }
+        let name = types.display(id);
+        let (size, align) = layout(types, id);
+        let ty = match types.kind(id) {
+            TypeKind::Integer => self.basic(&name, size, DW_ATE_SIGNED),
+            TypeKind::Real => self.basic(&name, size, DW_ATE_FLOAT),
+            TypeKind::Boolean => self.basic(&name, size, DW_ATE_BOOLEAN),
+            TypeKind::Char => self.basic(&name, size, DW_ATE_UNSIGNED_CHAR),
+            TypeKind::Set => self.basic(&name, size, DW_ATE_UNSIGNED),
+            TypeKind::Array { .. } => {
+                // ARRAY 2, 3 OF T: eine Beschreibung mit beiden Dimensionen
+                let mut dimensions = Vec::new();
+                let mut element = id;
+                while let TypeKind::Array { length, element: inner } = types.kind(element) {
+                    dimensions.push(0..*length as i64);
+                    element = *inner;
+                }
+                let element = self.ty(types, element);
+                self.builder.create_array_type(element, size, align as u32, &dimensions).as_type()
+            }
+            TypeKind::Record { .. } => self.record(types, id, &name, size, align),
+            TypeKind::Pointer { target } => {
+                let target = *target;
+                let pointee = if self.pending.contains(&target) {
+                    // Zeiger auf einen Record, der gerade beschrieben wird:
+                    // Vorwärtsdeklaration, Debugger lösen sie über den Namen auf
+                    let target_name = types.display(target);
+                    self.builder
+                        .create_struct_type(
+                            self.compile_unit.as_debug_info_scope(),
+                            &target_name,
+                            self.file,
+                            0,
+                            0,
+                            0,
+                            DIFlags::FWD_DECL,
+                            None,
+                            &[],
+                            0,
+                            None,
+                            &target_name,
+                        )
+                        .as_type()
+                } else {
+                    self.ty(types, target)
+                };
+                self.builder.create_pointer_type(&name, pointee, size, align as u32, AddressSpace::default()).as_type()
+            }
+            // Prozedurvariablen und Übriges als untypisierte Adresse
+            _ => self.basic(&name, POINTER_BITS, DW_ATE_UNSIGNED),
+        };
+        self.types.insert(id, ty);
+        ty
+    }
+
+    fn basic(&self, name: &str, size: u64, encoding: u32) -> DIType<'ctx> {
+        self.builder
+            .create_basic_type(name, size, encoding, DIFlags::ZERO)
+            .expect("Grundtyp mit Größe > 0")
+            .as_type()
+    }
+
+    // Struct mit allen Feldern in der Reihenfolge des LLVM-Structs; der
+    // Deskriptor davor bleibt unbenannt
+    fn record(&mut self, types: &TypeArena, id: TypeId, name: &str, size: u64, align: u64) -> DIType<'ctx> {
+        self.pending.insert(id);
+        let scope = self.compile_unit.as_debug_info_scope();
+        let mut members = Vec::new();
+        let mut offset = POINTER_BITS;
+        for field in types.fields(id) {
+            let (field_size, field_align) = layout(types, field.ty);
+            offset = offset.next_multiple_of(field_align);
+            let field_type = self.ty(types, field.ty);
+            let member = self.builder.create_member_type(
+                scope,
+                &field.name,
+                self.file,
+                0,
+                field_size,
+                field_align as u32,
+                offset,
+                DIFlags::ZERO,
+                field_type,
+            );
+            members.push(member.as_type());
+            offset += field_size;
+        }
+        self.pending.remove(&id);
+        self.builder
+            .create_struct_type(scope, name, self.file, 0, size, align as u32, DIFlags::ZERO, None, &members, 0, None, name)
+            .as_type()
+    }
+}
+
+// Größe und Ausrichtung in Bits, wie LLVM sie für die Typen des Generators
+// wählt (i1 belegt ein Byte)
+fn layout(types: &TypeArena, id: TypeId) -> (u64, u64) {
+    match types.kind(id) {
+        TypeKind::Integer | TypeKind::Real => (64, 64),
+        TypeKind::Boolean | TypeKind::Char => (8, 8),
+        TypeKind::Set => (32, 32),
+        TypeKind::Array { length, element } => {
+            let (size, align) = layout(types, *element);
+            (size * *length as u64, align)
+        }
+        TypeKind::Record { .. } => {
+            let mut offset = POINTER_BITS;
+            let mut align = POINTER_BITS;
+            for field in types.fields(id) {
+                let (field_size, field_align) = layout(types, field.ty);
+                offset = offset.next_multiple_of(field_align) + field_size;
+                align = align.max(field_align);
+            }
+            (offset.next_multiple_of(align), align)
+        }
+        _ => (POINTER_BITS, POINTER_BITS),
+    }
+}
@@ -1,789 +1,1647 @@
-use crate::parser::ast::*;
-use crate::semantic::{ResolvedType, SymbolTable, Symbol, SymbolKind};
-use inkwell::context::Context;
+use super::layout::contains_record;
+use super::llvm_debug::DebugInfo;
+use crate::ir::ssa::DominatorTree;
+use crate::ir::{
+    self, BinaryOp, BlockId, Callee, CompareOp, Constant, Function, InstKind, Instruction, Lowering, Program, Slot,
+    Terminator, Type, UnaryOp, Value,
+};
+use crate::semantic::hir::{Declarations, Module};
+use crate::semantic::library::LibraryProcedure;
+use crate::semantic::{TypeArena, TypeId, TypeKind};
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
-use inkwell::module::Module as LLVMModule;
-use inkwell::types::{BasicType, BasicTypeEnum};
-use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue, PointerValue};
-use inkwell::{AddressSpace, IntPredicate, FloatPredicate};
-use std::collections::HashMap;
+use inkwell::context::Context;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::module::{Linkage, Module as LLVMModule};
+use inkwell::passes::PassBuilderOptions;
+use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple};
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType, PointerType, StructType};
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue, GlobalValue, IntValue, PhiValue, PointerValue};
+use inkwell::{AddressSpace, FloatPredicate, IntPredicate, OptimizationLevel};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::Command;
+
+// ============================================================================
+// LLVM-Backend (together llvm)
+// ============================================================================
+//
+// Übersetzt das IR (src/ir) wie der C-Generator: ein IR-Block wird ein
+// LLVM-Block, Phi-Knoten werden Phi-Knoten, Slots liegen im Stack. Die
+// Laufzeitprüfungen und ihre Meldungen entsprechen denen des Interpreters
+// (oberon_trap, Exit-Code 1); Überläufe erkennen die Intrinsics
+// llvm.*.with.overflow. Mit -g bleibt das IR ohne SSA-Aufbau, damit alle
+// Variablen in ihren Slots liegen und der Debugger sie findet; mem2reg in
+// der Pipeline von LLVM holt sie danach wieder in Register.
+//
+// Records beginnen wie im C-Backend mit dem Zeiger auf ihren Typdeskriptor,
+// danach folgen alle Felder einschließlich der geerbten. Prozeduren mit
+// geschachtelten Prozeduren legen ihre Slots in einen Rahmen, dessen erstes
+// Feld auf den Rahmen der umgebenden Prozedur zeigt; geschachtelte
+// Prozeduren erhalten ihn als ersten Parameter.
+//
+// Das Backend übersetzt einzelne Module; Zugriffe auf importierte
+// Variablen und Prozeduren melden einen Fehler.
+
+const TRAP: &str = "oberon_trap";
+const IS: &str = "oberon_is";
+const COMPARE: &str = "oberon_compare";
+
+// Größere CASE-Bereiche prüft ein Vergleich statt einzelner Fälle im switch
+const MAX_CASE_RANGE: i128 = 64;
 
 pub struct LLVMGenerator<'ctx> {
     context: &'ctx Context,
     builder: Builder<'ctx>,
     module: LLVMModule<'ctx>,
-    symbol_table: SymbolTable,
-    variables: HashMap<String, PointerValue<'ctx>>,
-    current_function: Option<FunctionValue<'ctx>>,
-    type_table: HashMap<String, BasicTypeEnum<'ctx>>,
-    variable_types: HashMap<String, BasicTypeEnum<'ctx>>,
+    types: TypeArena,
+    // Struct je Record: Deskriptor, dann die Felder in der Reihenfolge von
+    // TypeArena::fields
+    records: HashMap<TypeId, StructType<'ctx>>,
+    descriptors: HashMap<TypeId, GlobalValue<'ctx>>,
+    strings: HashMap<Vec<u8>, PointerValue<'ctx>>,
+    // Rahmen der Prozeduren mit geschachtelten Prozeduren
+    frames: HashMap<String, StructType<'ctx>>,
+    // Typen und Zeilen aus dem HIR für die Debuginformationen
+    signatures: HashMap<String, Signature>,
+    // Funktion in Arbeit
+    name: String,
+    depth: usize,
+    values: HashMap<Value, BasicValueEnum<'ctx>>,
+    slots: Vec<PointerValue<'ctx>>,
+    frame: Option<PointerValue<'ctx>>,
+    link: Option<PointerValue<'ctx>>,
+    // Zeile und Prozedur für die Meldungen der Laufzeitfehler
+    line: usize,
+    procedure: String,
+    // DWARF-Beschreibung bei -g
+    debug: Option<DebugInfo<'ctx>>,
+}
+
+// Parameter- und Ergebnistypen einer Prozedur sowie die Zeilen ihrer
+// benannten Slots (Parameter, dann lokale Variablen)
+#[derive(Default)]
+struct Signature {
+    params: Vec<TypeId>,
+    result: Option<TypeId>,
+    lines: Vec<usize>,
 }
 
 impl<'ctx> LLVMGenerator<'ctx> {
 
     pub fn new(context: &'ctx Context, module_name: &str) -> Self {
+        Self::with_debug_info(context, module_name, None)
+    }
+
+    // Mit Quelldatei: Debuginformationen für gdb/lldb (-g)
+    pub fn with_debug_info(context: &'ctx Context, module_name: &str, source: Option<&Path>) -> Self {
+        let module = context.create_module(module_name);
+        let debug = source.map(|source| DebugInfo::new(context, &module, source));
         LLVMGenerator {
             context,
             builder: context.create_builder(),
-            module: context.create_module(module_name),
-            symbol_table: SymbolTable::new(),
-            variables: HashMap::new(),
-            current_function: None,
-            type_table: HashMap::new(),
-            variable_types: HashMap::new(),  // NEU  // NEU: Initialisierung
+            module,
+            types: TypeArena::new(),
+            records: HashMap::new(),
+            descriptors: HashMap::new(),
+            strings: HashMap::new(),
+            frames: HashMap::new(),
+            signatures: HashMap::new(),
+            name: String::new(),
+            depth: 0,
+            values: HashMap::new(),
+            slots: Vec::new(),
+            frame: None,
+            link: None,
+            line: 0,
+            procedure: String::new(),
+            debug,
         }
     }
 
     pub fn generate(&mut self, module: &Module) -> Result<String, String> {
-        // Externe Funktionen deklarieren (printf, puts)
-        self.declare_external_functions();
+        let program = match self.debug {
+            Some(_) => Lowering::new().lower(module)?,
+            None => ir::build(module)?,
+        };
+        self.types = program.types.clone();
+        self.signatures.clear();
+        collect_signatures("", &module.declarations, &mut self.signatures);
+
+        self.declare_runtime();
+        self.declare_globals(&program)?;
+        self.declare_functions(&program)?;
+        for function in &program.functions {
+            self.generate_function(function)?;
+        }
+
+        if let Some(debug) = &self.debug {
+            debug.finalize();
+        }
+
+        // LLVM-IR als String zurückgeben
+        Ok(self.module.print_to_string().to_string())
+    }
 
-        // Module-Symbol hinzufügen
-        self.symbol_table.define(Symbol {
-            name: module.name.clone(),
-            kind: SymbolKind::Module,
-            exported: ExportMark::None,
-            defined_at: None,
-        }).ok();
+    // ========================================================================
+    // Typen
+    // ========================================================================
 
-        // Konstanten registrieren
-        for const_decl in &module.declarations.constants {
-            self.symbol_table.define(Symbol {
-                name: const_decl.name.name.clone(),
-                kind: SymbolKind::Constant {
-                    value: const_decl.value.clone(),
-                    resolved_type: ResolvedType::Integer, // Vereinfacht
-                },
-                exported: const_decl.name.exported.clone(),
-                defined_at: None,
-            }).ok();
+    fn pointer_type(&self) -> PointerType<'ctx> {
+        self.context.i8_type().ptr_type(AddressSpace::default())
+    }
+
+    fn value_type(&self, ty: Type) -> BasicTypeEnum<'ctx> {
+        match ty {
+            Type::Int => self.context.i64_type().into(),
+            Type::Real => self.context.f64_type().into(),
+            Type::Bool => self.context.bool_type().into(),
+            Type::Char => self.context.i8_type().into(),
+            Type::Set => self.context.i32_type().into(),
+            Type::Ptr => self.pointer_type().into(),
         }
+    }
 
-        // Globale Typen deklarieren
-        for type_decl in &module.declarations.types {
-            self.declare_type(type_decl)?;
+    // Typ einer Variablen im Speicher
+    fn memory_type(&mut self, ty: TypeId) -> Result<BasicTypeEnum<'ctx>, String> {
+        match self.types.kind(ty).clone() {
+            TypeKind::Array { length, element } => {
+                let element = self.memory_type(element)?;
+                let length = u32::try_from(length)
+                    .map_err(|_| format!("{} ist zu groß für das LLVM-Backend", self.types.display(ty)))?;
+                Ok(element.array_type(length).into())
+            }
+            TypeKind::Record { .. } => Ok(self.record_type(ty)?.into()),
+            _ => Type::of(&self.types, ty)
+                .map(|ty| self.value_type(ty))
+                .ok_or_else(|| format!("Typ {} wird vom LLVM-Backend nicht unterstützt", self.types.display(ty))),
         }
+    }
 
-        // Globale Variablen
-        for var_decl in &module.declarations.variables {
-            self.declare_global_variable(var_decl)?;
+    // Benannter Struct, zuerst opak angelegt, damit rekursive Zeigertypen
+    // (Node = POINTER TO NodeDesc) ihn schon finden
+    fn record_type(&mut self, record: TypeId) -> Result<StructType<'ctx>, String> {
+        if let Some(&structure) = self.records.get(&record) {
+            return Ok(structure);
         }
+        let name = self.types.display(record);
+        let structure = self.context.opaque_struct_type(&name);
+        self.records.insert(record, structure);
+        let fields: Vec<TypeId> = self.types.fields(record).iter().map(|field| field.ty).collect();
+        let mut body = vec![BasicTypeEnum::from(self.pointer_type())];
+        for field in fields {
+            body.push(self.memory_type(field)?);
+        }
+        structure.set_body(&body, false);
+        Ok(structure)
+    }
 
-        // Prozeduren deklarieren
-        for proc_decl in &module.declarations.procedures {
-            if !proc_decl.is_forward {
-                self.declare_function(proc_decl)?;
-            }
+    fn slot_type(&mut self, slot: &Slot) -> Result<BasicTypeEnum<'ctx>, String> {
+        if slot.by_reference {
+            Ok(self.pointer_type().into())
+        } else {
+            self.memory_type(slot.ty)
         }
+    }
 
-        // Prozeduren implementieren
-        for proc_decl in &module.declarations.procedures {
-            if !proc_decl.is_forward {
-                self.generate_function(proc_decl)?;
-            }
+    fn function_type(&self, result: Option<Type>, params: &[BasicMetadataTypeEnum<'ctx>]) -> FunctionType<'ctx> {
+        match result {
+            Some(ty) => self.value_type(ty).fn_type(params, false),
+            None => self.context.void_type().fn_type(params, false),
         }
+    }
 
-        // Main-Funktion generieren
-        self.generate_main(module)?;
+    // Der Modulrumpf wird main; geschachtelte Prozeduren erhalten zuerst
+    // den Rahmen der umgebenden
+    fn signature(&self, function: &Function) -> FunctionType<'ctx> {
+        if function.depth == 0 {
+            return self.context.i32_type().fn_type(&[], false);
+        }
+        let mut params: Vec<BasicMetadataTypeEnum<'ctx>> = Vec::new();
+        if function.depth > 1 {
+            params.push(self.pointer_type().into());
+        }
+        for param in &function.params {
+            params.push(self.value_type(function.value_type(param.value)).into());
+        }
+        self.function_type(function.result, &params)
+    }
 
-        // LLVM-IR als String zurückgeben
-        Ok(self.module.print_to_string().to_string())
+    fn frame_type(&self, name: &str) -> Result<StructType<'ctx>, String> {
+        self.frames.get(name).copied().ok_or_else(|| format!("{} hat keinen Rahmen", name))
     }
 
     // ========================================================================
-    // Typen
+    // Laufzeit
     // ========================================================================
 
-    fn declare_type(&mut self, type_decl: &TypeDeclaration) -> Result<(), String> {
-        // Typ generieren und in Tabelle speichern
-        let llvm_type = self.resolve_llvm_type(&type_decl.type_def)?;
-        self.type_table.insert(type_decl.name.name.clone(), llvm_type);
-        Ok(())
+    fn declare_runtime(&mut self) {
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let pointer = self.pointer_type();
+        let void = self.context.void_type();
+        self.module.add_function("printf", i32_type.fn_type(&[pointer.into()], true), None);
+        self.module.add_function("dprintf", i32_type.fn_type(&[i32_type.into(), pointer.into()], true), None);
+        self.module.add_function("putchar", i32_type.fn_type(&[i32_type.into()], false), None);
+        self.module.add_function("fflush", i32_type.fn_type(&[pointer.into()], false), None);
+        self.module.add_function("exit", void.fn_type(&[i32_type.into()], false), None);
+        self.module.add_function("calloc", pointer.fn_type(&[i64_type.into(), i64_type.into()], false), None);
+
+        // Die Hilfsfunktionen haben keine Debuginformationen
+        self.builder.unset_current_debug_location();
+        self.generate_trap();
+        self.generate_is();
+        self.generate_compare();
+    }
+
+    fn runtime(&self, name: &str) -> FunctionValue<'ctx> {
+        self.module.get_function(name).expect("Laufzeitfunktion ist deklariert")
+    }
+
+    // oberon_trap(Zeile, Prozedur, Meldung, a, b): Meldung im Format des
+    // Interpreters auf stderr, dann exit(1); a und b füllen die Platzhalter
+    fn generate_trap(&mut self) {
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let pointer = self.pointer_type();
+        let ty = self.context.void_type().fn_type(
+            &[i64_type.into(), pointer.into(), pointer.into(), i64_type.into(), i64_type.into()],
+            false,
+        );
+        let function = self.module.add_function(TRAP, ty, Some(Linkage::Internal));
+        let entry = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(entry);
+        let param = |index| BasicMetadataValueEnum::from(function.get_nth_param(index).unwrap());
+
+        let stderr = BasicMetadataValueEnum::from(i32_type.const_int(2, false));
+        let prefix = BasicMetadataValueEnum::from(self.string(b"%lld: Laufzeitfehler in %s: "));
+        let newline = BasicMetadataValueEnum::from(self.string(b"\n"));
+        self.builder.build_call(self.runtime("fflush"), &[pointer.const_null().into()], "").unwrap();
+        self.builder.build_call(self.runtime("dprintf"), &[stderr, prefix, param(0), param(1)], "").unwrap();
+        self.builder.build_call(self.runtime("dprintf"), &[stderr, param(2), param(3), param(4)], "").unwrap();
+        self.builder.build_call(self.runtime("dprintf"), &[stderr, newline], "").unwrap();
+        self.builder.build_call(self.runtime("exit"), &[i32_type.const_int(1, false).into()], "").unwrap();
+        self.builder.build_unreachable().unwrap();
+    }
+
+    // oberon_is(d, t): ist d der Deskriptor von t oder einer Erweiterung davon?
+    fn generate_is(&mut self) {
+        let pointer = self.pointer_type();
+        let bool_type = self.context.bool_type();
+        let ty = bool_type.fn_type(&[pointer.into(), pointer.into()], false);
+        let function = self.module.add_function(IS, ty, Some(Linkage::Internal));
+        let entry = self.context.append_basic_block(function, "entry");
+        let test = self.context.append_basic_block(function, "test");
+        let next = self.context.append_basic_block(function, "next");
+        let yes = self.context.append_basic_block(function, "yes");
+        let no = self.context.append_basic_block(function, "no");
+        let start = function.get_nth_param(0).unwrap().into_pointer_value();
+        let target = function.get_nth_param(1).unwrap().into_pointer_value();
+
+        self.builder.position_at_end(entry);
+        self.builder.build_unconditional_branch(test).unwrap();
+
+        self.builder.position_at_end(test);
+        let phi = self.builder.build_phi(pointer, "d").unwrap();
+        let descriptor = phi.as_basic_value().into_pointer_value();
+        let end = self.builder.build_is_null(descriptor, "").unwrap();
+        self.builder.build_conditional_branch(end, no, next).unwrap();
+
+        self.builder.position_at_end(next);
+        let same = self.pointers_equal(descriptor, target);
+        let base = self.builder.build_load(pointer, descriptor, "base").unwrap();
+        self.builder.build_conditional_branch(same, yes, test).unwrap();
+        phi.add_incoming(&[(&start, entry), (&base, next)]);
+
+        self.builder.position_at_end(yes);
+        self.builder.build_return(Some(&bool_type.const_int(1, false))).unwrap();
+        self.builder.position_at_end(no);
+        self.builder.build_return(Some(&bool_type.const_zero())).unwrap();
+    }
+
+    // oberon_compare(a, la, b, lb): vergleicht zwei Zeichenketten bis zum
+    // ersten 0X, liefert -1, 0 oder 1; hinter ihrer Länge gilt eine Kette
+    // als beendet
+    fn generate_compare(&mut self) {
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let pointer = self.pointer_type();
+        let ty = i32_type.fn_type(&[pointer.into(), i64_type.into(), pointer.into(), i64_type.into()], false);
+        let function = self.module.add_function(COMPARE, ty, Some(Linkage::Internal));
+        let entry = self.context.append_basic_block(function, "entry");
+        let compare = self.context.append_basic_block(function, "compare");
+        let different = self.context.append_basic_block(function, "different");
+        let same = self.context.append_basic_block(function, "same");
+        let equal = self.context.append_basic_block(function, "equal");
+        let param = |index| function.get_nth_param(index).unwrap();
+
+        self.builder.position_at_end(entry);
+        self.builder.build_unconditional_branch(compare).unwrap();
+
+        self.builder.position_at_end(compare);
+        let phi = self.builder.build_phi(i64_type, "i").unwrap();
+        let index = phi.as_basic_value().into_int_value();
+        let x = self.character(param(0).into_pointer_value(), param(1).into_int_value(), index);
+        let y = self.character(param(2).into_pointer_value(), param(3).into_int_value(), index);
+        let differ = self.builder.build_int_compare(IntPredicate::NE, x, y, "").unwrap();
+        self.builder.build_conditional_branch(differ, different, same).unwrap();
+
+        self.builder.position_at_end(different);
+        let less = self.builder.build_int_compare(IntPredicate::ULT, x, y, "").unwrap();
+        let result = self.builder.build_select(less, i32_type.const_all_ones(), i32_type.const_int(1, false), "").unwrap();
+        self.builder.build_return(Some(&result)).unwrap();
+
+        self.builder.position_at_end(same);
+        let end = self.builder.build_int_compare(IntPredicate::EQ, x, x.get_type().const_zero(), "").unwrap();
+        let next = self.builder.build_int_add(index, i64_type.const_int(1, false), "").unwrap();
+        self.builder.build_conditional_branch(end, equal, compare).unwrap();
+        phi.add_incoming(&[(&i64_type.const_zero(), entry), (&next, same)]);
+
+        self.builder.position_at_end(equal);
+        self.builder.build_return(Some(&i32_type.const_zero())).unwrap();
+    }
+
+    // Zeichen `index` einer Zeichenkette der Länge `length`, dahinter 0X
+    fn character(&self, text: PointerValue<'ctx>, length: IntValue<'ctx>, index: IntValue<'ctx>) -> IntValue<'ctx> {
+        let i8_type = self.context.i8_type();
+        let inside = self.builder.build_int_compare(IntPredicate::SLT, index, length, "").unwrap();
+        let position = self.builder.build_select(inside, index, index.get_type().const_zero(), "").unwrap();
+        let address = unsafe { self.builder.build_gep(i8_type, text, &[position.into_int_value()], "") }.unwrap();
+        let ch = self.builder.build_load(i8_type, address, "").unwrap().into_int_value();
+        self.builder.build_select(inside, ch, i8_type.const_zero(), "").unwrap().into_int_value()
     }
 
     // ========================================================================
-    // Globale Variablen
+    // Globale Variablen und Deklarationen
     // ========================================================================
 
-    fn resolve_llvm_type(&self, oberon_type: &Type) -> Result<BasicTypeEnum<'ctx>, String> {
-        match oberon_type {
-            Type::Qualident(q) => {
-                match q.name.as_str() {
-                    "INTEGER" => Ok(self.context.i64_type().into()),
-                    "REAL" => Ok(self.context.f64_type().into()),
-                    "BOOLEAN" => Ok(self.context.bool_type().into()),
-                    "CHAR" => Ok(self.context.i8_type().into()),
-                    _ => {
-                        // Benutzerdefinierten Typ aus Tabelle suchen
-                        self.type_table
-                            .get(&q.name)
-                            .copied()
-                            .ok_or(format!("Unbekannter Typ: {}", q.name))
-                    }
-                }
+    fn declare_globals(&mut self, program: &Program) -> Result<(), String> {
+        for global in &program.globals {
+            let ty = self.memory_type(global.ty)?;
+            let value = self.module.add_global(ty, None, &global_name(&global.name));
+            value.set_initializer(&ty.const_zero());
+            if !global.exported {
+                value.set_linkage(Linkage::Internal);
             }
-            Type::Array { lengths, element_type } => {
-                let elem_type = self.resolve_llvm_type(element_type)?;
-                let mut array_type = elem_type;
-
-                // Arrays von rechts nach links aufbauen
-                for length_expr in lengths.iter().rev() {
-                    let size = if let Expression::IntegerLiteral(val) = length_expr {
-                        *val as u32
-                    } else {
-                        100 // Fallback
-                    };
-                    array_type = array_type.array_type(size).into();
-                }
-
-                Ok(array_type)
+            if let Some(debug) = &mut self.debug {
+                debug.global(self.context, &self.types, value, &global.name, global.ty, global.line);
             }
-            Type::Record { fields, .. } => {
-                let mut field_types = Vec::new();
-                for field_list in fields {
-                    let field_type = self.resolve_llvm_type(&field_list.field_type)?;
-                    for _ in &field_list.names {
-                        field_types.push(field_type);
-                    }
-                }
-                Ok(self.context.struct_type(&field_types, false).into())
+        }
+        Ok(())
+    }
+
+    fn declare_functions(&mut self, program: &Program) -> Result<(), String> {
+        self.frames.clear();
+        let framed: HashSet<String> = program
+            .functions
+            .iter()
+            .filter(|function| function.depth > 1)
+            .map(|function| function.ancestor(function.depth - 1))
+            .collect();
+        for function in &program.functions {
+            if !framed.contains(&function.name) {
+                continue;
             }
-            Type::Pointer { target_type } => {
-                let target = self.resolve_llvm_type(target_type)?;
-                Ok(target.ptr_type(AddressSpace::default()).into())
+            let mut fields = vec![BasicTypeEnum::from(self.pointer_type())];
+            for slot in &function.slots {
+                fields.push(self.slot_type(slot)?);
             }
-            _ => Err("Nicht unterstützter Typ".to_string()),
+            let frame = self.context.opaque_struct_type(&format!("{}.frame", function_name(function)));
+            frame.set_body(&fields, false);
+            self.frames.insert(function.name.clone(), frame);
+        }
+
+        for function in &program.functions {
+            let linkage = if function.depth == 0 || function.exported { None } else { Some(Linkage::Internal) };
+            self.module.add_function(&function_name(function), self.signature(function), linkage);
         }
+        Ok(())
     }
 
     // ========================================================================
-    // Externe Funktionen
+    // Funktionen
     // ========================================================================
 
-    fn declare_external_functions(&mut self) {
-        // printf deklarieren: i32 printf(i8*, ...)
-        let i8_ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
-        let printf_type = self.context.i32_type().fn_type(&[i8_ptr_type.into()], true); // varargs
-        self.module.add_function("printf", printf_type, None);
-
-        // puts deklarieren: i32 puts(i8*)
-        let puts_type = self.context.i32_type().fn_type(&[i8_ptr_type.into()], false);
-        self.module.add_function("puts", puts_type, None);
-    }
+    fn generate_function(&mut self, function: &Function) -> Result<(), String> {
+        let value = self
+            .module
+            .get_function(&function_name(function))
+            .ok_or_else(|| format!("Funktion {} ist nicht deklariert", function.name))?;
+        self.name = function.name.clone();
+        self.procedure = function.name.clone();
+        self.depth = function.depth;
+        self.values.clear();
+
+        let entry = self.context.append_basic_block(value, "entry");
+        self.builder.position_at_end(entry);
+        if let Some(debug) = &mut self.debug {
+            let signature = self.signatures.get(&function.name);
+            let params = signature.map(|signature| signature.params.clone()).unwrap_or_default();
+            let result = signature.and_then(|signature| signature.result);
+            debug.begin_function(&self.types, value, &function.name, &params, result, function.line);
+        }
+        self.line = function.line;
+        self.set_line(function.line);
 
-    // ========================================================================
-    // Globale Variablen
-    // ========================================================================
+        let offset = if function.depth > 1 { 1 } else { 0 };
+        self.link = match function.depth > 1 {
+            true => value.get_first_param().map(|link| link.into_pointer_value()),
+            false => None,
+        };
+        for (index, param) in function.params.iter().enumerate() {
+            let argument = value
+                .get_nth_param((index + offset) as u32)
+                .ok_or_else(|| format!("Parameter {} von {} fehlt", index, function.name))?;
+            self.values.insert(param.value, argument);
+        }
 
-    fn declare_global_variable(&mut self, var_decl: &VariableDeclaration) -> Result<(), String> {
-        let var_type = self.resolve_llvm_type(&var_decl.var_type)?;
+        // Slots, beim Eintritt mit 0 belegt
+        self.slots.clear();
+        self.frame = None;
+        match self.frames.get(&function.name).copied() {
+            Some(frame) => {
+                let address = self.builder.build_alloca(frame, "frame").unwrap();
+                self.zero(address, frame.into())?;
+                if let Some(link) = self.link {
+                    let field = self.builder.build_struct_gep(frame, address, 0, "link").unwrap();
+                    self.builder.build_store(field, link).unwrap();
+                }
+                for index in 0..function.slots.len() {
+                    let name = slot_name(&function.slots[index]);
+                    let field = self.builder.build_struct_gep(frame, address, index as u32 + 1, name).unwrap();
+                    self.slots.push(field);
+                }
+                self.frame = Some(address);
+            }
+            None => {
+                for slot in &function.slots {
+                    let ty = self.slot_type(slot)?;
+                    let address = self.builder.build_alloca(ty, slot_name(slot)).unwrap();
+                    self.zero(address, ty)?;
+                    self.slots.push(address);
+                }
+            }
+        }
 
-        for name in &var_decl.names {
-            let global = self.module.add_global(
-                var_type,
-                Some(AddressSpace::default()),
-                &self.mangle_name(&name.name),
-            );
+        if let Some(debug) = &mut self.debug {
+            let lines = self.signatures.get(&function.name).map(|signature| signature.lines.as_slice()).unwrap_or(&[]);
+            for (index, slot) in function.slots.iter().enumerate() {
+                let Some(name) = &slot.name else { continue };
+                let line = lines.get(index).copied().unwrap_or(function.line);
+                let argument = (index < function.params.len()).then_some(index as u32 + 1);
+                debug.variable(self.context, &self.types, self.slots[index], name, slot.ty, argument, slot.by_reference, line, entry);
+            }
+        }
 
-            // Initialisierung mit Null
-            global.set_initializer(&var_type.const_zero());
+        // Blöcke in umgekehrter Postordnung; unerreichbare entfallen
+        let order = DominatorTree::new(function).reverse_postorder().to_vec();
+        let blocks: HashMap<BlockId, BasicBlock<'ctx>> =
+            order.iter().map(|&id| (id, self.context.append_basic_block(value, &format!("b{}", id.index())))).collect();
+        self.builder.build_unconditional_branch(blocks[&order[0]]).unwrap();
+
+        // Argumente der Phi-Knoten erst am Ende: sie können aus später
+        // übersetzten Blöcken stammen
+        let mut phis: Vec<(&ir::Phi, PhiValue<'ctx>)> = Vec::new();
+        let mut exits: HashMap<BlockId, BasicBlock<'ctx>> = HashMap::new();
+        for &id in &order {
+            self.builder.position_at_end(blocks[&id]);
+            let block = function.block(id);
+            for phi in &block.phis {
+                let node = self.builder.build_phi(self.value_type(function.value_type(phi.dest)), "").unwrap();
+                self.values.insert(phi.dest, node.as_basic_value());
+                phis.push((phi, node));
+            }
+            for instruction in &block.instructions {
+                self.generate_instruction(function, instruction)?;
+            }
+            // Prüfungen teilen Blöcke; der Abschluss steht im letzten
+            exits.insert(id, self.builder.get_insert_block().unwrap());
+            self.generate_terminator(function, &block.terminator, &blocks)?;
+        }
+        for (phi, node) in phis {
+            for (source, argument) in &phi.arguments {
+                let Some(&exit) = exits.get(source) else { continue };
+                let argument = self.value(*argument)?;
+                node.add_incoming(&[(&argument, exit)]);
+            }
+        }
 
-            self.variables.insert(name.name.clone(), global.as_pointer_value());
-            self.variable_types.insert(name.name.clone(), var_type);  // NEU: Typ speichern
+        if let Some(debug) = &mut self.debug {
+            debug.end_function();
         }
+        Ok(())
+    }
 
+    fn generate_terminator(
+        &mut self,
+        function: &Function,
+        terminator: &Terminator,
+        blocks: &HashMap<BlockId, BasicBlock<'ctx>>,
+    ) -> Result<(), String> {
+        match terminator {
+            Terminator::Jump(target) => {
+                self.builder.build_unconditional_branch(blocks[target]).unwrap();
+            }
+            Terminator::Branch { condition, then_block, else_block } => {
+                let condition = self.int(*condition)?;
+                self.builder.build_conditional_branch(condition, blocks[then_block], blocks[else_block]).unwrap();
+            }
+            Terminator::Switch { value, cases, default } => {
+                let value = self.int(*value)?;
+                self.generate_switch(value, cases, blocks[default], blocks);
+            }
+            Terminator::Return(Some(value)) => {
+                let value = self.value(*value)?;
+                self.builder.build_return(Some(&value)).unwrap();
+            }
+            Terminator::Return(None) if function.depth == 0 => {
+                self.builder.build_return(Some(&self.context.i32_type().const_zero())).unwrap();
+            }
+            // Ende einer Funktionsprozedur ohne RETURN; das IR erreicht es nicht
+            Terminator::Return(None) if function.result.is_some() => {
+                self.builder.build_unreachable().unwrap();
+            }
+            Terminator::Return(None) => {
+                self.builder.build_return(None).unwrap();
+            }
+            Terminator::CaseTrap { value, line } => {
+                self.line = *line;
+                self.set_line(*line);
+                let signed = function.value_type(*value) == Type::Int;
+                let value = self.int(*value)?;
+                let value = self.extend(value, signed);
+                self.trap("Kein CASE-Label für den Wert %lld", Some(value), None);
+            }
+        }
         Ok(())
     }
 
+    // CASE: kleine Bereiche als einzelne Fälle des switch, große prüft
+    // vorher je ein Vergleich in Reihenfolge der Labels
+    fn generate_switch(
+        &mut self,
+        value: IntValue<'ctx>,
+        cases: &[(i64, i64, BlockId)],
+        default: BasicBlock<'ctx>,
+        blocks: &HashMap<BlockId, BasicBlock<'ctx>>,
+    ) {
+        let ty = value.get_type();
+        let mut labels = Vec::new();
+        for &(start, end, target) in cases {
+            let target = blocks[&target];
+            if (end as i128) - (start as i128) < MAX_CASE_RANGE {
+                for label in start..=end {
+                    labels.push((ty.const_int(label as u64, true), target));
+                }
+            } else {
+                let offset = self.builder.build_int_sub(value, ty.const_int(start as u64, true), "").unwrap();
+                let width = ty.const_int(end.wrapping_sub(start) as u64, false);
+                let inside = self.builder.build_int_compare(IntPredicate::ULE, offset, width, "").unwrap();
+                let next = self.append_block("case");
+                self.builder.build_conditional_branch(inside, target, next).unwrap();
+                self.builder.position_at_end(next);
+            }
+        }
+        self.builder.build_switch(value, default, &labels).unwrap();
+    }
+
     // ========================================================================
-    // Funktionen
+    // Befehle
     // ========================================================================
 
-    fn declare_function(&mut self, proc: &ProcedureDeclaration) -> Result<(), String> {
-        let return_type = if let Some(params) = &proc.params {
-            if let Some(ret_type) = &params.return_type {
-                Some(self.resolve_llvm_type(&Type::Qualident(ret_type.clone()))?)
-            } else {
+    fn generate_instruction(&mut self, function: &Function, instruction: &Instruction) -> Result<(), String> {
+        self.line = instruction.line;
+        self.procedure = instruction.origin.clone().unwrap_or_else(|| function.name.clone());
+        self.set_line(instruction.line);
+
+        let result: Option<BasicValueEnum<'ctx>> = match &instruction.kind {
+            InstKind::Const(constant) => Some(self.constant(constant)),
+            InstKind::String(text) => Some(self.string(text).into()),
+            InstKind::Global(name) => {
+                let global = self.module.get_global(&global_name(name)).ok_or_else(|| imported("Variable", name))?;
+                Some(global.as_pointer_value().into())
+            }
+            InstKind::Slot { depth, index } => Some(self.slot(*depth, *index)?.into()),
+            InstKind::Procedure(name) => {
+                let procedure = self.module.get_function(&procedure_name(name)).ok_or_else(|| imported("Prozedur", name))?;
+                Some(procedure.as_global_value().as_pointer_value().into())
+            }
+            InstKind::Field { base, record, field } => {
+                let structure = self.record_type(*record)?;
+                let base = self.pointer(*base)?;
+                Some(self.builder.build_struct_gep(structure, base, *field as u32 + 1, "").unwrap().into())
+            }
+            InstKind::Element { base, array, index } => {
+                let element = match self.types.kind(*array) {
+                    TypeKind::Array { element, .. } => *element,
+                    _ => return Err(format!("Elementzugriff auf {}", self.types.display(*array))),
+                };
+                let element = self.memory_type(element)?;
+                let base = self.pointer(*base)?;
+                let index = self.int(*index)?;
+                Some(unsafe { self.builder.build_gep(element, base, &[index], "") }.unwrap().into())
+            }
+            InstKind::Load { ty, address } => {
+                let address = self.pointer(*address)?;
+                Some(self.builder.build_load(self.value_type(*ty), address, "").unwrap())
+            }
+            InstKind::Store { address, value, .. } => {
+                let address = self.pointer(*address)?;
+                let value = self.value(*value)?;
+                self.builder.build_store(address, value).unwrap();
                 None
             }
-        } else {
-            None
-        };
-
-        let mut param_types = Vec::new();
-        if let Some(params) = &proc.params {
-            for section in &params.sections {
-                let param_type = self.resolve_llvm_type(&section.param_type)?;
-                for _ in &section.names {
-                    if section.is_var {
-                        // VAR-Parameter als Pointer
-                        param_types.push(param_type.ptr_type(AddressSpace::default()).into());
-                    } else {
-                        param_types.push(param_type.into());
+            InstKind::Copy { ty, dest, source } => {
+                let dest = self.pointer(*dest)?;
+                let source = self.pointer(*source)?;
+                self.copy(*ty, dest, source)?;
+                None
+            }
+            InstKind::CopyString { dest, source, length } => {
+                let dest = self.pointer(*dest)?;
+                let source = self.pointer(*source)?;
+                let length = self.context.i64_type().const_int(*length as u64, false);
+                self.builder.build_memcpy(dest, 1, source, 1, length).map_err(|e| e.to_string())?;
+                None
+            }
+            InstKind::InitDescriptors { ty, address } => {
+                let address = self.pointer(*address)?;
+                self.init_descriptors(address, *ty)?;
+                None
+            }
+            InstKind::New { record } => {
+                let structure = self.record_type(*record)?;
+                let i64_type = self.context.i64_type();
+                let size = structure.size_of().ok_or("Record ohne Größe")?;
+                let arguments: [BasicMetadataValueEnum<'ctx>; 2] = [i64_type.const_int(1, false).into(), size.into()];
+                let object = self.builder.build_call(self.runtime("calloc"), &arguments, "").unwrap();
+                let object = object.try_as_basic_value().left().ok_or("calloc ohne Ergebnis")?.into_pointer_value();
+                let failed = self.builder.build_is_null(object, "").unwrap();
+                self.trap_if(failed, "Kein Speicher mehr für NEW", None, None);
+                self.init_descriptors(object, *record)?;
+                Some(object.into())
+            }
+            InstKind::Unary { op, operand } => {
+                let operand = self.value(*operand)?;
+                Some(self.unary(*op, operand))
+            }
+            InstKind::Binary { op, left, right } => {
+                let left = self.value(*left)?;
+                let right = self.value(*right)?;
+                Some(self.binary(*op, left, right))
+            }
+            InstKind::Compare { op, left, right } => {
+                let ty = function.value_type(*left);
+                let left = self.value(*left)?;
+                let right = self.value(*right)?;
+                Some(self.compare(*op, ty, left, right).into())
+            }
+            InstKind::CompareStrings { op, left, left_length, right, right_length } => {
+                let i64_type = self.context.i64_type();
+                let arguments: [BasicMetadataValueEnum<'ctx>; 4] = [
+                    self.value(*left)?.into(),
+                    i64_type.const_int(*left_length as u64, false).into(),
+                    self.value(*right)?.into(),
+                    i64_type.const_int(*right_length as u64, false).into(),
+                ];
+                let order = self.builder.build_call(self.runtime(COMPARE), &arguments, "").unwrap();
+                let order = order.try_as_basic_value().left().unwrap().into_int_value();
+                let zero = order.get_type().const_zero();
+                Some(self.builder.build_int_compare(signed_predicate(*op), order, zero, "").unwrap().into())
+            }
+            InstKind::TypeTest { address, record } => {
+                let address = self.pointer(*address)?;
+                let tag = self.builder.build_load(self.pointer_type(), address, "tag").unwrap();
+                let descriptor = self.descriptor(*record);
+                let result = self.builder.build_call(self.runtime(IS), &[tag.into(), descriptor.into()], "").unwrap();
+                result.try_as_basic_value().left()
+            }
+            InstKind::Call { callee, arguments } => {
+                let mut values: Vec<BasicMetadataValueEnum<'ctx>> = Vec::new();
+                for argument in arguments {
+                    values.push(self.value(*argument)?.into());
+                }
+                let call = match callee {
+                    Callee::Direct { name, depth } => {
+                        let target =
+                            self.module.get_function(&procedure_name(name)).ok_or_else(|| imported("Prozedur", name))?;
+                        if *depth > 1 {
+                            values.insert(0, self.frame(depth - 1)?.into());
+                        }
+                        self.builder.build_call(target, &values, "").unwrap()
+                    }
+                    Callee::Indirect(target) => {
+                        let target = self.pointer(*target)?;
+                        let is_nil = self.builder.build_is_null(target, "").unwrap();
+                        self.trap_if(is_nil, "Aufruf einer Prozedurvariablen mit Wert NIL", None, None);
+                        let params: Vec<BasicMetadataTypeEnum<'ctx>> = arguments
+                            .iter()
+                            .map(|argument| self.value_type(function.value_type(*argument)).into())
+                            .collect();
+                        let ty = self.function_type(instruction.dest.map(|dest| function.value_type(dest)), &params);
+                        self.builder.build_indirect_call(ty, target, &values, "").unwrap()
                     }
+                };
+                call.try_as_basic_value().left()
+            }
+            InstKind::Output { procedure, arguments } => {
+                let mut values = Vec::new();
+                for argument in arguments {
+                    values.push(self.value(*argument)?);
                 }
+                self.output(*procedure, &values)?;
+                None
+            }
+            InstKind::CheckIndex { index, length } => {
+                let index = self.int(*index)?;
+                let i64_type = self.context.i64_type();
+                let bound = i64_type.const_int(*length as u64, false);
+                let outside = self.builder.build_int_compare(IntPredicate::UGE, index, bound, "").unwrap();
+                let last = i64_type.const_int((*length as u64).wrapping_sub(1), true);
+                self.trap_if(outside, "Index %lld außerhalb von 0..%lld", Some(index), Some(last));
+                None
             }
+            InstKind::CheckNil(value) => {
+                let value = self.pointer(*value)?;
+                let is_nil = self.builder.build_is_null(value, "").unwrap();
+                self.trap_if(is_nil, "Dereferenzierung von NIL", None, None);
+                None
+            }
+            InstKind::Assert(value) => {
+                let value = self.int(*value)?;
+                let failed = self.builder.build_not(value, "").unwrap();
+                self.trap_if(failed, "ASSERT fehlgeschlagen", None, None);
+                None
+            }
+        };
+
+        if let (Some(dest), Some(result)) = (instruction.dest, result) {
+            self.values.insert(dest, result);
         }
+        Ok(())
+    }
 
-        let fn_type = if let Some(ret) = return_type {
-            ret.fn_type(&param_types, false)
-        } else {
-            self.context.void_type().fn_type(&param_types, false)
-        };
+    fn value(&self, value: Value) -> Result<BasicValueEnum<'ctx>, String> {
+        self.values.get(&value).copied().ok_or_else(|| format!("Wert {} ist nicht definiert", value))
+    }
 
-        self.module.add_function(
-            &self.mangle_name(&proc.name.name),
-            fn_type,
-            None,
-        );
+    fn int(&self, value: Value) -> Result<IntValue<'ctx>, String> {
+        Ok(self.value(value)?.into_int_value())
+    }
 
-        Ok(())
+    fn pointer(&self, value: Value) -> Result<PointerValue<'ctx>, String> {
+        Ok(self.value(value)?.into_pointer_value())
     }
 
-    fn generate_function(&mut self, proc: &ProcedureDeclaration) -> Result<(), String> {
-        // Spezielle Built-in Funktionen
-        if proc.name.name == "WriteInt" {
-            return self.generate_write_int();
+    fn constant(&self, constant: &Constant) -> BasicValueEnum<'ctx> {
+        match constant {
+            Constant::Int(value) => self.context.i64_type().const_int(*value as u64, true).into(),
+            Constant::Real(value) => self.context.f64_type().const_float(*value).into(),
+            Constant::Bool(value) => self.context.bool_type().const_int(*value as u64, false).into(),
+            Constant::Char(value) => self.context.i8_type().const_int(*value as u64, false).into(),
+            Constant::Set(bits) => self.context.i32_type().const_int(*bits as u64, false).into(),
+            Constant::Nil => self.pointer_type().const_null().into(),
         }
-        if proc.name.name == "WriteLn" {
-            return self.generate_write_ln();
+    }
+
+    // Mit 0X abgeschlossene Konstante, gleiche Texte teilen sich eine
+    fn string(&mut self, text: &[u8]) -> PointerValue<'ctx> {
+        if let Some(&pointer) = self.strings.get(text) {
+            return pointer;
         }
+        let value = self.context.const_string(text, true);
+        let global = self.module.add_global(value.get_type(), None, ".str");
+        global.set_linkage(Linkage::Private);
+        global.set_initializer(&value);
+        global.set_constant(true);
+        let pointer = global.as_pointer_value();
+        self.strings.insert(text.to_vec(), pointer);
+        pointer
+    }
 
-        let function = self.module
-            .get_function(&self.mangle_name(&proc.name.name))
-            .ok_or("Funktion nicht gefunden")?;
+    // Slot der eigenen oder einer umgebenden Funktion
+    fn slot(&self, depth: usize, index: usize) -> Result<PointerValue<'ctx>, String> {
+        if depth == self.depth {
+            return self.slots.get(index).copied().ok_or_else(|| format!("Slot {} von {} fehlt", index, self.name));
+        }
+        let frame = self.frame(depth)?;
+        let ty = self.frame_type(&ancestor(&self.name, depth))?;
+        Ok(self.builder.build_struct_gep(ty, frame, index as u32 + 1, "").unwrap())
+    }
 
-        self.current_function = Some(function);
+    // Rahmen der Funktion auf Tiefe `depth`: der eigene oder über die
+    // statischen Verweise der einer umgebenden
+    fn frame(&self, depth: usize) -> Result<PointerValue<'ctx>, String> {
+        if depth == self.depth {
+            return self.frame.ok_or_else(|| format!("{} hat keinen Rahmen", self.name));
+        }
+        let mut frame = self.link.ok_or_else(|| format!("{} hat keinen statischen Verweis", self.name))?;
+        for level in (depth + 1..self.depth).rev() {
+            let ty = self.frame_type(&ancestor(&self.name, level))?;
+            let link = self.builder.build_struct_gep(ty, frame, 0, "link").unwrap();
+            frame = self.builder.build_load(self.pointer_type(), link, "frame").unwrap().into_pointer_value();
+        }
+        Ok(frame)
+    }
 
-        let entry = self.context.append_basic_block(function, "entry");
-        self.builder.position_at_end(entry);
+    fn zero(&self, address: PointerValue<'ctx>, ty: BasicTypeEnum<'ctx>) -> Result<(), String> {
+        let size = ty.size_of().ok_or("Slot ohne Größe")?;
+        let zero = self.context.i8_type().const_zero();
+        self.builder.build_memset(address, 1, zero, size).map_err(|e| e.to_string())?;
+        Ok(())
+    }
 
-        // Neuer Scope für lokale Variablen
-        self.symbol_table.enter_scope();
-        let old_vars = self.variables.clone();
-
-        // Parameter als lokale Variablen
-        if let Some(params) = &proc.params {
-            let mut param_idx = 0;
-            for section in &params.sections {
-                let param_type = self.resolve_llvm_type(&section.param_type)?;
-                for name in &section.names {
-                    let param_value = function.get_nth_param(param_idx as u32)
-                        .ok_or("Parameter nicht gefunden")?;
-
-                    if section.is_var {
-                        // VAR-Parameter ist bereits ein Pointer
-                        self.variables.insert(name.clone(), param_value.into_pointer_value());
-                        self.variable_types.insert(name.clone(), param_type);  // NEU: Typ speichern
-                    } else {
-                        // Normale Parameter: alloca + store
-                        let alloca = self.builder.build_alloca(param_type, name).unwrap();
-                        self.builder.build_store(alloca, param_value).unwrap();
-                        self.variables.insert(name.clone(), alloca);
-                        self.variable_types.insert(name.clone(), param_type);  // NEU: Typ speichern
-                    }
+    // Records ohne Deskriptor und nur die Felder von ty: das Ziel kann eine
+    // Erweiterung sein, deren Deskriptor bleiben muss
+    fn copy(&mut self, ty: TypeId, dest: PointerValue<'ctx>, source: PointerValue<'ctx>) -> Result<(), String> {
+        if !matches!(self.types.kind(ty), TypeKind::Record { .. }) {
+            let size = self.memory_type(ty)?.size_of().ok_or("Typ ohne Größe")?;
+            self.builder.build_memcpy(dest, 1, source, 1, size).map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        let structure = self.record_type(ty)?;
+        let fields: Vec<TypeId> = self.types.fields(ty).iter().map(|field| field.ty).collect();
+        for (index, field) in fields.into_iter().enumerate() {
+            let size = self.memory_type(field)?.size_of().ok_or("Feld ohne Größe")?;
+            let to = self.builder.build_struct_gep(structure, dest, index as u32 + 1, "").unwrap();
+            let from = self.builder.build_struct_gep(structure, source, index as u32 + 1, "").unwrap();
+            self.builder.build_memcpy(to, 1, from, 1, size).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
 
-                    param_idx += 1;
-                }
-            }
+    // Deskriptor eines Records: Zeiger auf den Deskriptor der Basis oder NIL
+    fn descriptor(&mut self, record: TypeId) -> PointerValue<'ctx> {
+        if let Some(global) = self.descriptors.get(&record) {
+            return global.as_pointer_value();
         }
+        let base = match self.types.kind(record) {
+            TypeKind::Record { base, .. } => *base,
+            _ => None,
+        };
+        let base = match base {
+            Some(base) => self.descriptor(base),
+            None => self.pointer_type().const_null(),
+        };
+        let name = format!("{}.desc", self.types.display(record));
+        let global = self.module.add_global(self.pointer_type(), None, &name);
+        global.set_linkage(Linkage::Private);
+        global.set_initializer(&base);
+        global.set_constant(true);
+        self.descriptors.insert(record, global);
+        global.as_pointer_value()
+    }
 
-        // Lokale Variablen
-        for var_decl in &proc.declarations.variables {
-            let var_type = self.resolve_llvm_type(&var_decl.var_type)?;
-            for name in &var_decl.names {
-                let alloca = self.builder.build_alloca(var_type, &name.name).unwrap();
-                self.variables.insert(name.name.clone(), alloca);
-                self.variable_types.insert(name.name.clone(), var_type);  // NEU: Typ speichern
+    fn init_descriptors(&mut self, address: PointerValue<'ctx>, ty: TypeId) -> Result<(), String> {
+        match self.types.kind(ty).clone() {
+            TypeKind::Record { .. } => {
+                let structure = self.record_type(ty)?;
+                let descriptor = self.descriptor(ty);
+                let tag = self.builder.build_struct_gep(structure, address, 0, "tag").unwrap();
+                self.builder.build_store(tag, descriptor).unwrap();
+                let fields: Vec<TypeId> = self.types.fields(ty).iter().map(|field| field.ty).collect();
+                for (index, field) in fields.into_iter().enumerate() {
+                    if contains_record(&self.types, field) {
+                        let inner = self.builder.build_struct_gep(structure, address, index as u32 + 1, "").unwrap();
+                        self.init_descriptors(inner, field)?;
+                    }
+                }
+            }
+            TypeKind::Array { length, element } if contains_record(&self.types, element) => {
+                let i64_type = self.context.i64_type();
+                let element_type = self.memory_type(element)?;
+                let before = self.builder.get_insert_block().unwrap();
+                let body = self.append_block("init");
+                let done = self.append_block("init.done");
+                self.builder.build_unconditional_branch(body).unwrap();
+
+                self.builder.position_at_end(body);
+                let phi = self.builder.build_phi(i64_type, "i").unwrap();
+                let index = phi.as_basic_value().into_int_value();
+                let inner = unsafe { self.builder.build_gep(element_type, address, &[index], "") }.unwrap();
+                self.init_descriptors(inner, element)?;
+                let next = self.builder.build_int_add(index, i64_type.const_int(1, false), "").unwrap();
+                let more = self
+                    .builder
+                    .build_int_compare(IntPredicate::ULT, next, i64_type.const_int(length as u64, false), "")
+                    .unwrap();
+                let latch = self.builder.get_insert_block().unwrap();
+                self.builder.build_conditional_branch(more, body, done).unwrap();
+                phi.add_incoming(&[(&i64_type.const_zero(), before), (&next, latch)]);
+
+                self.builder.position_at_end(done);
             }
+            _ => {}
         }
+        Ok(())
+    }
 
-        // Body
-        if let Some(body) = &proc.body {
-            self.generate_statement_sequence(body)?;
-        }
+    // ========================================================================
+    // Operatoren
+    // ========================================================================
 
-        // Return
-        if let Some(ret_expr) = &proc.return_expr {
-            let ret_val = self.generate_expression(ret_expr)?;
-            self.builder.build_return(Some(&ret_val)).unwrap();
-        } else if proc.params.is_none() || proc.params.as_ref().unwrap().return_type.is_none() {
-            self.builder.build_return(None).unwrap();
+    fn unary(&mut self, op: UnaryOp, operand: BasicValueEnum<'ctx>) -> BasicValueEnum<'ctx> {
+        let i64_type = self.context.i64_type();
+        match op {
+            UnaryOp::Neg => {
+                let overflow = format!("Überlauf bei '{}'", op.symbol());
+                self.checked("llvm.ssub.with.overflow", i64_type.const_zero(), operand.into_int_value(), &overflow).into()
+            }
+            UnaryOp::Abs => {
+                let x = operand.into_int_value();
+                let minimum = i64_type.const_int(i64::MIN as u64, true);
+                let overflow = self.builder.build_int_compare(IntPredicate::EQ, x, minimum, "").unwrap();
+                self.trap_if(overflow, &format!("Überlauf bei '{}'", op.symbol()), None, None);
+                let negative = self.builder.build_int_compare(IntPredicate::SLT, x, i64_type.const_zero(), "").unwrap();
+                let negated = self.builder.build_int_neg(x, "").unwrap();
+                self.builder.build_select(negative, negated, x, "").unwrap()
+            }
+            UnaryOp::FNeg => self.builder.build_float_neg(operand.into_float_value(), "").unwrap().into(),
+            UnaryOp::FAbs => self.intrinsic("llvm.fabs", &[operand]),
+            UnaryOp::Not | UnaryOp::Complement => self.builder.build_not(operand.into_int_value(), "").unwrap().into(),
+            UnaryOp::Odd => {
+                let x = operand.into_int_value();
+                let bit = self.builder.build_and(x, i64_type.const_int(1, false), "").unwrap();
+                self.builder.build_int_compare(IntPredicate::NE, bit, i64_type.const_zero(), "").unwrap().into()
+            }
+            UnaryOp::IntToReal => {
+                let x = operand.into_int_value();
+                self.builder.build_signed_int_to_float(x, self.context.f64_type(), "").unwrap().into()
+            }
+            UnaryOp::Floor => {
+                let x = operand.into_float_value();
+                let f64_type = self.context.f64_type();
+                let above = self
+                    .builder
+                    .build_float_compare(FloatPredicate::OGT, x, f64_type.const_float(-9223372036854775808.0), "")
+                    .unwrap();
+                let below = self
+                    .builder
+                    .build_float_compare(FloatPredicate::OLT, x, f64_type.const_float(9223372036854775808.0), "")
+                    .unwrap();
+                let inside = self.builder.build_and(above, below, "").unwrap();
+                let outside = self.builder.build_not(inside, "").unwrap();
+                self.trap_if(outside, &format!("Überlauf bei '{}'", op.symbol()), None, None);
+                let floor = self.intrinsic("llvm.floor", &[operand]).into_float_value();
+                self.builder.build_float_to_signed_int(floor, i64_type, "").unwrap().into()
+            }
+            UnaryOp::Ord => self.extend(operand.into_int_value(), false).into(),
+            UnaryOp::Chr => {
+                let x = operand.into_int_value();
+                let outside =
+                    self.builder.build_int_compare(IntPredicate::UGT, x, i64_type.const_int(255, false), "").unwrap();
+                self.trap_if(outside, "CHR(%lld) ist kein gültiges Zeichen", Some(x), None);
+                self.builder.build_int_truncate(x, self.context.i8_type(), "").unwrap().into()
+            }
         }
+    }
 
-        // Scope verlassen
-        self.symbol_table.exit_scope();
-        self.variables = old_vars;
-        self.current_function = None;
+    fn binary(&mut self, op: BinaryOp, left: BasicValueEnum<'ctx>, right: BasicValueEnum<'ctx>) -> BasicValueEnum<'ctx> {
+        let overflow = format!("Überlauf bei '{}'", op.symbol());
+        match op {
+            BinaryOp::Add | BinaryOp::Inc | BinaryOp::For => {
+                self.checked("llvm.sadd.with.overflow", left.into_int_value(), right.into_int_value(), &overflow).into()
+            }
+            BinaryOp::Sub | BinaryOp::Dec => {
+                self.checked("llvm.ssub.with.overflow", left.into_int_value(), right.into_int_value(), &overflow).into()
+            }
+            BinaryOp::Mul => {
+                self.checked("llvm.smul.with.overflow", left.into_int_value(), right.into_int_value(), &overflow).into()
+            }
+            BinaryOp::Div | BinaryOp::Mod => self.divide(op, left.into_int_value(), right.into_int_value()).into(),
+            BinaryOp::FAdd => {
+                self.builder.build_float_add(left.into_float_value(), right.into_float_value(), "").unwrap().into()
+            }
+            BinaryOp::FSub => {
+                self.builder.build_float_sub(left.into_float_value(), right.into_float_value(), "").unwrap().into()
+            }
+            BinaryOp::FMul => {
+                self.builder.build_float_mul(left.into_float_value(), right.into_float_value(), "").unwrap().into()
+            }
+            BinaryOp::FDiv => {
+                self.builder.build_float_div(left.into_float_value(), right.into_float_value(), "").unwrap().into()
+            }
+            BinaryOp::Union => self.builder.build_or(left.into_int_value(), right.into_int_value(), "").unwrap().into(),
+            BinaryOp::Difference => {
+                let complement = self.builder.build_not(right.into_int_value(), "").unwrap();
+                self.builder.build_and(left.into_int_value(), complement, "").unwrap().into()
+            }
+            BinaryOp::Intersection => {
+                self.builder.build_and(left.into_int_value(), right.into_int_value(), "").unwrap().into()
+            }
+            BinaryOp::SymmetricDifference => {
+                self.builder.build_xor(left.into_int_value(), right.into_int_value(), "").unwrap().into()
+            }
+            BinaryOp::In => {
+                let element = left.into_int_value();
+                let set = right.into_int_value();
+                let i32_type = self.context.i32_type();
+                let i64_type = self.context.i64_type();
+                let inside =
+                    self.builder.build_int_compare(IntPredicate::ULT, element, i64_type.const_int(32, false), "").unwrap();
+                let shift = self.builder.build_int_truncate(element, i32_type, "").unwrap();
+                let shift = self.builder.build_select(inside, shift, i32_type.const_zero(), "").unwrap().into_int_value();
+                let bits = self.builder.build_right_shift(set, shift, false, "").unwrap();
+                let bit = self.builder.build_and(bits, i32_type.const_int(1, false), "").unwrap();
+                let bit = self.builder.build_int_compare(IntPredicate::NE, bit, i32_type.const_zero(), "").unwrap();
+                self.builder.build_and(inside, bit, "").unwrap().into()
+            }
+            BinaryOp::Range => self.range(left.into_int_value(), right.into_int_value()).into(),
+            BinaryOp::Lsl | BinaryOp::Asr | BinaryOp::Ror => {
+                self.shift(op, left.into_int_value(), right.into_int_value()).into()
+            }
+        }
+    }
 
-        Ok(())
+    // INTEGER-Arithmetik mit Überlaufprüfung über llvm.*.with.overflow
+    fn checked(&mut self, intrinsic: &str, left: IntValue<'ctx>, right: IntValue<'ctx>, message: &str) -> IntValue<'ctx> {
+        let result = self.intrinsic(intrinsic, &[left.into(), right.into()]).into_struct_value();
+        let value = self.builder.build_extract_value(result, 0, "").unwrap().into_int_value();
+        let overflow = self.builder.build_extract_value(result, 1, "").unwrap().into_int_value();
+        self.trap_if(overflow, message, None, None);
+        value
     }
 
-    // ========================================================================
-    // Main-Funktion
-    // ========================================================================
+    // DIV und MOD runden zur negativen Unendlichkeit: sdiv/srem schneiden
+    // ab, bei Rest # 0 und verschiedenen Vorzeichen von Rest und Divisor
+    // wird korrigiert. b = -1 vermeidet den Überlauf von sdiv bei MIN.
+    fn divide(&mut self, op: BinaryOp, a: IntValue<'ctx>, b: IntValue<'ctx>) -> IntValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let zero = i64_type.const_zero();
+        let minus_one = i64_type.const_all_ones();
+        let by_zero = self.builder.build_int_compare(IntPredicate::EQ, b, zero, "").unwrap();
+        self.trap_if(by_zero, "Division durch 0", None, None);
+        let by_minus_one = self.builder.build_int_compare(IntPredicate::EQ, b, minus_one, "").unwrap();
+        if op == BinaryOp::Div {
+            let minimum = i64_type.const_int(i64::MIN as u64, true);
+            let minimum = self.builder.build_int_compare(IntPredicate::EQ, a, minimum, "").unwrap();
+            let overflow = self.builder.build_and(by_minus_one, minimum, "").unwrap();
+            self.trap_if(overflow, "Überlauf bei 'DIV'", None, None);
+        }
+        let divisor = self.builder.build_select(by_minus_one, i64_type.const_int(1, false), b, "").unwrap().into_int_value();
+        let remainder = self.builder.build_int_signed_rem(a, divisor, "").unwrap();
+        let nonzero = self.builder.build_int_compare(IntPredicate::NE, remainder, zero, "").unwrap();
+        let signs = self.builder.build_xor(remainder, divisor, "").unwrap();
+        let differ = self.builder.build_int_compare(IntPredicate::SLT, signs, zero, "").unwrap();
+        let adjust = self.builder.build_and(nonzero, differ, "").unwrap();
+        match op {
+            BinaryOp::Div => {
+                let quotient = self.builder.build_int_signed_div(a, divisor, "").unwrap();
+                let correction = self.builder.build_int_z_extend(adjust, i64_type, "").unwrap();
+                let floored = self.builder.build_int_sub(quotient, correction, "").unwrap();
+                let negated = self.builder.build_int_sub(zero, a, "").unwrap();
+                self.builder.build_select(by_minus_one, negated, floored, "").unwrap().into_int_value()
+            }
+            _ => {
+                let correction = self.builder.build_select(adjust, divisor, zero, "").unwrap().into_int_value();
+                self.builder.build_int_add(remainder, correction, "").unwrap()
+            }
+        }
+    }
 
-    fn generate_main(&mut self, module: &Module) -> Result<(), String> {
+    // Menge low..high; beide Grenzen müssen in 0..31 liegen
+    fn range(&mut self, low: IntValue<'ctx>, high: IntValue<'ctx>) -> IntValue<'ctx> {
         let i32_type = self.context.i32_type();
-        let fn_type = i32_type.fn_type(&[], false);
-        let function = self.module.add_function("main", fn_type, None);
+        let i64_type = self.context.i64_type();
+        for bound in [low, high] {
+            let outside =
+                self.builder.build_int_compare(IntPredicate::UGT, bound, i64_type.const_int(31, false), "").unwrap();
+            self.trap_if(outside, "Mengenelement %lld liegt außerhalb von 0..31", Some(bound), None);
+        }
+        let upper = self.builder.build_left_shift(i64_type.const_int(2, false), high, "").unwrap();
+        let lower = self.builder.build_left_shift(i64_type.const_int(1, false), low, "").unwrap();
+        let bits = self.builder.build_int_sub(upper, lower, "").unwrap();
+        let set = self.builder.build_int_truncate(bits, i32_type, "").unwrap();
+        let empty = self.builder.build_int_compare(IntPredicate::SGT, low, high, "").unwrap();
+        self.builder.build_select(empty, i32_type.const_zero(), set, "").unwrap().into_int_value()
+    }
 
-        let entry = self.context.append_basic_block(function, "entry");
-        self.builder.position_at_end(entry);
+    fn shift(&mut self, op: BinaryOp, a: IntValue<'ctx>, n: IntValue<'ctx>) -> IntValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let outside = self.builder.build_int_compare(IntPredicate::UGT, n, i64_type.const_int(63, false), "").unwrap();
+        let message = format!("Schiebeweite %lld für {} außerhalb von 0..63", op.symbol());
+        self.trap_if(outside, &message, Some(n), None);
+        match op {
+            BinaryOp::Lsl => {
+                let result = self.builder.build_left_shift(a, n, "").unwrap();
+                let back = self.builder.build_right_shift(result, n, true, "").unwrap();
+                let overflow = self.builder.build_int_compare(IntPredicate::NE, back, a, "").unwrap();
+                self.trap_if(overflow, &format!("Überlauf bei '{}'", op.symbol()), None, None);
+                result
+            }
+            BinaryOp::Asr => self.builder.build_right_shift(a, n, true, "").unwrap(),
+            _ => {
+                let low = self.builder.build_right_shift(a, n, false, "").unwrap();
+                let rest = self.builder.build_int_sub(i64_type.const_int(64, false), n, "").unwrap();
+                let rest = self.builder.build_and(rest, i64_type.const_int(63, false), "").unwrap();
+                let high = self.builder.build_left_shift(a, rest, "").unwrap();
+                self.builder.build_or(low, high, "").unwrap()
+            }
+        }
+    }
 
-        if let Some(body) = &module.body {
-            self.generate_statement_sequence(body)?;
+    fn compare(&self, op: CompareOp, ty: Type, left: BasicValueEnum<'ctx>, right: BasicValueEnum<'ctx>) -> IntValue<'ctx> {
+        match ty {
+            // # ist auch bei NaN wahr
+            Type::Real => {
+                let predicate = match op {
+                    CompareOp::Eq => FloatPredicate::OEQ,
+                    CompareOp::Ne => FloatPredicate::UNE,
+                    CompareOp::Lt => FloatPredicate::OLT,
+                    CompareOp::Le => FloatPredicate::OLE,
+                    CompareOp::Gt => FloatPredicate::OGT,
+                    CompareOp::Ge => FloatPredicate::OGE,
+                };
+                let (left, right) = (left.into_float_value(), right.into_float_value());
+                self.builder.build_float_compare(predicate, left, right, "").unwrap()
+            }
+            Type::Ptr => {
+                let i64_type = self.context.i64_type();
+                let left = self.builder.build_ptr_to_int(left.into_pointer_value(), i64_type, "").unwrap();
+                let right = self.builder.build_ptr_to_int(right.into_pointer_value(), i64_type, "").unwrap();
+                self.builder.build_int_compare(signed_predicate(op), left, right, "").unwrap()
+            }
+            Type::Int => {
+                let (left, right) = (left.into_int_value(), right.into_int_value());
+                self.builder.build_int_compare(signed_predicate(op), left, right, "").unwrap()
+            }
+            Type::Bool | Type::Char | Type::Set => {
+                let predicate = match op {
+                    CompareOp::Eq => IntPredicate::EQ,
+                    CompareOp::Ne => IntPredicate::NE,
+                    CompareOp::Lt => IntPredicate::ULT,
+                    CompareOp::Le => IntPredicate::ULE,
+                    CompareOp::Gt => IntPredicate::UGT,
+                    CompareOp::Ge => IntPredicate::UGE,
+                };
+                let (left, right) = (left.into_int_value(), right.into_int_value());
+                self.builder.build_int_compare(predicate, left, right, "").unwrap()
+            }
         }
+    }
 
-        self.builder.build_return(Some(&i32_type.const_zero())).unwrap();
+    fn pointers_equal(&self, left: PointerValue<'ctx>, right: PointerValue<'ctx>) -> IntValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let left = self.builder.build_ptr_to_int(left, i64_type, "").unwrap();
+        let right = self.builder.build_ptr_to_int(right, i64_type, "").unwrap();
+        self.builder.build_int_compare(IntPredicate::EQ, left, right, "").unwrap()
+    }
 
-        Ok(())
+    // Auf INTEGER erweitert; CHAR und SET ohne, INTEGER mit Vorzeichen
+    fn extend(&self, value: IntValue<'ctx>, signed: bool) -> IntValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        if value.get_type().get_bit_width() == 64 {
+            value
+        } else if signed {
+            self.builder.build_int_s_extend(value, i64_type, "").unwrap()
+        } else {
+            self.builder.build_int_z_extend(value, i64_type, "").unwrap()
+        }
+    }
+
+    // Überladenes Intrinsic für die Typen der Operanden
+    fn intrinsic(&self, name: &str, arguments: &[BasicValueEnum<'ctx>]) -> BasicValueEnum<'ctx> {
+        let intrinsic = Intrinsic::find(name).expect("LLVM kennt das Intrinsic");
+        let ty = arguments[0].get_type();
+        let function = intrinsic.get_declaration(&self.module, &[ty]).expect("Intrinsic für den Typ");
+        let arguments: Vec<BasicMetadataValueEnum<'ctx>> = arguments.iter().map(|&argument| argument.into()).collect();
+        let call = self.builder.build_call(function, &arguments, "").unwrap();
+        call.try_as_basic_value().left().expect("Intrinsic mit Ergebnis")
     }
 
     // ========================================================================
-    // Statements
+    // Ausgabe (Out)
     // ========================================================================
 
-    fn generate_statement_sequence(&mut self, statements: &[Statement]) -> Result<(), String> {
-        for stmt in statements {
-            self.generate_statement(stmt)?;
+    fn output(&mut self, procedure: LibraryProcedure, arguments: &[BasicValueEnum<'ctx>]) -> Result<(), String> {
+        let i32_type = self.context.i32_type();
+        let argument = |index: usize| {
+            arguments.get(index).copied().ok_or_else(|| format!("Argument {} von {:?} fehlt", index, procedure))
+        };
+        let width = |generator: &Self, index: usize| -> Result<BasicMetadataValueEnum<'ctx>, String> {
+            let width = argument(index)?.into_int_value();
+            Ok(generator.builder.build_int_truncate(width, i32_type, "").unwrap().into())
+        };
+        match procedure {
+            LibraryProcedure::OutOpen => {}
+            LibraryProcedure::OutLn => {
+                self.builder.build_call(self.runtime("putchar"), &[i32_type.const_int(10, false).into()], "").unwrap();
+            }
+            LibraryProcedure::OutChar => {
+                let ch = self.builder.build_int_z_extend(argument(0)?.into_int_value(), i32_type, "").unwrap();
+                self.builder.build_call(self.runtime("putchar"), &[ch.into()], "").unwrap();
+            }
+            LibraryProcedure::OutString => {
+                let format = BasicMetadataValueEnum::from(self.string(b"%.*s"));
+                let length = width(self, 1)?;
+                self.builder.build_call(self.runtime("printf"), &[format, length, argument(0)?.into()], "").unwrap();
+            }
+            LibraryProcedure::OutInt => {
+                let format = BasicMetadataValueEnum::from(self.string(b"%*lld"));
+                let width = width(self, 1)?;
+                self.builder.build_call(self.runtime("printf"), &[format, width, argument(0)?.into()], "").unwrap();
+            }
+            LibraryProcedure::OutReal => {
+                let format = BasicMetadataValueEnum::from(self.string(b"%*.6f"));
+                let width = width(self, 1)?;
+                self.builder.build_call(self.runtime("printf"), &[format, width, argument(0)?.into()], "").unwrap();
+            }
         }
         Ok(())
     }
 
-    fn generate_statement(&mut self, stmt: &Statement) -> Result<(), String> {
-        match stmt {
-            Statement::Empty => Ok(()),
-            Statement::Assignment { target, value } => {
-                let ptr = self.generate_designator_ptr(target)?;
-                let val = self.generate_expression(value)?;
-                self.builder.build_store(ptr, val).unwrap();
-                Ok(())
-            }
-            Statement::ProcedureCall { designator, arguments } => {
-                let func_name = self.mangle_name(&designator.base.name);
-                let function = self.module
-                    .get_function(&func_name)
-                    .ok_or(format!("Funktion nicht gefunden: {}", func_name))?;
-
-                let mut args: Vec<BasicMetadataValueEnum> = Vec::new();
-                for arg in arguments {
-                    let arg_val = self.generate_expression(arg)?;
-                    args.push(arg_val.into());
-                }
-
-                self.builder.build_call(function, &args, "call").unwrap();
-                Ok(())
-            }
-            Statement::If { condition, then_body, elsif_parts, else_body } => {
-                let function = self.current_function.ok_or("Keine aktuelle Funktion")?;
-
-                let cond_val = self.generate_expression(condition)?;
-                let cond_bool = cond_val.into_int_value();
-
-                let then_bb = self.context.append_basic_block(function, "then");
-                let merge_bb = self.context.append_basic_block(function, "ifcont");
-                let else_bb = if !elsif_parts.is_empty() || else_body.is_some() {
-                    self.context.append_basic_block(function, "else")
-                } else {
-                    merge_bb
-                };
+    // ========================================================================
+    // Laufzeitfehler
+    // ========================================================================
 
-                self.builder.build_conditional_branch(cond_bool, then_bb, else_bb).unwrap();
+    // Bricht an der Zeile des Befehls in Arbeit ab; a und b füllen die
+    // Platzhalter der Meldung
+    fn trap(&mut self, message: &str, a: Option<IntValue<'ctx>>, b: Option<IntValue<'ctx>>) {
+        let i64_type = self.context.i64_type();
+        let line = i64_type.const_int(self.line as u64, false);
+        let procedure = self.procedure.clone();
+        let procedure = self.string(procedure.as_bytes());
+        let message = self.string(message.as_bytes());
+        let a = a.unwrap_or(i64_type.const_zero());
+        let b = b.unwrap_or(i64_type.const_zero());
+        let arguments: [BasicMetadataValueEnum<'ctx>; 5] = [line.into(), procedure.into(), message.into(), a.into(), b.into()];
+        self.builder.build_call(self.runtime(TRAP), &arguments, "").unwrap();
+        self.builder.build_unreachable().unwrap();
+    }
 
-                // Then-Block
-                self.builder.position_at_end(then_bb);
-                self.generate_statement_sequence(then_body)?;
-                self.builder.build_unconditional_branch(merge_bb).unwrap();
+    fn trap_if(&mut self, condition: IntValue<'ctx>, message: &str, a: Option<IntValue<'ctx>>, b: Option<IntValue<'ctx>>) {
+        let trap = self.append_block("trap");
+        let next = self.append_block("ok");
+        self.builder.build_conditional_branch(condition, trap, next).unwrap();
+        self.builder.position_at_end(trap);
+        self.trap(message, a, b);
+        self.builder.position_at_end(next);
+    }
 
-                // ELSIF/ELSE-Blocks (vereinfacht)
-                if !elsif_parts.is_empty() || else_body.is_some() {
-                    self.builder.position_at_end(else_bb);
-                    if let Some(else_stmts) = else_body {
-                        self.generate_statement_sequence(else_stmts)?;
-                    }
-                    self.builder.build_unconditional_branch(merge_bb).unwrap();
-                }
+    // ========================================================================
+    // Hilfsfunktionen
+    // ========================================================================
 
-                self.builder.position_at_end(merge_bb);
-                Ok(())
-            }
-            Statement::While { condition, body, .. } => {
-                let function = self.current_function.ok_or("Keine aktuelle Funktion")?;
-
-                let cond_bb = self.context.append_basic_block(function, "whilecond");
-                let body_bb = self.context.append_basic_block(function, "whilebody");
-                let merge_bb = self.context.append_basic_block(function, "whilecont");
-
-                self.builder.build_unconditional_branch(cond_bb).unwrap();
-
-                // Condition
-                self.builder.position_at_end(cond_bb);
-                let cond_val = self.generate_expression(condition)?;
-                let cond_bool = cond_val.into_int_value();
-                self.builder.build_conditional_branch(cond_bool, body_bb, merge_bb).unwrap();
-
-                // Body
-                self.builder.position_at_end(body_bb);
-                self.generate_statement_sequence(body)?;
-                self.builder.build_unconditional_branch(cond_bb).unwrap();
-
-                self.builder.position_at_end(merge_bb);
-                Ok(())
-            }
-            Statement::For { variable, start, end, step, body } => {
-                let function = self.current_function.ok_or("Keine aktuelle Funktion")?;
-
-                // WICHTIG: Pointer KOPIEREN (dereferenzieren beim get)
-                let var_ptr = *self.variables.get(variable)
-                    .ok_or(format!("Variable nicht gefunden: {}", variable))?;
-
-                // Initialisierung
-                let start_val = self.generate_expression(start)?;
-                self.builder.build_store(var_ptr, start_val).unwrap();
-
-                let cond_bb = self.context.append_basic_block(function, "forcond");
-                let body_bb = self.context.append_basic_block(function, "forbody");
-                let incr_bb = self.context.append_basic_block(function, "forincr");
-                let merge_bb = self.context.append_basic_block(function, "forcont");
-
-                self.builder.build_unconditional_branch(cond_bb).unwrap();
-
-                // Condition
-                self.builder.position_at_end(cond_bb);
-                let var_val = self.builder.build_load(self.context.i64_type(), var_ptr, variable).unwrap();
-                let end_val = self.generate_expression(end)?;
-                let cmp = self.builder.build_int_compare(
-                    IntPredicate::SLE,
-                    var_val.into_int_value(),
-                    end_val.into_int_value(),
-                    "forcmp"
-                ).unwrap();
-                self.builder.build_conditional_branch(cmp, body_bb, merge_bb).unwrap();
-
-                // Body
-                self.builder.position_at_end(body_bb);
-                self.generate_statement_sequence(body)?;
-                self.builder.build_unconditional_branch(incr_bb).unwrap();
-
-                // Increment
-                self.builder.position_at_end(incr_bb);
-                let current = self.builder.build_load(self.context.i64_type(), var_ptr, "current").unwrap();
-                let step_val = if let Some(s) = step {
-                    self.generate_expression(s)?
-                } else {
-                    self.context.i64_type().const_int(1, false).into()
-                };
-                let next = self.builder.build_int_add(
-                    current.into_int_value(),
-                    step_val.into_int_value(),
-                    "next"
-                ).unwrap();
-                self.builder.build_store(var_ptr, next).unwrap();
-                self.builder.build_unconditional_branch(cond_bb).unwrap();
+    fn append_block(&self, name: &str) -> BasicBlock<'ctx> {
+        let function = self.builder.get_insert_block().and_then(|block| block.get_parent()).expect("Builder steht in einer Funktion");
+        self.context.append_basic_block(function, name)
+    }
 
-                self.builder.position_at_end(merge_bb);
-                Ok(())
-            }
-            _ => Err("Statement nicht implementiert".to_string()),
+    // Zeile für die folgenden Befehle (nur mit Debuginformationen)
+    fn set_line(&self, line: usize) {
+        if let Some(debug) = &self.debug {
+            self.builder.set_current_debug_location(debug.location(self.context, line));
         }
     }
 
+    pub fn write_to_file(&self, filename: &str) -> Result<(), String> {
+        self.module
+            .print_to_file(filename)
+            .map_err(|e| e.to_string())
+    }
+
     // ========================================================================
-    // Ausdrücke
+    // Optimierung und Ausgabe
     // ========================================================================
 
-    fn generate_expression(&mut self, expr: &Expression) -> Result<BasicValueEnum<'ctx>, String> {
-        match expr {
-            Expression::IntegerLiteral(val) => {
-                Ok(self.context.i64_type().const_int(*val as u64, false).into())
-            }
-            Expression::RealLiteral(val) => {
-                Ok(self.context.f64_type().const_float(*val).into())
-            }
-            Expression::BooleanLiteral(val) => {
-                Ok(self.context.bool_type().const_int(*val as u64, false).into())
-            }
-            Expression::Designator(designator) => {
-                let ptr = self.generate_designator_ptr(designator)?;
-                // Typ aus der Symbol-Tabelle oder aus dem Designator ableiten
-                // Für INTEGER verwenden wir i64 als Standard
-                let var_type = self.context.i64_type(); // Vereinfacht - sollte aus Type-Info kommen
-                Ok(self.builder.build_load(var_type, ptr, "load").unwrap())
-            }
-            Expression::FunctionCall { designator, arguments } => {
-                let func_name = self.mangle_name(&designator.base.name);
-                let function = self.module
-                    .get_function(&func_name)
-                    .ok_or(format!("Funktion nicht gefunden: {}", func_name))?;
-
-                let mut args: Vec<BasicMetadataValueEnum> = Vec::new();
-                for arg in arguments {
-                    let arg_val = self.generate_expression(arg)?;
-                    args.push(arg_val.into());
-                }
+    // Setzt Triple und Datenlayout der Zielmaschine, prüft das Modul und
+    // führt die Standard-Pipeline von LLVM aus (default<O0> .. default<O3>)
+    pub fn optimize(&self, machine: &TargetMachine, opt_level: u8) -> Result<(), String> {
+        self.module.set_triple(&machine.get_triple());
+        self.module.set_data_layout(&machine.get_target_data().get_data_layout());
+        self.module.verify().map_err(|e| format!("Ungültiges LLVM-Modul: {}", e))?;
+
+        let options = PassBuilderOptions::create();
+        options.set_verify_each(cfg!(debug_assertions));
+        options.set_loop_vectorization(opt_level >= 2);
+        options.set_loop_slp_vectorization(opt_level >= 2);
+        options.set_loop_unrolling(opt_level >= 2);
+        options.set_merge_functions(opt_level >= 2);
+        let passes = format!("default<O{}>", opt_level.min(3));
+        self.module.run_passes(&passes, machine, options).map_err(|e| e.to_string())
+    }
 
-                let call = self.builder.build_call(function, &args, "call").unwrap();
-                call.try_as_basic_value()
-                    .left()
-                    .ok_or("Funktion gibt keinen Wert zurück".to_string())
-            }
-            Expression::Binary { left, op, right } => {
-                let lhs = self.generate_expression(left)?;
-                let rhs = self.generate_expression(right)?;
-
-                match op {
-                    BinaryOp::Add => {
-                        if lhs.is_int_value() {
-                            Ok(self.builder.build_int_add(
-                                lhs.into_int_value(),
-                                rhs.into_int_value(),
-                                "add"
-                            ).unwrap().into())
-                        } else {
-                            Ok(self.builder.build_float_add(
-                                lhs.into_float_value(),
-                                rhs.into_float_value(),
-                                "fadd"
-                            ).unwrap().into())
-                        }
-                    }
-                    BinaryOp::Sub => {
-                        if lhs.is_int_value() {
-                            Ok(self.builder.build_int_sub(
-                                lhs.into_int_value(),
-                                rhs.into_int_value(),
-                                "sub"
-                            ).unwrap().into())
-                        } else {
-                            Ok(self.builder.build_float_sub(
-                                lhs.into_float_value(),
-                                rhs.into_float_value(),
-                                "fsub"
-                            ).unwrap().into())
-                        }
-                    }
-                    BinaryOp::Mul => {
-                        if lhs.is_int_value() {
-                            Ok(self.builder.build_int_mul(
-                                lhs.into_int_value(),
-                                rhs.into_int_value(),
-                                "mul"
-                            ).unwrap().into())
-                        } else {
-                            Ok(self.builder.build_float_mul(
-                                lhs.into_float_value(),
-                                rhs.into_float_value(),
-                                "fmul"
-                            ).unwrap().into())
-                        }
-                    }
-                    BinaryOp::IntDiv => {
-                        Ok(self.builder.build_int_signed_div(
-                            lhs.into_int_value(),
-                            rhs.into_int_value(),
-                            "div"
-                        ).unwrap().into())
-                    }
-                    BinaryOp::Equal => {
-                        if lhs.is_int_value() {
-                            Ok(self.builder.build_int_compare(
-                                IntPredicate::EQ,
-                                lhs.into_int_value(),
-                                rhs.into_int_value(),
-                                "eq"
-                            ).unwrap().into())
-                        } else {
-                            Ok(self.builder.build_float_compare(
-                                FloatPredicate::OEQ,
-                                lhs.into_float_value(),
-                                rhs.into_float_value(),
-                                "feq"
-                            ).unwrap().into())
-                        }
-                    }
-                    BinaryOp::Less => {
-                        if lhs.is_int_value() {
-                            Ok(self.builder.build_int_compare(
-                                IntPredicate::SLT,
-                                lhs.into_int_value(),
-                                rhs.into_int_value(),
-                                "lt"
-                            ).unwrap().into())
-                        } else {
-                            Ok(self.builder.build_float_compare(
-                                FloatPredicate::OLT,
-                                lhs.into_float_value(),
-                                rhs.into_float_value(),
-                                "flt"
-                            ).unwrap().into())
-                        }
-                    }
-                    BinaryOp::LessEqual => {
-                        if lhs.is_int_value() {
-                            Ok(self.builder.build_int_compare(
-                                IntPredicate::SLE,
-                                lhs.into_int_value(),
-                                rhs.into_int_value(),
-                                "le"
-                            ).unwrap().into())
-                        } else {
-                            Ok(self.builder.build_float_compare(
-                                FloatPredicate::OLE,
-                                lhs.into_float_value(),
-                                rhs.into_float_value(),
-                                "fle"
-                            ).unwrap().into())
-                        }
-                    }
-                    _ => Err(format!("Operator {:?} nicht implementiert", op)),
-                }
-            }
-            _ => Err("Ausdruck nicht implementiert".to_string()),
-        }
-    }
-
-    fn generate_designator_ptr(&mut self, designator: &Designator) -> Result<PointerValue<'ctx>, String> {
-        let base_name = &designator.base.name;
-        let mut ptr = self.variables
-            .get(base_name)
-            .ok_or(format!("Variable nicht gefunden: {}", base_name))?
-            .clone();
-        
-        // Typ der Variable holen
-        let mut current_type = self.variable_types
-            .get(base_name)
-            .ok_or(format!("Typ für Variable nicht gefunden: {}", base_name))?
-            .clone();
-
-        for selector in &designator.selectors {
-            match selector {
-                Selector::Field(field_name) => {
-                    // Für Struct-Felder: struct_gep verwenden
-                    let field_idx = 0; // TODO: Richtigen Index aus Typ-Info ermitteln
-                    
-                    ptr = self.builder.build_struct_gep(
-                        current_type,
-                        ptr,
-                        field_idx,
-                        field_name
-                    ).map_err(|e| format!("build_struct_gep Fehler: {:?}", e))?;
-                    
-                    // Typ aktualisieren (vereinfacht: nehmen wir an es ist ein Struct)
-                    if let BasicTypeEnum::StructType(struct_type) = current_type {
-                        if let Some(field_type) = struct_type.get_field_type_at_index(field_idx) {
-                            current_type = field_type;
-                        }
-                    }
-                }
-                Selector::Index(indices) => {
-                    for index_expr in indices {
-                        let index = self.generate_expression(index_expr)?;
-                        let zero = self.context.i64_type().const_zero();
-                        
-                        ptr = unsafe {
-                            self.builder.build_gep(
-                                current_type,
-                                ptr,
-                                &[zero, index.into_int_value()],
-                                "arrayidx"
-                            ).map_err(|e| format!("build_gep Fehler: {:?}", e))?
-                        };
-                        
-                        // Typ aktualisieren (Element-Typ des Arrays)
-                        if let BasicTypeEnum::ArrayType(array_type) = current_type {
-                            current_type = array_type.get_element_type();
-                        }
-                    }
+    pub fn emit(&self, machine: &TargetMachine, emit: Emit, path: &Path) -> Result<(), String> {
+        match emit {
+            Emit::Object => machine.write_to_file(&self.module, FileType::Object, path).map_err(|e| e.to_string()),
+            Emit::Assembly => machine.write_to_file(&self.module, FileType::Assembly, path).map_err(|e| e.to_string()),
+            Emit::LlvmIr => self.module.print_to_file(path).map_err(|e| e.to_string()),
+            Emit::Bitcode => {
+                if self.module.write_bitcode_to_path(path) {
+                    Ok(())
+                } else {
+                    Err(format!("Kann Bitcode nicht nach {} schreiben", path.display()))
                 }
-                _ => return Err("Selector nicht implementiert".to_string()),
             }
         }
+    }
+}
 
-        Ok(ptr)
+// Namen wie im C-Backend; der Modulrumpf ist main
+fn function_name(function: &Function) -> String {
+    match function.depth {
+        0 => "main".to_string(),
+        _ => format!("oberon_{}", function.name),
     }
+}
 
-    // ========================================================================
-    // Built-in Funktionen
-    // ========================================================================
+fn procedure_name(name: &str) -> String {
+    format!("oberon_{}", name)
+}
 
-    fn generate_write_int(&mut self) -> Result<(), String> {
-        // Funktion aus Modul holen (wurde bereits in declare_function deklariert)
-        let function = self.module
-            .get_function("oberon_WriteInt")
-            .ok_or("WriteInt nicht deklariert")?;
+fn global_name(name: &str) -> String {
+    format!("oberon_{}", name)
+}
 
-        let entry = self.context.append_basic_block(function, "entry");
-        self.builder.position_at_end(entry);
+fn slot_name(slot: &Slot) -> &str {
+    slot.name.as_deref().unwrap_or("tmp")
+}
+
+// Name der umgebenden Prozedur auf Tiefe `depth` ("P.Q.R", 1 -> "P")
+fn ancestor(name: &str, depth: usize) -> String {
+    name.split('.').take(depth).collect::<Vec<_>>().join(".")
+}
 
-        // printf("%lld", n)
-        let format_str = self.create_string_literal("%lld");
-        let printf = self.module.get_function("printf").unwrap();
-        let n = function.get_nth_param(0).unwrap();
+// Importierte Namen ("Modul.Name") fehlen im Modul
+fn imported(what: &str, name: &str) -> String {
+    format!("{} {} ist nicht definiert; das LLVM-Backend übersetzt nur einzelne Module", what, name)
+}
 
-        self.builder.build_call(
-            printf,
-            &[format_str.into(), n.into()],
-            "printf_call"
-        ).unwrap();
+fn signed_predicate(op: CompareOp) -> IntPredicate {
+    match op {
+        CompareOp::Eq => IntPredicate::EQ,
+        CompareOp::Ne => IntPredicate::NE,
+        CompareOp::Lt => IntPredicate::SLT,
+        CompareOp::Le => IntPredicate::SLE,
+        CompareOp::Gt => IntPredicate::SGT,
+        CompareOp::Ge => IntPredicate::SGE,
+    }
+}
 
-        self.builder.build_return(None).unwrap();
-        Ok(())
+// Typen und Zeilen der Prozeduren, Schlüssel wie ir::Function::name
+fn collect_signatures(prefix: &str, declarations: &Declarations, signatures: &mut HashMap<String, Signature>) {
+    for procedure in &declarations.procedures {
+        let name = match prefix.is_empty() {
+            true => procedure.name.clone(),
+            false => format!("{}.{}", prefix, procedure.name),
+        };
+        let lines = procedure
+            .params
+            .iter()
+            .map(|param| param.line)
+            .chain(procedure.declarations.variables.iter().map(|variable| variable.line))
+            .collect();
+        let params = procedure.params.iter().map(|param| param.param_type).collect();
+        signatures.insert(name.clone(), Signature { params, result: procedure.return_type, lines });
+        collect_signatures(&name, &procedure.declarations, signatures);
     }
+}
 
-    fn generate_write_ln(&mut self) -> Result<(), String> {
-        // Funktion aus Modul holen (wurde bereits in declare_function deklariert)
-        let function = self.module
-            .get_function("oberon_WriteLn")
-            .ok_or("WriteLn nicht deklariert")?;
+// ============================================================================
+// Zielmaschine und Binden
+// ============================================================================
+
+// Ausgabeformate für --emit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    Object,
+    Assembly,
+    LlvmIr,
+    Bitcode,
+}
 
-        let entry = self.context.append_basic_block(function, "entry");
-        self.builder.position_at_end(entry);
+impl Emit {
+    pub fn parse(name: &str) -> Option<Emit> {
+        match name {
+            "obj" => Some(Emit::Object),
+            "asm" => Some(Emit::Assembly),
+            "llvm-ir" => Some(Emit::LlvmIr),
+            "bc" => Some(Emit::Bitcode),
+            _ => None,
+        }
+    }
 
-        // puts("")
-        let newline = self.create_string_literal("");
-        let puts = self.module.get_function("puts").unwrap();
+    pub fn extension(self) -> &'static str {
+        match self {
+            Emit::Object => "o",
+            Emit::Assembly => "s",
+            Emit::LlvmIr => "ll",
+            Emit::Bitcode => "bc",
+        }
+    }
+}
 
-        self.builder.build_call(
-            puts,
-            &[newline.into()],
-            "puts_call"
-        ).unwrap();
+// Zielmaschine für ein Triple wie "x86_64-unknown-linux-gnu"; ohne Triple
+// für den Host mit dessen Prozessor und Erweiterungen. Erzeugt
+// positionsunabhängigen Code, wie ihn die Linker der Systeme erwarten.
+pub fn target_machine(triple: Option<&str>, opt_level: u8) -> Result<TargetMachine, String> {
+    Target::initialize_all(&InitializationConfig::default());
+    let (triple, cpu, features) = match triple {
+        Some(triple) => (TargetTriple::create(triple), "generic".to_string(), String::new()),
+        None => (
+            TargetMachine::get_default_triple(),
+            TargetMachine::get_host_cpu_name().to_string(),
+            TargetMachine::get_host_cpu_features().to_string(),
+        ),
+    };
+    let name = triple.as_str().to_string_lossy().into_owned();
+    let target = Target::from_triple(&triple).map_err(|e| format!("Unbekanntes Ziel {}: {}", name, e))?;
+    let level = match opt_level {
+        0 => OptimizationLevel::None,
+        1 => OptimizationLevel::Less,
+        2 => OptimizationLevel::Default,
+        _ => OptimizationLevel::Aggressive,
+    };
+    target
+        .create_target_machine(&triple, &cpu, &features, level, RelocMode::PIC, CodeModel::Default)
+        .ok_or_else(|| format!("Keine Zielmaschine für {}", name))
+}
 
-        self.builder.build_return(None).unwrap();
+// Bindet Objektdateien mit dem C-Compiler des Systems ($CC, sonst cc) zu
+// einem Programm; printf und die Mathematikfunktionen kommen aus der
+// C-Bibliothek. Für ein fremdes Ziel muss der Compiler --target verstehen
+// (clang).
+pub fn link(objects: &[&Path], output: &Path, triple: Option<&str>) -> Result<(), String> {
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let mut command = Command::new(&compiler);
+    if let Some(triple) = triple {
+        command.arg(format!("--target={}", triple));
+    }
+    command.args(objects).arg("-o").arg(output).arg("-lm");
+    let result = command.output().map_err(|e| format!("Kann {} nicht starten: {}", compiler, e))?;
+    if result.status.success() {
         Ok(())
+    } else {
+        Err(format!("{} ist fehlgeschlagen:\n{}", compiler, String::from_utf8_lossy(&result.stderr)))
     }
+}
 
-    fn create_string_literal(&self, text: &str) -> PointerValue<'ctx> {
-        // String als globale Konstante erstellen
-        let string_val = self.context.const_string(text.as_bytes(), true);
-        let global = self.module.add_global(string_val.get_type(), None, ".str");
-        global.set_linkage(inkwell::module::Linkage::Private);
-        global.set_initializer(&string_val);
-        global.set_constant(true);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::checked;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const SOURCE: &str = "MODULE T;
+        IMPORT Out;
+        VAR i, sum: INTEGER;
+
+        PROCEDURE Square(n: INTEGER): INTEGER;
+        BEGIN RETURN n * n
+        END Square;
+
+        BEGIN
+          sum := 0;
+          FOR i := 1 TO 10 DO sum := sum + Square(i) END;
+          Out.Int(sum, 0); Out.Ln;
+          Out.Int(-7 DIV 2, 0); Out.Char(\" \"); Out.Int(-7 MOD 2, 0); Out.Ln
+        END T.";
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("together-llvm-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 
-        unsafe {
-            self.builder.build_gep(
-                string_val.get_type(),
-                global.as_pointer_value(),
-                &[self.context.i32_type().const_zero(), self.context.i32_type().const_zero()],
-                "str_ptr"
-            ).unwrap()
+    // Übersetzt, bindet mit cc und liefert stdout; None ohne C-Compiler
+    fn run(name: &str, source: &str, opt_level: u8) -> Option<String> {
+        if Command::new("cc").arg("--version").output().is_err() {
+            return None;
         }
+        let context = Context::create();
+        let mut generator = LLVMGenerator::new(&context, "T");
+        generator.generate(&checked(source)).unwrap();
+        let machine = target_machine(None, opt_level).unwrap();
+        generator.optimize(&machine, opt_level).unwrap();
+
+        let dir = scratch(name);
+        let object = dir.join("T.o");
+        let program = dir.join("T");
+        generator.emit(&machine, Emit::Object, &object).unwrap();
+        link(&[&object], &program, None).unwrap();
+        let output = Command::new(&program).output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        Some(String::from_utf8(output.stdout).unwrap())
     }
 
-    // ========================================================================
-    // Hilfsfunktionen
-    // ========================================================================
+    #[test]
+    fn test_run() {
+        for opt_level in 0..=3 {
+            if let Some(output) = run(&format!("run-O{}", opt_level), SOURCE, opt_level) {
+                assert_eq!(output, "385\n-4 1\n", "-O{}", opt_level);
+            }
+        }
+    }
 
-    fn mangle_name(&self, name: &str) -> String {
-        format!("oberon_{}", name)
+    #[test]
+    fn test_records() {
+        let source = "MODULE T;
+            IMPORT Out;
+            TYPE
+              Shape = POINTER TO ShapeDesc;
+              ShapeDesc = RECORD x, y: INTEGER END;
+              Circle = POINTER TO CircleDesc;
+              CircleDesc = RECORD (ShapeDesc) r: INTEGER; name: ARRAY 8 OF CHAR END;
+            VAR s: Shape; c: Circle; d: CircleDesc; shapes: ARRAY 3 OF ShapeDesc;
+            BEGIN
+              NEW(c); c.x := 1; c.y := 2; c.r := 3; c.name := \"Kreis\";
+              s := c; s^ := shapes[1]; d := c^;
+              IF s IS Circle THEN Out.String(s(Circle).name) END;
+              Out.Int(d.x + d.y, 2); Out.Int(d.r, 2); Out.Ln
+            END T.";
+        if let Some(output) = run("records", source, 0) {
+            assert_eq!(output, "Kreis 0 3\n");
+        }
     }
 
-    pub fn write_to_file(&self, filename: &str) -> Result<(), String> {
-        self.module
-            .print_to_file(filename)
-            .map_err(|e| e.to_string())
+    // Die Typprüfung begrenzt jede Dimension auf 2^31 - 1; hier ein Typ,
+    // den nur das IR selbst bauen könnte
+    #[test]
+    fn test_array_too_large() {
+        let context = Context::create();
+        let mut generator = LLVMGenerator::new(&context, "T");
+        let array = generator.types.add(TypeKind::Array { length: 1 << 32, element: TypeId::CHAR });
+        let error = generator.memory_type(array).unwrap_err();
+        assert!(error.contains("zu groß"), "{}", error);
+    }
+
+    // -g: das Modul muss die Prüfung von LLVM bestehen, jede übersetzte
+    // Prozedur ein Unterprogramm und ihre Befehle Zeilen tragen
+    #[test]
+    fn test_debug_info() {
+        let dir = scratch("debug");
+        let source = dir.join("T.Mod");
+        fs::write(&source, SOURCE).unwrap();
+        let context = Context::create();
+        let mut generator = LLVMGenerator::with_debug_info(&context, "T", Some(&source));
+        let ir = generator.generate(&checked(SOURCE)).unwrap();
+        generator.module.verify().unwrap();
+
+        for name in ["main", "oberon_Square"] {
+            let function = generator.module.get_function(name).unwrap();
+            assert!(function.get_subprogram().is_some(), "{}", name);
+        }
+        assert!(ir.contains("!DISubprogram(name: \"Square\""), "{}", ir);
+        assert!(ir.contains("!DILocation(line: 6,"), "{}", ir);
+        assert!(ir.contains("!DILocalVariable(name: \"n\", arg: 1"), "{}", ir);
+        assert!(ir.contains("!DIGlobalVariable(name: \"sum\""), "{}", ir);
+
+        let machine = target_machine(None, 0).unwrap();
+        generator.optimize(&machine, 0).unwrap();
+        generator.emit(&machine, Emit::Object, &dir.join("T.o")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_emit() {
+        let context = Context::create();
+        let mut generator = LLVMGenerator::new(&context, "T");
+        generator.generate(&checked(SOURCE)).unwrap();
+        let machine = target_machine(None, 2).unwrap();
+        generator.optimize(&machine, 2).unwrap();
+
+        let dir = scratch("emit");
+        for emit in [Emit::Object, Emit::Assembly, Emit::LlvmIr, Emit::Bitcode] {
+            let path = dir.join(format!("T.{}", emit.extension()));
+            generator.emit(&machine, emit, &path).unwrap();
+            assert!(fs::metadata(&path).unwrap().len() > 0, "{:?}", emit);
+        }
+        let assembly = fs::read_to_string(dir.join("T.s")).unwrap();
+        assert!(assembly.contains("main"), "{}", assembly);
+        let ir = fs::read_to_string(dir.join("T.ll")).unwrap();
+        assert!(ir.contains("define i32 @main()"), "{}", ir);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_emit_parse() {
+        for emit in [Emit::Object, Emit::Assembly, Emit::LlvmIr, Emit::Bitcode] {
+            let name = match emit {
+                Emit::Object => "obj",
+                Emit::Assembly => "asm",
+                Emit::LlvmIr => "llvm-ir",
+                Emit::Bitcode => "bc",
+            };
+            assert_eq!(Emit::parse(name), Some(emit));
+        }
+        assert_eq!(Emit::parse("exe"), None);
+    }
+
+    #[test]
+    fn test_target_machine() {
+        assert!(target_machine(None, 0).is_ok());
+        let host = TargetMachine::get_default_triple();
+        assert!(target_machine(Some(&host.as_str().to_string_lossy()), 2).is_ok());
+        assert!(target_machine(Some("kein-ziel"), 2).is_err());
+    }
+
+    #[test]
+    fn test_link_error() {
+        let dir = scratch("link-error");
+        let missing = dir.join("fehlt.o");
+        assert!(link(&[&missing], &dir.join("T"), None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,23 @@
+pub mod bytecode_generator;
 pub mod c_generator;
+#[cfg(test)]
+mod differential;
+mod layout;
+mod native;
+pub mod riscv_generator;
+pub mod wasm_generator;
+pub mod x86_generator;
 
+#[cfg(feature = "llvm")]
+mod llvm_debug;
 #[cfg(feature = "llvm")]
 pub mod llvm_generator;
 
+pub use bytecode_generator::BytecodeGenerator;
 pub use c_generator::CGenerator;
+pub use riscv_generator::RiscvGenerator;
+pub use wasm_generator::WasmGenerator;
+pub use x86_generator::X86Generator;
 
 #[cfg(feature = "llvm")]
-pub use llvm_generator::LLVMGenerator;
\ No newline at end of file
+pub use llvm_generator::LLVMGenerator;
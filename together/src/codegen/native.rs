@@ -0,0 +1,954 @@
+use super::layout::{self, align_up, Layout, RECORD_HEADER};
+use crate::ir::opt::{self, OptLevel, Remark};
+use crate::ir::{self, BinaryOp, BlockId, Callee, CompareOp, Constant, Function, InstKind, Instruction, Terminator, Type, UnaryOp, Value};
+use crate::semantic::hir::Module;
+use crate::semantic::library::LibraryProcedure;
+use crate::semantic::{TypeArena, TypeId, TypeKind};
+use std::collections::{HashMap, HashSet};
+
+// Gemeinsamer Teil der nativen Backends (x86-64, RISC-V): Übersetzung der
+// IR (ir::build, danach ir::opt), Speicherlayout, Rahmen, Registerstapel und
+// Laufzeitprüfungen. Die Backends liefern über `Target` nur die
+// Befehlsauswahl.
+//
+// Jeder SSA-Wert hat einen Platz von 8 Bytes im Rahmen. Ein Befehl lädt
+// seine Operanden in Register des Stapels, rechnet und schreibt das Ergebnis
+// in den Platz seines Werts zurück. Die Register des Stapels sind
+// callee-saved und werden wie bei Wirth in Stapelreihenfolge vergeben;
+// reichen sie nicht, wird der älteste belegte Wert ausgelagert und beim
+// Freigeben zurückgeholt. Phi-Knoten haben zusätzlich einen Schattenplatz,
+// den jeder Vorgänger vor seinem Sprung schreibt (wie die _in-Variablen des
+// C-Backends). REAL-Werte stehen als Bitmuster in den Allzweckregistern.
+//
+// Rahmen: unter dem Rahmenzeiger liegen die gesicherten Register und als
+// letztes der statische Verweis, darunter die Slots der Funktion und die
+// Plätze der Werte. Records beginnen mit der Adresse ihres Typdeskriptors
+// wie im WebAssembly-Backend; Laufzeitfehler rufen oberon_trap mit Zeile,
+// Prozedur und einer printf-Meldung auf.
+
+// Register eines Arguments in der Aufrufkonvention des Zielsystems bzw.
+// sein Platz (in 8-Byte-Schritten) im Argumentbereich auf dem Stapel
+#[derive(Clone, Copy)]
+pub enum ArgumentRegister {
+    Integer(usize),
+    Float(usize),
+    Stack(usize),
+}
+
+// Bedingung für `branch` nach `compare`; Below, Above und AboveEqual
+// vergleichen vorzeichenlos
+#[derive(Clone, Copy)]
+pub enum Condition {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    Below,
+    Above,
+    AboveEqual,
+}
+
+// Inhalt einer globalen Variablen bzw. eines Typdeskriptors
+pub(super) enum Datum {
+    Zero(u32),
+    // Adresse der Marke, None für 0
+    Address(Option<String>),
+}
+
+pub(super) struct DataBlock {
+    pub label: String,
+    pub global: bool,
+    pub align: u32,
+    pub data: Vec<Datum>,
+}
+
+// Befehlsauswahl eines Zielsystems. Die Hooks lassen den Registerstapel
+// unverändert; Hilfsregister wählt jedes Backend selbst.
+pub trait Target {
+    type Reg: Copy;
+
+    // Name des Backends in Fehlermeldungen
+    const NAME: &'static str;
+    // Register für Zwischenwerte, ganzzahlige und REAL-Argumente
+    const POOL: usize;
+    const ARGUMENTS: usize;
+    const FLOAT_ARGUMENTS: usize;
+    // REAL-Argumente weichen in freie Allzweckregister aus, wenn die
+    // Gleitkommaregister belegt sind
+    const REAL_IN_INTEGER_REGISTERS: bool;
+    // Gesicherte Register und statischer Verweis unter dem Rahmenzeiger
+    const SAVED_SIZE: u32;
+
+    // Register des Stapels an Position `index` (modulo POOL)
+    fn register(index: usize) -> Self::Reg;
+    fn frame_pointer() -> Self::Reg;
+    // Statischer Verweis beim Aufruf geschachtelter Prozeduren
+    fn static_link() -> Self::Reg;
+    // Hilfsregister, das Freigaben und die Argumentübergabe überlebt
+    fn scratch() -> Self::Reg;
+
+    fn spill(&mut self, reg: Self::Reg);
+    fn reload(&mut self, reg: Self::Reg);
+
+    fn place(&mut self, label: &str);
+    fn jump(&mut self, label: &str);
+    // Vergleicht a mit b bzw. einer Konstanten; das Ergebnis überlebt
+    // Freigaben bis zum folgenden `branch`
+    fn compare(&mut self, a: Self::Reg, b: Self::Reg);
+    fn compare_immediate(&mut self, a: Self::Reg, value: i64);
+    fn branch(&mut self, condition: Condition, label: &str);
+
+    fn prologue(&mut self);
+    fn epilogue(&mut self, label: &str, global: bool);
+    // Legt `size` Bytes für Slots und Werte an und füllt sie mit 0
+    fn allocate_frame(&mut self, size: u32);
+    // Sichert ein Argument aus seinem Register bzw. vom Stapel des Aufrufers
+    // in die 8 Bytes bei `offset` im Rahmen
+    fn store_argument(&mut self, register: ArgumentRegister, offset: i64);
+    fn pass_argument(&mut self, register: ArgumentRegister, value: Self::Reg);
+    // Platz für `count` Argumente auf dem Stapel vor der Übergabe anlegen
+    // bzw. nach dem Aufruf wieder freigeben
+    fn reserve_arguments(&mut self, count: usize);
+    fn release_arguments(&mut self, count: usize);
+    fn call(&mut self, label: &str);
+    fn call_indirect(&mut self, target: Self::Reg);
+    fn set_result(&mut self, value: Self::Reg, is_real: bool);
+    fn get_result(&mut self, target: Self::Reg, is_real: bool);
+    // Code hinter `label`, der oberon_trap mit Zeile, Prozedur, Meldung und
+    // den Werten für die Platzhalter der Meldung aufruft
+    fn trap_stub(&mut self, label: &str, procedure: &str, message: &str, value: Option<Self::Reg>, maximum: Option<i64>);
+
+    fn load_immediate(&mut self, target: Self::Reg, value: i64);
+    fn load_label(&mut self, target: Self::Reg, label: &str);
+    fn move_register(&mut self, target: Self::Reg, source: Self::Reg);
+    fn add_immediate(&mut self, target: Self::Reg, source: Self::Reg, value: i64);
+    // address := address + index * size
+    fn add_scaled(&mut self, address: Self::Reg, index: Self::Reg, size: u32);
+    fn load(&mut self, ty: Type, base: Self::Reg, target: Self::Reg);
+    fn store(&mut self, ty: Type, value: Self::Reg, base: Self::Reg);
+    // target := 8 Bytes bei offset(base)
+    fn load_word(&mut self, target: Self::Reg, base: Self::Reg, offset: i64);
+    // 8 Bytes bei offset(base) := value
+    fn store_word(&mut self, value: Self::Reg, base: Self::Reg, offset: i64);
+    // Schreibt die Adresse von `label` nach offset(base)
+    fn store_label(&mut self, label: &str, base: Self::Reg, offset: i64);
+    // Kopiert `length` Bytes ab source + offset nach destination + offset
+    fn copy(&mut self, destination: Self::Reg, source: Self::Reg, offset: u32, length: u32);
+    // target := Adresse von `size` Bytes neuem, mit 0 gefülltem Speicher
+    fn allocate(&mut self, target: Self::Reg, size: u32);
+    // low := {low..high}, leer für low > high; beide liegen in 0..31
+    fn range(&mut self, low: Self::Reg, high: Self::Reg);
+    // Out-Prozeduren mit den Argumenten von InstKind::Output
+    fn output(&mut self, procedure: LibraryProcedure, arguments: &[Self::Reg]);
+    // value := op value für alle Operatoren außer ORD und CHR
+    fn unary(&mut self, op: UnaryOp, value: Self::Reg);
+    // a := a op b für Arithmetik auf INTEGER (mit Überlaufprüfung), REAL
+    // und SET
+    fn arithmetic(&mut self, op: BinaryOp, a: Self::Reg, b: Self::Reg);
+    // a := a IN b
+    fn membership(&mut self, a: Self::Reg, b: Self::Reg);
+    // a := a DIV b bzw. a MOD b, zur negativen Unendlichkeit gerundet; b # 0
+    fn division(&mut self, op: BinaryOp, a: Self::Reg, b: Self::Reg);
+    // a := a op b als 0 oder 1 für Werte vom Typ `ty`
+    fn compare_values(&mut self, op: CompareOp, ty: Type, a: Self::Reg, b: Self::Reg);
+    // a := Vergleich der Zeichenketten bei a und b als 0 oder 1
+    fn compare_strings(&mut self, op: CompareOp, a: Self::Reg, a_length: usize, b: Self::Reg, b_length: usize);
+    // value := LSL, ASR bzw. ROR(value, shift) mit 0 <= shift <= 63
+    fn shift(&mut self, op: BinaryOp, value: Self::Reg, shift: Self::Reg);
+}
+
+pub struct NativeGenerator<T> {
+    pub(super) types: TypeArena,
+    layout: Layout,
+    optimization: OptLevel,
+    remarks: Vec<Remark>,
+    pub(super) module: String,
+    labels: usize,
+    // Marke, Typ und Export der globalen Variablen
+    globals: Vec<(String, TypeId, bool)>,
+    descriptors: Vec<(TypeId, String)>,
+    pub(super) strings: Vec<(Vec<u8>, String)>,
+    // Offsets der Slots jeder Funktion relativ zu ihrem Rahmenzeiger
+    frames: HashMap<String, Vec<i64>>,
+    // Plätze der Werte und Schattenplätze der Phi-Knoten der laufenden
+    // Funktion
+    values: Vec<i64>,
+    shadows: HashMap<Value, i64>,
+    // Marken der Blöcke und des Epilogs
+    blocks: Vec<String>,
+    exit: String,
+    // Tiefe des Registerstapels
+    depth: usize,
+    // Schachtelungstiefe der laufenden Funktion
+    level: usize,
+    procedure: String,
+    pub(super) line: usize,
+    // Code-Puffer und Zustand des Zielsystems
+    pub(super) target: T,
+}
+
+impl<T: Default> NativeGenerator<T> {
+    pub fn new() -> Self {
+        Self::with_optimization(OptLevel::O0)
+    }
+
+    // Optimiert die IR vor der Befehlsauswahl (ir::opt)
+    pub fn with_optimization(level: OptLevel) -> Self {
+        NativeGenerator {
+            types: TypeArena::new(),
+            layout: Layout::new(8),
+            optimization: level,
+            remarks: Vec::new(),
+            module: String::new(),
+            labels: 0,
+            globals: Vec::new(),
+            descriptors: Vec::new(),
+            strings: Vec::new(),
+            frames: HashMap::new(),
+            values: Vec::new(),
+            shadows: HashMap::new(),
+            blocks: Vec::new(),
+            exit: String::new(),
+            depth: 0,
+            level: 0,
+            procedure: String::new(),
+            line: 0,
+            target: T::default(),
+        }
+    }
+
+    // Hinweise der Optimierungen aus dem letzten Aufruf von generate
+    pub fn remarks(&self) -> &[Remark] {
+        &self.remarks
+    }
+}
+
+impl<T: Default> Default for NativeGenerator<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default> NativeGenerator<T>
+where
+    Self: Target,
+{
+    // Übersetzt alle Prozeduren und den Modulrumpf als main in den
+    // Code-Puffer des Zielsystems
+    pub(super) fn lower(&mut self, module: &Module) -> Result<(), String> {
+        *self = NativeGenerator::with_optimization(self.optimization);
+        self.module = module.name.clone();
+
+        for import in &module.imports {
+            if !LibraryProcedure::is_library_module(&import.module_name) {
+                return Err(format!(
+                    "Import {}: das {}-Backend übersetzt nur einzelne Module (und Out)",
+                    import.module_name,
+                    Self::NAME
+                ));
+            }
+        }
+
+        let mut program = ir::build(module)?;
+        self.remarks = opt::optimize(&mut program, self.optimization);
+        self.types = program.types.clone();
+
+        for global in &program.globals {
+            let label = format!("{}.{}", self.module, global.name);
+            self.globals.push((label, global.ty, global.exported));
+        }
+        for function in &program.functions {
+            let (slots, _) = self.slot_layout(function);
+            self.frames.insert(function.name.clone(), slots);
+        }
+        for function in &program.functions {
+            self.generate_function(function)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> NativeGenerator<T>
+where
+    Self: Target,
+{
+    // Globale Variablen mit vorbelegten Typdeskriptoren, danach die
+    // Deskriptoren selbst; diese können beim Ausgeben weitere Basistypen
+    // anfordern
+    pub(super) fn data_blocks(&mut self) -> Vec<DataBlock> {
+        let mut blocks = Vec::new();
+        for (label, ty, global) in self.globals.clone() {
+            let mut tags = Vec::new();
+            self.static_descriptors(0, ty, &mut tags);
+            let mut data = Vec::new();
+            let mut position = 0;
+            for (offset, descriptor) in tags {
+                if offset > position {
+                    data.push(Datum::Zero(offset - position));
+                }
+                data.push(Datum::Address(Some(descriptor)));
+                position = offset + 8;
+            }
+            let size = self.size(ty).max(1);
+            if size > position {
+                data.push(Datum::Zero(size - position));
+            }
+            blocks.push(DataBlock { label, global, align: self.align(ty), data });
+        }
+
+        let mut index = 0;
+        while index < self.descriptors.len() {
+            let (record, label) = self.descriptors[index].clone();
+            let base = match self.types.kind(record) {
+                TypeKind::Record { base: Some(base), .. } => Some(*base),
+                _ => None,
+            };
+            let base = base.map(|base| self.descriptor(base));
+            blocks.push(DataBlock { label, global: false, align: 8, data: vec![Datum::Address(base)] });
+            index += 1;
+        }
+        blocks
+    }
+
+    fn static_descriptors(&mut self, offset: u32, ty: TypeId, tags: &mut Vec<(u32, String)>) {
+        match self.types.kind(ty).clone() {
+            TypeKind::Record { .. } => {
+                let descriptor = self.descriptor(ty);
+                tags.push((offset, descriptor));
+                let fields: Vec<TypeId> = self.types.fields(ty).iter().map(|field| field.ty).collect();
+                let offsets = self.record_layout(ty).0;
+                for (field, field_offset) in fields.into_iter().zip(offsets) {
+                    self.static_descriptors(offset + field_offset, field, tags);
+                }
+            }
+            TypeKind::Array { length, element } if self.contains_record(element) => {
+                let size = self.size(element);
+                for index in 0..length as u32 {
+                    self.static_descriptors(offset + index * size, element, tags);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // ========================================================================
+    // Marken und Registerstapel
+    // ========================================================================
+
+    pub(super) fn label(&mut self) -> String {
+        self.labels += 1;
+        format!(".L{}", self.labels)
+    }
+
+    // Nächstes Register des Stapels; ist es noch belegt, wird sein Wert
+    // ausgelagert
+    pub(super) fn alloc(&mut self) -> <Self as Target>::Reg {
+        let reg = Self::register(self.depth % Self::POOL);
+        if self.depth >= Self::POOL {
+            self.spill(reg);
+        }
+        self.depth += 1;
+        reg
+    }
+
+    pub(super) fn free(&mut self) {
+        self.depth -= 1;
+        if self.depth >= Self::POOL {
+            let reg = Self::register(self.depth % Self::POOL);
+            self.reload(reg);
+        }
+    }
+
+    // Gibt alle Register des Stapels frei
+    fn release(&mut self) {
+        while self.depth > 0 {
+            self.free();
+        }
+    }
+
+    // Lädt einen Wert aus seinem Platz in ein neues Register des Stapels
+    fn fetch(&mut self, value: Value) -> <Self as Target>::Reg {
+        let reg = self.alloc();
+        let offset = self.values[value.index()];
+        self.load_word(reg, Self::frame_pointer(), offset);
+        reg
+    }
+
+    // Kopiert 8 Bytes im Rahmen über das Hilfsregister
+    fn move_word(&mut self, from: i64, to: i64) {
+        self.load_word(Self::scratch(), Self::frame_pointer(), from);
+        self.store_word(Self::scratch(), Self::frame_pointer(), to);
+    }
+
+    // ========================================================================
+    // Laufzeitfehler
+    // ========================================================================
+
+    // Sprungziel für einen Laufzeitfehler in der aktuellen Zeile; `value`
+    // und `maximum` füllen die Platzhalter der Meldung
+    pub(super) fn trap(&mut self, message: &str, value: Option<<Self as Target>::Reg>, maximum: Option<i64>) -> String {
+        let label = self.label();
+        let message = self.string(message.as_bytes());
+        let procedure = self.string(self.procedure.clone().as_bytes());
+        self.trap_stub(&label, &procedure, &message, value, maximum);
+        label
+    }
+
+    pub(super) fn overflow(&mut self, op: &str) -> String {
+        self.trap(&format!("Überlauf bei '{}'", op), None, None)
+    }
+
+    fn check_set_element(&mut self, element: <Self as Target>::Reg) {
+        let trap = self.trap("Mengenelement %ld liegt außerhalb von 0..31", Some(element), None);
+        self.compare_immediate(element, 31);
+        self.branch(Condition::Above, &trap);
+    }
+
+    // ========================================================================
+    // Typen und Speicherlayout
+    // ========================================================================
+
+    fn contains_record(&self, ty: TypeId) -> bool {
+        layout::contains_record(&self.types, ty)
+    }
+
+    fn size(&mut self, ty: TypeId) -> u32 {
+        self.layout.size(&self.types, ty)
+    }
+
+    fn align(&self, ty: TypeId) -> u32 {
+        self.layout.align(&self.types, ty)
+    }
+
+    fn record_layout(&mut self, record: TypeId) -> (Vec<u32>, u32) {
+        self.layout.record(&self.types, record)
+    }
+
+    // Deskriptor: Adresse des Deskriptors des Basistyps (0 ohne Basistyp)
+    fn descriptor(&mut self, record: TypeId) -> String {
+        if let Some((_, label)) = self.descriptors.iter().find(|(ty, _)| *ty == record) {
+            return label.clone();
+        }
+        let label = format!("{}.desc{}", self.module, self.descriptors.len());
+        self.descriptors.push((record, label.clone()));
+        label
+    }
+
+    // Konstante Zeichenkette mit 0X am Ende
+    pub(super) fn string(&mut self, text: &[u8]) -> String {
+        let mut bytes = text.to_vec();
+        bytes.push(0);
+        if let Some((_, label)) = self.strings.iter().find(|(existing, _)| *existing == bytes) {
+            return label.clone();
+        }
+        let label = format!(".LS{}", self.strings.len());
+        self.strings.push((bytes, label.clone()));
+        label
+    }
+
+    // Setzt die Typdeskriptoren aller Records in einem Wert vom Typ `ty` an
+    // der Adresse base + offset
+    fn init_descriptors(&mut self, base: <Self as Target>::Reg, offset: u32, ty: TypeId) {
+        match self.types.kind(ty).clone() {
+            TypeKind::Record { .. } => {
+                let descriptor = self.descriptor(ty);
+                self.store_label(&descriptor, base, offset as i64);
+                let fields: Vec<TypeId> = self.types.fields(ty).iter().map(|field| field.ty).collect();
+                let offsets = self.record_layout(ty).0;
+                for (field, field_offset) in fields.into_iter().zip(offsets) {
+                    if self.contains_record(field) {
+                        self.init_descriptors(base, offset + field_offset, field);
+                    }
+                }
+            }
+            TypeKind::Array { length, element } if self.contains_record(element) => {
+                let size = self.size(element);
+                let current = self.alloc();
+                let end = self.alloc();
+                self.add_immediate(current, base, offset as i64);
+                self.add_immediate(end, current, (length as u32 * size) as i64);
+                let loop_label = self.label();
+                self.place(&loop_label);
+                self.init_descriptors(current, 0, element);
+                self.add_immediate(current, current, size as i64);
+                self.compare(current, end);
+                self.branch(Condition::Below, &loop_label);
+                self.free();
+                self.free();
+            }
+            _ => {}
+        }
+    }
+
+    // ========================================================================
+    // Funktionen
+    // ========================================================================
+
+    // Offsets der Slots unter den gesicherten Registern und das Ende des
+    // belegten Bereichs; VAR-Parameter belegen eine Adresse
+    fn slot_layout(&mut self, function: &Function) -> (Vec<i64>, u32) {
+        let mut cursor = Self::SAVED_SIZE;
+        let mut offsets = Vec::new();
+        for slot in &function.slots {
+            let (size, align) = if slot.by_reference { (8, 8) } else { (self.size(slot.ty), self.align(slot.ty)) };
+            cursor = align_up(cursor + size, align);
+            offsets.push(-(cursor as i64));
+        }
+        (offsets, cursor)
+    }
+
+    // Marke einer Prozedur; der Modulrumpf ist main
+    fn function_label(&self, name: &str, depth: usize) -> String {
+        if depth == 0 {
+            "main".to_string()
+        } else {
+            format!("{}.{}", self.module, name)
+        }
+    }
+
+    // Parameter einfacher Typen als Wert, alles andere als Adresse; REAL in
+    // den Gleitkommaregistern, der Rest in den Allzweckregistern. Was nicht
+    // mehr in Register passt, liegt in der Reihenfolge der Parameter auf dem
+    // Stapel.
+    fn parameter_registers(&self, params: &[Type]) -> Vec<ArgumentRegister> {
+        let mut integers = 0;
+        let mut floats = 0;
+        let mut stack = 0;
+        let mut registers = Vec::new();
+        for &ty in params {
+            if ty == Type::Real && floats < Self::FLOAT_ARGUMENTS {
+                registers.push(ArgumentRegister::Float(floats));
+                floats += 1;
+            } else if integers < Self::ARGUMENTS && (ty != Type::Real || Self::REAL_IN_INTEGER_REGISTERS) {
+                registers.push(ArgumentRegister::Integer(integers));
+                integers += 1;
+            } else {
+                registers.push(ArgumentRegister::Stack(stack));
+                stack += 1;
+            }
+        }
+        registers
+    }
+
+    fn generate_function(&mut self, function: &Function) -> Result<(), String> {
+        // Plätze der Werte und der Schatten unter den Slots
+        let (_, slots_end) = self.slot_layout(function);
+        let mut cursor = align_up(slots_end, 8);
+        self.values.clear();
+        for _ in &function.values {
+            cursor += 8;
+            self.values.push(-(cursor as i64));
+        }
+        self.shadows.clear();
+        for phi in function.blocks.iter().flat_map(|block| &block.phis) {
+            cursor += 8;
+            self.shadows.insert(phi.dest, -(cursor as i64));
+        }
+        let frame_size = align_up(cursor - Self::SAVED_SIZE, 16);
+
+        let signature: Vec<Type> = function.params.iter().map(|param| function.value_type(param.value)).collect();
+        let registers = self.parameter_registers(&signature);
+
+        self.procedure = function.name.clone();
+        self.level = function.depth;
+        self.line = function.line;
+        self.blocks.clear();
+        for _ in &function.blocks {
+            let label = self.label();
+            self.blocks.push(label);
+        }
+        self.exit = self.label();
+        self.depth = 0;
+
+        self.prologue();
+        if frame_size > 0 {
+            self.allocate_frame(frame_size);
+        }
+        for (param, register) in function.params.iter().zip(registers) {
+            let offset = self.values[param.value.index()];
+            self.store_argument(register, offset);
+        }
+        for id in function.block_ids() {
+            self.generate_block(function, id)?;
+        }
+        let exit = self.exit.clone();
+        self.place(&exit);
+
+        let label = self.function_label(&function.name, function.depth);
+        let global = function.depth == 0 || (function.depth == 1 && function.exported);
+        self.epilogue(&label, global);
+        Ok(())
+    }
+
+    // Rahmen der Funktion auf Tiefe `level`: den statischen Verweisen folgen
+    fn frame_address(&mut self, level: usize, target: <Self as Target>::Reg) {
+        self.move_register(target, Self::frame_pointer());
+        for _ in level..self.level {
+            self.load_word(target, target, -(Self::SAVED_SIZE as i64));
+        }
+    }
+
+    fn generate_block(&mut self, function: &Function, id: BlockId) -> Result<(), String> {
+        let block = function.block(id);
+        let label = self.blocks[id.index()].clone();
+        self.place(&label);
+        for phi in &block.phis {
+            self.move_word(self.shadows[&phi.dest], self.values[phi.dest.index()]);
+        }
+
+        for instruction in &block.instructions {
+            self.line = instruction.line;
+            self.procedure = instruction.origin.clone().unwrap_or_else(|| function.name.clone());
+            if let Some(result) = self.generate_instruction(function, instruction)? {
+                if let Some(dest) = instruction.dest {
+                    self.store_word(result, Self::frame_pointer(), self.values[dest.index()]);
+                }
+            }
+            self.release();
+        }
+        self.procedure = function.name.clone();
+
+        // Schattenplätze der Phi-Knoten in den Nachfolgern
+        let mut seen = HashSet::new();
+        for successor in block.terminator.successors() {
+            if !seen.insert(successor) {
+                continue;
+            }
+            for phi in &function.block(successor).phis {
+                if let Some(&(_, value)) = phi.arguments.iter().find(|(source, _)| *source == id) {
+                    self.move_word(self.values[value.index()], self.shadows[&phi.dest]);
+                }
+            }
+        }
+        self.generate_terminator(function, id, &block.terminator);
+        Ok(())
+    }
+
+    // Sprung zu einem Block, entfällt vor dem unmittelbar folgenden
+    fn jump_to(&mut self, current: BlockId, target: BlockId) {
+        if target.index() != current.index() + 1 {
+            let label = self.blocks[target.index()].clone();
+            self.jump(&label);
+        }
+    }
+
+    fn generate_terminator(&mut self, function: &Function, id: BlockId, terminator: &Terminator) {
+        match terminator {
+            Terminator::Jump(target) => self.jump_to(id, *target),
+            Terminator::Branch { condition, then_block, else_block } => {
+                let condition = self.fetch(*condition);
+                let label = self.blocks[then_block.index()].clone();
+                self.compare_immediate(condition, 0);
+                self.branch(Condition::NotEqual, &label);
+                self.free();
+                self.jump_to(id, *else_block);
+            }
+            Terminator::Switch { value, cases, default } => {
+                let value = self.fetch(*value);
+                for &(start, end, target) in cases {
+                    let label = self.blocks[target.index()].clone();
+                    if start == end {
+                        self.compare_immediate(value, start);
+                        self.branch(Condition::Equal, &label);
+                    } else {
+                        let next = self.label();
+                        self.compare_immediate(value, start);
+                        self.branch(Condition::Less, &next);
+                        self.compare_immediate(value, end);
+                        self.branch(Condition::LessEqual, &label);
+                        self.place(&next);
+                    }
+                }
+                self.free();
+                self.jump_to(id, *default);
+            }
+            Terminator::Return(value) => {
+                match value {
+                    Some(value) => {
+                        let result = self.fetch(*value);
+                        self.set_result(result, function.value_type(*value) == Type::Real);
+                        self.free();
+                    }
+                    // main liefert den Exit-Code 0
+                    None if function.depth == 0 => {
+                        let status = self.alloc();
+                        self.load_immediate(status, 0);
+                        self.set_result(status, false);
+                        self.free();
+                    }
+                    None => {}
+                }
+                if id.index() + 1 != function.blocks.len() {
+                    let exit = self.exit.clone();
+                    self.jump(&exit);
+                }
+            }
+            Terminator::CaseTrap { value, line } => {
+                self.line = *line;
+                let value = self.fetch(*value);
+                let trap = self.trap("Kein CASE-Label für den Wert %ld", Some(value), None);
+                self.jump(&trap);
+                self.free();
+            }
+        }
+    }
+
+    // ========================================================================
+    // Befehle
+    // ========================================================================
+
+    // Übersetzt einen Befehl; das Ergebnis steht im gelieferten Register
+    fn generate_instruction(
+        &mut self,
+        function: &Function,
+        instruction: &Instruction,
+    ) -> Result<Option<<Self as Target>::Reg>, String> {
+        let result = match &instruction.kind {
+            InstKind::Const(constant) => {
+                let value = match constant {
+                    Constant::Int(value) => *value,
+                    Constant::Real(value) => value.to_bits() as i64,
+                    Constant::Bool(value) => *value as i64,
+                    Constant::Char(value) => *value as i64,
+                    Constant::Set(bits) => *bits as i64,
+                    Constant::Nil => 0,
+                };
+                let target = self.alloc();
+                self.load_immediate(target, value);
+                target
+            }
+            InstKind::String(text) => {
+                let label = self.string(text);
+                let target = self.alloc();
+                self.load_label(target, &label);
+                target
+            }
+            InstKind::Global(name) | InstKind::Procedure(name) => {
+                let label = format!("{}.{}", self.module, name);
+                let target = self.alloc();
+                self.load_label(target, &label);
+                target
+            }
+            InstKind::Slot { depth, index } => {
+                let owner = if *depth == function.depth { function.name.clone() } else { function.ancestor(*depth) };
+                let offset = self
+                    .frames
+                    .get(&owner)
+                    .and_then(|slots| slots.get(*index))
+                    .copied()
+                    .ok_or_else(|| format!("Slot {} von {} nicht gefunden", index, owner))?;
+                let target = self.alloc();
+                self.frame_address(*depth, target);
+                self.add_immediate(target, target, offset);
+                target
+            }
+            InstKind::Field { base, record, field } => {
+                let offset = self.record_layout(*record).0[*field];
+                let base = self.fetch(*base);
+                self.add_immediate(base, base, offset as i64);
+                base
+            }
+            InstKind::Element { base, array, index } => {
+                let element = match self.types.kind(*array) {
+                    TypeKind::Array { element, .. } => *element,
+                    _ => *array,
+                };
+                let size = self.size(element);
+                let base = self.fetch(*base);
+                let index = self.fetch(*index);
+                self.add_scaled(base, index, size);
+                base
+            }
+            InstKind::Load { ty, address } => {
+                let address = self.fetch(*address);
+                self.load(*ty, address, address);
+                address
+            }
+            InstKind::Store { ty, address, value } => {
+                let address = self.fetch(*address);
+                let value = self.fetch(*value);
+                self.store(*ty, value, address);
+                return Ok(None);
+            }
+            // Records behalten ihren Deskriptor
+            InstKind::Copy { ty, dest, source } => {
+                let header = if layout::is_record(&self.types, *ty) { RECORD_HEADER } else { 0 };
+                let size = self.size(*ty);
+                let dest = self.fetch(*dest);
+                let source = self.fetch(*source);
+                self.copy(dest, source, header, size - header);
+                return Ok(None);
+            }
+            InstKind::CopyString { dest, source, length } => {
+                let dest = self.fetch(*dest);
+                let source = self.fetch(*source);
+                self.copy(dest, source, 0, *length as u32);
+                return Ok(None);
+            }
+            InstKind::InitDescriptors { ty, address } => {
+                let address = self.fetch(*address);
+                self.init_descriptors(address, 0, *ty);
+                return Ok(None);
+            }
+            InstKind::New { record } => {
+                let size = self.record_layout(*record).1;
+                let target = self.alloc();
+                self.allocate(target, size);
+                self.init_descriptors(target, 0, *record);
+                target
+            }
+            InstKind::Unary { op, operand } => {
+                let value = self.fetch(*operand);
+                match op {
+                    UnaryOp::Ord => {}
+                    UnaryOp::Chr => {
+                        let trap = self.trap("CHR(%ld) ist kein gültiges Zeichen", Some(value), None);
+                        self.compare_immediate(value, 255);
+                        self.branch(Condition::Above, &trap);
+                    }
+                    _ => self.unary(*op, value),
+                }
+                value
+            }
+            InstKind::Binary { op, left, right } => {
+                let a = self.fetch(*left);
+                let b = self.fetch(*right);
+                match op {
+                    BinaryOp::Div | BinaryOp::Mod => {
+                        let zero = self.trap("Division durch 0", None, None);
+                        self.compare_immediate(b, 0);
+                        self.branch(Condition::Equal, &zero);
+                        self.division(*op, a, b);
+                    }
+                    BinaryOp::Lsl | BinaryOp::Asr | BinaryOp::Ror => {
+                        let message = format!("Schiebeweite %ld für {} außerhalb von 0..63", op.symbol());
+                        let trap = self.trap(&message, Some(b), None);
+                        self.compare_immediate(b, 63);
+                        self.branch(Condition::Above, &trap);
+                        self.shift(*op, a, b);
+                    }
+                    BinaryOp::Range => {
+                        self.check_set_element(a);
+                        self.check_set_element(b);
+                        self.range(a, b);
+                    }
+                    BinaryOp::In => self.membership(a, b),
+                    _ => self.arithmetic(*op, a, b),
+                }
+                a
+            }
+            InstKind::Compare { op, left, right } => {
+                let ty = function.value_type(*left);
+                let a = self.fetch(*left);
+                let b = self.fetch(*right);
+                self.compare_values(*op, ty, a, b);
+                a
+            }
+            InstKind::CompareStrings { op, left, left_length, right, right_length } => {
+                let a = self.fetch(*left);
+                let b = self.fetch(*right);
+                self.compare_strings(*op, a, *left_length, b, *right_length);
+                a
+            }
+            // Kette der Basistypen vom Deskriptor des Records aus
+            InstKind::TypeTest { address, record } => {
+                let value = self.fetch(*address);
+                let descriptor = self.descriptor(*record);
+                let expected = self.alloc();
+                let (top, found, end) = (self.label(), self.label(), self.label());
+                self.load_label(expected, &descriptor);
+                self.load_word(value, value, 0);
+                self.place(&top);
+                self.compare(value, expected);
+                self.branch(Condition::Equal, &found);
+                self.load_word(value, value, 0);
+                self.compare_immediate(value, 0);
+                self.branch(Condition::NotEqual, &top);
+                self.jump(&end);
+                self.place(&found);
+                self.load_immediate(value, 1);
+                self.place(&end);
+                value
+            }
+            InstKind::Call { callee, arguments } => return self.generate_call(function, instruction, callee, arguments),
+            InstKind::Output { procedure, arguments } => {
+                let mut registers = Vec::new();
+                for argument in arguments {
+                    registers.push(self.fetch(*argument));
+                }
+                self.output(*procedure, &registers);
+                return Ok(None);
+            }
+            InstKind::CheckIndex { index, length } => {
+                let index = self.fetch(*index);
+                let trap = self.trap("Index %ld außerhalb von 0..%ld", Some(index), Some(*length as i64 - 1));
+                self.compare_immediate(index, *length as i64);
+                self.branch(Condition::AboveEqual, &trap);
+                return Ok(None);
+            }
+            InstKind::CheckNil(value) => {
+                let value = self.fetch(*value);
+                let trap = self.trap("Dereferenzierung von NIL", None, None);
+                self.compare_immediate(value, 0);
+                self.branch(Condition::Equal, &trap);
+                return Ok(None);
+            }
+            InstKind::Assert(value) => {
+                let value = self.fetch(*value);
+                let trap = self.trap("ASSERT fehlgeschlagen", None, None);
+                self.compare_immediate(value, 0);
+                self.branch(Condition::Equal, &trap);
+                return Ok(None);
+            }
+        };
+        Ok(Some(result))
+    }
+
+    // Argumente gehen über das Hilfsregister direkt aus ihren Plätzen in die
+    // Argumentregister bzw. auf den Stapel
+    fn generate_call(
+        &mut self,
+        function: &Function,
+        instruction: &Instruction,
+        callee: &Callee,
+        arguments: &[Value],
+    ) -> Result<Option<<Self as Target>::Reg>, String> {
+        let signature: Vec<Type> = arguments.iter().map(|argument| function.value_type(*argument)).collect();
+        let registers = self.parameter_registers(&signature);
+        let stack = registers.iter().filter(|register| matches!(register, ArgumentRegister::Stack(_))).count();
+        if stack > 0 {
+            self.reserve_arguments(stack);
+        }
+        for (argument, register) in arguments.iter().zip(registers) {
+            self.load_word(Self::scratch(), Self::frame_pointer(), self.values[argument.index()]);
+            self.pass_argument(register, Self::scratch());
+        }
+
+        match callee {
+            Callee::Direct { name, depth } => {
+                if *depth > 1 {
+                    self.frame_address(depth - 1, Self::static_link());
+                }
+                let label = self.function_label(name, *depth);
+                self.call(&label);
+            }
+            Callee::Indirect(target) => {
+                self.load_word(Self::scratch(), Self::frame_pointer(), self.values[target.index()]);
+                let trap = self.trap("Aufruf einer Prozedurvariablen mit Wert NIL", None, None);
+                self.compare_immediate(Self::scratch(), 0);
+                self.branch(Condition::Equal, &trap);
+                self.call_indirect(Self::scratch());
+            }
+        }
+        if stack > 0 {
+            self.release_arguments(stack);
+        }
+
+        Ok(instruction.dest.map(|dest| {
+            let target = self.alloc();
+            self.get_result(target, function.value_type(dest) == Type::Real);
+            target
+        }))
+    }
+}
@@ -0,0 +1,805 @@
+use super::layout::align_up;
+use super::native::{ArgumentRegister, Condition, Datum, NativeGenerator, Target};
+use crate::ir::{BinaryOp, CompareOp, Type, UnaryOp};
+use crate::riscv::{fits_immediate, Cond, Data, FCmp, FOp, FReg, Instruction, Item, Load, Op, OpImm, Program, Reg, Store};
+use crate::semantic::hir::Module;
+use crate::semantic::library::LibraryProcedure;
+
+// Übersetzt ein geprüftes Modul für RV64IMAFD nach der LP64D-Konvention.
+// Das Ergebnis ist ein riscv::Program, das riscv::to_asm als GNU-as-Quelltext
+// ausgibt; gebunden wird mit einem RISC-V-C-Compiler, Out, NEW und die
+// Meldungen der Laufzeitfehler benutzen die C-Bibliothek. Durchlauf und
+// Layout teilt das Backend mit x86-64 (native.rs), hier steht nur die
+// Befehlsauswahl.
+//
+// Zwischenwerte liegen in s1..s11; ausgelagert wird in 16 Bytes auf dem
+// Stapel (der Stapel bleibt so ausgerichtet). t0, t3..t6 sind Hilfsregister
+// einzelner Befehlsfolgen, t1 überlebt als `scratch` auch Freigaben, ft0/ft1
+// dienen zum Rechnen mit REAL. RISC-V kennt keine Flags: `compare` legt die
+// Operanden in t0 und t4 (bzw. zero) ab, `branch` vergleicht diese. Überläufe
+// werden über Vergleiche erkannt.
+//
+// Rahmen: ra, s0, s1..s11 und der statische Verweis (t2 beim Aufruf
+// geschachtelter Prozeduren) belegen 112 Bytes unter dem alten sp, s0 zeigt
+// darüber; der Verweis liegt also immer bei -112(s0). Reichen a0..a7 und
+// fa0..fa7 nicht, übergibt der Aufrufer die übrigen Argumente auf dem
+// Stapel, REAL-Argumente zuvor noch in freien a-Registern.
+
+const POOL: usize = 11;
+
+pub type RiscvGenerator = NativeGenerator<RiscvCode>;
+
+pub struct RiscvCode {
+    text: Vec<Item>,
+    // Code der Prozedur, die gerade entsteht, und ihre Trap-Sprünge
+    code: Vec<Item>,
+    stubs: Vec<Item>,
+    // Rechter Operand des letzten Vergleichs (t4 oder zero); der linke
+    // steht in t0
+    right: Reg,
+}
+
+impl Default for RiscvCode {
+    fn default() -> Self {
+        RiscvCode { text: Vec::new(), code: Vec::new(), stubs: Vec::new(), right: Reg::T4 }
+    }
+}
+
+impl RiscvGenerator {
+    pub fn generate(&mut self, module: &Module) -> Result<Program, String> {
+        self.lower(module)?;
+        self.generate_runtime();
+
+        let mut data = Vec::new();
+        for block in self.data_blocks() {
+            if block.global {
+                data.push(Data::Global(block.label.clone()));
+            }
+            data.extend([Data::Align(block.align), Data::Label(block.label)]);
+            for datum in block.data {
+                data.push(match datum {
+                    Datum::Zero(size) => Data::Zero(size),
+                    Datum::Address(label) => Data::Address(label),
+                });
+            }
+        }
+
+        let mut rodata = Vec::new();
+        for (bytes, label) in &self.strings {
+            rodata.extend([Data::Label(label.clone()), Data::Bytes(bytes.clone())]);
+        }
+        Ok(Program { name: self.module.clone(), text: std::mem::take(&mut self.target.text), data, rodata })
+    }
+
+    // ========================================================================
+    // Befehle
+    // ========================================================================
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.target.code.push(Item::Instruction(instruction));
+    }
+
+    fn op(&mut self, op: Op, rd: Reg, rs1: Reg, rs2: Reg) {
+        self.emit(Instruction::Op(op, rd, rs1, rs2));
+    }
+
+    fn op_imm(&mut self, op: OpImm, rd: Reg, rs1: Reg, imm: i64) {
+        self.emit(Instruction::OpImm(op, rd, rs1, imm));
+    }
+
+    fn mv(&mut self, rd: Reg, rs: Reg) {
+        self.op_imm(OpImm::Addi, rd, rs, 0);
+    }
+
+    fn li(&mut self, rd: Reg, value: i64) {
+        self.emit(Instruction::Li(rd, value));
+    }
+
+    fn la(&mut self, rd: Reg, label: &str) {
+        self.emit(Instruction::La(rd, label.to_string()));
+    }
+
+    fn branch_if(&mut self, cond: Cond, rs1: Reg, rs2: Reg, label: &str) {
+        self.emit(Instruction::Branch(cond, rs1, rs2, label.to_string()));
+    }
+
+    // offset(base) als Operand eines Lade- oder Speicherbefehls; passt der
+    // Offset nicht in 12 Bit, wird die Adresse in t0 berechnet
+    fn operand(&mut self, offset: i64, base: Reg) -> (i64, Reg) {
+        if fits_immediate(offset) {
+            (offset, base)
+        } else {
+            self.li(Reg::T0, offset);
+            self.op(Op::Add, Reg::T0, base, Reg::T0);
+            (0, Reg::T0)
+        }
+    }
+
+    fn load_at(&mut self, kind: Load, rd: Reg, offset: i64, base: Reg) {
+        let (offset, base) = self.operand(offset, base);
+        self.emit(Instruction::Load(kind, rd, offset, base));
+    }
+
+    fn store_at(&mut self, kind: Store, rs: Reg, offset: i64, base: Reg) {
+        let (offset, base) = self.operand(offset, base);
+        self.emit(Instruction::Store(kind, rs, offset, base));
+    }
+
+    // rd := a op b für +, - und * mit Überlaufprüfung; rd darf a oder b sein
+    fn checked(&mut self, op: Op, rd: Reg, a: Reg, b: Reg, name: &str) {
+        let overflow = self.overflow(name);
+        match op {
+            // Das Ergebnis muss auf der Seite von a liegen, die das
+            // Vorzeichen von b verlangt
+            Op::Add | Op::Sub => {
+                self.op(op, Reg::T3, a, b);
+                self.op(Op::Slt, Reg::T4, Reg::T3, a);
+                if op == Op::Add {
+                    self.op(Op::Slt, Reg::T5, b, Reg::ZERO);
+                } else {
+                    self.op(Op::Slt, Reg::T5, Reg::ZERO, b);
+                }
+            }
+            // Die oberen 64 Bit müssen die Vorzeichenerweiterung sein
+            _ => {
+                self.op(Op::Mul, Reg::T3, a, b);
+                self.op(Op::Mulh, Reg::T4, a, b);
+                self.op_imm(OpImm::Srai, Reg::T5, Reg::T3, 63);
+            }
+        }
+        self.branch_if(Cond::Ne, Reg::T4, Reg::T5, &overflow);
+        self.mv(rd, Reg::T3);
+    }
+
+    // Springt bei MIN(INTEGER) in den Überlauf (für -, ABS und DIV)
+    fn check_minimum(&mut self, value: Reg, overflow: &str) {
+        self.li(Reg::T0, i64::MIN);
+        self.branch_if(Cond::Eq, value, Reg::T0, overflow);
+    }
+
+    // rd := a op b als 0 oder 1
+    fn comparison(&mut self, op: CompareOp, rd: Reg, a: Reg, b: Reg, unsigned: bool) {
+        let less = if unsigned { Op::Sltu } else { Op::Slt };
+        match op {
+            CompareOp::Eq => {
+                self.op(Op::Sub, rd, a, b);
+                self.op_imm(OpImm::Sltiu, rd, rd, 1);
+            }
+            CompareOp::Ne => {
+                self.op(Op::Sub, rd, a, b);
+                self.op(Op::Sltu, rd, Reg::ZERO, rd);
+            }
+            CompareOp::Lt => self.op(less, rd, a, b),
+            CompareOp::Gt => self.op(less, rd, b, a),
+            CompareOp::Le => {
+                self.op(less, rd, b, a);
+                self.op_imm(OpImm::Xori, rd, rd, 1);
+            }
+            CompareOp::Ge => {
+                self.op(less, rd, a, b);
+                self.op_imm(OpImm::Xori, rd, rd, 1);
+            }
+        }
+    }
+
+    fn printf(&mut self, format: &str) {
+        let format = self.string(format.as_bytes());
+        self.la(Reg::A0, &format);
+        self.call("printf");
+    }
+
+    // Gesicherte Register im Rahmen: ra, s0, s1..s11 von oben, t2 ganz unten
+    fn saved_registers() -> Vec<(Reg, i64)> {
+        let size = Self::SAVED_SIZE as i64;
+        let mut saved = vec![(Reg::RA, size - 8), (Reg::S0, size - 16)];
+        for index in 0..POOL {
+            saved.push((Reg::saved(index), size - 24 - 8 * index as i64));
+        }
+        saved
+    }
+
+    // Laufzeit: oberon_trap(Zeile, Prozedur, Meldung, a, b) meldet einen
+    // Laufzeitfehler im Format des Interpreters auf stderr und beendet das
+    // Programm mit Exit-Code 1; die Meldung ist ein printf-Format für a und
+    // b. oberon_compare(a, Länge a, b, Länge b) vergleicht zwei
+    // Zeichenketten bis zum ersten 0X und liefert -1, 0 oder 1.
+    fn generate_runtime(&mut self) {
+        let (a1, a2, a3, a4) = (Reg::argument(1), Reg::argument(2), Reg::argument(3), Reg::argument(4));
+        let head = self.string(b"%ld: Laufzeitfehler in %s: ");
+        let tail = self.string(b"\n");
+
+        // Kehrt nicht zurück, die gesicherten Register sind frei
+        self.target.code.clear();
+        let (line, procedure, message, first, second) = (Reg::saved(0), Reg::saved(1), Reg::saved(2), Reg::saved(3), Reg::saved(4));
+        for (target, source) in [(line, Reg::A0), (procedure, a1), (message, a2), (first, a3), (second, a4)] {
+            self.mv(target, source);
+        }
+        self.li(Reg::A0, 0);
+        self.call("fflush");
+        self.li(Reg::A0, 2);
+        self.la(a1, &head);
+        self.mv(a2, line);
+        self.mv(a3, procedure);
+        self.call("dprintf");
+        self.li(Reg::A0, 2);
+        self.mv(a1, message);
+        self.mv(a2, first);
+        self.mv(a3, second);
+        self.call("dprintf");
+        self.li(Reg::A0, 2);
+        self.la(a1, &tail);
+        self.call("dprintf");
+        self.li(Reg::A0, 1);
+        self.call("exit");
+        self.target.text.push(Item::Function { name: "oberon_trap".to_string(), global: false });
+        self.target.text.append(&mut self.target.code);
+
+        let (index, left, right) = (Reg::T0, Reg::T1, Reg::T4);
+        let (top, first_done, second_done, differ, equal) =
+            (self.label(), self.label(), self.label(), self.label(), self.label());
+        self.li(index, 0);
+        self.place(&top);
+        self.li(left, 0);
+        self.branch_if(Cond::Geu, index, a1, &first_done);
+        self.op(Op::Add, Reg::T3, Reg::A0, index);
+        self.emit(Instruction::Load(Load::Lbu, left, 0, Reg::T3));
+        self.place(&first_done);
+        self.li(right, 0);
+        self.branch_if(Cond::Geu, index, a3, &second_done);
+        self.op(Op::Add, Reg::T3, a2, index);
+        self.emit(Instruction::Load(Load::Lbu, right, 0, Reg::T3));
+        self.place(&second_done);
+        self.branch_if(Cond::Ne, left, right, &differ);
+        self.branch_if(Cond::Eq, left, Reg::ZERO, &equal);
+        self.op_imm(OpImm::Addi, index, index, 1);
+        self.jump(&top);
+        // 1 - 2 * (links < rechts)
+        self.place(&differ);
+        self.op(Op::Sltu, Reg::T5, left, right);
+        self.op_imm(OpImm::Slli, Reg::T5, Reg::T5, 1);
+        self.li(Reg::A0, 1);
+        self.op(Op::Sub, Reg::A0, Reg::A0, Reg::T5);
+        self.emit(Instruction::Ret);
+        self.place(&equal);
+        self.li(Reg::A0, 0);
+        self.emit(Instruction::Ret);
+        self.target.text.push(Item::Function { name: "oberon_compare".to_string(), global: false });
+        self.target.text.append(&mut self.target.code);
+    }
+}
+
+impl Target for RiscvGenerator {
+    type Reg = Reg;
+
+    const NAME: &'static str = "RISC-V";
+    const POOL: usize = POOL;
+    const ARGUMENTS: usize = 8;
+    const FLOAT_ARGUMENTS: usize = 8;
+    const REAL_IN_INTEGER_REGISTERS: bool = true;
+    const SAVED_SIZE: u32 = 112;
+
+    fn register(index: usize) -> Reg {
+        Reg::saved(index)
+    }
+
+    fn frame_pointer() -> Reg {
+        Reg::S0
+    }
+
+    fn static_link() -> Reg {
+        Reg::T2
+    }
+
+    fn scratch() -> Reg {
+        Reg::T1
+    }
+
+    fn spill(&mut self, reg: Reg) {
+        self.op_imm(OpImm::Addi, Reg::SP, Reg::SP, -16);
+        self.emit(Instruction::Store(Store::Sd, reg, 0, Reg::SP));
+    }
+
+    fn reload(&mut self, reg: Reg) {
+        self.emit(Instruction::Load(Load::Ld, reg, 0, Reg::SP));
+        self.op_imm(OpImm::Addi, Reg::SP, Reg::SP, 16);
+    }
+
+    fn place(&mut self, label: &str) {
+        self.target.code.push(Item::Label(label.to_string()));
+    }
+
+    fn jump(&mut self, label: &str) {
+        self.emit(Instruction::Jump(label.to_string()));
+    }
+
+    fn compare(&mut self, a: Reg, b: Reg) {
+        self.mv(Reg::T0, a);
+        self.mv(Reg::T4, b);
+        self.target.right = Reg::T4;
+    }
+
+    fn compare_immediate(&mut self, a: Reg, value: i64) {
+        self.mv(Reg::T0, a);
+        if value == 0 {
+            self.target.right = Reg::ZERO;
+        } else {
+            self.li(Reg::T4, value);
+            self.target.right = Reg::T4;
+        }
+    }
+
+    fn branch(&mut self, condition: Condition, label: &str) {
+        let (left, right) = (Reg::T0, self.target.right);
+        let (cond, rs1, rs2) = match condition {
+            Condition::Equal => (Cond::Eq, left, right),
+            Condition::NotEqual => (Cond::Ne, left, right),
+            Condition::Less => (Cond::Lt, left, right),
+            Condition::LessEqual => (Cond::Ge, right, left),
+            Condition::Greater => (Cond::Lt, right, left),
+            Condition::Below => (Cond::Ltu, left, right),
+            Condition::Above => (Cond::Ltu, right, left),
+            Condition::AboveEqual => (Cond::Geu, left, right),
+        };
+        self.branch_if(cond, rs1, rs2, label);
+    }
+
+    fn prologue(&mut self) {
+        self.target.code.clear();
+        self.target.stubs.clear();
+        self.op_imm(OpImm::Addi, Reg::SP, Reg::SP, -(Self::SAVED_SIZE as i64));
+        for (reg, offset) in Self::saved_registers() {
+            self.emit(Instruction::Store(Store::Sd, reg, offset, Reg::SP));
+        }
+        self.emit(Instruction::Store(Store::Sd, Reg::T2, 0, Reg::SP));
+        self.op_imm(OpImm::Addi, Reg::S0, Reg::SP, Self::SAVED_SIZE as i64);
+    }
+
+    fn epilogue(&mut self, label: &str, global: bool) {
+        self.op_imm(OpImm::Addi, Reg::SP, Reg::S0, -(Self::SAVED_SIZE as i64));
+        for (reg, offset) in Self::saved_registers().into_iter().rev() {
+            self.emit(Instruction::Load(Load::Ld, reg, offset, Reg::SP));
+        }
+        self.op_imm(OpImm::Addi, Reg::SP, Reg::SP, Self::SAVED_SIZE as i64);
+        self.emit(Instruction::Ret);
+
+        self.target.text.push(Item::Function { name: label.to_string(), global });
+        self.target.text.append(&mut self.target.code);
+        self.target.text.append(&mut self.target.stubs);
+    }
+
+    fn allocate_frame(&mut self, size: u32) {
+        self.add_immediate(Reg::SP, Reg::SP, -(size as i64));
+        self.mv(Reg::T0, Reg::SP);
+        self.li(Reg::T1, (size / 8) as i64);
+        let zero = self.label();
+        self.place(&zero);
+        self.emit(Instruction::Store(Store::Sd, Reg::ZERO, 0, Reg::T0));
+        self.op_imm(OpImm::Addi, Reg::T0, Reg::T0, 8);
+        self.op_imm(OpImm::Addi, Reg::T1, Reg::T1, -1);
+        self.branch_if(Cond::Ne, Reg::T1, Reg::ZERO, &zero);
+    }
+
+    // Stapelargumente liegen ab dem alten sp, also ab 0(s0)
+    fn store_argument(&mut self, register: ArgumentRegister, offset: i64) {
+        match register {
+            ArgumentRegister::Float(index) => {
+                let (offset, base) = self.operand(offset, Reg::S0);
+                self.emit(Instruction::Fsd(FReg::argument(index), offset, base));
+            }
+            ArgumentRegister::Integer(index) => self.store_at(Store::Sd, Reg::argument(index), offset, Reg::S0),
+            ArgumentRegister::Stack(index) => {
+                self.load_at(Load::Ld, Reg::T1, 8 * index as i64, Reg::S0);
+                self.store_at(Store::Sd, Reg::T1, offset, Reg::S0);
+            }
+        }
+    }
+
+    fn pass_argument(&mut self, register: ArgumentRegister, value: Reg) {
+        match register {
+            ArgumentRegister::Integer(index) => self.mv(Reg::argument(index), value),
+            ArgumentRegister::Float(index) => self.emit(Instruction::FmvDX(FReg::argument(index), value)),
+            ArgumentRegister::Stack(index) => self.store_at(Store::Sd, value, 8 * index as i64, Reg::SP),
+        }
+    }
+
+    // In 16-Byte-Schritten, damit der Stapel ausgerichtet bleibt
+    fn reserve_arguments(&mut self, count: usize) {
+        self.add_immediate(Reg::SP, Reg::SP, -(align_up(8 * count as u32, 16) as i64));
+    }
+
+    fn release_arguments(&mut self, count: usize) {
+        self.add_immediate(Reg::SP, Reg::SP, align_up(8 * count as u32, 16) as i64);
+    }
+
+    fn call(&mut self, label: &str) {
+        self.emit(Instruction::Call(label.to_string()));
+    }
+
+    fn call_indirect(&mut self, target: Reg) {
+        self.emit(Instruction::CallIndirect(target));
+    }
+
+    fn set_result(&mut self, value: Reg, is_real: bool) {
+        if is_real {
+            self.emit(Instruction::FmvDX(FReg::argument(0), value));
+        } else {
+            self.mv(Reg::A0, value);
+        }
+    }
+
+    fn get_result(&mut self, target: Reg, is_real: bool) {
+        if is_real {
+            self.emit(Instruction::FmvXD(target, FReg::argument(0)));
+        } else {
+            self.mv(target, Reg::A0);
+        }
+    }
+
+    // `value` und `maximum` landen in a3 und a4
+    fn trap_stub(&mut self, label: &str, procedure: &str, message: &str, value: Option<Reg>, maximum: Option<i64>) {
+        self.target.stubs.push(Item::Label(label.to_string()));
+        let mut stub = Vec::new();
+        if let Some(value) = value {
+            stub.push(Instruction::OpImm(OpImm::Addi, Reg::argument(3), value, 0));
+        }
+        if let Some(maximum) = maximum {
+            stub.push(Instruction::Li(Reg::argument(4), maximum));
+        }
+        stub.extend([
+            Instruction::Li(Reg::A0, self.line as i64),
+            Instruction::La(Reg::argument(1), procedure.to_string()),
+            Instruction::La(Reg::argument(2), message.to_string()),
+            Instruction::Call("oberon_trap".to_string()),
+        ]);
+        self.target.stubs.extend(stub.into_iter().map(Item::Instruction));
+    }
+
+    fn load_immediate(&mut self, target: Reg, value: i64) {
+        self.li(target, value);
+    }
+
+    fn load_label(&mut self, target: Reg, label: &str) {
+        self.la(target, label);
+    }
+
+    fn move_register(&mut self, target: Reg, source: Reg) {
+        self.mv(target, source);
+    }
+
+    // Große Werte über t0
+    fn add_immediate(&mut self, target: Reg, source: Reg, value: i64) {
+        if fits_immediate(value) {
+            self.op_imm(OpImm::Addi, target, source, value);
+        } else {
+            self.li(Reg::T0, value);
+            self.op(Op::Add, target, source, Reg::T0);
+        }
+    }
+
+    fn add_scaled(&mut self, address: Reg, index: Reg, size: u32) {
+        self.li(Reg::T0, size as i64);
+        self.op(Op::Mul, index, index, Reg::T0);
+        self.op(Op::Add, address, address, index);
+    }
+
+    fn load(&mut self, ty: Type, base: Reg, target: Reg) {
+        let kind = match ty {
+            Type::Bool | Type::Char => Load::Lbu,
+            Type::Set => Load::Lwu,
+            _ => Load::Ld,
+        };
+        self.emit(Instruction::Load(kind, target, 0, base));
+    }
+
+    fn store(&mut self, ty: Type, value: Reg, base: Reg) {
+        let kind = match ty {
+            Type::Bool | Type::Char => Store::Sb,
+            Type::Set => Store::Sw,
+            _ => Store::Sd,
+        };
+        self.emit(Instruction::Store(kind, value, 0, base));
+    }
+
+    fn load_word(&mut self, target: Reg, base: Reg, offset: i64) {
+        self.load_at(Load::Ld, target, offset, base);
+    }
+
+    fn store_word(&mut self, value: Reg, base: Reg, offset: i64) {
+        self.store_at(Store::Sd, value, offset, base);
+    }
+
+    fn store_label(&mut self, label: &str, base: Reg, offset: i64) {
+        self.la(Reg::T3, label);
+        self.store_at(Store::Sd, Reg::T3, offset, base);
+    }
+
+    fn copy(&mut self, destination: Reg, source: Reg, offset: u32, length: u32) {
+        self.add_immediate(Reg::A0, destination, offset as i64);
+        self.add_immediate(Reg::argument(1), source, offset as i64);
+        self.li(Reg::argument(2), length as i64);
+        self.call("memcpy");
+    }
+
+    fn allocate(&mut self, target: Reg, size: u32) {
+        self.li(Reg::A0, 1);
+        self.li(Reg::argument(1), size as i64);
+        self.call("calloc");
+        let trap = self.trap("Kein Speicher mehr für NEW", None, None);
+        self.branch_if(Cond::Eq, Reg::A0, Reg::ZERO, &trap);
+        self.mv(target, Reg::A0);
+    }
+
+    // Bereiche als Maske: (-1 << a) & (-1 >>> (63 - b)), leer für a > b
+    fn range(&mut self, low: Reg, high: Reg) {
+        self.li(Reg::T0, -1);
+        self.op(Op::Sll, Reg::T0, Reg::T0, low);
+        self.li(Reg::T3, 63);
+        self.op(Op::Sub, Reg::T3, Reg::T3, high);
+        self.li(Reg::T4, -1);
+        self.op(Op::Srl, Reg::T4, Reg::T4, Reg::T3);
+        self.op(Op::And, low, Reg::T0, Reg::T4);
+    }
+
+    // Out über printf und putchar; REAL-Werte werden variadisch in
+    // Allzweckregistern übergeben
+    fn output(&mut self, procedure: LibraryProcedure, arguments: &[Reg]) {
+        let (a1, a2) = (Reg::argument(1), Reg::argument(2));
+        match procedure {
+            LibraryProcedure::OutOpen => {}
+            LibraryProcedure::OutLn => {
+                self.li(Reg::A0, 10);
+                self.call("putchar");
+            }
+            LibraryProcedure::OutChar => {
+                self.mv(Reg::A0, arguments[0]);
+                self.call("putchar");
+            }
+            LibraryProcedure::OutString => {
+                self.mv(a2, arguments[0]);
+                self.mv(a1, arguments[1]);
+                self.printf("%.*s");
+            }
+            LibraryProcedure::OutInt | LibraryProcedure::OutReal => {
+                self.mv(a1, arguments[1]);
+                self.mv(a2, arguments[0]);
+                self.printf(if procedure == LibraryProcedure::OutInt { "%*ld" } else { "%*.6f" });
+            }
+        }
+    }
+
+    fn unary(&mut self, op: UnaryOp, value: Reg) {
+        match op {
+            UnaryOp::Neg => {
+                let overflow = self.overflow(op.symbol());
+                self.check_minimum(value, &overflow);
+                self.op(Op::Sub, value, Reg::ZERO, value);
+            }
+            UnaryOp::Abs => {
+                let overflow = self.overflow(op.symbol());
+                self.check_minimum(value, &overflow);
+                self.op_imm(OpImm::Srai, Reg::T0, value, 63);
+                self.op(Op::Xor, value, value, Reg::T0);
+                self.op(Op::Sub, value, value, Reg::T0);
+            }
+            UnaryOp::FNeg => {
+                self.li(Reg::T0, i64::MIN);
+                self.op(Op::Xor, value, value, Reg::T0);
+            }
+            UnaryOp::FAbs => {
+                self.op_imm(OpImm::Slli, value, value, 1);
+                self.op_imm(OpImm::Srli, value, value, 1);
+            }
+            UnaryOp::Not => self.op_imm(OpImm::Xori, value, value, 1),
+            // Komplement auf 32 Bit
+            UnaryOp::Complement => {
+                self.op_imm(OpImm::Xori, value, value, -1);
+                self.op_imm(OpImm::Slli, value, value, 32);
+                self.op_imm(OpImm::Srli, value, value, 32);
+            }
+            UnaryOp::Odd => self.op_imm(OpImm::Andi, value, value, 1),
+            UnaryOp::IntToReal => {
+                self.emit(Instruction::FcvtDL(FReg::FT0, value));
+                self.emit(Instruction::FmvXD(value, FReg::FT0));
+            }
+            // fcvt sättigt statt zu melden: NaN und Werte außerhalb von
+            // -2^63..2^63 werden vorher abgefangen
+            UnaryOp::Floor => {
+                let overflow = self.overflow(op.symbol());
+                let (ft0, ft1) = (FReg::FT0, FReg::FT1);
+                self.emit(Instruction::FmvDX(ft0, value));
+                self.emit(Instruction::FCmp(FCmp::Eq, Reg::T0, ft0, ft0));
+                self.branch_if(Cond::Eq, Reg::T0, Reg::ZERO, &overflow);
+                self.li(Reg::T0, (-(i64::MIN as f64)).to_bits() as i64);
+                self.emit(Instruction::FmvDX(ft1, Reg::T0));
+                self.emit(Instruction::FCmp(FCmp::Le, Reg::T0, ft1, ft0));
+                self.branch_if(Cond::Ne, Reg::T0, Reg::ZERO, &overflow);
+                self.li(Reg::T0, (i64::MIN as f64).to_bits() as i64);
+                self.emit(Instruction::FmvDX(ft1, Reg::T0));
+                self.emit(Instruction::FCmp(FCmp::Lt, Reg::T0, ft0, ft1));
+                self.branch_if(Cond::Ne, Reg::T0, Reg::ZERO, &overflow);
+                self.emit(Instruction::FcvtLD(value, ft0));
+            }
+            UnaryOp::Ord | UnaryOp::Chr => {}
+        }
+    }
+
+    fn arithmetic(&mut self, op: BinaryOp, a: Reg, b: Reg) {
+        let fop = match op {
+            BinaryOp::Add | BinaryOp::Inc | BinaryOp::For => return self.checked(Op::Add, a, a, b, op.symbol()),
+            BinaryOp::Sub | BinaryOp::Dec => return self.checked(Op::Sub, a, a, b, op.symbol()),
+            BinaryOp::Mul => return self.checked(Op::Mul, a, a, b, op.symbol()),
+            BinaryOp::FAdd => FOp::Add,
+            BinaryOp::FSub => FOp::Sub,
+            BinaryOp::FMul => FOp::Mul,
+            BinaryOp::FDiv => FOp::Div,
+            BinaryOp::Union => return self.op(Op::Or, a, a, b),
+            BinaryOp::Difference => {
+                self.op_imm(OpImm::Xori, Reg::T0, b, -1);
+                return self.op(Op::And, a, a, Reg::T0);
+            }
+            BinaryOp::Intersection => return self.op(Op::And, a, a, b),
+            BinaryOp::SymmetricDifference => return self.op(Op::Xor, a, a, b),
+            _ => return,
+        };
+        let (ft0, ft1) = (FReg::FT0, FReg::FT1);
+        self.emit(Instruction::FmvDX(ft0, a));
+        self.emit(Instruction::FmvDX(ft1, b));
+        self.emit(Instruction::FOp(fop, ft0, ft0, ft1));
+        self.emit(Instruction::FmvXD(a, ft0));
+    }
+
+    fn membership(&mut self, a: Reg, b: Reg) {
+        self.op_imm(OpImm::Sltiu, Reg::T0, a, 32);
+        self.op(Op::Srl, Reg::T1, b, a);
+        self.op_imm(OpImm::Andi, Reg::T1, Reg::T1, 1);
+        self.op(Op::And, a, Reg::T0, Reg::T1);
+    }
+
+    // div rundet zur 0, bei Rest # 0 mit anderem Vorzeichen als der Divisor
+    // wird korrigiert
+    fn division(&mut self, op: BinaryOp, a: Reg, b: Reg) {
+        // MIN(INTEGER) DIV -1 ist nicht darstellbar; rem liefert hier schon 0
+        if op == BinaryOp::Div {
+            let overflow = self.overflow(op.symbol());
+            let divide = self.label();
+            self.li(Reg::T0, -1);
+            self.branch_if(Cond::Ne, b, Reg::T0, &divide);
+            self.check_minimum(a, &overflow);
+            self.place(&divide);
+        }
+
+        let (quotient, remainder, adjust) = (Reg::T3, Reg::T4, Reg::T5);
+        self.op(Op::Div, quotient, a, b);
+        self.op(Op::Rem, remainder, a, b);
+        self.op(Op::Xor, adjust, remainder, b);
+        self.op(Op::Slt, adjust, adjust, Reg::ZERO);
+        self.op(Op::Sltu, Reg::T6, Reg::ZERO, remainder);
+        self.op(Op::And, adjust, adjust, Reg::T6);
+        if op == BinaryOp::Div {
+            self.op(Op::Sub, a, quotient, adjust);
+        } else {
+            self.op(Op::Sub, adjust, Reg::ZERO, adjust);
+            self.op(Op::And, adjust, adjust, b);
+            self.op(Op::Add, a, remainder, adjust);
+        }
+    }
+
+    fn compare_values(&mut self, op: CompareOp, ty: Type, a: Reg, b: Reg) {
+        match ty {
+            // Ungeordnete Vergleiche (NaN) sind nur für # wahr
+            Type::Real => {
+                let (ft0, ft1) = (FReg::FT0, FReg::FT1);
+                self.emit(Instruction::FmvDX(ft0, a));
+                self.emit(Instruction::FmvDX(ft1, b));
+                match op {
+                    CompareOp::Eq => self.emit(Instruction::FCmp(FCmp::Eq, a, ft0, ft1)),
+                    CompareOp::Ne => {
+                        self.emit(Instruction::FCmp(FCmp::Eq, a, ft0, ft1));
+                        self.op_imm(OpImm::Xori, a, a, 1);
+                    }
+                    CompareOp::Lt => self.emit(Instruction::FCmp(FCmp::Lt, a, ft0, ft1)),
+                    CompareOp::Le => self.emit(Instruction::FCmp(FCmp::Le, a, ft0, ft1)),
+                    CompareOp::Gt => self.emit(Instruction::FCmp(FCmp::Lt, a, ft1, ft0)),
+                    CompareOp::Ge => self.emit(Instruction::FCmp(FCmp::Le, a, ft1, ft0)),
+                }
+            }
+            Type::Int | Type::Set => self.comparison(op, a, a, b, false),
+            // CHAR und BOOLEAN vorzeichenlos, Zeiger und Prozeduren nur = und #
+            _ => self.comparison(op, a, a, b, true),
+        }
+    }
+
+    fn compare_strings(&mut self, op: CompareOp, a: Reg, a_length: usize, b: Reg, b_length: usize) {
+        self.mv(Reg::argument(2), b);
+        self.mv(Reg::A0, a);
+        self.li(Reg::argument(1), a_length as i64);
+        self.li(Reg::argument(3), b_length as i64);
+        self.call("oberon_compare");
+        self.comparison(op, a, Reg::A0, Reg::ZERO, false);
+    }
+
+    fn shift(&mut self, op: BinaryOp, value: Reg, shift: Reg) {
+        match op {
+            // Überlauf, wenn das Zurückschieben den Wert nicht ergibt
+            BinaryOp::Lsl => {
+                let overflow = self.overflow(op.symbol());
+                self.op(Op::Sll, Reg::T3, value, shift);
+                self.op(Op::Sra, Reg::T4, Reg::T3, shift);
+                self.branch_if(Cond::Ne, Reg::T4, value, &overflow);
+                self.mv(value, Reg::T3);
+            }
+            BinaryOp::Asr => self.op(Op::Sra, value, value, shift),
+            // Ohne Zba/Zbb: (x >>> n) | (x << (64 - n))
+            _ => {
+                self.op(Op::Srl, Reg::T3, value, shift);
+                self.op(Op::Sub, Reg::T4, Reg::ZERO, shift);
+                self.op(Op::Sll, Reg::T4, value, Reg::T4);
+                self.op(Op::Or, value, Reg::T3, Reg::T4);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::opt::OptLevel;
+    use crate::riscv::simulator::Simulator;
+    use crate::riscv::to_asm;
+    use crate::testing::checked;
+
+    fn compile(source: &str, level: OptLevel) -> Program {
+        RiscvGenerator::with_optimization(level).generate(&checked(source)).unwrap()
+    }
+
+    // Ausgabe und Laufzeitfehler der übersetzten Programme prüft der
+    // Differenztest (differential.rs) mit -O0 und -O2
+    #[test]
+    fn test_assembly() {
+        let assembly = to_asm(&compile("MODULE T;
+            TYPE R = RECORD x: INTEGER END;
+            VAR r*: R; a, b, c, d, e, f, g, h, i, j, k: INTEGER;
+            PROCEDURE P*(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END P;
+            PROCEDURE Q(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END Q;
+            BEGIN a := a + (b + (c + (d + (e + (f + (g + (h + (i + (j + (k + 1))))))))))
+            END T.", OptLevel::O0));
+
+        assert!(assembly.contains("\t.globl main\n"));
+        assert!(assembly.contains("\t.globl T.P\n"));
+        assert!(!assembly.contains("\t.globl T.Q\n"));
+        // Der Deskriptor von R ist in r vorbelegt
+        assert!(assembly.contains("\t.globl T.r\n\t.balign 8\nT.r:\n\t.dword T.desc0\n\t.zero 8\n"));
+
+        // Mit -O1 faltet ir::opt den Ausdruck zur Konstanten
+        let source = "MODULE T; VAR a: INTEGER; BEGIN a := 2 * 3 + 1 END T.";
+        assert!(!to_asm(&compile(source, OptLevel::O0)).contains("\tli s1, 7\n"));
+        assert!(to_asm(&compile(source, OptLevel::O1)).contains("\tli s1, 7\n"));
+    }
+
+    #[test]
+    fn test_stack_arguments() {
+        // x8 weicht nach a1 aus, i und y liegen auf dem Stapel
+        let source = "MODULE T;
+            IMPORT Out;
+            PROCEDURE P(a, b, c, d, e, f, g, h, i: INTEGER): INTEGER;
+            BEGIN RETURN a + h * 10 + i * 100
+            END P;
+            PROCEDURE Q(n: INTEGER; x0, x1, x2, x3, x4, x5, x6, x7, x8: REAL; b, c, d, e, f, g, h: INTEGER; y: REAL): REAL;
+            BEGIN RETURN x0 + x8 * 10.0 + y * 100.0 + FLT(h)
+            END Q;
+            BEGIN Out.Int(P(1, 2, 3, 4, 5, 6, 7, 8, 9), 0); Out.Ln;
+              Out.Real(Q(0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0, 0, 0, 0, 0, 0, 3, 4.0), 0)
+            END T.";
+        assert!(to_asm(&compile(source, OptLevel::O0)).contains("\tld t1, 0(s0)\n"));
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let program = compile(source, level);
+            let mut simulator = Simulator::new(&program).unwrap();
+            assert_eq!(simulator.run("main").unwrap(), 0);
+            assert_eq!(String::from_utf8(simulator.stdout).unwrap(), "981\n424.000000");
+        }
+    }
+}
@@ -0,0 +1,1391 @@
+use super::layout::{self, align_up, Layout, RECORD_HEADER};
+use crate::ir::opt::{self, OptLevel, Remark};
+use crate::ir::{self, BinaryOp, BlockId, Callee, CompareOp, Constant, InstKind, Terminator, Type, UnaryOp, Value};
+use crate::semantic::hir::Module;
+use crate::semantic::library::LibraryProcedure;
+use crate::semantic::{TypeArena, TypeId, TypeKind};
+use crate::wasm::{
+    BlockType, Data, FuncType, Function, Global, Import, Instruction, ValType, WasmModule, MEMORY_EXPORT, PAGE_SIZE,
+};
+use std::collections::{HashMap, HashSet};
+
+// Übersetzt ein geprüftes Modul über die IR (ir::build, danach ir::opt)
+// nach WebAssembly.
+//
+// Arrays, Records und Slots liegen im linearen Speicher: globale Variablen
+// ab Adresse 16, Slots in Rahmen auf einem Stapel, der von `stack_top`
+// abwärts wächst, NEW-Objekte in einem Heap dahinter, der nie aufgeräumt
+// wird. Adresse 0 ist NIL. Jeder Rahmen beginnt mit dem statischen Verweis
+// auf den Rahmen der umgebenden Prozedur; VAR-Parameter enthalten die
+// Adresse des Arguments. SSA-Werte sind lokale Variablen der Funktion,
+// Phi-Knoten haben zusätzlich eine Schattenvariable wie die _in-Variablen
+// des C-Backends.
+//
+// Die Blöcke einer Funktion stehen in Reihenfolge in geschachtelten
+// `block`s einer Schleife: Vorwärtssprünge verlassen die Blöcke bis zum
+// Ziel, Rückwärtssprünge setzen die Nummer des Ziels und springen über
+// `br_table` am Schleifenanfang.
+//
+// INTEGER ist i64, REAL f64, alle übrigen Grundtypen, Zeiger und
+// Prozedurvariablen (Index in die Funktionstabelle) sind i32. Records
+// beginnen mit der Adresse ihres Typdeskriptors, der wiederum auf den
+// Deskriptor des Basistyps verweist; darauf beruhen IS und Projektion.
+//
+// Laufzeitfehler rufen den Import Oberon.trap(Code, Zeile) auf und brechen
+// danach mit unreachable ab; es sind dieselben Prüfungen wie im Interpreter.
+
+// Importmodul der Laufzeitfunktionen
+pub const RUNTIME_MODULE: &str = "Oberon";
+
+// Exportname des Modulrumpfs
+pub const BODY_EXPORT: &str = "main";
+
+// Adressen 0..16 bleiben frei, damit NIL nie auf eine Variable zeigt
+const GLOBALS_START: u32 = 16;
+const STACK_SIZE: u32 = 256 * 1024;
+
+// Statischer Verweis am Anfang jedes Rahmens
+const LINK_SIZE: u32 = 8;
+
+// Globale Variablen des WebAssembly-Moduls
+const SP: u32 = 0;
+const HEAP: u32 = 1;
+const STACK_LIMIT: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapCode {
+    Index = 1,
+    Nil = 2,
+    DivisionByZero = 3,
+    Assert = 4,
+    Case = 5,
+    StackOverflow = 6,
+    OutOfMemory = 7,
+    ProcedureNil = 8,
+    Overflow = 9,
+    SetElement = 10,
+    Shift = 11,
+    Character = 12,
+}
+
+impl TrapCode {
+    pub const ALL: [TrapCode; 12] = [
+        TrapCode::Index,
+        TrapCode::Nil,
+        TrapCode::DivisionByZero,
+        TrapCode::Assert,
+        TrapCode::Case,
+        TrapCode::StackOverflow,
+        TrapCode::OutOfMemory,
+        TrapCode::ProcedureNil,
+        TrapCode::Overflow,
+        TrapCode::SetElement,
+        TrapCode::Shift,
+        TrapCode::Character,
+    ];
+
+    pub fn from_code(code: i32) -> Option<TrapCode> {
+        Self::ALL.into_iter().find(|trap| *trap as i32 == code)
+    }
+
+    // Meldung wie beim Interpreter, soweit der Host sie kennen kann
+    pub fn message(self) -> &'static str {
+        match self {
+            TrapCode::Index => "Index außerhalb der Grenzen",
+            TrapCode::Nil => "Dereferenzierung von NIL",
+            TrapCode::DivisionByZero => "Division durch 0",
+            TrapCode::Assert => "ASSERT fehlgeschlagen",
+            TrapCode::Case => "Kein CASE-Label für den Wert",
+            TrapCode::StackOverflow => "Stapelüberlauf",
+            TrapCode::OutOfMemory => "Kein Speicher mehr für NEW",
+            TrapCode::ProcedureNil => "Aufruf einer Prozedurvariablen mit Wert NIL",
+            TrapCode::Overflow => "Überlauf",
+            TrapCode::SetElement => "Mengenelement außerhalb von 0..31",
+            TrapCode::Shift => "Schiebeweite außerhalb von 0..63",
+            TrapCode::Character => "CHR-Argument ist kein gültiges Zeichen",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ProcedureInfo {
+    index: u32,
+    // Platz in der Funktionstabelle (nur Modulebene)
+    table_slot: Option<u32>,
+    result: Option<Type>,
+}
+
+pub struct WasmGenerator {
+    types: TypeArena,
+    optimization: OptLevel,
+    remarks: Vec<Remark>,
+    module: WasmModule,
+    func_types: HashMap<FuncType, u32>,
+    // Record-Layouts: Feldoffsets (alle Felder) und Größe
+    layout: Layout,
+    globals: HashMap<String, (u32, TypeId)>,
+    globals_end: u32,
+    // Typdeskriptoren und String-Konstanten hinter den globalen Variablen
+    data: Vec<u8>,
+    data_start: u32,
+    descriptors: HashMap<TypeId, u32>,
+    strings: HashMap<Vec<u8>, u32>,
+    // Funktionsindizes der Importe und Hilfsfunktionen
+    trap: u32,
+    library: HashMap<LibraryProcedure, u32>,
+    alloc: u32,
+    is_extension: u32,
+    compare: u32,
+    procedures: HashMap<String, ProcedureInfo>,
+    // Offsets der Slots jeder Funktion im Rahmen und dessen Größe
+    frames: HashMap<String, (Vec<u32>, u32)>,
+    // Funktion, deren Code gerade entsteht
+    code: Vec<Instruction>,
+    wasm_locals: Vec<ValType>,
+    param_count: u32,
+    frame_pointer: u32,
+    // Lokale Variablen der SSA-Werte, der Phi-Schatten und der Nummer des
+    // nächsten Blocks
+    values: Vec<u32>,
+    shadows: HashMap<Value, u32>,
+    next_block: u32,
+    depth: usize,
+    line: usize,
+}
+
+impl WasmGenerator {
+    pub fn new() -> Self {
+        Self::with_optimization(OptLevel::O0)
+    }
+
+    // Optimiert die IR vor der Befehlsauswahl (ir::opt)
+    pub fn with_optimization(level: OptLevel) -> Self {
+        WasmGenerator {
+            types: TypeArena::new(),
+            optimization: level,
+            remarks: Vec::new(),
+            module: WasmModule {
+                types: Vec::new(),
+                imports: Vec::new(),
+                functions: Vec::new(),
+                table: Vec::new(),
+                memory_pages: 0,
+                globals: Vec::new(),
+                data: Vec::new(),
+                start: None,
+            },
+            func_types: HashMap::new(),
+            layout: Layout::new(4),
+            globals: HashMap::new(),
+            globals_end: GLOBALS_START,
+            data: Vec::new(),
+            data_start: 0,
+            descriptors: HashMap::new(),
+            strings: HashMap::new(),
+            trap: 0,
+            library: HashMap::new(),
+            alloc: 0,
+            is_extension: 0,
+            compare: 0,
+            procedures: HashMap::new(),
+            frames: HashMap::new(),
+            code: Vec::new(),
+            wasm_locals: Vec::new(),
+            param_count: 0,
+            frame_pointer: 0,
+            values: Vec::new(),
+            shadows: HashMap::new(),
+            next_block: 0,
+            depth: 0,
+            line: 0,
+        }
+    }
+
+    // Hinweise der Optimierungen aus dem letzten Aufruf von generate
+    pub fn remarks(&self) -> &[Remark] {
+        &self.remarks
+    }
+
+    pub fn generate(&mut self, module: &Module) -> Result<WasmModule, String> {
+        *self = WasmGenerator::with_optimization(self.optimization);
+
+        for import in &module.imports {
+            if !LibraryProcedure::is_library_module(&import.module_name) {
+                return Err(format!(
+                    "Import {}: das WebAssembly-Backend übersetzt nur einzelne Module (und Out)",
+                    import.module_name
+                ));
+            }
+        }
+
+        let mut program = ir::build(module)?;
+        self.remarks = opt::optimize(&mut program, self.optimization);
+        self.types = program.types.clone();
+
+        // Importe zuerst, sie belegen die ersten Funktionsindizes
+        let trap = self.func_type(vec![ValType::I32, ValType::I32], vec![]);
+        self.trap = self.import(RUNTIME_MODULE, "trap", trap);
+        if !module.imports.is_empty() {
+            for procedure in LibraryProcedure::ALL {
+                let params = match procedure {
+                    LibraryProcedure::OutOpen | LibraryProcedure::OutLn => vec![],
+                    LibraryProcedure::OutChar => vec![ValType::I32],
+                    LibraryProcedure::OutString => vec![ValType::I32, ValType::I32],
+                    LibraryProcedure::OutInt => vec![ValType::I64, ValType::I64],
+                    LibraryProcedure::OutReal => vec![ValType::F64, ValType::I64],
+                };
+                let ty = self.func_type(params, vec![]);
+                let index = self.import(procedure.module(), procedure.name(), ty);
+                self.library.insert(procedure, index);
+            }
+        }
+
+        for global in &program.globals {
+            let address = align_up(self.globals_end, self.align(global.ty));
+            self.globals.insert(global.name.clone(), (address, global.ty));
+            self.globals_end = address + self.size(global.ty);
+        }
+        self.data_start = align_up(self.globals_end, 8);
+
+        self.generate_helpers();
+        self.register_functions(&program.functions);
+        for function in &program.functions {
+            self.generate_function(function)?;
+        }
+
+        // Typdeskriptoren der globalen Records schon beim Instanziieren
+        // setzen, damit exportierte Prozeduren auch ohne den Modulrumpf
+        // gültige Records sehen
+        let init_type = self.func_type(vec![], vec![]);
+        self.begin_function(0, 0);
+        let base = self.temp(ValType::I32);
+        let globals: Vec<(u32, TypeId)> = program.globals.iter().map(|global| self.globals[&global.name]).collect();
+        for (address, ty) in globals {
+            if self.contains_record(ty) {
+                self.emit(Instruction::I32Const(address as i32));
+                self.emit(Instruction::LocalSet(base));
+                self.init_descriptors(base, 0, ty);
+            }
+        }
+        if !self.code.is_empty() {
+            let start = self.finish_function("$init", init_type, None);
+            self.module.functions.push(start);
+            self.module.start = Some(self.function_count() - 1);
+        }
+
+        self.check_exports()?;
+        self.layout_memory();
+        Ok(std::mem::replace(&mut self.module, WasmGenerator::new().module))
+    }
+
+    // Stapel hinter den Daten, Heap dahinter; die globalen Zeiger erhalten
+    // erst jetzt ihre Anfangswerte
+    fn layout_memory(&mut self) {
+        let data_end = self.data_start + self.data.len() as u32;
+        let stack_limit = align_up(data_end, 8);
+        let stack_top = stack_limit + STACK_SIZE;
+        if !self.data.is_empty() {
+            self.module.data.push(Data { offset: self.data_start, bytes: std::mem::take(&mut self.data) });
+        }
+        self.module.globals = vec![
+            Global { name: "sp".to_string(), ty: ValType::I32, mutable: true, init: stack_top as i64 },
+            Global { name: "heap".to_string(), ty: ValType::I32, mutable: true, init: stack_top as i64 },
+            Global { name: "stack_limit".to_string(), ty: ValType::I32, mutable: false, init: stack_limit as i64 },
+        ];
+        self.module.memory_pages = stack_top.div_ceil(PAGE_SIZE) + 1;
+    }
+
+    fn check_exports(&self) -> Result<(), String> {
+        let mut seen: Vec<&str> = vec![MEMORY_EXPORT];
+        for function in &self.module.functions {
+            if let Some(export) = &function.export {
+                if seen.contains(&export.as_str()) {
+                    return Err(format!("Exportname {} ist doppelt vergeben", export));
+                }
+                seen.push(export);
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Modulaufbau
+    // ========================================================================
+
+    fn func_type(&mut self, params: Vec<ValType>, results: Vec<ValType>) -> u32 {
+        let ty = FuncType { params, results };
+        if let Some(&index) = self.func_types.get(&ty) {
+            return index;
+        }
+        let index = self.module.types.len() as u32;
+        self.module.types.push(ty.clone());
+        self.func_types.insert(ty, index);
+        index
+    }
+
+    fn import(&mut self, module: &str, name: &str, ty: u32) -> u32 {
+        self.module.imports.push(Import { module: module.to_string(), name: name.to_string(), ty });
+        self.module.imports.len() as u32 - 1
+    }
+
+    fn function_count(&self) -> u32 {
+        (self.module.imports.len() + self.module.functions.len()) as u32
+    }
+
+    fn begin_function(&mut self, param_count: u32, depth: usize) {
+        self.code.clear();
+        self.wasm_locals.clear();
+        self.param_count = param_count;
+        self.depth = depth;
+        self.frame_pointer = self.temp(ValType::I32);
+    }
+
+    fn finish_function(&mut self, name: &str, ty: u32, export: Option<String>) -> Function {
+        self.emit(Instruction::End);
+        Function {
+            name: name.to_string(),
+            ty,
+            export,
+            locals: std::mem::take(&mut self.wasm_locals),
+            body: std::mem::take(&mut self.code),
+        }
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.code.push(instruction);
+    }
+
+    // Neue lokale Variable der WebAssembly-Funktion für Zwischenwerte
+    fn temp(&mut self, ty: ValType) -> u32 {
+        self.wasm_locals.push(ty);
+        self.param_count + self.wasm_locals.len() as u32 - 1
+    }
+
+    fn emit_trap(&mut self, code: TrapCode) {
+        self.emit(Instruction::I32Const(code as i32));
+        self.emit(Instruction::I32Const(self.line as i32));
+        self.emit(Instruction::Call(self.trap));
+        self.emit(Instruction::Unreachable);
+    }
+
+    // Trap, wenn der i32-Wert oben auf dem Stapel ungleich 0 ist
+    fn trap_if(&mut self, code: TrapCode) {
+        self.emit(Instruction::If(BlockType::Empty));
+        self.emit_trap(code);
+        self.emit(Instruction::End);
+    }
+
+    // a op b für die beiden INTEGER oben auf dem Stapel mit Überlaufprüfung
+    // (+, - und *); das Ergebnis bleibt auf dem Stapel
+    fn checked(&mut self, op: Instruction) {
+        use Instruction::*;
+        let a = self.temp(ValType::I64);
+        let b = self.temp(ValType::I64);
+        let result = self.temp(ValType::I64);
+        self.code.extend([LocalSet(b), LocalTee(a), LocalGet(b), op.clone(), LocalSet(result)]);
+        match op {
+            // Überlauf, wenn das Ergebnis ein anderes Vorzeichen hat als a
+            // und (bei +) b bzw. (bei -) als a, aber nicht b
+            I64Add => self.code.extend([LocalGet(a), LocalGet(result), I64Xor, LocalGet(b), LocalGet(result), I64Xor]),
+            I64Sub => self.code.extend([LocalGet(a), LocalGet(b), I64Xor, LocalGet(a), LocalGet(result), I64Xor]),
+            // Bei * muss result / a wieder b ergeben; a = -1 prüft b direkt,
+            // weil MIN / -1 selbst einen Trap der Maschine auslöst
+            _ => {
+                self.code.extend([LocalGet(a), I64Const(0), I64Ne, If(BlockType::Empty)]);
+                self.code.extend([LocalGet(a), I64Const(-1), I64Eq, If(BlockType::Value(ValType::I32))]);
+                self.code.extend([LocalGet(b), I64Const(i64::MIN), I64Eq, Else]);
+                self.code.extend([LocalGet(result), LocalGet(a), I64DivS, LocalGet(b), I64Ne, End]);
+                self.trap_if(TrapCode::Overflow);
+                self.code.extend([End, LocalGet(result)]);
+                return;
+            }
+        }
+        self.code.extend([I64And, I64Const(0), I64LtS]);
+        self.trap_if(TrapCode::Overflow);
+        self.emit(LocalGet(result));
+    }
+
+    // ========================================================================
+    // Hilfsfunktionen der Laufzeit
+    // ========================================================================
+
+    fn generate_helpers(&mut self) {
+        use Instruction::*;
+
+        // alloc(size, line) -> Adresse; vergrößert den Speicher bei Bedarf
+        let ty = self.func_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        self.begin_function(2, 0);
+        let object = self.frame_pointer;
+        self.code.extend([
+            GlobalGet(HEAP),
+            LocalSet(object),
+            GlobalGet(HEAP),
+            LocalGet(0),
+            I32Const(7),
+            I32Add,
+            I32Const(-8),
+            I32And,
+            I32Add,
+            GlobalSet(HEAP),
+            Block(BlockType::Empty),
+            GlobalGet(HEAP),
+            MemorySize,
+            I32Const(16),
+            I32Shl,
+            I32LeU,
+            BrIf(0),
+            GlobalGet(HEAP),
+            MemorySize,
+            I32Const(16),
+            I32Shl,
+            I32Sub,
+            I32Const(PAGE_SIZE as i32 - 1),
+            I32Add,
+            I32Const(16),
+            I32ShrU,
+            MemoryGrow,
+            I32Const(-1),
+            I32Ne,
+            BrIf(0),
+            I32Const(TrapCode::OutOfMemory as i32),
+            LocalGet(1),
+            Call(self.trap),
+            Unreachable,
+            End,
+            LocalGet(object),
+        ]);
+        self.alloc = self.function_count();
+        let function = self.finish_function("$alloc", ty, None);
+        self.module.functions.push(function);
+
+        // is_extension(Deskriptor, Ziel): folgt der Kette der Basistypen
+        let ty = self.func_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        self.begin_function(2, 0);
+        self.code.extend([
+            Block(BlockType::Empty),
+            Loop(BlockType::Empty),
+            LocalGet(0),
+            LocalGet(1),
+            I32Eq,
+            If(BlockType::Empty),
+            I32Const(1),
+            Return,
+            End,
+            LocalGet(0),
+            I32Load,
+            LocalTee(0),
+            I32Eqz,
+            BrIf(1),
+            Br(0),
+            End,
+            End,
+            I32Const(0),
+        ]);
+        self.is_extension = self.function_count();
+        let function = self.finish_function("$is_extension", ty, None);
+        self.module.functions.push(function);
+
+        // compare(a, Länge a, b, Länge b) -> -1, 0, 1; Zeichenketten enden
+        // am ersten 0X oder an der Länge
+        let ty = self.func_type(vec![ValType::I32; 4], vec![ValType::I32]);
+        self.begin_function(4, 0);
+        let index = self.frame_pointer;
+        let left = self.temp(ValType::I32);
+        let right = self.temp(ValType::I32);
+        self.emit(Loop(BlockType::Empty));
+        for (string, length, target) in [(0, 1, left), (2, 3, right)] {
+            self.code.extend([
+                LocalGet(index),
+                LocalGet(length),
+                I32LtU,
+                If(BlockType::Value(ValType::I32)),
+                LocalGet(string),
+                LocalGet(index),
+                I32Add,
+                I32Load8U,
+                Else,
+                I32Const(0),
+                End,
+                LocalSet(target),
+            ]);
+        }
+        self.code.extend([
+            LocalGet(left),
+            LocalGet(right),
+            I32Ne,
+            If(BlockType::Empty),
+            I32Const(-1),
+            I32Const(1),
+            LocalGet(left),
+            LocalGet(right),
+            I32LtU,
+            Select,
+            Return,
+            End,
+            LocalGet(left),
+            I32Eqz,
+            If(BlockType::Empty),
+            I32Const(0),
+            Return,
+            End,
+            LocalGet(index),
+            I32Const(1),
+            I32Add,
+            LocalSet(index),
+            Br(0),
+            End,
+            Unreachable,
+        ]);
+        self.compare = self.function_count();
+        let function = self.finish_function("$compare", ty, None);
+        self.module.functions.push(function);
+    }
+
+    // ========================================================================
+    // Typen und Speicherlayout
+    // ========================================================================
+
+    fn val_type(ty: Type) -> ValType {
+        match ty {
+            Type::Int => ValType::I64,
+            Type::Real => ValType::F64,
+            _ => ValType::I32,
+        }
+    }
+
+    fn contains_record(&self, ty: TypeId) -> bool {
+        layout::contains_record(&self.types, ty)
+    }
+
+    // Zeiger und Prozedurvariablen belegen 4 Bytes
+    fn size(&mut self, ty: TypeId) -> u32 {
+        self.layout.size(&self.types, ty)
+    }
+
+    fn align(&self, ty: TypeId) -> u32 {
+        self.layout.align(&self.types, ty)
+    }
+
+    fn record_layout(&mut self, record: TypeId) -> (Vec<u32>, u32) {
+        self.layout.record(&self.types, record)
+    }
+
+    // Deskriptor: Adresse des Deskriptors des Basistyps (0 ohne Basistyp)
+    fn descriptor(&mut self, record: TypeId) -> u32 {
+        if let Some(&address) = self.descriptors.get(&record) {
+            return address;
+        }
+        let base = match self.types.kind(record) {
+            TypeKind::Record { base: Some(base), .. } => *base,
+            _ => TypeId::NIL,
+        };
+        let base = if base == TypeId::NIL { 0 } else { self.descriptor(base) };
+        let address = self.data_start + self.data.len() as u32;
+        self.data.extend_from_slice(&base.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]);
+        self.descriptors.insert(record, address);
+        address
+    }
+
+    // Konstante Zeichenkette mit 0X am Ende
+    fn string(&mut self, text: &[u8]) -> u32 {
+        if let Some(&address) = self.strings.get(text) {
+            return address;
+        }
+        let address = self.data_start + self.data.len() as u32;
+        self.data.extend_from_slice(text);
+        self.data.push(0);
+        self.strings.insert(text.to_vec(), address);
+        address
+    }
+
+    fn load(&mut self, ty: Type) {
+        self.emit(match ty {
+            Type::Int => Instruction::I64Load,
+            Type::Real => Instruction::F64Load,
+            Type::Bool | Type::Char => Instruction::I32Load8U,
+            Type::Set | Type::Ptr => Instruction::I32Load,
+        });
+    }
+
+    fn store(&mut self, ty: Type) {
+        self.emit(match ty {
+            Type::Int => Instruction::I64Store,
+            Type::Real => Instruction::F64Store,
+            Type::Bool | Type::Char => Instruction::I32Store8,
+            Type::Set | Type::Ptr => Instruction::I32Store,
+        });
+    }
+
+    // Setzt die Typdeskriptoren aller Records in einem Wert vom Typ `ty` an
+    // der Adresse base + offset (base ist eine lokale i32-Variable)
+    fn init_descriptors(&mut self, base: u32, offset: u32, ty: TypeId) {
+        use Instruction::*;
+        match self.types.kind(ty).clone() {
+            TypeKind::Record { .. } => {
+                let descriptor = self.descriptor(ty);
+                self.code.extend([LocalGet(base), I32Const(offset as i32), I32Add, I32Const(descriptor as i32), I32Store]);
+                let fields: Vec<TypeId> = self.types.fields(ty).iter().map(|field| field.ty).collect();
+                let offsets = self.record_layout(ty).0;
+                for (field, field_offset) in fields.into_iter().zip(offsets) {
+                    if self.contains_record(field) {
+                        self.init_descriptors(base, offset + field_offset, field);
+                    }
+                }
+            }
+            TypeKind::Array { length, element } if self.contains_record(element) => {
+                let size = self.size(element);
+                let current = self.temp(ValType::I32);
+                let end = self.temp(ValType::I32);
+                self.code.extend([
+                    LocalGet(base),
+                    I32Const(offset as i32),
+                    I32Add,
+                    LocalTee(current),
+                    I32Const((length as u32 * size) as i32),
+                    I32Add,
+                    LocalSet(end),
+                    Block(BlockType::Empty),
+                    Loop(BlockType::Empty),
+                    LocalGet(current),
+                    LocalGet(end),
+                    I32GeU,
+                    BrIf(1),
+                ]);
+                self.init_descriptors(current, 0, element);
+                self.code.extend([LocalGet(current), I32Const(size as i32), I32Add, LocalSet(current), Br(0), End, End]);
+            }
+            _ => {}
+        }
+    }
+
+    // ========================================================================
+    // Funktionen
+    // ========================================================================
+
+    // Funktionsindizes, Tabellenplätze und Rahmen aller Funktionen, bevor
+    // der erste Aufruf entsteht
+    fn register_functions(&mut self, functions: &[ir::Function]) {
+        for function in functions {
+            let index = self.function_count();
+            let table_slot = if function.depth == 1 {
+                self.module.table.push(index);
+                Some(self.module.table.len() as u32)
+            } else {
+                None
+            };
+            let info = ProcedureInfo { index, table_slot, result: function.result };
+            self.procedures.insert(function.name.clone(), info);
+            let frame = self.frame_layout(function);
+            self.frames.insert(function.name.clone(), frame);
+            // Platzhalter, bis der Code erzeugt ist
+            self.module.functions.push(Function {
+                name: function.name.clone(),
+                ty: 0,
+                export: None,
+                locals: Vec::new(),
+                body: Vec::new(),
+            });
+        }
+    }
+
+    // Rahmen: statischer Verweis, dann die Slots; VAR-Parameter belegen eine
+    // Adresse
+    fn frame_layout(&mut self, function: &ir::Function) -> (Vec<u32>, u32) {
+        let mut offset = LINK_SIZE;
+        let mut offsets = Vec::new();
+        for slot in &function.slots {
+            let (size, align) = if slot.by_reference { (4, 4) } else { (self.size(slot.ty), self.align(slot.ty)) };
+            offset = align_up(offset, align);
+            offsets.push(offset);
+            offset += size;
+        }
+        (offsets, align_up(offset, 8))
+    }
+
+    // Parameter nach ihren Werttypen; geschachtelte Prozeduren erhalten
+    // vorne den statischen Verweis
+    fn signature(&mut self, params: &[Type], result: Option<Type>, depth: usize) -> u32 {
+        let mut wasm_params = Vec::new();
+        if depth > 1 {
+            wasm_params.push(ValType::I32);
+        }
+        wasm_params.extend(params.iter().map(|&ty| Self::val_type(ty)));
+        let results = result.map(|ty| vec![Self::val_type(ty)]).unwrap_or_default();
+        self.func_type(wasm_params, results)
+    }
+
+    fn generate_function(&mut self, function: &ir::Function) -> Result<(), String> {
+        use Instruction::*;
+        let info = self.procedures[&function.name];
+        let (_, frame_size) = self.frames[&function.name].clone();
+        let params: Vec<Type> = function.params.iter().map(|param| function.value_type(param.value)).collect();
+        let ty = self.signature(&params, function.result, function.depth);
+        let link = if function.depth > 1 { 1 } else { 0 };
+        self.begin_function(link + params.len() as u32, function.depth);
+        let fp = self.frame_pointer;
+
+        // Lokale Variablen der Werte; Parameter sind schon welche
+        self.values.clear();
+        for (index, &ty) in function.values.iter().enumerate() {
+            let local = match function.params.iter().position(|param| param.value.index() == index) {
+                Some(position) => link + position as u32,
+                None => self.temp(Self::val_type(ty)),
+            };
+            self.values.push(local);
+        }
+        self.shadows.clear();
+        for phi in function.blocks.iter().flat_map(|block| &block.phis) {
+            let shadow = self.temp(Self::val_type(function.value_type(phi.dest)));
+            self.shadows.insert(phi.dest, shadow);
+        }
+        self.next_block = self.temp(ValType::I32);
+
+        // Prolog: Rahmen anlegen und mit 0 füllen; ein Stapelüberlauf gilt
+        // der ersten Zeile der Funktion
+        self.line = function.blocks[0].instructions.first().map(|instruction| instruction.line).unwrap_or(function.line);
+        self.code.extend([
+            GlobalGet(SP),
+            I32Const(frame_size as i32),
+            I32Sub,
+            LocalTee(fp),
+            GlobalGet(STACK_LIMIT),
+            I32LtU,
+        ]);
+        self.trap_if(TrapCode::StackOverflow);
+        self.code.extend([LocalGet(fp), GlobalSet(SP), LocalGet(fp), I32Const(0), I32Const(frame_size as i32), MemoryFill]);
+        if function.depth > 1 {
+            self.code.extend([LocalGet(fp), LocalGet(0), I32Store]);
+        }
+
+        // Block i steht hinter dem Ende des i-ten inneren `block`s; der
+        // innerste enthält nur den Sprung über die Tabelle
+        let count = function.blocks.len() as u32;
+        self.emit(Loop(BlockType::Empty));
+        for _ in 0..count {
+            self.emit(Block(BlockType::Empty));
+        }
+        self.code.extend([LocalGet(self.next_block), BrTable((0..count).collect(), 0), End]);
+        for id in function.block_ids() {
+            self.generate_block(function, id, frame_size)?;
+            if id.index() + 1 < function.blocks.len() {
+                self.emit(End);
+            }
+        }
+        self.code.extend([End, Unreachable]);
+
+        let export = if function.depth == 0 {
+            Some(BODY_EXPORT.to_string())
+        } else if function.depth == 1 && function.exported {
+            Some(function.name.clone())
+        } else {
+            None
+        };
+        let result = self.finish_function(&function.name, ty, export);
+        self.module.functions[info.index as usize - self.module.imports.len()] = result;
+        Ok(())
+    }
+
+    // Rahmen der Funktion auf Tiefe `level`: den statischen Verweisen folgen
+    fn frame_address(&mut self, level: usize) {
+        self.emit(Instruction::LocalGet(self.frame_pointer));
+        for _ in level..self.depth {
+            self.emit(Instruction::I32Load);
+        }
+    }
+
+    fn generate_block(&mut self, function: &ir::Function, id: BlockId, frame_size: u32) -> Result<(), String> {
+        use Instruction::*;
+        let block = function.block(id);
+        for phi in &block.phis {
+            self.code.extend([LocalGet(self.shadows[&phi.dest]), LocalSet(self.values[phi.dest.index()])]);
+        }
+
+        for instruction in &block.instructions {
+            self.line = instruction.line;
+            if self.generate_instruction(function, instruction)? {
+                match instruction.dest {
+                    Some(dest) => self.emit(LocalSet(self.values[dest.index()])),
+                    None => self.emit(Drop),
+                }
+            }
+        }
+
+        // Schatten der Phi-Knoten in den Nachfolgern
+        let mut seen = HashSet::new();
+        for successor in block.terminator.successors() {
+            if !seen.insert(successor) {
+                continue;
+            }
+            for phi in &function.block(successor).phis {
+                if let Some(&(_, value)) = phi.arguments.iter().find(|(source, _)| *source == id) {
+                    self.code.extend([LocalGet(self.values[value.index()]), LocalSet(self.shadows[&phi.dest])]);
+                }
+            }
+        }
+        self.generate_terminator(function, id, &block.terminator, frame_size);
+        Ok(())
+    }
+
+    // Sprung von Block `from` nach `to` innerhalb von `nesting` weiteren
+    // Konstrukten; Vorwärtssprünge verlassen die Blöcke bis zum Ziel,
+    // Rückwärtssprünge gehen über die Tabelle am Schleifenanfang
+    fn jump(&mut self, function: &ir::Function, from: BlockId, to: BlockId, nesting: u32) {
+        use Instruction::*;
+        let (from, to) = (from.0, to.0);
+        if to == from + 1 && nesting == 0 {
+            return;
+        }
+        if to > from {
+            self.emit(Br(to - from - 1 + nesting));
+        } else {
+            let outer = function.blocks.len() as u32 - 1 - from;
+            self.code.extend([I32Const(to as i32), LocalSet(self.next_block), Br(outer + nesting)]);
+        }
+    }
+
+    fn generate_terminator(&mut self, function: &ir::Function, id: BlockId, terminator: &Terminator, frame_size: u32) {
+        use Instruction::*;
+        match terminator {
+            Terminator::Jump(target) => self.jump(function, id, *target, 0),
+            Terminator::Branch { condition, then_block, else_block } => {
+                self.code.extend([LocalGet(self.values[condition.index()]), If(BlockType::Empty)]);
+                self.jump(function, id, *then_block, 1);
+                self.emit(End);
+                self.jump(function, id, *else_block, 0);
+            }
+            Terminator::Switch { value, cases, default } => {
+                // CHAR-Werte werden für den Vergleich zu i64
+                let local = self.values[value.index()];
+                let value = if function.value_type(*value) == Type::Int {
+                    local
+                } else {
+                    let wide = self.temp(ValType::I64);
+                    self.code.extend([LocalGet(local), I64ExtendI32U, LocalSet(wide)]);
+                    wide
+                };
+                for &(start, end, target) in cases {
+                    if start == end {
+                        self.code.extend([LocalGet(value), I64Const(start), I64Eq]);
+                    } else {
+                        self.code.extend([LocalGet(value), I64Const(start), I64GeS, LocalGet(value), I64Const(end), I64LeS, I32And]);
+                    }
+                    self.emit(If(BlockType::Empty));
+                    self.jump(function, id, target, 1);
+                    self.emit(End);
+                }
+                self.jump(function, id, *default, 0);
+            }
+            Terminator::Return(value) => {
+                self.code.extend([LocalGet(self.frame_pointer), I32Const(frame_size as i32), I32Add, GlobalSet(SP)]);
+                if let Some(value) = value {
+                    self.emit(LocalGet(self.values[value.index()]));
+                }
+                self.emit(Return);
+            }
+            Terminator::CaseTrap { line, .. } => {
+                self.line = *line;
+                self.emit_trap(TrapCode::Case);
+            }
+        }
+    }
+
+    // ========================================================================
+    // Befehle
+    // ========================================================================
+
+    // Übersetzt einen Befehl; true, wenn sein Ergebnis auf dem Stapel liegt
+    fn generate_instruction(&mut self, function: &ir::Function, instruction: &ir::Instruction) -> Result<bool, String> {
+        use Instruction::*;
+        match &instruction.kind {
+            InstKind::Const(constant) => self.emit(match constant {
+                Constant::Int(value) => I64Const(*value),
+                Constant::Real(value) => F64Const(*value),
+                Constant::Bool(value) => I32Const(*value as i32),
+                Constant::Char(value) => I32Const(*value as i32),
+                Constant::Set(bits) => I32Const(*bits as i32),
+                Constant::Nil => I32Const(0),
+            }),
+            InstKind::String(text) => {
+                let address = self.string(text);
+                self.emit(I32Const(address as i32));
+            }
+            InstKind::Global(name) => {
+                let (address, _) = *self.globals.get(name).ok_or_else(|| format!("Variable {} nicht gefunden", name))?;
+                self.emit(I32Const(address as i32));
+            }
+            InstKind::Slot { depth, index } => {
+                let owner = if *depth == function.depth { function.name.clone() } else { function.ancestor(*depth) };
+                let offset = self
+                    .frames
+                    .get(&owner)
+                    .and_then(|(slots, _)| slots.get(*index))
+                    .copied()
+                    .ok_or_else(|| format!("Slot {} von {} nicht gefunden", index, owner))?;
+                self.frame_address(*depth);
+                self.code.extend([I32Const(offset as i32), I32Add]);
+            }
+            InstKind::Procedure(name) => match self.procedure(name)?.table_slot {
+                Some(slot) => self.emit(I32Const(slot as i32)),
+                None => return Err(format!("Lokale Prozedur {} kann kein Wert sein", name)),
+            },
+            InstKind::Field { base, record, field } => {
+                let offset = self.record_layout(*record).0[*field];
+                self.code.extend([LocalGet(self.local(base)), I32Const(offset as i32), I32Add]);
+            }
+            InstKind::Element { base, array, index } => {
+                let element = match self.types.kind(*array) {
+                    TypeKind::Array { element, .. } => *element,
+                    _ => *array,
+                };
+                let size = self.size(element);
+                self.code.extend([LocalGet(self.local(base)), LocalGet(self.local(index)), I32WrapI64, I32Const(size as i32), I32Mul, I32Add]);
+            }
+            InstKind::Load { ty, address } => {
+                self.emit(LocalGet(self.local(address)));
+                self.load(*ty);
+            }
+            InstKind::Store { ty, address, value } => {
+                self.code.extend([LocalGet(self.local(address)), LocalGet(self.local(value))]);
+                self.store(*ty);
+            }
+            // Records behalten ihren Deskriptor
+            InstKind::Copy { ty, dest, source } => {
+                let header = if layout::is_record(&self.types, *ty) { RECORD_HEADER } else { 0 };
+                let size = self.size(*ty);
+                self.code.extend([
+                    LocalGet(self.local(dest)),
+                    I32Const(header as i32),
+                    I32Add,
+                    LocalGet(self.local(source)),
+                    I32Const(header as i32),
+                    I32Add,
+                    I32Const((size - header) as i32),
+                    MemoryCopy,
+                ]);
+            }
+            InstKind::CopyString { dest, source, length } => {
+                self.code.extend([LocalGet(self.local(dest)), LocalGet(self.local(source)), I32Const(*length as i32), MemoryCopy]);
+            }
+            InstKind::InitDescriptors { ty, address } => self.init_descriptors(self.local(address), 0, *ty),
+            InstKind::New { record } => {
+                let size = self.record_layout(*record).1;
+                let object = self.temp(ValType::I32);
+                self.code.extend([I32Const(size as i32), I32Const(self.line as i32), Call(self.alloc), LocalSet(object)]);
+                self.init_descriptors(object, 0, *record);
+                self.emit(LocalGet(object));
+            }
+            InstKind::Unary { op, operand } => self.generate_unary(*op, self.local(operand)),
+            InstKind::Binary { op, left, right } => self.generate_binary(*op, self.local(left), self.local(right)),
+            InstKind::Compare { op, left, right } => {
+                let ty = function.value_type(*left);
+                self.code.extend([LocalGet(self.local(left)), LocalGet(self.local(right))]);
+                self.emit(match (ty, op) {
+                    (Type::Int, CompareOp::Eq) => I64Eq,
+                    (Type::Int, CompareOp::Ne) => I64Ne,
+                    (Type::Int, CompareOp::Lt) => I64LtS,
+                    (Type::Int, CompareOp::Le) => I64LeS,
+                    (Type::Int, CompareOp::Gt) => I64GtS,
+                    (Type::Int, CompareOp::Ge) => I64GeS,
+                    (Type::Real, CompareOp::Eq) => F64Eq,
+                    (Type::Real, CompareOp::Ne) => F64Ne,
+                    (Type::Real, CompareOp::Lt) => F64Lt,
+                    (Type::Real, CompareOp::Le) => F64Le,
+                    (Type::Real, CompareOp::Gt) => F64Gt,
+                    (Type::Real, CompareOp::Ge) => F64Ge,
+                    (_, CompareOp::Eq) => I32Eq,
+                    (_, CompareOp::Ne) => I32Ne,
+                    (Type::Bool | Type::Char, CompareOp::Lt) => I32LtU,
+                    (Type::Bool | Type::Char, CompareOp::Le) => I32LeU,
+                    (Type::Bool | Type::Char, CompareOp::Gt) => I32GtU,
+                    (Type::Bool | Type::Char, CompareOp::Ge) => I32GeU,
+                    _ => return Err(format!("Vergleich {:?} für {}", op, ty.name())),
+                });
+            }
+            // Vergleiche von Zeichenketten über die Hilfsfunktion
+            InstKind::CompareStrings { op, left, left_length, right, right_length } => {
+                self.code.extend([
+                    LocalGet(self.local(left)),
+                    I32Const(*left_length as i32),
+                    LocalGet(self.local(right)),
+                    I32Const(*right_length as i32),
+                    Call(self.compare),
+                    I32Const(0),
+                ]);
+                self.emit(match op {
+                    CompareOp::Eq => I32Eq,
+                    CompareOp::Ne => I32Ne,
+                    CompareOp::Lt => I32LtS,
+                    CompareOp::Le => I32LeS,
+                    CompareOp::Gt => I32GtS,
+                    CompareOp::Ge => I32GeS,
+                });
+            }
+            InstKind::TypeTest { address, record } => {
+                let descriptor = self.descriptor(*record);
+                self.code.extend([LocalGet(self.local(address)), I32Load, I32Const(descriptor as i32), Call(self.is_extension)]);
+            }
+            InstKind::Call { callee, arguments } => return self.generate_call(function, instruction, callee, arguments),
+            InstKind::Output { procedure, arguments } => {
+                let index = *self
+                    .library
+                    .get(procedure)
+                    .ok_or_else(|| format!("{} ist nicht importiert", procedure))?;
+                for argument in arguments {
+                    self.emit(LocalGet(self.local(argument)));
+                }
+                // Die Länge einer Zeichenkette ist ein INTEGER
+                if *procedure == LibraryProcedure::OutString {
+                    self.emit(I32WrapI64);
+                }
+                self.emit(Call(index));
+            }
+            InstKind::CheckIndex { index, length } => {
+                self.code.extend([LocalGet(self.local(index)), I64Const(*length as i64), I64GeU]);
+                self.trap_if(TrapCode::Index);
+            }
+            InstKind::CheckNil(value) => {
+                self.code.extend([LocalGet(self.local(value)), I32Eqz]);
+                self.trap_if(TrapCode::Nil);
+            }
+            InstKind::Assert(value) => {
+                self.code.extend([LocalGet(self.local(value)), I32Eqz]);
+                self.trap_if(TrapCode::Assert);
+            }
+        }
+        Ok(!matches!(
+            instruction.kind,
+            InstKind::Store { .. }
+                | InstKind::Copy { .. }
+                | InstKind::CopyString { .. }
+                | InstKind::InitDescriptors { .. }
+                | InstKind::Output { .. }
+                | InstKind::CheckIndex { .. }
+                | InstKind::CheckNil(_)
+                | InstKind::Assert(_)
+        ))
+    }
+
+    // Lokale Variable eines Werts
+    fn local(&self, value: &Value) -> u32 {
+        self.values[value.index()]
+    }
+
+    fn generate_unary(&mut self, op: UnaryOp, value: u32) {
+        use Instruction::*;
+        match op {
+            UnaryOp::Neg => {
+                self.code.extend([I64Const(0), LocalGet(value)]);
+                self.checked(I64Sub);
+            }
+            UnaryOp::Abs => {
+                self.code.extend([LocalGet(value), I64Const(i64::MIN), I64Eq]);
+                self.trap_if(TrapCode::Overflow);
+                self.code.extend([
+                    I64Const(0),
+                    LocalGet(value),
+                    I64Sub,
+                    LocalGet(value),
+                    LocalGet(value),
+                    I64Const(0),
+                    I64LtS,
+                    Select,
+                ]);
+            }
+            UnaryOp::FNeg => self.code.extend([LocalGet(value), F64Neg]),
+            UnaryOp::FAbs => self.code.extend([LocalGet(value), F64Abs]),
+            UnaryOp::Not => self.code.extend([LocalGet(value), I32Eqz]),
+            UnaryOp::Complement => self.code.extend([LocalGet(value), I32Const(-1), I32Xor]),
+            UnaryOp::Odd => self.code.extend([LocalGet(value), I64Const(1), I64And, I32WrapI64]),
+            UnaryOp::IntToReal => self.code.extend([LocalGet(value), F64ConvertI64S]),
+            // Nur abgerundete Werte in -2^63..2^63 passen in ein INTEGER
+            UnaryOp::Floor => {
+                let floor = self.temp(ValType::F64);
+                self.code.extend([
+                    LocalGet(value),
+                    F64Floor,
+                    LocalTee(floor),
+                    F64Const(-9223372036854775808.0),
+                    F64Ge,
+                    LocalGet(floor),
+                    F64Const(9223372036854775808.0),
+                    F64Lt,
+                    I32And,
+                    I32Eqz,
+                ]);
+                self.trap_if(TrapCode::Overflow);
+                self.code.extend([LocalGet(floor), I64TruncF64S]);
+            }
+            UnaryOp::Ord => self.code.extend([LocalGet(value), I64ExtendI32U]),
+            UnaryOp::Chr => {
+                self.code.extend([LocalGet(value), I64Const(256), I64GeU]);
+                self.trap_if(TrapCode::Character);
+                self.code.extend([LocalGet(value), I32WrapI64]);
+            }
+        }
+    }
+
+    fn generate_binary(&mut self, op: BinaryOp, a: u32, b: u32) {
+        use Instruction::*;
+        match op {
+            BinaryOp::Add | BinaryOp::Inc | BinaryOp::For => {
+                self.code.extend([LocalGet(a), LocalGet(b)]);
+                self.checked(I64Add);
+            }
+            BinaryOp::Sub | BinaryOp::Dec => {
+                self.code.extend([LocalGet(a), LocalGet(b)]);
+                self.checked(I64Sub);
+            }
+            BinaryOp::Mul => {
+                self.code.extend([LocalGet(a), LocalGet(b)]);
+                self.checked(I64Mul);
+            }
+            BinaryOp::Div | BinaryOp::Mod => self.generate_division(op, a, b),
+            BinaryOp::FAdd => self.code.extend([LocalGet(a), LocalGet(b), F64Add]),
+            BinaryOp::FSub => self.code.extend([LocalGet(a), LocalGet(b), F64Sub]),
+            BinaryOp::FMul => self.code.extend([LocalGet(a), LocalGet(b), F64Mul]),
+            BinaryOp::FDiv => self.code.extend([LocalGet(a), LocalGet(b), F64Div]),
+            BinaryOp::Union => self.code.extend([LocalGet(a), LocalGet(b), I32Or]),
+            BinaryOp::Difference => self.code.extend([LocalGet(a), LocalGet(b), I32Const(-1), I32Xor, I32And]),
+            BinaryOp::Intersection => self.code.extend([LocalGet(a), LocalGet(b), I32And]),
+            BinaryOp::SymmetricDifference => self.code.extend([LocalGet(a), LocalGet(b), I32Xor]),
+            BinaryOp::In => self.code.extend([
+                LocalGet(a),
+                I64Const(32),
+                I64LtU,
+                LocalGet(b),
+                LocalGet(a),
+                I32WrapI64,
+                I32ShrU,
+                I32Const(1),
+                I32And,
+                I32And,
+            ]),
+            // {a..b}: (-1 << a) & (-1 >> (31 - b)), leer für a > b
+            BinaryOp::Range => {
+                for element in [a, b] {
+                    self.code.extend([LocalGet(element), I64Const(32), I64GeU]);
+                    self.trap_if(TrapCode::SetElement);
+                }
+                self.code.extend([
+                    I32Const(-1),
+                    LocalGet(a),
+                    I32WrapI64,
+                    I32Shl,
+                    I32Const(-1),
+                    I32Const(31),
+                    LocalGet(b),
+                    I32WrapI64,
+                    I32Sub,
+                    I32ShrU,
+                    I32And,
+                    I32Const(0),
+                    LocalGet(a),
+                    LocalGet(b),
+                    I64LeS,
+                    Select,
+                ]);
+            }
+            BinaryOp::Lsl | BinaryOp::Asr | BinaryOp::Ror => {
+                self.code.extend([LocalGet(b), I64Const(64), I64GeU]);
+                self.trap_if(TrapCode::Shift);
+                self.code.extend([LocalGet(a), LocalGet(b)]);
+                match op {
+                    // Überlauf, wenn das Zurückschieben a nicht wiederherstellt
+                    BinaryOp::Lsl => {
+                        let result = self.temp(ValType::I64);
+                        self.code.extend([I64Shl, LocalTee(result), LocalGet(b), I64ShrS, LocalGet(a), I64Ne]);
+                        self.trap_if(TrapCode::Overflow);
+                        self.emit(LocalGet(result));
+                    }
+                    BinaryOp::Asr => self.emit(I64ShrS),
+                    _ => self.emit(I64Rotr),
+                }
+            }
+        }
+    }
+
+    // DIV und MOD runden zur negativen Unendlichkeit:
+    // q = a / b - (a % b # 0 & a ^ b < 0), r = a % b + (r # 0 & r ^ b < 0 ? b : 0)
+    fn generate_division(&mut self, op: BinaryOp, a: u32, b: u32) {
+        use Instruction::*;
+        self.code.extend([LocalGet(b), I64Eqz]);
+        self.trap_if(TrapCode::DivisionByZero);
+
+        if op == BinaryOp::Div {
+            self.code.extend([LocalGet(a), I64Const(i64::MIN), I64Eq, LocalGet(b), I64Const(-1), I64Eq, I32And]);
+            self.trap_if(TrapCode::Overflow);
+            self.code.extend([
+                LocalGet(a),
+                LocalGet(b),
+                I64DivS,
+                LocalGet(a),
+                LocalGet(b),
+                I64RemS,
+                I64Const(0),
+                I64Ne,
+                LocalGet(a),
+                LocalGet(b),
+                I64Xor,
+                I64Const(0),
+                I64LtS,
+                I32And,
+                I64ExtendI32U,
+                I64Sub,
+            ]);
+        } else {
+            let remainder = self.temp(ValType::I64);
+            self.code.extend([
+                LocalGet(a),
+                LocalGet(b),
+                I64RemS,
+                LocalTee(remainder),
+                LocalGet(b),
+                I64Const(0),
+                LocalGet(remainder),
+                I64Const(0),
+                I64Ne,
+                LocalGet(remainder),
+                LocalGet(b),
+                I64Xor,
+                I64Const(0),
+                I64LtS,
+                I32And,
+                Select,
+                I64Add,
+            ]);
+        }
+    }
+
+    // Direkt oder über die Funktionstabelle; geschachtelte Prozeduren
+    // erhalten vorne den statischen Verweis
+    fn generate_call(
+        &mut self,
+        function: &ir::Function,
+        instruction: &ir::Instruction,
+        callee: &Callee,
+        arguments: &[Value],
+    ) -> Result<bool, String> {
+        use Instruction::*;
+        let result = match callee {
+            Callee::Direct { name, depth } => {
+                let info = self.procedure(name)?;
+                if *depth > 1 {
+                    self.frame_address(depth - 1);
+                }
+                for argument in arguments {
+                    self.emit(LocalGet(self.values[argument.index()]));
+                }
+                self.emit(Call(info.index));
+                info.result
+            }
+            Callee::Indirect(target) => {
+                let target = self.values[target.index()];
+                self.code.extend([LocalGet(target), I32Eqz]);
+                self.trap_if(TrapCode::ProcedureNil);
+                for argument in arguments {
+                    self.emit(LocalGet(self.values[argument.index()]));
+                }
+                let params: Vec<Type> = arguments.iter().map(|argument| function.value_type(*argument)).collect();
+                let result = instruction.dest.map(|dest| function.value_type(dest));
+                let ty = self.signature(&params, result, 1);
+                self.code.extend([LocalGet(target), CallIndirect(ty)]);
+                result
+            }
+        };
+        if let (Some(_), None) = (result, instruction.dest) {
+            self.emit(Drop);
+        }
+        Ok(result.is_some() && instruction.dest.is_some())
+    }
+
+    fn procedure(&self, name: &str) -> Result<ProcedureInfo, String> {
+        self.procedures
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("Prozedur {} nicht gefunden", name))
+    }
+}
+
+impl Default for WasmGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::differential::Backend;
+    use crate::testing::checked;
+    use crate::wasm::machine::{Machine, Val};
+    use crate::wasm::to_wat;
+
+    fn compile(source: &str) -> WasmModule {
+        WasmGenerator::new().generate(&checked(source)).unwrap()
+    }
+
+    // Der Stapel liegt im linearen Speicher und ist begrenzt; die übrigen
+    // Laufzeitfehler prüft der Differenztest (differential.rs)
+    #[test]
+    fn test_stack_overflow() {
+        let trap = Backend::Wasm(OptLevel::O0).run("MODULE T;
+            PROCEDURE Down(n: INTEGER): INTEGER;
+            BEGIN
+              RETURN Down(n - 1)
+            END Down;
+            BEGIN ASSERT(Down(0) = 0)
+            END T.");
+        assert_eq!(trap, Some(Err("4: Stapelüberlauf".to_string())));
+    }
+
+    #[test]
+    fn test_exports() {
+        let module = compile("MODULE Plugin;
+            TYPE Pair = RECORD a, b: INTEGER END;
+            VAR count: INTEGER; pair: Pair;
+            PROCEDURE Square*(x: INTEGER): INTEGER;
+            BEGIN INC(count)
+              RETURN x * x
+            END Square;
+            PROCEDURE Scale*(x: REAL; n: INTEGER): REAL;
+            BEGIN RETURN x * FLT(n)
+            END Scale;
+            PROCEDURE Hidden(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END Hidden;
+            BEGIN count := 10; pair.a := 1
+            END Plugin.");
+        assert!(module.start.is_some());
+
+        let mut machine = Machine::new(&module).unwrap();
+        let mut host = |_: &str, _: &str, _: &[Val], _: &mut [u8]| Ok(None);
+        machine.run_start(&mut host).unwrap();
+        machine.invoke(BODY_EXPORT, &[], &mut host).unwrap();
+        assert_eq!(machine.invoke("Square", &[Val::I64(7)], &mut host), Ok(Some(Val::I64(49))));
+        assert_eq!(machine.invoke("Scale", &[Val::F64(1.5), Val::I64(4)], &mut host), Ok(Some(Val::F64(6.0))));
+        assert!(machine.invoke("Hidden", &[Val::I64(1)], &mut host).is_err());
+
+        // count liegt als erste globale Variable an Adresse 16
+        let count = i64::from_le_bytes(machine.memory[16..24].try_into().unwrap());
+        assert_eq!(count, 11);
+    }
+
+    #[test]
+    fn test_wat() {
+        let wat = to_wat(&compile("MODULE T;
+            IMPORT Out;
+            PROCEDURE Hello*;
+            BEGIN Out.String(\"Hallo\"); Out.Ln
+            END Hello;
+            BEGIN Hello
+            END T."));
+
+        assert!(wat.contains("(import \"Oberon\" \"trap\" (func $Oberon.trap"));
+        assert!(wat.contains("(import \"Out\" \"String\" (func $Out.String"));
+        assert!(wat.contains("(func $Hello (export \"Hello\")"));
+        assert!(wat.contains("(func $T (export \"main\")"));
+        assert!(wat.contains("\"Hallo\\00\")"));
+        assert!(wat.contains("(elem (i32.const 1) func $Hello)"));
+    }
+}
@@ -0,0 +1,785 @@
+use super::native::{ArgumentRegister, Condition, Datum, NativeGenerator, Target};
+use crate::ir::{BinaryOp, CompareOp, Type, UnaryOp};
+use crate::semantic::hir::Module;
+use crate::semantic::library::LibraryProcedure;
+use std::fmt::Write;
+
+// Übersetzt ein geprüftes Modul in x86-64-Assembler (GNU as, AT&T-Syntax)
+// nach der System-V-Aufrufkonvention. Die Ausgabe wird mit dem C-Compiler des
+// Systems gebunden (`cc Modul.s -o Modul`); Out, NEW und die Meldungen der
+// Laufzeitfehler benutzen die C-Bibliothek. Durchlauf und Layout teilt das
+// Backend mit RISC-V (native.rs), hier steht nur die Befehlsauswahl.
+//
+// Zwischenwerte liegen in den callee-saved Registern rbx, r12..r15;
+// ausgelagert wird mit push und pop. rax, rcx, rdx, r11 und xmm0/xmm1 sind
+// Hilfsregister einzelner Befehlsfolgen, r11 überlebt als `scratch` auch
+// Freigaben.
+//
+// Rahmen: rbp, die fünf Register und der statische Verweis (r10 beim Aufruf
+// geschachtelter Prozeduren) liegen über den lokalen Variablen, der Verweis
+// also immer bei -48(%rbp). Argumente jenseits von sechs ganzzahligen bzw.
+// acht REAL-Parametern übergibt der Aufrufer auf dem Stapel.
+
+// Dateiendung der Ausgabe
+pub const EXTENSION: &str = "s";
+
+pub type X86Generator = NativeGenerator<X86Code>;
+
+#[derive(Default)]
+pub struct X86Code {
+    text: String,
+    // Code der Prozedur, die gerade entsteht, und ihre Trap-Sprünge
+    code: String,
+    stubs: String,
+    // Zahl der ausgelagerten Werte, für die Ausrichtung bei Aufrufen
+    spilled: usize,
+    // 8-Byte-Worte des Argumentbereichs auf dem Stapel samt Ausrichtung
+    arguments: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct Reg {
+    q: &'static str,
+    d: &'static str,
+    b: &'static str,
+}
+
+const fn reg(q: &'static str, d: &'static str, b: &'static str) -> Reg {
+    Reg { q, d, b }
+}
+
+const POOL: [Reg; 5] = [
+    reg("%rbx", "%ebx", "%bl"),
+    reg("%r12", "%r12d", "%r12b"),
+    reg("%r13", "%r13d", "%r13b"),
+    reg("%r14", "%r14d", "%r14b"),
+    reg("%r15", "%r15d", "%r15b"),
+];
+
+const ARGUMENTS: [Reg; 6] = [
+    reg("%rdi", "%edi", "%dil"),
+    reg("%rsi", "%esi", "%sil"),
+    reg("%rdx", "%edx", "%dl"),
+    reg("%rcx", "%ecx", "%cl"),
+    reg("%r8", "%r8d", "%r8b"),
+    reg("%r9", "%r9d", "%r9b"),
+];
+
+const RBP: Reg = reg("%rbp", "%ebp", "%bpl");
+const R10: Reg = reg("%r10", "%r10d", "%r10b");
+const R11: Reg = reg("%r11", "%r11d", "%r11b");
+
+impl X86Generator {
+    pub fn generate(&mut self, module: &Module) -> Result<String, String> {
+        self.lower(module)?;
+
+        let mut out = String::new();
+        writeln!(out, "\t.file\t\"{}.s\"", self.module).unwrap();
+        writeln!(out, "\t.text").unwrap();
+        out.push_str(&self.target.text);
+        out.push_str(RUNTIME);
+
+        writeln!(out, "\n\t.data").unwrap();
+        for block in self.data_blocks() {
+            if block.global {
+                writeln!(out, "\t.globl {}", block.label).unwrap();
+            }
+            writeln!(out, "\t.balign {}\n{}:", block.align, block.label).unwrap();
+            for datum in block.data {
+                match datum {
+                    Datum::Zero(size) => writeln!(out, "\t.zero {}", size).unwrap(),
+                    Datum::Address(label) => writeln!(out, "\t.quad {}", label.as_deref().unwrap_or("0")).unwrap(),
+                }
+            }
+        }
+
+        writeln!(out, "\n\t.section .rodata").unwrap();
+        for (bytes, label) in &self.strings {
+            let bytes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+            writeln!(out, "{}:\n\t.byte {}", label, bytes.join(", ")).unwrap();
+        }
+        writeln!(out, "\n\t.section .note.GNU-stack,\"\",@progbits").unwrap();
+        Ok(out)
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        writeln!(self.target.code, "\t{}", instruction).unwrap();
+    }
+
+    fn check_overflow(&mut self, op: &str) {
+        let trap = self.overflow(op);
+        self.emit(&format!("jo {}", trap));
+    }
+
+    // Ergebnis eines Vergleichs als 0 oder 1
+    fn set_flag(&mut self, condition: &str, target: Reg) {
+        self.emit(&format!("set{} {}", condition, target.b));
+        self.emit(&format!("movzbq {}, {}", target.b, target.q));
+    }
+
+    fn printf(&mut self, format: &str, vector_registers: u32) {
+        let format = self.string(format.as_bytes());
+        self.emit(&format!("leaq {}(%rip), %rdi", format));
+        self.emit(&format!("movl ${}, %eax", vector_registers));
+        self.call("printf@PLT");
+    }
+}
+
+impl Target for X86Generator {
+    type Reg = Reg;
+
+    const NAME: &'static str = "x86-64";
+    const POOL: usize = POOL.len();
+    const ARGUMENTS: usize = ARGUMENTS.len();
+    const FLOAT_ARGUMENTS: usize = 8;
+    const REAL_IN_INTEGER_REGISTERS: bool = false;
+    const SAVED_SIZE: u32 = 48;
+
+    fn register(index: usize) -> Reg {
+        POOL[index]
+    }
+
+    fn frame_pointer() -> Reg {
+        RBP
+    }
+
+    fn static_link() -> Reg {
+        R10
+    }
+
+    fn scratch() -> Reg {
+        R11
+    }
+
+    fn spill(&mut self, reg: Reg) {
+        self.emit(&format!("pushq {}", reg.q));
+        self.target.spilled += 1;
+    }
+
+    fn reload(&mut self, reg: Reg) {
+        self.emit(&format!("popq {}", reg.q));
+        self.target.spilled -= 1;
+    }
+
+    fn place(&mut self, label: &str) {
+        writeln!(self.target.code, "{}:", label).unwrap();
+    }
+
+    fn jump(&mut self, label: &str) {
+        self.emit(&format!("jmp {}", label));
+    }
+
+    // pop verändert die Flags nicht
+    fn compare(&mut self, a: Reg, b: Reg) {
+        self.emit(&format!("cmpq {}, {}", b.q, a.q));
+    }
+
+    fn compare_immediate(&mut self, a: Reg, value: i64) {
+        if value == 0 {
+            self.emit(&format!("testq {}, {}", a.q, a.q));
+        } else if i32::try_from(value).is_ok() {
+            self.emit(&format!("cmpq ${}, {}", value, a.q));
+        } else {
+            self.emit(&format!("movabsq ${}, %rax", value));
+            self.emit(&format!("cmpq %rax, {}", a.q));
+        }
+    }
+
+    fn branch(&mut self, condition: Condition, label: &str) {
+        let suffix = match condition {
+            Condition::Equal => "e",
+            Condition::NotEqual => "ne",
+            Condition::Less => "l",
+            Condition::LessEqual => "le",
+            Condition::Greater => "g",
+            Condition::Below => "b",
+            Condition::Above => "a",
+            Condition::AboveEqual => "ae",
+        };
+        self.emit(&format!("j{} {}", suffix, label));
+    }
+
+    fn prologue(&mut self) {
+        self.target.code.clear();
+        self.target.stubs.clear();
+        self.target.spilled = 0;
+        for instruction in [
+            "pushq %rbp",
+            "movq %rsp, %rbp",
+            "pushq %rbx",
+            "pushq %r12",
+            "pushq %r13",
+            "pushq %r14",
+            "pushq %r15",
+            "pushq %r10",
+        ] {
+            self.emit(instruction);
+        }
+    }
+
+    fn epilogue(&mut self, label: &str, global: bool) {
+        for instruction in [
+            "leaq -40(%rbp), %rsp",
+            "popq %r15",
+            "popq %r14",
+            "popq %r13",
+            "popq %r12",
+            "popq %rbx",
+            "popq %rbp",
+            "ret",
+        ] {
+            self.emit(instruction);
+        }
+        let text = &mut self.target.text;
+        if global {
+            writeln!(text, "\n\t.globl {}", label).unwrap();
+        } else {
+            writeln!(text).unwrap();
+        }
+        writeln!(text, "\t.type {}, @function\n{}:", label, label).unwrap();
+        text.push_str(&self.target.code);
+        text.push_str(&self.target.stubs);
+        writeln!(text, "\t.size {}, .-{}", label, label).unwrap();
+    }
+
+    fn allocate_frame(&mut self, size: u32) {
+        self.emit(&format!("subq ${}, %rsp", size));
+        self.emit("movq %rsp, %r11");
+        self.emit(&format!("movl ${}, %eax", size / 8));
+        let zero = self.label();
+        self.place(&zero);
+        self.emit("movq $0, (%r11)");
+        self.emit("addq $8, %r11");
+        self.emit("decl %eax");
+        self.emit(&format!("jnz {}", zero));
+    }
+
+    // Stapelargumente liegen über der Rücksprungadresse ab 16(%rbp)
+    fn store_argument(&mut self, register: ArgumentRegister, offset: i64) {
+        match register {
+            ArgumentRegister::Float(index) => self.emit(&format!("movsd %xmm{}, {}(%rbp)", index, offset)),
+            ArgumentRegister::Integer(index) => self.emit(&format!("movq {}, {}(%rbp)", ARGUMENTS[index].q, offset)),
+            ArgumentRegister::Stack(index) => {
+                self.emit(&format!("movq {}(%rbp), %r11", 16 + 8 * index));
+                self.emit(&format!("movq %r11, {}(%rbp)", offset));
+            }
+        }
+    }
+
+    fn pass_argument(&mut self, register: ArgumentRegister, value: Reg) {
+        match register {
+            ArgumentRegister::Integer(index) => self.emit(&format!("movq {}, {}", value.q, ARGUMENTS[index].q)),
+            ArgumentRegister::Float(index) => self.emit(&format!("movq {}, %xmm{}", value.q, index)),
+            ArgumentRegister::Stack(index) => self.emit(&format!("movq {}, {}(%rsp)", value.q, 8 * index)),
+        }
+    }
+
+    // Die Ausrichtung liegt über den Argumenten, damit sie beim Aufruf
+    // unmittelbar über der Rücksprungadresse stehen; `call` fügt dann nichts
+    // mehr ein
+    fn reserve_arguments(&mut self, count: usize) {
+        self.target.arguments = count + (self.target.spilled + count) % 2;
+        self.emit(&format!("subq ${}, %rsp", 8 * self.target.arguments));
+        self.target.spilled += self.target.arguments;
+    }
+
+    fn release_arguments(&mut self, _count: usize) {
+        self.emit(&format!("addq ${}, %rsp", 8 * self.target.arguments));
+        self.target.spilled -= self.target.arguments;
+    }
+
+    // Aufruf mit 16-Byte-ausgerichtetem Stapel
+    fn call(&mut self, label: &str) {
+        if self.target.spilled % 2 == 1 {
+            self.emit("subq $8, %rsp");
+        }
+        self.emit(&format!("call {}", label));
+        if self.target.spilled % 2 == 1 {
+            self.emit("addq $8, %rsp");
+        }
+    }
+
+    fn call_indirect(&mut self, target: Reg) {
+        self.call(&format!("*{}", target.q));
+    }
+
+    fn set_result(&mut self, value: Reg, is_real: bool) {
+        let target = if is_real { "%xmm0" } else { "%rax" };
+        self.emit(&format!("movq {}, {}", value.q, target));
+    }
+
+    fn get_result(&mut self, target: Reg, is_real: bool) {
+        let source = if is_real { "%xmm0" } else { "%rax" };
+        self.emit(&format!("movq {}, {}", source, target.q));
+    }
+
+    // `value` und `maximum` landen in rcx und r8
+    fn trap_stub(&mut self, label: &str, procedure: &str, message: &str, value: Option<Reg>, maximum: Option<i64>) {
+        let stubs = &mut self.target.stubs;
+        writeln!(stubs, "{}:", label).unwrap();
+        if let Some(value) = value {
+            writeln!(stubs, "\tmovq {}, %rcx", value.q).unwrap();
+        }
+        if let Some(maximum) = maximum {
+            writeln!(stubs, "\tmovq ${}, %r8", maximum).unwrap();
+        }
+        writeln!(stubs, "\tmovq ${}, %rdi", self.line).unwrap();
+        writeln!(stubs, "\tleaq {}(%rip), %rsi", procedure).unwrap();
+        writeln!(stubs, "\tleaq {}(%rip), %rdx", message).unwrap();
+        writeln!(stubs, "\tcall oberon_trap").unwrap();
+    }
+
+    fn load_immediate(&mut self, target: Reg, value: i64) {
+        if i32::try_from(value).is_ok() {
+            self.emit(&format!("movq ${}, {}", value, target.q));
+        } else {
+            self.emit(&format!("movabsq ${}, {}", value, target.q));
+        }
+    }
+
+    fn load_label(&mut self, target: Reg, label: &str) {
+        self.emit(&format!("leaq {}(%rip), {}", label, target.q));
+    }
+
+    fn move_register(&mut self, target: Reg, source: Reg) {
+        self.emit(&format!("movq {}, {}", source.q, target.q));
+    }
+
+    fn add_immediate(&mut self, target: Reg, source: Reg, value: i64) {
+        if target.q != source.q {
+            self.emit(&format!("leaq {}({}), {}", value, source.q, target.q));
+        } else if value != 0 {
+            self.emit(&format!("addq ${}, {}", value, target.q));
+        }
+    }
+
+    fn add_scaled(&mut self, address: Reg, index: Reg, size: u32) {
+        self.emit(&format!("imulq ${}, {}, {}", size, index.q, index.q));
+        self.emit(&format!("addq {}, {}", index.q, address.q));
+    }
+
+    fn load(&mut self, ty: Type, base: Reg, target: Reg) {
+        match ty {
+            Type::Bool | Type::Char => self.emit(&format!("movzbq ({}), {}", base.q, target.q)),
+            Type::Set => self.emit(&format!("movl ({}), {}", base.q, target.d)),
+            _ => self.emit(&format!("movq ({}), {}", base.q, target.q)),
+        }
+    }
+
+    fn store(&mut self, ty: Type, value: Reg, base: Reg) {
+        match ty {
+            Type::Bool | Type::Char => self.emit(&format!("movb {}, ({})", value.b, base.q)),
+            Type::Set => self.emit(&format!("movl {}, ({})", value.d, base.q)),
+            _ => self.emit(&format!("movq {}, ({})", value.q, base.q)),
+        }
+    }
+
+    fn load_word(&mut self, target: Reg, base: Reg, offset: i64) {
+        self.emit(&format!("movq {}({}), {}", offset, base.q, target.q));
+    }
+
+    fn store_word(&mut self, value: Reg, base: Reg, offset: i64) {
+        self.emit(&format!("movq {}, {}({})", value.q, offset, base.q));
+    }
+
+    fn store_label(&mut self, label: &str, base: Reg, offset: i64) {
+        self.emit(&format!("leaq {}(%rip), %rax", label));
+        self.emit(&format!("movq %rax, {}({})", offset, base.q));
+    }
+
+    fn copy(&mut self, destination: Reg, source: Reg, offset: u32, length: u32) {
+        self.emit(&format!("leaq {}({}), %rdi", offset, destination.q));
+        self.emit(&format!("leaq {}({}), %rsi", offset, source.q));
+        self.emit(&format!("movq ${}, %rdx", length));
+        self.call("memcpy@PLT");
+    }
+
+    fn allocate(&mut self, target: Reg, size: u32) {
+        self.emit("movl $1, %edi");
+        self.emit(&format!("movq ${}, %rsi", size));
+        self.call("calloc@PLT");
+        let trap = self.trap("Kein Speicher mehr für NEW", None, None);
+        self.emit("testq %rax, %rax");
+        self.emit(&format!("jz {}", trap));
+        self.emit(&format!("movq %rax, {}", target.q));
+    }
+
+    fn range(&mut self, low: Reg, high: Reg) {
+        let (top, exit) = (self.label(), self.label());
+        self.emit("xorl %eax, %eax");
+        self.place(&top);
+        self.emit(&format!("cmpq {}, {}", high.q, low.q));
+        self.emit(&format!("jg {}", exit));
+        self.emit(&format!("btsl {}, %eax", low.d));
+        self.emit(&format!("incq {}", low.q));
+        self.emit(&format!("jmp {}", top));
+        self.place(&exit);
+        self.emit(&format!("movl %eax, {}", low.d));
+    }
+
+    // Out über printf und putchar
+    fn output(&mut self, procedure: LibraryProcedure, arguments: &[Reg]) {
+        match procedure {
+            LibraryProcedure::OutOpen => {}
+            LibraryProcedure::OutLn => {
+                self.emit("movl $10, %edi");
+                self.call("putchar@PLT");
+            }
+            LibraryProcedure::OutChar => {
+                self.emit(&format!("movq {}, %rdi", arguments[0].q));
+                self.call("putchar@PLT");
+            }
+            LibraryProcedure::OutString => {
+                self.emit(&format!("movq {}, %rsi", arguments[1].q));
+                self.emit(&format!("movq {}, %rdx", arguments[0].q));
+                self.printf("%.*s", 0);
+            }
+            LibraryProcedure::OutInt => {
+                self.emit(&format!("movq {}, %rsi", arguments[1].q));
+                self.emit(&format!("movq {}, %rdx", arguments[0].q));
+                self.printf("%*ld", 0);
+            }
+            LibraryProcedure::OutReal => {
+                self.emit(&format!("movq {}, %rsi", arguments[1].q));
+                self.emit(&format!("movq {}, %xmm0", arguments[0].q));
+                self.printf("%*.6f", 1);
+            }
+        }
+    }
+
+    fn unary(&mut self, op: UnaryOp, value: Reg) {
+        match op {
+            UnaryOp::Neg => {
+                self.emit(&format!("negq {}", value.q));
+                self.check_overflow(op.symbol());
+            }
+            UnaryOp::Abs => {
+                self.emit(&format!("movq {}, %rax", value.q));
+                self.emit("negq %rax");
+                self.check_overflow(op.symbol());
+                self.emit(&format!("cmovgq %rax, {}", value.q));
+            }
+            UnaryOp::FNeg => self.emit(&format!("btcq $63, {}", value.q)),
+            UnaryOp::FAbs => self.emit(&format!("btrq $63, {}", value.q)),
+            UnaryOp::Not => self.emit(&format!("xorq $1, {}", value.q)),
+            UnaryOp::Complement => self.emit(&format!("notl {}", value.d)),
+            UnaryOp::Odd => self.emit(&format!("andl $1, {}", value.d)),
+            UnaryOp::IntToReal => {
+                self.emit(&format!("cvtsi2sdq {}, %xmm0", value.q));
+                self.emit(&format!("movq %xmm0, {}", value.q));
+            }
+            UnaryOp::Floor => {
+                let overflow = self.overflow(op.symbol());
+                self.emit(&format!("movq {}, %xmm0", value.q));
+                self.emit(&format!("cvttsd2siq %xmm0, {}", value.q));
+                self.emit("movabsq $-9223372036854775808, %rax");
+                self.emit(&format!("cmpq %rax, {}", value.q));
+                self.emit(&format!("je {}", overflow));
+                self.emit(&format!("cvtsi2sdq {}, %xmm1", value.q));
+                self.emit("ucomisd %xmm0, %xmm1");
+                self.emit("seta %al");
+                self.emit("movzbq %al, %rax");
+                self.emit(&format!("subq %rax, {}", value.q));
+            }
+            UnaryOp::Ord | UnaryOp::Chr => {}
+        }
+    }
+
+    fn arithmetic(&mut self, op: BinaryOp, a: Reg, b: Reg) {
+        let instruction = match op {
+            BinaryOp::Add | BinaryOp::Inc | BinaryOp::For => "addq",
+            BinaryOp::Sub | BinaryOp::Dec => "subq",
+            BinaryOp::Mul => "imulq",
+            BinaryOp::FAdd | BinaryOp::FSub | BinaryOp::FMul | BinaryOp::FDiv => {
+                let instruction = match op {
+                    BinaryOp::FAdd => "addsd",
+                    BinaryOp::FSub => "subsd",
+                    BinaryOp::FMul => "mulsd",
+                    _ => "divsd",
+                };
+                self.emit(&format!("movq {}, %xmm0", a.q));
+                self.emit(&format!("movq {}, %xmm1", b.q));
+                self.emit(&format!("{} %xmm1, %xmm0", instruction));
+                self.emit(&format!("movq %xmm0, {}", a.q));
+                return;
+            }
+            BinaryOp::Union => "orq",
+            BinaryOp::Difference => {
+                self.emit(&format!("notq {}", b.q));
+                "andq"
+            }
+            BinaryOp::Intersection => "andq",
+            BinaryOp::SymmetricDifference => "xorq",
+            _ => return,
+        };
+        self.emit(&format!("{} {}, {}", instruction, b.q, a.q));
+        if matches!(op, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Inc | BinaryOp::Dec | BinaryOp::For) {
+            self.check_overflow(op.symbol());
+        }
+    }
+
+    fn membership(&mut self, a: Reg, b: Reg) {
+        self.emit(&format!("cmpq $32, {}", a.q));
+        self.emit("setb %al");
+        self.emit(&format!("btq {}, {}", a.q, b.q));
+        self.emit("setc %dl");
+        self.emit("andb %dl, %al");
+        self.emit(&format!("movzbq %al, {}", a.q));
+    }
+
+    // idiv rundet zur 0, bei Rest # 0 mit anderem Vorzeichen als der
+    // Divisor wird korrigiert
+    fn division(&mut self, op: BinaryOp, a: Reg, b: Reg) {
+        let overflow = self.overflow(op.symbol());
+        let (divide, end) = (self.label(), self.label());
+
+        // MIN(INTEGER) DIV -1 löst in idiv eine Ausnahme aus
+        self.emit(&format!("cmpq $-1, {}", b.q));
+        self.emit(&format!("jne {}", divide));
+        if op == BinaryOp::Div {
+            self.emit(&format!("negq {}", a.q));
+            self.emit(&format!("jo {}", overflow));
+        } else {
+            self.emit(&format!("xorl {}, {}", a.d, a.d));
+        }
+        self.emit(&format!("jmp {}", end));
+
+        self.place(&divide);
+        self.emit(&format!("movq {}, %rax", a.q));
+        self.emit("cqto");
+        self.emit(&format!("idivq {}", b.q));
+        self.emit("testq %rdx, %rdx");
+        self.emit("setnz %cl");
+        self.emit("movq %rdx, %r11");
+        self.emit(&format!("xorq {}, %r11", b.q));
+        self.emit("sets %r11b");
+        self.emit("andb %r11b, %cl");
+        self.emit("movzbq %cl, %rcx");
+        if op == BinaryOp::Div {
+            self.emit("subq %rcx, %rax");
+            self.emit(&format!("movq %rax, {}", a.q));
+        } else {
+            self.emit("negq %rcx");
+            self.emit(&format!("andq {}, %rcx", b.q));
+            self.emit("addq %rcx, %rdx");
+            self.emit(&format!("movq %rdx, {}", a.q));
+        }
+        self.place(&end);
+    }
+
+    fn compare_values(&mut self, op: CompareOp, ty: Type, a: Reg, b: Reg) {
+        match ty {
+            Type::Real => {
+                self.emit(&format!("movq {}, %xmm0", a.q));
+                self.emit(&format!("movq {}, %xmm1", b.q));
+                match op {
+                    // Ungeordnete Vergleiche (NaN) sind nur für # wahr
+                    CompareOp::Eq | CompareOp::Ne => {
+                        self.emit("ucomisd %xmm1, %xmm0");
+                        let (condition, parity, combine) =
+                            if op == CompareOp::Eq { ("e", "np", "andb") } else { ("ne", "p", "orb") };
+                        self.emit(&format!("set{} %al", condition));
+                        self.emit(&format!("set{} %dl", parity));
+                        self.emit(&format!("{} %dl, %al", combine));
+                        self.emit(&format!("movzbq %al, {}", a.q));
+                    }
+                    CompareOp::Gt | CompareOp::Ge => {
+                        self.emit("ucomisd %xmm1, %xmm0");
+                        self.set_flag(if op == CompareOp::Gt { "a" } else { "ae" }, a);
+                    }
+                    CompareOp::Lt | CompareOp::Le => {
+                        self.emit("ucomisd %xmm0, %xmm1");
+                        self.set_flag(if op == CompareOp::Lt { "a" } else { "ae" }, a);
+                    }
+                }
+            }
+            Type::Int | Type::Set => {
+                self.emit(&format!("cmpq {}, {}", b.q, a.q));
+                self.set_flag(signed_condition(op), a);
+            }
+            // CHAR und BOOLEAN vorzeichenlos, Zeiger und Prozeduren nur = und #
+            _ => {
+                self.emit(&format!("cmpq {}, {}", b.q, a.q));
+                let condition = match op {
+                    CompareOp::Lt => "b",
+                    CompareOp::Le => "be",
+                    CompareOp::Gt => "a",
+                    CompareOp::Ge => "ae",
+                    _ => signed_condition(op),
+                };
+                self.set_flag(condition, a);
+            }
+        }
+    }
+
+    fn compare_strings(&mut self, op: CompareOp, a: Reg, a_length: usize, b: Reg, b_length: usize) {
+        self.emit(&format!("movq {}, %rdx", b.q));
+        self.emit(&format!("movq {}, %rdi", a.q));
+        self.emit(&format!("movq ${}, %rsi", a_length));
+        self.emit(&format!("movq ${}, %rcx", b_length));
+        self.call("oberon_compare");
+        self.emit("cmpq $0, %rax");
+        self.set_flag(signed_condition(op), a);
+    }
+
+    fn shift(&mut self, op: BinaryOp, value: Reg, shift: Reg) {
+        self.emit(&format!("movq {}, %rcx", shift.q));
+        match op {
+            // Überlauf, wenn das Zurückschieben den Wert nicht ergibt
+            BinaryOp::Lsl => {
+                self.emit(&format!("movq {}, %rax", value.q));
+                self.emit(&format!("shlq %cl, {}", value.q));
+                self.emit(&format!("movq {}, %rdx", value.q));
+                self.emit("sarq %cl, %rdx");
+                self.emit("cmpq %rax, %rdx");
+                let overflow = self.overflow(op.symbol());
+                self.emit(&format!("jne {}", overflow));
+            }
+            BinaryOp::Asr => self.emit(&format!("sarq %cl, {}", value.q)),
+            _ => self.emit(&format!("rorq %cl, {}", value.q)),
+        }
+    }
+}
+
+fn signed_condition(op: CompareOp) -> &'static str {
+    match op {
+        CompareOp::Eq => "e",
+        CompareOp::Ne => "ne",
+        CompareOp::Lt => "l",
+        CompareOp::Le => "le",
+        CompareOp::Gt => "g",
+        CompareOp::Ge => "ge",
+    }
+}
+
+// Laufzeit, in jede Ausgabe kopiert:
+//
+// oberon_trap(Zeile, Prozedur, Meldung, a, b) meldet einen Laufzeitfehler im
+// Format des Interpreters auf stderr und beendet das Programm mit Exit-Code 1;
+// die Meldung ist ein printf-Format für a und b.
+//
+// oberon_compare(a, Länge a, b, Länge b) vergleicht zwei Zeichenketten bis
+// zum ersten 0X und liefert -1, 0 oder 1.
+const RUNTIME: &str = r#"
+	.type oberon_trap, @function
+oberon_trap:
+	andq $-16, %rsp
+	movq %rdi, %rbx
+	movq %rsi, %r12
+	movq %rdx, %r13
+	movq %rcx, %r14
+	movq %r8, %r15
+	xorl %edi, %edi
+	call fflush@PLT
+	movq stderr@GOTPCREL(%rip), %rax
+	movq (%rax), %rdi
+	leaq .Ltrap_head(%rip), %rsi
+	movq %rbx, %rdx
+	movq %r12, %rcx
+	xorl %eax, %eax
+	call fprintf@PLT
+	movq stderr@GOTPCREL(%rip), %rax
+	movq (%rax), %rdi
+	movq %r13, %rsi
+	movq %r14, %rdx
+	movq %r15, %rcx
+	xorl %eax, %eax
+	call fprintf@PLT
+	movq stderr@GOTPCREL(%rip), %rax
+	movq (%rax), %rsi
+	movl $10, %edi
+	call fputc@PLT
+	movl $1, %edi
+	call exit@PLT
+	.size oberon_trap, .-oberon_trap
+
+	.type oberon_compare, @function
+oberon_compare:
+	xorl %r8d, %r8d
+1:	xorl %eax, %eax
+	cmpq %rsi, %r8
+	jae 2f
+	movzbl (%rdi,%r8), %eax
+2:	xorl %r9d, %r9d
+	cmpq %rcx, %r8
+	jae 3f
+	movzbl (%rdx,%r8), %r9d
+3:	cmpl %r9d, %eax
+	jne 4f
+	testl %eax, %eax
+	jz 5f
+	incq %r8
+	jmp 1b
+4:	movl $1, %eax
+	movq $-1, %r10
+	cmovbq %r10, %rax
+5:	ret
+	.size oberon_compare, .-oberon_compare
+
+	.section .rodata
+.Ltrap_head:
+	.string "%ld: Laufzeitfehler in %s: "
+	.text
+"#;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::opt::OptLevel;
+    use crate::testing::checked;
+
+    fn compile(source: &str, level: OptLevel) -> String {
+        X86Generator::with_optimization(level).generate(&checked(source)).unwrap()
+    }
+
+    // Ausgabe und Laufzeitfehler der übersetzten Programme prüft der
+    // Differenztest (differential.rs) mit -O0 und -O2
+    #[test]
+    fn test_assembly() {
+        let assembly = compile("MODULE T;
+            TYPE R = RECORD x: INTEGER END;
+            VAR r*: R; a, b, c, d, e, f: INTEGER;
+            PROCEDURE P*(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END P;
+            PROCEDURE Q(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END Q;
+            BEGIN a := a + (b + (c + (d + (e + (f + 1)))))
+            END T.", OptLevel::O0);
+
+        assert!(assembly.contains("\t.globl main\n"));
+        assert!(assembly.contains("\t.globl T.P\n"));
+        assert!(!assembly.contains("\t.globl T.Q\n"));
+        // Der Deskriptor von R ist in r vorbelegt
+        assert!(assembly.contains("\t.globl T.r\n\t.balign 8\nT.r:\n\t.quad T.desc0\n\t.zero 8\n"));
+
+        // Mit -O1 faltet ir::opt den Ausdruck zur Konstanten
+        let source = "MODULE T; VAR a: INTEGER; BEGIN a := 2 * 3 + 1 END T.";
+        assert!(!compile(source, OptLevel::O0).contains("\tmovq $7, "));
+        assert!(compile(source, OptLevel::O1).contains("\tmovq $7, "));
+    }
+
+    #[test]
+    fn test_stack_arguments() {
+        // g und h liegen über der Rücksprungadresse, y hinter xmm0..xmm7
+        let assembly = compile("MODULE T;
+            VAR i: INTEGER; r: REAL;
+            PROCEDURE P(a, b, c, d, e, f, g, h: INTEGER): INTEGER;
+            BEGIN RETURN g + h
+            END P;
+            PROCEDURE Q(x0, x1, x2, x3, x4, x5, x6, x7, y: REAL): REAL;
+            BEGIN RETURN y
+            END Q;
+            BEGIN i := P(1, 2, 3, 4, 5, 6, 7, 8); r := Q(0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0)
+            END T.", OptLevel::O0);
+
+        assert!(assembly.contains("\tmovq 16(%rbp), %r11\n"));
+        assert!(assembly.contains("\tmovq 24(%rbp), %r11\n"));
+        assert!(assembly.contains("\tsubq $16, %rsp\n"));
+        assert!(assembly.contains("\tmovq %r11, 8(%rsp)\n"));
+        assert!(assembly.contains("\taddq $16, %rsp\n"));
+        assert!(assembly.contains("\tmovq %r11, %xmm7\n"));
+    }
+}
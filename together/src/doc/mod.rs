@@ -0,0 +1,566 @@
+pub mod render;
+
+use crate::parser::ast::{
+    DeclSequence, ExportMark, FieldList, FormalParameters, IdentDef, Module, Qualident, Type,
+};
+use crate::parser::cst::{self, SyntaxElement, SyntaxKind, SyntaxNode};
+use crate::parser::PrettyPrinter;
+use crate::scanner::TokenType;
+use std::collections::HashMap;
+
+pub use render::{render_html, render_index_html, render_index_markdown, render_markdown};
+
+// ============================================================================
+// Dokumentation der Schnittstelle (together doc)
+// ============================================================================
+//
+// Sammelt die exportierten Deklarationen eines Moduls mit ihren
+// Doc-Kommentaren (** ... *) unmittelbar davor. Ein Doc-Kommentar vor
+// CONST/TYPE/VAR gilt für die erste Deklaration des Abschnitts, einer vor
+// MODULE für das Modul. Typnamen in Signaturen werden zu Verweisen, soweit
+// sie auf exportierte Typen dieses oder eines importierten Moduls zeigen.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Constant,
+    Type,
+    Variable,
+    Procedure,
+    Field,
+}
+
+// Verweisziel eines Typnamens; `module` None heißt dieses Modul
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub module: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Span {
+    Text(String),
+    Link(String, Link),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub kind: ItemKind,
+    // exportierte Namen, zugleich Anker
+    pub names: Vec<String>,
+    pub signature: Vec<Span>,
+    pub comment: Option<String>,
+    // exportierte Felder, wenn der Typ ein Record (oder Zeiger darauf) ist
+    pub fields: Option<Vec<Item>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDoc {
+    pub name: String,
+    pub comment: Option<String>,
+    // (Alias, Modulname)
+    pub imports: Vec<(String, String)>,
+    pub items: Vec<Item>,
+}
+
+impl ModuleDoc {
+    // Liest ein Modul samt Doc-Kommentaren; Dateien mit Fehlern werden abgelehnt
+    pub fn from_source(source: &str) -> Result<ModuleDoc, String> {
+        let (tree, module, errors) = cst::parse(source);
+        if let Some(error) = errors.first() {
+            return Err(format!("Zeile {}:{}: {}", error.line, error.column, error.message));
+        }
+        let module = module.ok_or("Kein Modul gefunden")?;
+        Ok(Self::new(&module, &tree))
+    }
+
+    pub fn new(module: &Module, tree: &SyntaxNode) -> ModuleDoc {
+        let module_node = tree.child_nodes().find(|node| node.kind == SyntaxKind::Module);
+        let comments = module_node.map(declaration_comments).unwrap_or_default();
+
+        let mut builder = Builder {
+            imports: module
+                .imports
+                .iter()
+                .map(|import| (import.alias.clone().unwrap_or_else(|| import.module_name.clone()), import.module_name.clone()))
+                .collect(),
+            exported_types: module
+                .declarations
+                .types
+                .iter()
+                .filter(|declaration| is_exported(&declaration.name))
+                .map(|declaration| declaration.name.name.clone())
+                .collect(),
+            printer: PrettyPrinter::new(),
+        };
+
+        ModuleDoc {
+            name: module.name.clone(),
+            comment: module_node.and_then(|node| doc_comment(node.leading_comments())),
+            imports: module
+                .imports
+                .iter()
+                .map(|import| (import.alias.clone().unwrap_or_default(), import.module_name.clone()))
+                .collect(),
+            items: builder.items(&module.declarations, &comments),
+        }
+    }
+
+    // Erster Satz des Modulkommentars, für Übersichtsseiten
+    pub fn summary(&self) -> String {
+        let comment = self.comment.as_deref().unwrap_or("");
+        let end = comment.find(". ").map_or(comment.len(), |i| i + 1);
+        comment[..end].lines().collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn is_exported(name: &IdentDef) -> bool {
+    name.exported != ExportMark::None
+}
+
+fn export_mark(name: &IdentDef) -> &'static str {
+    match name.exported {
+        ExportMark::None => "",
+        ExportMark::ReadOnly => "*",
+        ExportMark::ReadWrite => "-",
+    }
+}
+
+// Text eines Doc-Kommentars ohne (** *), gemeinsame Einrückung entfernt
+fn doc_comment(comments: Vec<&str>) -> Option<String> {
+    let comment = comments.into_iter().rev().find(|comment| comment.starts_with("(**"))?;
+    let body = comment.trim_start_matches("(**").trim_end_matches("*)");
+
+    let lines: Vec<&str> = body.lines().map(str::trim_end).collect();
+    let indent = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let text: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| if i == 0 { line.trim() } else { line.get(indent..).unwrap_or("") })
+        .collect();
+
+    let text = text.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+// Doc-Kommentare der Deklarationen einer Deklarationsfolge je Art in
+// Quelltextreihenfolge, passend zu den Listen in DeclSequence
+#[derive(Debug, Default)]
+struct Comments {
+    constants: Vec<Option<String>>,
+    types: Vec<Option<String>>,
+    variables: Vec<Option<String>>,
+    procedures: Vec<Option<String>>,
+    // Kommentare der Felder je Typdeklaration
+    fields: Vec<Vec<Option<String>>>,
+}
+
+fn nth(comments: &[Option<String>], index: usize) -> Option<String> {
+    comments.get(index).cloned().flatten()
+}
+
+fn declaration_comments(module: &SyntaxNode) -> Comments {
+    let mut comments = Comments::default();
+    let Some(declarations) = module.child_nodes().find(|node| node.kind == SyntaxKind::DeclSequence) else {
+        return comments;
+    };
+
+    let mut section_comment = None;
+    for child in &declarations.children {
+        match child {
+            SyntaxElement::Token(token)
+                if matches!(token.token_type, TokenType::Const | TokenType::Type | TokenType::Var) =>
+            {
+                let leading = token.leading_trivia.iter().map(|trivia| trivia.text.as_str()).collect();
+                section_comment = doc_comment(leading);
+            }
+            SyntaxElement::Node(node) => {
+                let list = match node.kind {
+                    SyntaxKind::ConstDeclaration => &mut comments.constants,
+                    SyntaxKind::TypeDeclaration => &mut comments.types,
+                    SyntaxKind::VariableDeclaration => &mut comments.variables,
+                    SyntaxKind::ProcedureDeclaration => &mut comments.procedures,
+                    _ => continue,
+                };
+                let section = section_comment.take();
+                list.push(doc_comment(node.leading_comments()).or(section));
+
+                if node.kind == SyntaxKind::TypeDeclaration {
+                    let fields = record_node(node)
+                        .map(|record| {
+                            record
+                                .child_nodes()
+                                .filter(|field| field.kind == SyntaxKind::FieldList)
+                                .map(|field| doc_comment(field.leading_comments()))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    comments.fields.push(fields);
+                }
+            }
+            _ => {}
+        }
+    }
+    comments
+}
+
+// Record eines Typs, auch hinter POINTER TO
+fn record_node(declaration: &SyntaxNode) -> Option<&SyntaxNode> {
+    let mut node = declaration.child_nodes().last()?;
+    if node.kind == SyntaxKind::PointerType {
+        node = node.child_nodes().last()?;
+    }
+    (node.kind == SyntaxKind::RecordType).then_some(node)
+}
+
+fn record_fields(type_def: &Type) -> Option<(&Option<Qualident>, &[FieldList])> {
+    match type_def {
+        Type::Record { base_type, fields } => Some((base_type, fields)),
+        Type::Pointer { target_type } => match target_type.as_ref() {
+            Type::Record { base_type, fields } => Some((base_type, fields)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Signaturen
+// ============================================================================
+
+struct Builder {
+    // Alias -> Modulname
+    imports: HashMap<String, String>,
+    exported_types: Vec<String>,
+    printer: PrettyPrinter,
+}
+
+impl Builder {
+    fn items(&mut self, declarations: &DeclSequence, comments: &Comments) -> Vec<Item> {
+        let mut items = Vec::new();
+
+        for (i, constant) in declarations.constants.iter().enumerate() {
+            if !is_exported(&constant.name) {
+                continue;
+            }
+            let mut signature = Signature::default();
+            signature.text(&format!("{}{} = ", constant.name.name, export_mark(&constant.name)));
+            self.printer.print_expression(&mut signature.buffer, &constant.value);
+            items.push(Item {
+                kind: ItemKind::Constant,
+                names: vec![constant.name.name.clone()],
+                signature: signature.finish(),
+                comment: nth(&comments.constants, i),
+                fields: None,
+            });
+        }
+
+        for (i, declaration) in declarations.types.iter().enumerate() {
+            if !is_exported(&declaration.name) {
+                continue;
+            }
+            let mut signature = Signature::default();
+            signature.text(&format!("{}{} = ", declaration.name.name, export_mark(&declaration.name)));
+
+            // Records: Kopf in der Signatur, exportierte Felder einzeln
+            let mut fields = None;
+            match record_fields(&declaration.type_def) {
+                Some((base_type, field_lists)) => {
+                    if matches!(declaration.type_def, Type::Pointer { .. }) {
+                        signature.text("POINTER TO ");
+                    }
+                    signature.text("RECORD");
+                    if let Some(base) = base_type {
+                        signature.text(" (");
+                        self.qualident(&mut signature, base);
+                        signature.text(")");
+                    }
+                    let field_comments = comments.fields.get(i);
+                    let items = field_lists
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(j, field)| self.field(field, field_comments.and_then(|comments| nth(comments, j))))
+                        .collect();
+                    fields = Some(items);
+                }
+                None => self.type_signature(&mut signature, &declaration.type_def),
+            }
+
+            items.push(Item {
+                kind: ItemKind::Type,
+                names: vec![declaration.name.name.clone()],
+                signature: signature.finish(),
+                comment: nth(&comments.types, i),
+                fields,
+            });
+        }
+
+        for (i, declaration) in declarations.variables.iter().enumerate() {
+            let names: Vec<&IdentDef> = declaration.names.iter().filter(|name| is_exported(name)).collect();
+            if names.is_empty() {
+                continue;
+            }
+            let mut signature = Signature::default();
+            self.names(&mut signature, &names);
+            self.type_signature(&mut signature, &declaration.var_type);
+            items.push(Item {
+                kind: ItemKind::Variable,
+                names: names.iter().map(|name| name.name.clone()).collect(),
+                signature: signature.finish(),
+                comment: nth(&comments.variables, i),
+                fields: None,
+            });
+        }
+
+        for (i, procedure) in declarations.procedures.iter().enumerate() {
+            if !is_exported(&procedure.name) || procedure.is_forward {
+                continue;
+            }
+            let mut signature = Signature::default();
+            signature.text(&format!("PROCEDURE {}{}", procedure.name.name, export_mark(&procedure.name)));
+            if let Some(params) = &procedure.params {
+                self.formal_parameters(&mut signature, params);
+            }
+            items.push(Item {
+                kind: ItemKind::Procedure,
+                names: vec![procedure.name.name.clone()],
+                signature: signature.finish(),
+                comment: nth(&comments.procedures, i),
+                fields: None,
+            });
+        }
+
+        items
+    }
+
+    fn field(&mut self, field: &FieldList, comment: Option<String>) -> Option<Item> {
+        let names: Vec<&IdentDef> = field.names.iter().filter(|name| is_exported(name)).collect();
+        if names.is_empty() {
+            return None;
+        }
+        let mut signature = Signature::default();
+        self.names(&mut signature, &names);
+        self.type_signature(&mut signature, &field.field_type);
+        Some(Item {
+            kind: ItemKind::Field,
+            names: names.iter().map(|name| name.name.clone()).collect(),
+            signature: signature.finish(),
+            comment,
+            fields: None,
+        })
+    }
+
+    fn names(&self, signature: &mut Signature, names: &[&IdentDef]) {
+        let names: Vec<String> = names.iter().map(|name| format!("{}{}", name.name, export_mark(name))).collect();
+        signature.text(&format!("{}: ", names.join(", ")));
+    }
+
+    fn qualident(&self, signature: &mut Signature, qualident: &Qualident) {
+        let link = match &qualident.module {
+            Some(alias) => self.imports.get(alias).map(|module| Link {
+                module: Some(module.clone()),
+                name: qualident.name.clone(),
+            }),
+            None => self.exported_types.contains(&qualident.name).then(|| Link {
+                module: None,
+                name: qualident.name.clone(),
+            }),
+        };
+        match link {
+            Some(link) => signature.link(&qualident.to_string(), link),
+            None => signature.text(&qualident.to_string()),
+        }
+    }
+
+    fn type_signature(&mut self, signature: &mut Signature, type_def: &Type) {
+        match type_def {
+            Type::Qualident(qualident) => self.qualident(signature, qualident),
+            Type::Array { lengths, element_type } => {
+                signature.text("ARRAY ");
+                for (i, length) in lengths.iter().enumerate() {
+                    if i > 0 {
+                        signature.text(", ");
+                    }
+                    self.printer.print_expression(&mut signature.buffer, length);
+                }
+                signature.text(" OF ");
+                self.type_signature(signature, element_type);
+            }
+            Type::Record { base_type, fields } => {
+                signature.text("RECORD");
+                if let Some(base) = base_type {
+                    signature.text(" (");
+                    self.qualident(signature, base);
+                    signature.text(")");
+                }
+                let mut first = true;
+                for field in fields {
+                    let names: Vec<&IdentDef> = field.names.iter().filter(|name| is_exported(name)).collect();
+                    if names.is_empty() {
+                        continue;
+                    }
+                    signature.text(if first { " " } else { "; " });
+                    first = false;
+                    self.names(signature, &names);
+                    self.type_signature(signature, &field.field_type);
+                }
+                signature.text(" END");
+            }
+            Type::Pointer { target_type } => {
+                signature.text("POINTER TO ");
+                self.type_signature(signature, target_type);
+            }
+            Type::Procedure { params } => {
+                signature.text("PROCEDURE");
+                if let Some(params) = params {
+                    signature.text(" ");
+                    self.formal_parameters(signature, params);
+                }
+            }
+        }
+    }
+
+    fn formal_parameters(&mut self, signature: &mut Signature, params: &FormalParameters) {
+        signature.text("(");
+        for (i, section) in params.sections.iter().enumerate() {
+            if i > 0 {
+                signature.text("; ");
+            }
+            if section.is_var {
+                signature.text("VAR ");
+            }
+            signature.text(&format!("{}: ", section.names.join(", ")));
+            self.type_signature(signature, &section.param_type);
+        }
+        signature.text(")");
+        if let Some(return_type) = &params.return_type {
+            signature.text(": ");
+            self.qualident(signature, return_type);
+        }
+    }
+}
+
+// Sammelt Text und Verweise; Text landet zunächst im Puffer, damit auch
+// PrettyPrinter direkt hineinschreiben kann
+#[derive(Default)]
+struct Signature {
+    spans: Vec<Span>,
+    buffer: String,
+}
+
+impl Signature {
+    fn text(&mut self, text: &str) {
+        self.buffer.push_str(text);
+    }
+
+    fn link(&mut self, text: &str, link: Link) {
+        self.flush();
+        self.spans.push(Span::Link(text.to_string(), link));
+    }
+
+    fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            self.spans.push(Span::Text(std::mem::take(&mut self.buffer)));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Span> {
+        self.flush();
+        self.spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) const SOURCE: &str = "(** Einfach verkettete Listen. Mit Einfügen vorne. *)
+MODULE Liste;
+IMPORT W := Writers, Out;
+
+(** Obergrenze *)
+CONST Max* = 2 * 50; Intern = 1;
+
+TYPE
+  (** Ein Knoten
+        mit Nutzlast *)
+  Node* = POINTER TO NodeDesc;
+  NodeDesc* = RECORD
+    (** Schlüssel *)
+    key*: INTEGER;
+    next: Node;
+    out-: W.Writer
+  END;
+  Hidden = RECORD END;
+
+VAR count*, secret: INTEGER;
+
+(** Fügt vorne ein *)
+PROCEDURE Insert*(VAR list: Node; key: INTEGER);
+BEGIN
+END Insert;
+
+PROCEDURE Helper(x: Hidden): BOOLEAN;
+BEGIN RETURN TRUE
+END Helper;
+
+END Liste.
+";
+
+    #[test]
+    fn test_exported_items() {
+        let doc = ModuleDoc::from_source(SOURCE).unwrap();
+        assert_eq!(doc.name, "Liste");
+        assert_eq!(doc.summary(), "Einfach verkettete Listen.");
+        assert_eq!(doc.imports, vec![("W".to_string(), "Writers".to_string()), (String::new(), "Out".to_string())]);
+
+        let names: Vec<&str> = doc.items.iter().map(|item| item.names[0].as_str()).collect();
+        assert_eq!(names, ["Max", "Node", "NodeDesc", "count", "Insert"]);
+
+        let max = &doc.items[0];
+        assert_eq!(max.signature, vec![Span::Text("Max* = 2 * 50".to_string())]);
+        assert_eq!(max.comment.as_deref(), Some("Obergrenze"));
+        assert_eq!(doc.items[1].comment.as_deref(), Some("Ein Knoten\nmit Nutzlast"));
+
+        // nur exportierte Felder, Verweise auf importierte Typen
+        let fields = doc.items[2].fields.as_ref().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].comment.as_deref(), Some("Schlüssel"));
+        assert_eq!(
+            fields[1].signature,
+            vec![
+                Span::Text("out-: ".to_string()),
+                Span::Link(
+                    "W.Writer".to_string(),
+                    Link {
+                        module: Some("Writers".to_string()),
+                        name: "Writer".to_string()
+                    }
+                ),
+            ]
+        );
+
+        assert_eq!(doc.items[3].names, ["count"]);
+        let insert = &doc.items[4];
+        assert_eq!(insert.comment.as_deref(), Some("Fügt vorne ein"));
+        assert_eq!(
+            insert.signature,
+            vec![
+                Span::Text("PROCEDURE Insert*(VAR list: ".to_string()),
+                Span::Link(
+                    "Node".to_string(),
+                    Link {
+                        module: None,
+                        name: "Node".to_string()
+                    }
+                ),
+                Span::Text("; key: INTEGER)".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use super::{Item, ItemKind, Link, ModuleDoc, Span};
+use std::fmt::Write;
+
+// ============================================================================
+// Ausgabe als HTML und Markdown
+// ============================================================================
+//
+// Eine Seite je Modul (Modul.html bzw. Modul.md) im DEFINITION-Stil und eine
+// Übersicht (index.html bzw. index.md). Verweise auf andere Module entstehen
+// nur für Module, die mitdokumentiert werden (`modules`).
+
+const SECTIONS: [(ItemKind, &str); 4] = [
+    (ItemKind::Constant, "CONST"),
+    (ItemKind::Type, "TYPE"),
+    (ItemKind::Variable, "VAR"),
+    (ItemKind::Procedure, "PROCEDURE"),
+];
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 50em; margin: 2em auto; }
+pre { background: #f4f4f4; padding: 0.5em; }
+.item { margin-bottom: 1.5em; }
+dt { font-family: monospace; }";
+
+// Ziel eines Verweises relativ zur Seite von `current`
+fn href(link: &Link, current: &str, modules: &[String], extension: &str) -> Option<String> {
+    match &link.module {
+        None => Some(format!("#{}", link.name)),
+        Some(module) if module == current => Some(format!("#{}", link.name)),
+        Some(module) if modules.contains(module) => Some(format!("{}.{}#{}", module, extension, link.name)),
+        Some(_) => None,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// ============================================================================
+// HTML
+// ============================================================================
+
+pub fn render_html(doc: &ModuleDoc, modules: &[String]) -> String {
+    let mut out = String::new();
+    let title = format!("DEFINITION {}", doc.name);
+    html_header(&mut out, &title);
+    writeln!(out, "<p><a href=\"index.html\">Übersicht</a></p>").unwrap();
+    writeln!(out, "<h1>{}</h1>", escape(&title)).unwrap();
+    if let Some(comment) = &doc.comment {
+        html_comment(&mut out, comment);
+    }
+
+    if !doc.imports.is_empty() {
+        let imports: Vec<String> = doc
+            .imports
+            .iter()
+            .map(|(alias, module)| {
+                let text = if alias.is_empty() { module.clone() } else { format!("{} := {}", alias, module) };
+                if modules.contains(module) {
+                    format!("<a href=\"{}.html\">{}</a>", escape(module), escape(&text))
+                } else {
+                    escape(&text)
+                }
+            })
+            .collect();
+        writeln!(out, "<pre>IMPORT {};</pre>", imports.join(", ")).unwrap();
+    }
+
+    for (kind, heading) in SECTIONS {
+        let items: Vec<&Item> = doc.items.iter().filter(|item| item.kind == kind).collect();
+        if items.is_empty() {
+            continue;
+        }
+        writeln!(out, "<h2>{}</h2>", heading).unwrap();
+        for item in items {
+            html_item(&mut out, item, &doc.name, modules);
+        }
+    }
+
+    writeln!(out, "<pre>END {}.</pre>", escape(&doc.name)).unwrap();
+    writeln!(out, "</body>\n</html>").unwrap();
+    out
+}
+
+pub fn render_index_html(docs: &[ModuleDoc]) -> String {
+    let mut out = String::new();
+    html_header(&mut out, "Module");
+    writeln!(out, "<h1>Module</h1>\n<dl>").unwrap();
+    for doc in docs {
+        writeln!(
+            out,
+            "<dt><a href=\"{0}.html\">{0}</a></dt><dd>{1}</dd>",
+            escape(&doc.name),
+            escape(&doc.summary())
+        )
+        .unwrap();
+    }
+    writeln!(out, "</dl>\n</body>\n</html>").unwrap();
+    out
+}
+
+fn html_header(out: &mut String, title: &str) {
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"de\">\n<head>\n<meta charset=\"utf-8\">").unwrap();
+    writeln!(out, "<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>", escape(title), STYLE).unwrap();
+}
+
+// Absätze an Leerzeilen
+fn html_comment(out: &mut String, comment: &str) {
+    for paragraph in comment.split("\n\n") {
+        writeln!(out, "<p>{}</p>", escape(paragraph.trim()).replace('\n', "<br>\n")).unwrap();
+    }
+}
+
+fn html_spans(spans: &[Span], current: &str, modules: &[String]) -> String {
+    spans
+        .iter()
+        .map(|span| match span {
+            Span::Text(text) => escape(text),
+            Span::Link(text, link) => match href(link, current, modules, "html") {
+                Some(target) => format!("<a href=\"{}\">{}</a>", escape(&target), escape(text)),
+                None => escape(text),
+            },
+        })
+        .collect()
+}
+
+fn html_item(out: &mut String, item: &Item, current: &str, modules: &[String]) {
+    writeln!(out, "<div class=\"item\" id=\"{}\">", escape(&item.names[0])).unwrap();
+    for name in &item.names[1..] {
+        writeln!(out, "<span id=\"{}\"></span>", escape(name)).unwrap();
+    }
+
+    write!(out, "<pre>{}", html_spans(&item.signature, current, modules)).unwrap();
+    let fields = item.fields.as_deref().unwrap_or_default();
+    match &item.fields {
+        Some(fields) if fields.is_empty() => write!(out, " END").unwrap(),
+        Some(fields) => {
+            for (i, field) in fields.iter().enumerate() {
+                let separator = if i + 1 < fields.len() { ";" } else { "" };
+                write!(out, "\n  {}{}", html_spans(&field.signature, current, modules), separator).unwrap();
+            }
+            write!(out, "\nEND").unwrap();
+        }
+        None => {}
+    }
+    writeln!(out, "</pre>").unwrap();
+
+    if let Some(comment) = &item.comment {
+        html_comment(out, comment);
+    }
+
+    let documented: Vec<&Item> = fields.iter().filter(|field| field.comment.is_some()).collect();
+    if !documented.is_empty() {
+        writeln!(out, "<dl>").unwrap();
+        for field in documented {
+            let id = format!("{}.{}", item.names[0], field.names[0]);
+            let comment = escape(field.comment.as_deref().unwrap_or("")).replace('\n', "<br>\n");
+            writeln!(out, "<dt id=\"{}\">{}</dt><dd>{}</dd>", escape(&id), escape(&field.names.join(", ")), comment).unwrap();
+        }
+        writeln!(out, "</dl>").unwrap();
+    }
+    writeln!(out, "</div>").unwrap();
+}
+
+// ============================================================================
+// Markdown
+// ============================================================================
+
+pub fn render_markdown(doc: &ModuleDoc, modules: &[String]) -> String {
+    let mut out = String::new();
+    writeln!(out, "[Übersicht](index.md)\n\n# DEFINITION {}\n", doc.name).unwrap();
+    if let Some(comment) = &doc.comment {
+        writeln!(out, "{}\n", comment).unwrap();
+    }
+
+    if !doc.imports.is_empty() {
+        let imports: Vec<String> = doc
+            .imports
+            .iter()
+            .map(|(alias, module)| {
+                let text = if alias.is_empty() { module.clone() } else { format!("{} := {}", alias, module) };
+                if modules.contains(module) {
+                    format!("[{}]({}.md)", code(&text), module)
+                } else {
+                    code(&text)
+                }
+            })
+            .collect();
+        writeln!(out, "{} {}\n", code("IMPORT"), imports.join(", ")).unwrap();
+    }
+
+    for (kind, heading) in SECTIONS {
+        let items: Vec<&Item> = doc.items.iter().filter(|item| item.kind == kind).collect();
+        if items.is_empty() {
+            continue;
+        }
+        writeln!(out, "## {}\n", heading).unwrap();
+        for item in items {
+            markdown_item(&mut out, item, &doc.name, modules);
+        }
+    }
+
+    writeln!(out, "{}", code(&format!("END {}.", doc.name))).unwrap();
+    out
+}
+
+pub fn render_index_markdown(docs: &[ModuleDoc]) -> String {
+    let mut out = String::from("# Module\n\n");
+    for doc in docs {
+        let summary = doc.summary();
+        if summary.is_empty() {
+            writeln!(out, "- [{0}]({0}.md)", doc.name).unwrap();
+        } else {
+            writeln!(out, "- [{0}]({0}.md): {1}", doc.name, summary).unwrap();
+        }
+    }
+    out
+}
+
+// Inline-Code; enthält der Text Backticks, mit doppelten Begrenzern
+fn code(text: &str) -> String {
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+// Text ohne Verweis wird zusammengefasst, da zwei aufeinanderfolgende
+// Code-Spannen als ein doppelter Begrenzer gelesen würden
+fn markdown_spans(spans: &[Span], current: &str, modules: &[String]) -> String {
+    let mut out = String::new();
+    let mut plain = String::new();
+    for span in spans {
+        match span {
+            Span::Link(text, link) => match href(link, current, modules, "md") {
+                Some(target) => {
+                    if !plain.is_empty() {
+                        out.push_str(&code(&std::mem::take(&mut plain)));
+                    }
+                    write!(out, "[{}]({})", code(text), target).unwrap();
+                }
+                None => plain.push_str(text),
+            },
+            Span::Text(text) => plain.push_str(text),
+        }
+    }
+    if !plain.is_empty() {
+        out.push_str(&code(&plain));
+    }
+    out
+}
+
+fn markdown_item(out: &mut String, item: &Item, current: &str, modules: &[String]) {
+    for name in &item.names {
+        writeln!(out, "<a id=\"{}\"></a>", name).unwrap();
+    }
+    match &item.fields {
+        Some(fields) if fields.is_empty() => {
+            let mut signature = item.signature.clone();
+            signature.push(Span::Text(" END".to_string()));
+            writeln!(out, "{}\n", markdown_spans(&signature, current, modules)).unwrap();
+        }
+        _ => writeln!(out, "{}\n", markdown_spans(&item.signature, current, modules)).unwrap(),
+    }
+
+    if let Some(fields) = item.fields.as_ref().filter(|fields| !fields.is_empty()) {
+        for field in fields {
+            let signature = markdown_spans(&field.signature, current, modules);
+            match &field.comment {
+                Some(comment) => writeln!(out, "- {}: {}", signature, comment.replace('\n', " ")).unwrap(),
+                None => writeln!(out, "- {}", signature).unwrap(),
+            }
+        }
+        writeln!(out, "\n{}\n", code("END")).unwrap();
+    }
+
+    if let Some(comment) = &item.comment {
+        writeln!(out, "{}\n", comment).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doc::tests::SOURCE;
+
+    #[test]
+    fn test_render() {
+        let doc = ModuleDoc::from_source(SOURCE).unwrap();
+        let modules = vec!["Liste".to_string(), "Writers".to_string()];
+
+        let html = render_html(&doc, &modules);
+        assert!(html.contains("<title>DEFINITION Liste</title>"));
+        assert!(html.contains("<pre>IMPORT <a href=\"Writers.html\">W := Writers</a>, Out;</pre>"));
+        assert!(html.contains("<div class=\"item\" id=\"Max\">\n<pre>Max* = 2 * 50</pre>\n<p>Obergrenze</p>"));
+        assert!(html.contains(
+            "<pre>NodeDesc* = RECORD\n  key*: INTEGER;\n  out-: <a href=\"Writers.html#Writer\">W.Writer</a>\nEND</pre>"
+        ));
+        assert!(html.contains("<dt id=\"NodeDesc.key\">key</dt><dd>Schlüssel</dd>"));
+        assert!(html.contains("PROCEDURE Insert*(VAR list: <a href=\"#Node\">Node</a>; key: INTEGER)"));
+        assert!(!html.contains("Helper") && !html.contains("Hidden") && !html.contains("secret"));
+
+        let markdown = render_markdown(&doc, &modules);
+        assert!(markdown.contains("# DEFINITION Liste\n\nEinfach verkettete Listen. Mit Einfügen vorne.\n"));
+        assert!(markdown.contains("- `out-: `[`W.Writer`](Writers.md#Writer)\n"));
+        assert!(markdown.contains("<a id=\"Insert\"></a>\n`PROCEDURE Insert*(VAR list: `[`Node`](#Node)`; key: INTEGER)`\n\nFügt vorne ein\n"));
+
+        // ohne Writers in der Dokumentation kein Verweis dorthin
+        let markdown = render_markdown(&doc, &["Liste".to_string()]);
+        assert!(markdown.contains("- `out-: W.Writer`\n"));
+        assert_eq!(render_index_markdown(&[doc]), "# Module\n\n- [Liste](Liste.md): Einfach verkettete Listen.\n");
+    }
+}
@@ -0,0 +1,912 @@
+use crate::parser::cst::{self, SyntaxElement, SyntaxKind, SyntaxNode};
+use crate::parser::pretty_printer::AlignedEntry;
+use crate::parser::{Doc, PrettyPrinter};
+use crate::scanner::{Lexer, Token, TokenType, TriviaKind};
+
+// ============================================================================
+// Formatierer (together fmt)
+// ============================================================================
+//
+// Arbeitet auf dem konkreten Syntaxbaum, damit Kommentare und die genaue
+// Schreibweise von Literalen erhalten bleiben; das Layout kommt vom
+// PrettyPrinter: Blöcke und Deklarationsabschnitte eingerückt,
+// Deklarationen eines Abschnitts ausgerichtet, lange Ausdrücke und
+// Parameterlisten umbrochen.
+// Leerzeilen zwischen Anweisungen und Deklarationen bleiben (höchstens eine)
+// erhalten. Die Ausgabe ist ein Fixpunkt: erneutes Formatieren ändert nichts.
+//
+// Schlüsselwörter können klein ausgegeben werden, etwa für Listings. Das
+// betrifft nur die Ausgabe: Scanner und Compiler lesen Schlüsselwörter
+// weiterhin nur in Großschreibung, die kleine Fassung ist also kein
+// übersetzbarer (und kein erneut formatierbarer) Quelltext mehr.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub indent: usize,
+    pub max_width: usize,
+    // Namen und Typen innerhalb eines Abschnitts untereinander ausrichten
+    pub align: bool,
+    pub keyword_case: KeywordCase,
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        FormatOptions {
+            indent: 2,
+            max_width: 80,
+            align: true,
+            keyword_case: KeywordCase::Upper,
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Formatiert ein vollständiges Modul; Dateien mit Fehlern bleiben unangetastet
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, String> {
+    let (tree, module, errors) = cst::parse(source);
+    if let Some(error) = errors.first() {
+        return Err(format!("Zeile {}:{}: {}", error.line, error.column, error.message));
+    }
+    if module.is_none() {
+        return Err("Kein Modul gefunden".to_string());
+    }
+
+    let mut printer = PrettyPrinter::with_layout(options.indent, options.max_width);
+    printer.set_align(options.align);
+    let tokens = tree.tokens();
+    let mut formatter = Formatter::new(&tokens, &printer, options.keyword_case);
+    let output = printer.render(&formatter.node(&tree));
+
+    // Sicherheitsnetz: dieselben Token und Kommentare in derselben Reihenfolge
+    let case = options.keyword_case;
+    if significant_text(source, case) != significant_text(&output, case) {
+        return Err("Formatierung würde die Bedeutung ändern (interner Fehler)".to_string());
+    }
+    Ok(output)
+}
+
+// Token (Zahlen in Großschreibung, Schlüsselwörter wie verlangt) und
+// Kommentare; klein geschriebene Schlüsselwörter liest der Scanner als
+// Bezeichner mit derselben Schreibweise
+fn significant_text(source: &str, case: KeywordCase) -> Vec<String> {
+    let mut lexer = Lexer::new(source);
+    let mut text = Vec::new();
+    for token in lexer.tokenize_tolerant() {
+        for trivia in &token.leading_trivia {
+            if trivia.kind != TriviaKind::Whitespace {
+                text.push(trivia.text.replace("\r\n", "\n"));
+            }
+        }
+        text.push(token_text(&token, case));
+    }
+    text
+}
+
+// Schreibweise eines Tokens in der Ausgabe
+fn token_text(token: &Token, case: KeywordCase) -> String {
+    let is_keyword = !matches!(token.token_type, TokenType::Identifier(_)) && Token::keyword(&token.lexeme).is_some();
+    if token.lexeme.starts_with(|c: char| c.is_ascii_digit()) {
+        // Hex-Ziffern, Exponent und Suffixe H/X einheitlich groß
+        token.lexeme.to_ascii_uppercase()
+    } else if is_keyword && case == KeywordCase::Lower {
+        token.lexeme.to_ascii_lowercase()
+    } else {
+        token.lexeme.clone()
+    }
+}
+
+// Endet hier ein Operand? Dann ist ein folgendes +/- binär.
+fn is_operand_end(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Identifier(_)
+            | TokenType::IntegerLiteral(_)
+            | TokenType::RealLiteral(_)
+            | TokenType::StringLiteral(_)
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::RParen
+            | TokenType::RBracket
+            | TokenType::RBrace
+            | TokenType::Caret
+    )
+}
+
+// Vorrangstufe eines binären Operators (Relation < Addition < Multiplikation)
+fn precedence(token_type: &TokenType) -> Option<u8> {
+    match token_type {
+        TokenType::Equal
+        | TokenType::NotEqual
+        | TokenType::Less
+        | TokenType::LessEqual
+        | TokenType::Greater
+        | TokenType::GreaterEqual
+        | TokenType::In
+        | TokenType::Is => Some(1),
+        TokenType::Plus | TokenType::Minus | TokenType::Or => Some(2),
+        TokenType::Times | TokenType::Slash | TokenType::Div | TokenType::Mod | TokenType::Ampersand => Some(3),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Kommentare
+// ============================================================================
+
+// Kommentare rund um ein Token: eigene Zeilen davor, auf derselben Zeile danach
+#[derive(Debug, Default)]
+struct Comments {
+    // (Zeilenumbrüche davor, Text)
+    leading: Vec<(usize, String)>,
+    // Zeilenumbrüche zwischen dem letzten Kommentar davor und dem Token
+    newlines: usize,
+    trailing: Vec<String>,
+    // nach dem letzten Kommentar dahinter folgt ein Zeilenumbruch
+    trailing_break: bool,
+}
+
+// Ordnet die Kommentare den Token zu: was ohne Zeilenumbruch auf ein Token
+// folgt, gehört zu diesem, alles andere zum nächsten Token
+fn collect_comments(tokens: &[&Token]) -> Vec<Comments> {
+    let mut comments: Vec<Comments> = tokens.iter().map(|_| Comments::default()).collect();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let mut newlines = 0;
+        let mut trailing = i > 0;
+
+        for trivia in &token.leading_trivia {
+            match trivia.kind {
+                TriviaKind::Whitespace => newlines += trivia.text.matches('\n').count(),
+                _ => {
+                    let text = trivia.text.replace("\r\n", "\n");
+                    if trailing && newlines == 0 {
+                        comments[i - 1].trailing.push(text);
+                    } else {
+                        if trailing {
+                            comments[i - 1].trailing_break = true;
+                            trailing = false;
+                        }
+                        comments[i].leading.push((newlines, text));
+                    }
+                    newlines = 0;
+                }
+            }
+        }
+
+        if trailing && !comments[i - 1].trailing.is_empty() {
+            comments[i - 1].trailing_break = newlines > 0;
+        }
+        comments[i].newlines = newlines;
+    }
+    comments
+}
+
+// ============================================================================
+// Layout
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Plain,
+    // Umbrüche nach Kommas
+    List,
+    // ohne Zwischenräume (Exportmarke)
+    Tight,
+}
+
+// Das zuletzt ausgegebene Token einer Folge
+struct Last {
+    token_type: TokenType,
+    operand: bool,
+    unary: bool,
+}
+
+struct Formatter<'a> {
+    printer: &'a PrettyPrinter,
+    tokens: &'a [&'a Token],
+    comments: Vec<Comments>,
+    keyword_case: KeywordCase,
+    // Index des nächsten auszugebenden Tokens
+    next: usize,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(tokens: &'a [&'a Token], printer: &'a PrettyPrinter, keyword_case: KeywordCase) -> Self {
+        Formatter {
+            printer,
+            tokens,
+            comments: collect_comments(tokens),
+            keyword_case,
+            next: 0,
+        }
+    }
+
+    // Token müssen in Quelltextreihenfolge ausgegeben werden
+    fn token(&mut self, token: &Token) -> Doc {
+        debug_assert!(std::ptr::eq(self.tokens[self.next], token));
+        let comments = &self.comments[self.next];
+        self.next += 1;
+
+        let mut docs = Vec::new();
+        for (newlines, text) in &comments.leading {
+            docs.push(Doc::newlines((*newlines).max(1)));
+            docs.push(Doc::text(text.as_str()));
+        }
+        // das Eof-Token trägt nur die Kommentare am Dateiende
+        if token.token_type == TokenType::Eof {
+            return Doc::concat(docs);
+        }
+        if !comments.leading.is_empty() {
+            docs.push(Doc::newlines(comments.newlines));
+        }
+        docs.push(Doc::text(token_text(token, self.keyword_case)));
+        for text in &comments.trailing {
+            docs.push(Doc::text(" "));
+            docs.push(Doc::text(text.as_str()));
+        }
+        if comments.trailing_break {
+            docs.push(Doc::HardLine);
+        }
+        Doc::concat(docs)
+    }
+
+    // Stand vor dem nächsten Token eine Leerzeile?
+    fn blank_before_next(&self) -> bool {
+        let comments = &self.comments[self.next];
+        let newlines = comments.leading.first().map_or(comments.newlines, |(newlines, _)| *newlines);
+        newlines >= 2
+    }
+
+    fn separator_line(&self) -> Doc {
+        Doc::newlines(if self.blank_before_next() { 2 } else { 1 })
+    }
+
+    fn element(&mut self, element: &SyntaxElement) -> Doc {
+        match element {
+            SyntaxElement::Node(node) => self.node(node),
+            SyntaxElement::Token(token) => self.token(token),
+        }
+    }
+
+    fn node(&mut self, node: &SyntaxNode) -> Doc {
+        match node.kind {
+            SyntaxKind::SourceFile => {
+                let mut docs: Vec<Doc> = node.children.iter().map(|child| self.element(child)).collect();
+                docs.push(Doc::HardLine);
+                Doc::concat(docs)
+            }
+            SyntaxKind::Module => self.module(node),
+            SyntaxKind::DeclSequence => self.decl_sequence(node),
+            SyntaxKind::ProcedureDeclaration => self.procedure(node),
+            SyntaxKind::RecordType => self.record(node),
+            SyntaxKind::StatementSequence => self.statement_sequence(node),
+            SyntaxKind::IfStatement
+            | SyntaxKind::WhileStatement
+            | SyntaxKind::RepeatStatement
+            | SyntaxKind::ForStatement => self.block_statement(node),
+            SyntaxKind::CaseStatement => self.case_statement(node),
+            SyntaxKind::CaseClause => self.case_clause(node),
+            SyntaxKind::FormalParameters | SyntaxKind::ActualParameters => self.parameters(node),
+            SyntaxKind::Expression => {
+                let doc = self.expression(&node.children);
+                self.printer.breakable(doc)
+            }
+            SyntaxKind::ImportList => {
+                let doc = self.pieces(&node.children, Context::List);
+                self.printer.breakable(doc)
+            }
+            SyntaxKind::IdentDef => self.pieces(&node.children, Context::Tight),
+            _ => self.pieces(&node.children, Context::Plain),
+        }
+    }
+
+    // Folge von Token und Knoten auf einer Zeile, Zwischenräume nach den
+    // Nachbartoken
+    fn pieces(&mut self, children: &[SyntaxElement], context: Context) -> Doc {
+        let mut docs = Vec::new();
+        let mut last: Option<Last> = None;
+
+        for child in children {
+            let (first, next) = match child {
+                SyntaxElement::Token(token) => {
+                    let unary = match token.token_type {
+                        TokenType::Tilde => true,
+                        TokenType::Plus | TokenType::Minus => !last.as_ref().is_some_and(|last| last.operand),
+                        _ => false,
+                    };
+                    let next = Last {
+                        token_type: token.token_type.clone(),
+                        operand: is_operand_end(&token.token_type),
+                        unary,
+                    };
+                    (token.token_type.clone(), next)
+                }
+                SyntaxElement::Node(node) => {
+                    let tokens = node.tokens();
+                    let first = tokens.first().map_or(TokenType::Eof, |token| token.token_type.clone());
+                    let token_type = tokens.last().map_or(TokenType::Eof, |token| token.token_type.clone());
+                    let operand = node.kind == SyntaxKind::IdentDef || is_operand_end(&token_type);
+                    let next = Last {
+                        token_type,
+                        operand,
+                        unary: false,
+                    };
+                    (first, next)
+                }
+            };
+
+            if let Some(separator) = last.as_ref().and_then(|last| separator(last, &first, context)) {
+                docs.push(separator);
+            }
+            docs.push(self.element(child));
+            last = Some(next);
+        }
+        Doc::concat(docs)
+    }
+
+    // Operanden und binäre Operatoren; ein Vorzeichen gehört zum Operanden
+    fn expression(&mut self, children: &[SyntaxElement]) -> Doc {
+        let mut operators = Vec::new();
+        let mut after_operand = false;
+        for (i, child) in children.iter().enumerate() {
+            match child {
+                SyntaxElement::Token(token) => {
+                    match precedence(&token.token_type) {
+                        Some(precedence) if after_operand => operators.push((i, precedence)),
+                        _ => {}
+                    }
+                    after_operand = is_operand_end(&token.token_type);
+                }
+                SyntaxElement::Node(_) => after_operand = true,
+            }
+        }
+
+        let mut operands = Vec::new();
+        let mut operator_docs = Vec::new();
+        let mut start = 0;
+        for (i, precedence) in operators {
+            operands.push(self.pieces(&children[start..i], Context::Plain));
+            operator_docs.push((precedence, self.element(&children[i])));
+            start = i + 1;
+        }
+        operands.push(self.pieces(&children[start..], Context::Plain));
+        self.printer.operators(operands, operator_docs)
+    }
+
+    fn module(&mut self, node: &SyntaxNode) -> Doc {
+        let mut docs = Vec::new();
+        let mut after_statements = false;
+
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(child) => match child.kind {
+                    SyntaxKind::StatementSequence => {
+                        let doc = self.node(child);
+                        docs.push(self.printer.block(doc));
+                        after_statements = true;
+                    }
+                    _ => {
+                        docs.push(Doc::BlankLine);
+                        docs.push(self.node(child));
+                    }
+                },
+                SyntaxElement::Token(token) => {
+                    match token.token_type {
+                        TokenType::Module | TokenType::Semicolon | TokenType::Period => {}
+                        TokenType::Begin => {
+                            docs.push(Doc::BlankLine);
+                            after_statements = true;
+                        }
+                        TokenType::End if after_statements => docs.push(Doc::HardLine),
+                        TokenType::End => docs.push(Doc::BlankLine),
+                        _ => docs.push(Doc::text(" ")),
+                    }
+                    docs.push(self.token(token));
+                }
+            }
+        }
+        Doc::concat(docs)
+    }
+
+    // CONST-, TYPE- und VAR-Abschnitte mit ausgerichteten Deklarationen,
+    // Prozeduren durch Leerzeilen getrennt
+    fn decl_sequence(&mut self, node: &SyntaxNode) -> Doc {
+        let mut docs = Vec::new();
+        let mut section: Vec<AlignedEntry> = Vec::new();
+
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(child)
+                    if matches!(
+                        child.kind,
+                        SyntaxKind::ConstDeclaration | SyntaxKind::TypeDeclaration | SyntaxKind::VariableDeclaration
+                    ) =>
+                {
+                    let declaration = self.declaration(child);
+                    section.push(declaration);
+                }
+                SyntaxElement::Token(token) if token.token_type == TokenType::Semicolon && !section.is_empty() => {
+                    let doc = self.token(token);
+                    if let Some(declaration) = section.last_mut() {
+                        declaration.rest.push(doc);
+                    }
+                }
+                _ => {
+                    if !section.is_empty() {
+                        docs.push(self.printer.aligned(std::mem::take(&mut section)));
+                    }
+                    let is_semicolon = matches!(child, SyntaxElement::Token(token) if token.token_type == TokenType::Semicolon);
+                    if !docs.is_empty() && !is_semicolon {
+                        docs.push(Doc::BlankLine);
+                    }
+                    docs.push(self.element(child));
+                }
+            }
+        }
+        if !section.is_empty() {
+            docs.push(self.printer.aligned(section));
+        }
+        Doc::concat(docs)
+    }
+
+    // Teilt an `=` (Konstanten, Typen) bzw. nach `:` (Variablen, Felder)
+    fn declaration(&mut self, node: &SyntaxNode) -> AlignedEntry {
+        let blank = self.blank_before_next();
+        let split = node
+            .children
+            .iter()
+            .position(|child| {
+                matches!(child, SyntaxElement::Token(token)
+                    if matches!(token.token_type, TokenType::Equal | TokenType::Colon))
+            })
+            .map(|i| match &node.children[i] {
+                SyntaxElement::Token(token) if token.token_type == TokenType::Colon => i + 1,
+                _ => i,
+            })
+            .unwrap_or(node.children.len());
+
+        let name = self.pieces(&node.children[..split], Context::Plain);
+        let rest = self.pieces(&node.children[split..], Context::Plain);
+        AlignedEntry {
+            blank,
+            name,
+            rest: vec![Doc::text(" "), rest],
+        }
+    }
+
+    fn procedure(&mut self, node: &SyntaxNode) -> Doc {
+        let heading_end = node
+            .children
+            .iter()
+            .position(|child| matches!(child, SyntaxElement::Token(token) if token.token_type == TokenType::Semicolon))
+            .map_or(node.children.len(), |i| i + 1);
+        let mut docs = vec![self.pieces(&node.children[..heading_end], Context::Plain)];
+
+        let mut children = node.children[heading_end..].iter().peekable();
+        while let Some(child) = children.next() {
+            match child {
+                SyntaxElement::Node(child) => {
+                    let doc = self.node(child);
+                    docs.push(self.printer.block(doc));
+                }
+                SyntaxElement::Token(token) => match token.token_type {
+                    TokenType::Return => {
+                        let mut line = vec![self.token(token)];
+                        if let Some(SyntaxElement::Node(value)) = children.peek() {
+                            line.push(Doc::text(" "));
+                            line.push(self.node(value));
+                            children.next();
+                        }
+                        docs.push(self.printer.block(Doc::concat(line)));
+                    }
+                    TokenType::Begin | TokenType::End => {
+                        docs.push(Doc::HardLine);
+                        docs.push(self.token(token));
+                    }
+                    _ => {
+                        docs.push(Doc::text(" "));
+                        docs.push(self.token(token));
+                    }
+                },
+            }
+        }
+        Doc::concat(docs)
+    }
+
+    fn record(&mut self, node: &SyntaxNode) -> Doc {
+        let is_field = |child: &SyntaxElement| matches!(child, SyntaxElement::Node(node) if node.kind == SyntaxKind::FieldList);
+        let fields_start = node.children.iter().position(is_field);
+        let end = node.children.len() - 1;
+
+        let Some(fields_start) = fields_start else {
+            let head = self.pieces(&node.children[..end], Context::Plain);
+            return Doc::concat(vec![head, Doc::text(" "), self.element(&node.children[end])]);
+        };
+
+        let head = self.pieces(&node.children[..fields_start], Context::Plain);
+        let mut fields: Vec<AlignedEntry> = Vec::new();
+        for child in &node.children[fields_start..end] {
+            match child {
+                SyntaxElement::Node(field) => {
+                    let field = self.declaration(field);
+                    fields.push(field);
+                }
+                SyntaxElement::Token(token) => {
+                    let doc = self.token(token);
+                    if let Some(field) = fields.last_mut() {
+                        field.rest.push(doc);
+                    }
+                }
+            }
+        }
+        let fields = self.printer.aligned(fields);
+        Doc::concat(vec![head, fields, Doc::HardLine, self.element(&node.children[end])])
+    }
+
+    fn statement_sequence(&mut self, node: &SyntaxNode) -> Doc {
+        let mut docs = Vec::new();
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(statement) => {
+                    if !docs.is_empty() {
+                        docs.push(self.separator_line());
+                    }
+                    docs.push(self.node(statement));
+                }
+                SyntaxElement::Token(token) => docs.push(self.token(token)),
+            }
+        }
+        Doc::concat(docs)
+    }
+
+    // IF, WHILE, REPEAT, FOR: Kopfzeilen mit den Schlüsselwörtern, Rümpfe
+    // eingerückt
+    fn block_statement(&mut self, node: &SyntaxNode) -> Doc {
+        let mut docs = Vec::new();
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(child) if child.kind == SyntaxKind::StatementSequence => {
+                    let doc = self.node(child);
+                    docs.push(self.printer.block(doc));
+                }
+                SyntaxElement::Token(token)
+                    if matches!(token.token_type, TokenType::Elsif | TokenType::Else | TokenType::Until | TokenType::End) =>
+                {
+                    docs.push(Doc::HardLine);
+                    docs.push(self.token(token));
+                }
+                _ => {
+                    if !docs.is_empty() {
+                        docs.push(Doc::text(" "));
+                    }
+                    docs.push(self.element(child));
+                }
+            }
+        }
+        Doc::concat(docs)
+    }
+
+    //   CASE k OF
+    //     0: a := 1
+    //   | 1, 2:
+    //       b := 2;
+    //       c := 3
+    //   ELSE d := 4
+    //   END
+    fn case_statement(&mut self, node: &SyntaxNode) -> Doc {
+        let mut docs = Vec::new();
+        let mut after_bar = false;
+        let mut after_else = false;
+
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(child) if child.kind == SyntaxKind::CaseClause => {
+                    let clause = self.node(child);
+                    docs.push(self.printer.case_clause(!after_bar, clause));
+                }
+                SyntaxElement::Node(child) if after_else => {
+                    let body = self.node(child);
+                    let else_clause = self.printer.clause(Doc::Concat(Vec::new()), body);
+                    docs.push(else_clause);
+                }
+                SyntaxElement::Token(token)
+                    if matches!(token.token_type, TokenType::Bar | TokenType::Else | TokenType::End) =>
+                {
+                    docs.push(Doc::HardLine);
+                    docs.push(self.token(token));
+                    after_bar = token.token_type == TokenType::Bar;
+                    after_else = token.token_type == TokenType::Else;
+                }
+                _ => {
+                    if !docs.is_empty() {
+                        docs.push(Doc::text(" "));
+                    }
+                    docs.push(self.element(child));
+                }
+            }
+        }
+        Doc::concat(docs)
+    }
+
+    // Marken, dann die Anweisungen auf derselben Zeile, falls sie passen
+    fn case_clause(&mut self, node: &SyntaxNode) -> Doc {
+        let body_start = node
+            .children
+            .iter()
+            .position(|child| matches!(child, SyntaxElement::Node(node) if node.kind == SyntaxKind::StatementSequence))
+            .unwrap_or(node.children.len());
+
+        let labels = self.pieces(&node.children[..body_start], Context::Plain);
+        match node.children.get(body_start) {
+            Some(body) => {
+                let body = self.element(body);
+                self.printer.clause(labels, body)
+            }
+            None => labels,
+        }
+    }
+
+    // ( a, b ) bzw. ( x: T; VAR y: U ): R, bei Bedarf je Eintrag eine Zeile
+    fn parameters(&mut self, node: &SyntaxNode) -> Doc {
+        let close = node
+            .children
+            .iter()
+            .position(|child| matches!(child, SyntaxElement::Token(token) if token.token_type == TokenType::RParen))
+            .unwrap_or(node.children.len() - 1);
+
+        let open = self.element(&node.children[0]);
+        let mut items = Vec::new();
+        let mut item = Vec::new();
+        for child in &node.children[1..close] {
+            match child {
+                SyntaxElement::Token(token) if matches!(token.token_type, TokenType::Comma | TokenType::Semicolon) => {
+                    item.push(self.token(token));
+                    items.push(Doc::concat(std::mem::take(&mut item)));
+                }
+                _ => item.push(self.element(child)),
+            }
+        }
+        if !item.is_empty() {
+            items.push(Doc::concat(item));
+        }
+
+        let close = self.pieces(&node.children[close..], Context::Plain);
+        self.printer.list(open, items, close)
+    }
+}
+
+fn separator(last: &Last, next: &TokenType, context: Context) -> Option<Doc> {
+    if context == Context::Tight || last.unary {
+        return None;
+    }
+    if matches!(
+        next,
+        TokenType::Comma
+            | TokenType::Semicolon
+            | TokenType::Colon
+            | TokenType::Period
+            | TokenType::DotDot
+            | TokenType::Caret
+            | TokenType::RParen
+            | TokenType::RBracket
+            | TokenType::RBrace
+    ) {
+        return None;
+    }
+    if matches!(
+        last.token_type,
+        TokenType::LParen | TokenType::LBracket | TokenType::LBrace | TokenType::Period | TokenType::DotDot
+    ) {
+        return None;
+    }
+    // Aufruf, Index, Parameterliste
+    if matches!(next, TokenType::LParen | TokenType::LBracket) && last.operand {
+        return None;
+    }
+
+    match context {
+        Context::List if last.token_type == TokenType::Comma => Some(Doc::Line),
+        _ => Some(Doc::text(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::PrettyPrinter;
+    use crate::scanner;
+    use crate::testing::parse;
+
+    const MESSY: &str = "(* Kopf *)
+MODULE  Liste ;  (* Listen *)
+IMPORT Out,In;
+
+CONST max=100;   LongerName = 0ffH;
+TYPE
+    Node* = POINTER TO NodeDesc;
+    NodeDesc = RECORD key,count: INTEGER; next: Node END;
+  Empty=RECORD END;
+VAR head: Node;  i,j : INTEGER;
+
+(** Fügt vorne ein *)
+PROCEDURE Insert*(VAR list: Node; key: INTEGER);
+  VAR n: Node;
+BEGIN
+  NEW(n); n^.key := key; (* Verkettung *) n^.next := list;
+
+  list := n
+END Insert;
+
+PROCEDURE Sum(a, b: INTEGER): INTEGER;
+BEGIN RETURN -a*b + (a - b) * 2 END Sum;
+
+BEGIN
+  i:=0;
+  WHILE i<max DO IF i MOD 2=0 THEN Out.Int(i,0) ELSIF i=3 THEN Out.Ln ELSE INC(i) END; INC(i) END;
+  CASE i OF 1: j := 1 | 2..5: j := 2; j := 3 ELSE j := 0 END;
+  REPEAT DEC(i) UNTIL i <= 0;
+  FOR i := 0 TO 10 BY 2 DO j := j + i END
+END Liste.
+(* Ende *)
+";
+
+    // AST ohne Positionen, in kanonischer Schreibweise
+    fn canonical(source: &str) -> String {
+        PrettyPrinter::new().print_module(&parse(source))
+    }
+
+    fn comments(source: &str) -> Vec<String> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        scanner::comments(&tokens).into_iter().map(|comment| comment.text).collect()
+    }
+
+    #[test]
+    fn test_format_layout() {
+        let formatted = format(MESSY, &FormatOptions::new()).unwrap();
+        let expected = "(* Kopf *)
+MODULE Liste; (* Listen *)
+
+IMPORT Out, In;
+
+CONST
+  max        = 100;
+  LongerName = 0FFH;
+
+TYPE
+  Node*    = POINTER TO NodeDesc;
+  NodeDesc = RECORD
+    key, count: INTEGER;
+    next:       Node
+  END;
+  Empty    = RECORD END;
+
+VAR
+  head: Node;
+  i, j: INTEGER;
+
+(** Fügt vorne ein *)
+PROCEDURE Insert*(VAR list: Node; key: INTEGER);
+  VAR
+    n: Node;
+BEGIN
+  NEW(n);
+  n^.key := key; (* Verkettung *)
+  n^.next := list;
+
+  list := n
+END Insert;
+
+PROCEDURE Sum(a, b: INTEGER): INTEGER;
+BEGIN
+  RETURN -a * b + (a - b) * 2
+END Sum;
+
+BEGIN
+  i := 0;
+  WHILE i < max DO
+    IF i MOD 2 = 0 THEN
+      Out.Int(i, 0)
+    ELSIF i = 3 THEN
+      Out.Ln
+    ELSE
+      INC(i)
+    END;
+    INC(i)
+  END;
+  CASE i OF
+    1: j := 1
+  | 2..5:
+      j := 2;
+      j := 3
+  ELSE j := 0
+  END;
+  REPEAT
+    DEC(i)
+  UNTIL i <= 0;
+  FOR i := 0 TO 10 BY 2 DO
+    j := j + i
+  END
+END Liste.
+(* Ende *)
+";
+        assert_eq!(formatted, expected);
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let narrow = FormatOptions {
+            indent: 4,
+            max_width: 30,
+            ..FormatOptions::new()
+        };
+        let unaligned = FormatOptions {
+            align: false,
+            ..FormatOptions::new()
+        };
+
+        for options in [FormatOptions::new(), narrow, unaligned] {
+            let once = format(MESSY, &options).unwrap();
+            let twice = format(&once, &options).unwrap();
+            assert_eq!(once, twice);
+
+            // gleiche Bedeutung, alle Kommentare erhalten
+            assert_eq!(canonical(&format(&once, &FormatOptions::new()).unwrap()), canonical(MESSY));
+            assert_eq!(comments(&once), comments(MESSY));
+        }
+    }
+
+    #[test]
+    fn test_keyword_case() {
+        let lower = FormatOptions {
+            keyword_case: KeywordCase::Lower,
+            ..FormatOptions::new()
+        };
+        let upper = format(MESSY, &FormatOptions::new()).unwrap();
+        let formatted = format(MESSY, &lower).unwrap();
+
+        // nur die Schlüsselwörter ändern sich, Bezeichner wie INTEGER und NEW nicht
+        assert!(formatted.starts_with("(* Kopf *)\nmodule Liste; (* Listen *)\n"), "{}", formatted);
+        assert!(formatted.contains("pointer to NodeDesc"));
+        assert!(formatted.contains("key, count: INTEGER"));
+        assert!(formatted.contains("while i < max do"));
+        assert!(formatted.contains("NEW(n)"));
+        assert!(formatted.contains("0FFH"));
+        assert_eq!(formatted.lines().count(), upper.lines().count());
+    }
+
+    #[test]
+    fn test_line_breaking() {
+        let source = "MODULE M;
+PROCEDURE Compute(VAR result: INTEGER; first, second: INTEGER; third: REAL): BOOLEAN;
+BEGIN
+  result := first * second + first DIV second + Compute(result, first, second, third);
+  RETURN result > 0
+END Compute;
+END M.";
+        let options = FormatOptions {
+            max_width: 40,
+            ..FormatOptions::new()
+        };
+        let formatted = format(source, &options).unwrap();
+        assert!(formatted.lines().all(|line| line.chars().count() <= 40), "{}", formatted);
+        assert!(formatted.contains("PROCEDURE Compute(\n  VAR result: INTEGER;\n  first, second: INTEGER;\n  third: REAL\n): BOOLEAN;"));
+        // zuerst an den schwächsten Operatoren umbrochen
+        assert!(formatted.contains("result := first * second +\n    first DIV second +\n"));
+        assert_eq!(format(&formatted, &options).unwrap(), formatted);
+
+        // fehlerhafte Dateien werden nicht angefasst
+        assert!(format("MODULE M; VAR x: ; END M.", &options).is_err());
+    }
+}
@@ -0,0 +1,890 @@
+use crate::parser::ast::{BinaryOp, UnaryOp};
+use crate::semantic::const_eval::{floor_div, floor_mod, SET_MAX_ELEMENT};
+use crate::semantic::hir::*;
+use crate::semantic::library::{write_padded, LibraryProcedure};
+use crate::semantic::symbol_table::Builtin;
+use crate::semantic::{ConstValue, TypeArena, TypeId, TypeKind};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+
+mod value;
+
+pub use value::Value;
+use value::{element, element_mut, Base, Location};
+
+// ============================================================================
+// Interpreter
+// ============================================================================
+//
+// Führt ein geprüftes Modul direkt auf dem HIR aus, ohne C-Compiler.
+// Laufzeitfehler (Index außerhalb der Grenzen, NIL-Dereferenzierung,
+// Division durch 0, Überlauf, ASSERT, CASE ohne passendes Label) brechen die
+// Ausführung mit einem Trap ab, der Zeile und Prozedur nennt.
+//
+// Variablen liegen in Speicherzellen, die lokalen einer Prozedur werden beim
+// Verlassen wieder freigegeben. VAR-Parameter binden den Ort des Arguments,
+// NEW legt Objekte im Heap an, der nie aufgeräumt wird.
+
+// Höchste Aufruftiefe, bevor ein Stapelüberlauf gemeldet wird
+pub const MAX_DEPTH: usize = 10_000;
+
+// Stapelgröße, die ein Thread für MAX_DEPTH geschachtelte Aufrufe braucht
+pub const STACK_SIZE: usize = 512 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trap {
+    pub message: String,
+    pub line: usize,
+    // Prozedur, in der der Fehler auftrat; der Modulname für den Rumpf
+    pub procedure: String,
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: Laufzeitfehler in {}: {}", self.line, self.procedure, self.message)
+    }
+}
+
+type Exec<T> = Result<T, Trap>;
+
+struct Frame {
+    // Qualifizierter Name der Prozedur
+    procedure: String,
+    // Parameter und lokale Variablen nach qualifiziertem Namen
+    variables: HashMap<String, Location>,
+}
+
+// Globale Variablen und Heap über einen Lauf hinaus, damit eine REPL
+// Eingabe für Eingabe mit demselben Speicher weiterrechnen kann
+#[derive(Default)]
+pub struct Memory {
+    cells: Vec<Value>,
+    heap: Vec<Value>,
+    globals: HashMap<String, Location>,
+}
+
+pub struct Interpreter<'a> {
+    module: &'a Module,
+    types: &'a TypeArena,
+    // Alle Prozeduren nach qualifiziertem Namen ("P", "P.Q")
+    procedures: HashMap<String, &'a Procedure>,
+    cells: Vec<Value>,
+    heap: Vec<Value>,
+    globals: HashMap<String, Location>,
+    frames: Vec<Frame>,
+    // Zeile der gerade ausgeführten Anweisung
+    line: usize,
+    out: &'a mut dyn Write,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(module: &'a Module, out: &'a mut dyn Write) -> Self {
+        Self::with_memory(module, out, Memory::default())
+    }
+
+    // Setzt auf dem Speicher eines früheren Laufs auf; globale Variablen, die
+    // dort schon existieren, behalten ihren Wert
+    pub fn with_memory(module: &'a Module, out: &'a mut dyn Write, memory: Memory) -> Self {
+        let mut procedures = HashMap::new();
+        register_procedures(&mut procedures, "", &module.declarations);
+
+        Interpreter {
+            module,
+            types: &module.types,
+            procedures,
+            cells: memory.cells,
+            heap: memory.heap,
+            globals: memory.globals,
+            frames: Vec::new(),
+            line: 0,
+            out,
+        }
+    }
+
+    pub fn into_memory(self) -> Memory {
+        Memory {
+            cells: self.cells,
+            heap: self.heap,
+            globals: self.globals,
+        }
+    }
+
+    // Legt die globalen Variablen an und führt den Modulrumpf aus
+    pub fn run(&mut self) -> Result<(), Trap> {
+        self.allocate_globals();
+        let body = &self.module.body;
+        let result = self.statements(body);
+        self.finish(result)
+    }
+
+    // Wertet einen Ausdruck auf Modulebene aus, etwa eine Eingabe der REPL
+    pub fn evaluate_expression(&mut self, expr: &Expr) -> Result<Value, Trap> {
+        self.allocate_globals();
+        let result = self.evaluate(expr);
+        self.finish(result)
+    }
+
+    fn allocate_globals(&mut self) {
+        let module = self.module;
+        for variable in &module.declarations.variables {
+            if !self.globals.contains_key(&variable.name) {
+                let location = self.allocate(Value::default_for(self.types, variable.var_type));
+                self.globals.insert(variable.name.clone(), location);
+            }
+        }
+    }
+
+    // Lokale Zellen eines abgebrochenen Aufrufs werden nicht mehr gebraucht
+    fn finish<T>(&mut self, result: Exec<T>) -> Exec<T> {
+        self.frames.clear();
+        self.cells.truncate(self.globals.len());
+        let flushed = self.out.flush();
+        let value = result?;
+        flushed.map_err(|e| self.trap(format!("Ausgabe fehlgeschlagen: {}", e)))?;
+        Ok(value)
+    }
+
+    // ========================================================================
+    // Traps
+    // ========================================================================
+
+    fn trap(&self, message: impl Into<String>) -> Trap {
+        Trap {
+            message: message.into(),
+            line: self.line,
+            procedure: match self.frames.last() {
+                Some(frame) => frame.procedure.clone(),
+                None => self.module.name.clone(),
+            },
+        }
+    }
+
+    fn overflow(&self, op: &str) -> Trap {
+        self.trap(format!("Überlauf bei '{}'", op))
+    }
+
+    // Nach der Typprüfung unmöglich; deutet auf einen Fehler im HIR hin
+    fn internal(&self, what: &str) -> Trap {
+        self.trap(format!("Interner Fehler: {}", what))
+    }
+
+    // ========================================================================
+    // Speicher
+    // ========================================================================
+
+    fn allocate(&mut self, value: Value) -> Location {
+        self.cells.push(value);
+        Location::new(Base::Cell(self.cells.len() - 1))
+    }
+
+    fn value_at(&self, location: &Location) -> Exec<&Value> {
+        let root = match location.base {
+            Base::Cell(index) => self.cells.get(index),
+            Base::Heap(index) => self.heap.get(index),
+        };
+        match root.and_then(|root| element(root, &location.path)) {
+            Some(value) => Ok(value),
+            None => Err(self.internal("ungültiger Speicherort")),
+        }
+    }
+
+    fn load(&self, location: &Location) -> Exec<Value> {
+        self.value_at(location).cloned()
+    }
+
+    fn store(&mut self, location: &Location, value: Value) -> Exec<()> {
+        let root = match location.base {
+            Base::Cell(index) => self.cells.get_mut(index),
+            Base::Heap(index) => self.heap.get_mut(index),
+        };
+        match root.and_then(|root| element_mut(root, &location.path)) {
+            Some(slot) => {
+                assign(slot, value);
+                Ok(())
+            }
+            None => Err(self.internal("ungültiger Speicherort")),
+        }
+    }
+
+    // Wert einer Erweiterung als Wert des Basistyps: Records werden auf die
+    // Felder des Basistyps projiziert
+    fn convert(&self, value: Value, target: TypeId) -> Value {
+        match (value, self.types.kind(target)) {
+            (Value::Record { mut fields, .. }, TypeKind::Record { .. }) => {
+                fields.truncate(self.types.fields(target).len());
+                Value::Record { ty: target, fields }
+            }
+            (value, _) => value,
+        }
+    }
+
+    fn variable(&self, symbol: &SymbolRef) -> Exec<Location> {
+        let found = match symbol.kind {
+            SymbolRefKind::Variable { level: 0, .. } => self.globals.get(&symbol.qualified_name),
+            _ => self
+                .frames
+                .iter()
+                .rev()
+                .find_map(|frame| frame.variables.get(&symbol.qualified_name)),
+        };
+        match found {
+            Some(location) => Ok(location.clone()),
+            None => Err(self.internal(&format!("Variable {} nicht gefunden", symbol.qualified_name))),
+        }
+    }
+
+    // Ort einer Variablen samt Selektoren; prüft Indizes und NIL
+    fn locate(&mut self, designator: &Designator) -> Exec<Location> {
+        let mut location = self.variable(&designator.symbol)?;
+
+        for selector in &designator.selectors {
+            match selector {
+                Selector::Field { name, .. } => {
+                    let index = match self.value_at(&location)? {
+                        Value::Record { ty, .. } => self.types.field(*ty, name).map(|(index, _)| index),
+                        _ => None,
+                    };
+                    match index {
+                        Some(index) => location.path.push(index),
+                        None => return Err(self.internal(&format!("Feld {} nicht gefunden", name))),
+                    }
+                }
+                Selector::Index { index, .. } => {
+                    let index = self.integer(index)?;
+                    let length = match self.value_at(&location)? {
+                        Value::Array(items) => items.len(),
+                        _ => return Err(self.internal("Index auf Nicht-Array")),
+                    };
+                    if index < 0 || index as usize >= length {
+                        return Err(self.trap(format!(
+                            "Index {} außerhalb von 0..{}",
+                            index,
+                            length as i64 - 1
+                        )));
+                    }
+                    location.path.push(index as usize);
+                }
+                Selector::Dereference { .. } => {
+                    location = match self.load(&location)? {
+                        Value::Pointer(address) => Location::new(Base::Heap(address)),
+                        Value::Nil => return Err(self.trap("Dereferenzierung von NIL")),
+                        _ => return Err(self.internal("Dereferenzierung eines Nicht-Zeigers")),
+                    };
+                }
+                Selector::TypeGuard { .. } => {}
+            }
+        }
+        Ok(location)
+    }
+
+    // ========================================================================
+    // Anweisungen
+    // ========================================================================
+
+    fn statements(&mut self, statements: &[Statement]) -> Exec<()> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Exec<()> {
+        self.line = statement.line();
+
+        match statement {
+            Statement::Assignment { target, value, .. } => {
+                let value = self.evaluate(value)?;
+                let value = self.convert(value, target.ty);
+                let location = self.locate(target)?;
+                self.store(&location, value)
+            }
+            Statement::ProcedureCall { procedure, arguments, .. } => {
+                self.call(procedure, arguments)?;
+                Ok(())
+            }
+            Statement::BuiltinCall { builtin, arguments, .. } => self.builtin_procedure(*builtin, arguments),
+            Statement::LibraryCall { procedure, arguments, .. } => self.library_call(*procedure, arguments),
+            Statement::If { branches, else_body, .. } => {
+                for (condition, body) in branches {
+                    if self.condition(condition)? {
+                        return self.statements(body);
+                    }
+                }
+                match else_body {
+                    Some(body) => self.statements(body),
+                    None => Ok(()),
+                }
+            }
+            Statement::Case { expr, clauses, else_body, .. } => {
+                let ordinal = match self.evaluate(expr)? {
+                    Value::Integer(value) => value,
+                    Value::Char(ch) => ch as i64,
+                    _ => return Err(self.internal("CASE-Ausdruck")),
+                };
+                for clause in clauses {
+                    if clause.labels.iter().any(|&(start, end)| start <= ordinal && ordinal <= end) {
+                        return self.statements(&clause.body);
+                    }
+                }
+                match else_body {
+                    Some(body) => self.statements(body),
+                    None => Err(self.trap(format!("Kein CASE-Label für den Wert {}", ordinal))),
+                }
+            }
+            Statement::While { line, branches, .. } => loop {
+                self.line = *line;
+                let mut taken = None;
+                for (condition, body) in branches {
+                    if self.condition(condition)? {
+                        taken = Some(body);
+                        break;
+                    }
+                }
+                match taken {
+                    Some(body) => self.statements(body)?,
+                    None => return Ok(()),
+                }
+            },
+            Statement::Repeat { line, body, condition, .. } => loop {
+                self.statements(body)?;
+                self.line = *line;
+                if self.condition(condition)? {
+                    return Ok(());
+                }
+            },
+            // Die Grenze wird vor jedem Durchlauf neu ausgewertet, die
+            // Erhöhung um den Schritt ist auf Überlauf geprüft
+            Statement::For { line, variable, start, end, step, body, .. } => {
+                let location = self.variable(variable)?;
+                let start = self.integer(start)?;
+                self.store(&location, Value::Integer(start))?;
+
+                loop {
+                    self.line = *line;
+                    let end = self.integer(end)?;
+                    let current = self.counter(&location)?;
+                    if (*step > 0 && current > end) || (*step < 0 && current < end) {
+                        return Ok(());
+                    }
+                    self.statements(body)?;
+
+                    self.line = *line;
+                    let next = self.counter(&location)?.checked_add(*step).ok_or_else(|| self.overflow("FOR"))?;
+                    self.store(&location, Value::Integer(next))?;
+                }
+            }
+        }
+    }
+
+    fn counter(&self, location: &Location) -> Exec<i64> {
+        match self.value_at(location)? {
+            Value::Integer(value) => Ok(*value),
+            _ => Err(self.internal("FOR-Variable")),
+        }
+    }
+
+    // ========================================================================
+    // Prozeduraufrufe
+    // ========================================================================
+
+    // Liefert den Rückgabewert bei Funktionsprozeduren
+    fn call(&mut self, procedure: &Designator, arguments: &[Expr]) -> Exec<Option<Value>> {
+        // Direkt oder über eine Prozedurvariable
+        let name = match self.designator_value(procedure)? {
+            Value::Procedure(name) => name,
+            Value::Nil => return Err(self.trap("Aufruf einer Prozedurvariablen mit Wert NIL")),
+            _ => return Err(self.internal("Aufruf eines Nicht-Prozedurwerts")),
+        };
+        let declaration = match self.procedures.get(&name) {
+            Some(&declaration) => declaration,
+            None => return Err(self.internal(&format!("Prozedur {} nicht gefunden", name))),
+        };
+        if self.frames.len() >= MAX_DEPTH {
+            return Err(self.trap(format!("Stapelüberlauf: mehr als {} geschachtelte Aufrufe", MAX_DEPTH)));
+        }
+
+        // Argumente werden im Kontext des Aufrufers ausgewertet
+        let mark = self.cells.len();
+        let mut variables = HashMap::new();
+        for (param, argument) in declaration.params.iter().zip(arguments) {
+            let location = if param.is_var {
+                match &argument.kind {
+                    ExprKind::Designator(designator) => self.locate(designator)?,
+                    _ => return Err(self.internal("VAR-Argument ist keine Variable")),
+                }
+            } else {
+                let value = self.evaluate(argument)?;
+                let value = self.convert(value, param.param_type);
+                self.allocate(value)
+            };
+            variables.insert(format!("{}.{}", name, param.name), location);
+        }
+        for variable in &declaration.declarations.variables {
+            let location = self.allocate(Value::default_for(self.types, variable.var_type));
+            variables.insert(format!("{}.{}", name, variable.name), location);
+        }
+
+        let line = self.line;
+        self.frames.push(Frame { procedure: name, variables });
+        let result = self.statements(&declaration.body).and_then(|_| match &declaration.return_expr {
+            Some(expr) => {
+                self.line = declaration.return_line;
+                self.evaluate(expr).map(Some)
+            }
+            None => Ok(None),
+        });
+        self.frames.pop();
+        self.cells.truncate(mark);
+        self.line = line;
+        result
+    }
+
+    // ========================================================================
+    // Ausdrücke
+    // ========================================================================
+
+    fn evaluate(&mut self, expr: &Expr) -> Exec<Value> {
+        match &expr.kind {
+            // String-Konstante als Wert eines Zeichen-Arrays
+            ExprKind::Literal(ConstValue::String(text)) if expr.ty != TypeId::STRING => {
+                Ok(Value::Array(text.chars().map(Value::Char).collect()))
+            }
+            ExprKind::Literal(value) => Ok(Value::from_const(value)),
+            ExprKind::Designator(designator) => self.designator_value(designator),
+            ExprKind::Call { procedure, arguments } => match self.call(procedure, arguments)? {
+                Some(value) => Ok(value),
+                None => Err(self.internal("Prozedur ohne Rückgabewert in Ausdruck")),
+            },
+            ExprKind::BuiltinCall { builtin, arguments } => self.builtin_function(*builtin, arguments),
+            ExprKind::Unary { op, operand } => {
+                let value = self.evaluate(operand)?;
+                self.unary(op, value)
+            }
+            ExprKind::Binary { left, op, right } => self.binary(left, op, right),
+            ExprKind::Set(elements) => {
+                let mut bits = 0u32;
+                for (start, end) in elements {
+                    let start = self.set_element(start)?;
+                    let end = match end {
+                        Some(end) => self.set_element(end)?,
+                        None => start,
+                    };
+                    for bit in start..=end {
+                        bits |= 1 << bit;
+                    }
+                }
+                Ok(Value::Set(bits))
+            }
+        }
+    }
+
+    fn integer(&mut self, expr: &Expr) -> Exec<i64> {
+        match self.evaluate(expr)? {
+            Value::Integer(value) => Ok(value),
+            _ => Err(self.internal("INTEGER erwartet")),
+        }
+    }
+
+    fn condition(&mut self, expr: &Expr) -> Exec<bool> {
+        match self.evaluate(expr)? {
+            Value::Boolean(value) => Ok(value),
+            _ => Err(self.internal("BOOLEAN erwartet")),
+        }
+    }
+
+    fn set_element(&mut self, expr: &Expr) -> Exec<u32> {
+        let value = self.integer(expr)?;
+        self.set_bit(value)
+    }
+
+    fn set_bit(&self, value: i64) -> Exec<u32> {
+        if (0..=SET_MAX_ELEMENT).contains(&value) {
+            Ok(value as u32)
+        } else {
+            Err(self.trap(format!(
+                "Mengenelement {} liegt außerhalb von 0..{}",
+                value, SET_MAX_ELEMENT
+            )))
+        }
+    }
+
+    fn designator_value(&mut self, designator: &Designator) -> Exec<Value> {
+        match &designator.symbol.kind {
+            SymbolRefKind::Constant(value) => Ok(Value::from_const(value)),
+            SymbolRefKind::Procedure => Ok(Value::Procedure(designator.symbol.qualified_name.clone())),
+            SymbolRefKind::Variable { .. } => {
+                let location = self.locate(designator)?;
+                self.load(&location)
+            }
+            SymbolRefKind::Type | SymbolRefKind::Module => {
+                Err(self.internal(&format!("'{}' ist kein Wert", designator.symbol.name)))
+            }
+        }
+    }
+
+    fn unary(&self, op: &UnaryOp, value: Value) -> Exec<Value> {
+        match (op, value) {
+            (UnaryOp::Plus, value) => Ok(value),
+            (UnaryOp::Minus, Value::Integer(x)) => x.checked_neg().map(Value::Integer).ok_or_else(|| self.overflow("-")),
+            (UnaryOp::Minus, Value::Real(x)) => Ok(Value::Real(-x)),
+            (UnaryOp::Minus, Value::Set(s)) => Ok(Value::Set(!s)),
+            (UnaryOp::Not, Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+            _ => Err(self.internal("Operand eines unären Operators")),
+        }
+    }
+
+    fn binary(&mut self, left: &Expr, op: &BinaryOp, right: &Expr) -> Exec<Value> {
+        // & und OR werten den rechten Operanden nur bei Bedarf aus
+        match op {
+            BinaryOp::And => return Ok(Value::Boolean(self.condition(left)? && self.condition(right)?)),
+            BinaryOp::Or => return Ok(Value::Boolean(self.condition(left)? || self.condition(right)?)),
+            BinaryOp::Is => return self.type_test(left, right),
+            _ => {}
+        }
+
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+
+        use Value::*;
+        let result = match (op, &left, &right) {
+            (BinaryOp::Add, Integer(x), Integer(y)) => Integer(x.checked_add(*y).ok_or_else(|| self.overflow("+"))?),
+            (BinaryOp::Sub, Integer(x), Integer(y)) => Integer(x.checked_sub(*y).ok_or_else(|| self.overflow("-"))?),
+            (BinaryOp::Mul, Integer(x), Integer(y)) => Integer(x.checked_mul(*y).ok_or_else(|| self.overflow("*"))?),
+            (BinaryOp::IntDiv | BinaryOp::Mod, Integer(_), Integer(0)) => {
+                return Err(self.trap("Division durch 0"));
+            }
+            (BinaryOp::IntDiv, Integer(x), Integer(y)) => Integer(floor_div(*x, *y).ok_or_else(|| self.overflow("DIV"))?),
+            (BinaryOp::Mod, Integer(x), Integer(y)) => Integer(floor_mod(*x, *y).ok_or_else(|| self.overflow("MOD"))?),
+
+            (BinaryOp::Add, Real(x), Real(y)) => Real(x + y),
+            (BinaryOp::Sub, Real(x), Real(y)) => Real(x - y),
+            (BinaryOp::Mul, Real(x), Real(y)) => Real(x * y),
+            (BinaryOp::Div, Real(x), Real(y)) => Real(x / y),
+
+            (BinaryOp::Add, Set(x), Set(y)) => Set(x | y),
+            (BinaryOp::Sub, Set(x), Set(y)) => Set(x & !y),
+            (BinaryOp::Mul, Set(x), Set(y)) => Set(x & y),
+            (BinaryOp::Div, Set(x), Set(y)) => Set(x ^ y),
+            (BinaryOp::In, Integer(x), Set(s)) => Boolean((0..=SET_MAX_ELEMENT).contains(x) && s & (1 << *x) != 0),
+
+            (BinaryOp::Equal, Array(_) | String(_), Array(_) | String(_)) => {
+                Boolean(ordering(&left, &right) == Some(Ordering::Equal))
+            }
+            (BinaryOp::NotEqual, Array(_) | String(_), Array(_) | String(_)) => {
+                Boolean(ordering(&left, &right) != Some(Ordering::Equal))
+            }
+            (BinaryOp::Equal, _, _) => Boolean(left == right),
+            (BinaryOp::NotEqual, _, _) => Boolean(left != right),
+            (BinaryOp::Less, _, _) => Boolean(ordering(&left, &right) == Some(Ordering::Less)),
+            (BinaryOp::LessEqual, _, _) => {
+                Boolean(matches!(ordering(&left, &right), Some(Ordering::Less | Ordering::Equal)))
+            }
+            (BinaryOp::Greater, _, _) => Boolean(ordering(&left, &right) == Some(Ordering::Greater)),
+            (BinaryOp::GreaterEqual, _, _) => {
+                Boolean(matches!(ordering(&left, &right), Some(Ordering::Greater | Ordering::Equal)))
+            }
+            _ => return Err(self.internal(&format!("Operator {:?}", op))),
+        };
+        Ok(result)
+    }
+
+    // p IS T: dynamischer Typ des Records bzw. des Objekts hinter dem Zeiger
+    fn type_test(&mut self, left: &Expr, right: &Expr) -> Exec<Value> {
+        let target = match self.types.kind(right.ty) {
+            TypeKind::Pointer { target } => *target,
+            _ => right.ty,
+        };
+        let dynamic = match self.evaluate(left)? {
+            Value::Record { ty, .. } => ty,
+            Value::Pointer(address) => match self.heap.get(address) {
+                Some(Value::Record { ty, .. }) => *ty,
+                _ => return Err(self.internal("IS auf Zeiger ohne Record")),
+            },
+            Value::Nil => return Err(self.trap("Typtest mit NIL")),
+            _ => return Err(self.internal("Operand von IS")),
+        };
+        Ok(Value::Boolean(self.types.is_extension(dynamic, target)))
+    }
+
+    // ========================================================================
+    // Standardprozeduren
+    // ========================================================================
+
+    fn builtin_function(&mut self, builtin: Builtin, arguments: &[Expr]) -> Exec<Value> {
+        let mut values = Vec::new();
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        let name = builtin.name();
+        use Value::*;
+        let result = match (builtin, values.as_slice()) {
+            (Builtin::Abs, [Integer(x)]) => Integer(x.checked_abs().ok_or_else(|| self.overflow(name))?),
+            (Builtin::Abs, [Real(x)]) => Real(x.abs()),
+            (Builtin::Odd, [Integer(x)]) => Boolean(x.rem_euclid(2) == 1),
+            (Builtin::Len, [Array(items)]) => Integer(items.len() as i64),
+            (Builtin::Len, [String(text)]) => Integer(text.chars().count() as i64),
+            (Builtin::Lsl, [Integer(x), Integer(n)]) => {
+                let shift = self.shift_amount(name, *n)?;
+                let result = x.wrapping_shl(shift);
+                if result.wrapping_shr(shift) != *x {
+                    return Err(self.overflow(name));
+                }
+                Integer(result)
+            }
+            (Builtin::Asr, [Integer(x), Integer(n)]) => Integer(x >> self.shift_amount(name, *n)?),
+            (Builtin::Ror, [Integer(x), Integer(n)]) => {
+                Integer((*x as u64).rotate_right(self.shift_amount(name, *n)?) as i64)
+            }
+            (Builtin::Floor, [Real(x)]) => {
+                let floored = x.floor();
+                if !floored.is_finite() || floored < i64::MIN as f64 || floored >= i64::MAX as f64 {
+                    return Err(self.overflow(name));
+                }
+                Integer(floored as i64)
+            }
+            (Builtin::Flt, [Integer(x)]) => Real(*x as f64),
+            (Builtin::Ord, [Char(ch)]) => Integer(*ch as i64),
+            (Builtin::Ord, [Boolean(b)]) => Integer(*b as i64),
+            (Builtin::Ord, [Set(s)]) => Integer(*s as i64),
+            (Builtin::Chr, [Integer(x)]) => match u32::try_from(*x).ok().and_then(char::from_u32) {
+                Some(ch) => Char(ch),
+                None => return Err(self.trap(format!("CHR({}) ist kein gültiges Zeichen", x))),
+            },
+            _ => return Err(self.internal(&format!("Argumente für {}", name))),
+        };
+        Ok(result)
+    }
+
+    fn shift_amount(&self, name: &str, n: i64) -> Exec<u32> {
+        if (0..64).contains(&n) {
+            Ok(n as u32)
+        } else {
+            Err(self.trap(format!("Schiebeweite {} für {} außerhalb von 0..63", n, name)))
+        }
+    }
+
+    fn builtin_procedure(&mut self, builtin: Builtin, arguments: &[Expr]) -> Exec<()> {
+        if builtin == Builtin::Assert {
+            return match arguments.first() {
+                Some(condition) if self.condition(condition)? => Ok(()),
+                _ => Err(self.trap("ASSERT fehlgeschlagen")),
+            };
+        }
+
+        // Alle übrigen verändern die Variable im ersten Argument
+        let target = match arguments.first().map(|argument| &argument.kind) {
+            Some(ExprKind::Designator(designator)) => self.locate(designator)?,
+            _ => return Err(self.internal(&format!("Argument für {}", builtin.name()))),
+        };
+        let operand = match arguments.get(1) {
+            Some(argument) => Some(self.integer(argument)?),
+            None => None,
+        };
+
+        let value = match (builtin, self.load(&target)?) {
+            (Builtin::Inc, Value::Integer(x)) => {
+                Value::Integer(x.checked_add(operand.unwrap_or(1)).ok_or_else(|| self.overflow("INC"))?)
+            }
+            (Builtin::Dec, Value::Integer(x)) => {
+                Value::Integer(x.checked_sub(operand.unwrap_or(1)).ok_or_else(|| self.overflow("DEC"))?)
+            }
+            (Builtin::Incl, Value::Set(s)) => Value::Set(s | 1 << self.set_bit(operand.unwrap_or_default())?),
+            (Builtin::Excl, Value::Set(s)) => Value::Set(s & !(1 << self.set_bit(operand.unwrap_or_default())?)),
+            (Builtin::New, _) => match self.types.kind(arguments[0].ty) {
+                TypeKind::Pointer { target } => {
+                    self.heap.push(Value::default_for(self.types, *target));
+                    Value::Pointer(self.heap.len() - 1)
+                }
+                _ => return Err(self.internal("NEW ohne Zeigertyp")),
+            },
+            _ => return Err(self.internal(&format!("Argumente für {}", builtin.name()))),
+        };
+        self.store(&target, value)
+    }
+
+    // ========================================================================
+    // Bibliotheksmodule
+    // ========================================================================
+
+    fn library_call(&mut self, procedure: LibraryProcedure, arguments: &[Expr]) -> Exec<()> {
+        let mut values = Vec::new();
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        let written = match (procedure, values.as_slice()) {
+            (LibraryProcedure::OutOpen, []) => Ok(()),
+            (LibraryProcedure::OutChar, [Value::Char(ch)]) => write!(self.out, "{}", ch),
+            (LibraryProcedure::OutString, [text]) => match text.text() {
+                Some(text) => write!(self.out, "{}", text),
+                None => return Err(self.internal("Argument für Out.String")),
+            },
+            (LibraryProcedure::OutInt, [Value::Integer(x), Value::Integer(n)]) => {
+                write_padded(self.out, &x.to_string(), *n)
+            }
+            (LibraryProcedure::OutReal, [Value::Real(x), Value::Integer(n)]) => {
+                write_padded(self.out, &format!("{:.6}", x), *n)
+            }
+            (LibraryProcedure::OutLn, []) => writeln!(self.out),
+            _ => return Err(self.internal(&format!("Argumente für {}", procedure))),
+        };
+        written.map_err(|e| self.trap(format!("Ausgabe fehlgeschlagen: {}", e)))
+    }
+}
+
+fn register_procedures<'a>(procedures: &mut HashMap<String, &'a Procedure>, prefix: &str, decls: &'a Declarations) {
+    for procedure in &decls.procedures {
+        let name = if prefix.is_empty() {
+            procedure.name.clone()
+        } else {
+            format!("{}.{}", prefix, procedure.name)
+        };
+        register_procedures(procedures, &name, &procedure.declarations);
+        procedures.insert(name, procedure);
+    }
+}
+
+// Records behalten bei der Zuweisung ihren dynamischen Typ; überschrieben
+// werden nur die Felder des (statischen) zugewiesenen Werts
+fn assign(slot: &mut Value, value: Value) {
+    match (slot, value) {
+        (Value::Record { fields, .. }, Value::Record { fields: assigned, .. }) => {
+            for (field, value) in fields.iter_mut().zip(assigned) {
+                *field = value;
+            }
+        }
+        (slot, value) => *slot = value,
+    }
+}
+
+fn ordering(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Integer(x), Value::Integer(y)) => x.partial_cmp(y),
+        (Value::Real(x), Value::Real(y)) => x.partial_cmp(y),
+        (Value::Char(x), Value::Char(y)) => x.partial_cmp(y),
+        // Zeichenketten nur bis zum ersten 0X
+        (Value::String(_) | Value::Array(_), Value::String(_) | Value::Array(_)) => {
+            left.text().zip(right.text()).map(|(x, y)| x.cmp(&y))
+        }
+        (Value::Boolean(x), Value::Boolean(y)) => x.partial_cmp(y),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::checked;
+
+    // Ausgabe des Programms bzw. der Trap
+    fn run(source: &str) -> Result<String, Trap> {
+        let mut out = Vec::new();
+        Interpreter::new(&checked(source), &mut out).run()?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_execution() {
+        let output = run("MODULE T;
+            IMPORT Out;
+            TYPE
+              Node = POINTER TO NodeDesc;
+              NodeDesc = RECORD key: INTEGER; next: Node END;
+              Point = RECORD x, y: INTEGER END;
+              Point3 = RECORD (Point) z: INTEGER END;
+              Vec = ARRAY 3 OF INTEGER;
+            VAR list: Node; a, b: Vec; p: Point; q: Point3; i: INTEGER; s: SET; name: ARRAY 4 OF CHAR;
+
+            PROCEDURE Insert(VAR list: Node; key: INTEGER);
+              VAR n: Node;
+            BEGIN NEW(n); n^.key := key; n^.next := list; list := n
+            END Insert;
+
+            PROCEDURE Fib(n: INTEGER): INTEGER;
+              VAR r: INTEGER;
+            BEGIN IF n < 2 THEN r := n ELSE r := Fib(n - 1) + Fib(n - 2) END
+              RETURN r
+            END Fib;
+
+            PROCEDURE Sum(n: INTEGER): INTEGER;
+              VAR total: INTEGER;
+              PROCEDURE Add(k: INTEGER);
+              BEGIN total := total + k
+              END Add;
+            BEGIN total := 0; WHILE n > 0 DO Add(n); DEC(n) END
+              RETURN total
+            END Sum;
+
+            PROCEDURE Clear(v: Vec);
+              VAR i: INTEGER;
+            BEGIN FOR i := 0 TO 2 DO v[i] := 0 END
+            END Clear;
+
+            BEGIN
+              FOR i := 1 TO 3 DO Insert(list, i * i) END;
+              WHILE list # NIL DO Out.Int(list^.key, 3); list := list^.next END; Out.Ln;
+              Out.Int(Fib(15), 0); Out.Char(\" \"); Out.Int(Sum(10), 0); Out.Ln;
+              FOR i := 0 TO 2 DO a[i] := i + 1 END;
+              b := a; b[0] := 100; Clear(a);
+              Out.Int(a[0], 0); Out.Char(\" \"); Out.Int(b[0], 0); Out.Ln;
+              q.x := 1; q.y := 2; q.z := 3; p := q; Out.Int(p.x + p.y, 0); Out.Ln;
+              s := {1, 3..5}; INCL(s, 7); EXCL(s, 4);
+              FOR i := 31 TO 0 BY -1 DO IF i IN s THEN Out.Int(i, 2) END END; Out.Ln;
+              Out.Int((-7) DIV 2, 0); Out.Int((-7) MOD 2, 3); Out.Real(2.5 * 2.0, 9); Out.Ln;
+              name[0] := \"H\"; name[1] := \"i\"; name[2] := 0X; name[3] := \"!\"; Out.String(name); Out.Ln;
+              CASE ORD(name[0]) OF 0..64: Out.String(\"klein\") | 65..90: Out.String(\"gross\") END; Out.Ln
+            END T.")
+        .unwrap();
+
+        assert_eq!(output, "  9  4  1\n610 55\n1 100\n3\n 7 5 3 1\n-4  1 5.000000\nHi\ngross\n");
+
+        // Breiten jenseits von u16::MAX werden aufgefüllt, negative ignoriert
+        let output = run("MODULE T; IMPORT Out; BEGIN Out.Int(1, 70000); Out.Real(0.5, 70000); Out.Int(-2, -5) END T.").unwrap();
+        assert_eq!(output, format!("{}1{}0.500000-2", " ".repeat(69999), " ".repeat(69992)));
+    }
+
+    #[test]
+    fn test_traps() {
+        let trap = run("MODULE T;
+            VAR a: ARRAY 3 OF INTEGER; i: INTEGER;
+            PROCEDURE Set(k: INTEGER);
+            BEGIN
+              a[k] := k
+            END Set;
+            BEGIN
+              FOR i := 0 TO 3 DO Set(i) END
+            END T.")
+        .unwrap_err();
+        assert_eq!(trap.to_string(), "5: Laufzeitfehler in Set: Index 3 außerhalb von 0..2");
+
+        let message = |source: &str| run(source).unwrap_err().message;
+        assert_eq!(
+            message("MODULE T; TYPE P = POINTER TO RECORD x: INTEGER END; VAR p: P; BEGIN p^.x := 1 END T."),
+            "Dereferenzierung von NIL"
+        );
+        assert_eq!(message("MODULE T; VAR i: INTEGER; BEGIN i := 7 MOD i END T."), "Division durch 0");
+        assert_eq!(
+            message("MODULE T; VAR i: INTEGER; BEGIN i := 4; CASE i OF 1..3: i := 0 END END T."),
+            "Kein CASE-Label für den Wert 4"
+        );
+        assert_eq!(message("MODULE T; VAR i: INTEGER; BEGIN ASSERT(i > 0) END T."), "ASSERT fehlgeschlagen");
+        assert!(message("MODULE T; VAR i: INTEGER; BEGIN i := 1; i := LSL(i, 62) * 4 END T.").starts_with("Überlauf"));
+
+        // RETURN-Ausdrücke melden ihre eigene Zeile
+        let recursion = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(|| {
+            run("MODULE T;
+                PROCEDURE Down(n: INTEGER): INTEGER;
+                BEGIN
+                  RETURN Down(n - 1)
+                END Down;
+                BEGIN ASSERT(Down(0) = 0)
+                END T.")
+        });
+        let trap = recursion.unwrap().join().unwrap().unwrap_err();
+        assert_eq!((trap.line, trap.procedure.as_str()), (4, "Down"));
+        assert!(trap.message.starts_with("Stapelüberlauf"));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::semantic::{ConstValue, TypeArena, TypeId, TypeKind};
+
+// ============================================================================
+// Laufzeitwerte
+// ============================================================================
+//
+// Arrays und Records sind Werte: Zuweisung und Wertparameter kopieren sie
+// vollständig. Nur über Zeiger (Heap) und VAR-Parameter (Orte) entstehen
+// gemeinsam benutzte Daten.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Real(f64),
+    Boolean(bool),
+    Char(char),
+    Set(u32),
+    // String-Konstanten
+    String(String),
+    Nil,
+    // Index in den Heap
+    Pointer(usize),
+    // Qualifizierter Name der Prozedur
+    Procedure(String),
+    Array(Vec<Value>),
+    // Dynamischer Typ und alle Felder in der Reihenfolge von TypeArena::fields
+    Record { ty: TypeId, fields: Vec<Value> },
+}
+
+impl Value {
+    pub fn from_const(value: &ConstValue) -> Value {
+        match value {
+            ConstValue::Integer(val) => Value::Integer(*val),
+            ConstValue::Real(val) => Value::Real(*val),
+            ConstValue::Boolean(val) => Value::Boolean(*val),
+            ConstValue::Char(ch) => Value::Char(*ch),
+            ConstValue::Set(bits) => Value::Set(*bits),
+            ConstValue::String(val) => Value::String(val.clone()),
+            ConstValue::Nil => Value::Nil,
+        }
+    }
+
+    // Anfangswert einer Variablen: Null, NIL bzw. elementweise
+    pub fn default_for(types: &TypeArena, ty: TypeId) -> Value {
+        match types.kind(ty) {
+            TypeKind::Integer => Value::Integer(0),
+            TypeKind::Real => Value::Real(0.0),
+            TypeKind::Boolean => Value::Boolean(false),
+            TypeKind::Char => Value::Char('\0'),
+            TypeKind::Set => Value::Set(0),
+            TypeKind::String => Value::String(String::new()),
+            TypeKind::Array { length, element } => {
+                Value::Array(vec![Value::default_for(types, *element); *length])
+            }
+            TypeKind::Record { .. } => Value::Record {
+                ty,
+                fields: types
+                    .fields(ty)
+                    .into_iter()
+                    .map(|field| Value::default_for(types, field.ty))
+                    .collect(),
+            },
+            TypeKind::Nil
+            | TypeKind::Pointer { .. }
+            | TypeKind::Procedure { .. }
+            | TypeKind::Imported(_)
+            | TypeKind::Forward(_) => Value::Nil,
+        }
+    }
+
+    // Inhalt einer Zeichenkette: String-Konstante oder ARRAY OF CHAR bis 0X
+    pub fn text(&self) -> Option<String> {
+        match self {
+            Value::String(text) => Some(text.clone()),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::Char(ch) => Some(*ch),
+                    _ => None,
+                })
+                .take_while(|ch| *ch != Some('\0'))
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// Orte
+// ============================================================================
+
+// Wurzel eines Orts: Speicherzelle einer Variablen oder Heap-Objekt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Base {
+    Cell(usize),
+    Heap(usize),
+}
+
+// Ort eines Werts: Wurzel und Indizes in Arrays bzw. Feldpositionen in Records
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub base: Base,
+    pub path: Vec<usize>,
+}
+
+impl Location {
+    pub fn new(base: Base) -> Self {
+        Location { base, path: Vec::new() }
+    }
+}
+
+// Folgt dem Pfad ab `value`; None bei einem Pfad, der nicht zum Wert passt
+pub fn element<'v>(mut value: &'v Value, path: &[usize]) -> Option<&'v Value> {
+    for &index in path {
+        value = match value {
+            Value::Array(items) | Value::Record { fields: items, .. } => items.get(index)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+pub fn element_mut<'v>(mut value: &'v mut Value, path: &[usize]) -> Option<&'v mut Value> {
+    for &index in path {
+        value = match value {
+            Value::Array(items) | Value::Record { fields: items, .. } => items.get_mut(index)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
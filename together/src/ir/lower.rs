@@ -0,0 +1,889 @@
+use super::{
+    BinaryOp, Block, BlockId, Callee, CompareOp, Constant, Function, Global, InstKind, Instruction, Param, Program,
+    Slot, Terminator, Type, UnaryOp, Value,
+};
+use crate::parser::ast::{self, ExportMark, Span};
+use crate::semantic::hir::{self, Declarations, Designator, Expr, ExprKind, Selector, Statement, SymbolRef, SymbolRefKind};
+use crate::semantic::library::LibraryProcedure;
+use crate::semantic::symbol_table::Builtin;
+use crate::semantic::{ConstValue, TypeArena, TypeId, TypeKind};
+use std::collections::HashMap;
+
+// ============================================================================
+// HIR -> IR
+// ============================================================================
+//
+// Jede Variable liegt zunächst in einem Slot bzw. einer globalen Variablen
+// und wird über Load und Store gelesen und geschrieben; & und OR laufen über
+// eine Hilfsvariable. Die SSA-Form baut erst ssa::construct.
+
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    depth: usize,
+    index: usize,
+    ty: TypeId,
+    by_reference: bool,
+}
+
+pub struct Lowering {
+    types: TypeArena,
+    globals: HashMap<String, TypeId>,
+    // Tiefe jeder Prozedur nach qualifiziertem Namen
+    procedures: HashMap<String, usize>,
+    locals: HashMap<String, Local>,
+    functions: Vec<Function>,
+
+    // Funktion in Arbeit; Blöcke ohne Abschluss sind noch offen
+    function: Function,
+    terminators: Vec<Option<Terminator>>,
+    current: BlockId,
+    line: usize,
+    span: Span,
+}
+
+impl Lowering {
+    pub fn new() -> Self {
+        Lowering {
+            types: TypeArena::new(),
+            globals: HashMap::new(),
+            procedures: HashMap::new(),
+            locals: HashMap::new(),
+            functions: Vec::new(),
+            function: empty_function(),
+            terminators: Vec::new(),
+            current: BlockId::ENTRY,
+            line: 0,
+            span: Span::default(),
+        }
+    }
+
+    // Bereits übersetzte Deklarationen, auf die das Modul verweisen darf
+    // (frühere Eingaben der REPL)
+    pub fn declare(&mut self, declarations: &Declarations) {
+        for variable in &declarations.variables {
+            self.globals.insert(variable.name.clone(), variable.var_type);
+        }
+        self.register_procedures("", declarations, 1);
+    }
+
+    pub fn lower(mut self, module: &hir::Module) -> Result<Program, String> {
+        self.types = module.types.clone();
+        self.declare(&module.declarations);
+        for variable in &module.imported_variables {
+            self.globals.insert(variable.name.clone(), variable.var_type);
+        }
+
+        let globals: Vec<Global> = module
+            .declarations
+            .variables
+            .iter()
+            .map(|variable| Global {
+                name: variable.name.clone(),
+                ty: variable.var_type,
+                exported: variable.exported != ExportMark::None,
+                line: variable.line,
+            })
+            .collect();
+
+        for procedure in &module.declarations.procedures {
+            self.lower_procedure("", procedure, 1)?;
+        }
+
+        // Modulrumpf
+        self.line = module.body.first().map(Statement::line).unwrap_or(0);
+        self.span = Span::default();
+        self.begin(&module.name, false, 0, Vec::new());
+        self.function.line = self.line;
+        self.function.end_line = module.body.last().map(Statement::line).unwrap_or(self.line);
+        for global in &globals {
+            if self.contains_record(global.ty) {
+                let address = self.value(Type::Ptr, InstKind::Global(global.name.clone()));
+                self.effect(InstKind::InitDescriptors { ty: global.ty, address });
+            }
+        }
+        self.statements(&module.body)?;
+        self.terminate(Terminator::Return(None));
+        self.finish();
+
+        Ok(Program {
+            name: module.name.clone(),
+            types: self.types,
+            globals,
+            functions: self.functions,
+        })
+    }
+
+    fn register_procedures(&mut self, prefix: &str, declarations: &Declarations, depth: usize) {
+        for procedure in &declarations.procedures {
+            let name = qualify(prefix, &procedure.name);
+            self.procedures.insert(name.clone(), depth);
+            self.register_procedures(&name, &procedure.declarations, depth + 1);
+        }
+    }
+
+    // ========================================================================
+    // Prozeduren
+    // ========================================================================
+
+    fn lower_procedure(&mut self, prefix: &str, procedure: &hir::Procedure, depth: usize) -> Result<(), String> {
+        let name = qualify(prefix, &procedure.name);
+
+        // Slots: Parameter, dann lokale Variablen; geschachtelte Prozeduren
+        // sehen sie über ihren qualifizierten Namen
+        let mut slots = Vec::new();
+        let variables = procedure
+            .params
+            .iter()
+            .map(|param| (&param.name, param.param_type, param.is_var))
+            .chain(procedure.declarations.variables.iter().map(|variable| (&variable.name, variable.var_type, false)));
+        for (variable, ty, by_reference) in variables {
+            let local = Local { depth, index: slots.len(), ty, by_reference };
+            self.locals.insert(qualify(&name, variable), local);
+            slots.push(Slot { name: Some(variable.clone()), ty, by_reference });
+        }
+
+        for nested in &procedure.declarations.procedures {
+            self.lower_procedure(&name, nested, depth + 1)?;
+        }
+
+        self.line = procedure.body.first().map(Statement::line).unwrap_or(procedure.return_line);
+        self.span = Span::default();
+        self.begin(&name, depth == 1 && procedure.exported != ExportMark::None, depth, slots);
+        self.function.result = procedure.return_type.and_then(|ty| Type::of(&self.types, ty));
+        self.function.line = procedure.line;
+        self.function.end_line = procedure.end_line;
+
+        // Parameter in ihre Slots; strukturierte Wertparameter werden kopiert
+        for param in &procedure.params {
+            let ty = if param.is_var || self.is_structured(param.param_type) { Type::Ptr } else { self.scalar(param.param_type)? };
+            let value = self.function.new_value(ty);
+            self.function.params.push(Param { name: param.name.clone(), ty: param.param_type, is_var: param.is_var, value });
+        }
+        for (index, param) in procedure.params.iter().enumerate() {
+            let by_address = param.is_var || self.is_structured(param.param_type);
+            let value = self.function.params[index].value;
+            let ty = self.function.value_type(value);
+            let address = self.value(Type::Ptr, InstKind::Slot { depth, index });
+            if param.is_var || !by_address {
+                self.effect(InstKind::Store { ty, address, value });
+            } else {
+                if self.contains_record(param.param_type) {
+                    self.effect(InstKind::InitDescriptors { ty: param.param_type, address });
+                }
+                self.effect(InstKind::Copy { ty: param.param_type, dest: address, source: value });
+            }
+        }
+        for (offset, variable) in procedure.declarations.variables.iter().enumerate() {
+            if self.contains_record(variable.var_type) {
+                let index = procedure.params.len() + offset;
+                let address = self.value(Type::Ptr, InstKind::Slot { depth, index });
+                self.effect(InstKind::InitDescriptors { ty: variable.var_type, address });
+            }
+        }
+
+        self.statements(&procedure.body)?;
+        let result = match (&procedure.return_expr, procedure.return_type) {
+            (Some(expr), Some(ty)) => {
+                self.line = procedure.return_line;
+                self.span = Span::default();
+                Some(self.value_for(expr, ty)?)
+            }
+            _ => None,
+        };
+        self.terminate(Terminator::Return(result));
+        self.finish();
+        Ok(())
+    }
+
+    fn begin(&mut self, name: &str, exported: bool, depth: usize, slots: Vec<Slot>) {
+        self.function = Function { name: name.to_string(), exported, depth, slots, ..empty_function() };
+        self.terminators.clear();
+        self.current = self.new_block();
+    }
+
+    fn finish(&mut self) {
+        let mut function = std::mem::replace(&mut self.function, empty_function());
+        for (block, terminator) in function.blocks.iter_mut().zip(self.terminators.drain(..)) {
+            // Offene Blöcke sind nach einem Abschluss angelegt und unerreichbar
+            block.terminator = terminator.unwrap_or(Terminator::Return(None));
+        }
+        self.functions.push(function);
+    }
+
+    // ========================================================================
+    // Blöcke und Befehle
+    // ========================================================================
+
+    fn new_block(&mut self) -> BlockId {
+        self.function.blocks.push(Block {
+            phis: Vec::new(),
+            instructions: Vec::new(),
+            terminator: Terminator::Return(None),
+        });
+        self.terminators.push(None);
+        BlockId(self.function.blocks.len() as u32 - 1)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let slot = &mut self.terminators[self.current.index()];
+        if slot.is_none() {
+            *slot = Some(terminator);
+        }
+    }
+
+    fn push(&mut self, dest: Option<Value>, kind: InstKind) {
+        let instruction = Instruction { dest, kind, line: self.line, span: self.span, origin: None };
+        self.function.blocks[self.current.index()].instructions.push(instruction);
+    }
+
+    fn value(&mut self, ty: Type, kind: InstKind) -> Value {
+        let dest = self.function.new_value(ty);
+        self.push(Some(dest), kind);
+        dest
+    }
+
+    fn effect(&mut self, kind: InstKind) {
+        self.push(None, kind);
+    }
+
+    fn constant(&mut self, constant: Constant) -> Value {
+        let ty = match constant {
+            Constant::Int(_) => Type::Int,
+            Constant::Real(_) => Type::Real,
+            Constant::Bool(_) => Type::Bool,
+            Constant::Char(_) => Type::Char,
+            Constant::Set(_) => Type::Set,
+            Constant::Nil => Type::Ptr,
+        };
+        self.value(ty, InstKind::Const(constant))
+    }
+
+    // ========================================================================
+    // Typen
+    // ========================================================================
+
+    fn scalar(&self, ty: TypeId) -> Result<Type, String> {
+        Type::of(&self.types, ty).ok_or_else(|| format!("{} ist kein einfacher Typ", self.types.display(ty)))
+    }
+
+    fn is_structured(&self, ty: TypeId) -> bool {
+        Type::of(&self.types, ty).is_none()
+    }
+
+    fn contains_record(&self, ty: TypeId) -> bool {
+        match self.types.kind(ty) {
+            TypeKind::Record { .. } => true,
+            TypeKind::Array { element, .. } => self.contains_record(*element),
+            _ => false,
+        }
+    }
+
+    // ========================================================================
+    // Anweisungen
+    // ========================================================================
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<(), String> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    // Verzweigt nach `then_block`, wenn die Bedingung gilt, sonst nach `else_block`
+    fn condition(&mut self, condition: &Expr, then_block: BlockId, else_block: BlockId) -> Result<(), String> {
+        let condition = self.expression(condition)?;
+        self.terminate(Terminator::Branch { condition, then_block, else_block });
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), String> {
+        self.line = statement.line();
+        self.span = statement.span();
+
+        match statement {
+            Statement::Assignment { target, value, .. } => self.assignment(target, value)?,
+            Statement::ProcedureCall { procedure, arguments, .. } => {
+                self.call(procedure, arguments)?;
+            }
+            Statement::BuiltinCall { builtin, arguments, .. } => self.builtin_procedure(*builtin, arguments)?,
+            Statement::LibraryCall { procedure, arguments, .. } => self.library_call(*procedure, arguments)?,
+            Statement::If { branches, else_body, .. } => {
+                let end = self.new_block();
+                for (condition, body) in branches {
+                    let (then_block, next) = (self.new_block(), self.new_block());
+                    self.condition(condition, then_block, next)?;
+                    self.switch_to(then_block);
+                    self.statements(body)?;
+                    self.terminate(Terminator::Jump(end));
+                    self.switch_to(next);
+                }
+                if let Some(body) = else_body {
+                    self.statements(body)?;
+                }
+                self.terminate(Terminator::Jump(end));
+                self.switch_to(end);
+            }
+            Statement::Case { line, expr, clauses, else_body, .. } => {
+                let value = self.value_for(expr, expr.ty)?;
+                let end = self.new_block();
+                let mut cases = Vec::new();
+                let mut bodies = Vec::new();
+                for clause in clauses {
+                    let body = self.new_block();
+                    cases.extend(clause.labels.iter().map(|&(start, stop)| (start, stop, body)));
+                    bodies.push(body);
+                }
+                let default = self.new_block();
+                self.terminate(Terminator::Switch { value, cases, default });
+
+                self.switch_to(default);
+                match else_body {
+                    Some(body) => {
+                        self.statements(body)?;
+                        self.terminate(Terminator::Jump(end));
+                    }
+                    None => self.terminate(Terminator::CaseTrap { value, line: *line }),
+                }
+                for (clause, body) in clauses.iter().zip(bodies) {
+                    self.switch_to(body);
+                    self.statements(&clause.body)?;
+                    self.terminate(Terminator::Jump(end));
+                }
+                self.switch_to(end);
+            }
+            // Jeder Zweig springt nach seinem Rumpf an den Schleifenanfang;
+            // trifft keiner zu, endet die Schleife
+            Statement::While { branches, .. } => {
+                let top = self.new_block();
+                self.terminate(Terminator::Jump(top));
+                self.switch_to(top);
+                for (condition, body) in branches {
+                    let (body_block, next) = (self.new_block(), self.new_block());
+                    self.condition(condition, body_block, next)?;
+                    self.switch_to(body_block);
+                    self.statements(body)?;
+                    self.terminate(Terminator::Jump(top));
+                    self.switch_to(next);
+                }
+            }
+            Statement::Repeat { line, span, body, condition } => {
+                let top = self.new_block();
+                self.terminate(Terminator::Jump(top));
+                self.switch_to(top);
+                self.statements(body)?;
+                self.line = *line;
+                self.span = *span;
+                let exit = self.new_block();
+                self.condition(condition, exit, top)?;
+                self.switch_to(exit);
+            }
+            // Nach dem Report: v := beg; WHILE v <= end DO S; v := v + inc END
+            Statement::For { line, span, variable, start, end, step, body } => {
+                let (address, _) = self.variable(variable)?;
+                let value = self.expression(start)?;
+                self.effect(InstKind::Store { ty: Type::Int, address, value });
+
+                let (top, body_block, exit) = (self.new_block(), self.new_block(), self.new_block());
+                self.terminate(Terminator::Jump(top));
+                self.switch_to(top);
+                let (address, _) = self.variable(variable)?;
+                let current = self.value(Type::Int, InstKind::Load { ty: Type::Int, address });
+                let limit = self.expression(end)?;
+                let op = if *step > 0 { CompareOp::Le } else { CompareOp::Ge };
+                let condition = self.value(Type::Bool, InstKind::Compare { op, left: current, right: limit });
+                self.terminate(Terminator::Branch { condition, then_block: body_block, else_block: exit });
+
+                self.switch_to(body_block);
+                self.statements(body)?;
+                self.line = *line;
+                self.span = *span;
+                let (address, _) = self.variable(variable)?;
+                let current = self.value(Type::Int, InstKind::Load { ty: Type::Int, address });
+                let step = self.constant(Constant::Int(*step));
+                let next = self.value(Type::Int, InstKind::Binary { op: BinaryOp::For, left: current, right: step });
+                self.effect(InstKind::Store { ty: Type::Int, address, value: next });
+                self.terminate(Terminator::Jump(top));
+                self.switch_to(exit);
+            }
+        }
+        Ok(())
+    }
+
+    // Einfache Werte werden gespeichert, Arrays und Records kopiert; Records
+    // behalten ihren Deskriptor und erhalten nur die Felder des Zieltyps
+    fn assignment(&mut self, target: &Designator, value: &Expr) -> Result<(), String> {
+        let dest = self.address(target)?;
+        if !self.is_structured(target.ty) {
+            let ty = self.scalar(target.ty)?;
+            let value = self.value_for(value, target.ty)?;
+            self.effect(InstKind::Store { ty, address: dest, value });
+            return Ok(());
+        }
+
+        let source = self.expression(value)?;
+        match (string_literal(value), self.types.kind(target.ty)) {
+            // Strings werden samt 0X kopiert, höchstens so viel, wie passt
+            (Some(text), TypeKind::Array { length, .. }) => {
+                let length = (text.chars().count() + 1).min(*length);
+                self.effect(InstKind::CopyString { dest, source, length });
+            }
+            _ => self.effect(InstKind::Copy { ty: target.ty, dest, source }),
+        }
+        Ok(())
+    }
+
+    fn builtin_procedure(&mut self, builtin: Builtin, arguments: &[Expr]) -> Result<(), String> {
+        if builtin == Builtin::Assert {
+            let condition = arguments.first().ok_or("ASSERT ohne Argument")?;
+            let condition = self.expression(condition)?;
+            self.effect(InstKind::Assert(condition));
+            return Ok(());
+        }
+
+        // INC, DEC, INCL, EXCL und NEW verändern ihr erstes Argument
+        let target = match arguments.first() {
+            Some(Expr { kind: ExprKind::Designator(designator), .. }) => designator,
+            _ => return Err(format!("{} erwartet eine Variable", builtin.name())),
+        };
+        let address = self.address(target)?;
+
+        if builtin == Builtin::New {
+            let record = match self.types.kind(target.ty) {
+                TypeKind::Pointer { target } => *target,
+                _ => return Err("NEW erwartet einen Zeiger".to_string()),
+            };
+            let object = self.value(Type::Ptr, InstKind::New { record });
+            self.effect(InstKind::Store { ty: Type::Ptr, address, value: object });
+            return Ok(());
+        }
+
+        let operand = match arguments.get(1) {
+            Some(operand) => self.expression(operand)?,
+            None => self.constant(Constant::Int(1)),
+        };
+        let (ty, op, operand) = match builtin {
+            Builtin::Inc => (Type::Int, BinaryOp::Inc, operand),
+            Builtin::Dec => (Type::Int, BinaryOp::Dec, operand),
+            Builtin::Incl | Builtin::Excl => {
+                let element = self.value(Type::Set, InstKind::Binary { op: BinaryOp::Range, left: operand, right: operand });
+                let op = if builtin == Builtin::Incl { BinaryOp::Union } else { BinaryOp::Difference };
+                (Type::Set, op, element)
+            }
+            _ => return Err(format!("{} ist keine eigentliche Prozedur", builtin.name())),
+        };
+        let old = self.value(ty, InstKind::Load { ty, address });
+        let new = self.value(ty, InstKind::Binary { op, left: old, right: operand });
+        self.effect(InstKind::Store { ty, address, value: new });
+        Ok(())
+    }
+
+    fn library_call(&mut self, procedure: LibraryProcedure, arguments: &[Expr]) -> Result<(), String> {
+        let arguments = match procedure {
+            LibraryProcedure::OutOpen => return Ok(()),
+            LibraryProcedure::OutLn => Vec::new(),
+            LibraryProcedure::OutChar => vec![self.value_for(&arguments[0], TypeId::CHAR)?],
+            LibraryProcedure::OutString => {
+                let length = self.string_length(&arguments[0])?;
+                let text = self.expression(&arguments[0])?;
+                vec![text, self.constant(Constant::Int(length as i64))]
+            }
+            LibraryProcedure::OutInt | LibraryProcedure::OutReal => {
+                let value = self.expression(&arguments[0])?;
+                vec![value, self.expression(&arguments[1])?]
+            }
+        };
+        self.effect(InstKind::Output { procedure, arguments });
+        Ok(())
+    }
+
+    // Platz, den eine Zeichenkette höchstens belegt: Länge des Arrays bzw.
+    // des Strings samt 0X
+    fn string_length(&self, expr: &Expr) -> Result<usize, String> {
+        if let Some(text) = string_literal(expr) {
+            return Ok(text.chars().count() + 1);
+        }
+        match self.types.kind(expr.ty) {
+            TypeKind::Array { length, .. } => Ok(*length),
+            _ => Err("Zeichenkette erwartet".to_string()),
+        }
+    }
+
+    // Liefert das Ergebnis bei Funktionsprozeduren
+    fn call(&mut self, procedure: &Designator, arguments: &[Expr]) -> Result<Option<Value>, String> {
+        let (params, result) = match self.types.kind(procedure.ty) {
+            TypeKind::Procedure { params, result } => (params.clone(), *result),
+            _ => return Err(format!("{} ist keine Prozedur", procedure.symbol.name)),
+        };
+
+        let mut values = Vec::new();
+        for (param, argument) in params.iter().zip(arguments) {
+            let value = if param.is_var {
+                match &argument.kind {
+                    ExprKind::Designator(designator) => self.address(designator)?,
+                    _ => return Err(format!("VAR-Argument für {} ist keine Variable", param.name)),
+                }
+            } else if self.is_structured(param.param_type) {
+                self.expression(argument)?
+            } else {
+                self.value_for(argument, param.param_type)?
+            };
+            values.push(value);
+        }
+
+        let callee = match (&procedure.symbol.kind, procedure.selectors.is_empty()) {
+            (SymbolRefKind::Procedure, true) => {
+                let name = procedure.symbol.qualified_name.clone();
+                // Prozeduren importierter Module liegen auf Modulebene
+                let depth = match procedure.symbol.module {
+                    Some(_) => 1,
+                    None => self.procedures.get(&name).copied().unwrap_or(1),
+                };
+                Callee::Direct { name, depth }
+            }
+            _ => Callee::Indirect(self.expression(&Expr::new(ExprKind::Designator(procedure.clone()), procedure.ty))?),
+        };
+
+        let kind = InstKind::Call { callee, arguments: values };
+        match result {
+            Some(ty) => {
+                let ty = self.scalar(ty)?;
+                Ok(Some(self.value(ty, kind)))
+            }
+            None => {
+                self.effect(kind);
+                Ok(None)
+            }
+        }
+    }
+
+    // ========================================================================
+    // Adressen
+    // ========================================================================
+
+    // Adresse und Typ einer Variablen
+    fn variable(&mut self, symbol: &SymbolRef) -> Result<(Value, TypeId), String> {
+        match symbol.kind {
+            SymbolRefKind::Variable { level: 0, .. } => {
+                let ty = *self
+                    .globals
+                    .get(&symbol.qualified_name)
+                    .ok_or_else(|| format!("Variable {} nicht gefunden", symbol.qualified_name))?;
+                Ok((self.value(Type::Ptr, InstKind::Global(symbol.qualified_name.clone())), ty))
+            }
+            SymbolRefKind::Variable { .. } => {
+                let local = *self
+                    .locals
+                    .get(&symbol.qualified_name)
+                    .ok_or_else(|| format!("Variable {} nicht gefunden", symbol.qualified_name))?;
+                let mut address = self.value(Type::Ptr, InstKind::Slot { depth: local.depth, index: local.index });
+                if local.by_reference {
+                    address = self.value(Type::Ptr, InstKind::Load { ty: Type::Ptr, address });
+                }
+                Ok((address, local.ty))
+            }
+            _ => Err(format!("'{}' ist keine Variable", symbol.name)),
+        }
+    }
+
+    // Prüft Indizes und NIL
+    fn address(&mut self, designator: &Designator) -> Result<Value, String> {
+        let (mut address, mut ty) = self.variable(&designator.symbol)?;
+
+        for selector in &designator.selectors {
+            match selector {
+                Selector::Field { name, ty: next } => {
+                    let (field, _) = self
+                        .types
+                        .field(ty, name)
+                        .ok_or_else(|| format!("Feld {} nicht gefunden", name))?;
+                    address = self.value(Type::Ptr, InstKind::Field { base: address, record: ty, field });
+                    ty = *next;
+                }
+                Selector::Index { index, ty: next } => {
+                    let length = match self.types.kind(ty) {
+                        TypeKind::Array { length, .. } => *length,
+                        _ => return Err("Index auf Nicht-Array".to_string()),
+                    };
+                    let index = self.expression(index)?;
+                    self.effect(InstKind::CheckIndex { index, length });
+                    address = self.value(Type::Ptr, InstKind::Element { base: address, array: ty, index });
+                    ty = *next;
+                }
+                Selector::Dereference { ty: next } => {
+                    address = self.value(Type::Ptr, InstKind::Load { ty: Type::Ptr, address });
+                    self.effect(InstKind::CheckNil(address));
+                    ty = *next;
+                }
+                Selector::TypeGuard { ty: next } => ty = *next,
+            }
+        }
+        Ok(address)
+    }
+
+    // ========================================================================
+    // Ausdrücke
+    // ========================================================================
+
+    // Ausdruck für ein Ziel vom Typ `target`: einbuchstabige Strings werden
+    // zu CHAR
+    fn value_for(&mut self, expr: &Expr, target: TypeId) -> Result<Value, String> {
+        match string_literal(expr) {
+            Some(text) if target == TypeId::CHAR => {
+                let ch = text.chars().next().unwrap_or('\0');
+                Ok(self.constant(Constant::Char(ch as u32 as u8)))
+            }
+            _ => self.expression(expr),
+        }
+    }
+
+    fn literal(&mut self, value: &ConstValue) -> Value {
+        match value {
+            ConstValue::Integer(value) => self.constant(Constant::Int(*value)),
+            ConstValue::Real(value) => self.constant(Constant::Real(*value)),
+            ConstValue::Boolean(value) => self.constant(Constant::Bool(*value)),
+            ConstValue::Char(ch) => self.constant(Constant::Char(*ch as u32 as u8)),
+            ConstValue::Set(bits) => self.constant(Constant::Set(*bits)),
+            ConstValue::String(text) => {
+                let bytes = text.chars().map(|ch| ch as u32 as u8).collect();
+                self.value(Type::Ptr, InstKind::String(bytes))
+            }
+            ConstValue::Nil => self.constant(Constant::Nil),
+        }
+    }
+
+    // Einfache Werte als Wert, Arrays, Records und Strings als Adresse
+    fn expression(&mut self, expr: &Expr) -> Result<Value, String> {
+        let value = match &expr.kind {
+            ExprKind::Literal(value) => self.literal(value),
+            ExprKind::Designator(designator) => match &designator.symbol.kind {
+                SymbolRefKind::Constant(value) => self.literal(value),
+                SymbolRefKind::Procedure => {
+                    let name = &designator.symbol.qualified_name;
+                    if designator.symbol.module.is_none() && self.procedures.get(name).copied().unwrap_or(1) > 1 {
+                        return Err(format!("Lokale Prozedur {} kann kein Wert sein", name));
+                    }
+                    self.value(Type::Ptr, InstKind::Procedure(name.clone()))
+                }
+                SymbolRefKind::Variable { .. } => {
+                    let address = self.address(designator)?;
+                    match Type::of(&self.types, designator.ty) {
+                        Some(ty) => self.value(ty, InstKind::Load { ty, address }),
+                        None => address,
+                    }
+                }
+                SymbolRefKind::Type | SymbolRefKind::Module => {
+                    return Err(format!("'{}' ist kein Wert", designator.symbol.name));
+                }
+            },
+            ExprKind::Call { procedure, arguments } => self
+                .call(procedure, arguments)?
+                .ok_or_else(|| format!("{} liefert keinen Wert", procedure.symbol.name))?,
+            ExprKind::BuiltinCall { builtin, arguments } => self.builtin_function(*builtin, arguments)?,
+            ExprKind::Unary { op, operand } => {
+                let value = self.expression(operand)?;
+                let op = match (op, self.types.kind(operand.ty)) {
+                    (ast::UnaryOp::Plus, _) => return Ok(value),
+                    (ast::UnaryOp::Minus, TypeKind::Integer) => UnaryOp::Neg,
+                    (ast::UnaryOp::Minus, TypeKind::Real) => UnaryOp::FNeg,
+                    (ast::UnaryOp::Minus, _) => UnaryOp::Complement,
+                    (ast::UnaryOp::Not, _) => UnaryOp::Not,
+                };
+                let ty = self.function.value_type(value);
+                self.value(ty, InstKind::Unary { op, operand: value })
+            }
+            ExprKind::Binary { left, op, right } => self.binary(left, op, right)?,
+            ExprKind::Set(elements) => {
+                let mut set = self.constant(Constant::Set(0));
+                for (start, end) in elements {
+                    let low = self.expression(start)?;
+                    let high = match end {
+                        Some(end) => self.expression(end)?,
+                        None => low,
+                    };
+                    let range = self.value(Type::Set, InstKind::Binary { op: BinaryOp::Range, left: low, right: high });
+                    set = self.value(Type::Set, InstKind::Binary { op: BinaryOp::Union, left: set, right: range });
+                }
+                set
+            }
+        };
+        Ok(value)
+    }
+
+    fn binary(&mut self, left: &Expr, op: &ast::BinaryOp, right: &Expr) -> Result<Value, String> {
+        match op {
+            ast::BinaryOp::And | ast::BinaryOp::Or => return self.short_circuit(left, op, right),
+            ast::BinaryOp::Is => {
+                let record = match self.types.kind(right.ty) {
+                    TypeKind::Pointer { target } => *target,
+                    _ => right.ty,
+                };
+                let address = self.expression(left)?;
+                if let TypeKind::Pointer { .. } = self.types.kind(left.ty) {
+                    self.effect(InstKind::CheckNil(address));
+                }
+                return Ok(self.value(Type::Bool, InstKind::TypeTest { address, record }));
+            }
+            _ => {}
+        }
+
+        // Vergleiche von Zeichenketten
+        let is_text = |ty: TypeId| matches!(self.types.kind(ty), TypeKind::String | TypeKind::Array { .. });
+        if is_text(left.ty) && is_text(right.ty) {
+            let op = compare_op(op).ok_or_else(|| format!("Operator {:?} für Zeichenketten", op))?;
+            let (left_length, right_length) = (self.string_length(left)?, self.string_length(right)?);
+            let left = self.expression(left)?;
+            let right = self.expression(right)?;
+            return Ok(self.value(
+                Type::Bool,
+                InstKind::CompareStrings { op, left, left_length, right, right_length },
+            ));
+        }
+
+        // Ein einbuchstabiger String neben einem CHAR ist ein Zeichen
+        let operand_type = if right.ty == TypeId::CHAR { right.ty } else { left.ty };
+        let a = self.value_for(left, operand_type)?;
+        let b = self.value_for(right, operand_type)?;
+
+        if let Some(compare) = compare_op(op) {
+            return Ok(self.value(Type::Bool, InstKind::Compare { op: compare, left: a, right: b }));
+        }
+        let (ty, op) = match (self.types.kind(operand_type), op) {
+            (_, ast::BinaryOp::In) => (Type::Bool, BinaryOp::In),
+            (TypeKind::Integer, ast::BinaryOp::Add) => (Type::Int, BinaryOp::Add),
+            (TypeKind::Integer, ast::BinaryOp::Sub) => (Type::Int, BinaryOp::Sub),
+            (TypeKind::Integer, ast::BinaryOp::Mul) => (Type::Int, BinaryOp::Mul),
+            (TypeKind::Integer, ast::BinaryOp::IntDiv) => (Type::Int, BinaryOp::Div),
+            (TypeKind::Integer, ast::BinaryOp::Mod) => (Type::Int, BinaryOp::Mod),
+            (TypeKind::Real, ast::BinaryOp::Add) => (Type::Real, BinaryOp::FAdd),
+            (TypeKind::Real, ast::BinaryOp::Sub) => (Type::Real, BinaryOp::FSub),
+            (TypeKind::Real, ast::BinaryOp::Mul) => (Type::Real, BinaryOp::FMul),
+            (TypeKind::Real, ast::BinaryOp::Div) => (Type::Real, BinaryOp::FDiv),
+            (TypeKind::Set, ast::BinaryOp::Add) => (Type::Set, BinaryOp::Union),
+            (TypeKind::Set, ast::BinaryOp::Sub) => (Type::Set, BinaryOp::Difference),
+            (TypeKind::Set, ast::BinaryOp::Mul) => (Type::Set, BinaryOp::Intersection),
+            (TypeKind::Set, ast::BinaryOp::Div) => (Type::Set, BinaryOp::SymmetricDifference),
+            _ => return Err(format!("Operator {:?} für {}", op, self.types.display(operand_type))),
+        };
+        Ok(self.value(ty, InstKind::Binary { op, left: a, right: b }))
+    }
+
+    // & und OR werten den rechten Operanden nur bei Bedarf aus; das Ergebnis
+    // läuft über eine Hilfsvariable, aus der die SSA-Form einen Phi-Knoten macht
+    fn short_circuit(&mut self, left: &Expr, op: &ast::BinaryOp, right: &Expr) -> Result<Value, String> {
+        let index = self.function.slots.len();
+        self.function.slots.push(Slot { name: None, ty: TypeId::BOOLEAN, by_reference: false });
+        let depth = self.function.depth;
+
+        let value = self.expression(left)?;
+        let address = self.value(Type::Ptr, InstKind::Slot { depth, index });
+        self.effect(InstKind::Store { ty: Type::Bool, address, value });
+        let (rest, end) = (self.new_block(), self.new_block());
+        let (then_block, else_block) = if *op == ast::BinaryOp::And { (rest, end) } else { (end, rest) };
+        self.terminate(Terminator::Branch { condition: value, then_block, else_block });
+
+        self.switch_to(rest);
+        let value = self.expression(right)?;
+        let address = self.value(Type::Ptr, InstKind::Slot { depth, index });
+        self.effect(InstKind::Store { ty: Type::Bool, address, value });
+        self.terminate(Terminator::Jump(end));
+
+        self.switch_to(end);
+        let address = self.value(Type::Ptr, InstKind::Slot { depth, index });
+        Ok(self.value(Type::Bool, InstKind::Load { ty: Type::Bool, address }))
+    }
+
+    fn builtin_function(&mut self, builtin: Builtin, arguments: &[Expr]) -> Result<Value, String> {
+        // LEN ist statisch bekannt
+        if builtin == Builtin::Len {
+            let length = match arguments.first().map(|argument| (&argument.kind, self.types.kind(argument.ty))) {
+                Some((_, TypeKind::Array { length, .. })) => *length,
+                Some((ExprKind::Literal(ConstValue::String(text)), _)) => text.chars().count(),
+                _ => return Err("LEN erwartet ein Array".to_string()),
+            };
+            return Ok(self.constant(Constant::Int(length as i64)));
+        }
+
+        let argument = arguments.first().ok_or_else(|| format!("{} ohne Argument", builtin.name()))?;
+        let target = if builtin == Builtin::Ord && string_literal(argument).is_some() { TypeId::CHAR } else { argument.ty };
+        let value = self.value_for(argument, target)?;
+
+        if matches!(builtin, Builtin::Lsl | Builtin::Asr | Builtin::Ror) {
+            let shift = arguments.get(1).ok_or_else(|| format!("{} ohne Schiebeweite", builtin.name()))?;
+            let shift = self.expression(shift)?;
+            let op = match builtin {
+                Builtin::Lsl => BinaryOp::Lsl,
+                Builtin::Asr => BinaryOp::Asr,
+                _ => BinaryOp::Ror,
+            };
+            return Ok(self.value(Type::Int, InstKind::Binary { op, left: value, right: shift }));
+        }
+
+        let (ty, op) = match builtin {
+            Builtin::Abs if argument.ty == TypeId::INTEGER => (Type::Int, UnaryOp::Abs),
+            Builtin::Abs => (Type::Real, UnaryOp::FAbs),
+            Builtin::Odd => (Type::Bool, UnaryOp::Odd),
+            Builtin::Floor => (Type::Int, UnaryOp::Floor),
+            Builtin::Flt => (Type::Real, UnaryOp::IntToReal),
+            Builtin::Ord => (Type::Int, UnaryOp::Ord),
+            Builtin::Chr => (Type::Char, UnaryOp::Chr),
+            _ => return Err(format!("{} ist keine Funktionsprozedur", builtin.name())),
+        };
+        Ok(self.value(ty, InstKind::Unary { op, operand: value }))
+    }
+}
+
+impl Default for Lowering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn empty_function() -> Function {
+    Function {
+        name: String::new(),
+        exported: false,
+        depth: 0,
+        params: Vec::new(),
+        result: None,
+        slots: Vec::new(),
+        blocks: Vec::new(),
+        values: Vec::new(),
+        line: 0,
+        end_line: 0,
+    }
+}
+
+fn compare_op(op: &ast::BinaryOp) -> Option<CompareOp> {
+    Some(match op {
+        ast::BinaryOp::Equal => CompareOp::Eq,
+        ast::BinaryOp::NotEqual => CompareOp::Ne,
+        ast::BinaryOp::Less => CompareOp::Lt,
+        ast::BinaryOp::LessEqual => CompareOp::Le,
+        ast::BinaryOp::Greater => CompareOp::Gt,
+        ast::BinaryOp::GreaterEqual => CompareOp::Ge,
+        _ => return None,
+    })
+}
+
+fn qualify(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+// Text eines String-Literals bzw. einer String-Konstanten
+fn string_literal(expr: &Expr) -> Option<&str> {
+    match &expr.kind {
+        ExprKind::Literal(ConstValue::String(text)) => Some(text),
+        ExprKind::Designator(Designator { symbol: SymbolRef { kind: SymbolRefKind::Constant(ConstValue::String(text)), .. }, .. }) => {
+            Some(text)
+        }
+        _ => None,
+    }
+}
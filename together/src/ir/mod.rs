@@ -0,0 +1,903 @@
+pub mod lower;
+pub mod opt;
+pub mod ssa;
+pub mod verify;
+
+use crate::parser::ast::Span;
+use crate::semantic::hir;
+use crate::semantic::library::LibraryProcedure;
+use crate::semantic::{TypeArena, TypeId, TypeKind};
+use std::fmt;
+
+pub use lower::Lowering;
+pub use verify::verify;
+
+// ============================================================================
+// Zwischendarstellung (IR)
+// ============================================================================
+//
+// Backend-unabhängige Darstellung zwischen HIR und Code-Generatoren: jede
+// Prozedur (und der Modulrumpf) ist ein Kontrollflussgraph aus Basisblöcken
+// mit Drei-Adress-Befehlen in SSA-Form. Werte sind einfache Typen oder
+// Adressen; Arrays und Records liegen immer im Speicher (globale Variablen,
+// Slots im Rahmen einer Prozedur, Heap) und werden über typisierte
+// Adressrechnung (Field, Element) erreicht.
+//
+// Laufzeitprüfungen sind eigene Befehle (CheckIndex, CheckNil, Assert) oder
+// Teil der Semantik eines Befehls (Überlauf bei Add, Division durch 0, ...),
+// damit Optimierungen sie an einer Stelle sehen und entfernen können.
+//
+// Slots sind beim Eintritt in die Prozedur mit 0 belegt. ssa::construct
+// ersetzt Slots einfacher Typen, deren Adresse nicht entkommt, durch
+// SSA-Werte mit Phi-Knoten.
+
+// Einzige Einstiegsstelle: HIR -> IR in SSA-Form
+pub fn build(module: &hir::Module) -> Result<Program, String> {
+    let mut program = Lowering::new().lower(module)?;
+    ssa::construct(&mut program);
+    Ok(program)
+}
+
+// ============================================================================
+// Werte und Typen
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Value(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+impl Value {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl BlockId {
+    pub const ENTRY: BlockId = BlockId(0);
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// Typ eines SSA-Werts; Zeiger, Prozedurwerte und Adressen sind Ptr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    Int,
+    Real,
+    Bool,
+    Char,
+    Set,
+    Ptr,
+}
+
+impl Type {
+    // Werttyp eines Oberon-Typs; None für Arrays und Records
+    pub fn of(types: &TypeArena, ty: TypeId) -> Option<Type> {
+        match types.kind(ty) {
+            TypeKind::Integer => Some(Type::Int),
+            TypeKind::Real => Some(Type::Real),
+            TypeKind::Boolean => Some(Type::Bool),
+            TypeKind::Char => Some(Type::Char),
+            TypeKind::Set => Some(Type::Set),
+            TypeKind::String | TypeKind::Nil | TypeKind::Pointer { .. } | TypeKind::Procedure { .. } => Some(Type::Ptr),
+            TypeKind::Array { .. } | TypeKind::Record { .. } | TypeKind::Imported(_) | TypeKind::Forward(_) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Real => "real",
+            Type::Bool => "bool",
+            Type::Char => "char",
+            Type::Set => "set",
+            Type::Ptr => "ptr",
+        }
+    }
+}
+
+// ============================================================================
+// Programm und Funktionen
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub name: String,
+    pub types: TypeArena,
+    pub globals: Vec<Global>,
+    // Prozeduren in Deklarationsreihenfolge, geschachtelte vor ihrer
+    // umgebenden; der Modulrumpf (depth 0) zuletzt
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Global {
+    // Wie SymbolRef::qualified_name auf Modulebene
+    pub name: String,
+    pub ty: TypeId,
+    pub exported: bool,
+    // Zeile der Deklaration
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    // "P" bzw. "P.Q" für geschachtelte Prozeduren, der Modulname für den Rumpf
+    pub name: String,
+    pub exported: bool,
+    // 0 = Modulrumpf, 1 = Prozedur auf Modulebene, ...
+    pub depth: usize,
+    pub params: Vec<Param>,
+    pub result: Option<Type>,
+    pub slots: Vec<Slot>,
+    pub blocks: Vec<Block>,
+    // Typ jedes Werts, Index = Value
+    pub values: Vec<Type>,
+    // Zeilen von Prozedurname und END, beim Modulrumpf erste und letzte
+    // Anweisung
+    pub line: usize,
+    pub end_line: usize,
+}
+
+// Parameter einfacher Typen kommen als Wert, VAR-Parameter und strukturierte
+// Wertparameter als Adresse
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub ty: TypeId,
+    pub is_var: bool,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct Slot {
+    // None für Hilfsvariablen der Übersetzung
+    pub name: Option<String>,
+    pub ty: TypeId,
+    // Der Slot hält die Adresse einer Variablen vom Typ ty (VAR-Parameter)
+    pub by_reference: bool,
+}
+
+impl Slot {
+    // Typ des Inhalts, falls er in einen SSA-Wert passt
+    pub fn value_type(&self, types: &TypeArena) -> Option<Type> {
+        if self.by_reference {
+            Some(Type::Ptr)
+        } else {
+            Type::of(types, self.ty)
+        }
+    }
+}
+
+impl Function {
+    pub fn new_value(&mut self, ty: Type) -> Value {
+        self.values.push(ty);
+        Value(self.values.len() as u32 - 1)
+    }
+
+    pub fn value_type(&self, value: Value) -> Type {
+        self.values[value.index()]
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.index()]
+    }
+
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len() as u32).map(BlockId)
+    }
+
+    // Vorgänger jedes Blocks ohne Duplikate
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for id in self.block_ids() {
+            for successor in self.block(id).terminator.successors() {
+                let list: &mut Vec<BlockId> = &mut predecessors[successor.index()];
+                if !list.contains(&id) {
+                    list.push(id);
+                }
+            }
+        }
+        predecessors
+    }
+
+    // Name der umgebenden Prozedur auf Tiefe `depth` ("P.Q.R", 1 -> "P")
+    pub fn ancestor(&self, depth: usize) -> String {
+        ancestor(&self.name, depth)
+    }
+}
+
+fn ancestor(name: &str, depth: usize) -> String {
+    name.split('.').take(depth).collect::<Vec<_>>().join(".")
+}
+
+// ============================================================================
+// Blöcke und Befehle
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub phis: Vec<Phi>,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+// Ein Argument je Vorgängerblock
+#[derive(Debug, Clone)]
+pub struct Phi {
+    pub dest: Value,
+    pub arguments: Vec<(BlockId, Value)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub dest: Option<Value>,
+    pub kind: InstKind,
+    // Quelltextzeile für Laufzeitfehler
+    pub line: usize,
+    // Anweisung, aus der die Instruktion stammt (Source-Maps); 0 für unbekannt
+    pub span: Span,
+    // Prozedur, deren Rumpf hier eingesetzt wurde (opt::inline); Laufzeitfehler
+    // nennen sie statt der umgebenden Funktion
+    pub origin: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+    Char(u8),
+    Set(u32),
+    Nil,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstKind {
+    Const(Constant),
+    // Adresse einer mit 0X abgeschlossenen String-Konstanten
+    String(Vec<u8>),
+    // Adressen von Variablen; importierte heißen "Modul.Name"
+    Global(String),
+    // Slot `index` der Funktion auf Tiefe `depth` (eigene oder umgebende)
+    Slot { depth: usize, index: usize },
+    // Prozedurwert (nur Prozeduren auf Modulebene, importierte als "Modul.Name")
+    Procedure(String),
+    // Feld `field` (Position in TypeArena::fields) eines Records
+    Field { base: Value, record: TypeId, field: usize },
+    // Element eines Arrays ohne Indexprüfung
+    Element { base: Value, array: TypeId, index: Value },
+
+    Load { ty: Type, address: Value },
+    Store { ty: Type, address: Value, value: Value },
+    // Arrays vollständig, Records ohne Deskriptor und nur die Felder von ty
+    Copy { ty: TypeId, dest: Value, source: Value },
+    // String samt 0X, höchstens `length` Bytes
+    CopyString { dest: Value, source: Value, length: usize },
+    // Typdeskriptoren aller Records in einer Variablen vom Typ ty setzen
+    InitDescriptors { ty: TypeId, address: Value },
+    // Neues, mit 0 gefülltes Record samt Deskriptoren
+    New { record: TypeId },
+
+    Unary { op: UnaryOp, operand: Value },
+    Binary { op: BinaryOp, left: Value, right: Value },
+    Compare { op: CompareOp, left: Value, right: Value },
+    // Vergleich zweier Zeichenketten bis zum ersten 0X bzw. ihrer Länge
+    CompareStrings { op: CompareOp, left: Value, left_length: usize, right: Value, right_length: usize },
+    // Dynamischer Typ des Records an `address` ist eine Erweiterung von `record`
+    TypeTest { address: Value, record: TypeId },
+
+    // Indirekte Aufrufe brechen bei NIL ab
+    Call { callee: Callee, arguments: Vec<Value> },
+    // Out.String erhält Adresse und Länge (Const) der Zeichenkette
+    Output { procedure: LibraryProcedure, arguments: Vec<Value> },
+
+    // Laufzeitprüfungen
+    CheckIndex { index: Value, length: usize },
+    CheckNil(Value),
+    Assert(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callee {
+    // Geschachtelte Prozeduren (depth > 1) erhalten den Rahmen der
+    // umgebenden Prozedur als statischen Verweis. Auf Tiefe 1 bezeichnet
+    // ein qualifizierter Name ("Modul.Name") eine importierte Prozedur.
+    Direct { name: String, depth: usize },
+    Indirect(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    // INTEGER, mit Überlaufprüfung
+    Neg,
+    Abs,
+    // REAL
+    FNeg,
+    FAbs,
+    // BOOLEAN
+    Not,
+    // SET
+    Complement,
+    Odd,
+    IntToReal,
+    // Überlauf, wenn das Ergebnis nicht in INTEGER passt
+    Floor,
+    // CHAR, BOOLEAN oder SET -> INTEGER
+    Ord,
+    // Prüft 0..255
+    Chr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    // INTEGER mit Überlaufprüfung; Inc, Dec und For unterscheiden sich von
+    // Add und Sub nur in der Meldung
+    Add,
+    Sub,
+    Mul,
+    Inc,
+    Dec,
+    For,
+    // Abrunden zur negativen Unendlichkeit, Division durch 0 bricht ab
+    Div,
+    Mod,
+    FAdd,
+    FSub,
+    FMul,
+    FDiv,
+    Union,
+    Difference,
+    Intersection,
+    SymmetricDifference,
+    // Element IN Menge, INTEGER x SET -> BOOLEAN
+    In,
+    // Menge der Elemente left..right, beide müssen in 0..31 liegen
+    Range,
+    // Schiebeweite muss in 0..63 liegen, Lsl prüft zusätzlich auf Überlauf
+    Lsl,
+    Asr,
+    Ror,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl UnaryOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "neg",
+            UnaryOp::Abs => "abs",
+            UnaryOp::FNeg => "fneg",
+            UnaryOp::FAbs => "fabs",
+            UnaryOp::Not => "not",
+            UnaryOp::Complement => "complement",
+            UnaryOp::Odd => "odd",
+            UnaryOp::IntToReal => "flt",
+            UnaryOp::Floor => "floor",
+            UnaryOp::Ord => "ord",
+            UnaryOp::Chr => "chr",
+        }
+    }
+
+    // Operator in der Meldung "Überlauf bei '...'"
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Abs => "ABS",
+            UnaryOp::Floor => "FLOOR",
+            UnaryOp::Chr => "CHR",
+            _ => self.name(),
+        }
+    }
+
+    // Kann zur Laufzeit abbrechen
+    pub fn can_trap(self) -> bool {
+        matches!(self, UnaryOp::Neg | UnaryOp::Abs | UnaryOp::Floor | UnaryOp::Chr)
+    }
+}
+
+impl BinaryOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Inc => "inc",
+            BinaryOp::Dec => "dec",
+            BinaryOp::For => "for",
+            BinaryOp::Div => "div",
+            BinaryOp::Mod => "mod",
+            BinaryOp::FAdd => "fadd",
+            BinaryOp::FSub => "fsub",
+            BinaryOp::FMul => "fmul",
+            BinaryOp::FDiv => "fdiv",
+            BinaryOp::Union => "union",
+            BinaryOp::Difference => "diff",
+            BinaryOp::Intersection => "intersect",
+            BinaryOp::SymmetricDifference => "symdiff",
+            BinaryOp::In => "in",
+            BinaryOp::Range => "range",
+            BinaryOp::Lsl => "lsl",
+            BinaryOp::Asr => "asr",
+            BinaryOp::Ror => "ror",
+        }
+    }
+
+    // Operator in den Meldungen der Laufzeitfehler
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Inc => "INC",
+            BinaryOp::Dec => "DEC",
+            BinaryOp::For => "FOR",
+            BinaryOp::Div => "DIV",
+            BinaryOp::Mod => "MOD",
+            BinaryOp::Lsl => "LSL",
+            BinaryOp::Asr => "ASR",
+            BinaryOp::Ror => "ROR",
+            _ => self.name(),
+        }
+    }
+
+    // Subtraktion: Dec zieht ab, alle anderen Überlaufoperatoren addieren
+    // bzw. multiplizieren
+    pub fn is_subtraction(self) -> bool {
+        matches!(self, BinaryOp::Sub | BinaryOp::Dec)
+    }
+
+    pub fn is_checked_addition(self) -> bool {
+        matches!(self, BinaryOp::Add | BinaryOp::Inc | BinaryOp::For)
+    }
+
+    pub fn can_trap(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Add
+                | BinaryOp::Sub
+                | BinaryOp::Mul
+                | BinaryOp::Inc
+                | BinaryOp::Dec
+                | BinaryOp::For
+                | BinaryOp::Div
+                | BinaryOp::Mod
+                | BinaryOp::Range
+                | BinaryOp::Lsl
+                | BinaryOp::Asr
+                | BinaryOp::Ror
+        )
+    }
+
+    // Operanden dürfen vertauscht werden
+    pub fn is_commutative(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Add
+                | BinaryOp::Mul
+                | BinaryOp::Inc
+                | BinaryOp::For
+                | BinaryOp::FAdd
+                | BinaryOp::FMul
+                | BinaryOp::Union
+                | BinaryOp::Intersection
+                | BinaryOp::SymmetricDifference
+        )
+    }
+}
+
+impl CompareOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "eq",
+            CompareOp::Ne => "ne",
+            CompareOp::Lt => "lt",
+            CompareOp::Le => "le",
+            CompareOp::Gt => "gt",
+            CompareOp::Ge => "ge",
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+}
+
+impl InstKind {
+    // Alle gelesenen Werte in Reihenfolge
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            InstKind::Const(_)
+            | InstKind::String(_)
+            | InstKind::Global(_)
+            | InstKind::Slot { .. }
+            | InstKind::Procedure(_)
+            | InstKind::New { .. } => Vec::new(),
+            InstKind::Field { base, .. } => vec![*base],
+            InstKind::Element { base, index, .. } => vec![*base, *index],
+            InstKind::Load { address, .. } => vec![*address],
+            InstKind::Store { address, value, .. } => vec![*address, *value],
+            InstKind::Copy { dest, source, .. } | InstKind::CopyString { dest, source, .. } => vec![*dest, *source],
+            InstKind::InitDescriptors { address, .. } | InstKind::TypeTest { address, .. } => vec![*address],
+            InstKind::Unary { operand, .. } => vec![*operand],
+            InstKind::Binary { left, right, .. }
+            | InstKind::Compare { left, right, .. }
+            | InstKind::CompareStrings { left, right, .. } => vec![*left, *right],
+            InstKind::Call { callee, arguments } => {
+                let mut operands = match callee {
+                    Callee::Indirect(target) => vec![*target],
+                    Callee::Direct { .. } => Vec::new(),
+                };
+                operands.extend(arguments);
+                operands
+            }
+            InstKind::Output { arguments, .. } => arguments.clone(),
+            InstKind::CheckIndex { index, .. } => vec![*index],
+            InstKind::CheckNil(value) | InstKind::Assert(value) => vec![*value],
+        }
+    }
+
+    // Ersetzt jeden gelesenen Wert
+    pub fn map_operands(&mut self, mut f: impl FnMut(Value) -> Value) {
+        match self {
+            InstKind::Const(_)
+            | InstKind::String(_)
+            | InstKind::Global(_)
+            | InstKind::Slot { .. }
+            | InstKind::Procedure(_)
+            | InstKind::New { .. } => {}
+            InstKind::Field { base, .. } => *base = f(*base),
+            InstKind::Element { base, index, .. } => {
+                *base = f(*base);
+                *index = f(*index);
+            }
+            InstKind::Load { address, .. } => *address = f(*address),
+            InstKind::Store { address, value, .. } => {
+                *address = f(*address);
+                *value = f(*value);
+            }
+            InstKind::Copy { dest, source, .. } | InstKind::CopyString { dest, source, .. } => {
+                *dest = f(*dest);
+                *source = f(*source);
+            }
+            InstKind::InitDescriptors { address, .. } | InstKind::TypeTest { address, .. } => *address = f(*address),
+            InstKind::Unary { operand, .. } => *operand = f(*operand),
+            InstKind::Binary { left, right, .. }
+            | InstKind::Compare { left, right, .. }
+            | InstKind::CompareStrings { left, right, .. } => {
+                *left = f(*left);
+                *right = f(*right);
+            }
+            InstKind::Call { callee, arguments } => {
+                if let Callee::Indirect(target) = callee {
+                    *target = f(*target);
+                }
+                for argument in arguments {
+                    *argument = f(*argument);
+                }
+            }
+            InstKind::Output { arguments, .. } => {
+                for argument in arguments {
+                    *argument = f(*argument);
+                }
+            }
+            InstKind::CheckIndex { index, .. } => *index = f(*index),
+            InstKind::CheckNil(value) | InstKind::Assert(value) => *value = f(*value),
+        }
+    }
+}
+
+// ============================================================================
+// Blockabschlüsse
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch { condition: Value, then_block: BlockId, else_block: BlockId },
+    // CASE: Bereiche start..end (einschließlich) in Reihenfolge der Labels
+    Switch { value: Value, cases: Vec<(i64, i64, BlockId)>, default: BlockId },
+    Return(Option<Value>),
+    // Kein CASE-Label passt zum Wert
+    CaseTrap { value: Value, line: usize },
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch { then_block, else_block, .. } => vec![*then_block, *else_block],
+            Terminator::Switch { cases, default, .. } => {
+                let mut successors: Vec<BlockId> = cases.iter().map(|&(_, _, target)| target).collect();
+                successors.push(*default);
+                successors
+            }
+            Terminator::Return(_) | Terminator::CaseTrap { .. } => Vec::new(),
+        }
+    }
+
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Terminator::Jump(_) | Terminator::Return(None) => Vec::new(),
+            Terminator::Branch { condition, .. } => vec![*condition],
+            Terminator::Switch { value, .. } | Terminator::CaseTrap { value, .. } | Terminator::Return(Some(value)) => {
+                vec![*value]
+            }
+        }
+    }
+
+    pub fn map_operands(&mut self, mut f: impl FnMut(Value) -> Value) {
+        match self {
+            Terminator::Jump(_) | Terminator::Return(None) => {}
+            Terminator::Branch { condition, .. } => *condition = f(*condition),
+            Terminator::Switch { value, .. } | Terminator::CaseTrap { value, .. } | Terminator::Return(Some(value)) => {
+                *value = f(*value)
+            }
+        }
+    }
+
+    pub fn map_successors(&mut self, mut f: impl FnMut(BlockId) -> BlockId) {
+        match self {
+            Terminator::Jump(target) => *target = f(*target),
+            Terminator::Branch { then_block, else_block, .. } => {
+                *then_block = f(*then_block);
+                *else_block = f(*else_block);
+            }
+            Terminator::Switch { cases, default, .. } => {
+                for case in cases {
+                    case.2 = f(case.2);
+                }
+                *default = f(*default);
+            }
+            Terminator::Return(_) | Terminator::CaseTrap { .. } => {}
+        }
+    }
+}
+
+// ============================================================================
+// Textausgabe
+// ============================================================================
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "b{}", self.0)
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constant::Int(value) => write!(f, "{}", value),
+            Constant::Real(value) => write!(f, "{:?}", value),
+            Constant::Bool(value) => write!(f, "{}", value),
+            Constant::Char(value) => write!(f, "{:X}X", value),
+            Constant::Set(bits) => write!(f, "{{0x{:X}}}", bits),
+            Constant::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "module {}", self.name)?;
+        for global in &self.globals {
+            writeln!(f, "global {}: {}", global.name, self.types.display(global.ty))?;
+        }
+        for function in &self.functions {
+            writeln!(f)?;
+            function.write(f, &self.types)?;
+        }
+        Ok(())
+    }
+}
+
+impl Function {
+    fn write(&self, f: &mut fmt::Formatter, types: &TypeArena) -> fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|param| {
+                let var = if param.is_var { "VAR " } else { "" };
+                format!("{}{} {}: {}", var, param.value, param.name, types.display(param.ty))
+            })
+            .collect();
+        write!(f, "function {}({})", self.name, params.join(", "))?;
+        if let Some(result) = self.result {
+            write!(f, ": {}", result.name())?;
+        }
+        writeln!(f)?;
+        for (index, slot) in self.slots.iter().enumerate() {
+            let reference = if slot.by_reference { "VAR " } else { "" };
+            let name = slot.name.as_deref().unwrap_or("_");
+            writeln!(f, "  slot {} {}: {}{}", index, name, reference, types.display(slot.ty))?;
+        }
+        for id in self.block_ids() {
+            let block = self.block(id);
+            writeln!(f, "{}:", id)?;
+            for phi in &block.phis {
+                let arguments: Vec<String> =
+                    phi.arguments.iter().map(|(block, value)| format!("[{}: {}]", block, value)).collect();
+                writeln!(f, "  {} = phi {} {}", phi.dest, self.value_type(phi.dest).name(), arguments.join(", "))?;
+            }
+            for instruction in &block.instructions {
+                write!(f, "  ")?;
+                if let Some(dest) = instruction.dest {
+                    write!(f, "{} = ", dest)?;
+                }
+                write!(f, "{}", self.describe(&instruction.kind, types))?;
+                match &instruction.origin {
+                    Some(origin) => writeln!(f, "  ; aus {}", origin)?,
+                    None => writeln!(f)?,
+                }
+            }
+            writeln!(f, "  {}", describe_terminator(&block.terminator))?;
+        }
+        Ok(())
+    }
+
+    fn describe(&self, kind: &InstKind, types: &TypeArena) -> String {
+        let list = |values: &[Value]| values.iter().map(Value::to_string).collect::<Vec<_>>().join(", ");
+        match kind {
+            InstKind::Const(constant) => format!("const {}", constant),
+            InstKind::String(text) => format!("string {:?}", String::from_utf8_lossy(text)),
+            InstKind::Global(name) => format!("global {}", name),
+            InstKind::Slot { depth, index } => format!("slot {}.{}", depth, index),
+            InstKind::Procedure(name) => format!("procedure {}", name),
+            InstKind::Field { base, record, field } => {
+                let name = types.fields(*record).get(*field).map(|field| field.name.clone()).unwrap_or_default();
+                format!("field {}, {}.{}", base, types.display(*record), name)
+            }
+            InstKind::Element { base, array, index } => format!("element {}, {}, {}", base, types.display(*array), index),
+            InstKind::Load { ty, address } => format!("load {} {}", ty.name(), address),
+            InstKind::Store { ty, address, value } => format!("store {} {}, {}", ty.name(), address, value),
+            InstKind::Copy { ty, dest, source } => format!("copy {} {}, {}", types.display(*ty), dest, source),
+            InstKind::CopyString { dest, source, length } => format!("copystring {}, {}, {}", dest, source, length),
+            InstKind::InitDescriptors { ty, address } => format!("init {} {}", types.display(*ty), address),
+            InstKind::New { record } => format!("new {}", types.display(*record)),
+            InstKind::Unary { op, operand } => format!("{} {}", op.name(), operand),
+            InstKind::Binary { op, left, right } => format!("{} {}, {}", op.name(), left, right),
+            InstKind::Compare { op, left, right } => format!("{} {}, {}", op.name(), left, right),
+            InstKind::CompareStrings { op, left, left_length, right, right_length } => {
+                format!("strcmp.{} {}[{}], {}[{}]", op.name(), left, left_length, right, right_length)
+            }
+            InstKind::TypeTest { address, record } => format!("is {}, {}", address, types.display(*record)),
+            InstKind::Call { callee: Callee::Direct { name, .. }, arguments } => format!("call {}({})", name, list(arguments)),
+            InstKind::Call { callee: Callee::Indirect(target), arguments } => {
+                format!("call {}({})", target, list(arguments))
+            }
+            InstKind::Output { procedure, arguments } => format!("call {}({})", procedure, list(arguments)),
+            InstKind::CheckIndex { index, length } => format!("checkindex {}, {}", index, length),
+            InstKind::CheckNil(value) => format!("checknil {}", value),
+            InstKind::Assert(value) => format!("assert {}", value),
+        }
+    }
+}
+
+fn describe_terminator(terminator: &Terminator) -> String {
+    match terminator {
+        Terminator::Jump(target) => format!("jump {}", target),
+        Terminator::Branch { condition, then_block, else_block } => {
+            format!("branch {}, {}, {}", condition, then_block, else_block)
+        }
+        Terminator::Switch { value, cases, default } => {
+            let cases: Vec<String> = cases
+                .iter()
+                .map(|&(start, end, target)| {
+                    if start == end {
+                        format!("{}: {}", start, target)
+                    } else {
+                        format!("{}..{}: {}", start, end, target)
+                    }
+                })
+                .collect();
+            format!("switch {} [{}], {}", value, cases.join(", "), default)
+        }
+        Terminator::Return(Some(value)) => format!("return {}", value),
+        Terminator::Return(None) => "return".to_string(),
+        Terminator::CaseTrap { value, .. } => format!("casetrap {}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::checked;
+
+    pub(crate) fn compile(source: &str) -> Program {
+        let program = build(&checked(source)).unwrap();
+        verify(&program).unwrap();
+        program
+    }
+
+    #[test]
+    fn test_ssa() {
+        let program = compile("MODULE T;
+            VAR g: INTEGER;
+            PROCEDURE Max(a, b: INTEGER): INTEGER;
+              VAR m: INTEGER;
+            BEGIN IF a > b THEN m := a ELSE m := b END
+              RETURN m
+            END Max;
+            PROCEDURE Sum(n: INTEGER): INTEGER;
+              VAR i, s: INTEGER;
+            BEGIN s := 0; FOR i := 1 TO n DO s := s + i END
+              RETURN s
+            END Sum;
+            BEGIN g := Max(Sum(3), 2)
+            END T.");
+
+        // Lokale Variablen ohne Adresse verschwinden, an Zusammenflüssen
+        // entstehen Phi-Knoten
+        let max = program.function("Max").unwrap();
+        let text = program.to_string();
+        assert!(max.blocks.iter().all(|block| block
+            .instructions
+            .iter()
+            .all(|instruction| !matches!(instruction.kind, InstKind::Slot { .. }))));
+        assert_eq!(max.blocks.iter().map(|block| block.phis.len()).sum::<usize>(), 1);
+        let sum = program.function("Sum").unwrap();
+        assert_eq!(sum.blocks.iter().map(|block| block.phis.len()).sum::<usize>(), 2);
+        assert!(text.contains("function Max(v0 a: INTEGER, v1 b: INTEGER): int\n"), "{}", text);
+        assert!(text.contains(" = phi int ["), "{}", text);
+        assert!(text.contains("store int v"), "{}", text);
+        assert!(text.contains("global g: INTEGER\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_memory() {
+        let program = compile("MODULE T;
+            TYPE P = POINTER TO R; R = RECORD x: INTEGER; a: ARRAY 4 OF INTEGER END;
+            PROCEDURE Get(p: P; i: INTEGER): INTEGER;
+            BEGIN RETURN p^.a[i]
+            END Get;
+            PROCEDURE Outer(k: INTEGER): INTEGER;
+              VAR acc: INTEGER;
+              PROCEDURE Add(j: INTEGER);
+              BEGIN acc := acc + j * k
+              END Add;
+            BEGIN acc := 0; Add(1); Add(2) RETURN acc
+            END Outer;
+            END T.");
+
+        let text = program.to_string();
+        assert!(text.contains("checknil v0\n"), "{}", text);
+        assert!(text.contains("checkindex v1, 4\n"), "{}", text);
+
+        // Von Add benutzte Variablen bleiben im Rahmen von Outer
+        let outer = program.function("Outer").unwrap();
+        assert_eq!(outer.slots.len(), 2);
+        assert!(text.contains("function Outer.Add(v0 j: INTEGER)\n"), "{}", text);
+        assert!(text.contains("slot 1.0\n"), "{}", text);
+        assert!(text.contains("call Outer.Add("), "{}", text);
+    }
+}
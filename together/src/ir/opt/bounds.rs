@@ -0,0 +1,239 @@
+use super::{remark, Remark};
+use crate::ir::ssa::DominatorTree;
+use crate::ir::{BinaryOp, BlockId, CompareOp, Constant, Function, InstKind, Terminator, Type, UnaryOp, Value};
+
+// ============================================================================
+// Entfernen von Indexprüfungen
+// ============================================================================
+//
+// Einfache Wertebereichsanalyse für INTEGER-Werte: Konstanten, Addition und
+// Subtraktion einer Konstanten, MOD durch eine positive Konstante, ORD und
+// monotone Schleifenvariablen (Phi-Knoten, deren übrige Argumente den Wert
+// nur um eine Konstante erhöhen bzw. verringern). Bedingungen dominierender
+// Verzweigungen der Form `v < k` schränken den Bereich weiter ein. Liegt der
+// Index einer CheckIndex-Prüfung sicher in 0..length-1, entfällt sie.
+
+type Range = (i64, i64);
+
+const FULL: Range = (i64::MIN, i64::MAX);
+
+// Schranke für die Rekursion über Definitionen
+const DEPTH: usize = 8;
+
+pub fn run(function: &mut Function, remarks: &mut Vec<Remark>) {
+    let tree = DominatorTree::new(function);
+    let analysis = Analysis::new(function, &tree);
+
+    let mut removed = Vec::new();
+    for id in function.block_ids() {
+        for (index, instruction) in function.block(id).instructions.iter().enumerate() {
+            let InstKind::CheckIndex { index: value, length } = instruction.kind else { continue };
+            let (low, high) = analysis.range(value, id, DEPTH);
+            if low >= 0 && high < length as i64 {
+                removed.push((id, index));
+                let message = format!("Indexprüfung entfällt, der Index liegt in {}..{}", low, high);
+                remark(remarks, "bounds", function, instruction.line, message);
+            }
+        }
+    }
+    for &(id, index) in removed.iter().rev() {
+        function.blocks[id.index()].instructions.remove(index);
+    }
+}
+
+#[derive(Clone)]
+enum Definition {
+    None,
+    Instruction(InstKind),
+    Phi(BlockId, Vec<(BlockId, Value)>),
+}
+
+struct Analysis<'a> {
+    function: &'a Function,
+    tree: &'a DominatorTree,
+    definitions: Vec<Definition>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(function: &'a Function, tree: &'a DominatorTree) -> Self {
+        let mut definitions = vec![Definition::None; function.values.len()];
+        for id in function.block_ids() {
+            let block = function.block(id);
+            for phi in &block.phis {
+                definitions[phi.dest.index()] = Definition::Phi(id, phi.arguments.clone());
+            }
+            for instruction in &block.instructions {
+                if let Some(dest) = instruction.dest {
+                    definitions[dest.index()] = Definition::Instruction(instruction.kind.clone());
+                }
+            }
+        }
+        Analysis { function, tree, definitions }
+    }
+
+    fn constant(&self, value: Value) -> Option<i64> {
+        match &self.definitions[value.index()] {
+            Definition::Instruction(InstKind::Const(Constant::Int(constant))) => Some(*constant),
+            _ => None,
+        }
+    }
+
+    // Bereich von `value` an einer Stelle in `block`
+    fn range(&self, value: Value, block: BlockId, depth: usize) -> Range {
+        if depth == 0 || self.function.value_type(value) != Type::Int {
+            return FULL;
+        }
+        let range = self.definition_range(value, block, depth - 1);
+        self.refine(value, block, range)
+    }
+
+    fn definition_range(&self, value: Value, block: BlockId, depth: usize) -> Range {
+        match &self.definitions[value.index()] {
+            Definition::None => FULL,
+            Definition::Instruction(kind) => match kind {
+                InstKind::Const(Constant::Int(constant)) => (*constant, *constant),
+                // Überläufe brechen ab, gesättigte Schranken sind deshalb sicher
+                InstKind::Binary { op, left, right } if op.is_checked_addition() || op.is_subtraction() => {
+                    let (other, step) = match (self.constant(*left), self.constant(*right)) {
+                        (_, Some(step)) => (*left, step),
+                        (Some(step), None) if op.is_checked_addition() => (*right, step),
+                        _ => return FULL,
+                    };
+                    let step = if op.is_subtraction() { step.checked_neg() } else { Some(step) };
+                    let Some(step) = step else { return FULL };
+                    let (low, high) = self.range(other, block, depth);
+                    (low.saturating_add(step), high.saturating_add(step))
+                }
+                InstKind::Binary { op: BinaryOp::Mod, right, .. } => match self.constant(*right) {
+                    Some(divisor) if divisor > 0 => (0, divisor - 1),
+                    _ => FULL,
+                },
+                InstKind::Unary { op: UnaryOp::Ord, operand } => match self.function.value_type(*operand) {
+                    Type::Char => (0, 255),
+                    Type::Bool => (0, 1),
+                    _ => (0, u32::MAX as i64),
+                },
+                _ => FULL,
+            },
+            Definition::Phi(header, arguments) => self.induction_range(value, *header, arguments, depth),
+        }
+    }
+
+    // Schleifenvariable: Argumente über Rückwärtskanten entstehen aus dem
+    // Phi-Knoten durch Addition einer Konstanten gleichen Vorzeichens, die
+    // übrigen bestimmen die Startwerte
+    fn induction_range(&self, phi: Value, header: BlockId, arguments: &[(BlockId, Value)], depth: usize) -> Range {
+        let mut direction = 0;
+        let mut start: Option<Range> = None;
+        for &(source, argument) in arguments {
+            if self.tree.dominates(header, source) {
+                // CSE ordnet die Operanden der Addition, die Konstante kann
+                // deshalb auch links stehen
+                let step = match &self.definitions[argument.index()] {
+                    Definition::Instruction(InstKind::Binary { op, left, right }) if *left == phi => {
+                        match (self.constant(*right), op.is_subtraction()) {
+                            (Some(step), true) => step.checked_neg(),
+                            (Some(step), false) if op.is_checked_addition() => Some(step),
+                            _ => None,
+                        }
+                    }
+                    Definition::Instruction(InstKind::Binary { op, left, right })
+                        if *right == phi && op.is_checked_addition() =>
+                    {
+                        self.constant(*left)
+                    }
+                    _ => None,
+                };
+                match step.map(i64::signum) {
+                    Some(sign) if sign != 0 && (direction == 0 || direction == sign) => direction = sign,
+                    _ => return FULL,
+                }
+            } else {
+                let (low, high) = self.range(argument, source, depth);
+                start = Some(match start {
+                    None => (low, high),
+                    Some((a, b)) => (a.min(low), b.max(high)),
+                });
+            }
+        }
+        match (start, direction) {
+            (Some((low, _)), 1) => (low, i64::MAX),
+            (Some((_, high)), -1) => (i64::MIN, high),
+            (Some(range), _) => range,
+            (None, _) => FULL,
+        }
+    }
+
+    // Bedingungen dominierender Verzweigungen, deren Ziel nur über diese
+    // Kante erreichbar ist
+    fn refine(&self, value: Value, block: BlockId, mut range: Range) -> Range {
+        let mut child = block;
+        while let Some(parent) = self.tree.idom(child) {
+            if let Terminator::Branch { condition, then_block, else_block } = self.function.block(parent).terminator {
+                let taken = if child == then_block && else_block != child {
+                    Some(true)
+                } else if child == else_block && then_block != child {
+                    Some(false)
+                } else {
+                    None
+                };
+                if let Some(taken) = taken.filter(|_| self.tree.predecessors(child) == [parent]) {
+                    range = self.apply(condition, taken, value, range);
+                }
+            }
+            child = parent;
+        }
+        range
+    }
+
+    fn apply(&self, condition: Value, taken: bool, value: Value, (low, high): Range) -> Range {
+        let Definition::Instruction(InstKind::Compare { op, left, right }) = &self.definitions[condition.index()] else {
+            return (low, high);
+        };
+        // Vergleich als `value op bound`
+        let (op, bound) = if *left == value {
+            match self.constant(*right) {
+                Some(bound) => (*op, bound),
+                None => return (low, high),
+            }
+        } else if *right == value {
+            match self.constant(*left) {
+                Some(bound) => (mirror(*op), bound),
+                None => return (low, high),
+            }
+        } else {
+            return (low, high);
+        };
+        let op = if taken { op } else { negate(op) };
+        match op {
+            CompareOp::Lt => (low, high.min(bound.saturating_sub(1))),
+            CompareOp::Le => (low, high.min(bound)),
+            CompareOp::Gt => (low.max(bound.saturating_add(1)), high),
+            CompareOp::Ge => (low.max(bound), high),
+            CompareOp::Eq => (low.max(bound), high.min(bound)),
+            CompareOp::Ne => (low, high),
+        }
+    }
+}
+
+// a op b  <=>  b mirror(op) a
+fn mirror(op: CompareOp) -> CompareOp {
+    match op {
+        CompareOp::Lt => CompareOp::Gt,
+        CompareOp::Le => CompareOp::Ge,
+        CompareOp::Gt => CompareOp::Lt,
+        CompareOp::Ge => CompareOp::Le,
+        other => other,
+    }
+}
+
+fn negate(op: CompareOp) -> CompareOp {
+    match op {
+        CompareOp::Eq => CompareOp::Ne,
+        CompareOp::Ne => CompareOp::Eq,
+        CompareOp::Lt => CompareOp::Ge,
+        CompareOp::Le => CompareOp::Gt,
+        CompareOp::Gt => CompareOp::Le,
+        CompareOp::Ge => CompareOp::Lt,
+    }
+}
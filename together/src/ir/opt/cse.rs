@@ -0,0 +1,135 @@
+use super::{is_pure, remark, replace_uses, Remark};
+use crate::ir::ssa::DominatorTree;
+use crate::ir::{BlockId, CompareOp, Function, InstKind, Type, Value};
+use std::collections::HashMap;
+
+// ============================================================================
+// Gemeinsame Teilausdrücke (CSE)
+// ============================================================================
+//
+// Wertnummerierung entlang des Dominatorbaums: ein Befehl, den ein gleicher
+// Befehl dominiert, entfällt zugunsten von dessen Ergebnis. Das gilt auch
+// für Befehle, die abbrechen können (Überlauf, CheckNil, CheckIndex): der
+// dominierende ist vorher ohne Fehler gelaufen. Operanden kommutativer
+// Operatoren werden vorher geordnet.
+//
+// Innerhalb eines Blocks entfallen außerdem wiederholte Loads derselben
+// Adresse, solange dazwischen nichts in den Speicher schreibt; ein Store
+// liefert den Wert für folgende Loads gleich mit.
+
+pub fn run(function: &mut Function, remarks: &mut Vec<Remark>) {
+    let tree = DominatorTree::new(function);
+    let mut numbering = Numbering { available: HashMap::new(), replacements: HashMap::new(), removed: Vec::new() };
+    numbering.visit(function, &tree, BlockId::ENTRY);
+
+    let Numbering { replacements, removed, .. } = numbering;
+    for (line, message) in removed {
+        remark(remarks, "cse", function, line, message);
+    }
+    replace_uses(function, &replacements);
+}
+
+struct Numbering {
+    // Schlüssel (Debug-Darstellung des Befehls, unterscheidet anders als ==
+    // auch 0.0 und -0.0) -> Ergebnis des dominierenden Befehls
+    available: HashMap<String, Option<Value>>,
+    replacements: HashMap<Value, Value>,
+    // Zeile und Meldung je entfernten Befehl
+    removed: Vec<(usize, String)>,
+}
+
+impl Numbering {
+    fn visit(&mut self, function: &mut Function, tree: &DominatorTree, block: BlockId) {
+        let mut added = Vec::new();
+        // Bekannte Speicherinhalte in diesem Block: (Adresse, Typ) -> Wert
+        let mut memory: HashMap<(Value, Type), Value> = HashMap::new();
+
+        let instructions = std::mem::take(&mut function.blocks[block.index()].instructions);
+        let mut kept = Vec::with_capacity(instructions.len());
+        for mut instruction in instructions {
+            let replacements = &self.replacements;
+            instruction.kind.map_operands(|value| *replacements.get(&value).unwrap_or(&value));
+            canonicalize(&mut instruction.kind);
+
+            match &instruction.kind {
+                InstKind::Load { ty, address } => {
+                    if let (Some(&known), Some(dest)) = (memory.get(&(*address, *ty)), instruction.dest) {
+                        self.replacements.insert(dest, known);
+                        self.removed.push((instruction.line, "Load wiederholt einen bekannten Wert".to_string()));
+                        continue;
+                    }
+                    if let Some(dest) = instruction.dest {
+                        memory.insert((*address, *ty), dest);
+                    }
+                }
+                InstKind::Store { ty, address, value } => {
+                    memory.clear();
+                    memory.insert((*address, *ty), *value);
+                }
+                InstKind::Copy { .. } | InstKind::CopyString { .. } | InstKind::InitDescriptors { .. } | InstKind::Call { .. } => {
+                    memory.clear();
+                }
+                _ => {}
+            }
+
+            if let Some(key) = key(&instruction.kind) {
+                match self.available.get(&key) {
+                    Some(&existing) => {
+                        if let (Some(dest), Some(existing)) = (instruction.dest, existing) {
+                            self.replacements.insert(dest, existing);
+                        }
+                        if !matches!(instruction.kind, InstKind::Const(_) | InstKind::Global(_) | InstKind::Slot { .. }) {
+                            self.removed.push((instruction.line, describe(&instruction.kind)));
+                        }
+                        continue;
+                    }
+                    None => {
+                        self.available.insert(key.clone(), instruction.dest);
+                        added.push(key);
+                    }
+                }
+            }
+            kept.push(instruction);
+        }
+        function.blocks[block.index()].instructions = kept;
+
+        for &child in tree.children(block) {
+            self.visit(function, tree, child);
+        }
+        for key in added {
+            self.available.remove(&key);
+        }
+    }
+}
+
+// Schlüssel für Befehle, deren Wiederholung überflüssig ist
+fn key(kind: &InstKind) -> Option<String> {
+    let eligible = is_pure(kind)
+        || matches!(
+            kind,
+            InstKind::Unary { .. } | InstKind::Binary { .. } | InstKind::CheckNil(_) | InstKind::CheckIndex { .. }
+        );
+    eligible.then(|| format!("{:?}", kind))
+}
+
+fn canonicalize(kind: &mut InstKind) {
+    let (left, right) = match kind {
+        InstKind::Binary { op, left, right } if op.is_commutative() => (left, right),
+        InstKind::Compare { op: CompareOp::Eq | CompareOp::Ne, left, right } => (left, right),
+        _ => return,
+    };
+    if *left > *right {
+        std::mem::swap(left, right);
+    }
+}
+
+fn describe(kind: &InstKind) -> String {
+    match kind {
+        InstKind::CheckNil(_) => "NIL-Prüfung wiederholt eine vorige".to_string(),
+        InstKind::CheckIndex { .. } => "Indexprüfung wiederholt eine vorige".to_string(),
+        InstKind::Unary { op, .. } => format!("'{}' wiederholt einen vorigen Ausdruck", op.symbol()),
+        InstKind::Binary { op, .. } => format!("'{}' wiederholt einen vorigen Ausdruck", op.symbol()),
+        InstKind::Compare { op, .. } => format!("'{}' wiederholt einen vorigen Vergleich", op.symbol()),
+        _ => "Adressrechnung wiederholt eine vorige".to_string(),
+    }
+}
@@ -0,0 +1,134 @@
+use super::{is_removable, remark, simplify_phis, use_counts, Remark};
+use crate::ir::ssa::remove_unreachable;
+use crate::ir::{BlockId, Function, Terminator};
+
+// ============================================================================
+// Toter Code und Kontrollfluss
+// ============================================================================
+//
+// Entfernt Befehle ohne Nebenwirkung, deren Ergebnis niemand benutzt, und
+// Phi-Knoten, die nur sich selbst speisen. Danach wird der Kontrollfluss
+// vereinfacht: Verzweigungen mit gleichen Zielen werden Sprünge, leere
+// Blöcke übersprungen und Blöcke mit genau einem Vorgänger an diesen
+// angehängt.
+
+pub fn run(function: &mut Function, remarks: &mut Vec<Remark>) {
+    let mut removed = 0;
+    let mut line = 0;
+    loop {
+        let counts = use_counts(function);
+        let mut changed = false;
+        for block in &mut function.blocks {
+            block.instructions.retain(|instruction| {
+                let dead = instruction.dest.is_some_and(|dest| counts[dest.index()] == 0) && is_removable(&instruction.kind);
+                if dead {
+                    removed += 1;
+                    if line == 0 {
+                        line = instruction.line;
+                    }
+                    changed = true;
+                }
+                !dead
+            });
+            block.phis.retain(|phi| {
+                let uses = counts[phi.dest.index()];
+                let own = phi.arguments.iter().filter(|&&(_, value)| value == phi.dest).count();
+                let dead = uses == own;
+                changed |= dead;
+                !dead
+            });
+        }
+        if !changed {
+            break;
+        }
+    }
+    if removed > 0 {
+        remark(remarks, "dce", function, line, format!("{} unbenutzte Befehle entfernt", removed));
+    }
+
+    simplify_cfg(function);
+}
+
+fn simplify_cfg(function: &mut Function) {
+    loop {
+        let mut changed = false;
+        for block in &mut function.blocks {
+            if let Terminator::Branch { then_block, else_block, .. } = block.terminator {
+                if then_block == else_block {
+                    block.terminator = Terminator::Jump(then_block);
+                    changed = true;
+                }
+            }
+        }
+        changed |= skip_empty_blocks(function);
+        changed |= merge_blocks(function);
+        if !changed {
+            break;
+        }
+        remove_unreachable(function);
+        simplify_phis(function);
+    }
+}
+
+// Leerer Block, der nur weiterspringt: Vorgänger springen direkt zum Ziel.
+// Phi-Knoten im Ziel erhalten das Argument des leeren Blocks je Vorgänger;
+// ist ein Vorgänger schon Vorgänger des Ziels, bleibt der Block stehen.
+fn skip_empty_blocks(function: &mut Function) -> bool {
+    let predecessors = function.predecessors();
+    for id in function.block_ids().skip(1) {
+        let block = function.block(id);
+        let Terminator::Jump(target) = block.terminator else { continue };
+        if target == id || !block.phis.is_empty() || !block.instructions.is_empty() {
+            continue;
+        }
+        let sources = &predecessors[id.index()];
+        if sources.is_empty() || sources.iter().any(|source| predecessors[target.index()].contains(source)) {
+            continue;
+        }
+        for &source in sources {
+            function.blocks[source.index()].terminator.map_successors(|successor| if successor == id { target } else { successor });
+        }
+        for phi in &mut function.blocks[target.index()].phis {
+            let Some(position) = phi.arguments.iter().position(|&(source, _)| source == id) else { continue };
+            let (_, value) = phi.arguments.remove(position);
+            phi.arguments.extend(sources.iter().map(|&source| (source, value)));
+        }
+        return true;
+    }
+    false
+}
+
+// Block mit genau einem Vorgänger, der nur zu ihm springt: anhängen
+fn merge_blocks(function: &mut Function) -> bool {
+    let predecessors = function.predecessors();
+    let mut changed = false;
+    for id in function.block_ids() {
+        let Terminator::Jump(target) = function.block(id).terminator else { continue };
+        if target == id || target == BlockId::ENTRY || predecessors[target.index()] != [id] {
+            continue;
+        }
+        // Phi-Knoten mit einem Argument wurden bereits durch simplify_phis
+        // ersetzt
+        if !function.block(target).phis.is_empty() {
+            continue;
+        }
+        let moved = std::mem::take(&mut function.blocks[target.index()].instructions);
+        let terminator = std::mem::replace(&mut function.blocks[target.index()].terminator, Terminator::Return(None));
+        for successor in terminator.successors() {
+            for phi in &mut function.blocks[successor.index()].phis {
+                for argument in &mut phi.arguments {
+                    if argument.0 == target {
+                        argument.0 = id;
+                    }
+                }
+            }
+        }
+        let block = &mut function.blocks[id.index()];
+        block.instructions.extend(moved);
+        block.terminator = terminator;
+        changed = true;
+        // Vorgänger haben sich geändert
+        break;
+    }
+    changed
+}
@@ -0,0 +1,172 @@
+use super::{remark, replace_uses, Remark};
+use crate::ir::{Block, BlockId, Callee, Function, InstKind, Phi, Program, Terminator, Value};
+use std::collections::HashMap;
+
+// ============================================================================
+// Einsetzen kleiner Blattprozeduren
+// ============================================================================
+//
+// Prozeduren auf Modulebene ohne Aufrufe, ohne Slots, ohne geschachtelte
+// Prozeduren und mit höchstens LIMIT Befehlen werden an jeder direkten
+// Aufrufstelle eingesetzt: der Block wird hinter dem Aufruf geteilt, die
+// Blöcke der Prozedur kopiert und ihre Rückgaben zu Sprüngen hinter den
+// Aufruf, bei mehreren Rückgaben mit einem Phi-Knoten für das Ergebnis.
+// Eingesetzte Befehle behalten Zeile und Prozedurnamen für Laufzeitfehler
+// (Instruction::origin).
+//
+// Nicht exportierte Prozeduren, die danach nirgends mehr vorkommen, entfallen.
+
+const LIMIT: usize = 20;
+
+pub fn run(program: &mut Program, remarks: &mut Vec<Remark>) {
+    let candidates: HashMap<String, Function> = program
+        .functions
+        .iter()
+        .filter(|function| is_candidate(program, function))
+        .map(|function| (function.name.clone(), function.clone()))
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+
+    // Zeile des letzten Aufrufs je eingesetzter Prozedur
+    let mut lines: HashMap<String, usize> = HashMap::new();
+    for function in &mut program.functions {
+        while let Some((block, index, callee)) = find_call(function, &candidates) {
+            let line = function.block(block).instructions[index].line;
+            lines.insert(callee.name.clone(), line);
+            inline_call(function, block, index, callee);
+            remark(remarks, "inline", function, line, format!("{} eingesetzt", callee.name));
+        }
+    }
+
+    // Nicht mehr benutzte Prozeduren
+    let mut unused: Vec<String> = candidates
+        .values()
+        .filter(|callee| lines.contains_key(&callee.name) && !callee.exported && !is_referenced(program, &callee.name))
+        .map(|callee| callee.name.clone())
+        .collect();
+    unused.sort();
+    for name in unused {
+        program.functions.retain(|function| function.name != name);
+        let message = format!("{} entfernt, alle Aufrufe sind eingesetzt", name);
+        remarks.push(Remark { pass: "inline", function: name.clone(), line: lines[&name], message });
+    }
+}
+
+fn is_candidate(program: &Program, function: &Function) -> bool {
+    let prefix = format!("{}.", function.name);
+    let size: usize = function.blocks.iter().map(|block| block.instructions.len() + block.phis.len()).sum();
+    function.depth == 1
+        && function.slots.is_empty()
+        && size <= LIMIT
+        && function.blocks[BlockId::ENTRY.index()].phis.is_empty()
+        && !program.functions.iter().any(|other| other.name.starts_with(&prefix))
+        && function.blocks.iter().all(|block| {
+            !matches!(block.terminator, Terminator::CaseTrap { .. })
+                && block.instructions.iter().all(|instruction| !matches!(instruction.kind, InstKind::Call { .. }))
+        })
+}
+
+fn find_call<'a>(function: &Function, candidates: &'a HashMap<String, Function>) -> Option<(BlockId, usize, &'a Function)> {
+    for id in function.block_ids() {
+        for (index, instruction) in function.block(id).instructions.iter().enumerate() {
+            if let InstKind::Call { callee: Callee::Direct { name, .. }, .. } = &instruction.kind {
+                if let Some(callee) = candidates.get(name).filter(|callee| callee.name != function.name) {
+                    return Some((id, index, callee));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn is_referenced(program: &Program, name: &str) -> bool {
+    program.functions.iter().flat_map(|function| &function.blocks).flat_map(|block| &block.instructions).any(
+        |instruction| match &instruction.kind {
+            InstKind::Call { callee: Callee::Direct { name: callee, .. }, .. } => callee == name,
+            InstKind::Procedure(procedure) => procedure == name,
+            _ => false,
+        },
+    )
+}
+
+fn inline_call(function: &mut Function, block: BlockId, index: usize, callee: &Function) {
+    // Block hinter dem Aufruf teilen
+    let mut instructions = std::mem::take(&mut function.blocks[block.index()].instructions);
+    let after: Vec<_> = instructions.split_off(index + 1);
+    let call = instructions.pop().expect("Aufruf");
+    let InstKind::Call { arguments, .. } = call.kind else { unreachable!() };
+    let terminator = std::mem::replace(&mut function.blocks[block.index()].terminator, Terminator::Return(None));
+
+    let continuation = BlockId(function.blocks.len() as u32);
+    for successor in terminator.successors() {
+        for phi in &mut function.blocks[successor.index()].phis {
+            for argument in &mut phi.arguments {
+                if argument.0 == block {
+                    argument.0 = continuation;
+                }
+            }
+        }
+    }
+    function.blocks.push(Block { phis: Vec::new(), instructions: after, terminator });
+
+    // Werte und Blöcke der Prozedur umbenennen
+    let offset = function.blocks.len() as u32;
+    let mut values: HashMap<Value, Value> =
+        callee.params.iter().zip(&arguments).map(|(param, &argument)| (param.value, argument)).collect();
+    for (index, &ty) in callee.values.iter().enumerate() {
+        values.entry(Value(index as u32)).or_insert_with(|| function.new_value(ty));
+    }
+    let map = |value: Value| values[&value];
+    let mut returns = Vec::new();
+    for (index, original) in callee.blocks.iter().enumerate() {
+        let id = BlockId(offset + index as u32);
+        let phis = original
+            .phis
+            .iter()
+            .map(|phi| Phi {
+                dest: map(phi.dest),
+                arguments: phi.arguments.iter().map(|&(source, value)| (BlockId(offset + source.0), map(value))).collect(),
+            })
+            .collect();
+        let instructions = original
+            .instructions
+            .iter()
+            .map(|instruction| {
+                let mut instruction = instruction.clone();
+                instruction.dest = instruction.dest.map(map);
+                instruction.kind.map_operands(map);
+                instruction.origin.get_or_insert_with(|| callee.name.clone());
+                instruction
+            })
+            .collect();
+        let terminator = match &original.terminator {
+            Terminator::Return(value) => {
+                if let Some(value) = value {
+                    returns.push((id, map(*value)));
+                }
+                Terminator::Jump(continuation)
+            }
+            other => {
+                let mut terminator = other.clone();
+                terminator.map_operands(map);
+                terminator.map_successors(|target| BlockId(offset + target.0));
+                terminator
+            }
+        };
+        function.blocks.push(Block { phis, instructions, terminator });
+    }
+
+    let caller = &mut function.blocks[block.index()];
+    caller.instructions = instructions;
+    caller.terminator = Terminator::Jump(BlockId(offset));
+
+    // Ergebnis
+    if let Some(dest) = call.dest {
+        match returns[..] {
+            [(_, value)] => replace_uses(function, &HashMap::from([(dest, value)])),
+            _ => function.blocks[continuation.index()].phis.push(Phi { dest, arguments: returns }),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use super::{is_pure, remark, Remark};
+use crate::ir::ssa::DominatorTree;
+use crate::ir::{Block, BlockId, Function, InstKind, Phi, Terminator, Value};
+use std::collections::HashSet;
+
+// ============================================================================
+// Schleifeninvariante Befehle (LICM)
+// ============================================================================
+//
+// Natürliche Schleifen ergeben sich aus Rückwärtskanten zu einem Block, der
+// ihren Ursprung dominiert. Befehle ohne Nebenwirkung und ohne
+// Laufzeitfehler, deren Operanden außerhalb der Schleife entstehen, wandern
+// in einen Vorblock, der bei Bedarf angelegt wird. Loads bleiben stehen,
+// weil die Schleife in den Speicher schreiben kann; Befehle, die abbrechen
+// können, ebenso, weil die Schleife womöglich nie betreten wird.
+// Innere Schleifen kommen zuerst an die Reihe, damit ihre Befehle danach
+// auch aus der äußeren wandern können.
+
+pub fn run(function: &mut Function, remarks: &mut Vec<Remark>) {
+    let mut done: HashSet<BlockId> = HashSet::new();
+    loop {
+        let tree = DominatorTree::new(function);
+        let mut loops = natural_loops(function, &tree);
+        // Der Eintrittsblock hat keinen Platz für einen Vorblock
+        loops.retain(|(header, _)| *header != BlockId::ENTRY && !done.contains(header));
+        loops.sort_by_key(|(_, body)| body.len());
+        let Some((header, body)) = loops.into_iter().next() else { break };
+        done.insert(header);
+        hoist(function, &tree, header, &body, remarks);
+    }
+}
+
+// Kopf und Blöcke jeder natürlichen Schleife; Schleifen mit gleichem Kopf
+// werden zusammengefasst
+fn natural_loops(function: &Function, tree: &DominatorTree) -> Vec<(BlockId, HashSet<BlockId>)> {
+    let mut loops: Vec<(BlockId, HashSet<BlockId>)> = Vec::new();
+    for &source in tree.reverse_postorder() {
+        for header in function.block(source).terminator.successors() {
+            if !tree.dominates(header, source) {
+                continue;
+            }
+            let index = match loops.iter().position(|(other, _)| *other == header) {
+                Some(index) => index,
+                None => {
+                    loops.push((header, HashSet::from([header])));
+                    loops.len() - 1
+                }
+            };
+            let body = &mut loops[index].1;
+            let mut stack = vec![source];
+            while let Some(block) = stack.pop() {
+                if body.insert(block) {
+                    stack.extend(tree.predecessors(block).iter().copied());
+                }
+            }
+        }
+    }
+    loops
+}
+
+fn hoist(function: &mut Function, tree: &DominatorTree, header: BlockId, body: &HashSet<BlockId>, remarks: &mut Vec<Remark>) {
+    // Werte, die in der Schleife entstehen
+    let mut inside: HashSet<Value> = HashSet::new();
+    for &id in body {
+        let block = function.block(id);
+        inside.extend(block.phis.iter().map(|phi| phi.dest));
+        inside.extend(block.instructions.iter().filter_map(|instruction| instruction.dest));
+    }
+
+    // Kandidaten in Reverse-Postorder, damit Operanden vor ihren Benutzern
+    // wandern
+    let mut hoisted = Vec::new();
+    for &id in tree.reverse_postorder().iter().filter(|id| body.contains(id)) {
+        let instructions = std::mem::take(&mut function.blocks[id.index()].instructions);
+        let mut kept = Vec::with_capacity(instructions.len());
+        for instruction in instructions {
+            let invariant = is_pure(&instruction.kind)
+                && instruction.dest.is_some()
+                && instruction.kind.operands().iter().all(|operand| !inside.contains(operand));
+            if invariant {
+                if let Some(dest) = instruction.dest {
+                    inside.remove(&dest);
+                }
+                hoisted.push(instruction);
+            } else {
+                kept.push(instruction);
+            }
+        }
+        function.blocks[id.index()].instructions = kept;
+    }
+    if hoisted.is_empty() {
+        return;
+    }
+
+    for instruction in &hoisted {
+        if matches!(instruction.kind, InstKind::Unary { .. } | InstKind::Binary { .. } | InstKind::Compare { .. }) {
+            remark(remarks, "licm", function, instruction.line, "Ausdruck wandert vor die Schleife".to_string());
+        }
+    }
+    let preheader = preheader(function, tree, header, body);
+    function.blocks[preheader.index()].instructions.extend(hoisted);
+}
+
+// Einziger Vorgänger des Schleifenkopfs außerhalb der Schleife, der nur zu
+// ihm springt; sonst ein neuer Block, auf den alle äußeren Vorgänger
+// umgelenkt werden
+fn preheader(function: &mut Function, tree: &DominatorTree, header: BlockId, body: &HashSet<BlockId>) -> BlockId {
+    let outside: Vec<BlockId> = tree.predecessors(header).iter().copied().filter(|block| !body.contains(block)).collect();
+    if let [single] = outside[..] {
+        if function.block(single).terminator == Terminator::Jump(header) {
+            return single;
+        }
+    }
+
+    let preheader = BlockId(function.blocks.len() as u32);
+    let mut phis = Vec::new();
+    let mut updated = Vec::new();
+    for phi in function.blocks[header.index()].phis.clone() {
+        let (incoming, mut arguments): (Vec<_>, Vec<_>) =
+            phi.arguments.into_iter().partition(|(source, _)| outside.contains(source));
+        let value = match incoming[..] {
+            [(_, value)] => value,
+            _ => {
+                let dest = function.new_value(function.value_type(phi.dest));
+                phis.push(Phi { dest, arguments: incoming });
+                dest
+            }
+        };
+        arguments.push((preheader, value));
+        updated.push(Phi { dest: phi.dest, arguments });
+    }
+    function.blocks[header.index()].phis = updated;
+    for &source in &outside {
+        function.blocks[source.index()].terminator.map_successors(|target| if target == header { preheader } else { target });
+    }
+    function.blocks.push(Block { phis, instructions: Vec::new(), terminator: Terminator::Jump(header) });
+    preheader
+}
@@ -0,0 +1,357 @@
+pub mod bounds;
+pub mod cse;
+pub mod dce;
+pub mod inline;
+pub mod licm;
+pub mod sccp;
+
+use super::{Function, InstKind, Program, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+// ============================================================================
+// Optimierungen
+// ============================================================================
+//
+// Passes auf der IR in SSA-Form. Jeder Pass hinterlässt eine Funktion, die
+// verify besteht, und erhält das beobachtbare Verhalten samt aller
+// Laufzeitfehler: ein Befehl, der abbrechen kann, entfällt nur, wenn er
+// nachweislich nicht abbricht (konstante Operanden, bekannter Wertebereich)
+// oder ein gleicher Befehl ihn dominiert.
+//
+//   -O0  keine Optimierung
+//   -O1  Konstantenpropagation (SCCP), gemeinsame Teilausdrücke (CSE),
+//        toter Code (DCE) und Vereinfachung des Kontrollflusses
+//   -O2  vorab Einsetzen kleiner Blattprozeduren, zusätzlich Entfernen von
+//        Indexprüfungen und Herausziehen schleifeninvarianter Befehle (LICM)
+//
+// Jeder Pass meldet, was er getan hat, als Remark mit Quelltextzeile.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    #[default]
+    O0,
+    O1,
+    O2,
+}
+
+impl OptLevel {
+    // "-O0", "-O1" oder "-O2"
+    pub fn from_flag(flag: &str) -> Option<OptLevel> {
+        match flag {
+            "-O0" => Some(OptLevel::O0),
+            "-O1" => Some(OptLevel::O1),
+            "-O2" => Some(OptLevel::O2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remark {
+    pub pass: &'static str,
+    pub function: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Remark {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {} [{}]", self.line, self.function, self.message, self.pass)
+    }
+}
+
+pub fn optimize(program: &mut Program, level: OptLevel) -> Vec<Remark> {
+    let mut remarks = Vec::new();
+    if level == OptLevel::O0 {
+        return remarks;
+    }
+    if level >= OptLevel::O2 {
+        inline::run(program, &mut remarks);
+    }
+    for function in &mut program.functions {
+        sccp::run(function, &mut remarks);
+        cse::run(function, &mut remarks);
+        if level >= OptLevel::O2 {
+            bounds::run(function, &mut remarks);
+            licm::run(function, &mut remarks);
+            // Vor mehrere Schleifen gezogene Befehle zusammenfassen
+            cse::run(function, &mut remarks);
+        }
+        dce::run(function, &mut remarks);
+    }
+    remarks
+}
+
+fn remark(remarks: &mut Vec<Remark>, pass: &'static str, function: &Function, line: usize, message: String) {
+    remarks.push(Remark { pass, function: function.name.clone(), line, message });
+}
+
+// ============================================================================
+// Gemeinsame Hilfsfunktionen
+// ============================================================================
+
+// Ohne Nebenwirkung und ohne Laufzeitfehler: darf entfallen, mehrfach oder
+// an anderer Stelle ausgeführt werden
+fn is_pure(kind: &InstKind) -> bool {
+    match kind {
+        InstKind::Const(_)
+        | InstKind::String(_)
+        | InstKind::Global(_)
+        | InstKind::Slot { .. }
+        | InstKind::Procedure(_)
+        | InstKind::Field { .. }
+        | InstKind::Element { .. }
+        | InstKind::Compare { .. } => true,
+        InstKind::Unary { op, .. } => !op.can_trap(),
+        InstKind::Binary { op, .. } => !op.can_trap(),
+        _ => false,
+    }
+}
+
+// Darf entfallen, wenn das Ergebnis unbenutzt ist: zusätzlich Lesezugriffe
+// auf den Speicher und NEW
+fn is_removable(kind: &InstKind) -> bool {
+    is_pure(kind)
+        || matches!(
+            kind,
+            InstKind::Load { .. } | InstKind::CompareStrings { .. } | InstKind::TypeTest { .. } | InstKind::New { .. }
+        )
+}
+
+// Ersetzt jede Verwendung eines Werts, auch über Ketten v1 -> v2 -> v3
+fn replace_uses(function: &mut Function, replacements: &HashMap<Value, Value>) {
+    if replacements.is_empty() {
+        return;
+    }
+    let resolve = |mut value: Value| {
+        while let Some(&next) = replacements.get(&value) {
+            if next == value {
+                break;
+            }
+            value = next;
+        }
+        value
+    };
+    for block in &mut function.blocks {
+        for phi in &mut block.phis {
+            for argument in &mut phi.arguments {
+                argument.1 = resolve(argument.1);
+            }
+        }
+        for instruction in &mut block.instructions {
+            instruction.kind.map_operands(resolve);
+        }
+        block.terminator.map_operands(resolve);
+    }
+}
+
+// Anzahl der Verwendungen jedes Werts
+fn use_counts(function: &Function) -> Vec<usize> {
+    let mut counts = vec![0; function.values.len()];
+    for block in &function.blocks {
+        for phi in &block.phis {
+            for &(_, value) in &phi.arguments {
+                counts[value.index()] += 1;
+            }
+        }
+        for instruction in &block.instructions {
+            for operand in instruction.kind.operands() {
+                counts[operand.index()] += 1;
+            }
+        }
+        for operand in block.terminator.operands() {
+            counts[operand.index()] += 1;
+        }
+    }
+    counts
+}
+
+// Nach Änderungen am Kontrollfluss: Phi-Argumente entfallener Kanten
+// streichen, Phi-Knoten mit nur noch einem Wert (außer sich selbst) durch
+// diesen ersetzen
+fn simplify_phis(function: &mut Function) {
+    let predecessors = function.predecessors();
+    for (index, block) in function.blocks.iter_mut().enumerate() {
+        for phi in &mut block.phis {
+            phi.arguments.retain(|(source, _)| predecessors[index].contains(source));
+        }
+    }
+    loop {
+        let mut replacements = HashMap::new();
+        for block in &mut function.blocks {
+            block.phis.retain(|phi| {
+                let mut values = phi.arguments.iter().map(|&(_, value)| value).filter(|&value| value != phi.dest);
+                let Some(first) = values.next() else { return true };
+                if values.all(|value| value == first) {
+                    replacements.insert(phi.dest, first);
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        if replacements.is_empty() {
+            break;
+        }
+        replace_uses(function, &replacements);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::tests::compile;
+    use crate::ir::{verify, Terminator};
+
+    fn optimized(source: &str, level: OptLevel) -> (Program, Vec<Remark>) {
+        let mut program = compile(source);
+        let remarks = optimize(&mut program, level);
+        if let Err(e) = verify(&program) {
+            panic!("{}\n{}", e, program);
+        }
+        (program, remarks)
+    }
+
+    fn count(function: &Function, matches: impl Fn(&InstKind) -> bool) -> usize {
+        function.blocks.iter().flat_map(|block| &block.instructions).filter(|instruction| matches(&instruction.kind)).count()
+    }
+
+    #[test]
+    fn test_constants() {
+        let (program, remarks) = optimized(
+            "MODULE T;
+            PROCEDURE F(x: INTEGER): INTEGER;
+              VAR k, r, z: INTEGER;
+            BEGIN k := 3; k := k * 4; z := 0;
+              IF k > 10 THEN r := x + k ELSE r := x - k END;
+              k := 7 DIV z
+              RETURN r
+            END F;
+            END T.",
+            OptLevel::O1,
+        );
+
+        // Die Bedingung ist konstant, der ELSE-Zweig entfällt; 7 DIV z
+        // bleibt als Laufzeitfehler stehen, obwohl sein Ergebnis unbenutzt ist
+        let f = program.function("F").unwrap();
+        assert_eq!(f.blocks.len(), 1, "{}", program);
+        assert!(f.blocks.iter().all(|block| block.phis.is_empty()));
+        assert_eq!(count(f, |kind| matches!(kind, InstKind::Binary { .. })), 2, "{}", program);
+        assert!(program.to_string().contains("const 12\n"), "{}", program);
+        assert!(remarks.iter().any(|remark| remark.pass == "sccp" && remark.line == 4), "{:?}", remarks);
+    }
+
+    #[test]
+    fn test_common_subexpressions() {
+        let (program, remarks) = optimized(
+            "MODULE T;
+            TYPE P = POINTER TO RECORD a: ARRAY 8 OF INTEGER END;
+            PROCEDURE F(p: P; i, j: INTEGER): INTEGER;
+            BEGIN RETURN p^.a[i] * (i + j) + p^.a[i] * (j + i)
+            END F;
+            END T.",
+            OptLevel::O1,
+        );
+
+        // Eine Nil- und eine Indexprüfung, ein Load, eine Addition dank
+        // vertauschter Operanden
+        let f = program.function("F").unwrap();
+        assert_eq!(count(f, |kind| matches!(kind, InstKind::CheckNil(_))), 1, "{}", program);
+        assert_eq!(count(f, |kind| matches!(kind, InstKind::CheckIndex { .. })), 1, "{}", program);
+        assert_eq!(count(f, |kind| matches!(kind, InstKind::Load { .. })), 1, "{}", program);
+        assert_eq!(count(f, |kind| matches!(kind, InstKind::Binary { .. })), 3, "{}", program);
+        assert!(remarks.iter().any(|remark| remark.pass == "cse"), "{:?}", remarks);
+    }
+
+    #[test]
+    fn test_loops() {
+        let source = "MODULE T;
+            VAR a: ARRAY 10 OF INTEGER; r: ARRAY 10 OF REAL;
+            PROCEDURE Fill*(x: REAL);
+              VAR i: INTEGER;
+            BEGIN
+              FOR i := 0 TO 9 DO
+                r[i] := x * 2.0 + FLT(i)
+              END
+            END Fill;
+            PROCEDURE Shift*;
+              VAR i: INTEGER;
+            BEGIN i := 9;
+              WHILE i > 0 DO a[i] := a[i - 1]; DEC(i) END
+            END Shift;
+            END T.";
+        let (program, remarks) = optimized(source, OptLevel::O2);
+
+        // Indexprüfungen entfallen, x * 2.0 wandert vor die Schleife
+        let fill = program.function("Fill").unwrap();
+        let shift = program.function("Shift").unwrap();
+        assert_eq!(count(fill, |kind| matches!(kind, InstKind::CheckIndex { .. })), 0, "{}", program);
+        assert_eq!(count(shift, |kind| matches!(kind, InstKind::CheckIndex { .. })), 0, "{}", program);
+        assert!(remarks.iter().any(|remark| remark.pass == "bounds" && remark.line == 7), "{:?}", remarks);
+        assert!(remarks.iter().any(|remark| remark.pass == "licm" && remark.line == 7), "{:?}", remarks);
+
+        let (program, _) = optimized(source, OptLevel::O1);
+        let fill = program.function("Fill").unwrap();
+        assert_eq!(count(fill, |kind| matches!(kind, InstKind::CheckIndex { .. })), 1, "{}", program);
+    }
+
+    #[test]
+    fn test_inlining() {
+        let (program, remarks) = optimized(
+            "MODULE T;
+            VAR g: INTEGER;
+            PROCEDURE Max(a, b: INTEGER): INTEGER;
+              VAR m: INTEGER;
+            BEGIN IF a > b THEN m := a ELSE m := b END
+              RETURN m
+            END Max;
+            PROCEDURE Square*(x: INTEGER): INTEGER;
+            BEGIN RETURN x * x
+            END Square;
+            PROCEDURE Fact(n: INTEGER): INTEGER;
+              VAR r: INTEGER;
+            BEGIN IF n <= 1 THEN r := 1 ELSE r := n * Fact(n - 1) END
+              RETURN r
+            END Fact;
+            BEGIN g := Max(g, 3) + Square(g) + Fact(5)
+            END T.",
+            OptLevel::O2,
+        );
+
+        // Max verschwindet ganz, das exportierte Square bleibt erhalten,
+        // das rekursive Fact wird nicht eingesetzt
+        assert!(program.function("Max").is_none(), "{}", program);
+        assert!(program.function("Square").is_some());
+        let body = program.function("T").unwrap();
+        let calls: Vec<String> = body
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .filter_map(|instruction| match &instruction.kind {
+                InstKind::Call { callee: crate::ir::Callee::Direct { name, .. }, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(calls, ["Fact"]);
+        assert!(program.to_string().contains("; aus Square\n"), "{}", program);
+        assert!(remarks.iter().any(|remark| remark.pass == "inline" && remark.message.contains("Max")), "{:?}", remarks);
+    }
+
+    #[test]
+    fn test_levels() {
+        let source = "MODULE T;
+            VAR g: INTEGER;
+            BEGIN IF 1 < 2 THEN g := 1 ELSE g := 2 END
+            END T.";
+        let (program, remarks) = optimized(source, OptLevel::O0);
+        assert!(remarks.is_empty());
+        let body = program.function("T").unwrap();
+        assert!(body.blocks.iter().any(|block| matches!(block.terminator, Terminator::Branch { .. })));
+
+        let (program, _) = optimized(source, OptLevel::O1);
+        assert_eq!(program.function("T").unwrap().blocks.len(), 1, "{}", program);
+        assert_eq!(OptLevel::from_flag("-O2"), Some(OptLevel::O2));
+        assert_eq!(OptLevel::from_flag("-O3"), None);
+    }
+}
@@ -0,0 +1,318 @@
+use super::{remark, simplify_phis, Remark};
+use crate::ir::ssa::remove_unreachable;
+use crate::ir::{BinaryOp, BlockId, CompareOp, Constant, Function, InstKind, Instruction, Terminator, UnaryOp, Value};
+use crate::parser::ast::Span;
+use crate::semantic::const_eval::{floor_div, floor_mod};
+use std::collections::HashSet;
+
+// ============================================================================
+// Bedingte Konstantenpropagation (SCCP)
+// ============================================================================
+//
+// Verfahren von Wegman und Zadeck: Werte und Kanten werden gemeinsam
+// ausgewertet, Phi-Knoten berücksichtigen nur Argumente über ausführbare
+// Kanten. Danach werden konstante Befehle durch Const ersetzt, Verzweigungen
+// mit konstanter Bedingung zu Sprüngen und nie ausgeführte Blöcke entfernt.
+//
+// Ein Befehl, der mit seinen konstanten Operanden abbrechen würde (Überlauf,
+// Division durch 0, ...), gilt als nicht konstant und bleibt stehen.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Lattice {
+    // Noch kein Wert bekannt
+    Unknown,
+    Constant(Constant),
+    Varying,
+}
+
+impl Lattice {
+    fn meet(&self, other: &Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Unknown, other) | (other, Lattice::Unknown) => other.clone(),
+            (Lattice::Constant(a), Lattice::Constant(b)) if same_constant(a, b) => self.clone(),
+            _ => Lattice::Varying,
+        }
+    }
+}
+
+pub fn run(function: &mut Function, remarks: &mut Vec<Remark>) {
+    let mut values = vec![Lattice::Unknown; function.values.len()];
+    for param in &function.params {
+        values[param.value.index()] = Lattice::Varying;
+    }
+    let mut executable = vec![false; function.blocks.len()];
+    let mut edges: HashSet<(BlockId, BlockId)> = HashSet::new();
+    executable[BlockId::ENTRY.index()] = true;
+
+    // Bis zum Fixpunkt; Werte sinken nur im Verband, Kanten kommen nur hinzu
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in function.block_ids() {
+            if !executable[id.index()] {
+                continue;
+            }
+            let block = function.block(id);
+            let mut update = |value: Value, new: Lattice, values: &mut Vec<Lattice>| {
+                let merged = values[value.index()].meet(&new);
+                if merged != values[value.index()] {
+                    values[value.index()] = merged;
+                    changed = true;
+                }
+            };
+            for phi in &block.phis {
+                let mut result = Lattice::Unknown;
+                for &(source, value) in &phi.arguments {
+                    if edges.contains(&(source, id)) {
+                        result = result.meet(&values[value.index()]);
+                    }
+                }
+                update(phi.dest, result, &mut values);
+            }
+            for instruction in &block.instructions {
+                if let Some(dest) = instruction.dest {
+                    let result = evaluate(&instruction.kind, &values);
+                    update(dest, result, &mut values);
+                }
+            }
+            for successor in feasible_successors(&block.terminator, &values) {
+                if edges.insert((id, successor)) {
+                    executable[successor.index()] = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    // Konstante Befehle und Phi-Knoten ersetzen
+    let mut folded = Vec::new();
+    for id in function.block_ids() {
+        let block = &mut function.blocks[id.index()];
+        let mut constants = Vec::new();
+        block.phis.retain(|phi| match &values[phi.dest.index()] {
+            Lattice::Constant(constant) => {
+                constants.push((phi.dest, constant.clone()));
+                false
+            }
+            _ => true,
+        });
+        for instruction in &mut block.instructions {
+            let Some(dest) = instruction.dest else { continue };
+            if let Lattice::Constant(constant) = &values[dest.index()] {
+                if !matches!(instruction.kind, InstKind::Const(_)) {
+                    instruction.kind = InstKind::Const(constant.clone());
+                    folded.push((instruction.line, constant.clone()));
+                }
+            }
+        }
+        let (line, span) = block
+            .instructions
+            .first()
+            .map_or((0, Span::default()), |instruction| (instruction.line, instruction.span));
+        for (index, (dest, constant)) in constants.into_iter().enumerate() {
+            let instruction = Instruction { dest: Some(dest), kind: InstKind::Const(constant), line, span, origin: None };
+            block.instructions.insert(index, instruction);
+        }
+    }
+    for (line, constant) in folded {
+        remark(remarks, "sccp", function, line, format!("Ausdruck hat den konstanten Wert {}", constant));
+    }
+
+    // Verzweigungen mit bekanntem Ziel
+    for id in function.block_ids() {
+        if !executable[id.index()] {
+            continue;
+        }
+        let block = function.block(id);
+        let successors = feasible_successors(&block.terminator, &values);
+        let target = match &block.terminator {
+            Terminator::Branch { .. } | Terminator::Switch { .. } if successors.len() == 1 => successors[0],
+            _ => continue,
+        };
+        let condition = block.terminator.operands()[0];
+        let line = block
+            .instructions
+            .iter()
+            .find(|instruction| instruction.dest == Some(condition))
+            .map_or_else(|| block.instructions.last().map_or(0, |instruction| instruction.line), |instruction| instruction.line);
+        let message = match &values[condition.index()] {
+            Lattice::Constant(Constant::Bool(true)) => "Bedingung ist immer erfüllt".to_string(),
+            Lattice::Constant(Constant::Bool(false)) => "Bedingung ist nie erfüllt".to_string(),
+            Lattice::Constant(constant) => format!("CASE-Wert ist immer {}", constant),
+            _ => continue,
+        };
+        remark(remarks, "sccp", function, line, message);
+        function.blocks[id.index()].terminator = Terminator::Jump(target);
+    }
+
+    remove_unreachable(function);
+    simplify_phis(function);
+}
+
+// Ziele, die bei den bekannten Werten erreicht werden können
+fn feasible_successors(terminator: &Terminator, values: &[Lattice]) -> Vec<BlockId> {
+    match terminator {
+        Terminator::Branch { condition, then_block, else_block } => match &values[condition.index()] {
+            Lattice::Unknown => Vec::new(),
+            Lattice::Constant(Constant::Bool(true)) => vec![*then_block],
+            Lattice::Constant(Constant::Bool(false)) => vec![*else_block],
+            _ => vec![*then_block, *else_block],
+        },
+        Terminator::Switch { value, cases, default } => match &values[value.index()] {
+            Lattice::Unknown => Vec::new(),
+            Lattice::Constant(Constant::Int(value)) => {
+                let target = cases
+                    .iter()
+                    .find(|&&(start, end, _)| start <= *value && *value <= end)
+                    .map_or(*default, |&(_, _, target)| target);
+                vec![target]
+            }
+            _ => terminator.successors(),
+        },
+        _ => terminator.successors(),
+    }
+}
+
+fn evaluate(kind: &InstKind, values: &[Lattice]) -> Lattice {
+    let operands = match kind {
+        InstKind::Const(constant) => return Lattice::Constant(constant.clone()),
+        InstKind::Unary { .. } | InstKind::Binary { .. } | InstKind::Compare { .. } => kind.operands(),
+        _ => return Lattice::Varying,
+    };
+    let mut constants = Vec::with_capacity(operands.len());
+    for operand in operands {
+        match &values[operand.index()] {
+            Lattice::Unknown => return Lattice::Unknown,
+            Lattice::Varying => return Lattice::Varying,
+            Lattice::Constant(constant) => constants.push(constant),
+        }
+    }
+    let result = match kind {
+        InstKind::Unary { op, .. } => fold_unary(*op, constants[0]),
+        InstKind::Binary { op, .. } => fold_binary(*op, constants[0], constants[1]),
+        InstKind::Compare { op, .. } => fold_compare(*op, constants[0], constants[1]),
+        _ => None,
+    };
+    result.map_or(Lattice::Varying, Lattice::Constant)
+}
+
+// ============================================================================
+// Auswertung
+// ============================================================================
+//
+// Wie die Laufzeit des C-Backends; None, wenn der Befehl abbrechen würde
+// oder das Ergebnis nicht als Konstante darstellbar ist.
+
+// Gleichheit für den Verband: 0.0 und -0.0 sind verschieden
+fn same_constant(a: &Constant, b: &Constant) -> bool {
+    match (a, b) {
+        (Constant::Real(a), Constant::Real(b)) => a.to_bits() == b.to_bits(),
+        _ => a == b,
+    }
+}
+
+fn real(value: f64) -> Option<Constant> {
+    value.is_finite().then_some(Constant::Real(value))
+}
+
+fn fold_unary(op: UnaryOp, operand: &Constant) -> Option<Constant> {
+    match (op, operand) {
+        (UnaryOp::Neg, Constant::Int(x)) => x.checked_neg().map(Constant::Int),
+        (UnaryOp::Abs, Constant::Int(x)) => x.checked_abs().map(Constant::Int),
+        (UnaryOp::FNeg, Constant::Real(x)) => real(-x),
+        (UnaryOp::FAbs, Constant::Real(x)) => real(x.abs()),
+        (UnaryOp::Not, Constant::Bool(x)) => Some(Constant::Bool(!x)),
+        (UnaryOp::Complement, Constant::Set(x)) => Some(Constant::Set(!x)),
+        (UnaryOp::Odd, Constant::Int(x)) => Some(Constant::Bool(x & 1 != 0)),
+        (UnaryOp::IntToReal, Constant::Int(x)) => real(*x as f64),
+        (UnaryOp::Floor, Constant::Real(x)) => {
+            (*x > -9223372036854775808.0 && *x < 9223372036854775808.0).then(|| Constant::Int(x.floor() as i64))
+        }
+        (UnaryOp::Ord, Constant::Char(x)) => Some(Constant::Int(*x as i64)),
+        (UnaryOp::Ord, Constant::Bool(x)) => Some(Constant::Int(*x as i64)),
+        (UnaryOp::Ord, Constant::Set(x)) => Some(Constant::Int(*x as i64)),
+        (UnaryOp::Chr, Constant::Int(x)) => u8::try_from(*x).ok().map(Constant::Char),
+        _ => None,
+    }
+}
+
+fn fold_binary(op: BinaryOp, left: &Constant, right: &Constant) -> Option<Constant> {
+    match (left, right) {
+        (Constant::Int(a), Constant::Int(b)) => {
+            let (a, b) = (*a, *b);
+            let shift = u32::try_from(b).ok().filter(|&n| n <= 63);
+            let result = match op {
+                BinaryOp::Add | BinaryOp::Inc | BinaryOp::For => a.checked_add(b),
+                BinaryOp::Sub | BinaryOp::Dec => a.checked_sub(b),
+                BinaryOp::Mul => a.checked_mul(b),
+                BinaryOp::Div => floor_div(a, b),
+                BinaryOp::Mod if b == -1 => Some(0),
+                BinaryOp::Mod => floor_mod(a, b),
+                BinaryOp::Lsl => shift.and_then(|n| {
+                    let result = ((a as u64) << n) as i64;
+                    (result >> n == a).then_some(result)
+                }),
+                BinaryOp::Asr => shift.map(|n| a >> n),
+                BinaryOp::Ror => shift.map(|n| (a as u64).rotate_right(n) as i64),
+                BinaryOp::Range => {
+                    if !(0..=31).contains(&a) || !(0..=31).contains(&b) {
+                        return None;
+                    }
+                    let bits = if a > b { 0 } else { ((2u64 << b) - (1u64 << a)) as u32 };
+                    return Some(Constant::Set(bits));
+                }
+                _ => None,
+            };
+            result.map(Constant::Int)
+        }
+        (Constant::Real(a), Constant::Real(b)) => match op {
+            BinaryOp::FAdd => real(a + b),
+            BinaryOp::FSub => real(a - b),
+            BinaryOp::FMul => real(a * b),
+            BinaryOp::FDiv => real(a / b),
+            _ => None,
+        },
+        (Constant::Set(a), Constant::Set(b)) => match op {
+            BinaryOp::Union => Some(Constant::Set(a | b)),
+            BinaryOp::Difference => Some(Constant::Set(a & !b)),
+            BinaryOp::Intersection => Some(Constant::Set(a & b)),
+            BinaryOp::SymmetricDifference => Some(Constant::Set(a ^ b)),
+            _ => None,
+        },
+        (Constant::Int(a), Constant::Set(b)) if op == BinaryOp::In => {
+            Some(Constant::Bool((0..32).contains(a) && (b >> a) & 1 != 0))
+        }
+        _ => None,
+    }
+}
+
+fn fold_compare(op: CompareOp, left: &Constant, right: &Constant) -> Option<Constant> {
+    let ordering = match (left, right) {
+        (Constant::Int(a), Constant::Int(b)) => a.cmp(b),
+        (Constant::Char(a), Constant::Char(b)) => a.cmp(b),
+        (Constant::Bool(a), Constant::Bool(b)) => a.cmp(b),
+        (Constant::Set(a), Constant::Set(b)) => a.cmp(b),
+        (Constant::Nil, Constant::Nil) => std::cmp::Ordering::Equal,
+        (Constant::Real(a), Constant::Real(b)) => {
+            let result = match op {
+                CompareOp::Eq => a == b,
+                CompareOp::Ne => a != b,
+                CompareOp::Lt => a < b,
+                CompareOp::Le => a <= b,
+                CompareOp::Gt => a > b,
+                CompareOp::Ge => a >= b,
+            };
+            return Some(Constant::Bool(result));
+        }
+        _ => return None,
+    };
+    let result = match op {
+        CompareOp::Eq => ordering.is_eq(),
+        CompareOp::Ne => ordering.is_ne(),
+        CompareOp::Lt => ordering.is_lt(),
+        CompareOp::Le => ordering.is_le(),
+        CompareOp::Gt => ordering.is_gt(),
+        CompareOp::Ge => ordering.is_ge(),
+    };
+    Some(Constant::Bool(result))
+}
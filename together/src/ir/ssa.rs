@@ -0,0 +1,491 @@
+use crate::parser::ast::Span;
+use super::{ancestor, BlockId, Constant, Function, InstKind, Instruction, Phi, Program, Type, Value};
+use crate::semantic::TypeArena;
+use std::collections::{HashMap, HashSet};
+
+// ============================================================================
+// Dominatoren
+// ============================================================================
+//
+// Iteratives Verfahren von Cooper, Harvey und Kennedy ("A Simple, Fast
+// Dominance Algorithm") über die Reverse-Postorder der erreichbaren Blöcke.
+
+pub struct DominatorTree {
+    // Unmittelbarer Dominator; der Eintrittsblock dominiert sich selbst,
+    // unerreichbare Blöcke haben keinen
+    idom: Vec<Option<BlockId>>,
+    children: Vec<Vec<BlockId>>,
+    order: Vec<BlockId>,
+    predecessors: Vec<Vec<BlockId>>,
+}
+
+impl DominatorTree {
+    pub fn new(function: &Function) -> Self {
+        let predecessors = function.predecessors();
+        let order = reverse_postorder(function);
+        let mut position = vec![usize::MAX; function.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            position[block.index()] = index;
+        }
+
+        let mut idom: Vec<Option<BlockId>> = vec![None; function.blocks.len()];
+        idom[BlockId::ENTRY.index()] = Some(BlockId::ENTRY);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut candidate: Option<BlockId> = None;
+                for &predecessor in &predecessors[block.index()] {
+                    if idom[predecessor.index()].is_none() {
+                        continue;
+                    }
+                    candidate = Some(match candidate {
+                        None => predecessor,
+                        Some(other) => intersect(&idom, &position, predecessor, other),
+                    });
+                }
+                if candidate.is_some() && idom[block.index()] != candidate {
+                    idom[block.index()] = candidate;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children = vec![Vec::new(); function.blocks.len()];
+        for &block in order.iter().skip(1) {
+            if let Some(parent) = idom[block.index()] {
+                children[parent.index()].push(block);
+            }
+        }
+        DominatorTree { idom, children, order, predecessors }
+    }
+
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        self.idom[block.index()].filter(|&parent| parent != block)
+    }
+
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        &self.children[block.index()]
+    }
+
+    // Erreichbare Blöcke in Reverse-Postorder
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.order
+    }
+
+    pub fn predecessors(&self, block: BlockId) -> &[BlockId] {
+        &self.predecessors[block.index()]
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idom[block.index()].is_some()
+    }
+
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        let mut block = b;
+        loop {
+            if block == a {
+                return true;
+            }
+            match self.idom(block) {
+                Some(parent) => block = parent,
+                None => return false,
+            }
+        }
+    }
+
+    // Dominanzgrenzen: Blöcke, an denen die Dominanz eines Blocks endet
+    pub fn frontiers(&self) -> Vec<Vec<BlockId>> {
+        let mut frontiers: Vec<Vec<BlockId>> = vec![Vec::new(); self.idom.len()];
+        for &block in &self.order {
+            let predecessors = &self.predecessors[block.index()];
+            if predecessors.len() < 2 {
+                continue;
+            }
+            let Some(dominator) = self.idom(block) else { continue };
+            for &predecessor in predecessors {
+                let mut runner = predecessor;
+                while self.is_reachable(runner) && runner != dominator {
+                    if !frontiers[runner.index()].contains(&block) {
+                        frontiers[runner.index()].push(block);
+                    }
+                    match self.idom(runner) {
+                        Some(parent) => runner = parent,
+                        None => break,
+                    }
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+fn intersect(idom: &[Option<BlockId>], position: &[usize], mut a: BlockId, mut b: BlockId) -> BlockId {
+    while a != b {
+        while position[a.index()] > position[b.index()] {
+            a = idom[a.index()].unwrap_or(BlockId::ENTRY);
+        }
+        while position[b.index()] > position[a.index()] {
+            b = idom[b.index()].unwrap_or(BlockId::ENTRY);
+        }
+    }
+    a
+}
+
+fn reverse_postorder(function: &Function) -> Vec<BlockId> {
+    let mut visited = vec![false; function.blocks.len()];
+    let mut postorder = Vec::new();
+    // Block samt Index des nächsten zu besuchenden Nachfolgers
+    let mut stack = vec![(BlockId::ENTRY, 0)];
+    visited[BlockId::ENTRY.index()] = true;
+    while let Some((block, next)) = stack.pop() {
+        let successors = function.block(block).terminator.successors();
+        match successors.get(next) {
+            Some(&successor) => {
+                stack.push((block, next + 1));
+                if !visited[successor.index()] {
+                    visited[successor.index()] = true;
+                    stack.push((successor, 0));
+                }
+            }
+            None => postorder.push(block),
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+// Entfernt unerreichbare Blöcke und nummeriert die übrigen neu
+pub fn remove_unreachable(function: &mut Function) {
+    let order = reverse_postorder(function);
+    if order.len() == function.blocks.len() {
+        return;
+    }
+    let mut reachable = vec![false; function.blocks.len()];
+    for block in &order {
+        reachable[block.index()] = true;
+    }
+    let mut renumber = vec![BlockId(0); function.blocks.len()];
+    let mut next = 0;
+    for (index, &keep) in reachable.iter().enumerate() {
+        if keep {
+            renumber[index] = BlockId(next);
+            next += 1;
+        }
+    }
+
+    let blocks = std::mem::take(&mut function.blocks);
+    for (index, mut block) in blocks.into_iter().enumerate() {
+        if !reachable[index] {
+            continue;
+        }
+        block.terminator.map_successors(|target| renumber[target.index()]);
+        for phi in &mut block.phis {
+            phi.arguments.retain(|(predecessor, _)| reachable[predecessor.index()]);
+            for argument in &mut phi.arguments {
+                argument.0 = renumber[argument.0.index()];
+            }
+        }
+        function.blocks.push(block);
+    }
+}
+
+// ============================================================================
+// SSA-Aufbau
+// ============================================================================
+//
+// Verfahren von Cytron et al.: Phi-Knoten an den iterierten Dominanzgrenzen
+// der Blöcke, die einen Slot beschreiben, danach Umbenennen entlang des
+// Dominatorbaums. Befördert werden Slots einfacher Typen, deren Adresse nur
+// für Load und Store benutzt wird und die keine geschachtelte Prozedur
+// anspricht. Nicht benutzte Phi-Knoten werden anschließend entfernt.
+
+pub fn construct(program: &mut Program) {
+    // Slots, auf die geschachtelte Prozeduren zugreifen, bleiben im Speicher
+    let mut captured: HashSet<(String, usize)> = HashSet::new();
+    for function in &program.functions {
+        for instruction in function.blocks.iter().flat_map(|block| &block.instructions) {
+            if let InstKind::Slot { depth, index } = instruction.kind {
+                if depth != function.depth {
+                    captured.insert((function.ancestor(depth), index));
+                }
+            }
+        }
+    }
+
+    let mut remaining: HashMap<String, Vec<Option<usize>>> = HashMap::new();
+    for function in &mut program.functions {
+        remove_unreachable(function);
+        let promoted = promote(function, &program.types, &captured);
+
+        // Übrige Slots rücken zusammen
+        let mut next = 0;
+        let renumber = promoted
+            .iter()
+            .map(|&promoted| {
+                (!promoted).then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut index = 0;
+        function.slots.retain(|_| {
+            index += 1;
+            !promoted[index - 1]
+        });
+        remaining.insert(function.name.clone(), renumber);
+    }
+
+    for function in &mut program.functions {
+        let own = function.depth;
+        let name = function.name.clone();
+        for instruction in function.blocks.iter_mut().flat_map(|block| &mut block.instructions) {
+            if let InstKind::Slot { depth, index } = &mut instruction.kind {
+                let owner = if *depth == own { name.clone() } else { ancestor(&name, *depth) };
+                if let Some(Some(new)) = remaining.get(&owner).and_then(|renumber| renumber.get(*index)) {
+                    *index = *new;
+                }
+            }
+        }
+    }
+}
+
+// Liefert für jeden Slot, ob er durch SSA-Werte ersetzt wurde
+fn promote(function: &mut Function, types: &TypeArena, captured: &HashSet<(String, usize)>) -> Vec<bool> {
+    let depth = function.depth;
+    let mut candidates: Vec<bool> = function
+        .slots
+        .iter()
+        .enumerate()
+        .map(|(index, slot)| slot.value_type(types).is_some() && !captured.contains(&(function.name.clone(), index)))
+        .collect();
+
+    // Adresswerte der eigenen Slots
+    let mut addresses: HashMap<Value, usize> = HashMap::new();
+    for instruction in function.blocks.iter().flat_map(|block| &block.instructions) {
+        if let (Some(dest), InstKind::Slot { depth: slot_depth, index }) = (instruction.dest, &instruction.kind) {
+            if *slot_depth == depth {
+                addresses.insert(dest, *index);
+            }
+        }
+    }
+
+    // Eine Adresse, die anders als für Load und Store benutzt wird, entkommt
+    let escape = |value: Value, candidates: &mut Vec<bool>| {
+        if let Some(&index) = addresses.get(&value) {
+            candidates[index] = false;
+        }
+    };
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            match &instruction.kind {
+                InstKind::Load { .. } => {}
+                InstKind::Store { value, .. } => escape(*value, &mut candidates),
+                kind => {
+                    for operand in kind.operands() {
+                        escape(operand, &mut candidates);
+                    }
+                }
+            }
+        }
+        for operand in block.terminator.operands() {
+            escape(operand, &mut candidates);
+        }
+        for phi in &block.phis {
+            for &(_, value) in &phi.arguments {
+                escape(value, &mut candidates);
+            }
+        }
+    }
+    if !candidates.contains(&true) {
+        return candidates;
+    }
+
+    let tree = DominatorTree::new(function);
+    let frontiers = tree.frontiers();
+
+    // Phi-Knoten an den iterierten Dominanzgrenzen der schreibenden Blöcke
+    let mut phi_slots: HashMap<Value, usize> = HashMap::new();
+    for (slot, _) in candidates.iter().enumerate().filter(|(_, &candidate)| candidate) {
+        let ty = function.slots[slot].value_type(types).unwrap_or(Type::Int);
+        let mut definitions: Vec<BlockId> = function
+            .block_ids()
+            .filter(|&block| {
+                function.block(block).instructions.iter().any(|instruction| {
+                    matches!(&instruction.kind, InstKind::Store { address, .. } if addresses.get(address) == Some(&slot))
+                })
+            })
+            .collect();
+        let mut has_phi: HashSet<BlockId> = HashSet::new();
+        let mut defined: HashSet<BlockId> = definitions.iter().copied().collect();
+        while let Some(block) = definitions.pop() {
+            for &frontier in &frontiers[block.index()] {
+                if has_phi.insert(frontier) {
+                    let dest = function.new_value(ty);
+                    function.blocks[frontier.index()].phis.push(Phi { dest, arguments: Vec::new() });
+                    phi_slots.insert(dest, slot);
+                    if defined.insert(frontier) {
+                        definitions.push(frontier);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut renamer = Renamer {
+        depth,
+        candidates: &candidates,
+        addresses: &addresses,
+        phi_slots: &phi_slots,
+        stacks: vec![Vec::new(); function.slots.len()],
+        replacements: HashMap::new(),
+        zeros: HashMap::new(),
+        initial: Vec::new(),
+    };
+    renamer.rename(function, &tree, BlockId::ENTRY, types);
+
+    // Nullwerte für Slots, die vor dem ersten Schreiben gelesen werden
+    let Renamer { replacements, initial, .. } = renamer;
+    let entry = &mut function.blocks[BlockId::ENTRY.index()];
+    entry.instructions.splice(0..0, initial);
+
+    let resolve = |mut value: Value| {
+        while let Some(&replacement) = replacements.get(&value) {
+            value = replacement;
+        }
+        value
+    };
+    for block in &mut function.blocks {
+        for phi in &mut block.phis {
+            for argument in &mut phi.arguments {
+                argument.1 = resolve(argument.1);
+            }
+        }
+        for instruction in &mut block.instructions {
+            instruction.kind.map_operands(resolve);
+        }
+        block.terminator.map_operands(resolve);
+    }
+
+    remove_dead_phis(function);
+    candidates
+}
+
+struct Renamer<'a> {
+    depth: usize,
+    candidates: &'a [bool],
+    addresses: &'a HashMap<Value, usize>,
+    phi_slots: &'a HashMap<Value, usize>,
+    // Aktueller Wert jedes Slots entlang des Dominatorbaums
+    stacks: Vec<Vec<Value>>,
+    replacements: HashMap<Value, Value>,
+    zeros: HashMap<usize, Value>,
+    initial: Vec<Instruction>,
+}
+
+impl Renamer<'_> {
+    fn promoted(&self, address: &Value) -> Option<usize> {
+        self.addresses.get(address).copied().filter(|&slot| self.candidates[slot])
+    }
+
+    fn current(&mut self, function: &mut Function, slot: usize, types: &TypeArena) -> Value {
+        if let Some(&value) = self.stacks[slot].last() {
+            return value;
+        }
+        if let Some(&zero) = self.zeros.get(&slot) {
+            return zero;
+        }
+        let ty = function.slots[slot].value_type(types).unwrap_or(Type::Int);
+        let constant = match ty {
+            Type::Int => Constant::Int(0),
+            Type::Real => Constant::Real(0.0),
+            Type::Bool => Constant::Bool(false),
+            Type::Char => Constant::Char(0),
+            Type::Set => Constant::Set(0),
+            Type::Ptr => Constant::Nil,
+        };
+        let dest = function.new_value(ty);
+        self.initial.push(Instruction { dest: Some(dest), kind: InstKind::Const(constant), line: 0, span: Span::default(), origin: None });
+        self.zeros.insert(slot, dest);
+        dest
+    }
+
+    fn rename(&mut self, function: &mut Function, tree: &DominatorTree, block: BlockId, types: &TypeArena) {
+        let mut pushed = Vec::new();
+        for phi in &function.blocks[block.index()].phis {
+            if let Some(&slot) = self.phi_slots.get(&phi.dest) {
+                self.stacks[slot].push(phi.dest);
+                pushed.push(slot);
+            }
+        }
+
+        let instructions = std::mem::take(&mut function.blocks[block.index()].instructions);
+        let mut kept = Vec::with_capacity(instructions.len());
+        for instruction in instructions {
+            match &instruction.kind {
+                InstKind::Slot { depth, index } if *depth == self.depth && self.candidates[*index] => {}
+                InstKind::Load { address, .. } if self.promoted(address).is_some() => {
+                    let slot = self.promoted(address).unwrap_or_default();
+                    let value = self.current(function, slot, types);
+                    if let Some(dest) = instruction.dest {
+                        self.replacements.insert(dest, value);
+                    }
+                }
+                InstKind::Store { address, value, .. } if self.promoted(address).is_some() => {
+                    let slot = self.promoted(address).unwrap_or_default();
+                    self.stacks[slot].push(*value);
+                    pushed.push(slot);
+                }
+                _ => kept.push(instruction),
+            }
+        }
+        function.blocks[block.index()].instructions = kept;
+
+        let mut seen = HashSet::new();
+        for successor in function.block(block).terminator.successors() {
+            if !seen.insert(successor) {
+                continue;
+            }
+            for phi_index in 0..function.blocks[successor.index()].phis.len() {
+                let dest = function.blocks[successor.index()].phis[phi_index].dest;
+                if let Some(&slot) = self.phi_slots.get(&dest) {
+                    let value = self.current(function, slot, types);
+                    function.blocks[successor.index()].phis[phi_index].arguments.push((block, value));
+                }
+            }
+        }
+
+        for &child in tree.children(block) {
+            self.rename(function, tree, child, types);
+        }
+        for slot in pushed {
+            self.stacks[slot].pop();
+        }
+    }
+}
+
+// Phi-Knoten, deren Ergebnis nur von ihnen selbst oder gar nicht benutzt wird
+fn remove_dead_phis(function: &mut Function) {
+    loop {
+        let mut used: HashSet<Value> = HashSet::new();
+        for block in &function.blocks {
+            for phi in &block.phis {
+                used.extend(phi.arguments.iter().map(|&(_, value)| value).filter(|&value| value != phi.dest));
+            }
+            for instruction in &block.instructions {
+                used.extend(instruction.kind.operands());
+            }
+            used.extend(block.terminator.operands());
+        }
+        let mut removed = false;
+        for block in &mut function.blocks {
+            let before = block.phis.len();
+            block.phis.retain(|phi| used.contains(&phi.dest));
+            removed |= block.phis.len() != before;
+        }
+        if !removed {
+            break;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use super::ssa::DominatorTree;
+use super::{BlockId, Callee, Function, InstKind, Program, Terminator, Type, Value};
+
+// ============================================================================
+// Prüfung der IR
+// ============================================================================
+//
+// Strukturelle Regeln, auf die sich Optimierungen und Backends verlassen:
+// jeder Wert wird genau einmal definiert und seine Definition dominiert jede
+// Verwendung, Phi-Knoten haben genau ein Argument je Vorgänger, Sprungziele
+// existieren und alle Blöcke sind erreichbar.
+
+pub fn verify(program: &Program) -> Result<(), String> {
+    for function in &program.functions {
+        verify_function(function).map_err(|e| format!("{}: {}", function.name, e))?;
+    }
+    Ok(())
+}
+
+// Ort einer Definition: Block und Position (0 = Parameter oder Phi-Knoten,
+// Befehl i an Position i + 1)
+type Location = (BlockId, usize);
+
+fn verify_function(function: &Function) -> Result<(), String> {
+    if function.blocks.is_empty() {
+        return Err("Funktion ohne Blöcke".to_string());
+    }
+    for id in function.block_ids() {
+        for successor in function.block(id).terminator.successors() {
+            if successor.index() >= function.blocks.len() {
+                return Err(format!("{} springt zum unbekannten Block {}", id, successor));
+            }
+        }
+    }
+
+    let tree = DominatorTree::new(function);
+    if let Some(id) = function.block_ids().find(|&id| !tree.is_reachable(id)) {
+        return Err(format!("{} ist nicht erreichbar", id));
+    }
+
+    // Definitionen
+    let mut definitions: Vec<Option<Location>> = vec![None; function.values.len()];
+    let mut define = |value: Value, location: Location| -> Result<(), String> {
+        let slot = definitions.get_mut(value.index()).ok_or_else(|| format!("{} hat keinen Typ", value))?;
+        if slot.is_some() {
+            return Err(format!("{} ist mehrfach definiert", value));
+        }
+        *slot = Some(location);
+        Ok(())
+    };
+    for param in &function.params {
+        define(param.value, (BlockId::ENTRY, 0))?;
+    }
+    for id in function.block_ids() {
+        let block = function.block(id);
+        for phi in &block.phis {
+            define(phi.dest, (id, 0))?;
+        }
+        for (index, instruction) in block.instructions.iter().enumerate() {
+            let produces = produces_value(&instruction.kind);
+            match (instruction.dest, produces) {
+                (Some(dest), Some(false)) => return Err(format!("{} erhält keinen Wert", dest)),
+                (None, Some(true)) => return Err(format!("Ergebnis eines Befehls in {} fehlt", id)),
+                (Some(dest), _) => define(dest, (id, index + 1))?,
+                (None, _) => {}
+            }
+        }
+    }
+
+    // Verwendungen
+    let check = |value: Value, block: BlockId, position: usize| -> Result<(), String> {
+        let (definition, defined_at) = definitions
+            .get(value.index())
+            .copied()
+            .flatten()
+            .ok_or_else(|| format!("{} wird in {} benutzt, aber nie definiert", value, block))?;
+        let dominated = if definition == block { defined_at < position } else { tree.dominates(definition, block) };
+        if !dominated {
+            return Err(format!("Definition von {} dominiert die Verwendung in {} nicht", value, block));
+        }
+        Ok(())
+    };
+    let expect = |value: Value, ty: Type, context: &str| -> Result<(), String> {
+        let actual = function.value_type(value);
+        if actual != ty {
+            return Err(format!("{} in {} hat Typ {} statt {}", value, context, actual.name(), ty.name()));
+        }
+        Ok(())
+    };
+
+    for id in function.block_ids() {
+        let block = function.block(id);
+        let mut predecessors = tree.predecessors(id).to_vec();
+        predecessors.sort();
+        for phi in &block.phis {
+            let mut sources: Vec<BlockId> = phi.arguments.iter().map(|&(source, _)| source).collect();
+            sources.sort();
+            if sources != predecessors {
+                return Err(format!("Phi-Knoten {} in {} passt nicht zu den Vorgängern", phi.dest, id));
+            }
+            for &(source, value) in &phi.arguments {
+                check(value, source, usize::MAX)?;
+                expect(value, function.value_type(phi.dest), "Phi-Knoten")?;
+            }
+        }
+        for (index, instruction) in block.instructions.iter().enumerate() {
+            for operand in instruction.kind.operands() {
+                check(operand, id, index + 1)?;
+            }
+            match &instruction.kind {
+                InstKind::Load { address, .. } => expect(*address, Type::Ptr, "load")?,
+                InstKind::Store { ty, address, value } => {
+                    expect(*address, Type::Ptr, "store")?;
+                    expect(*value, *ty, "store")?;
+                }
+                InstKind::Field { base, .. } | InstKind::Element { base, .. } => expect(*base, Type::Ptr, "Adressrechnung")?,
+                InstKind::Call { callee: Callee::Indirect(target), .. } => expect(*target, Type::Ptr, "call")?,
+                InstKind::Assert(condition) => expect(*condition, Type::Bool, "assert")?,
+                _ => {}
+            }
+        }
+        for operand in block.terminator.operands() {
+            check(operand, id, usize::MAX)?;
+        }
+        match &block.terminator {
+            Terminator::Branch { condition, .. } => expect(*condition, Type::Bool, "branch")?,
+            Terminator::Return(value) => match (value, function.result) {
+                (Some(value), Some(ty)) => expect(*value, ty, "return")?,
+                (None, None) => {}
+                _ => return Err(format!("Rückgabe in {} passt nicht zum Ergebnistyp", id)),
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Some(true): Befehl liefert immer einen Wert, Some(false): nie, None: je
+// nach Aufruf
+fn produces_value(kind: &InstKind) -> Option<bool> {
+    match kind {
+        InstKind::Store { .. }
+        | InstKind::Copy { .. }
+        | InstKind::CopyString { .. }
+        | InstKind::InitDescriptors { .. }
+        | InstKind::Output { .. }
+        | InstKind::CheckIndex { .. }
+        | InstKind::CheckNil(_)
+        | InstKind::Assert(_) => Some(false),
+        InstKind::Call { .. } => None,
+        _ => Some(true),
+    }
+}
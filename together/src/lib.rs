@@ -0,0 +1,19 @@
+pub mod scanner;
+pub mod parser;
+pub mod semantic;
+pub mod ir;
+pub mod codegen;
+pub mod bytecode;
+pub mod wasm;
+pub mod riscv;
+pub mod interpreter;
+pub mod vm;
+pub mod doc;
+pub mod fmt;
+pub mod lint;
+pub mod lsp;
+pub mod repl;
+pub mod cli;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,89 @@
+use super::Severity;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Projektweite Lint-Konfiguration, eine Einstellung pro Zeile:
+//
+//   # Kommentar
+//   magic-number = off
+//   procedure-length = error
+//   procedure-length.max-lines = 40
+//
+// `<regel> = off|warning|error` setzt die Schwere, `<regel>.<option> = <wert>`
+// eine Option der Regel.
+
+pub const CONFIG_FILE_NAME: &str = "together.lint";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    severities: HashMap<String, Severity>,
+    options: HashMap<String, String>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = LintConfig::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("Zeile {}: '=' erwartet", i + 1));
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            if key.contains('.') {
+                config.options.insert(key.to_string(), value.to_string());
+            } else {
+                let severity = Severity::from_name(value)
+                    .ok_or_else(|| format!("Zeile {}: unbekannte Schwere '{}'", i + 1, value))?;
+                config.severities.insert(key.to_string(), severity);
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Kann '{}' nicht lesen: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Sucht die Konfigurationsdatei im Verzeichnis `start` und dessen Elternverzeichnissen
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    pub fn severity(&self, rule: &str) -> Option<Severity> {
+        self.severities.get(rule).copied()
+    }
+
+    pub fn option(&self, rule: &str, name: &str) -> Option<&str> {
+        self.options.get(&format!("{}.{}", rule, name)).map(String::as_str)
+    }
+
+    // Alle in der Konfiguration genannten Regelnamen (zur Prüfung auf Tippfehler)
+    pub fn rule_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .severities
+            .keys()
+            .map(String::as_str)
+            .chain(self.options.keys().filter_map(|key| key.split('.').next()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
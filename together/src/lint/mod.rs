@@ -0,0 +1,254 @@
+pub mod config;
+pub mod rules;
+
+use crate::parser::ast::Module;
+use crate::scanner::Comment;
+use std::collections::HashSet;
+use std::fmt;
+
+pub use config::LintConfig;
+pub use rules::Rule;
+
+// ============================================================================
+// Lint: Stil- und Strukturprüfungen über dem AST
+// ============================================================================
+//
+// Jede Regel meldet Befunde mit Zeilennummer; Schwere und Optionen kommen aus
+// der Projektkonfiguration (together.lint). Kommentare der Form
+//
+//   (* lint:allow magic-number, shadowing *)   diese und die folgende Zeile
+//   (* lint:allow-file empty-if *)             die ganze Datei
+//
+// unterdrücken einzelne Regeln, `all` steht für alle Regeln.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Off => "off",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Severity> {
+        [Severity::Off, Severity::Warning, Severity::Error]
+            .into_iter()
+            .find(|severity| severity.name() == name)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Off => write!(f, "aus"),
+            Severity::Warning => write!(f, "Warnung"),
+            Severity::Error => write!(f, "Fehler"),
+        }
+    }
+}
+
+// Befund einer Regel, bevor Schwere und Unterdrückung angewendet werden
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(line: usize, message: String) -> Self {
+        Finding { line, message }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {} [{}]", self.line, self.severity, self.message, self.rule)
+    }
+}
+
+// ============================================================================
+// Unterdrückung per Kommentar
+// ============================================================================
+
+#[derive(Debug, Default)]
+struct Suppressions {
+    // (Regel, Zeile); Regel "all" gilt für alle
+    lines: HashSet<(String, usize)>,
+    file: HashSet<String>,
+}
+
+impl Suppressions {
+    fn from_comments(comments: &[Comment]) -> Self {
+        let mut suppressions = Suppressions::default();
+
+        for comment in comments {
+            let text = comment.text.trim();
+            let (whole_file, rules) = if let Some(rules) = text.strip_prefix("lint:allow-file") {
+                (true, rules)
+            } else if let Some(rules) = text.strip_prefix("lint:allow") {
+                (false, rules)
+            } else {
+                continue;
+            };
+
+            let rules = rules
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|rule| !rule.is_empty());
+            for rule in rules {
+                if whole_file {
+                    suppressions.file.insert(rule.to_string());
+                } else {
+                    for line in comment.line..=comment.end_line + 1 {
+                        suppressions.lines.insert((rule.to_string(), line));
+                    }
+                }
+            }
+        }
+
+        suppressions
+    }
+
+    fn is_suppressed(&self, rule: &str, line: usize) -> bool {
+        [rule, "all"].iter().any(|name| {
+            self.file.contains(*name) || self.lines.contains(&(name.to_string(), line))
+        })
+    }
+}
+
+// ============================================================================
+// Linter
+// ============================================================================
+
+pub struct Linter {
+    rules: Vec<(Box<dyn Rule>, Severity)>,
+}
+
+impl Linter {
+    // Alle registrierten Regeln, konfiguriert nach `config`
+    pub fn new(config: &LintConfig) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for mut rule in rules::registry() {
+            rule.configure(config)?;
+            let severity = config.severity(rule.name()).unwrap_or(rule.default_severity());
+            rules.push((rule, severity));
+        }
+
+        for name in config.rule_names() {
+            if !rules.iter().any(|(rule, _)| rule.name() == name) {
+                return Err(format!("Unbekannte Lint-Regel '{}'", name));
+            }
+        }
+
+        Ok(Linter { rules })
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = (&dyn Rule, Severity)> {
+        self.rules.iter().map(|(rule, severity)| (rule.as_ref(), *severity))
+    }
+
+    // Befunde aller eingeschalteten Regeln, nach Zeile sortiert
+    pub fn lint(&self, module: &Module, comments: &[Comment]) -> Vec<Diagnostic> {
+        let suppressions = Suppressions::from_comments(comments);
+        let mut diagnostics = Vec::new();
+
+        for (rule, severity) in &self.rules {
+            if *severity == Severity::Off {
+                continue;
+            }
+
+            for finding in rule.check(module) {
+                if suppressions.is_suppressed(rule.name(), finding.line) {
+                    continue;
+                }
+                diagnostics.push(Diagnostic {
+                    rule: rule.name(),
+                    severity: *severity,
+                    line: finding.line,
+                    message: finding.message,
+                });
+            }
+        }
+
+        diagnostics.sort_by(|a, b| a.line.cmp(&b.line).then(a.rule.cmp(b.rule)));
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::{comments, Lexer};
+
+    fn lint(source: &str, config: &str) -> Vec<Diagnostic> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let comments = comments(&tokens);
+        let module = Parser::new(tokens).parse().unwrap();
+        let linter = Linter::new(&LintConfig::parse(config).unwrap()).unwrap();
+        linter.lint(&module, &comments)
+    }
+
+    fn found(diagnostics: &[Diagnostic]) -> Vec<(&'static str, usize)> {
+        diagnostics.iter().map(|d| (d.rule, d.line)).collect()
+    }
+
+    const SOURCE: &str = "MODULE T;
+VAR count: INTEGER;
+PROCEDURE add*(count: INTEGER): INTEGER;
+  VAR x: INTEGER;
+BEGIN
+  x := count * 42;
+  IF x > 0 THEN END
+  RETURN x
+END add;
+END T.";
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(
+            found(&lint(SOURCE, "procedure-length.max-lines = 5")),
+            vec![
+                ("export-case", 3),
+                ("procedure-length", 3),
+                ("shadowing", 3),
+                ("magic-number", 6),
+                ("empty-if", 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_configuration() {
+        let diagnostics = lint(SOURCE, "# Projekt\nshadowing = error\nmagic-number = off\nexport-case=off\n");
+        assert_eq!(found(&diagnostics), vec![("shadowing", 3), ("empty-if", 7)]);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+
+        assert!(LintConfig::parse("shadowing = laut").is_err());
+        assert!(Linter::new(&LintConfig::parse("shadowin = off").unwrap()).is_err());
+        assert!(Linter::new(&LintConfig::parse("magic-number.allowed = x").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_suppression() {
+        let source = SOURCE
+            .replace("  x := count", "  (* lint:allow magic-number *)\n  x := count")
+            .replace("MODULE T;", "MODULE T; (* lint:allow-file export-case, empty-if *)");
+        assert_eq!(found(&lint(&source, "")), vec![("shadowing", 3)]);
+    }
+}
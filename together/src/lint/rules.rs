@@ -0,0 +1,514 @@
+use super::{Finding, LintConfig, Severity};
+use crate::parser::ast::*;
+use crate::semantic::{Symbol, SymbolKind, SymbolTable, TypeId};
+
+// ============================================================================
+// Regeln
+// ============================================================================
+
+pub trait Rule {
+    // Name für Konfiguration, Unterdrückung und Ausgabe
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    // Liest die Optionen der Regel aus der Projektkonfiguration
+    fn configure(&mut self, _config: &LintConfig) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn check(&self, module: &Module) -> Vec<Finding>;
+}
+
+// Alle bekannten Regeln in Ausgabereihenfolge
+pub fn registry() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(Shadowing),
+        Box::new(ProcedureLength { max_lines: 60 }),
+        Box::new(MagicNumber { allowed: vec![0.0, 1.0] }),
+        Box::new(ExportCase),
+        Box::new(EmptyIf),
+    ]
+}
+
+// ============================================================================
+// shadowing: lokale Deklaration verdeckt einen Bezeichner eines äußeren Scopes
+// ============================================================================
+
+struct Shadowing;
+
+impl Shadowing {
+    // Für die Regel zählen nur Name und Position, die Art ist ein Platzhalter
+    fn declare(table: &mut SymbolTable, name: &str, line: usize, column: usize) {
+        table
+            .define(Symbol {
+                name: name.to_string(),
+                kind: SymbolKind::Variable {
+                    var_type: TypeId::NIL,
+                    is_parameter: false,
+                    is_var_param: false,
+                },
+                exported: ExportMark::None,
+                defined_at: Some((line, column)),
+            })
+            .ok();
+    }
+
+    fn check_procedure(&self, proc: &ProcedureDeclaration, table: &mut SymbolTable, findings: &mut Vec<Finding>) {
+        table.enter_scope();
+
+        let mut declarations: Vec<(&str, usize, usize)> = Vec::new();
+        if let Some(params) = &proc.params {
+            for section in &params.sections {
+                declarations.extend(
+                    section
+                        .names
+                        .iter()
+                        .zip(&section.positions)
+                        .map(|(name, &(line, column))| (name.as_str(), line, column)),
+                );
+            }
+        }
+        declarations.extend(
+            declared_names(&proc.declarations)
+                .into_iter()
+                .map(|ident| (ident.name.as_str(), ident.line, ident.column)),
+        );
+
+        for (name, line, column) in declarations {
+            if table.lookup_in_current_scope(name).is_none() {
+                if let Some(outer) = table.lookup(name) {
+                    let message = match outer.defined_at {
+                        Some((outer_line, _)) => format!(
+                            "'{}' in '{}' verdeckt den Bezeichner aus Zeile {}",
+                            name, proc.name.name, outer_line
+                        ),
+                        None => format!(
+                            "'{}' in '{}' verdeckt einen vordefinierten Bezeichner",
+                            name, proc.name.name
+                        ),
+                    };
+                    findings.push(Finding::new(line, message));
+                }
+            }
+            Self::declare(table, name, line, column);
+        }
+
+        for nested in &proc.declarations.procedures {
+            self.check_procedure(nested, table, findings);
+        }
+
+        table.exit_scope();
+    }
+}
+
+impl Rule for Shadowing {
+    fn name(&self) -> &'static str {
+        "shadowing"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lokale Deklaration verdeckt einen Bezeichner eines umgebenden Scopes"
+    }
+
+    fn check(&self, module: &Module) -> Vec<Finding> {
+        let mut table = SymbolTable::new();
+        let mut findings = Vec::new();
+
+        for import in &module.imports {
+            let name = import.alias.as_ref().unwrap_or(&import.module_name);
+            Self::declare(&mut table, name, import.line, import.column);
+        }
+        for ident in declared_names(&module.declarations) {
+            Self::declare(&mut table, &ident.name, ident.line, ident.column);
+        }
+
+        for proc in &module.declarations.procedures {
+            self.check_procedure(proc, &mut table, &mut findings);
+        }
+        findings
+    }
+}
+
+// ============================================================================
+// procedure-length: Prozeduren mit zu vielen Zeilen
+// ============================================================================
+
+struct ProcedureLength {
+    max_lines: usize,
+}
+
+impl Rule for ProcedureLength {
+    fn name(&self) -> &'static str {
+        "procedure-length"
+    }
+
+    fn description(&self) -> &'static str {
+        "Prozedur ist länger als max-lines Zeilen (Standard 60)"
+    }
+
+    fn configure(&mut self, config: &LintConfig) -> Result<(), String> {
+        if let Some(value) = config.option(self.name(), "max-lines") {
+            self.max_lines = value
+                .parse()
+                .map_err(|_| format!("{}.max-lines: Zahl erwartet, gefunden '{}'", self.name(), value))?;
+        }
+        Ok(())
+    }
+
+    fn check(&self, module: &Module) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for_each_procedure(&module.declarations, &mut |proc| {
+            let lines = proc.end_line + 1 - proc.name.line;
+            if !proc.is_forward && lines > self.max_lines {
+                let message = format!(
+                    "Prozedur '{}' ist {} Zeilen lang (höchstens {})",
+                    proc.name.name, lines, self.max_lines
+                );
+                findings.push(Finding::new(proc.name.line, message));
+            }
+        });
+        findings
+    }
+}
+
+// ============================================================================
+// magic-number: Zahlenliterale in Anweisungen statt benannter Konstanten
+// ============================================================================
+
+struct MagicNumber {
+    allowed: Vec<f64>,
+}
+
+impl MagicNumber {
+    fn check_expression(&self, expr: &Expression, line: usize, findings: &mut Vec<Finding>) {
+        walk_expression(expr, &mut |expr| {
+            let value = match expr {
+                Expression::IntegerLiteral(value) => *value as f64,
+                Expression::RealLiteral(value) => *value,
+                _ => return,
+            };
+            if !self.allowed.contains(&value) {
+                let message = format!("Magische Zahl {} - besser als Konstante deklarieren", value);
+                findings.push(Finding::new(line, message));
+            }
+        });
+    }
+
+    fn check_statements(&self, statements: &[Statement], findings: &mut Vec<Finding>) {
+        walk_statements(statements, &mut |stmt| {
+            for expr in statement_expressions(stmt) {
+                self.check_expression(expr, stmt.line(), findings);
+            }
+        });
+    }
+}
+
+impl Rule for MagicNumber {
+    fn name(&self) -> &'static str {
+        "magic-number"
+    }
+
+    fn description(&self) -> &'static str {
+        "Zahlenliteral in einer Anweisung statt einer benannten Konstante (erlaubt: allowed, Standard 0, 1)"
+    }
+
+    fn configure(&mut self, config: &LintConfig) -> Result<(), String> {
+        if let Some(value) = config.option(self.name(), "allowed") {
+            self.allowed = value
+                .split(',')
+                .map(|number| number.trim())
+                .filter(|number| !number.is_empty())
+                .map(|number| {
+                    number
+                        .parse()
+                        .map_err(|_| format!("{}.allowed: Zahl erwartet, gefunden '{}'", self.name(), number))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(())
+    }
+
+    fn check(&self, module: &Module) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for_each_procedure(&module.declarations, &mut |proc| {
+            if let Some(body) = &proc.body {
+                self.check_statements(body, &mut findings);
+            }
+            // RETURN steht unmittelbar vor dem abschließenden END
+            if let Some(return_expr) = &proc.return_expr {
+                self.check_expression(return_expr, proc.end_line, &mut findings);
+            }
+        });
+        if let Some(body) = &module.body {
+            self.check_statements(body, &mut findings);
+        }
+        findings
+    }
+}
+
+// ============================================================================
+// export-case: Schreibweise exportierter Namen
+// ============================================================================
+
+// Exportierte Prozeduren und Typen beginnen mit einem Großbuchstaben,
+// exportierte Variablen und Recordfelder mit einem Kleinbuchstaben
+struct ExportCase;
+
+impl ExportCase {
+    fn check_ident(ident: &IdentDef, what: &str, upper: bool, findings: &mut Vec<Finding>) {
+        if ident.exported == ExportMark::None {
+            return;
+        }
+        let Some(first) = ident.name.chars().next() else {
+            return;
+        };
+
+        if upper && !first.is_uppercase() {
+            let message = format!("Exportierte{} '{}' sollte mit einem Großbuchstaben beginnen", what, ident.name);
+            findings.push(Finding::new(ident.line, message));
+        } else if !upper && !first.is_lowercase() {
+            let message = format!("Exportierte{} '{}' sollte mit einem Kleinbuchstaben beginnen", what, ident.name);
+            findings.push(Finding::new(ident.line, message));
+        }
+    }
+
+    fn check_fields(ty: &Type, findings: &mut Vec<Finding>) {
+        match ty {
+            Type::Record { fields, .. } => {
+                for field_list in fields {
+                    for name in &field_list.names {
+                        Self::check_ident(name, "s Feld", false, findings);
+                    }
+                    Self::check_fields(&field_list.field_type, findings);
+                }
+            }
+            Type::Array { element_type, .. } => Self::check_fields(element_type, findings),
+            Type::Pointer { target_type } => Self::check_fields(target_type, findings),
+            Type::Qualident(_) | Type::Procedure { .. } => {}
+        }
+    }
+
+    fn check_declarations(decls: &DeclSequence, findings: &mut Vec<Finding>) {
+        for type_decl in &decls.types {
+            Self::check_ident(&type_decl.name, "r Typ", true, findings);
+            Self::check_fields(&type_decl.type_def, findings);
+        }
+        for var_decl in &decls.variables {
+            for name in &var_decl.names {
+                Self::check_ident(name, " Variable", false, findings);
+            }
+            Self::check_fields(&var_decl.var_type, findings);
+        }
+        for proc in &decls.procedures {
+            Self::check_ident(&proc.name, " Prozedur", true, findings);
+        }
+    }
+}
+
+impl Rule for ExportCase {
+    fn name(&self) -> &'static str {
+        "export-case"
+    }
+
+    fn description(&self) -> &'static str {
+        "Exportierte Prozeduren und Typen groß, exportierte Variablen und Felder klein schreiben"
+    }
+
+    fn check(&self, module: &Module) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        Self::check_declarations(&module.declarations, &mut findings);
+        findings
+    }
+}
+
+// ============================================================================
+// empty-if: IF-, ELSIF- oder ELSE-Zweig ohne Anweisungen
+// ============================================================================
+
+struct EmptyIf;
+
+impl EmptyIf {
+    fn check_statements(statements: &[Statement], findings: &mut Vec<Finding>) {
+        walk_statements(statements, &mut |stmt| {
+            let Statement::If { line, then_body, elsif_parts, else_body, .. } = stmt else {
+                return;
+            };
+
+            let mut branches = vec![("THEN", then_body)];
+            branches.extend(elsif_parts.iter().map(|(_, body)| ("ELSIF", body)));
+            branches.extend(else_body.iter().map(|body| ("ELSE", body)));
+
+            for (branch, body) in branches {
+                if is_empty(body) {
+                    findings.push(Finding::new(*line, format!("Leerer {}-Zweig", branch)));
+                }
+            }
+        });
+    }
+}
+
+impl Rule for EmptyIf {
+    fn name(&self) -> &'static str {
+        "empty-if"
+    }
+
+    fn description(&self) -> &'static str {
+        "IF-Anweisung mit leerem Zweig"
+    }
+
+    fn check(&self, module: &Module) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for_each_procedure(&module.declarations, &mut |proc| {
+            if let Some(body) = &proc.body {
+                Self::check_statements(body, &mut findings);
+            }
+        });
+        if let Some(body) = &module.body {
+            Self::check_statements(body, &mut findings);
+        }
+        findings
+    }
+}
+
+// ============================================================================
+// Hilfsfunktionen zum Durchlaufen des AST
+// ============================================================================
+
+// Konstanten, Typen, Variablen und Prozeduren einer Deklarationsfolge
+fn declared_names(decls: &DeclSequence) -> Vec<&IdentDef> {
+    let mut names: Vec<&IdentDef> = Vec::new();
+    names.extend(decls.constants.iter().map(|decl| &decl.name));
+    names.extend(decls.types.iter().map(|decl| &decl.name));
+    names.extend(decls.variables.iter().flat_map(|decl| decl.names.iter()));
+    names.extend(decls.procedures.iter().map(|decl| &decl.name));
+    names
+}
+
+// Alle Prozeduren einschließlich geschachtelter
+fn for_each_procedure<'a>(decls: &'a DeclSequence, f: &mut impl FnMut(&'a ProcedureDeclaration)) {
+    for proc in &decls.procedures {
+        f(proc);
+        for_each_procedure(&proc.declarations, f);
+    }
+}
+
+// Alle Anweisungen einschließlich der in IF, CASE und Schleifen geschachtelten
+fn walk_statements(statements: &[Statement], f: &mut impl FnMut(&Statement)) {
+    for stmt in statements {
+        f(stmt);
+        match stmt {
+            Statement::If { then_body, elsif_parts, else_body, .. } => {
+                walk_statements(then_body, f);
+                for (_, body) in elsif_parts {
+                    walk_statements(body, f);
+                }
+                if let Some(body) = else_body {
+                    walk_statements(body, f);
+                }
+            }
+            Statement::Case { cases, else_body, .. } => {
+                for clause in cases {
+                    walk_statements(&clause.body, f);
+                }
+                if let Some(body) = else_body {
+                    walk_statements(body, f);
+                }
+            }
+            Statement::While { body, elsif_parts, .. } => {
+                walk_statements(body, f);
+                for (_, body) in elsif_parts {
+                    walk_statements(body, f);
+                }
+            }
+            Statement::Repeat { body, .. } | Statement::For { body, .. } => walk_statements(body, f),
+            Statement::Assignment { .. } | Statement::ProcedureCall { .. } | Statement::Empty => {}
+        }
+    }
+}
+
+// Ausdrücke, die direkt zur Anweisung gehören (ohne geschachtelte Anweisungen
+// und ohne CASE-Marken)
+fn statement_expressions(stmt: &Statement) -> Vec<&Expression> {
+    let mut exprs = Vec::new();
+    match stmt {
+        Statement::Assignment { target, value, .. } => {
+            exprs.extend(designator_expressions(target));
+            exprs.push(value);
+        }
+        Statement::ProcedureCall { designator, arguments, .. } => {
+            exprs.extend(designator_expressions(designator));
+            exprs.extend(arguments);
+        }
+        Statement::If { condition, elsif_parts, .. } | Statement::While { condition, elsif_parts, .. } => {
+            exprs.push(condition);
+            exprs.extend(elsif_parts.iter().map(|(condition, _)| condition));
+        }
+        Statement::Case { expr, .. } => exprs.push(expr),
+        Statement::Repeat { condition, .. } => exprs.push(condition),
+        Statement::For { start, end, step, .. } => {
+            exprs.push(start);
+            exprs.push(end);
+            exprs.extend(step);
+        }
+        Statement::Empty => {}
+    }
+    exprs
+}
+
+fn designator_expressions(designator: &Designator) -> Vec<&Expression> {
+    designator
+        .selectors
+        .iter()
+        .flat_map(|selector| match selector {
+            Selector::Index(indices) => indices.iter().collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+// Der Ausdruck selbst und alle Teilausdrücke
+fn walk_expression(expr: &Expression, f: &mut impl FnMut(&Expression)) {
+    f(expr);
+    match expr {
+        Expression::Binary { left, right, .. } => {
+            walk_expression(left, f);
+            walk_expression(right, f);
+        }
+        Expression::Unary { expr, .. } => walk_expression(expr, f),
+        Expression::Set(elements) => {
+            for element in elements {
+                walk_expression(&element.start, f);
+                if let Some(end) = &element.end {
+                    walk_expression(end, f);
+                }
+            }
+        }
+        Expression::Designator(designator) => {
+            for index in designator_expressions(designator) {
+                walk_expression(index, f);
+            }
+        }
+        Expression::FunctionCall { designator, arguments } => {
+            for index in designator_expressions(designator) {
+                walk_expression(index, f);
+            }
+            for arg in arguments {
+                walk_expression(arg, f);
+            }
+        }
+        Expression::IntegerLiteral(_)
+        | Expression::RealLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::Nil => {}
+    }
+}
+
+fn is_empty(statements: &[Statement]) -> bool {
+    statements.iter().all(|stmt| matches!(stmt, Statement::Empty))
+}
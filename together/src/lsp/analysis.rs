@@ -0,0 +1,772 @@
+use crate::parser::ast::{DeclSequence, IdentDef, Module, Type};
+use crate::parser::Parser;
+use crate::scanner::{Lexer, Token, TokenType};
+use crate::semantic::index::{Definition, DefinitionKind, Position, SymbolIndex};
+use crate::semantic::{ConstValue, SymbolKind, SymbolTable, TypeChecker, TypeId, TypeKind, WarningKind};
+
+// ============================================================================
+// Analyse eines Dokuments für den Language Server
+// ============================================================================
+//
+// Positionen sind 1-basiert wie im Scanner; die Umrechnung in LSP-Positionen
+// übernimmt der Server.
+
+// (Anfang, Ende), das Ende exklusiv
+pub type Range = (Position, Position);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub message: String,
+    // Kategorie bei Warnungen, None bei Fehlern
+    pub warning: Option<WarningKind>,
+}
+
+// Art eines Eintrags in der Gliederung oder der Vervollständigung
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Module,
+    Constant,
+    Type,
+    Variable,
+    Procedure,
+    Field,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: ItemKind,
+    // gesamte Deklaration
+    pub range: Range,
+    // nur der Name
+    pub selection: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: ItemKind,
+    pub detail: String,
+}
+
+pub struct Analysis {
+    source: String,
+    module: Option<Module>,
+    diagnostics: Vec<Diagnostic>,
+    index: SymbolIndex,
+}
+
+impl Analysis {
+    // Scannt, parst und prüft den Quelltext; Fehler werden als Diagnosen
+    // gesammelt, die Analyse läuft auf dem lesbaren Teil weiter
+    pub fn new(source: &str) -> Self {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.tokenize_tolerant();
+        let (module, syntax_errors) = Parser::new(tokens).parse_tolerant();
+
+        let mut diagnostics: Vec<Diagnostic> = lexer
+            .errors()
+            .iter()
+            .chain(&syntax_errors)
+            .map(|error| Diagnostic {
+                range: ((error.line, error.column), (error.line, error.column + 1)),
+                message: error.message.clone(),
+                warning: None,
+            })
+            .collect();
+
+        let mut index = SymbolIndex::new("");
+        if let Some(module) = &module {
+            let mut checker = TypeChecker::new();
+            // Die Fehler liefert located_errors, auch wenn check_module abbricht
+            checker.check_module(module).ok();
+            for (line, message) in checker.located_errors() {
+                diagnostics.push(Diagnostic {
+                    range: line_range(source, line.max(1)),
+                    message,
+                    warning: None,
+                });
+            }
+            for warning in checker.warnings() {
+                let range = match (warning.line, warning.column) {
+                    (0, _) => continue,
+                    (line, 0) => line_range(source, line),
+                    position => word_range(source, position),
+                };
+                diagnostics.push(Diagnostic {
+                    range,
+                    message: warning.message.clone(),
+                    warning: Some(warning.kind),
+                });
+            }
+            index = checker.index();
+        }
+
+        Analysis {
+            source: source.to_string(),
+            module,
+            diagnostics,
+            index,
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn index(&self) -> &SymbolIndex {
+        &self.index
+    }
+
+    // ========================================================================
+    // Navigation
+    // ========================================================================
+
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        self.index.definition_at(position).map(name_range)
+    }
+
+    // Verwendungen des Bezeichners an `position`, auf Wunsch mit der Deklaration
+    pub fn references(&self, position: Position, include_declaration: bool) -> Vec<Range> {
+        let Some(definition) = self.index.definition_at(position) else {
+            return Vec::new();
+        };
+
+        let mut ranges = Vec::new();
+        if include_declaration {
+            ranges.push(name_range(definition));
+        }
+        for reference in self.index.references_to(definition.position) {
+            ranges.push((reference, (reference.0, reference.1 + definition.name.chars().count())));
+        }
+        ranges
+    }
+
+    // Stellen, die beim Umbenennen zu ersetzen sind
+    pub fn rename(&self, position: Position, new_name: &str) -> Result<Vec<Range>, String> {
+        let definition = self
+            .index
+            .definition_at(position)
+            .ok_or("Kein umbenennbarer Bezeichner an dieser Stelle")?;
+        if definition.kind == DefinitionKind::Module {
+            return Err("Module können nicht umbenannt werden".to_string());
+        }
+        if !is_identifier(new_name) {
+            return Err(format!("'{}' ist kein gültiger Bezeichner", new_name));
+        }
+        if new_name != definition.name {
+            self.check_rename(definition, new_name)?;
+        }
+        Ok(self.references(position, true))
+    }
+
+    // Der neue Name darf weder mit einer Deklaration desselben Bereichs
+    // zusammenfallen noch an einer Verwendung eine andere Deklaration
+    // verdecken oder von ihr verdeckt werden
+    fn check_rename(&self, definition: &Definition, new_name: &str) -> Result<(), String> {
+        let conflict = |other: &Definition| {
+            let (line, column) = other.position;
+            Err(format!("'{}' ist bereits in Zeile {}, Spalte {} deklariert", new_name, line, column))
+        };
+
+        if let DefinitionKind::Field { record } = definition.kind {
+            let fields = self.index.fields(record);
+            return match fields.into_iter().find(|field| field.name == new_name) {
+                Some(field) => conflict(field),
+                None => Ok(()),
+            };
+        }
+
+        let is_field = |def: &Definition| matches!(def.kind, DefinitionKind::Field { .. });
+        let same_scope = self
+            .index
+            .definitions
+            .iter()
+            .find(|def| def.scope == definition.scope && def.name == new_name && !is_field(def));
+        if let Some(other) = same_scope {
+            return conflict(other);
+        }
+        if definition.scope == 0 && predefined_items().iter().any(|item| item.label == new_name) {
+            return Err(format!("'{}' ist vordefiniert und würde verdeckt", new_name));
+        }
+
+        // Verwendungen der Deklaration, die eine innere Deklaration des
+        // neuen Namens binden würde
+        for reference in self.index.references_to(definition.position) {
+            let inner = self.index.visible_at(reference.0).into_iter().find(|def| def.name == new_name);
+            if let Some(inner) = inner {
+                if self.encloses(definition.scope, inner.scope) {
+                    return conflict(inner);
+                }
+            }
+        }
+
+        // Verwendungen äußerer Deklarationen des neuen Namens im Bereich der
+        // umbenannten Deklaration
+        for reference in &self.index.references {
+            let Some(outer) = self.index.definition(reference.definition) else {
+                continue;
+            };
+            if outer.name == new_name
+                && !is_field(outer)
+                && outer.scope != definition.scope
+                && self.encloses(outer.scope, definition.scope)
+                && self.encloses(definition.scope, self.index.scope_at(reference.position.0))
+            {
+                return Err(format!(
+                    "'{}' würde die Deklaration in Zeile {} verdecken (verwendet in Zeile {})",
+                    new_name, outer.position.0, reference.position.0
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Liegt der Bereich `inner` in `outer` (oder ist er es selbst)?
+    fn encloses(&self, outer: usize, inner: usize) -> bool {
+        let mut scope = Some(inner);
+        while let Some(current) = scope {
+            if current == outer {
+                return true;
+            }
+            scope = self.index.scopes[current].parent;
+        }
+        false
+    }
+
+    // ========================================================================
+    // Hover
+    // ========================================================================
+
+    pub fn hover(&self, position: Position) -> Option<String> {
+        let definition = self.index.definition_at(position)?;
+        let name = &definition.name;
+        let ty = |id: Option<TypeId>| id.map_or(String::new(), |id| self.index.types.display(id));
+
+        Some(match &definition.kind {
+            DefinitionKind::Constant(value) => format!("CONST {} = {}", name, const_text(value)),
+            DefinitionKind::Type => match definition.ty {
+                Some(id) => format!("TYPE {} = {}", name, self.describe(id)),
+                None => format!("TYPE {}", name),
+            },
+            DefinitionKind::Variable => format!("VAR {}: {}", name, ty(definition.ty)),
+            DefinitionKind::Parameter { is_var: true } => format!("VAR-Parameter {}: {}", name, ty(definition.ty)),
+            DefinitionKind::Parameter { is_var: false } => format!("Parameter {}: {}", name, ty(definition.ty)),
+            DefinitionKind::Field { .. } => format!("Feld {}: {}", name, ty(definition.ty)),
+            DefinitionKind::Procedure => {
+                let signature = definition.ty.map_or(String::new(), |id| self.signature(id));
+                format!("PROCEDURE {}{}", name, signature)
+            }
+            DefinitionKind::Module => format!("IMPORT {}", name),
+        })
+    }
+
+    // Typ mit einer Ebene Struktur, auch wenn er einen Namen hat
+    fn describe(&self, id: TypeId) -> String {
+        let types = &self.index.types;
+        match types.kind(id) {
+            TypeKind::Array { length, element } => format!("ARRAY {} OF {}", length, types.display(*element)),
+            TypeKind::Pointer { target } => format!("POINTER TO {}", types.display(*target)),
+            TypeKind::Record { base, fields } => {
+                let mut text = "RECORD".to_string();
+                if let Some(base) = base {
+                    text.push_str(&format!(" ({})", types.display(*base)));
+                }
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name, types.display(field.ty)))
+                    .collect();
+                if !fields.is_empty() {
+                    text.push(' ');
+                    text.push_str(&fields.join("; "));
+                }
+                text + " END"
+            }
+            TypeKind::Procedure { .. } => format!("PROCEDURE{}", self.signature(id)),
+            _ => types.display(id),
+        }
+    }
+
+    // Parameterliste und Ergebnistyp eines Prozedurtyps
+    fn signature(&self, id: TypeId) -> String {
+        let types = &self.index.types;
+        let TypeKind::Procedure { params, result } = types.kind(id) else {
+            return String::new();
+        };
+
+        let params: Vec<String> = params
+            .iter()
+            .map(|param| {
+                let var = if param.is_var { "VAR " } else { "" };
+                format!("{}{}: {}", var, param.name, types.display(param.param_type))
+            })
+            .collect();
+        let mut text = format!("({})", params.join("; "));
+        if let Some(result) = result {
+            text.push_str(&format!(": {}", types.display(*result)));
+        }
+        text
+    }
+
+    // ========================================================================
+    // Gliederung
+    // ========================================================================
+
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        match &self.module {
+            Some(module) => decl_symbols(&module.declarations),
+            None => Vec::new(),
+        }
+    }
+
+    // ========================================================================
+    // Vervollständigung
+    // ========================================================================
+
+    // Nach `a.b.` die Felder des Records, sonst alle an der Stelle sichtbaren
+    // Bezeichner einschließlich der vordefinierten, jeweils passend zum
+    // bereits getippten Anfang
+    pub fn completion(&self, (line, column): Position) -> Vec<CompletionItem> {
+        let text: Vec<char> = self
+            .source
+            .lines()
+            .nth(line.saturating_sub(1))
+            .unwrap_or("")
+            .chars()
+            .take(column.saturating_sub(1))
+            .collect();
+
+        let prefix_start = identifier_start(&text, text.len());
+        let prefix: String = text[prefix_start..].iter().collect();
+
+        let mut items: Vec<CompletionItem> = if prefix_start > 0 && text[prefix_start - 1] == '.' {
+            match self.designator_type(&text[..prefix_start - 1], line) {
+                Some(record) => self
+                    .index
+                    .fields(record)
+                    .into_iter()
+                    .map(|field| self.completion_item(field))
+                    .collect(),
+                None => Vec::new(),
+            }
+        } else {
+            let mut items: Vec<CompletionItem> = self
+                .index
+                .visible_at(line)
+                .into_iter()
+                .map(|definition| self.completion_item(definition))
+                .collect();
+            let predefined: Vec<CompletionItem> = predefined_items()
+                .into_iter()
+                .filter(|item| !items.iter().any(|visible| visible.label == item.label))
+                .collect();
+            items.extend(predefined);
+            items
+        };
+
+        items.retain(|item| item.label.starts_with(&prefix));
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+        items
+    }
+
+    fn completion_item(&self, definition: &Definition) -> CompletionItem {
+        let kind = match definition.kind {
+            DefinitionKind::Constant(_) => ItemKind::Constant,
+            DefinitionKind::Type => ItemKind::Type,
+            DefinitionKind::Variable | DefinitionKind::Parameter { .. } => ItemKind::Variable,
+            DefinitionKind::Procedure => ItemKind::Procedure,
+            DefinitionKind::Field { .. } => ItemKind::Field,
+            DefinitionKind::Module => ItemKind::Module,
+        };
+        let detail = match (&definition.kind, definition.ty) {
+            (DefinitionKind::Procedure, Some(id)) => self.signature(id),
+            (DefinitionKind::Type, Some(id)) => self.describe(id),
+            (_, Some(id)) => self.index.types.display(id),
+            (_, None) => String::new(),
+        };
+        CompletionItem {
+            label: definition.name.clone(),
+            kind,
+            detail,
+        }
+    }
+
+    // Record-Typ des Bezeichners, der in `text` endet (`a`, `a.b[i]`, `p^` ...)
+    fn designator_type(&self, text: &[char], line: usize) -> Option<TypeId> {
+        let steps = designator_steps(text)?;
+        let types = &self.index.types;
+
+        let (base, selectors) = steps.split_first()?;
+        let Step::Name(base) = base else {
+            return None;
+        };
+        let definition = self
+            .index
+            .visible_at(line)
+            .into_iter()
+            .find(|definition| &definition.name == base)?;
+        if matches!(definition.kind, DefinitionKind::Module | DefinitionKind::Type) {
+            return None;
+        }
+        let mut current = definition.ty?;
+
+        for step in selectors {
+            current = match (step, types.kind(current)) {
+                (Step::Name(name), TypeKind::Record { .. }) => {
+                    self.index.fields(current).into_iter().find(|field| &field.name == name)?.ty?
+                }
+                (Step::Index, TypeKind::Array { element, .. }) => *element,
+                (Step::Dereference, TypeKind::Pointer { target }) => *target,
+                _ => return None,
+            };
+        }
+
+        matches!(types.kind(current), TypeKind::Record { .. }).then_some(current)
+    }
+}
+
+// ============================================================================
+// Hilfsfunktionen
+// ============================================================================
+
+fn name_range(definition: &Definition) -> Range {
+    let (line, column) = definition.position;
+    ((line, column), (line, column + definition.name.chars().count()))
+}
+
+fn ident_range(ident: &IdentDef) -> Range {
+    ((ident.line, ident.column), (ident.line, ident.column + ident.name.chars().count()))
+}
+
+// Die ganze Zeile ohne führende Leerzeichen
+fn line_range(source: &str, line: usize) -> Range {
+    let text = source.lines().nth(line - 1).unwrap_or("");
+    let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+    ((line, indent + 1), (line, text.chars().count() + 1))
+}
+
+// Das Wort (Bezeichner oder Schlüsselwort) ab `position`, mindestens ein Zeichen
+fn word_range(source: &str, (line, column): Position) -> Range {
+    let text = source.lines().nth(line - 1).unwrap_or("");
+    let length = text
+        .chars()
+        .skip(column - 1)
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .count();
+    ((line, column), (line, column + length.max(1)))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut lexer = Lexer::new(name);
+    matches!(
+        lexer.tokenize().as_deref(),
+        Ok([Token { token_type: TokenType::Identifier(_), .. }, Token { token_type: TokenType::Eof, .. }])
+    )
+}
+
+fn const_text(value: &ConstValue) -> String {
+    match value {
+        ConstValue::Integer(n) => n.to_string(),
+        ConstValue::Real(x) => format!("{:?}", x),
+        ConstValue::Boolean(true) => "TRUE".to_string(),
+        ConstValue::Boolean(false) => "FALSE".to_string(),
+        ConstValue::Char(c) if c.is_ascii_graphic() || *c == ' ' => format!("\"{}\"", c),
+        ConstValue::Char(c) => format!("{:X}X", *c as u32),
+        ConstValue::Set(bits) => {
+            let elements: Vec<String> = (0..32).filter(|i| bits & (1 << i) != 0).map(|i| i.to_string()).collect();
+            format!("{{{}}}", elements.join(", "))
+        }
+        ConstValue::String(text) => format!("\"{}\"", text),
+        ConstValue::Nil => "NIL".to_string(),
+    }
+}
+
+fn decl_symbols(decls: &DeclSequence) -> Vec<DocumentSymbol> {
+    let leaf = |ident: &IdentDef, kind: ItemKind, children: Vec<DocumentSymbol>| DocumentSymbol {
+        name: ident.name.clone(),
+        kind,
+        range: ident_range(ident),
+        selection: ident_range(ident),
+        children,
+    };
+
+    let mut symbols = Vec::new();
+    for constant in &decls.constants {
+        symbols.push(leaf(&constant.name, ItemKind::Constant, Vec::new()));
+    }
+    for type_decl in &decls.types {
+        let fields = match &type_decl.type_def {
+            Type::Record { fields, .. } => fields
+                .iter()
+                .flat_map(|field_list| &field_list.names)
+                .map(|name| leaf(name, ItemKind::Field, Vec::new()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        symbols.push(leaf(&type_decl.name, ItemKind::Type, fields));
+    }
+    for variable in &decls.variables {
+        for name in &variable.names {
+            symbols.push(leaf(name, ItemKind::Variable, Vec::new()));
+        }
+    }
+    for procedure in &decls.procedures {
+        let name = &procedure.name;
+        let end = (procedure.end_line, procedure.end_column + procedure.end_name.chars().count());
+        symbols.push(DocumentSymbol {
+            name: name.name.clone(),
+            kind: ItemKind::Procedure,
+            range: ((name.line, name.column), end.max(ident_range(name).1)),
+            selection: ident_range(name),
+            children: decl_symbols(&procedure.declarations),
+        });
+    }
+    symbols
+}
+
+// Vordefinierte Typen und Standardprozeduren
+fn predefined_items() -> Vec<CompletionItem> {
+    SymbolTable::new()
+        .current_scope_symbols()
+        .into_iter()
+        .filter_map(|symbol| {
+            let kind = match symbol.kind {
+                SymbolKind::Type { .. } => ItemKind::Type,
+                SymbolKind::Builtin(_) => ItemKind::Procedure,
+                SymbolKind::Constant { .. } => ItemKind::Constant,
+                _ => return None,
+            };
+            Some(CompletionItem {
+                label: symbol.name.clone(),
+                kind,
+                detail: String::new(),
+            })
+        })
+        .collect()
+}
+
+// Anfang des Bezeichners, der bei `end` endet
+fn identifier_start(text: &[char], end: usize) -> usize {
+    let mut start = end;
+    while start > 0 && text[start - 1].is_ascii_alphanumeric() {
+        start -= 1;
+    }
+    start
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Name(String),
+    Index,
+    Dereference,
+}
+
+// Zerlegt den Bezeichner am Ende von `text` von hinten nach vorn
+fn designator_steps(text: &[char]) -> Option<Vec<Step>> {
+    let mut steps = Vec::new();
+    let mut end = text.len();
+
+    loop {
+        while end > 0 && matches!(text[end - 1], ']' | '^') {
+            if text[end - 1] == '^' {
+                steps.push(Step::Dereference);
+                end -= 1;
+                continue;
+            }
+
+            // passende öffnende Klammer suchen
+            let mut depth = 0;
+            loop {
+                end = end.checked_sub(1)?;
+                match text[end] {
+                    ']' => depth += 1,
+                    '[' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    break;
+                }
+            }
+            steps.push(Step::Index);
+        }
+
+        let start = identifier_start(text, end);
+        if start == end || !text[start].is_ascii_alphabetic() {
+            return None;
+        }
+        steps.push(Step::Name(text[start..end].iter().collect()));
+
+        if start > 0 && text[start - 1] == '.' {
+            end = start - 1;
+        } else {
+            steps.reverse();
+            return Some(steps);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "MODULE T;
+CONST Max = 10;
+TYPE
+  List = POINTER TO Node;
+  Node = RECORD key: INTEGER; next: List END;
+VAR list: List; count: INTEGER; first: Node;
+
+PROCEDURE Insert(VAR l: List; key: INTEGER): BOOLEAN;
+  VAR n: List;
+BEGIN
+  NEW(n); n^.key := key; n^.next := l; l := n;
+  count := count + 1
+  RETURN count < Max
+END Insert;
+
+BEGIN
+  IF Insert(list, 1) THEN first.key := 2 END
+END T.";
+
+    // Position des n-ten Vorkommens von `text`
+    fn find(source: &str, text: &str, n: usize) -> Position {
+        source
+            .lines()
+            .enumerate()
+            .flat_map(|(i, line)| line.match_indices(text).map(move |(column, _)| (i + 1, column + 1)))
+            .nth(n)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_navigation() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(analysis.diagnostics(), &[]);
+
+        // `count` in Insert verweist auf die globale Variable
+        let usage = find(SOURCE, "count", 1);
+        let declaration = find(SOURCE, "count", 0);
+        assert_eq!(analysis.definition(usage), Some((declaration, (declaration.0, declaration.1 + 5))));
+        assert_eq!(analysis.references(declaration, false).len(), 3);
+
+        // Feldzugriff `first.key` (vom Parser als qualifizierter Bezeichner gelesen)
+        let key_field = find(SOURCE, "key", 0);
+        assert_eq!(analysis.definition(find(SOURCE, "key", 4)).map(|range| range.0), Some(key_field));
+
+        // Umbenennen erfasst den Namen nach END
+        let renamed = analysis.rename(find(SOURCE, "Insert", 0), "Add").unwrap();
+        assert_eq!(renamed.len(), 3);
+        assert!(renamed.iter().any(|range| range.0 == find(SOURCE, "Insert", 1)));
+        assert!(analysis.rename(find(SOURCE, "Insert", 0), "END").is_err());
+
+        let (line, column) = find(SOURCE, "VAR l:", 0);
+        assert_eq!(analysis.hover((line, column + 4)).as_deref(), Some("VAR-Parameter l: List"));
+        assert_eq!(
+            analysis.hover(find(SOURCE, "Insert", 2)).as_deref(),
+            Some("PROCEDURE Insert(VAR l: List; key: INTEGER): BOOLEAN")
+        );
+        assert_eq!(
+            analysis.hover(find(SOURCE, "Node", 1)).as_deref(),
+            Some("TYPE Node = RECORD key: INTEGER; next: List END")
+        );
+        assert_eq!(analysis.hover(find(SOURCE, "Max", 1)).as_deref(), Some("CONST Max = 10"));
+
+        let symbols = analysis.document_symbols();
+        let names: Vec<&str> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+        assert_eq!(names, vec!["Max", "List", "Node", "list", "count", "first", "Insert"]);
+        assert_eq!(symbols[2].children.len(), 2);
+        assert_eq!(symbols[6].range.1 .0, 14);
+        assert_eq!(symbols[6].children[0].name, "n");
+    }
+
+    #[test]
+    fn test_rename_conflicts() {
+        let analysis = Analysis::new(SOURCE);
+        let rename = |text: &str, n: usize, new_name: &str| analysis.rename(find(SOURCE, text, n), new_name);
+
+        // Deklaration desselben Bereichs, Parameter und lokale Variablen teilen ihn
+        assert!(rename("list", 0, "count").is_err());
+        let (line, column) = find(SOURCE, "VAR l:", 0);
+        assert!(analysis.rename((line, column + 4), "n").unwrap_err().contains("Zeile 9"));
+        assert!(rename("key", 0, "next").is_err());
+        // die lokale Variable n würde die Verwendung von count in Insert binden
+        assert!(rename("count", 0, "n").is_err());
+        // n würde die globale Variable count in Insert verdecken
+        assert!(rename("n: List", 0, "count").is_err());
+        assert!(rename("Max", 0, "INTEGER").is_err());
+
+        assert_eq!(rename("count", 0, "total").map(|ranges| ranges.len()), Ok(4));
+        assert!(rename("key", 0, "count").is_ok());
+        assert!(rename("n: List", 0, "list").is_ok());
+    }
+
+    #[test]
+    fn test_completion() {
+        let source = SOURCE.replace("l := n;", "l := n; n^.next^.ne");
+        let analysis = Analysis::new(&source);
+        assert!(!analysis.diagnostics().is_empty());
+
+        let (line, column) = find(&source, "n^.next^.ne", 0);
+        let labels = |items: Vec<CompletionItem>| -> Vec<String> { items.into_iter().map(|item| item.label).collect() };
+        assert_eq!(labels(analysis.completion((line, column + 11))), vec!["next"]);
+        assert_eq!(labels(analysis.completion((line, column + 3))), vec!["key", "next"]);
+        assert_eq!(labels(analysis.completion((line, column + 1))), vec!["n"]);
+
+        let visible = labels(analysis.completion((line, column)));
+        assert!(visible.contains(&"n".to_string()) && visible.contains(&"NEW".to_string()));
+        assert!(!visible.contains(&"next".to_string()));
+    }
+
+    #[test]
+    fn test_partial_source() {
+        let source = "MODULE T;
+VAR a: INTEGER;
+  b: ;
+PROCEDURE P;
+BEGIN
+  a := ;
+  a := c
+END P;
+END T.";
+        let analysis = Analysis::new(source);
+        let lines: Vec<usize> = analysis.diagnostics().iter().map(|d| d.range.0 .0).collect();
+        assert_eq!(lines, vec![3, 6, 7]);
+
+        // die lesbaren Teile stehen trotzdem zur Verfügung
+        assert_eq!(analysis.hover(find(source, "a", 2)).as_deref(), Some("VAR a: INTEGER"));
+        assert_eq!(analysis.document_symbols().len(), 2);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let source = "MODULE T;
+PROCEDURE F(): INTEGER;
+  VAR x, unused: INTEGER;
+BEGIN
+  IF FALSE THEN x := 1 END
+  RETURN x
+END F;
+END T.";
+        let analysis = Analysis::new(source);
+        let warnings: Vec<(Option<WarningKind>, Range)> =
+            analysis.diagnostics().iter().map(|d| (d.warning, d.range)).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (Some(WarningKind::Unused), (find(source, "unused", 0), (3, 16))),
+                (Some(WarningKind::UninitializedResult), ((6, 3), (6, 11))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syntax_messages() {
+        // Meldungen nennen das Token so, wie es im Quelltext steht
+        let analysis = Analysis::new("MODULE T;\n(* c *) VAR x INTEGER;\nEND T.");
+        let messages: Vec<&str> = analysis.diagnostics().iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages[0], "Erwarte ':', gefunden: Bezeichner 'INTEGER' in Zeile 2");
+        assert!(messages.iter().all(|message| !message.contains("Trivia")), "{:?}", messages);
+    }
+}
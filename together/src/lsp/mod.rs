@@ -0,0 +1,462 @@
+pub mod analysis;
+pub mod json;
+
+use analysis::{Analysis, DocumentSymbol, ItemKind, Range};
+use json::Json;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+// ============================================================================
+// Language Server (together-lsp)
+// ============================================================================
+//
+// JSON-RPC über stdin/stdout mit Content-Length-Rahmen. Dokumente werden
+// vollständig synchronisiert und bei jeder Änderung neu analysiert; die
+// Diagnosen gehen als publishDiagnostics an den Client.
+//
+// LSP-Positionen sind 0-basiert, die des Compilers 1-basiert. Spalten werden
+// als Zeichen gezählt, was für ASCII-Quelltexte den UTF-16-Einheiten entspricht.
+
+pub struct Server {
+    documents: HashMap<String, Analysis>,
+    shutdown_requested: bool,
+    // Exit-Code, sobald der Client `exit` geschickt hat
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            shutdown_requested: false,
+            exit_code: None,
+        }
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    // Verarbeitet eine Nachricht und liefert Antwort und Benachrichtigungen
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.handle_notification(method, &params);
+        };
+
+        let response = match self.handle_request(method, &params) {
+            Ok(result) => Json::object(vec![
+                ("jsonrpc", Json::string("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            Err((code, text)) => Json::object(vec![
+                ("jsonrpc", Json::string("2.0")),
+                ("id", id),
+                (
+                    "error",
+                    Json::object(vec![("code", Json::Number(code as f64)), ("message", Json::String(text))]),
+                ),
+            ]),
+        };
+        vec![response]
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params.path("textDocument.uri").and_then(Json::as_str).unwrap_or("").to_string();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.path("textDocument.text").and_then(Json::as_str).unwrap_or("");
+                self.update(&uri, text)
+            }
+            "textDocument/didChange" => {
+                // Vollständige Synchronisation: die letzte Änderung enthält den ganzen Text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                match text {
+                    Some(text) => self.update(&uri, text),
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            "exit" => {
+                self.exit_code = Some(if self.shutdown_requested { 0 } else { 1 });
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> Vec<Json> {
+        let analysis = Analysis::new(text);
+        let diagnostics = analysis
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                // LSP DiagnosticSeverity: 1 Fehler, 2 Warnung
+                let mut fields = vec![
+                    ("range", range_json(diagnostic.range)),
+                    ("severity", Json::from(if diagnostic.warning.is_some() { 2 } else { 1 })),
+                    ("source", Json::string("together")),
+                    ("message", Json::string(&diagnostic.message)),
+                ];
+                if let Some(kind) = diagnostic.warning {
+                    fields.push(("code", Json::string(kind.name())));
+                }
+                Json::object(fields)
+            })
+            .collect();
+        self.documents.insert(uri.to_string(), analysis);
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Json) -> Result<Json, (i32, String)> {
+        if method == "initialize" {
+            return Ok(initialize_result());
+        }
+        if method == "shutdown" {
+            self.shutdown_requested = true;
+            return Ok(Json::Null);
+        }
+
+        let uri = params.path("textDocument.uri").and_then(Json::as_str).unwrap_or("");
+        let Some(analysis) = self.documents.get(uri) else {
+            return Err((-32602, format!("Unbekanntes Dokument: {}", uri)));
+        };
+        let position = params.get("position").and_then(position_from_json);
+        let location = |range: Range| Json::object(vec![("uri", Json::string(uri)), ("range", range_json(range))]);
+
+        Ok(match (method, position) {
+            ("textDocument/definition", Some(position)) => {
+                analysis.definition(position).map_or(Json::Null, location)
+            }
+            ("textDocument/hover", Some(position)) => match analysis.hover(position) {
+                Some(text) => Json::object(vec![(
+                    "contents",
+                    Json::object(vec![
+                        ("kind", Json::string("markdown")),
+                        ("value", Json::String(format!("```oberon\n{}\n```", text))),
+                    ]),
+                )]),
+                None => Json::Null,
+            },
+            ("textDocument/references", Some(position)) => {
+                let include_declaration = params
+                    .path("context.includeDeclaration")
+                    .and_then(Json::as_bool)
+                    .unwrap_or(false);
+                Json::from(
+                    analysis
+                        .references(position, include_declaration)
+                        .into_iter()
+                        .map(location)
+                        .collect::<Vec<_>>(),
+                )
+            }
+            ("textDocument/rename", Some(position)) => {
+                let new_name = params.get("newName").and_then(Json::as_str).unwrap_or("");
+                let ranges = analysis.rename(position, new_name).map_err(|e| (-32602, e))?;
+                let edits = ranges
+                    .into_iter()
+                    .map(|range| Json::object(vec![("range", range_json(range)), ("newText", Json::string(new_name))]))
+                    .collect();
+                Json::object(vec![("changes", Json::Object(vec![(uri.to_string(), Json::Array(edits))]))])
+            }
+            ("textDocument/completion", Some(position)) => Json::from(
+                analysis
+                    .completion(position)
+                    .into_iter()
+                    .map(|item| {
+                        Json::object(vec![
+                            ("label", Json::String(item.label)),
+                            ("kind", Json::from(completion_kind(item.kind))),
+                            ("detail", Json::String(item.detail)),
+                        ])
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            ("textDocument/documentSymbol", _) => {
+                Json::from(analysis.document_symbols().iter().map(symbol_json).collect::<Vec<_>>())
+            }
+            (
+                "textDocument/definition"
+                | "textDocument/hover"
+                | "textDocument/references"
+                | "textDocument/rename"
+                | "textDocument/completion",
+                None,
+            ) => return Err((-32602, "Position fehlt".to_string())),
+            _ => return Err((-32601, format!("Methode nicht unterstützt: {}", method))),
+        })
+    }
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Transport
+// ============================================================================
+
+// Liest eine Nachricht; None am Ende der Eingabe
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Json>, String> {
+    match read_body(input)? {
+        Some(body) => parse_body(body).map(Some),
+        None => Ok(None),
+    }
+}
+
+// Liest den Rumpf einer Nachricht ohne ihn zu parsen; Fehler betreffen den
+// Rahmen, danach ist der Datenstrom nicht mehr lesbar
+fn read_body(input: &mut impl BufRead) -> Result<Option<Vec<u8>>, String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).map_err(|e| e.to_string())? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().map_err(|e| format!("Content-Length: {}", e))?);
+        }
+    }
+
+    let length = length.ok_or("Content-Length fehlt")?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).map_err(|e| e.to_string())?;
+    Ok(Some(body))
+}
+
+fn parse_body(body: Vec<u8>) -> Result<Json, String> {
+    let body = String::from_utf8(body).map_err(|e| e.to_string())?;
+    Json::parse(&body)
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+// Bedient den Client bis `exit` oder zum Ende der Eingabe; liefert den Exit-Code
+pub fn run(input: &mut impl BufRead, output: &mut impl Write) -> i32 {
+    let mut server = Server::new();
+    loop {
+        let body = match read_body(input) {
+            Ok(Some(body)) => body,
+            Ok(None) => return 1,
+            Err(e) => {
+                eprintln!("together-lsp: {}", e);
+                return 1;
+            }
+        };
+
+        // Ein fehlerhafter Rumpf beendet nur diese Nachricht (JSON-RPC Parse error)
+        let replies = match parse_body(body) {
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![parse_error(&e)],
+        };
+        for reply in replies {
+            if let Err(e) = write_message(output, &reply) {
+                eprintln!("together-lsp: {}", e);
+                return 1;
+            }
+        }
+        if let Some(code) = server.exit_code() {
+            return code;
+        }
+    }
+}
+
+// ============================================================================
+// Umrechnung in LSP-Strukturen
+// ============================================================================
+
+fn initialize_result() -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                ("textDocumentSync", Json::from(1)),
+                ("definitionProvider", Json::from(true)),
+                ("hoverProvider", Json::from(true)),
+                ("documentSymbolProvider", Json::from(true)),
+                ("referencesProvider", Json::from(true)),
+                ("renameProvider", Json::from(true)),
+                (
+                    "completionProvider",
+                    Json::object(vec![("triggerCharacters", Json::from(vec![Json::string(".")]))]),
+                ),
+            ]),
+        ),
+        ("serverInfo", Json::object(vec![("name", Json::string("together-lsp"))])),
+    ])
+}
+
+// Antwort ohne id, da die Nachricht nicht gelesen werden konnte
+fn parse_error(message: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::string("2.0")),
+        ("id", Json::Null),
+        (
+            "error",
+            Json::object(vec![("code", Json::Number(-32700.0)), ("message", Json::string(message))]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::string("2.0")),
+        ("method", Json::string("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Json::object(vec![("uri", Json::string(uri)), ("diagnostics", Json::Array(diagnostics))]),
+        ),
+    ])
+}
+
+fn position_from_json(position: &Json) -> Option<(usize, usize)> {
+    let line = position.get("line").and_then(Json::as_usize)?;
+    let character = position.get("character").and_then(Json::as_usize)?;
+    Some((line + 1, character + 1))
+}
+
+fn position_json((line, column): (usize, usize)) -> Json {
+    Json::object(vec![
+        ("line", Json::from(line.saturating_sub(1))),
+        ("character", Json::from(column.saturating_sub(1))),
+    ])
+}
+
+fn range_json((start, end): Range) -> Json {
+    Json::object(vec![("start", position_json(start)), ("end", position_json(end))])
+}
+
+fn symbol_json(symbol: &DocumentSymbol) -> Json {
+    // LSP SymbolKind
+    let kind = match symbol.kind {
+        ItemKind::Module => 2,
+        ItemKind::Field => 8,
+        ItemKind::Procedure => 12,
+        ItemKind::Variable => 13,
+        ItemKind::Constant => 14,
+        ItemKind::Type => 23,
+    };
+    Json::object(vec![
+        ("name", Json::string(&symbol.name)),
+        ("kind", Json::from(kind)),
+        ("range", range_json(symbol.range)),
+        ("selectionRange", range_json(symbol.selection)),
+        ("children", Json::from(symbol.children.iter().map(symbol_json).collect::<Vec<_>>())),
+    ])
+}
+
+// LSP CompletionItemKind
+fn completion_kind(kind: ItemKind) -> usize {
+    match kind {
+        ItemKind::Procedure => 3,
+        ItemKind::Field => 5,
+        ItemKind::Variable => 6,
+        ItemKind::Module => 9,
+        ItemKind::Constant => 21,
+        ItemKind::Type => 22,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame(message: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", message.len(), message)
+    }
+
+    #[test]
+    fn test_session() {
+        let source = "MODULE T;\\nVAR x: INTEGER;\\nBEGIN\\n  x := y\\nEND T.";
+        let input = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"file:///t.Mod","text":"{}"}}}}}}"#,
+                source
+            ),
+            r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///t.Mod"},"position":{"line":3,"character":2}}}"#.to_string(),
+            r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+        ]
+        .iter()
+        .map(|message| frame(message))
+        .collect::<String>();
+
+        let mut output = Vec::new();
+        assert_eq!(run(&mut Cursor::new(input), &mut output), 0);
+
+        let mut output = Cursor::new(output);
+        let mut replies = Vec::new();
+        while let Some(reply) = read_message(&mut output).unwrap() {
+            replies.push(reply);
+        }
+        assert_eq!(replies.len(), 4);
+        assert!(replies[0].path("result.capabilities.hoverProvider").is_some());
+
+        let diagnostics = replies[1].path("params.diagnostics").and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path("range.start.line").and_then(Json::as_usize), Some(3));
+
+        assert_eq!(replies[2].path("result.range.start.line").and_then(Json::as_usize), Some(1));
+        assert_eq!(replies[2].path("result.range.start.character").and_then(Json::as_usize), Some(4));
+        assert_eq!(replies[3].get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn test_malformed_message() {
+        let source = "MODULE T;\\nPROCEDURE P(x: INTEGER);\\nEND P;\\nEND T.";
+        let input = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"#.to_string(),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"file:///t.Mod","text":"{}"}}}}}}"#,
+                source
+            ),
+            r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+        ]
+        .iter()
+        .map(|message| frame(message))
+        .collect::<String>();
+
+        // Der Server liest nach der fehlerhaften Nachricht weiter
+        let mut output = Vec::new();
+        assert_eq!(run(&mut Cursor::new(input), &mut output), 1);
+
+        let mut output = Cursor::new(output);
+        let error = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(error.get("id"), Some(&Json::Null));
+        assert_eq!(error.path("error.code"), Some(&Json::Number(-32700.0)));
+
+        // Warnungen erscheinen mit Schwere 2
+        let published = read_message(&mut output).unwrap().unwrap();
+        let diagnostics = published.path("params.diagnostics").and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get("severity").and_then(Json::as_usize), Some(2));
+        assert_eq!(diagnostics[0].get("code").and_then(Json::as_str), Some("unused"));
+        assert_eq!(diagnostics[0].path("range.start.line").and_then(Json::as_usize), Some(1));
+        assert!(read_message(&mut output).unwrap().is_none());
+    }
+}
@@ -1,21 +1,47 @@
-mod scanner;
-mod parser;
-mod semantic;
-mod codegen;
-
-use scanner::Lexer;
-use parser::{Parser, PrettyPrinter};
-use semantic::TypeChecker;
-use codegen::CGenerator;
+use together::cli;
+use together::codegen::CGenerator;
+use together::parser::{Parser, PrettyPrinter};
+use together::scanner::Lexer;
+use together::semantic::TypeChecker;
 
 #[cfg(feature = "llvm")]
-use codegen::LLVMGenerator;
+use together::codegen::llvm_generator::{self, Emit};
+#[cfg(feature = "llvm")]
+use together::codegen::LLVMGenerator;
 #[cfg(feature = "llvm")]
 use inkwell::context::Context;
 
+use std::env;
 use std::fs;
+#[cfg(feature = "llvm")]
+use std::path::Path;
+use std::process;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("lint") => process::exit(cli::lint(&args[1..])),
+        Some("fmt") => process::exit(cli::fmt(&args[1..])),
+        Some("doc") => process::exit(cli::doc(&args[1..])),
+        Some("def") => process::exit(cli::def(&args[1..])),
+        Some("run") => process::exit(cli::run(&args[1..])),
+        Some("repl") => process::exit(cli::repl(&args[1..])),
+        Some("bc") => process::exit(cli::bc(&args[1..])),
+        Some("vm") => process::exit(cli::vm(&args[1..])),
+        Some("disasm") => process::exit(cli::disasm(&args[1..])),
+        Some("wasm") => process::exit(cli::wasm(&args[1..])),
+        Some("asm") => process::exit(cli::asm(&args[1..])),
+        Some("riscv") => process::exit(cli::riscv(&args[1..])),
+        Some("ir") => process::exit(cli::ir(&args[1..])),
+        Some("c") => process::exit(cli::c(&args[1..])),
+        #[cfg(feature = "llvm")]
+        Some("llvm") => process::exit(cli::llvm(&args[1..])),
+        _ => demo(),
+    }
+}
+
+// Ohne Unterbefehl: Beispielmodul durch alle Phasen schicken
+fn demo() {
     let source = r#"
     MODULE Example;
 
@@ -103,9 +129,13 @@ fn main() {
     println!("\n=== SEMANTISCHE ANALYSE ===\n");
 
     let mut type_checker = TypeChecker::new();
-    match type_checker.check_module(&module) {
-        Ok(()) => {
+    let checked = match type_checker.check_module(&module) {
+        Ok(checked) => {
             println!("✓ Semantische Analyse erfolgreich!");
+            for warning in type_checker.warnings() {
+                println!("  {}", warning);
+            }
+            checked
         }
         Err(errors) => {
             eprintln!("✗ Semantische Fehler gefunden:\n");
@@ -114,7 +144,7 @@ fn main() {
             }
             return;
         }
-    }
+    };
 
     // LLVM Backend (optional, nur wenn Feature aktiviert)
     #[cfg(feature = "llvm")]
@@ -124,14 +154,21 @@ fn main() {
         let context = Context::create();
         let mut llvm_gen = LLVMGenerator::new(&context, &module.name);
 
-        match llvm_gen.generate(&module) {
+        match llvm_gen.generate(&checked) {
             Ok(_llvm_ir) => {
                 println!("✓ LLVM-IR erfolgreich generiert!");
 
-                if let Err(e) = llvm_gen.write_to_file("output.ll") {
-                    eprintln!("✗ Fehler beim Schreiben: {}", e);
-                } else {
-                    println!("  - Ausgabe: output.ll\n");
+                // Optimieren (-O2), Objektdatei schreiben und binden, ohne
+                // dass llc oder gcc von Hand aufgerufen werden müssen
+                let result = llvm_generator::target_machine(None, 2).and_then(|machine| {
+                    llvm_gen.optimize(&machine, 2)?;
+                    llvm_gen.write_to_file("output.ll")?;
+                    llvm_gen.emit(&machine, Emit::Object, Path::new("output.o"))?;
+                    llvm_generator::link(&[Path::new("output.o")], Path::new("output_llvm"), None)
+                });
+                match result {
+                    Ok(()) => println!("  - Ausgabe: output.ll, output.o, output_llvm\n"),
+                    Err(e) => eprintln!("✗ Fehler bei der Ausgabe: {}", e),
                 }
             }
             Err(e) => {
@@ -144,7 +181,13 @@ fn main() {
     println!("\n=== C CODE-GENERATOR ===\n");
 
     let mut c_gen = CGenerator::new();
-    let c_code = c_gen.generate(&module);
+    let c_code = match c_gen.generate(&checked) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("✗ C-Fehler: {}", e);
+            return;
+        }
+    };
 
     println!("✓ C-Code erfolgreich generiert!");
     println!("  - Ausgabe: output.c\n");
@@ -165,8 +208,7 @@ fn main() {
     #[cfg(feature = "llvm")]
     {
         println!("LLVM (falls generiert):");
-        println!("  llc output.ll -o output.s");
-        println!("  gcc output.s -o output_llvm");
+        println!("  ./output_llvm");
         println!();
     }
 
@@ -17,6 +17,9 @@ pub struct Module {
 pub struct Import {
     pub alias: Option<String>,
     pub module_name: String,
+    // Position des ersten Namens (Alias oder Modulname)
+    pub line: usize,
+    pub column: usize,
 }
 
 // ============================================================================
@@ -89,6 +92,8 @@ pub struct FPSection {
     pub is_var: bool,
     pub names: Vec<String>,
     pub param_type: Type,
+    // (Zeile, Spalte) je Name
+    pub positions: Vec<(usize, usize)>,
 }
 
 // ============================================================================
@@ -102,46 +107,69 @@ pub struct ProcedureDeclaration {
     pub declarations: DeclSequence,
     pub body: Option<Vec<Statement>>,
     pub return_expr: Option<Expression>,
+    // Zeile des RETURN (0 ohne Rückgabe)
+    pub return_line: usize,
     pub end_name: String,
     pub is_forward: bool,
+    // Position des Namens nach dem abschließenden END
+    pub end_line: usize,
+    pub end_column: usize,
 }
 
 // ============================================================================
 // Statements
 // ============================================================================
 
+// `line` ist jeweils die Zeile, in der die Anweisung beginnt, `span` ihre
+// ganze Ausdehnung
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Assignment {
+        line: usize,
+        span: Span,
         target: Designator,
         value: Expression,
     },
     ProcedureCall {
+        line: usize,
+        span: Span,
         designator: Designator,
         arguments: Vec<Expression>,
     },
     If {
+        line: usize,
+        span: Span,
         condition: Expression,
         then_body: Vec<Statement>,
         elsif_parts: Vec<(Expression, Vec<Statement>)>,
         else_body: Option<Vec<Statement>>,
     },
     Case {
+        line: usize,
+        span: Span,
         expr: Expression,
         cases: Vec<CaseClause>,
         else_body: Option<Vec<Statement>>,
     },
     While {
+        line: usize,
+        span: Span,
         condition: Expression,
         body: Vec<Statement>,
         elsif_parts: Vec<(Expression, Vec<Statement>)>,
     },
     Repeat {
+        line: usize,
+        span: Span,
         body: Vec<Statement>,
         condition: Expression,
     },
     For {
+        line: usize,
+        span: Span,
         variable: String,
+        variable_column: usize,
         start: Expression,
         end: Expression,
         step: Option<Expression>,
@@ -240,7 +268,11 @@ pub struct Designator {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Selector {
-    Field(String),
+    Field {
+        name: String,
+        line: usize,
+        column: usize,
+    },
     Index(Vec<Expression>),
     Dereference,
     TypeGuard(Qualident),
@@ -250,10 +282,22 @@ pub enum Selector {
 // Hilfsdefinitionen
 // ============================================================================
 
+// Ausdehnung im Quelltext: erstes Zeichen bis hinter das letzte, Zeilen
+// und Spalten ab 1; 0 steht für unbekannt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct IdentDef {
     pub name: String,
     pub exported: ExportMark,
+    pub line: usize,
+    pub column: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -267,6 +311,10 @@ pub enum ExportMark {
 pub struct Qualident {
     pub module: Option<String>,
     pub name: String,
+    // Beginn des Bezeichners und Spalte von `name` (bei M.x hinter dem Punkt)
+    pub line: usize,
+    pub column: usize,
+    pub name_column: usize,
 }
 
 // ============================================================================
@@ -289,6 +337,48 @@ impl fmt::Display for Qualident {
     }
 }
 
+impl Statement {
+    // Zeile, in der die Anweisung beginnt (0 für die leere Anweisung)
+    pub fn line(&self) -> usize {
+        match self {
+            Statement::Assignment { line, .. }
+            | Statement::ProcedureCall { line, .. }
+            | Statement::If { line, .. }
+            | Statement::Case { line, .. }
+            | Statement::While { line, .. }
+            | Statement::Repeat { line, .. }
+            | Statement::For { line, .. } => *line,
+            Statement::Empty => 0,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Assignment { span, .. }
+            | Statement::ProcedureCall { span, .. }
+            | Statement::If { span, .. }
+            | Statement::Case { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Repeat { span, .. }
+            | Statement::For { span, .. } => *span,
+            Statement::Empty => Span::default(),
+        }
+    }
+
+    pub fn set_span(&mut self, value: Span) {
+        match self {
+            Statement::Assignment { span, .. }
+            | Statement::ProcedureCall { span, .. }
+            | Statement::If { span, .. }
+            | Statement::Case { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Repeat { span, .. }
+            | Statement::For { span, .. } => *span = value,
+            Statement::Empty => {}
+        }
+    }
+}
+
 impl fmt::Display for IdentDef {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.name)?;
@@ -300,22 +390,6 @@ impl fmt::Display for IdentDef {
     }
 }
 
-impl Qualident {
-    pub fn new(name: String) -> Self {
-        Qualident {
-            module: None,
-            name,
-        }
-    }
-
-    pub fn with_module(module: String, name: String) -> Self {
-        Qualident {
-            module: Some(module),
-            name,
-        }
-    }
-}
-
 // ... existing code ...
 
 impl IdentDef {
@@ -324,11 +398,18 @@ impl IdentDef {
         IdentDef {
             name,
             exported: ExportMark::None,
+            line: 0,
+            column: 0,
         }
     }
 
     #[allow(dead_code)]
     pub fn with_export(name: String, exported: ExportMark) -> Self {
-        IdentDef { name, exported }
+        IdentDef {
+            name,
+            exported,
+            line: 0,
+            column: 0,
+        }
     }
 }
\ No newline at end of file
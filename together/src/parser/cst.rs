@@ -0,0 +1,279 @@
+use crate::scanner::{Lexer, SyntaxError, Token, TriviaKind};
+use super::ast::Module;
+use super::grammar::Parser;
+
+// ============================================================================
+// Konkreter Syntaxbaum (CST)
+// ============================================================================
+//
+// Blätter sind die Token mit ihrer Trivia (Leerraum, Kommentare), innere
+// Knoten die Konstrukte der Grammatik. Der Baum ist verlustfrei: text()
+// ergibt den Quelltext Zeichen für Zeichen, auch für fehlerhafte Dateien.
+// Der Parser zeichnet dazu auf, welche Token jeder erfolgreich gelesene
+// Knoten umfasst; Token außerhalb aller Knoten hängen am nächsten umfassenden.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    SourceFile,
+    Module,
+    ImportList,
+    Import,
+    DeclSequence,
+    ConstDeclaration,
+    TypeDeclaration,
+    VariableDeclaration,
+    ProcedureDeclaration,
+    FormalParameters,
+    FPSection,
+    ArrayType,
+    RecordType,
+    FieldList,
+    PointerType,
+    ProcedureType,
+    StatementSequence,
+    Assignment,
+    ProcedureCall,
+    IfStatement,
+    CaseStatement,
+    CaseClause,
+    CaseLabel,
+    WhileStatement,
+    RepeatStatement,
+    ForStatement,
+    Expression,
+    Set,
+    ActualParameters,
+    Designator,
+    IdentDef,
+    Qualident,
+}
+
+// Knoten über den Token start..end, vom Parser beim Verlassen aufgezeichnet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeSpan {
+    pub kind: SyntaxKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    // Der Quelltext des Knotens einschließlich aller Trivia
+    pub fn text(&self) -> String {
+        self.tokens().iter().map(|token| token.full_text()).collect()
+    }
+
+    // Alle Token in Quelltextreihenfolge
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    pub fn first_token(&self) -> Option<&Token> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    // Direkte Kindknoten
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    // Alle Knoten des Teilbaums in Vorordnung, beginnend mit diesem
+    pub fn descendants(&self) -> Vec<&SyntaxNode> {
+        let mut nodes = vec![self];
+        for child in self.child_nodes() {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+
+    // Kommentare unmittelbar vor dem Knoten (Trivia des ersten Tokens)
+    pub fn leading_comments(&self) -> Vec<&str> {
+        self.first_token()
+            .map(|token| {
+                token
+                    .leading_trivia
+                    .iter()
+                    .filter(|trivia| trivia.kind == TriviaKind::Comment)
+                    .map(|trivia| trivia.text.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+// Scannt und parst fehlertolerant; liefert Syntaxbaum, AST (sofern der
+// Modulkopf lesbar ist) und alle lexikalischen und syntaktischen Fehler
+pub fn parse(source: &str) -> (SyntaxNode, Option<Module>, Vec<SyntaxError>) {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize_tolerant();
+    let mut parser = Parser::new(tokens);
+    let (module, syntax_errors) = parser.parse_tolerant();
+
+    let mut errors = lexer.errors().to_vec();
+    errors.extend(syntax_errors);
+    (parser.syntax_tree(), module, errors)
+}
+
+// Setzt die aufgezeichneten Knoten zu einem Baum über allen Token zusammen
+pub(crate) fn build(tokens: &[Token], spans: &[NodeSpan]) -> SyntaxNode {
+    // Äußere Knoten zuerst: nach Anfang, dann absteigend nach Ende; bei gleichem
+    // Bereich wurde der äußere Knoten später aufgezeichnet
+    let mut order: Vec<usize> = (0..spans.len()).filter(|&i| spans[i].start < spans[i].end).collect();
+    order.sort_by_key(|&i| (spans[i].start, std::cmp::Reverse(spans[i].end), std::cmp::Reverse(i)));
+
+    let mut builder = Builder {
+        tokens,
+        spans: order.into_iter().map(|i| spans[i]).collect(),
+        next: 0,
+    };
+    builder.node(SyntaxKind::SourceFile, 0, tokens.len())
+}
+
+struct Builder<'a> {
+    tokens: &'a [Token],
+    spans: Vec<NodeSpan>,
+    next: usize,
+}
+
+impl Builder<'_> {
+    fn node(&mut self, kind: SyntaxKind, start: usize, end: usize) -> SyntaxNode {
+        let mut children = Vec::new();
+        let mut position = start;
+
+        while position < end {
+            // Knoten, die nicht sauber verschachtelt sind, werden übergangen
+            while let Some(span) = self.spans.get(self.next) {
+                if span.start < position || (span.start == position && span.end > end) {
+                    self.next += 1;
+                } else {
+                    break;
+                }
+            }
+
+            match self.spans.get(self.next).copied() {
+                Some(span) if span.start == position => {
+                    self.next += 1;
+                    children.push(SyntaxElement::Node(self.node(span.kind, span.start, span.end)));
+                    position = span.end;
+                }
+                _ => {
+                    children.push(SyntaxElement::Token(self.tokens[position].clone()));
+                    position += 1;
+                }
+            }
+        }
+
+        SyntaxNode { kind, children }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "(* Kopf *)
+MODULE Liste;  (* Listen *)
+IMPORT Out;
+
+TYPE
+\tNode* = POINTER TO NodeDesc;
+\tNodeDesc = RECORD key: INTEGER; next: Node END;
+
+(** Fügt vorne ein *)
+PROCEDURE Insert*(VAR list: Node; key: INTEGER);
+  VAR n: Node;
+BEGIN
+  NEW(n); n^.key := key; (* Verkettung *) n^.next := list; list := n
+END Insert;
+
+BEGIN
+  IF TRUE THEN Out.Int(1, 0) END
+END Liste.
+(* Ende *)
+";
+
+    #[test]
+    fn test_lossless_round_trip() {
+        let (tree, module, errors) = parse(SOURCE);
+        assert!(errors.is_empty());
+        assert!(module.is_some());
+        assert_eq!(tree.text(), SOURCE);
+
+        // fehlerhafte Quelltexte bleiben ebenfalls vollständig erhalten
+        for broken in ["MODULE M; VAR x: ; BEGIN x := ? END M.", "MODULE M; (* offen", "", "\r\n  x :="] {
+            let (tree, _, errors) = parse(broken);
+            assert_eq!(tree.text(), broken);
+            assert!(!errors.is_empty() || broken.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_structure() {
+        let (tree, _, _) = parse(SOURCE);
+        let module = tree.child_nodes().next().unwrap();
+        assert_eq!(module.kind, SyntaxKind::Module);
+        assert_eq!(module.leading_comments(), vec!["(* Kopf *)"]);
+
+        let procedure = tree
+            .descendants()
+            .into_iter()
+            .find(|node| node.kind == SyntaxKind::ProcedureDeclaration)
+            .unwrap();
+        assert_eq!(procedure.leading_comments(), vec!["(** Fügt vorne ein *)"]);
+        assert!(procedure.text().trim_start().starts_with("(** Fügt vorne ein *)\nPROCEDURE Insert*"));
+        assert!(procedure.text().ends_with("END Insert"));
+
+        let kinds = |kind| tree.descendants().iter().filter(|node| node.kind == kind).count();
+        assert_eq!(kinds(SyntaxKind::TypeDeclaration), 2);
+        assert_eq!(kinds(SyntaxKind::Assignment), 3);
+        assert_eq!(kinds(SyntaxKind::ProcedureCall), 2);
+        assert_eq!(kinds(SyntaxKind::FPSection), 2);
+    }
+
+    #[test]
+    fn test_recovery_terminates() {
+        // Jede Folge endet auch an ihrem eigenen Schlüsselwort, an dem die
+        // Fehlerbehandlung anhält; der Text bleibt verlustfrei erhalten
+        let sources = [
+            "MODULE T; CONST a = 1; b = (CONST c = 2; BEGIN END T.",
+            "MODULE T; TYPE A = INTEGER; B = (TYPE C = INTEGER; BEGIN END T.",
+            "MODULE T; VAR y: INTEGER; x(VAR y); BEGIN END T.",
+            "MODULE T; PROCEDUR Swap(VAR a, b: INTEGER); VAR t: INTEGER; BEGIN t := a END Swap; BEGIN END T.",
+            "MODULE T; PROCEDURE P; PROCEDUR Q(VAR a: INTEGER); BEGIN END Q; BEGIN END P; END T.",
+        ];
+        for source in sources {
+            let (tree, module, errors) = parse(source);
+            assert!(module.is_some(), "{}", source);
+            assert!(!errors.is_empty(), "{}", source);
+            assert_eq!(tree.text(), source);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+// ============================================================================
+// Layout-Dokumente nach Wadler ("A prettier printer")
+// ============================================================================
+//
+// Ein Dokument beschreibt Text mit möglichen Zeilenumbrüchen. Eine Gruppe
+// wird flach (Umbrüche als Leerzeichen) ausgegeben, wenn sie bis zum nächsten
+// möglichen Umbruch in die Zeile passt, sonst brechen alle ihre Umbrüche.
+// Zeilenumbrüche werden erst beim nächsten Text geschrieben; mehrere
+// Umbrüche hintereinander verschmelzen, und Zeilenenden tragen nie
+// Leerzeichen.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Doc {
+    Text(String),
+    // Leerzeichen oder Zeilenumbruch
+    Line,
+    // nichts oder Zeilenumbruch
+    SoftLine,
+    // immer ein Zeilenumbruch; erzwingt den Umbruch der umgebenden Gruppen
+    HardLine,
+    // Leerzeile
+    BlankLine,
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn nest(indent: usize, doc: Doc) -> Doc {
+        Doc::Nest(indent, Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn concat(docs: Vec<Doc>) -> Doc {
+        Doc::Concat(docs)
+    }
+
+    // Zeilenwechsel entsprechend der Anzahl Zeilenumbrüche im Quelltext
+    pub fn newlines(count: usize) -> Doc {
+        match count {
+            0 => Doc::text(" "),
+            1 => Doc::HardLine,
+            _ => Doc::BlankLine,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+// Gibt das Dokument mit höchstens `width` Zeichen pro Zeile aus, soweit die
+// Umbruchstellen das zulassen
+pub fn render(doc: &Doc, width: usize) -> String {
+    // usize::MAX: beliebig breit
+    let width = isize::try_from(width).unwrap_or(isize::MAX);
+    let mut out = String::new();
+    let mut column = 0;
+    // ausstehende Zeilenumbrüche (Anzahl, Einrückung der neuen Zeile)
+    let mut pending: Option<(usize, usize)> = None;
+    let mut stack: Vec<(usize, Mode, &Doc)> = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                if let Some((count, new_indent)) = pending.take() {
+                    // am Dateianfang keine Leerzeilen
+                    if !out.is_empty() {
+                        let trimmed = out.trim_end_matches(' ').len();
+                        out.truncate(trimmed);
+                        out.push_str(&"\n".repeat(count));
+                        out.push_str(&" ".repeat(new_indent));
+                        column = new_indent;
+                    }
+                }
+                out.push_str(text);
+                column = match text.rfind('\n') {
+                    Some(pos) => text[pos + 1..].chars().count(),
+                    None => column + text.chars().count(),
+                };
+            }
+            Doc::Line if mode == Mode::Flat => {
+                if pending.is_none() {
+                    out.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                let count = pending.map_or(1, |(count, _)| count.max(1));
+                pending = Some((count, indent));
+            }
+            Doc::BlankLine => pending = Some((2, indent)),
+            Doc::Nest(extra, doc) => stack.push((indent + extra, mode, doc)),
+            Doc::Group(doc) => {
+                let mode = if mode == Mode::Flat {
+                    Mode::Flat
+                } else {
+                    let start = pending.map_or(column, |(_, indent)| indent);
+                    if fits(width - start as isize, (indent, doc), &stack) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    }
+                };
+                stack.push((indent, mode, doc));
+            }
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((indent, mode, doc));
+                }
+            }
+        }
+    }
+
+    if pending.is_some() && !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+// Passt die Gruppe flach in den verbleibenden Platz? Betrachtet wird alles
+// bis zum nächsten Umbruch danach, der ohnehin bricht.
+fn fits(mut remaining: isize, group: (usize, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut stack = vec![(Mode::Flat, group.1)];
+    let mut rest = rest.iter().rev();
+
+    loop {
+        if remaining < 0 {
+            return false;
+        }
+        let (mode, doc) = match stack.pop() {
+            Some(entry) => entry,
+            None => match rest.next() {
+                Some(&(_, mode, doc)) => (mode, doc),
+                None => return true,
+            },
+        };
+
+        match doc {
+            Doc::Text(text) => {
+                if text.contains('\n') {
+                    return mode == Mode::Break;
+                }
+                remaining -= text.chars().count() as isize;
+            }
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::HardLine | Doc::BlankLine if mode == Mode::Flat => return false,
+            Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::BlankLine => return true,
+            Doc::Nest(_, doc) | Doc::Group(doc) => stack.push((mode, doc)),
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((mode, doc));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_groups() {
+        // f(alpha, beta, gamma) mit Umbrüchen nach den Kommas
+        let call = Doc::group(Doc::concat(vec![
+            Doc::text("f("),
+            Doc::nest(
+                4,
+                Doc::concat(vec![
+                    Doc::SoftLine,
+                    Doc::text("alpha,"),
+                    Doc::Line,
+                    Doc::text("beta,"),
+                    Doc::Line,
+                    Doc::text("gamma"),
+                ]),
+            ),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]));
+
+        assert_eq!(render(&call, 80), "f(alpha, beta, gamma)");
+        assert_eq!(render(&call, 10), "f(\n    alpha,\n    beta,\n    gamma\n)");
+
+        // HardLine bricht die Gruppe, verschmilzt aber mit anderen Umbrüchen
+        let forced = Doc::group(Doc::concat(vec![
+            Doc::text("a "),
+            Doc::HardLine,
+            Doc::Line,
+            Doc::text("b"),
+            Doc::BlankLine,
+            Doc::text("c"),
+        ]));
+        assert_eq!(render(&forced, 80), "a\nb\n\nc");
+    }
+}
@@ -1,28 +1,190 @@
-use crate::scanner::{Token, TokenType};
+use crate::scanner::{SyntaxError, Token, TokenType};
 use super::ast::*;
+use super::cst::{self, NodeSpan, SyntaxKind, SyntaxNode};
+
+// Synchronisationspunkte für die Fehlerbehandlung
+const DECLARATION_SYNC: &[TokenType] = &[
+    TokenType::Semicolon,
+    TokenType::Const,
+    TokenType::Type,
+    TokenType::Var,
+    TokenType::Procedure,
+    TokenType::Begin,
+    TokenType::End,
+];
+
+// Tokens, mit denen eine Anweisungsfolge endet
+const STATEMENT_END: &[TokenType] = &[
+    TokenType::End,
+    TokenType::Else,
+    TokenType::Elsif,
+    TokenType::Until,
+    TokenType::Bar,
+    TokenType::Return,
+];
+
+const STATEMENT_SYNC: &[TokenType] = &[
+    TokenType::Semicolon,
+    TokenType::End,
+    TokenType::Else,
+    TokenType::Elsif,
+    TokenType::Until,
+    TokenType::Bar,
+    TokenType::Return,
+];
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    // Im fehlertoleranten Modus gesammelte Fehler
+    errors: Vec<SyntaxError>,
+    recovering: bool,
+    // Erfolgreich geparste Knoten für den konkreten Syntaxbaum
+    nodes: Vec<NodeSpan>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            recovering: false,
+            nodes: Vec::new(),
+        }
     }
 
     pub fn parse(&mut self) -> Result<Module, String> {
         self.parse_module()
     }
 
+    // Parst auch fehlerhafte Quelltexte so weit wie möglich: fehlerhafte
+    // Deklarationen und Anweisungen werden gemeldet und übersprungen.
+    // Das Modul fehlt nur, wenn schon der Modulkopf nicht lesbar ist.
+    pub fn parse_tolerant(&mut self) -> (Option<Module>, Vec<SyntaxError>) {
+        self.recovering = true;
+        let module = match self.parse_module() {
+            Ok(module) => Some(module),
+            Err(message) => {
+                self.record_error(message);
+                None
+            }
+        };
+        (module, std::mem::take(&mut self.errors))
+    }
+
+    // Konkreter Syntaxbaum über allen Token (nach parse oder parse_tolerant);
+    // enthält Kommentare und Leerraum und ergibt wieder den Quelltext
+    pub fn syntax_tree(&self) -> SyntaxNode {
+        cst::build(&self.tokens, &self.nodes)
+    }
+
+    // ========================================================================
+    // Einzelne Eingaben (REPL)
+    // ========================================================================
+
+    // Jede Eingabe muss alle Token verbrauchen
+
+    // Importliste und Deklarationen, wie sie nach dem Modulkopf stehen
+    pub fn parse_declaration_input(&mut self) -> Result<(Vec<Import>, DeclSequence), String> {
+        let imports = if self.check(&TokenType::Import) {
+            self.parse_import_list()?
+        } else {
+            Vec::new()
+        };
+        let declarations = self.parse_decl_sequence()?;
+        self.expect_input_end()?;
+        Ok((imports, declarations))
+    }
+
+    pub fn parse_statement_input(&mut self) -> Result<Vec<Statement>, String> {
+        let statements = self.parse_statement_sequence()?;
+        self.expect_input_end()?;
+        Ok(statements)
+    }
+
+    pub fn parse_expression_input(&mut self) -> Result<Expression, String> {
+        let expression = self.parse_expression()?;
+        self.expect_input_end()?;
+        Ok(expression)
+    }
+
+    fn expect_input_end(&self) -> Result<(), String> {
+        if self.is_at_end() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Unerwartetes {} in Zeile {}",
+                self.peek().token_type,
+                self.peek().line
+            ))
+        }
+    }
+
+    fn finish_node(&mut self, kind: SyntaxKind, start: usize) {
+        self.nodes.push(NodeSpan {
+            kind,
+            start,
+            end: self.current,
+        });
+    }
+
+    // ========================================================================
+    // Fehlerbehandlung
+    // ========================================================================
+
+    fn record_error(&mut self, message: String) {
+        let token = self.peek();
+        let error = SyntaxError {
+            message,
+            line: token.line,
+            column: token.column,
+        };
+        self.errors.push(error);
+    }
+
+    // Außerhalb des fehlertoleranten Modus wird der Fehler weitergereicht,
+    // sonst gemeldet und bis zum nächsten Token aus `sync` übersprungen
+    fn recover(&mut self, message: String, sync: &[TokenType]) -> Result<(), String> {
+        if !self.recovering {
+            return Err(message);
+        }
+        self.record_error(message);
+        while !self.is_at_end() && !sync.iter().any(|token_type| self.check(token_type)) {
+            self.advance();
+        }
+        Ok(())
+    }
+
+    // Semikolon nach einer Deklaration
+    fn expect_declaration_end(&mut self) -> Result<(), String> {
+        if let Err(e) = self.expect(TokenType::Semicolon) {
+            self.recover(e, DECLARATION_SYNC)?;
+            self.match_token(&TokenType::Semicolon);
+        }
+        Ok(())
+    }
+
+    // END eines Blocks; fehlt es, wird bis zum nächsten END übersprungen
+    fn expect_end(&mut self) -> Result<(), String> {
+        if let Err(e) = self.expect(TokenType::End) {
+            self.recover(e, &[TokenType::End])?;
+            self.match_token(&TokenType::End);
+        }
+        Ok(())
+    }
+
     // ========================================================================
     // Module
     // ========================================================================
 
     fn parse_module(&mut self) -> Result<Module, String> {
+        let node_start = self.current;
         self.expect(TokenType::Module)?;
         let name = self.parse_identifier()?;
-        self.expect(TokenType::Semicolon)?;
+        if let Err(e) = self.expect(TokenType::Semicolon) {
+            self.recover(e, &[])?;
+        }
 
         let imports = if self.check(&TokenType::Import) {
             self.parse_import_list()?
@@ -39,17 +201,26 @@ impl Parser {
             None
         };
 
-        self.expect(TokenType::End)?;
-        let end_name = self.parse_identifier()?;
-        self.expect(TokenType::Period)?;
+        self.expect_end()?;
+        let end_name = match self.parse_identifier() {
+            Ok(end_name) => end_name,
+            Err(e) => {
+                self.recover(e, &[])?;
+                name.clone()
+            }
+        };
+        if let Err(e) = self.expect(TokenType::Period) {
+            self.recover(e, &[])?;
+        }
 
         if name != end_name {
-            return Err(format!(
-                "Modulname stimmt nicht überein: '{}' != '{}'",
-                name, end_name
-            ));
+            self.recover(
+                format!("Modulname stimmt nicht überein: '{}' != '{}'", name, end_name),
+                &[],
+            )?;
         }
 
+        self.finish_node(SyntaxKind::Module, node_start);
         Ok(Module {
             name,
             imports,
@@ -60,36 +231,48 @@ impl Parser {
     }
 
     fn parse_import_list(&mut self) -> Result<Vec<Import>, String> {
+        let node_start = self.current;
         self.expect(TokenType::Import)?;
         let mut imports = Vec::new();
 
         loop {
-            let import = self.parse_import()?;
-            imports.push(import);
+            match self.parse_import() {
+                Ok(import) => imports.push(import),
+                Err(e) => self.recover(e, DECLARATION_SYNC)?,
+            }
 
             if !self.match_token(&TokenType::Comma) {
                 break;
             }
         }
 
-        self.expect(TokenType::Semicolon)?;
+        self.expect_declaration_end()?;
+        self.finish_node(SyntaxKind::ImportList, node_start);
         Ok(imports)
     }
 
     fn parse_import(&mut self) -> Result<Import, String> {
+        let node_start = self.current;
+        let (line, column) = (self.peek().line, self.peek().column);
         let first_ident = self.parse_identifier()?;
 
         if self.match_token(&TokenType::Assign) {
             // alias := module
             let module_name = self.parse_identifier()?;
+            self.finish_node(SyntaxKind::Import, node_start);
             Ok(Import {
                 alias: Some(first_ident),
                 module_name,
+                line,
+                column,
             })
         } else {
+            self.finish_node(SyntaxKind::Import, node_start);
             Ok(Import {
                 alias: None,
                 module_name: first_ident,
+                line,
+                column,
             })
         }
     }
@@ -99,74 +282,111 @@ impl Parser {
     // ========================================================================
 
     fn parse_decl_sequence(&mut self) -> Result<DeclSequence, String> {
+        let node_start = self.current;
         let mut decls = DeclSequence::default();
 
         loop {
             if self.check(&TokenType::Const) {
                 self.advance();
-                while !self.check(&TokenType::Type)
-                    && !self.check(&TokenType::Var)
-                    && !self.check(&TokenType::Procedure)
-                    && !self.check(&TokenType::Begin)
-                    && !self.check(&TokenType::End)
-                {
-                    decls.constants.push(self.parse_const_declaration()?);
-                    self.expect(TokenType::Semicolon)?;
+                while !self.at_section_end() {
+                    match self.parse_const_declaration() {
+                        Ok(decl) => decls.constants.push(decl),
+                        Err(e) => self.recover(e, DECLARATION_SYNC)?,
+                    }
+                    self.expect_declaration_end()?;
                 }
             } else if self.check(&TokenType::Type) {
                 self.advance();
-                while !self.check(&TokenType::Const)
-                    && !self.check(&TokenType::Var)
-                    && !self.check(&TokenType::Procedure)
-                    && !self.check(&TokenType::Begin)
-                    && !self.check(&TokenType::End)
-                {
-                    decls.types.push(self.parse_type_declaration()?);
-                    self.expect(TokenType::Semicolon)?;
+                while !self.at_section_end() {
+                    match self.parse_type_declaration() {
+                        Ok(decl) => decls.types.push(decl),
+                        Err(e) => self.recover(e, DECLARATION_SYNC)?,
+                    }
+                    self.expect_declaration_end()?;
                 }
             } else if self.check(&TokenType::Var) {
                 self.advance();
-                while !self.check(&TokenType::Const)
-                    && !self.check(&TokenType::Type)
-                    && !self.check(&TokenType::Procedure)
-                    && !self.check(&TokenType::Begin)
-                    && !self.check(&TokenType::End)
-                {
-                    decls.variables.push(self.parse_variable_declaration()?);
-                    self.expect(TokenType::Semicolon)?;
+                while !self.at_section_end() {
+                    match self.parse_variable_declaration() {
+                        Ok(decl) => decls.variables.push(decl),
+                        Err(e) => self.recover(e, DECLARATION_SYNC)?,
+                    }
+                    self.expect_declaration_end()?;
                 }
             } else if self.check(&TokenType::Procedure) {
-                decls.procedures.push(self.parse_procedure_declaration()?);
-                self.expect(TokenType::Semicolon)?;
+                match self.parse_procedure_declaration() {
+                    Ok(decl) => decls.procedures.push(decl),
+                    Err(e) => self.recover(e, DECLARATION_SYNC)?,
+                }
+                self.expect_declaration_end()?;
+            } else if self.recovering
+                && !self.is_at_end()
+                && ![TokenType::Begin, TokenType::End, TokenType::Return]
+                    .iter()
+                    .any(|token_type| self.check(token_type))
+            {
+                let e = format!(
+                    "Deklaration erwartet, gefunden: {} in Zeile {}",
+                    self.peek().token_type,
+                    self.peek().line
+                );
+                self.recover(e, DECLARATION_SYNC)?;
+                self.match_token(&TokenType::Semicolon);
             } else {
                 break;
             }
         }
 
+        self.finish_node(SyntaxKind::DeclSequence, node_start);
         Ok(decls)
     }
 
+    // Ende einer CONST-, TYPE- oder VAR-Folge: der nächste Abschnitt (auch
+    // einer derselben Art), der Rumpf oder das Ende. Die Fehlerbehandlung hält
+    // vor diesen Schlüsselwörtern an, ohne sie zu verbrauchen; ohne sie hier
+    // käme die Schleife nicht weiter.
+    fn at_section_end(&self) -> bool {
+        self.is_at_end()
+            || [
+                TokenType::Const,
+                TokenType::Type,
+                TokenType::Var,
+                TokenType::Procedure,
+                TokenType::Begin,
+                TokenType::End,
+                TokenType::Return,
+            ]
+            .iter()
+            .any(|token_type| self.check(token_type))
+    }
+
     fn parse_const_declaration(&mut self) -> Result<ConstDeclaration, String> {
+        let node_start = self.current;
         let name = self.parse_ident_def()?;
         self.expect(TokenType::Equal)?;
         let value = self.parse_expression()?;
 
+        self.finish_node(SyntaxKind::ConstDeclaration, node_start);
         Ok(ConstDeclaration { name, value })
     }
 
     fn parse_type_declaration(&mut self) -> Result<TypeDeclaration, String> {
+        let node_start = self.current;
         let name = self.parse_ident_def()?;
         self.expect(TokenType::Equal)?;
         let type_def = self.parse_type()?;
 
+        self.finish_node(SyntaxKind::TypeDeclaration, node_start);
         Ok(TypeDeclaration { name, type_def })
     }
 
     fn parse_variable_declaration(&mut self) -> Result<VariableDeclaration, String> {
+        let node_start = self.current;
         let names = self.parse_ident_list()?;
         self.expect(TokenType::Colon)?;
         let var_type = self.parse_type()?;
 
+        self.finish_node(SyntaxKind::VariableDeclaration, node_start);
         Ok(VariableDeclaration { names, var_type })
     }
 
@@ -190,6 +410,7 @@ impl Parser {
     }
 
     fn parse_array_type(&mut self) -> Result<Type, String> {
+        let node_start = self.current;
         self.expect(TokenType::Array)?;
         let mut lengths = Vec::new();
 
@@ -203,6 +424,7 @@ impl Parser {
         self.expect(TokenType::Of)?;
         let element_type = Box::new(self.parse_type()?);
 
+        self.finish_node(SyntaxKind::ArrayType, node_start);
         Ok(Type::Array {
             lengths,
             element_type,
@@ -210,6 +432,7 @@ impl Parser {
     }
 
     fn parse_record_type(&mut self) -> Result<Type, String> {
+        let node_start = self.current;
         self.expect(TokenType::Record)?;
 
         let base_type = if self.match_token(&TokenType::LParen) {
@@ -235,26 +458,32 @@ impl Parser {
 
         self.expect(TokenType::End)?;
 
+        self.finish_node(SyntaxKind::RecordType, node_start);
         Ok(Type::Record { base_type, fields })
     }
 
     fn parse_field_list(&mut self) -> Result<FieldList, String> {
+        let node_start = self.current;
         let names = self.parse_ident_list()?;
         self.expect(TokenType::Colon)?;
         let field_type = self.parse_type()?;
 
+        self.finish_node(SyntaxKind::FieldList, node_start);
         Ok(FieldList { names, field_type })
     }
 
     fn parse_pointer_type(&mut self) -> Result<Type, String> {
+        let node_start = self.current;
         self.expect(TokenType::Pointer)?;
         self.expect(TokenType::To)?;
         let target_type = Box::new(self.parse_type()?);
 
+        self.finish_node(SyntaxKind::PointerType, node_start);
         Ok(Type::Pointer { target_type })
     }
 
     fn parse_procedure_type(&mut self) -> Result<Type, String> {
+        let node_start = self.current;
         self.expect(TokenType::Procedure)?;
 
         let params = if self.check(&TokenType::LParen) {
@@ -263,10 +492,12 @@ impl Parser {
             None
         };
 
+        self.finish_node(SyntaxKind::ProcedureType, node_start);
         Ok(Type::Procedure { params })
     }
 
     fn parse_formal_parameters(&mut self) -> Result<FormalParameters, String> {
+        let node_start = self.current;
         self.expect(TokenType::LParen)?;
 
         let mut sections = Vec::new();
@@ -287,6 +518,7 @@ impl Parser {
             None
         };
 
+        self.finish_node(SyntaxKind::FormalParameters, node_start);
         Ok(FormalParameters {
             sections,
             return_type,
@@ -294,10 +526,13 @@ impl Parser {
     }
 
     fn parse_fp_section(&mut self) -> Result<FPSection, String> {
+        let node_start = self.current;
         let is_var = self.match_token(&TokenType::Var);
 
         let mut names = Vec::new();
+        let mut positions = Vec::new();
         loop {
+            positions.push((self.peek().line, self.peek().column));
             names.push(self.parse_identifier()?);
             if !self.match_token(&TokenType::Comma) {
                 break;
@@ -307,10 +542,12 @@ impl Parser {
         self.expect(TokenType::Colon)?;
         let param_type = self.parse_type()?;
 
+        self.finish_node(SyntaxKind::FPSection, node_start);
         Ok(FPSection {
             is_var,
             names,
             param_type,
+            positions,
         })
     }
 
@@ -319,6 +556,7 @@ impl Parser {
     // ========================================================================
 
     fn parse_procedure_declaration(&mut self) -> Result<ProcedureDeclaration, String> {
+        let node_start = self.current;
         self.expect(TokenType::Procedure)?;
 
         // Forward-Deklaration?
@@ -327,22 +565,35 @@ impl Parser {
         let name = self.parse_ident_def()?;
 
         let params = if self.check(&TokenType::LParen) {
-            Some(self.parse_formal_parameters()?)
+            match self.parse_formal_parameters() {
+                Ok(params) => Some(params),
+                Err(e) => {
+                    self.recover(e, &[TokenType::Semicolon])?;
+                    None
+                }
+            }
         } else {
             None
         };
 
-        self.expect(TokenType::Semicolon)?;
+        if let Err(e) = self.expect(TokenType::Semicolon) {
+            self.recover(e, &[])?;
+        }
 
         if is_forward {
+            let (end_line, end_column) = (name.line, name.column);
+            self.finish_node(SyntaxKind::ProcedureDeclaration, node_start);
             return Ok(ProcedureDeclaration {
                 name,
                 params,
                 declarations: DeclSequence::default(),
                 body: None,
                 return_expr: None,
+                return_line: 0,
                 end_name: String::new(),
                 is_forward: true,
+                end_line,
+                end_column,
             });
         }
 
@@ -355,30 +606,42 @@ impl Parser {
             None
         };
 
-        let return_expr = if self.match_token(&TokenType::Return) {
-            Some(self.parse_expression()?)
+        let return_line = self.peek().line;
+        let (return_expr, return_line) = if self.match_token(&TokenType::Return) {
+            (Some(self.parse_expression()?), return_line)
         } else {
-            None
+            (None, 0)
         };
 
-        self.expect(TokenType::End)?;
-        let end_name = self.parse_identifier()?;
+        self.expect_end()?;
+        let (end_line, end_column) = (self.peek().line, self.peek().column);
+        let end_name = match self.parse_identifier() {
+            Ok(end_name) => end_name,
+            Err(e) => {
+                self.recover(e, &[])?;
+                name.name.clone()
+            }
+        };
 
         if name.name != end_name {
-            return Err(format!(
-                "Prozedurname stimmt nicht überein: '{}' != '{}'",
-                name.name, end_name
-            ));
+            self.recover(
+                format!("Prozedurname stimmt nicht überein: '{}' != '{}'", name.name, end_name),
+                &[],
+            )?;
         }
 
+        self.finish_node(SyntaxKind::ProcedureDeclaration, node_start);
         Ok(ProcedureDeclaration {
             name,
             params,
             declarations,
             body,
             return_expr,
+            return_line,
             end_name,
             is_forward: false,
+            end_line,
+            end_column,
         })
     }
 
@@ -387,18 +650,30 @@ impl Parser {
     // ========================================================================
 
     fn parse_statement_sequence(&mut self) -> Result<Vec<Statement>, String> {
+        let node_start = self.current;
         let mut statements = Vec::new();
 
         loop {
             if self.is_statement_start() {
-                statements.push(self.parse_statement()?);
-            } else {
-                // Leeres Statement nur wenn Semicolon folgt
-                if self.check(&TokenType::Semicolon) {
-                    statements.push(Statement::Empty);
-                } else {
-                    break;
+                match self.parse_statement() {
+                    Ok(statement) => statements.push(statement),
+                    Err(e) => self.recover(e, STATEMENT_SYNC)?,
                 }
+            } else if self.check(&TokenType::Semicolon) {
+                // Leeres Statement nur wenn Semicolon folgt
+                statements.push(Statement::Empty);
+            } else if self.recovering
+                && !self.is_at_end()
+                && !STATEMENT_END.iter().any(|token_type| self.check(token_type))
+            {
+                let e = format!(
+                    "Anweisung erwartet, gefunden: {} in Zeile {}",
+                    self.peek().token_type,
+                    self.peek().line
+                );
+                self.recover(e, STATEMENT_SYNC)?;
+            } else {
+                break;
             }
 
             if !self.match_token(&TokenType::Semicolon) {
@@ -406,6 +681,7 @@ impl Parser {
             }
         }
 
+        self.finish_node(SyntaxKind::StatementSequence, node_start);
         Ok(statements)
     }
 
@@ -422,35 +698,57 @@ impl Parser {
     }
 
     fn parse_statement(&mut self) -> Result<Statement, String> {
-        match &self.peek().token_type {
+        let (line, column) = (self.peek().line, self.peek().column);
+        let mut statement = match &self.peek().token_type {
             TokenType::If => self.parse_if_statement(),
             TokenType::Case => self.parse_case_statement(),
             TokenType::While => self.parse_while_statement(),
             TokenType::Repeat => self.parse_repeat_statement(),
             TokenType::For => self.parse_for_statement(),
             TokenType::Identifier(_) => self.parse_assignment_or_call(),
-            _ => Ok(Statement::Empty),
-        }
+            _ => return Ok(Statement::Empty),
+        }?;
+
+        // Das Ende liegt hinter dem letzten verbrauchten Token
+        let last = &self.tokens[self.current - 1];
+        statement.set_span(Span {
+            line,
+            column,
+            end_line: last.line,
+            end_column: last.column + last.lexeme.chars().count(),
+        });
+        Ok(statement)
     }
 
     fn parse_assignment_or_call(&mut self) -> Result<Statement, String> {
+        let node_start = self.current;
+        let line = self.peek().line;
         let designator = self.parse_designator()?;
 
         if self.match_token(&TokenType::Assign) {
             let value = self.parse_expression()?;
+            self.finish_node(SyntaxKind::Assignment, node_start);
             Ok(Statement::Assignment {
+                line,
+                span: Span::default(),
                 target: designator,
                 value,
             })
         } else if self.check(&TokenType::LParen) {
             let arguments = self.parse_actual_parameters()?;
+            self.finish_node(SyntaxKind::ProcedureCall, node_start);
             Ok(Statement::ProcedureCall {
+                line,
+                span: Span::default(),
                 designator,
                 arguments,
             })
         } else {
             // Prozeduraufruf ohne Parameter
+            self.finish_node(SyntaxKind::ProcedureCall, node_start);
             Ok(Statement::ProcedureCall {
+                line,
+                span: Span::default(),
                 designator,
                 arguments: Vec::new(),
             })
@@ -458,6 +756,8 @@ impl Parser {
     }
 
     fn parse_if_statement(&mut self) -> Result<Statement, String> {
+        let node_start = self.current;
+        let line = self.peek().line;
         self.expect(TokenType::If)?;
         let condition = self.parse_expression()?;
         self.expect(TokenType::Then)?;
@@ -479,7 +779,10 @@ impl Parser {
 
         self.expect(TokenType::End)?;
 
+        self.finish_node(SyntaxKind::IfStatement, node_start);
         Ok(Statement::If {
+            line,
+            span: Span::default(),
             condition,
             then_body,
             elsif_parts,
@@ -488,6 +791,8 @@ impl Parser {
     }
 
     fn parse_case_statement(&mut self) -> Result<Statement, String> {
+        let node_start = self.current;
+        let line = self.peek().line;
         self.expect(TokenType::Case)?;
         let expr = self.parse_expression()?;
         self.expect(TokenType::Of)?;
@@ -511,7 +816,10 @@ impl Parser {
 
         self.expect(TokenType::End)?;
 
+        self.finish_node(SyntaxKind::CaseStatement, node_start);
         Ok(Statement::Case {
+            line,
+            span: Span::default(),
             expr,
             cases,
             else_body,
@@ -519,6 +827,7 @@ impl Parser {
     }
 
     fn parse_case_clause(&mut self) -> Result<CaseClause, String> {
+        let node_start = self.current;
         let mut labels = Vec::new();
 
         if !self.check(&TokenType::Bar) && !self.check(&TokenType::Else) && !self.check(&TokenType::End) {
@@ -534,10 +843,12 @@ impl Parser {
 
         let body = self.parse_statement_sequence()?;
 
+        self.finish_node(SyntaxKind::CaseClause, node_start);
         Ok(CaseClause { labels, body })
     }
 
     fn parse_case_label(&mut self) -> Result<CaseLabel, String> {
+        let node_start = self.current;
         let start = self.parse_expression()?;
 
         let end = if self.match_token(&TokenType::DotDot) {
@@ -546,10 +857,13 @@ impl Parser {
             None
         };
 
+        self.finish_node(SyntaxKind::CaseLabel, node_start);
         Ok(CaseLabel { start, end })
     }
 
     fn parse_while_statement(&mut self) -> Result<Statement, String> {
+        let node_start = self.current;
+        let line = self.peek().line;
         self.expect(TokenType::While)?;
         let condition = self.parse_expression()?;
         self.expect(TokenType::Do)?;
@@ -565,7 +879,10 @@ impl Parser {
 
         self.expect(TokenType::End)?;
 
+        self.finish_node(SyntaxKind::WhileStatement, node_start);
         Ok(Statement::While {
+            line,
+            span: Span::default(),
             condition,
             body,
             elsif_parts,
@@ -573,16 +890,22 @@ impl Parser {
     }
 
     fn parse_repeat_statement(&mut self) -> Result<Statement, String> {
+        let node_start = self.current;
+        let line = self.peek().line;
         self.expect(TokenType::Repeat)?;
         let body = self.parse_statement_sequence()?;
         self.expect(TokenType::Until)?;
         let condition = self.parse_expression()?;
 
-        Ok(Statement::Repeat { body, condition })
+        self.finish_node(SyntaxKind::RepeatStatement, node_start);
+        Ok(Statement::Repeat { line, span: Span::default(), body, condition })
     }
 
     fn parse_for_statement(&mut self) -> Result<Statement, String> {
+        let node_start = self.current;
+        let line = self.peek().line;
         self.expect(TokenType::For)?;
+        let variable_column = self.peek().column;
         let variable = self.parse_identifier()?;
         self.expect(TokenType::Assign)?;
         let start = self.parse_expression()?;
@@ -599,8 +922,12 @@ impl Parser {
         let body = self.parse_statement_sequence()?;
         self.expect(TokenType::End)?;
 
+        self.finish_node(SyntaxKind::ForStatement, node_start);
         Ok(Statement::For {
+            line,
+            span: Span::default(),
             variable,
+            variable_column,
             start,
             end,
             step,
@@ -613,6 +940,7 @@ impl Parser {
     // ========================================================================
 
     fn parse_expression(&mut self) -> Result<Expression, String> {
+        let node_start = self.current;
         let mut expr = self.parse_simple_expression()?;
 
         if self.is_relation() {
@@ -625,6 +953,7 @@ impl Parser {
             };
         }
 
+        self.finish_node(SyntaxKind::Expression, node_start);
         Ok(expr)
     }
 
@@ -652,7 +981,7 @@ impl Parser {
             TokenType::GreaterEqual => BinaryOp::GreaterEqual,
             TokenType::In => BinaryOp::In,
             TokenType::Is => BinaryOp::Is,
-            _ => return Err(format!("Erwarte Vergleichsoperator, gefunden: {:?}", self.peek())),
+            _ => return Err(self.unexpected("Erwarte Vergleichsoperator")),
         };
         self.advance();
         Ok(op)
@@ -707,7 +1036,7 @@ impl Parser {
             TokenType::Plus => BinaryOp::Add,
             TokenType::Minus => BinaryOp::Sub,
             TokenType::Or => BinaryOp::Or,
-            _ => return Err(format!("Erwarte Additionsoperator, gefunden: {:?}", self.peek())),
+            _ => return Err(self.unexpected("Erwarte Additionsoperator")),
         };
         self.advance();
         Ok(op)
@@ -743,7 +1072,7 @@ impl Parser {
             TokenType::Div => BinaryOp::IntDiv,
             TokenType::Mod => BinaryOp::Mod,
             TokenType::Ampersand => BinaryOp::And,
-            _ => return Err(format!("Erwarte Multiplikationsoperator, gefunden: {:?}", self.peek())),
+            _ => return Err(self.unexpected("Erwarte Multiplikationsoperator")),
         };
         self.advance();
         Ok(op)
@@ -805,11 +1134,12 @@ impl Parser {
                     Ok(Expression::Designator(designator))
                 }
             }
-            _ => Err(format!("Unerwartetes Token in Ausdruck: {:?}", self.peek())),
+            _ => Err(self.unexpected("Ausdruck erwartet")),
         }
     }
 
     fn parse_set(&mut self) -> Result<Expression, String> {
+        let node_start = self.current;
         self.expect(TokenType::LBrace)?;
         let mut elements = Vec::new();
 
@@ -830,10 +1160,12 @@ impl Parser {
         }
 
         self.expect(TokenType::RBrace)?;
+        self.finish_node(SyntaxKind::Set, node_start);
         Ok(Expression::Set(elements))
     }
 
     fn parse_actual_parameters(&mut self) -> Result<Vec<Expression>, String> {
+        let node_start = self.current;
         self.expect(TokenType::LParen)?;
         let mut args = Vec::new();
 
@@ -847,6 +1179,7 @@ impl Parser {
         }
 
         self.expect(TokenType::RParen)?;
+        self.finish_node(SyntaxKind::ActualParameters, node_start);
         Ok(args)
     }
 
@@ -855,6 +1188,7 @@ impl Parser {
     // ========================================================================
 
     fn parse_designator(&mut self) -> Result<Designator, String> {
+        let node_start = self.current;
         let base = self.parse_qualident()?;
         let mut selectors = Vec::new();
 
@@ -862,8 +1196,9 @@ impl Parser {
             match &self.peek().token_type {
                 TokenType::Period => {
                     self.advance();
-                    let field = self.parse_identifier()?;
-                    selectors.push(Selector::Field(field));
+                    let (line, column) = (self.peek().line, self.peek().column);
+                    let name = self.parse_identifier()?;
+                    selectors.push(Selector::Field { name, line, column });
                 }
                 TokenType::LBracket => {
                     self.advance();
@@ -881,19 +1216,42 @@ impl Parser {
                     self.advance();
                     selectors.push(Selector::Dereference);
                 }
-                // TypeGuard entfernt - wird später bei Bedarf hinzugefügt
+                // s(T) ist ein Typwächter, wenn ein Selektor folgt; sonst
+                // entscheidet der Typprüfer zwischen Aufruf und Typwächter
+                TokenType::LParen if self.is_type_guard() => {
+                    self.advance();
+                    let guard = self.parse_qualident()?;
+                    self.expect(TokenType::RParen)?;
+                    selectors.push(Selector::TypeGuard(guard));
+                }
                 _ => break,
             }
         }
 
+        self.finish_node(SyntaxKind::Designator, node_start);
         Ok(Designator { base, selectors })
     }
 
+    // ( Qualident ) gefolgt von . [ oder ^; Ergebnisse von Funktionsaufrufen
+    // haben keine Selektoren
+    fn is_type_guard(&self) -> bool {
+        let is = |offset: usize, expected: fn(&TokenType) -> bool| {
+            self.peek_ahead(offset).is_some_and(|token| expected(&token.token_type))
+        };
+        let identifier = |token: &TokenType| matches!(token, TokenType::Identifier(_));
+        let close = if is(2, |token| *token == TokenType::Period) && is(3, identifier) { 4 } else { 2 };
+        is(1, identifier)
+            && is(close, |token| *token == TokenType::RParen)
+            && is(close + 1, |token| matches!(token, TokenType::Period | TokenType::LBracket | TokenType::Caret))
+    }
+
     // ========================================================================
     // Hilfsfunktionen
     // ========================================================================
 
     fn parse_ident_def(&mut self) -> Result<IdentDef, String> {
+        let node_start = self.current;
+        let (line, column) = (self.peek().line, self.peek().column);
         let name = self.parse_identifier()?;
         let exported = if self.match_token(&TokenType::Times) {
             ExportMark::ReadOnly
@@ -903,17 +1261,40 @@ impl Parser {
             ExportMark::None
         };
 
-        Ok(IdentDef { name, exported })
+        self.finish_node(SyntaxKind::IdentDef, node_start);
+        Ok(IdentDef {
+            name,
+            exported,
+            line,
+            column,
+        })
     }
 
     fn parse_qualident(&mut self) -> Result<Qualident, String> {
+        let node_start = self.current;
+        let (line, column) = (self.peek().line, self.peek().column);
         let first = self.parse_identifier()?;
 
         if self.match_token(&TokenType::Period) {
+            let name_column = self.peek().column;
             let second = self.parse_identifier()?;
-            Ok(Qualident::with_module(first, second))
+            self.finish_node(SyntaxKind::Qualident, node_start);
+            Ok(Qualident {
+                module: Some(first),
+                name: second,
+                line,
+                column,
+                name_column,
+            })
         } else {
-            Ok(Qualident::new(first))
+            self.finish_node(SyntaxKind::Qualident, node_start);
+            Ok(Qualident {
+                module: None,
+                name: first,
+                line,
+                column,
+                name_column: column,
+            })
         }
     }
 
@@ -934,7 +1315,7 @@ impl Parser {
             self.advance();
             Ok(n)
         } else {
-            Err(format!("Erwarte Identifier, gefunden: {:?}", self.peek()))
+            Err(self.unexpected("Erwarte Bezeichner"))
         }
     }
 
@@ -981,13 +1362,18 @@ impl Parser {
         }
     }
 
+    // Meldung für ein unpassendes aktuelles Token, mit Zeile
+    fn unexpected(&self, expected: &str) -> String {
+        format!("{}, gefunden: {} in Zeile {}", expected, self.peek().token_type, self.peek().line)
+    }
+
     fn expect(&mut self, token_type: TokenType) -> Result<(), String> {
         if self.check(&token_type) {
             self.advance();
             Ok(())
         } else {
             Err(format!(
-                "Erwarte {:?}, gefunden: {:?} in Zeile {}",
+                "Erwarte {}, gefunden: {} in Zeile {}",
                 token_type,
                 self.peek().token_type,
                 self.peek().line
@@ -1,6 +1,10 @@
 pub mod ast;
-pub mod parser;
+pub mod cst;
+pub mod doc;
+pub mod grammar;
 pub mod pretty_printer;
 
-pub use parser::Parser;
-pub use pretty_printer::PrettyPrinter;
\ No newline at end of file
+pub use doc::Doc;
+pub use cst::{SyntaxElement, SyntaxKind, SyntaxNode};
+pub use grammar::Parser;
+pub use pretty_printer::PrettyPrinter;
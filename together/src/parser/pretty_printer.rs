@@ -1,432 +1,538 @@
 use super::ast::*;
-use std::fmt::Write;
+use super::doc::{self, Doc};
+use std::fmt::Display;
+
+// ============================================================================
+// Pretty Printer
+// ============================================================================
+//
+// Gibt den AST als Layout-Dokument (siehe doc) aus: Blöcke eingerückt,
+// lange Ausdrücke, Argument- und Parameterlisten bei Bedarf umbrochen.
+// Die Layout-Bausteine (block, operators, list, aligned, clause ...) benutzt
+// auch der Formatierer, der statt des AST den konkreten Syntaxbaum samt
+// Kommentaren ausgibt.
 
 pub struct PrettyPrinter {
-    indent_level: usize,
-    indent_string: String,
+    indent: usize,
+    width: usize,
+    // Namen und Typen der Deklarationen eines Abschnitts untereinander ausrichten
+    align: bool,
+    // Schnittstelle: DEFINITION-Kopf, Prozeduren nur mit Signatur
+    definition: bool,
+}
+
+// Eine Deklaration oder ein Record-Feld, aufgeteilt an der Ausrichtung
+pub struct AlignedEntry {
+    // Leerzeile davor
+    pub blank: bool,
+    pub name: Doc,
+    pub rest: Vec<Doc>,
 }
 
 impl PrettyPrinter {
     pub fn new() -> Self {
+        Self::with_layout(2, 80)
+    }
+
+    // Einrückung pro Ebene und höchste Zeilenbreite
+    pub fn with_layout(indent: usize, width: usize) -> Self {
         PrettyPrinter {
-            indent_level: 0,
-            indent_string: "  ".to_string(), // 2 Leerzeichen pro Ebene
+            indent,
+            width,
+            align: false,
+            definition: false,
         }
     }
 
+    pub fn set_align(&mut self, align: bool) {
+        self.align = align;
+    }
+
+    pub fn render(&self, doc: &Doc) -> String {
+        doc::render(doc, self.width)
+    }
+
+    // Schnittstelle des Moduls im Stil von Oberons DEFINITION: nur exportierte
+    // Konstanten, Typen (mit exportierten Feldern), Variablen und
+    // Prozedurköpfe. `*` entfällt, da alles Gezeigte exportiert ist; `-` für
+    // nur lesbare Exporte bleibt stehen.
+    pub fn print_definition(&mut self, module: &Module) -> String {
+        let interface = Module {
+            name: module.name.clone(),
+            imports: module.imports.clone(),
+            declarations: interface_declarations(&module.declarations),
+            body: None,
+            end_name: module.name.clone(),
+        };
+
+        self.definition = true;
+        let output = self.print_module(&interface);
+        self.definition = false;
+        output
+    }
+
     pub fn print_module(&mut self, module: &Module) -> String {
-        let mut output = String::new();
+        let doc = self.module(module);
+        self.render(&doc)
+    }
 
-        // MODULE header
-        writeln!(output, "MODULE {};", module.name).unwrap();
-        writeln!(output).unwrap();
+    // Einzeilig, etwa für Signaturen in der Dokumentation
+    pub fn print_expression(&mut self, output: &mut String, expr: &Expression) {
+        output.push_str(&doc::render(&self.expression(expr), usize::MAX));
+    }
 
-        // Imports
-        if !module.imports.is_empty() {
-            self.print_imports(&mut output, &module.imports);
-            writeln!(output).unwrap();
-        }
+    // ========================================================================
+    // Layout
+    // ========================================================================
 
-        // Declarations
-        self.print_decl_sequence(&mut output, &module.declarations);
+    // Eingerückter Rumpf ab der nächsten Zeile
+    pub fn block(&self, body: Doc) -> Doc {
+        Doc::nest(self.indent, Doc::concat(vec![Doc::HardLine, body]))
+    }
 
-        // Body
-        if let Some(body) = &module.body {
-            if !body.is_empty() {
-                writeln!(output, "BEGIN").unwrap();
-                self.indent_level += 1;
-                self.print_statement_sequence(&mut output, body);
-                self.indent_level -= 1;
+    // Ausdruck, der bei Bedarf eingerückt auf den Folgezeilen weiterläuft
+    pub fn breakable(&self, doc: Doc) -> Doc {
+        Doc::group(Doc::nest(self.indent, doc))
+    }
+
+    // Rumpf auf der Zeile des Kopfes, falls er passt (CASE-Zweige, ELSE)
+    pub fn clause(&self, head: Doc, body: Doc) -> Doc {
+        let body = Doc::group(Doc::nest(self.indent, Doc::concat(vec![Doc::Line, body])));
+        Doc::concat(vec![head, body])
+    }
+
+    // CASE-Zweig mit eingerückten Folgezeilen; der erste beginnt auf einer
+    // neuen Zeile, die weiteren hinter |
+    pub fn case_clause(&self, first: bool, clause: Doc) -> Doc {
+        let start = if first { Doc::nest(self.indent, Doc::HardLine) } else { Doc::text(" ") };
+        Doc::concat(vec![start, Doc::nest(self.indent, clause)])
+    }
+
+    // ( a, b ) bzw. ( x: T; VAR y: U ): R, bei Bedarf je Eintrag eine Zeile;
+    // die Einträge tragen ihr Trennzeichen selbst
+    pub fn list(&self, open: Doc, items: Vec<Doc>, close: Doc) -> Doc {
+        let mut inner = vec![Doc::SoftLine];
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                inner.push(Doc::Line);
             }
+            inner.push(item);
         }
+        Doc::group(Doc::concat(vec![open, Doc::nest(self.indent, Doc::concat(inner)), Doc::SoftLine, close]))
+    }
 
-        // END
-        writeln!(output, "END {}.", module.end_name).unwrap();
+    // Verbindet Operanden an den Operatoren der schwächsten Stufe; stärker
+    // bindende Teilausdrücke bilden eigene Gruppen und brechen erst danach
+    pub fn operators(&self, mut operands: Vec<Doc>, operators: Vec<(u8, Doc)>) -> Doc {
+        let Some(lowest) = operators.iter().map(|(precedence, _)| *precedence).min() else {
+            return operands.pop().unwrap_or(Doc::Concat(Vec::new()));
+        };
 
-        output
+        let segment = |mut operands: Vec<Doc>, operators: Vec<(u8, Doc)>| {
+            if operators.is_empty() {
+                operands.pop().unwrap_or(Doc::Concat(Vec::new()))
+            } else {
+                Doc::group(self.operators(operands, operators))
+            }
+        };
+
+        let mut docs = Vec::new();
+        let mut operands = operands.into_iter();
+        let mut segment_operands: Vec<Doc> = operands.next().into_iter().collect();
+        let mut segment_operators = Vec::new();
+        for ((precedence, operator), operand) in operators.into_iter().zip(operands) {
+            if precedence == lowest {
+                docs.push(segment(std::mem::take(&mut segment_operands), std::mem::take(&mut segment_operators)));
+                docs.extend([Doc::text(" "), operator, Doc::Line]);
+            } else {
+                segment_operators.push((precedence, operator));
+            }
+            segment_operands.push(operand);
+        }
+        docs.push(segment(segment_operands, segment_operators));
+        Doc::concat(docs)
+    }
+
+    // Eingerückte Deklarationen, je eine pro Zeile
+    pub fn aligned(&self, entries: Vec<AlignedEntry>) -> Doc {
+        let widths: Vec<usize> = entries
+            .iter()
+            .map(|entry| {
+                let text = doc::render(&entry.name, usize::MAX);
+                text.trim_end_matches('\n').rsplit('\n').next().unwrap_or("").chars().count()
+            })
+            .collect();
+        let column = widths.iter().copied().max().unwrap_or(0);
+
+        let mut docs = Vec::new();
+        for (i, (entry, width)) in entries.into_iter().zip(widths).enumerate() {
+            docs.push(Doc::newlines(if i > 0 && entry.blank { 2 } else { 1 }));
+            docs.push(entry.name);
+            if self.align && column > width {
+                docs.push(Doc::text(" ".repeat(column - width)));
+            }
+            docs.extend(entry.rest);
+        }
+        Doc::nest(self.indent, Doc::concat(docs))
     }
 
     // ========================================================================
-    // Imports
+    // Module und Deklarationen
     // ========================================================================
 
-    fn print_imports(&mut self, output: &mut String, imports: &[Import]) {
-        write!(output, "IMPORT").unwrap();
+    fn module(&self, module: &Module) -> Doc {
+        let keyword = if self.definition { "DEFINITION" } else { "MODULE" };
+        let mut docs = vec![Doc::text(format!("{} {};", keyword, module.name))];
+
+        if !module.imports.is_empty() {
+            docs.push(Doc::BlankLine);
+            docs.push(self.imports(&module.imports));
+        }
+        if let Some(declarations) = self.decl_sequence(&module.declarations) {
+            docs.push(Doc::BlankLine);
+            docs.push(declarations);
+        }
+
+        match &module.body {
+            Some(body) if !body.is_empty() => {
+                docs.push(Doc::BlankLine);
+                docs.push(Doc::text("BEGIN"));
+                docs.push(self.block(self.statement_sequence(body)));
+                docs.push(Doc::HardLine);
+            }
+            _ => docs.push(Doc::BlankLine),
+        }
+        docs.push(Doc::text(format!("END {}.", module.end_name)));
+        docs.push(Doc::HardLine);
+        Doc::concat(docs)
+    }
+
+    fn imports(&self, imports: &[Import]) -> Doc {
+        let mut docs = vec![Doc::text("IMPORT ")];
         for (i, import) in imports.iter().enumerate() {
             if i > 0 {
-                write!(output, ",").unwrap();
-            }
-            write!(output, " ").unwrap();
-            if let Some(alias) = &import.alias {
-                write!(output, "{} := {}", alias, import.module_name).unwrap();
-            } else {
-                write!(output, "{}", import.module_name).unwrap();
-            }
+                docs.push(Doc::text(","));
+                docs.push(Doc::Line);
+            }
+            let text = match &import.alias {
+                Some(alias) => format!("{} := {}", alias, import.module_name),
+                None => import.module_name.clone(),
+            };
+            docs.push(Doc::text(text));
         }
-        writeln!(output, ";").unwrap();
+        docs.push(Doc::text(";"));
+        self.breakable(Doc::concat(docs))
     }
 
-    // ========================================================================
-    // Deklarationen
-    // ========================================================================
+    // Abschnitte und Prozeduren durch Leerzeilen getrennt; None ohne Deklarationen
+    fn decl_sequence(&self, decls: &DeclSequence) -> Option<Doc> {
+        let mut parts = Vec::new();
 
-    fn print_decl_sequence(&mut self, output: &mut String, decls: &DeclSequence) {
-        // Constants
         if !decls.constants.is_empty() {
-            writeln!(output, "CONST").unwrap();
-            self.indent_level += 1;
-            for const_decl in &decls.constants {
-                self.print_indent(output);
-                write!(output, "{} = ", const_decl.name).unwrap();
-                self.print_expression(output, &const_decl.value);
-                writeln!(output, ";").unwrap();
-            }
-            self.indent_level -= 1;
-            writeln!(output).unwrap();
+            let entries = decls
+                .constants
+                .iter()
+                .map(|constant| AlignedEntry {
+                    blank: false,
+                    name: Doc::text(constant.name.to_string()),
+                    rest: vec![Doc::text(" = "), self.breakable(self.expression(&constant.value)), Doc::text(";")],
+                })
+                .collect();
+            parts.push(Doc::concat(vec![Doc::text("CONST"), self.aligned(entries)]));
         }
 
-        // Types
         if !decls.types.is_empty() {
-            writeln!(output, "TYPE").unwrap();
-            self.indent_level += 1;
-            for type_decl in &decls.types {
-                self.print_indent(output);
-                write!(output, "{} = ", type_decl.name).unwrap();
-                self.print_type(output, &type_decl.type_def);
-                writeln!(output, ";").unwrap();
-            }
-            self.indent_level -= 1;
-            writeln!(output).unwrap();
+            let entries = decls
+                .types
+                .iter()
+                .map(|declaration| AlignedEntry {
+                    blank: false,
+                    name: Doc::text(declaration.name.to_string()),
+                    rest: vec![Doc::text(" = "), self.type_doc(&declaration.type_def), Doc::text(";")],
+                })
+                .collect();
+            parts.push(Doc::concat(vec![Doc::text("TYPE"), self.aligned(entries)]));
         }
 
-        // Variables
         if !decls.variables.is_empty() {
-            writeln!(output, "VAR").unwrap();
-            self.indent_level += 1;
-            for var_decl in &decls.variables {
-                self.print_indent(output);
-                for (i, name) in var_decl.names.iter().enumerate() {
-                    if i > 0 {
-                        write!(output, ", ").unwrap();
-                    }
-                    write!(output, "{}", name).unwrap();
-                }
-                write!(output, ": ").unwrap();
-                self.print_type(output, &var_decl.var_type);
-                writeln!(output, ";").unwrap();
-            }
-            self.indent_level -= 1;
-            writeln!(output).unwrap();
+            let entries = decls
+                .variables
+                .iter()
+                .map(|declaration| AlignedEntry {
+                    blank: false,
+                    name: Doc::text(format!("{}:", names(&declaration.names))),
+                    rest: vec![Doc::text(" "), self.type_doc(&declaration.var_type), Doc::text(";")],
+                })
+                .collect();
+            parts.push(Doc::concat(vec![Doc::text("VAR"), self.aligned(entries)]));
+        }
+
+        for procedure in &decls.procedures {
+            parts.push(self.procedure(procedure));
         }
 
-        // Procedures
-        for proc_decl in &decls.procedures {
-            self.print_procedure(output, proc_decl);
-            writeln!(output).unwrap();
+        if parts.is_empty() {
+            return None;
         }
+        let mut docs = Vec::new();
+        for (i, part) in parts.into_iter().enumerate() {
+            if i > 0 {
+                docs.push(Doc::BlankLine);
+            }
+            docs.push(part);
+        }
+        Some(Doc::concat(docs))
     }
 
     // ========================================================================
     // Typen
     // ========================================================================
 
-    fn print_type(&mut self, output: &mut String, type_def: &Type) {
+    fn type_doc(&self, type_def: &Type) -> Doc {
         match type_def {
-            Type::Qualident(qualident) => {
-                write!(output, "{}", qualident).unwrap();
-            }
+            Type::Qualident(qualident) => Doc::text(qualident.to_string()),
             Type::Array { lengths, element_type } => {
-                write!(output, "ARRAY ").unwrap();
+                let mut docs = vec![Doc::text("ARRAY ")];
                 for (i, length) in lengths.iter().enumerate() {
                     if i > 0 {
-                        write!(output, ", ").unwrap();
+                        docs.push(Doc::text(", "));
                     }
-                    self.print_expression(output, length);
+                    docs.push(self.expression(length));
                 }
-                write!(output, " OF ").unwrap();
-                self.print_type(output, element_type);
+                docs.push(Doc::text(" OF "));
+                docs.push(self.type_doc(element_type));
+                Doc::concat(docs)
             }
             Type::Record { base_type, fields } => {
-                write!(output, "RECORD").unwrap();
-                if let Some(base) = base_type {
-                    write!(output, "({})", base).unwrap();
-                }
-                if !fields.is_empty() {
-                    writeln!(output).unwrap();
-                    self.indent_level += 1;
-                    for (i, field) in fields.iter().enumerate() {
-                        if i > 0 {
-                            writeln!(output, ";").unwrap();
-                        }
-                        self.print_indent(output);
-                        for (j, name) in field.names.iter().enumerate() {
-                            if j > 0 {
-                                write!(output, ", ").unwrap();
-                            }
-                            write!(output, "{}", name).unwrap();
-                        }
-                        write!(output, ": ").unwrap();
-                        self.print_type(output, &field.field_type);
-                    }
-                    writeln!(output).unwrap();
-                    self.indent_level -= 1;
-                    self.print_indent(output);
+                let head = match base_type {
+                    Some(base) => format!("RECORD ({})", base),
+                    None => "RECORD".to_string(),
+                };
+                if fields.is_empty() {
+                    return Doc::text(format!("{} END", head));
                 }
-                write!(output, "END").unwrap();
-            }
-            Type::Pointer { target_type } => {
-                write!(output, "POINTER TO ").unwrap();
-                self.print_type(output, target_type);
-            }
+
+                let last = fields.len() - 1;
+                let entries = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| AlignedEntry {
+                        blank: false,
+                        name: Doc::text(format!("{}:", names(&field.names))),
+                        rest: vec![
+                            Doc::text(" "),
+                            self.type_doc(&field.field_type),
+                            Doc::text(if i < last { ";" } else { "" }),
+                        ],
+                    })
+                    .collect();
+                Doc::concat(vec![Doc::text(head), self.aligned(entries), Doc::HardLine, Doc::text("END")])
+            }
+            Type::Pointer { target_type } => Doc::concat(vec![Doc::text("POINTER TO "), self.type_doc(target_type)]),
             Type::Procedure { params } => {
-                write!(output, "PROCEDURE").unwrap();
-                if let Some(p) = params {
-                    self.print_formal_parameters(output, p);
+                let mut docs = vec![Doc::text("PROCEDURE")];
+                if let Some(params) = params {
+                    docs.push(self.formal_parameters(params));
                 }
+                Doc::concat(docs)
             }
         }
     }
 
-    fn print_formal_parameters(&mut self, output: &mut String, params: &FormalParameters) {
-        write!(output, "(").unwrap();
-        for (i, section) in params.sections.iter().enumerate() {
-            if i > 0 {
-                write!(output, "; ").unwrap();
-            }
-            if section.is_var {
-                write!(output, "VAR ").unwrap();
-            }
-            for (j, name) in section.names.iter().enumerate() {
-                if j > 0 {
-                    write!(output, ", ").unwrap();
-                }
-                write!(output, "{}", name).unwrap();
-            }
-            write!(output, ": ").unwrap();
-            self.print_type(output, &section.param_type);
-        }
-        write!(output, ")").unwrap();
-        if let Some(ret) = &params.return_type {
-            write!(output, ": {}", ret).unwrap();
-        }
+    fn formal_parameters(&self, params: &FormalParameters) -> Doc {
+        let last = params.sections.len().saturating_sub(1);
+        let items = params
+            .sections
+            .iter()
+            .enumerate()
+            .map(|(i, section)| {
+                let var = if section.is_var { "VAR " } else { "" };
+                Doc::concat(vec![
+                    Doc::text(format!("{}{}: ", var, names(&section.names))),
+                    self.type_doc(&section.param_type),
+                    Doc::text(if i < last { ";" } else { "" }),
+                ])
+            })
+            .collect();
+        let close = match &params.return_type {
+            Some(return_type) => format!("): {}", return_type),
+            None => ")".to_string(),
+        };
+        self.list(Doc::text("("), items, Doc::text(close))
     }
 
     // ========================================================================
     // Prozeduren
     // ========================================================================
 
-    fn print_procedure(&mut self, output: &mut String, proc: &ProcedureDeclaration) {
-        self.print_indent(output);
-        write!(output, "PROCEDURE").unwrap();
-
-        if proc.is_forward {
-            write!(output, " ^").unwrap();
-        }
-
-        write!(output, " {}", proc.name).unwrap();
-
+    fn procedure(&self, proc: &ProcedureDeclaration) -> Doc {
+        let forward = if proc.is_forward { "^ " } else { "" };
+        let mut heading = vec![Doc::text(format!("PROCEDURE {}{}", forward, proc.name))];
         if let Some(params) = &proc.params {
-            self.print_formal_parameters(output, params);
+            heading.push(self.formal_parameters(params));
         }
+        heading.push(Doc::text(";"));
 
-        writeln!(output, ";").unwrap();
-
-        if proc.is_forward {
-            return;
+        let mut docs = vec![Doc::concat(heading)];
+        if proc.is_forward || self.definition {
+            return Doc::concat(docs);
         }
 
-        self.indent_level += 1;
-
-        // Declarations
-        if !proc.declarations.constants.is_empty()
-            || !proc.declarations.types.is_empty()
-            || !proc.declarations.variables.is_empty()
-            || !proc.declarations.procedures.is_empty()
-        {
-            self.print_decl_sequence(output, &proc.declarations);
+        if let Some(declarations) = self.decl_sequence(&proc.declarations) {
+            docs.push(self.block(declarations));
         }
-
-        // Body
         if let Some(body) = &proc.body {
             if !body.is_empty() {
-                self.print_indent(output);
-                writeln!(output, "BEGIN").unwrap();
-                self.indent_level += 1;
-                self.print_statement_sequence(output, body);
-                self.indent_level -= 1;
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text("BEGIN"));
+                docs.push(self.block(self.statement_sequence(body)));
             }
         }
-
-        // Return
-        if let Some(ret_expr) = &proc.return_expr {
-            self.print_indent(output);
-            write!(output, "RETURN ").unwrap();
-            self.print_expression(output, ret_expr);
-            writeln!(output).unwrap();
+        if let Some(return_expr) = &proc.return_expr {
+            let line = Doc::concat(vec![Doc::text("RETURN "), self.breakable(self.expression(return_expr))]);
+            docs.push(self.block(line));
         }
-
-        self.indent_level -= 1;
-        self.print_indent(output);
-        writeln!(output, "END {};", proc.end_name).unwrap();
+        docs.push(Doc::HardLine);
+        docs.push(Doc::text(format!("END {};", proc.end_name)));
+        Doc::concat(docs)
     }
 
     // ========================================================================
     // Statements
     // ========================================================================
 
-    fn print_statement_sequence(&mut self, output: &mut String, statements: &[Statement]) {
-        for (i, stmt) in statements.iter().enumerate() {
-            if i > 0 && !matches!(stmt, Statement::Empty) {
-                writeln!(output, ";").unwrap();
+    fn statement_sequence(&self, statements: &[Statement]) -> Doc {
+        let mut docs = Vec::new();
+        for stmt in statements.iter().filter(|stmt| !matches!(stmt, Statement::Empty)) {
+            if !docs.is_empty() {
+                docs.push(Doc::text(";"));
+                docs.push(Doc::HardLine);
             }
-            self.print_statement(output, stmt);
+            docs.push(self.statement(stmt));
         }
-        writeln!(output).unwrap();
+        Doc::concat(docs)
     }
 
-    fn print_statement(&mut self, output: &mut String, stmt: &Statement) {
+    // Bedingung zwischen zwei Schlüsselwörtern: IF c THEN, WHILE c DO
+    fn condition(&self, keyword: &str, condition: &Expression, then: &str) -> Doc {
+        Doc::concat(vec![
+            Doc::text(format!("{} ", keyword)),
+            self.breakable(self.expression(condition)),
+            Doc::text(format!(" {}", then)),
+        ])
+    }
+
+    fn statement(&self, stmt: &Statement) -> Doc {
         match stmt {
-            Statement::Empty => {}
-            Statement::Assignment { target, value } => {
-                self.print_indent(output);
-                self.print_designator(output, target);
-                write!(output, " := ").unwrap();
-                self.print_expression(output, value);
-            }
-            Statement::ProcedureCall { designator, arguments } => {
-                self.print_indent(output);
-                self.print_designator(output, designator);
+            Statement::Empty => Doc::Concat(Vec::new()),
+            Statement::Assignment { target, value, .. } => Doc::concat(vec![
+                self.designator(target),
+                Doc::text(" := "),
+                self.breakable(self.expression(value)),
+            ]),
+            Statement::ProcedureCall { designator, arguments, .. } => {
+                let mut docs = vec![self.designator(designator)];
                 if !arguments.is_empty() {
-                    write!(output, "(").unwrap();
-                    for (i, arg) in arguments.iter().enumerate() {
-                        if i > 0 {
-                            write!(output, ", ").unwrap();
-                        }
-                        self.print_expression(output, arg);
-                    }
-                    write!(output, ")").unwrap();
+                    docs.push(self.arguments(arguments));
                 }
-            }
-            Statement::If { condition, then_body, elsif_parts, else_body } => {
-                self.print_indent(output);
-                write!(output, "IF ").unwrap();
-                self.print_expression(output, condition);
-                writeln!(output, " THEN").unwrap();
-                self.indent_level += 1;
-                self.print_statement_sequence(output, then_body);
-                self.indent_level -= 1;
-
-                for (elsif_cond, elsif_body) in elsif_parts {
-                    self.print_indent(output);
-                    write!(output, "ELSIF ").unwrap();
-                    self.print_expression(output, elsif_cond);
-                    writeln!(output, " THEN").unwrap();
-                    self.indent_level += 1;
-                    self.print_statement_sequence(output, elsif_body);
-                    self.indent_level -= 1;
+                Doc::concat(docs)
+            }
+            Statement::If { condition, then_body, elsif_parts, else_body, .. } => {
+                let mut docs = vec![
+                    self.condition("IF", condition, "THEN"),
+                    self.block(self.statement_sequence(then_body)),
+                ];
+                for (elsif_condition, elsif_body) in elsif_parts {
+                    docs.push(Doc::HardLine);
+                    docs.push(self.condition("ELSIF", elsif_condition, "THEN"));
+                    docs.push(self.block(self.statement_sequence(elsif_body)));
                 }
-
-                if let Some(else_stmts) = else_body {
-                    self.print_indent(output);
-                    writeln!(output, "ELSE").unwrap();
-                    self.indent_level += 1;
-                    self.print_statement_sequence(output, else_stmts);
-                    self.indent_level -= 1;
+                if let Some(else_body) = else_body {
+                    docs.push(Doc::HardLine);
+                    docs.push(Doc::text("ELSE"));
+                    docs.push(self.block(self.statement_sequence(else_body)));
                 }
-
-                self.print_indent(output);
-                write!(output, "END").unwrap();
-            }
-            Statement::Case { expr, cases, else_body } => {
-                self.print_indent(output);
-                write!(output, "CASE ").unwrap();
-                self.print_expression(output, expr);
-                writeln!(output, " OF").unwrap();
-                self.indent_level += 1;
-
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text("END"));
+                Doc::concat(docs)
+            }
+            //   CASE k OF
+            //     0: a := 1
+            //   | 1, 2:
+            //       b := 2;
+            //       c := 3
+            //   ELSE d := 4
+            //   END
+            Statement::Case { expr, cases, else_body, .. } => {
+                let mut docs = vec![self.condition("CASE", expr, "OF")];
                 for (i, case) in cases.iter().enumerate() {
                     if i > 0 {
-                        writeln!(output).unwrap();
-                        self.print_indent(output);
-                        writeln!(output, "|").unwrap();
+                        docs.push(Doc::HardLine);
+                        docs.push(Doc::text("|"));
                     }
-                    self.print_indent(output);
+
+                    let mut labels = Vec::new();
                     for (j, label) in case.labels.iter().enumerate() {
                         if j > 0 {
-                            write!(output, ", ").unwrap();
+                            labels.push(Doc::text(", "));
                         }
-                        self.print_expression(output, &label.start);
+                        labels.push(self.expression(&label.start));
                         if let Some(end) = &label.end {
-                            write!(output, "..").unwrap();
-                            self.print_expression(output, end);
+                            labels.push(Doc::text(".."));
+                            labels.push(self.expression(end));
                         }
                     }
-                    writeln!(output, ":").unwrap();
-                    self.indent_level += 1;
-                    self.print_statement_sequence(output, &case.body);
-                    self.indent_level -= 1;
+                    labels.push(Doc::text(":"));
+                    let clause = self.clause(Doc::concat(labels), self.statement_sequence(&case.body));
+                    docs.push(self.case_clause(i == 0, clause));
                 }
-
-                if let Some(else_stmts) = else_body {
-                    self.print_indent(output);
-                    writeln!(output, "ELSE").unwrap();
-                    self.indent_level += 1;
-                    self.print_statement_sequence(output, else_stmts);
-                    self.indent_level -= 1;
+                if let Some(else_body) = else_body {
+                    docs.push(Doc::HardLine);
+                    docs.push(self.clause(Doc::text("ELSE"), self.statement_sequence(else_body)));
                 }
-
-                self.indent_level -= 1;
-                self.print_indent(output);
-                write!(output, "END").unwrap();
-            }
-            Statement::While { condition, body, elsif_parts } => {
-                self.print_indent(output);
-                write!(output, "WHILE ").unwrap();
-                self.print_expression(output, condition);
-                writeln!(output, " DO").unwrap();
-                self.indent_level += 1;
-                self.print_statement_sequence(output, body);
-                self.indent_level -= 1;
-
-                for (elsif_cond, elsif_body) in elsif_parts {
-                    self.print_indent(output);
-                    write!(output, "ELSIF ").unwrap();
-                    self.print_expression(output, elsif_cond);
-                    writeln!(output, " DO").unwrap();
-                    self.indent_level += 1;
-                    self.print_statement_sequence(output, elsif_body);
-                    self.indent_level -= 1;
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text("END"));
+                Doc::concat(docs)
+            }
+            Statement::While { condition, body, elsif_parts, .. } => {
+                let mut docs = vec![
+                    self.condition("WHILE", condition, "DO"),
+                    self.block(self.statement_sequence(body)),
+                ];
+                for (elsif_condition, elsif_body) in elsif_parts {
+                    docs.push(Doc::HardLine);
+                    docs.push(self.condition("ELSIF", elsif_condition, "DO"));
+                    docs.push(self.block(self.statement_sequence(elsif_body)));
                 }
-
-                self.print_indent(output);
-                write!(output, "END").unwrap();
-            }
-            Statement::Repeat { body, condition } => {
-                self.print_indent(output);
-                writeln!(output, "REPEAT").unwrap();
-                self.indent_level += 1;
-                self.print_statement_sequence(output, body);
-                self.indent_level -= 1;
-                self.print_indent(output);
-                write!(output, "UNTIL ").unwrap();
-                self.print_expression(output, condition);
-            }
-            Statement::For { variable, start, end, step, body } => {
-                self.print_indent(output);
-                write!(output, "FOR {} := ", variable).unwrap();
-                self.print_expression(output, start);
-                write!(output, " TO ").unwrap();
-                self.print_expression(output, end);
-                if let Some(step_expr) = step {
-                    write!(output, " BY ").unwrap();
-                    self.print_expression(output, step_expr);
+                docs.push(Doc::HardLine);
+                docs.push(Doc::text("END"));
+                Doc::concat(docs)
+            }
+            Statement::Repeat { body, condition, .. } => Doc::concat(vec![
+                Doc::text("REPEAT"),
+                self.block(self.statement_sequence(body)),
+                Doc::HardLine,
+                Doc::text("UNTIL "),
+                self.breakable(self.expression(condition)),
+            ]),
+            Statement::For { variable, start, end, step, body, .. } => {
+                let mut head = vec![
+                    Doc::text(format!("FOR {} := ", variable)),
+                    self.expression(start),
+                    Doc::text(" TO "),
+                    self.expression(end),
+                ];
+                if let Some(step) = step {
+                    head.push(Doc::text(" BY "));
+                    head.push(self.expression(step));
                 }
-                writeln!(output, " DO").unwrap();
-                self.indent_level += 1;
-                self.print_statement_sequence(output, body);
-                self.indent_level -= 1;
-                self.print_indent(output);
-                write!(output, "END").unwrap();
+                head.push(Doc::text(" DO"));
+                Doc::concat(vec![
+                    self.breakable(Doc::concat(head)),
+                    self.block(self.statement_sequence(body)),
+                    Doc::HardLine,
+                    Doc::text("END"),
+                ])
             }
         }
     }
@@ -435,141 +541,264 @@ impl PrettyPrinter {
     // Ausdrücke
     // ========================================================================
 
-    fn print_expression(&mut self, output: &mut String, expr: &Expression) {
+    fn expression(&self, expr: &Expression) -> Doc {
         match expr {
-            Expression::IntegerLiteral(val) => {
-                write!(output, "{}", val).unwrap();
-            }
-            Expression::RealLiteral(val) => {
-                write!(output, "{}", val).unwrap();
-            }
-            Expression::StringLiteral(val) => {
-                write!(output, "\"{}\"", val).unwrap();
-            }
-            Expression::BooleanLiteral(val) => {
-                write!(output, "{}", if *val { "TRUE" } else { "FALSE" }).unwrap();
-            }
-            Expression::Nil => {
-                write!(output, "NIL").unwrap();
-            }
+            Expression::IntegerLiteral(val) => Doc::text(val.to_string()),
+            Expression::RealLiteral(val) => Doc::text(val.to_string()),
+            Expression::StringLiteral(val) => Doc::text(format!("\"{}\"", val)),
+            Expression::BooleanLiteral(val) => Doc::text(if *val { "TRUE" } else { "FALSE" }),
+            Expression::Nil => Doc::text("NIL"),
             Expression::Set(elements) => {
-                write!(output, "{{").unwrap();
-                for (i, elem) in elements.iter().enumerate() {
+                let mut docs = vec![Doc::text("{")];
+                for (i, element) in elements.iter().enumerate() {
                     if i > 0 {
-                        write!(output, ", ").unwrap();
+                        docs.push(Doc::text(", "));
                     }
-                    self.print_expression(output, &elem.start);
-                    if let Some(end) = &elem.end {
-                        write!(output, "..").unwrap();
-                        self.print_expression(output, end);
+                    docs.push(self.expression(&element.start));
+                    if let Some(end) = &element.end {
+                        docs.push(Doc::text(".."));
+                        docs.push(self.expression(end));
                     }
                 }
-                write!(output, "}}").unwrap();
-            }
-            Expression::Designator(designator) => {
-                self.print_designator(output, designator);
+                docs.push(Doc::text("}"));
+                Doc::concat(docs)
             }
+            Expression::Designator(designator) => self.designator(designator),
             Expression::FunctionCall { designator, arguments } => {
-                self.print_designator(output, designator);
-                write!(output, "(").unwrap();
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 {
-                        write!(output, ", ").unwrap();
-                    }
-                    self.print_expression(output, arg);
-                }
-                write!(output, ")").unwrap();
+                Doc::concat(vec![self.designator(designator), self.arguments(arguments)])
             }
             Expression::Unary { op, expr } => {
-                match op {
-                    UnaryOp::Plus => write!(output, "+").unwrap(),
-                    UnaryOp::Minus => write!(output, "-").unwrap(),
-                    UnaryOp::Not => write!(output, "~").unwrap(),
-                }
-                self.print_expression(output, expr);
+                let sign = match op {
+                    UnaryOp::Plus => "+",
+                    UnaryOp::Minus => "-",
+                    UnaryOp::Not => "~",
+                };
+                // Vorzeichen gelten für einen Term, ~ für einen Faktor
+                let operand = if *op == UnaryOp::Not { 4 } else { 3 };
+                Doc::concat(vec![Doc::text(sign), self.operand(expr, operand)])
+            }
+            Expression::Binary { .. } => {
+                let mut operands = Vec::new();
+                let mut operators = Vec::new();
+                self.chain(expr, 0, &mut operands, &mut operators);
+                Doc::group(self.operators(operands, operators))
             }
-            Expression::Binary { left, op, right } => {
-                let needs_parens = matches!(**left, Expression::Binary { .. });
-                if needs_parens {
-                    write!(output, "(").unwrap();
-                }
-                self.print_expression(output, left);
-                if needs_parens {
-                    write!(output, ")").unwrap();
-                }
+        }
+    }
 
-                write!(output, " ").unwrap();
-                match op {
-                    BinaryOp::Add => write!(output, "+").unwrap(),
-                    BinaryOp::Sub => write!(output, "-").unwrap(),
-                    BinaryOp::Mul => write!(output, "*").unwrap(),
-                    BinaryOp::Div => write!(output, "/").unwrap(),
-                    BinaryOp::IntDiv => write!(output, "DIV").unwrap(),
-                    BinaryOp::Mod => write!(output, "MOD").unwrap(),
-                    BinaryOp::And => write!(output, "&").unwrap(),
-                    BinaryOp::Or => write!(output, "OR").unwrap(),
-                    BinaryOp::Equal => write!(output, "=").unwrap(),
-                    BinaryOp::NotEqual => write!(output, "#").unwrap(),
-                    BinaryOp::Less => write!(output, "<").unwrap(),
-                    BinaryOp::LessEqual => write!(output, "<=").unwrap(),
-                    BinaryOp::Greater => write!(output, ">").unwrap(),
-                    BinaryOp::GreaterEqual => write!(output, ">=").unwrap(),
-                    BinaryOp::In => write!(output, "IN").unwrap(),
-                    BinaryOp::Is => write!(output, "IS").unwrap(),
-                }
-                write!(output, " ").unwrap();
+    // Zerlegt verschachtelte binäre Ausdrücke in Operanden und Operatoren,
+    // soweit keine Klammern nötig sind
+    fn chain(&self, expr: &Expression, min_precedence: u8, operands: &mut Vec<Doc>, operators: &mut Vec<(u8, Doc)>) {
+        match expr {
+            Expression::Binary { left, op, right } if precedence(expr) >= min_precedence => {
+                let level = precedence(expr);
+                self.chain(left, level, operands, operators);
+                operators.push((level, Doc::text(operator(op))));
+                // linksassoziativ: rechts auch bei gleicher Stufe klammern
+                self.chain(right, level + 1, operands, operators);
+            }
+            _ => operands.push(self.operand(expr, min_precedence)),
+        }
+    }
 
-                let needs_parens = matches!(**right, Expression::Binary { .. });
-                if needs_parens {
-                    write!(output, "(").unwrap();
-                }
-                self.print_expression(output, right);
-                if needs_parens {
-                    write!(output, ")").unwrap();
-                }
-            }
+    // Klammert, wenn der Ausdruck schwächer bindet als verlangt
+    fn operand(&self, expr: &Expression, min_precedence: u8) -> Doc {
+        if precedence(expr) < min_precedence {
+            Doc::concat(vec![Doc::text("("), self.expression(expr), Doc::text(")")])
+        } else {
+            self.expression(expr)
         }
     }
 
+    fn arguments(&self, arguments: &[Expression]) -> Doc {
+        let last = arguments.len().saturating_sub(1);
+        let items = arguments
+            .iter()
+            .enumerate()
+            .map(|(i, argument)| {
+                let separator = if i < last { "," } else { "" };
+                Doc::concat(vec![self.expression(argument), Doc::text(separator)])
+            })
+            .collect();
+        self.list(Doc::text("("), items, Doc::text(")"))
+    }
+
     // ========================================================================
     // Designator
     // ========================================================================
 
-    fn print_designator(&mut self, output: &mut String, designator: &Designator) {
-        write!(output, "{}", designator.base).unwrap();
+    fn designator(&self, designator: &Designator) -> Doc {
+        let mut docs = vec![Doc::text(designator.base.to_string())];
         for selector in &designator.selectors {
             match selector {
-                Selector::Field(name) => {
-                    write!(output, ".{}", name).unwrap();
-                }
+                Selector::Field { name, .. } => docs.push(Doc::text(format!(".{}", name))),
                 Selector::Index(indices) => {
-                    write!(output, "[").unwrap();
-                    for (i, idx) in indices.iter().enumerate() {
+                    docs.push(Doc::text("["));
+                    for (i, index) in indices.iter().enumerate() {
                         if i > 0 {
-                            write!(output, ", ").unwrap();
+                            docs.push(Doc::text(", "));
                         }
-                        self.print_expression(output, idx);
+                        docs.push(self.expression(index));
                     }
-                    write!(output, "]").unwrap();
-                }
-                Selector::Dereference => {
-                    write!(output, "^").unwrap();
-                }
-                Selector::TypeGuard(type_name) => {
-                    write!(output, "({})", type_name).unwrap();
+                    docs.push(Doc::text("]"));
                 }
+                Selector::Dereference => docs.push(Doc::text("^")),
+                Selector::TypeGuard(type_name) => docs.push(Doc::text(format!("({})", type_name))),
             }
         }
+        Doc::concat(docs)
     }
+}
 
-    // ========================================================================
-    // Hilfsfunktionen
-    // ========================================================================
+fn names<T: Display>(names: &[T]) -> String {
+    names.iter().map(|name| name.to_string()).collect::<Vec<_>>().join(", ")
+}
 
-    fn print_indent(&self, output: &mut String) {
-        for _ in 0..self.indent_level {
-            write!(output, "{}", self.indent_string).unwrap();
-        }
+fn operator(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::IntDiv => "DIV",
+        BinaryOp::Mod => "MOD",
+        BinaryOp::And => "&",
+        BinaryOp::Or => "OR",
+        BinaryOp::Equal => "=",
+        BinaryOp::NotEqual => "#",
+        BinaryOp::Less => "<",
+        BinaryOp::LessEqual => "<=",
+        BinaryOp::Greater => ">",
+        BinaryOp::GreaterEqual => ">=",
+        BinaryOp::In => "IN",
+        BinaryOp::Is => "IS",
+    }
+}
+
+// ============================================================================
+// DEFINITION (Schnittstelle)
+// ============================================================================
+
+fn interface_name(name: &IdentDef) -> Option<IdentDef> {
+    let exported = match name.exported {
+        ExportMark::None => return None,
+        ExportMark::ReadOnly => ExportMark::None,
+        ExportMark::ReadWrite => ExportMark::ReadWrite,
+    };
+    Some(IdentDef {
+        exported,
+        ..name.clone()
+    })
+}
+
+fn interface_declarations(decls: &DeclSequence) -> DeclSequence {
+    DeclSequence {
+        constants: decls
+            .constants
+            .iter()
+            .filter_map(|constant| {
+                Some(ConstDeclaration {
+                    name: interface_name(&constant.name)?,
+                    value: constant.value.clone(),
+                })
+            })
+            .collect(),
+        types: decls
+            .types
+            .iter()
+            .filter_map(|declaration| {
+                Some(TypeDeclaration {
+                    name: interface_name(&declaration.name)?,
+                    type_def: interface_type(&declaration.type_def),
+                })
+            })
+            .collect(),
+        variables: decls
+            .variables
+            .iter()
+            .filter_map(|declaration| {
+                let names: Vec<IdentDef> = declaration.names.iter().filter_map(interface_name).collect();
+                (!names.is_empty()).then(|| VariableDeclaration {
+                    names,
+                    var_type: interface_type(&declaration.var_type),
+                })
+            })
+            .collect(),
+        procedures: decls
+            .procedures
+            .iter()
+            .filter(|procedure| !procedure.is_forward)
+            .filter_map(|procedure| {
+                Some(ProcedureDeclaration {
+                    name: interface_name(&procedure.name)?,
+                    params: procedure.params.as_ref().map(|params| FormalParameters {
+                        sections: params
+                            .sections
+                            .iter()
+                            .map(|section| FPSection {
+                                param_type: interface_type(&section.param_type),
+                                ..section.clone()
+                            })
+                            .collect(),
+                        return_type: params.return_type.clone(),
+                    }),
+                    declarations: DeclSequence::default(),
+                    body: None,
+                    return_expr: None,
+                    return_line: 0,
+                    ..procedure.clone()
+                })
+            })
+            .collect(),
+    }
+}
+
+// Records behalten nur ihre exportierten Felder, auch verschachtelt
+fn interface_type(type_def: &Type) -> Type {
+    match type_def {
+        Type::Qualident(_) => type_def.clone(),
+        Type::Array { lengths, element_type } => Type::Array {
+            lengths: lengths.clone(),
+            element_type: Box::new(interface_type(element_type)),
+        },
+        Type::Record { base_type, fields } => Type::Record {
+            base_type: base_type.clone(),
+            fields: fields
+                .iter()
+                .filter_map(|field| {
+                    let names: Vec<IdentDef> = field.names.iter().filter_map(interface_name).collect();
+                    (!names.is_empty()).then(|| FieldList {
+                        names,
+                        field_type: interface_type(&field.field_type),
+                    })
+                })
+                .collect(),
+        },
+        Type::Pointer { target_type } => Type::Pointer {
+            target_type: Box::new(interface_type(target_type)),
+        },
+        Type::Procedure { params } => Type::Procedure { params: params.clone() },
+    }
+}
+
+// Bindungsstärke: Relation 1, Addition 2, Multiplikation 3, Faktor 4
+fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Binary { op, .. } => match op {
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+            | BinaryOp::In
+            | BinaryOp::Is => 1,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Or => 2,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::IntDiv | BinaryOp::Mod | BinaryOp::And => 3,
+        },
+        Expression::Unary { op: UnaryOp::Not, .. } => 4,
+        Expression::Unary { .. } => 2,
+        _ => 4,
     }
 }
 
@@ -577,4 +806,104 @@ impl Default for PrettyPrinter {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::parse;
+
+    #[test]
+    fn test_expression_parentheses() {
+        let source = "MODULE M; CONST a = 1; b = 2;
+            c = -(a + b); d = (a - b) - (a - b); e = -a * b + a * (b DIV 2); f = ~(a = b) OR (a < b) & TRUE;
+            END M.";
+        let module = parse(source);
+
+        let values: Vec<String> = module.declarations.constants[2..]
+            .iter()
+            .map(|constant| {
+                let mut output = String::new();
+                PrettyPrinter::new().print_expression(&mut output, &constant.value);
+                output
+            })
+            .collect();
+        assert_eq!(values, ["-(a + b)", "a - b - (a - b)", "-a * b + a * (b DIV 2)", "~(a = b) OR (a < b) & TRUE"]);
+    }
+
+    #[test]
+    fn test_layout() {
+        let source = "MODULE M; VAR x: INTEGER;
+            PROCEDURE Compute(VAR result: INTEGER; first, second: INTEGER): BOOLEAN;
+            BEGIN result := first * second + first DIV second + second; CASE x OF 1: x := 2 | 2, 3: x := 1; x := 0 END
+            RETURN result > 0
+            END Compute;
+            END M.";
+        let module = parse(source);
+
+        let wide = PrettyPrinter::new().print_module(&module);
+        assert!(wide.contains("PROCEDURE Compute(VAR result: INTEGER; first, second: INTEGER): BOOLEAN;\n"));
+        assert!(wide.contains("  CASE x OF\n    1: x := 2\n  | 2, 3:\n      x := 1;\n      x := 0\n  END"), "{}", wide);
+
+        // schmal und mit 4 Leerzeichen: Parameter je Zeile, Umbruch an den schwächsten Operatoren
+        let narrow = PrettyPrinter::with_layout(4, 36).print_module(&module);
+        assert!(narrow.contains("PROCEDURE Compute(\n    VAR result: INTEGER;\n    first, second: INTEGER\n): BOOLEAN;"), "{}", narrow);
+        assert!(narrow.contains("    result := first * second +\n        first DIV second +\n        second;"), "{}", narrow);
+        assert!(narrow.lines().all(|line| line.chars().count() <= 36), "{}", narrow);
+    }
+
+    #[test]
+    fn test_definition() {
+        let source = "MODULE Liste;
+            IMPORT W := Writers;
+            CONST Max* = 100; Intern = 1;
+            TYPE
+              Node* = POINTER TO NodeDesc;
+              NodeDesc* = RECORD key*: INTEGER; next: Node; out-: W.Writer; inner*: RECORD a*, b: INTEGER END END;
+              Hidden = RECORD END;
+            VAR count-, secret: INTEGER;
+            PROCEDURE Insert*(VAR list: Node; key: INTEGER);
+              VAR n: Node;
+            BEGIN NEW(n); n^.key := key; n^.next := list; list := n
+            END Insert;
+            PROCEDURE Length*(list: Node): INTEGER;
+            BEGIN RETURN 0
+            END Length;
+            PROCEDURE Helper;
+            END Helper;
+            BEGIN count := 0
+            END Liste.";
+        let module = parse(source);
+
+        let expected = "DEFINITION Liste;
+
+IMPORT W := Writers;
+
+CONST
+  Max = 100;
+
+TYPE
+  Node = POINTER TO NodeDesc;
+  NodeDesc = RECORD
+    key: INTEGER;
+    out-: W.Writer;
+    inner: RECORD
+      a: INTEGER
+    END
+  END;
+
+VAR
+  count-: INTEGER;
+
+PROCEDURE Insert(VAR list: Node; key: INTEGER);
+
+PROCEDURE Length(list: Node): INTEGER;
+
+END Liste.
+";
+        let mut printer = PrettyPrinter::new();
+        assert_eq!(printer.print_definition(&module), expected);
+
+        // danach druckt derselbe Drucker wieder ganze Module
+        assert!(printer.print_module(&module).starts_with("MODULE Liste;"));
+    }
+}
@@ -0,0 +1,488 @@
+use crate::codegen::CGenerator;
+use crate::interpreter::{Interpreter, Memory, Value};
+use crate::parser::ast::{DeclSequence, Expression, Import, Statement};
+use crate::parser::Parser;
+use crate::scanner::{Lexer, Token, TokenType};
+use crate::semantic::{hir, TypeArena, TypeChecker, TypeId, TypeKind};
+use std::io::{self, BufRead, Write};
+
+// ============================================================================
+// REPL
+// ============================================================================
+//
+// Eingaben werden wie Teile eines Moduls "Repl" behandelt: Deklarationen
+// erweitern die Symboltabelle, Ausdrücke werden ausgewertet und mit ihrem Typ
+// ausgegeben, Anweisungen laufen im Interpreter. Globale Variablen behalten
+// ihren Wert über die Eingaben hinweg.
+//
+// Jede Eingabe wird auf einer Kopie des TypeCheckers geprüft; nur fehlerfreie
+// Eingaben werden übernommen.
+
+pub const MODULE_NAME: &str = "Repl";
+
+const HELP: &str = "\
+Eingaben:
+  IMPORT/CONST/TYPE/VAR/PROCEDURE ...  Deklarationen
+  <Ausdruck>                           Wert und Typ ausgeben
+  <Anweisungen>                        ausführen
+Befehle:
+  :type <Ausdruck>   Typ des Ausdrucks
+  :ast <Eingabe>     Syntaxbaum des Parsers
+  :c <Eingabe>       erzeugter C-Code
+  :help              diese Hilfe
+  :quit              beenden
+Unvollständige Blöcke (IF, WHILE, PROCEDURE, ...) werden über mehrere Zeilen
+fortgesetzt; eine leere Zeile schickt die Eingabe trotzdem ab.";
+
+enum Input {
+    Declarations(Vec<Import>, DeclSequence),
+    Statements(Vec<Statement>),
+    Expression(Expression),
+}
+
+pub struct Session {
+    checker: TypeChecker,
+    imports: Vec<Import>,
+    declarations: hir::Declarations,
+    memory: Memory,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        let mut checker = TypeChecker::new();
+        checker.begin_session(MODULE_NAME);
+        Session {
+            checker,
+            imports: Vec::new(),
+            declarations: hir::Declarations::default(),
+            memory: Memory::default(),
+        }
+    }
+
+    // Verarbeitet eine vollständige Eingabe; false nach :quit
+    pub fn eval(&mut self, input: &str, out: &mut dyn Write) -> io::Result<bool> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(true);
+        }
+
+        if let Some(command) = input.strip_prefix(':') {
+            let (name, argument) = match command.split_once(char::is_whitespace) {
+                Some((name, argument)) => (name, argument.trim()),
+                None => (command, ""),
+            };
+            match name {
+                "quit" | "q" => return Ok(false),
+                "help" | "h" => writeln!(out, "{}", HELP)?,
+                "type" | "t" => self.show_type(argument, out)?,
+                "ast" => self.show_ast(argument, out)?,
+                "c" => self.show_c(argument, out)?,
+                _ => writeln!(out, "Unbekannter Befehl :{} (siehe :help)", name)?,
+            }
+            return Ok(true);
+        }
+
+        match parse_input(input) {
+            Ok(Input::Declarations(imports, decls)) => self.declare(&imports, &decls, out)?,
+            Ok(Input::Expression(expr)) => self.print_expression(input, &expr, out)?,
+            Ok(Input::Statements(statements)) => self.execute(&statements, out)?,
+            Err(error) => print_error(&error, out)?,
+        }
+        Ok(true)
+    }
+
+    // ========================================================================
+    // Eingaben
+    // ========================================================================
+
+    fn declare(
+        &mut self,
+        imports: &[Import],
+        decls: &DeclSequence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut checker = self.checker.clone();
+        let checked = checker
+            .check_import_input(imports)
+            .and_then(|_| checker.check_declaration_input(decls));
+        let declarations = match checked {
+            Ok(declarations) => declarations,
+            Err(_) => return print_located(&checker, out),
+        };
+
+        self.checker = checker;
+        self.imports.extend(imports.iter().cloned());
+        let hir::Declarations { constants, types, variables, procedures } = declarations;
+        self.declarations.constants.extend(constants);
+        self.declarations.types.extend(types);
+        self.declarations.variables.extend(variables);
+        self.declarations.procedures.extend(procedures);
+
+        // Neue Variablen gleich anlegen, damit sie als Ausdruck lesbar sind
+        self.run(Vec::new(), out)
+    }
+
+    // Designatoren und Aufrufe sind auch Anweisungen: eigentliche Prozeduren
+    // werden aufgerufen statt als Wert ausgegeben
+    fn print_expression(&mut self, input: &str, expr: &Expression, out: &mut dyn Write) -> io::Result<()> {
+        let mut checker = self.checker.clone();
+        let checked = checker.check_expression_input(expr);
+        let is_value = match &checked {
+            Ok(checked) => !matches!(checker.types().kind(checked.ty), TypeKind::Procedure { result: None, .. }),
+            Err(_) => false,
+        };
+        if !is_value {
+            if let Ok(statements) = tokenize(input).and_then(|tokens| Parser::new(tokens).parse_statement_input()) {
+                return self.execute(&statements, out);
+            }
+        }
+        let checked = match checked {
+            Ok(checked) => checked,
+            Err(_) => return print_located(&checker, out),
+        };
+
+        let module = self.module(Vec::new());
+        let memory = std::mem::take(&mut self.memory);
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::with_memory(&module, &mut output, memory);
+        let result = interpreter.evaluate_expression(&checked);
+        self.memory = interpreter.into_memory();
+        let value = result.map(|value| format_value(&module.types, &value, checked.ty));
+
+        out.write_all(&output)?;
+        match value {
+            Ok(value) => writeln!(out, "{} : {}", value, module.types.display(checked.ty)),
+            Err(trap) => writeln!(out, "{}", trap),
+        }
+    }
+
+    fn execute(&mut self, statements: &[Statement], out: &mut dyn Write) -> io::Result<()> {
+        let mut checker = self.checker.clone();
+        match checker.check_statement_input(statements) {
+            Ok(body) => self.run(body, out),
+            Err(_) => print_located(&checker, out),
+        }
+    }
+
+    fn run(&mut self, body: Vec<hir::Statement>, out: &mut dyn Write) -> io::Result<()> {
+        let module = self.module(body);
+        let memory = std::mem::take(&mut self.memory);
+        let mut interpreter = Interpreter::with_memory(&module, out, memory);
+        let result = interpreter.run();
+        self.memory = interpreter.into_memory();
+        match result {
+            Ok(()) => Ok(()),
+            Err(trap) => writeln!(out, "{}", trap),
+        }
+    }
+
+    // Alle bisherigen Deklarationen als Modul mit dem gegebenen Rumpf
+    fn module(&self, body: Vec<hir::Statement>) -> hir::Module {
+        hir::Module {
+            name: MODULE_NAME.to_string(),
+            imports: self.imports.clone(),
+            types: self.checker.types().clone(),
+            declarations: self.declarations.clone(),
+            body,
+            imported_variables: Vec::new(),
+        }
+    }
+
+    // ========================================================================
+    // Befehle
+    // ========================================================================
+
+    fn show_type(&self, argument: &str, out: &mut dyn Write) -> io::Result<()> {
+        let expr = match tokenize(argument).and_then(|tokens| Parser::new(tokens).parse_expression_input()) {
+            Ok(expr) => expr,
+            Err(error) => return print_error(&error, out),
+        };
+        let mut checker = self.checker.clone();
+        match checker.check_expression_input(&expr) {
+            Ok(checked) => writeln!(out, "{}", checker.types().display(checked.ty)),
+            Err(_) => print_located(&checker, out),
+        }
+    }
+
+    fn show_ast(&self, argument: &str, out: &mut dyn Write) -> io::Result<()> {
+        match parse_input(argument) {
+            Ok(Input::Declarations(imports, decls)) => {
+                for import in imports {
+                    writeln!(out, "{:#?}", import)?;
+                }
+                writeln!(out, "{:#?}", decls)
+            }
+            Ok(Input::Statements(statements)) => writeln!(out, "{:#?}", statements),
+            Ok(Input::Expression(expr)) => writeln!(out, "{:#?}", expr),
+            Err(error) => print_error(&error, out),
+        }
+    }
+
+    // Nur der Code der Eingabe; frühere Deklarationen erscheinen nicht
+    fn show_c(&self, argument: &str, out: &mut dyn Write) -> io::Result<()> {
+        let mut checker = self.checker.clone();
+        let mut generator = CGenerator::new();
+        let code = match parse_input(argument) {
+            Ok(Input::Declarations(imports, decls)) => checker
+                .check_import_input(&imports)
+                .and_then(|_| checker.check_declaration_input(&decls))
+                .map(|declarations| {
+                    let mut module = self.module(Vec::new());
+                    module.types = checker.types().clone();
+                    module.declarations = declarations;
+                    generator.generate_fragment(&module, &self.declarations)
+                }),
+            Ok(Input::Statements(statements)) => checker.check_statement_input(&statements).map(|body| {
+                let module = hir::Module { declarations: hir::Declarations::default(), ..self.module(body) };
+                generator.generate_fragment(&module, &self.declarations)
+            }),
+            Ok(Input::Expression(expr)) => checker
+                .check_expression_input(&expr)
+                .map(|checked| format!("{}\n", generator.generate_expression(checker.types(), &checked))),
+            Err(error) => return print_error(&error, out),
+        };
+        match code {
+            Ok(code) => write!(out, "{}", code),
+            Err(_) => print_located(&checker, out),
+        }
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Eingabeschleife
+// ============================================================================
+
+// Liest Eingaben zeilenweise, bis :quit oder das Ende der Eingabe kommt
+pub fn run(input: &mut dyn BufRead, out: &mut dyn Write, prompt: bool) -> io::Result<()> {
+    let mut session = Session::new();
+    let mut pending = String::new();
+    loop {
+        if prompt {
+            write!(out, "{}", if pending.is_empty() { "> " } else { ". " })?;
+            out.flush()?;
+        }
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            if !pending.is_empty() {
+                session.eval(&pending, out)?;
+            }
+            return Ok(());
+        }
+
+        // Eine leere Zeile beendet auch eine unvollständige Eingabe
+        let blank = line.trim().is_empty();
+        pending.push_str(&line);
+        if pending.trim_start().starts_with(':') || blank || is_complete(&pending) {
+            let input = std::mem::take(&mut pending);
+            if !session.eval(&input, out)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Unvollständig sind Eingaben mit offenen Blöcken oder Kommentaren
+pub fn is_complete(input: &str) -> bool {
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.tokenize_tolerant();
+    if lexer.errors().iter().any(|error| error.message.starts_with("Nicht geschlossener Kommentar")) {
+        return false;
+    }
+
+    let mut depth = 0i32;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::If
+            | TokenType::While
+            | TokenType::For
+            | TokenType::Case
+            | TokenType::Record
+            | TokenType::Repeat => depth += 1,
+            // Nur Prozedurdeklarationen, keine Prozedurtypen
+            TokenType::Procedure => {
+                if let Some(TokenType::Identifier(_)) = tokens.get(i + 1).map(|next| &next.token_type) {
+                    depth += 1;
+                }
+            }
+            TokenType::End | TokenType::Until => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+// ============================================================================
+// Hilfsfunktionen
+// ============================================================================
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    Lexer::new(input).tokenize()
+}
+
+// Deklarationen am ersten Schlüsselwort, sonst zuerst als Ausdruck, dann als
+// Anweisungsfolge
+fn parse_input(input: &str) -> Result<Input, String> {
+    let mut tokens = tokenize(input)?;
+    let first = tokens.first().map(|token| token.token_type.clone());
+
+    if let Some(TokenType::Import | TokenType::Const | TokenType::Type | TokenType::Var | TokenType::Procedure) = first {
+        // Das abschließende Semikolon darf fehlen
+        let last = tokens.len().saturating_sub(2);
+        if tokens.len() >= 2 && tokens[last].token_type != TokenType::Semicolon {
+            let eof = &tokens[tokens.len() - 1];
+            let semicolon = Token::new(TokenType::Semicolon, ";".to_string(), eof.line, eof.column);
+            tokens.insert(tokens.len() - 1, semicolon);
+        }
+        let (imports, decls) = Parser::new(tokens).parse_declaration_input()?;
+        return Ok(Input::Declarations(imports, decls));
+    }
+
+    if let Ok(expr) = Parser::new(tokens.clone()).parse_expression_input() {
+        return Ok(Input::Expression(expr));
+    }
+    Parser::new(tokens).parse_statement_input().map(Input::Statements)
+}
+
+fn print_error(error: &str, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "Fehler: {}", error)
+}
+
+// Fehler der Typprüfung, bei mehrzeiligen Eingaben mit Zeile
+fn print_located(checker: &TypeChecker, out: &mut dyn Write) -> io::Result<()> {
+    for (line, error) in checker.located_errors() {
+        if line > 1 {
+            writeln!(out, "Fehler in Zeile {}: {}", line, error)?;
+        } else {
+            writeln!(out, "Fehler: {}", error)?;
+        }
+    }
+    Ok(())
+}
+
+fn format_value(types: &TypeArena, value: &Value, ty: TypeId) -> String {
+    match (value, types.kind(ty)) {
+        (Value::Integer(value), _) => value.to_string(),
+        (Value::Real(value), _) => format!("{:?}", value),
+        (Value::Boolean(value), _) => if *value { "TRUE" } else { "FALSE" }.to_string(),
+        (Value::Char(ch), _) if (' '..='~').contains(ch) && *ch != '"' => format!("\"{}\"", ch),
+        (Value::Char(ch), _) => format!("{:X}X", *ch as u32),
+        (Value::Set(bits), _) => format_set(*bits),
+        (Value::String(text), _) => format!("\"{}\"", text),
+        (Value::Nil, _) => "NIL".to_string(),
+        (Value::Pointer(index), _) => format!("POINTER #{}", index),
+        (Value::Procedure(name), _) => name.clone(),
+        (Value::Array(_), TypeKind::Array { element, .. }) if *element == TypeId::CHAR => match value.text() {
+            Some(text) => format!("\"{}\"", text),
+            None => "[..]".to_string(),
+        },
+        (Value::Array(items), TypeKind::Array { element, .. }) => {
+            let items: Vec<String> = items.iter().map(|item| format_value(types, item, *element)).collect();
+            format!("[{}]", items.join(", "))
+        }
+        (Value::Record { ty, fields }, _) => {
+            let fields: Vec<String> = types
+                .fields(*ty)
+                .into_iter()
+                .zip(fields)
+                .map(|(field, value)| format!("{}: {}", field.name, format_value(types, value, field.ty)))
+                .collect();
+            format!("({})", fields.join(", "))
+        }
+        (Value::Array(_), _) => "[..]".to_string(),
+    }
+}
+
+// {1, 3..5} wie im Quelltext
+fn format_set(bits: u32) -> String {
+    let mut parts = Vec::new();
+    let mut element = 0;
+    while element < 32 {
+        if bits & (1 << element) == 0 {
+            element += 1;
+            continue;
+        }
+        let start = element;
+        while element < 32 && bits & (1 << element) != 0 {
+            element += 1;
+        }
+        match element - 1 - start {
+            0 => parts.push(start.to_string()),
+            1 => parts.push(format!("{}, {}", start, start + 1)),
+            _ => parts.push(format!("{}..{}", start, element - 1)),
+        }
+    }
+    format!("{{{}}}", parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(lines: &str) -> String {
+        let mut out = Vec::new();
+        run(&mut lines.as_bytes(), &mut out, false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_session() {
+        let output = session(
+            "IMPORT Out\n\
+             VAR x: INTEGER; s: SET\n\
+             x := 6 * 7\n\
+             x DIV 5\n\
+             PROCEDURE Sq(n: INTEGER): INTEGER;\n\
+             BEGIN\n\
+               RETURN n * n\n\
+             END Sq\n\
+             Sq(x) > 1000\n\
+             FOR x := 1 TO 3 DO\n\
+               Out.Int(Sq(x), 3)\n\
+             END; Out.Ln\n\
+             s := {1, 3..5}\n\
+             s\n\
+             y\n\
+             x := x DIV (x - x)\n\
+             :type x = 4\n\
+             :c x + 1\n\
+             :quit\n\
+             x\n",
+        );
+        assert_eq!(
+            output,
+            "8 : INTEGER\n\
+             TRUE : BOOLEAN\n  \
+             1  4  9\n\
+             {1, 3..5} : SET\n\
+             Fehler: Unbekannter Bezeichner: y\n\
+             1: Laufzeitfehler in Repl: Division durch 0\n\
+             BOOLEAN\n\
+             (oberon_x + 1LL)\n"
+        );
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete("x := 1"));
+        assert!(is_complete("VAR p: PROCEDURE (x: INTEGER)"));
+        assert!(!is_complete("IF x > 0 THEN"));
+        assert!(!is_complete("PROCEDURE P;\nBEGIN"));
+        assert!(!is_complete("REPEAT x := x + 1"));
+        assert!(is_complete("REPEAT x := x + 1 UNTIL x > 3"));
+        assert!(!is_complete("x := 1 (* noch offen"));
+    }
+
+    #[test]
+    fn test_format_set() {
+        assert_eq!(format_set(0), "{}");
+        assert_eq!(format_set(0b11_1010), "{1, 3..5}");
+        assert_eq!(format_set(0b1100), "{2, 3}");
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Cond, Data, FCmp, FOp, Instruction, Item, Load, Op, OpImm, Program, Store};
+use std::fmt::Write;
+
+// ============================================================================
+// Ausgabe als GNU-as-Quelltext
+// ============================================================================
+
+pub fn to_asm(program: &Program) -> String {
+    let mut out = String::new();
+    writeln!(out, "\t.file\t\"{}.s\"", program.name).unwrap();
+    writeln!(out, "\t.option nopic").unwrap();
+    writeln!(out, "\t.attribute arch, \"rv64i2p1_m2p0_a2p1_f2p2_d2p2\"").unwrap();
+    writeln!(out, "\t.text").unwrap();
+
+    let mut function: Option<&str> = None;
+    for item in &program.text {
+        match item {
+            Item::Label(label) => writeln!(out, "{}:", label).unwrap(),
+            Item::Function { name, global } => {
+                if let Some(previous) = function {
+                    writeln!(out, "\t.size {}, .-{}", previous, previous).unwrap();
+                }
+                writeln!(out).unwrap();
+                if *global {
+                    writeln!(out, "\t.globl {}", name).unwrap();
+                }
+                writeln!(out, "\t.align 2\n\t.type {}, @function\n{}:", name, name).unwrap();
+                function = Some(name);
+            }
+            Item::Instruction(instruction) => writeln!(out, "\t{}", listing(instruction)).unwrap(),
+        }
+    }
+    if let Some(previous) = function {
+        writeln!(out, "\t.size {}, .-{}", previous, previous).unwrap();
+    }
+
+    for (section, items) in [(".data", &program.data), (".section .rodata", &program.rodata)] {
+        if items.is_empty() {
+            continue;
+        }
+        writeln!(out, "\n\t{}", section).unwrap();
+        for item in items {
+            match item {
+                Data::Label(label) => writeln!(out, "{}:", label).unwrap(),
+                Data::Global(label) => writeln!(out, "\t.globl {}", label).unwrap(),
+                Data::Align(bytes) => writeln!(out, "\t.balign {}", bytes).unwrap(),
+                Data::Zero(bytes) => writeln!(out, "\t.zero {}", bytes).unwrap(),
+                Data::Bytes(bytes) => {
+                    let bytes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+                    writeln!(out, "\t.byte {}", bytes.join(", ")).unwrap();
+                }
+                Data::Address(label) => writeln!(out, "\t.dword {}", label.as_deref().unwrap_or("0")).unwrap(),
+            }
+        }
+    }
+    writeln!(out, "\n\t.section .note.GNU-stack,\"\",@progbits").unwrap();
+    out
+}
+
+fn listing(instruction: &Instruction) -> String {
+    use Instruction::*;
+    match instruction {
+        Op(op, rd, rs1, rs2) => format!("{} {}, {}, {}", op_name(*op), rd.name(), rs1.name(), rs2.name()),
+        OpImm(op, rd, rs1, imm) => format!("{} {}, {}, {}", op_imm_name(*op), rd.name(), rs1.name(), imm),
+        Load(load, rd, offset, base) => format!("{} {}, {}({})", load_name(*load), rd.name(), offset, base.name()),
+        Store(store, rs, offset, base) => format!("{} {}, {}({})", store_name(*store), rs.name(), offset, base.name()),
+        Fld(rd, offset, base) => format!("fld {}, {}({})", rd.name(), offset, base.name()),
+        Fsd(rs, offset, base) => format!("fsd {}, {}({})", rs.name(), offset, base.name()),
+        Branch(cond, rs1, rs2, label) => format!("{} {}, {}, {}", cond_name(*cond), rs1.name(), rs2.name(), label),
+        Jump(label) => format!("j {}", label),
+        Call(target) => format!("call {}", target),
+        CallIndirect(rs) => format!("jalr ra, 0({})", rs.name()),
+        Ret => "ret".to_string(),
+        Li(rd, value) => format!("li {}, {}", rd.name(), value),
+        La(rd, label) => format!("la {}, {}", rd.name(), label),
+        FOp(op, rd, rs1, rs2) => format!("{} {}, {}, {}", fop_name(*op), rd.name(), rs1.name(), rs2.name()),
+        FCmp(cmp, rd, rs1, rs2) => format!("{} {}, {}, {}", fcmp_name(*cmp), rd.name(), rs1.name(), rs2.name()),
+        FcvtDL(rd, rs) => format!("fcvt.d.l {}, {}", rd.name(), rs.name()),
+        FcvtLD(rd, rs) => format!("fcvt.l.d {}, {}, rdn", rd.name(), rs.name()),
+        FmvXD(rd, rs) => format!("fmv.x.d {}, {}", rd.name(), rs.name()),
+        FmvDX(rd, rs) => format!("fmv.d.x {}, {}", rd.name(), rs.name()),
+    }
+}
+
+fn op_name(op: Op) -> &'static str {
+    match op {
+        Op::Add => "add",
+        Op::Sub => "sub",
+        Op::Mul => "mul",
+        Op::Mulh => "mulh",
+        Op::Div => "div",
+        Op::Rem => "rem",
+        Op::And => "and",
+        Op::Or => "or",
+        Op::Xor => "xor",
+        Op::Sll => "sll",
+        Op::Srl => "srl",
+        Op::Sra => "sra",
+        Op::Slt => "slt",
+        Op::Sltu => "sltu",
+    }
+}
+
+fn op_imm_name(op: OpImm) -> &'static str {
+    match op {
+        OpImm::Addi => "addi",
+        OpImm::Andi => "andi",
+        OpImm::Ori => "ori",
+        OpImm::Xori => "xori",
+        OpImm::Slli => "slli",
+        OpImm::Srli => "srli",
+        OpImm::Srai => "srai",
+        OpImm::Slti => "slti",
+        OpImm::Sltiu => "sltiu",
+    }
+}
+
+fn load_name(load: Load) -> &'static str {
+    match load {
+        Load::Ld => "ld",
+        Load::Lwu => "lwu",
+        Load::Lbu => "lbu",
+    }
+}
+
+fn store_name(store: Store) -> &'static str {
+    match store {
+        Store::Sd => "sd",
+        Store::Sw => "sw",
+        Store::Sb => "sb",
+    }
+}
+
+fn cond_name(cond: Cond) -> &'static str {
+    match cond {
+        Cond::Eq => "beq",
+        Cond::Ne => "bne",
+        Cond::Lt => "blt",
+        Cond::Ge => "bge",
+        Cond::Ltu => "bltu",
+        Cond::Geu => "bgeu",
+    }
+}
+
+fn fop_name(op: FOp) -> &'static str {
+    match op {
+        FOp::Add => "fadd.d",
+        FOp::Sub => "fsub.d",
+        FOp::Mul => "fmul.d",
+        FOp::Div => "fdiv.d",
+    }
+}
+
+fn fcmp_name(cmp: FCmp) -> &'static str {
+    match cmp {
+        FCmp::Eq => "feq.d",
+        FCmp::Lt => "flt.d",
+        FCmp::Le => "fle.d",
+    }
+}
@@ -0,0 +1,231 @@
+mod asm;
+
+#[cfg(test)]
+pub(crate) mod simulator;
+
+pub use asm::to_asm;
+
+// ============================================================================
+// RISC-V (RV64IMAFD)
+// ============================================================================
+//
+// Programm aus Befehlen und Daten, wie es der Code-Generator erzeugt, bevor es
+// als GNU-as-Quelltext ausgegeben wird. Sprungziele und Adressen sind Labels;
+// li, la, call, j und ret stehen als Pseudobefehle, die der Assembler
+// auflöst. REAL-Werte liegen meist als Bitmuster in den Allzweckregistern,
+// die Gleitkommaregister dienen nur zum Rechnen und zur Übergabe.
+
+// Dateiendung der Ausgabe
+pub const EXTENSION: &str = "s";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reg(pub u8);
+
+impl Reg {
+    pub const ZERO: Reg = Reg(0);
+    pub const RA: Reg = Reg(1);
+    pub const SP: Reg = Reg(2);
+    pub const T0: Reg = Reg(5);
+    pub const T1: Reg = Reg(6);
+    // Statischer Verweis geschachtelter Prozeduren (wie bei GCC)
+    pub const T2: Reg = Reg(7);
+    pub const S0: Reg = Reg(8);
+    pub const S1: Reg = Reg(9);
+    pub const A0: Reg = Reg(10);
+    pub const T3: Reg = Reg(28);
+    pub const T4: Reg = Reg(29);
+    pub const T5: Reg = Reg(30);
+    pub const T6: Reg = Reg(31);
+
+    // Argumentregister a0..a7
+    pub fn argument(index: usize) -> Reg {
+        Reg(10 + index as u8)
+    }
+
+    // Gesicherte Register s1..s11
+    pub fn saved(index: usize) -> Reg {
+        match index {
+            0 => Reg(9),
+            _ => Reg(17 + index as u8),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        const NAMES: [&str; 32] = [
+            "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5", "a6",
+            "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+        ];
+        NAMES[self.0 as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FReg(pub u8);
+
+impl FReg {
+    pub const FT0: FReg = FReg(0);
+    pub const FT1: FReg = FReg(1);
+
+    // Argumentregister fa0..fa7
+    pub fn argument(index: usize) -> FReg {
+        FReg(10 + index as u8)
+    }
+
+    pub fn name(self) -> &'static str {
+        const NAMES: [&str; 32] = [
+            "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2", "fa3", "fa4",
+            "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9", "fs10", "fs11", "ft8", "ft9",
+            "ft10", "ft11",
+        ];
+        NAMES[self.0 as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Mulh,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+    Slt,
+    Sltu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpImm {
+    Addi,
+    Andi,
+    Ori,
+    Xori,
+    Slli,
+    Srli,
+    Srai,
+    Slti,
+    Sltiu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Load {
+    Ld,
+    Lwu,
+    Lbu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    Sd,
+    Sw,
+    Sb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cond {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FCmp {
+    Eq,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    // rd, rs1, rs2
+    Op(Op, Reg, Reg, Reg),
+    // rd, rs1, imm (12 Bit, Schiebeweiten 6 Bit)
+    OpImm(OpImm, Reg, Reg, i64),
+    // rd, offset(base)
+    Load(Load, Reg, i64, Reg),
+    // rs, offset(base)
+    Store(Store, Reg, i64, Reg),
+    Fld(FReg, i64, Reg),
+    Fsd(FReg, i64, Reg),
+    Branch(Cond, Reg, Reg, String),
+    Jump(String),
+    Call(String),
+    // jalr ra, 0(rs)
+    CallIndirect(Reg),
+    Ret,
+    Li(Reg, i64),
+    La(Reg, String),
+    FOp(FOp, FReg, FReg, FReg),
+    FCmp(FCmp, Reg, FReg, FReg),
+    // fcvt.d.l: ganze Zahl nach REAL
+    FcvtDL(FReg, Reg),
+    // fcvt.l.d mit Abrunden (rdn), für FLOOR
+    FcvtLD(Reg, FReg),
+    FmvXD(Reg, FReg),
+    FmvDX(FReg, Reg),
+}
+
+// Eintrag im Textsegment
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Label(String),
+    // Beginn einer Funktion; exportierte sind global sichtbar
+    Function { name: String, global: bool },
+    Instruction(Instruction),
+}
+
+// Eintrag in .data bzw. .rodata
+#[derive(Debug, Clone, PartialEq)]
+pub enum Data {
+    Label(String),
+    Global(String),
+    Align(u32),
+    Zero(u32),
+    Bytes(Vec<u8>),
+    // .dword mit der Adresse eines Labels, 0 für keins
+    Address(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    pub name: String,
+    pub text: Vec<Item>,
+    pub data: Vec<Data>,
+    pub rodata: Vec<Data>,
+}
+
+// Passt der Wert in ein 12-Bit-Direktfeld?
+pub fn fits_immediate(value: i64) -> bool {
+    (-2048..2048).contains(&value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registers() {
+        assert_eq!(Reg::saved(0).name(), "s1");
+        assert_eq!(Reg::saved(1).name(), "s2");
+        assert_eq!(Reg::saved(10).name(), "s11");
+        assert_eq!(Reg::argument(7).name(), "a7");
+        assert_eq!(FReg::argument(0).name(), "fa0");
+        assert_eq!(Reg::T3.name(), "t3");
+    }
+}
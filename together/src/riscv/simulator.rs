@@ -0,0 +1,494 @@
+use super::{fits_immediate, Cond, Data, Instruction, Item, Load, Op, OpImm, Program, Reg, Store};
+use std::collections::HashMap;
+
+// ============================================================================
+// Befehlssatz-Simulator (nur für Tests)
+// ============================================================================
+//
+// Führt ein Program ohne Assembler aus: Befehle liegen ab TEXT_BASE im
+// Abstand von 4 Bytes, .data und .rodata ab DATA_BASE, dahinter Heap und
+// Stapel. Aufrufe von Funktionen, die das Programm nicht enthält, bedient der
+// Simulator selbst als kleine C-Bibliothek (printf, dprintf, putchar, calloc,
+// memcpy, fflush, exit). Direktwerte außerhalb der Befehlsformate gelten als
+// Fehler des Code-Generators.
+
+const TEXT_BASE: u64 = 0x1000;
+const DATA_BASE: u64 = 0x10_0000;
+const MEMORY_SIZE: usize = 16 * 1024 * 1024;
+const MAX_STEPS: u64 = 100_000_000;
+
+// Rücksprungadresse des ersten Aufrufs; erreicht pc sie, ist das Programm fertig
+const HALT: u64 = 0;
+
+pub struct Simulator<'p> {
+    code: Vec<&'p Instruction>,
+    labels: HashMap<String, u64>,
+    memory: Vec<u8>,
+    x: [u64; 32],
+    f: [u64; 32],
+    pc: u64,
+    heap: u64,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl<'p> Simulator<'p> {
+    pub fn new(program: &'p Program) -> Result<Self, String> {
+        let mut code = Vec::new();
+        let mut labels = HashMap::new();
+        for item in &program.text {
+            match item {
+                Item::Label(label) | Item::Function { name: label, .. } => {
+                    labels.insert(label.clone(), TEXT_BASE + 4 * code.len() as u64);
+                }
+                Item::Instruction(instruction) => code.push(instruction),
+            }
+        }
+
+        // Erst alle Labels, dann die Inhalte mit aufgelösten Adressen
+        let items: Vec<&Data> = program.data.iter().chain(&program.rodata).collect();
+        let mut address = DATA_BASE;
+        for item in &items {
+            match item {
+                Data::Label(label) => {
+                    labels.insert(label.clone(), address);
+                }
+                Data::Global(_) => {}
+                Data::Align(bytes) => address = address.div_ceil(*bytes as u64) * *bytes as u64,
+                Data::Zero(bytes) => address += *bytes as u64,
+                Data::Bytes(bytes) => address += bytes.len() as u64,
+                Data::Address(_) => address += 8,
+            }
+        }
+        let heap = address.div_ceil(16) * 16;
+
+        let mut simulator = Simulator {
+            code,
+            labels,
+            memory: vec![0; MEMORY_SIZE],
+            x: [0; 32],
+            f: [0; 32],
+            pc: 0,
+            heap,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        };
+        let mut address = DATA_BASE;
+        for item in items {
+            match item {
+                Data::Label(_) | Data::Global(_) => {}
+                Data::Align(bytes) => address = address.div_ceil(*bytes as u64) * *bytes as u64,
+                Data::Zero(bytes) => address += *bytes as u64,
+                Data::Bytes(bytes) => {
+                    simulator.slice(address, bytes.len())?.copy_from_slice(bytes);
+                    address += bytes.len() as u64;
+                }
+                Data::Address(label) => {
+                    let value = match label {
+                        Some(label) => simulator.label(label)?,
+                        None => 0,
+                    };
+                    simulator.write(address, 8, value)?;
+                    address += 8;
+                }
+            }
+        }
+        Ok(simulator)
+    }
+
+    // Ruft `entry` auf und liefert den Exit-Code (Rückgabewert oder exit)
+    pub fn run(&mut self, entry: &str) -> Result<i64, String> {
+        self.pc = self.label(entry)?;
+        self.x[Reg::RA.0 as usize] = HALT;
+        self.x[Reg::SP.0 as usize] = DATA_BASE + MEMORY_SIZE as u64;
+
+        for _ in 0..MAX_STEPS {
+            if self.pc == HALT {
+                return Ok(self.x[Reg::A0.0 as usize] as i64);
+            }
+            if let Some(code) = self.step()? {
+                return Ok(code);
+            }
+        }
+        Err(format!("Mehr als {} Schritte", MAX_STEPS))
+    }
+
+    fn label(&self, label: &str) -> Result<u64, String> {
+        self.labels.get(label).copied().ok_or_else(|| format!("Label {} nicht gefunden", label))
+    }
+
+    fn get(&self, reg: Reg) -> u64 {
+        self.x[reg.0 as usize]
+    }
+
+    fn set(&mut self, reg: Reg, value: u64) {
+        if reg != Reg::ZERO {
+            self.x[reg.0 as usize] = value;
+        }
+    }
+
+    fn slice(&mut self, address: u64, length: usize) -> Result<&mut [u8], String> {
+        let start = address.checked_sub(DATA_BASE).map(|start| start as usize);
+        match start {
+            Some(start) if start + length <= self.memory.len() => Ok(&mut self.memory[start..start + length]),
+            _ => Err(format!("Speicherzugriff außerhalb des Speichers: {:#x}", address)),
+        }
+    }
+
+    fn read(&mut self, address: u64, length: usize) -> Result<u64, String> {
+        let mut bytes = [0u8; 8];
+        bytes[..length].copy_from_slice(self.slice(address, length)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn write(&mut self, address: u64, length: usize, value: u64) -> Result<(), String> {
+        self.slice(address, length)?.copy_from_slice(&value.to_le_bytes()[..length]);
+        Ok(())
+    }
+
+    fn effective(&self, offset: i64, base: Reg) -> Result<u64, String> {
+        if !fits_immediate(offset) {
+            return Err(format!("Offset {} passt nicht in 12 Bit", offset));
+        }
+        Ok(self.get(base).wrapping_add(offset as u64))
+    }
+
+    // Führt einen Befehl aus; Some(Exit-Code) nach exit()
+    fn step(&mut self) -> Result<Option<i64>, String> {
+        let index = self.pc.wrapping_sub(TEXT_BASE) / 4;
+        let instruction = match self.code.get(index as usize) {
+            Some(instruction) if self.pc >= TEXT_BASE && self.pc.is_multiple_of(4) => *instruction,
+            _ => return Err(format!("Sprung an ungültige Adresse {:#x}", self.pc)),
+        };
+        let mut next = self.pc + 4;
+
+        use Instruction::*;
+        match instruction {
+            Op(op, rd, rs1, rs2) => {
+                let (a, b) = (self.get(*rs1), self.get(*rs2));
+                let value = alu(*op, a, b);
+                self.set(*rd, value);
+            }
+            OpImm(op, rd, rs1, imm) => {
+                let shift = matches!(op, super::OpImm::Slli | super::OpImm::Srli | super::OpImm::Srai);
+                if (shift && !(0..64).contains(imm)) || (!shift && !fits_immediate(*imm)) {
+                    return Err(format!("Direktwert {} passt nicht in {:?}", imm, op));
+                }
+                let a = self.get(*rs1);
+                let b = *imm as u64;
+                let value = match op {
+                    super::OpImm::Addi => alu(super::Op::Add, a, b),
+                    super::OpImm::Andi => a & b,
+                    super::OpImm::Ori => a | b,
+                    super::OpImm::Xori => a ^ b,
+                    super::OpImm::Slli => alu(super::Op::Sll, a, b),
+                    super::OpImm::Srli => alu(super::Op::Srl, a, b),
+                    super::OpImm::Srai => alu(super::Op::Sra, a, b),
+                    super::OpImm::Slti => alu(super::Op::Slt, a, b),
+                    super::OpImm::Sltiu => alu(super::Op::Sltu, a, b),
+                };
+                self.set(*rd, value);
+            }
+            Load(kind, rd, offset, base) => {
+                let address = self.effective(*offset, *base)?;
+                let length = match kind {
+                    super::Load::Ld => 8,
+                    super::Load::Lwu => 4,
+                    super::Load::Lbu => 1,
+                };
+                let value = self.read(address, length)?;
+                self.set(*rd, value);
+            }
+            Store(kind, rs, offset, base) => {
+                let address = self.effective(*offset, *base)?;
+                let length = match kind {
+                    super::Store::Sd => 8,
+                    super::Store::Sw => 4,
+                    super::Store::Sb => 1,
+                };
+                self.write(address, length, self.get(*rs))?;
+            }
+            Fld(rd, offset, base) => {
+                let address = self.effective(*offset, *base)?;
+                self.f[rd.0 as usize] = self.read(address, 8)?;
+            }
+            Fsd(rs, offset, base) => {
+                let address = self.effective(*offset, *base)?;
+                self.write(address, 8, self.f[rs.0 as usize])?;
+            }
+            Branch(cond, rs1, rs2, label) => {
+                let (a, b) = (self.get(*rs1), self.get(*rs2));
+                let taken = match cond {
+                    Cond::Eq => a == b,
+                    Cond::Ne => a != b,
+                    Cond::Lt => (a as i64) < (b as i64),
+                    Cond::Ge => (a as i64) >= (b as i64),
+                    Cond::Ltu => a < b,
+                    Cond::Geu => a >= b,
+                };
+                if taken {
+                    next = self.label(label)?;
+                }
+            }
+            Jump(label) => next = self.label(label)?,
+            Call(target) => match self.labels.get(target.as_str()) {
+                Some(&address) => {
+                    self.set(Reg::RA, next);
+                    next = address;
+                }
+                None => {
+                    if let Some(code) = self.library(target)? {
+                        return Ok(Some(code));
+                    }
+                }
+            },
+            CallIndirect(rs) => {
+                let target = self.get(*rs);
+                self.set(Reg::RA, next);
+                next = target;
+            }
+            Ret => next = self.get(Reg::RA),
+            Li(rd, value) => self.set(*rd, *value as u64),
+            La(rd, label) => {
+                let address = self.label(label)?;
+                self.set(*rd, address);
+            }
+            FOp(op, rd, rs1, rs2) => {
+                let (a, b) = (f64::from_bits(self.f[rs1.0 as usize]), f64::from_bits(self.f[rs2.0 as usize]));
+                let value = match op {
+                    super::FOp::Add => a + b,
+                    super::FOp::Sub => a - b,
+                    super::FOp::Mul => a * b,
+                    super::FOp::Div => a / b,
+                };
+                self.f[rd.0 as usize] = value.to_bits();
+            }
+            FCmp(cmp, rd, rs1, rs2) => {
+                let (a, b) = (f64::from_bits(self.f[rs1.0 as usize]), f64::from_bits(self.f[rs2.0 as usize]));
+                let value = match cmp {
+                    super::FCmp::Eq => a == b,
+                    super::FCmp::Lt => a < b,
+                    super::FCmp::Le => a <= b,
+                };
+                self.set(*rd, value as u64);
+            }
+            FcvtDL(rd, rs) => self.f[rd.0 as usize] = (self.get(*rs) as i64 as f64).to_bits(),
+            // Wie die Hardware: NaN und zu große Werte sättigen
+            FcvtLD(rd, rs) => {
+                let value = f64::from_bits(self.f[rs.0 as usize]);
+                let value = if value.is_nan() { i64::MAX } else { value.floor() as i64 };
+                self.set(*rd, value as u64);
+            }
+            FmvXD(rd, rs) => self.set(*rd, self.f[rs.0 as usize]),
+            FmvDX(rd, rs) => self.f[rd.0 as usize] = self.get(*rs),
+        }
+        self.pc = next;
+        Ok(None)
+    }
+
+    // ========================================================================
+    // C-Bibliothek
+    // ========================================================================
+
+    fn argument(&self, index: usize) -> u64 {
+        self.get(Reg::argument(index))
+    }
+
+    fn library(&mut self, name: &str) -> Result<Option<i64>, String> {
+        let result = match name {
+            "printf" => {
+                let text = self.format(self.argument(0), 1)?;
+                self.stdout.extend(text);
+                0
+            }
+            "dprintf" => {
+                let text = self.format(self.argument(1), 2)?;
+                match self.argument(0) {
+                    1 => self.stdout.extend(text),
+                    2 => self.stderr.extend(text),
+                    fd => return Err(format!("dprintf auf Dateideskriptor {}", fd)),
+                }
+                0
+            }
+            "putchar" => {
+                self.stdout.push(self.argument(0) as u8);
+                self.argument(0)
+            }
+            "calloc" => {
+                let size = self.argument(0).wrapping_mul(self.argument(1));
+                let address = self.heap;
+                self.heap = (self.heap + size).div_ceil(16) * 16;
+                // Der Heap darf den Stapel nicht erreichen
+                if self.heap + 1024 * 1024 > self.get(Reg::SP) {
+                    0
+                } else {
+                    self.slice(address, size as usize)?.fill(0);
+                    address
+                }
+            }
+            "memcpy" => {
+                let (target, source, length) = (self.argument(0), self.argument(1), self.argument(2) as usize);
+                let bytes = self.slice(source, length)?.to_vec();
+                self.slice(target, length)?.copy_from_slice(&bytes);
+                target
+            }
+            "fflush" => 0,
+            "exit" => return Ok(Some(self.argument(0) as i64)),
+            _ => return Err(format!("Unbekannte Funktion {}", name)),
+        };
+        self.set(Reg::A0, result);
+        Ok(None)
+    }
+
+    fn string(&mut self, mut address: u64, limit: Option<usize>) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        while limit.is_none_or(|limit| bytes.len() < limit) {
+            let byte = self.read(address, 1)? as u8;
+            if byte == 0 {
+                break;
+            }
+            bytes.push(byte);
+            address += 1;
+        }
+        Ok(bytes)
+    }
+
+    // printf mit %d, %s, %f, %c und %%, Breite (auch linksbündig) und
+    // Genauigkeit auch als *; die Argumente stehen ab a<first> in den
+    // Allzweckregistern
+    fn format(&mut self, format: u64, first: usize) -> Result<Vec<u8>, String> {
+        let format = self.string(format, None)?;
+        let arguments: Vec<u64> = (first..8).map(|index| self.argument(index)).collect();
+        let mut arguments = arguments.into_iter();
+        let mut next = || arguments.next().ok_or("Zu viele Argumente für printf");
+        let mut out = Vec::new();
+
+        let mut chars = format.into_iter().peekable();
+        while let Some(byte) = chars.next() {
+            if byte != b'%' {
+                out.push(byte);
+                continue;
+            }
+            let mut number = |chars: &mut std::iter::Peekable<std::vec::IntoIter<u8>>| -> Result<Option<i64>, String> {
+                if chars.peek() == Some(&b'*') {
+                    chars.next();
+                    return Ok(Some(next()? as i32 as i64));
+                }
+                let mut value = None;
+                while let Some(digit) = chars.peek().filter(|byte| byte.is_ascii_digit()) {
+                    value = Some(value.unwrap_or(0) * 10 + (digit - b'0') as i64);
+                    chars.next();
+                }
+                Ok(value)
+            };
+            let left = chars.peek() == Some(&b'-');
+            if left {
+                chars.next();
+            }
+            let width = number(&mut chars)?.map(|width| if left { -width.abs() } else { width });
+            let precision = match chars.peek() {
+                Some(b'.') => {
+                    chars.next();
+                    Some(number(&mut chars)?.unwrap_or(0))
+                }
+                _ => None,
+            };
+            while chars.peek() == Some(&b'l') {
+                chars.next();
+            }
+            let conversion = chars.next().ok_or("Unvollständiges Format")?;
+            let text: Vec<u8> = match conversion {
+                b'%' => vec![b'%'],
+                b'd' => (next()? as i64).to_string().into_bytes(),
+                b'c' => vec![next()? as u8],
+                b'f' => {
+                    let precision = precision.unwrap_or(6) as usize;
+                    format!("{:.*}", precision, f64::from_bits(next()?)).into_bytes()
+                }
+                b's' => {
+                    let address = next()?;
+                    self.string(address, precision.map(|precision| precision as usize))?
+                }
+                other => return Err(format!("Format %{} nicht unterstützt", other as char)),
+            };
+            let width = width.unwrap_or(0);
+            let padding = vec![b' '; (width.unsigned_abs() as usize).saturating_sub(text.len())];
+            if width < 0 {
+                out.extend(text);
+                out.extend(padding);
+            } else {
+                out.extend(padding);
+                out.extend(text);
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn alu(op: Op, a: u64, b: u64) -> u64 {
+    let (x, y) = (a as i64, b as i64);
+    match op {
+        Op::Add => a.wrapping_add(b),
+        Op::Sub => a.wrapping_sub(b),
+        Op::Mul => a.wrapping_mul(b),
+        Op::Mulh => ((x as i128 * y as i128) >> 64) as u64,
+        // Division durch 0 und Überlauf lösen auf RISC-V keine Ausnahme aus
+        Op::Div if y == 0 => u64::MAX,
+        Op::Div => x.wrapping_div(y) as u64,
+        Op::Rem if y == 0 => a,
+        Op::Rem => x.wrapping_rem(y) as u64,
+        Op::And => a & b,
+        Op::Or => a | b,
+        Op::Xor => a ^ b,
+        Op::Sll => a << (b & 63),
+        Op::Srl => a >> (b & 63),
+        Op::Sra => (x >> (b & 63)) as u64,
+        Op::Slt => (x < y) as u64,
+        Op::Sltu => (a < b) as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riscv::FReg;
+
+    fn instruction(instruction: Instruction) -> Item {
+        Item::Instruction(instruction)
+    }
+
+    #[test]
+    fn test_program() {
+        use Instruction::*;
+        // Summe 1..10 in einer Schleife, Ausgabe über printf
+        let (a0, a1, t0) = (Reg::A0, Reg::argument(1), Reg::T0);
+        let program = Program {
+            name: "T".to_string(),
+            text: vec![
+                Item::Function { name: "main".to_string(), global: true },
+                instruction(OpImm(super::OpImm::Addi, Reg::SP, Reg::SP, -16)),
+                instruction(Store(super::Store::Sd, Reg::RA, 8, Reg::SP)),
+                instruction(Li(a1, 0)),
+                instruction(Li(t0, 10)),
+                Item::Label(".L1".to_string()),
+                instruction(super::Instruction::Op(super::Op::Add, a1, a1, t0)),
+                instruction(OpImm(super::OpImm::Addi, t0, t0, -1)),
+                instruction(Branch(Cond::Ne, t0, Reg::ZERO, ".L1".to_string())),
+                instruction(FcvtDL(FReg::FT0, a1)),
+                instruction(FmvXD(Reg::argument(2), FReg::FT0)),
+                instruction(La(a0, ".LS0".to_string())),
+                instruction(Call("printf".to_string())),
+                instruction(Load(super::Load::Ld, Reg::RA, 8, Reg::SP)),
+                instruction(OpImm(super::OpImm::Addi, Reg::SP, Reg::SP, 16)),
+                instruction(Li(a0, 3)),
+                instruction(Ret),
+            ],
+            data: Vec::new(),
+            rodata: vec![Data::Label(".LS0".to_string()), Data::Bytes(b"%4ld|%-5.1f|\0".to_vec())],
+        };
+
+        let mut simulator = Simulator::new(&program).unwrap();
+        assert_eq!(simulator.run("main"), Ok(3));
+        assert_eq!(String::from_utf8(simulator.stdout).unwrap(), "  55|55.0 |");
+    }
+}
@@ -1,10 +1,11 @@
-use super::token::{Token, TokenType};
+use super::token::{Comment, SyntaxError, Token, TokenType, Trivia, TriviaKind};
 
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
     line: usize,
     column: usize,
+    errors: Vec<SyntaxError>,
 }
 
 impl Lexer {
@@ -14,36 +15,78 @@ impl Lexer {
             position: 0,
             line: 1,
             column: 1,
+            errors: Vec::new(),
         }
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
+        let tokens = self.tokenize_tolerant();
+        match self.errors.first() {
+            Some(error) => Err(error.message.clone()),
+            None => Ok(tokens),
+        }
+    }
+
+    // Fehlertolerant (für Editoren): fehlerhafte Zeichen werden übersprungen,
+    // die Fehler stehen anschließend in errors()
+    // Alles zwischen den Token landet als Trivia am folgenden Token, sodass
+    // sich der Quelltext aus den Token vollständig wiederherstellen lässt
+    pub fn tokenize_tolerant(&mut self) -> Vec<Token> {
         let mut tokens = Vec::new();
+        let mut trivia = Vec::new();
 
         loop {
+            let start = self.position;
             self.skip_whitespace();
+            if self.position > start {
+                trivia.push(self.trivia(TriviaKind::Whitespace, start));
+            }
 
             if self.is_at_end() {
-                tokens.push(Token::new(
-                    TokenType::Eof,
-                    String::new(),
-                    self.line,
-                    self.column,
-                ));
+                let mut eof = Token::new(TokenType::Eof, String::new(), self.line, self.column);
+                eof.leading_trivia = trivia;
+                tokens.push(eof);
                 break;
             }
 
-            // Kommentare überspringen
-            if self.peek() == '(' && self.peek_next() == Some('*') {
-                self.skip_comment()?;
-                continue;
+            // Kommentare erzeugen kein Token, sie landen als Trivia beim folgenden
+            let (start, line, column) = (self.position, self.line, self.column);
+            let result = if self.peek() == '(' && self.peek_next() == Some('*') {
+                let result = self.skip_comment();
+                trivia.push(self.trivia(TriviaKind::Comment, start));
+                result
+            } else {
+                match self.next_token() {
+                    Ok(mut token) => {
+                        token.leading_trivia = std::mem::take(&mut trivia);
+                        tokens.push(token);
+                        Ok(())
+                    }
+                    Err(message) => {
+                        trivia.push(self.trivia(TriviaKind::Skipped, start));
+                        Err(message)
+                    }
+                }
+            };
+
+            // Jeder Fehler verbraucht mindestens ein Zeichen, danach geht es weiter
+            if let Err(message) = result {
+                self.errors.push(SyntaxError { message, line, column });
             }
+        }
 
-            let token = self.next_token()?;
-            tokens.push(token);
+        tokens
+    }
+
+    fn trivia(&self, kind: TriviaKind, start: usize) -> Trivia {
+        Trivia {
+            kind,
+            text: self.input[start..self.position].iter().collect(),
         }
+    }
 
-        Ok(tokens)
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
     }
 
     fn next_token(&mut self) -> Result<Token, String> {
@@ -257,8 +300,9 @@ impl Lexer {
                 self.advance();
                 self.advance();
                 depth -= 1;
-            } else {
-                self.advance();
+            } else if self.advance() == '\n' {
+                self.line += 1;
+                self.column = 1;
             }
         }
 
@@ -324,10 +368,59 @@ impl Lexer {
     }
 }
 
+// Kommentare aus den Trivia der Token in Quelltextreihenfolge, ohne die
+// äußeren Klammern; die Zeilen ergeben sich aus den Zeilenumbrüchen davor
+pub fn comments(tokens: &[Token]) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut line = 1;
+    for token in tokens {
+        for trivia in &token.leading_trivia {
+            let end_line = line + trivia.text.matches('\n').count();
+            if trivia.kind == TriviaKind::Comment {
+                if let Some(text) = trivia.text.strip_prefix("(*").and_then(|text| text.strip_suffix("*)")) {
+                    comments.push(Comment { text: text.to_string(), line, end_line });
+                }
+            }
+            line = end_line;
+        }
+        line += token.lexeme.matches('\n').count();
+    }
+    comments
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_comments() {
+        let tokens = Lexer::new("(* eins\n (* zwei *) *)\nx (* drei *)").tokenize().unwrap();
+
+        assert_eq!(tokens[0].line, 3);
+        let comments = comments(&tokens);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].text, " eins\n (* zwei *) ");
+        assert_eq!((comments[0].line, comments[0].end_line), (1, 2));
+        assert_eq!(comments[1].text, " drei ");
+    }
+
+    #[test]
+    fn test_trivia() {
+        let source = "x := 1; (* c *)\r\n\ty ?:= 0FFH (* offen";
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.tokenize_tolerant();
+
+        let text: String = tokens.iter().map(Token::full_text).collect();
+        assert_eq!(text, source);
+
+        let kinds = |token: &Token| -> Vec<TriviaKind> { token.leading_trivia.iter().map(|t| t.kind).collect() };
+        assert_eq!(kinds(&tokens[4]), vec![TriviaKind::Whitespace, TriviaKind::Comment, TriviaKind::Whitespace]);
+        assert_eq!(kinds(&tokens[5]), vec![TriviaKind::Whitespace, TriviaKind::Skipped]);
+        assert_eq!(tokens[6].lexeme, "0FFH");
+        assert_eq!(tokens[7].leading_trivia[1].text, "(* offen");
+        assert_eq!(lexer.errors().len(), 2);
+    }
+
     #[test]
     fn test_keywords() {
         let mut lexer = Lexer::new("MODULE BEGIN END");
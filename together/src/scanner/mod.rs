@@ -1,5 +1,5 @@
 pub mod token;
 pub mod lexer;
 
-pub use token::{Token, TokenType};
-pub use lexer::Lexer;
+pub use token::{Comment, SyntaxError, Token, TokenType, Trivia, TriviaKind};
+pub use lexer::{comments, Lexer};
@@ -84,9 +84,44 @@ pub enum TokenType {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
+    // Quelltext des Tokens, unverändert
     pub lexeme: String,
     pub line: usize,
     pub column: usize,
+    // Leerraum, Kommentare und übersprungene Zeichen vor dem Token;
+    // das Eof-Token trägt den Rest der Datei
+    pub leading_trivia: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    // Kommentar einschließlich (* und *)
+    Comment,
+    // Zeichen, die der fehlertolerante Lexer übersprungen hat
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+// Kommentar ohne (* und *), siehe lexer::comments
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+// Lexikalischer oder syntaktischer Fehler mit Position (fehlertolerante Verarbeitung)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
 }
 
 impl Token {
@@ -96,9 +131,17 @@ impl Token {
             lexeme,
             line,
             column,
+            leading_trivia: Vec::new(),
         }
     }
 
+    // Quelltext einschließlich der vorangehenden Trivia
+    pub fn full_text(&self) -> String {
+        let mut text: String = self.leading_trivia.iter().map(|trivia| trivia.text.as_str()).collect();
+        text.push_str(&self.lexeme);
+        text
+    }
+
     pub fn keyword(keyword: &str) -> Option<TokenType> {
         match keyword {
             "ARRAY" => Some(TokenType::Array),
@@ -141,10 +184,80 @@ impl Token {
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "[{}:{}] {:?} '{}'",
-            self.line, self.column, self.token_type, self.lexeme
-        )
+        write!(f, "[{}:{}] {}", self.line, self.column, self.token_type)
+    }
+}
+
+// Für Fehlermeldungen: Schlüsselwörter und Operatoren so, wie sie im
+// Quelltext stehen, Literale mit ihrem Wert
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            TokenType::Identifier(name) => return write!(f, "Bezeichner '{}'", name),
+            TokenType::IntegerLiteral(value) => return write!(f, "Zahl {}", value),
+            TokenType::RealLiteral(value) => return write!(f, "Zahl {:?}", value),
+            TokenType::StringLiteral(text) => return write!(f, "String \"{}\"", text),
+            TokenType::Eof => return write!(f, "Dateiende"),
+            TokenType::Array => "ARRAY",
+            TokenType::Begin => "BEGIN",
+            TokenType::By => "BY",
+            TokenType::Case => "CASE",
+            TokenType::Const => "CONST",
+            TokenType::Div => "DIV",
+            TokenType::Do => "DO",
+            TokenType::Else => "ELSE",
+            TokenType::Elsif => "ELSIF",
+            TokenType::End => "END",
+            TokenType::False => "FALSE",
+            TokenType::For => "FOR",
+            TokenType::If => "IF",
+            TokenType::Import => "IMPORT",
+            TokenType::In => "IN",
+            TokenType::Is => "IS",
+            TokenType::Mod => "MOD",
+            TokenType::Module => "MODULE",
+            TokenType::Nil => "NIL",
+            TokenType::Of => "OF",
+            TokenType::Or => "OR",
+            TokenType::Pointer => "POINTER",
+            TokenType::Procedure => "PROCEDURE",
+            TokenType::Record => "RECORD",
+            TokenType::Repeat => "REPEAT",
+            TokenType::Return => "RETURN",
+            TokenType::Then => "THEN",
+            TokenType::To => "TO",
+            TokenType::True => "TRUE",
+            TokenType::Type => "TYPE",
+            TokenType::Until => "UNTIL",
+            TokenType::Var => "VAR",
+            TokenType::While => "WHILE",
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Times => "*",
+            TokenType::Slash => "/",
+            TokenType::Ampersand => "&",
+            TokenType::Tilde => "~",
+            TokenType::Equal => "=",
+            TokenType::NotEqual => "#",
+            TokenType::Less => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::Greater => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Assign => ":=",
+            TokenType::Colon => ":",
+            TokenType::Semicolon => ";",
+            TokenType::Comma => ",",
+            TokenType::Period => ".",
+            TokenType::DotDot => "..",
+            TokenType::Bar => "|",
+            TokenType::Caret => "^",
+            TokenType::LParen => "(",
+            TokenType::RParen => ")",
+            TokenType::LBracket => "[",
+            TokenType::RBracket => "]",
+            TokenType::LBrace => "{",
+            TokenType::RBrace => "}",
+        };
+        write!(f, "'{}'", symbol)
     }
 }
\ No newline at end of file
@@ -0,0 +1,470 @@
+use crate::parser::ast::*;
+use super::symbol_table::*;
+use super::types::TypeId;
+
+// Höchstes Element eines SET (SET wird als 32-Bit-Wort dargestellt)
+pub const SET_MAX_ELEMENT: i64 = 31;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Integer(i64),
+    Real(f64),
+    Boolean(bool),
+    Char(char),
+    Set(u32),
+    String(String),
+    Nil,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstError {
+    // Ausdruck ist zur Übersetzungszeit nicht auswertbar (z.B. Variable)
+    NotConstant,
+    // Ausdruck ist konstant, aber ungültig (Überlauf, Division durch Null, ...)
+    Invalid(String),
+}
+
+impl ConstValue {
+    pub fn type_id(&self) -> TypeId {
+        match self {
+            ConstValue::Integer(_) => TypeId::INTEGER,
+            ConstValue::Real(_) => TypeId::REAL,
+            ConstValue::Boolean(_) => TypeId::BOOLEAN,
+            ConstValue::Char(_) => TypeId::CHAR,
+            ConstValue::Set(_) => TypeId::SET,
+            ConstValue::String(_) => TypeId::STRING,
+            ConstValue::Nil => TypeId::NIL,
+        }
+    }
+
+    // Typname für Fehlermeldungen
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConstValue::Integer(_) => "INTEGER",
+            ConstValue::Real(_) => "REAL",
+            ConstValue::Boolean(_) => "BOOLEAN",
+            ConstValue::Char(_) => "CHAR",
+            ConstValue::Set(_) => "SET",
+            ConstValue::String(_) => "STRING",
+            ConstValue::Nil => "NIL",
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            ConstValue::Integer(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    // Einzeichige Strings sind zu CHAR kompatibel ("A" bzw. 41X)
+    pub fn as_char(&self) -> Option<char> {
+        match self {
+            ConstValue::Char(ch) => Some(*ch),
+            ConstValue::String(s) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => Some(ch),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct ConstEvaluator<'a> {
+    symbol_table: &'a SymbolTable,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(symbol_table: &'a SymbolTable) -> Self {
+        ConstEvaluator { symbol_table }
+    }
+
+    pub fn eval(&self, expr: &Expression) -> Result<ConstValue, ConstError> {
+        match expr {
+            Expression::IntegerLiteral(val) => Ok(ConstValue::Integer(*val)),
+            Expression::RealLiteral(val) => Ok(ConstValue::Real(*val)),
+            Expression::StringLiteral(val) => Ok(ConstValue::String(val.clone())),
+            Expression::BooleanLiteral(val) => Ok(ConstValue::Boolean(*val)),
+            Expression::Nil => Ok(ConstValue::Nil),
+            Expression::Set(elements) => self.eval_set(elements),
+            Expression::Designator(designator) => self.eval_designator(designator),
+            Expression::FunctionCall { designator, arguments } => {
+                self.eval_builtin_call(designator, arguments)
+            }
+            Expression::Unary { op, expr } => {
+                let val = self.eval(expr)?;
+                self.eval_unary(op, val)
+            }
+            Expression::Binary { left, op, right } => {
+                // IS ist eine Laufzeit-Typprüfung
+                if matches!(op, BinaryOp::Is) {
+                    return Err(ConstError::NotConstant);
+                }
+                let left_val = self.eval(left)?;
+                let right_val = self.eval(right)?;
+                self.eval_binary(op, left_val, right_val)
+            }
+        }
+    }
+
+    // ========================================================================
+    // Bezeichner und Standardfunktionen
+    // ========================================================================
+
+    fn eval_designator(&self, designator: &Designator) -> Result<ConstValue, ConstError> {
+        if !designator.selectors.is_empty() || designator.base.module.is_some() {
+            return Err(ConstError::NotConstant);
+        }
+
+        match self.symbol_table.lookup(&designator.base.name) {
+            Some(Symbol { kind: SymbolKind::Constant { value, .. }, .. }) => Ok(value.clone()),
+            _ => Err(ConstError::NotConstant),
+        }
+    }
+
+    fn eval_builtin_call(
+        &self,
+        designator: &Designator,
+        arguments: &[Expression],
+    ) -> Result<ConstValue, ConstError> {
+        if !designator.selectors.is_empty() || designator.base.module.is_some() {
+            return Err(ConstError::NotConstant);
+        }
+
+        match self.symbol_table.lookup(&designator.base.name) {
+            Some(Symbol { kind: SymbolKind::Builtin(builtin), .. }) => self.eval_builtin(*builtin, arguments),
+            _ => Err(ConstError::NotConstant),
+        }
+    }
+
+    pub fn eval_builtin(&self, builtin: Builtin, arguments: &[Expression]) -> Result<ConstValue, ConstError> {
+        let args = arguments
+            .iter()
+            .map(|arg| self.eval(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let name = builtin.name();
+        match (builtin, args.as_slice()) {
+            (Builtin::Abs, [ConstValue::Integer(x)]) => x
+                .checked_abs()
+                .map(ConstValue::Integer)
+                .ok_or_else(|| overflow(name)),
+            (Builtin::Abs, [ConstValue::Real(x)]) => Ok(ConstValue::Real(x.abs())),
+            (Builtin::Odd, [ConstValue::Integer(x)]) => Ok(ConstValue::Boolean(x.rem_euclid(2) == 1)),
+            (Builtin::Lsl, [ConstValue::Integer(x), ConstValue::Integer(n)]) => {
+                let shift = shift_amount(name, *n)?;
+                let result = x.wrapping_shl(shift);
+                if result.wrapping_shr(shift) != *x {
+                    return Err(overflow(name));
+                }
+                Ok(ConstValue::Integer(result))
+            }
+            (Builtin::Asr, [ConstValue::Integer(x), ConstValue::Integer(n)]) => {
+                let shift = shift_amount(name, *n)?;
+                Ok(ConstValue::Integer(x >> shift))
+            }
+            (Builtin::Ror, [ConstValue::Integer(x), ConstValue::Integer(n)]) => {
+                let shift = shift_amount(name, *n)?;
+                Ok(ConstValue::Integer((*x as u64).rotate_right(shift) as i64))
+            }
+            (Builtin::Floor, [ConstValue::Real(x)]) => {
+                let floored = x.floor();
+                if !floored.is_finite() || floored < i64::MIN as f64 || floored >= i64::MAX as f64 {
+                    return Err(overflow(name));
+                }
+                Ok(ConstValue::Integer(floored as i64))
+            }
+            (Builtin::Flt, [ConstValue::Integer(x)]) => Ok(ConstValue::Real(*x as f64)),
+            (Builtin::Ord, [ConstValue::Boolean(b)]) => Ok(ConstValue::Integer(*b as i64)),
+            (Builtin::Ord, [ConstValue::Set(s)]) => Ok(ConstValue::Integer(*s as i64)),
+            (Builtin::Ord, [value]) => value
+                .as_char()
+                .map(|ch| ConstValue::Integer(ch as i64))
+                .ok_or_else(|| invalid_argument(name)),
+            (Builtin::Chr, [ConstValue::Integer(x)]) => u32::try_from(*x)
+                .ok()
+                .and_then(char::from_u32)
+                .map(ConstValue::Char)
+                .ok_or_else(|| ConstError::Invalid(format!("CHR({}) ist kein gültiges Zeichen", x))),
+            (Builtin::Len, [ConstValue::String(s)]) => Ok(ConstValue::Integer(s.chars().count() as i64)),
+            (Builtin::Len, _) => Err(ConstError::NotConstant),
+            _ if !builtin.is_function() => Err(ConstError::NotConstant),
+            _ => Err(invalid_argument(name)),
+        }
+    }
+
+    // ========================================================================
+    // Mengen
+    // ========================================================================
+
+    fn eval_set(&self, elements: &[SetElement]) -> Result<ConstValue, ConstError> {
+        let mut bits = 0u32;
+        for element in elements {
+            let start = self.eval_set_element(&element.start)?;
+            let end = match &element.end {
+                Some(end) => self.eval_set_element(end)?,
+                None => start,
+            };
+            for i in start..=end {
+                bits |= 1 << i;
+            }
+        }
+        Ok(ConstValue::Set(bits))
+    }
+
+    fn eval_set_element(&self, expr: &Expression) -> Result<u32, ConstError> {
+        match self.eval(expr)? {
+            ConstValue::Integer(val) if (0..=SET_MAX_ELEMENT).contains(&val) => Ok(val as u32),
+            ConstValue::Integer(val) => Err(ConstError::Invalid(format!(
+                "Mengenelement {} liegt außerhalb von 0..{}",
+                val, SET_MAX_ELEMENT
+            ))),
+            other => Err(ConstError::Invalid(format!(
+                "Mengenelement muss INTEGER sein, ist {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    // ========================================================================
+    // Operatoren
+    // ========================================================================
+
+    fn eval_unary(&self, op: &UnaryOp, val: ConstValue) -> Result<ConstValue, ConstError> {
+        match (op, val) {
+            (UnaryOp::Plus, val @ (ConstValue::Integer(_) | ConstValue::Real(_))) => Ok(val),
+            (UnaryOp::Minus, ConstValue::Integer(x)) => x
+                .checked_neg()
+                .map(ConstValue::Integer)
+                .ok_or_else(|| overflow("-")),
+            (UnaryOp::Minus, ConstValue::Real(x)) => Ok(ConstValue::Real(-x)),
+            (UnaryOp::Minus, ConstValue::Set(s)) => Ok(ConstValue::Set(!s)),
+            (UnaryOp::Not, ConstValue::Boolean(b)) => Ok(ConstValue::Boolean(!b)),
+            (op, val) => Err(ConstError::Invalid(format!(
+                "Operator {:?} nicht anwendbar auf {}",
+                op,
+                val.type_name()
+            ))),
+        }
+    }
+
+    fn eval_binary(
+        &self,
+        op: &BinaryOp,
+        left: ConstValue,
+        right: ConstValue,
+    ) -> Result<ConstValue, ConstError> {
+        use ConstValue::*;
+
+        match (op, &left, &right) {
+            // Ganzzahl-Arithmetik mit Überlaufprüfung
+            (BinaryOp::Add, Integer(x), Integer(y)) => x.checked_add(*y).map(Integer).ok_or_else(|| overflow("+")),
+            (BinaryOp::Sub, Integer(x), Integer(y)) => x.checked_sub(*y).map(Integer).ok_or_else(|| overflow("-")),
+            (BinaryOp::Mul, Integer(x), Integer(y)) => x.checked_mul(*y).map(Integer).ok_or_else(|| overflow("*")),
+            (BinaryOp::IntDiv, Integer(_), Integer(0)) | (BinaryOp::Mod, Integer(_), Integer(0)) => {
+                Err(ConstError::Invalid("Division durch Null in konstantem Ausdruck".to_string()))
+            }
+            (BinaryOp::IntDiv, Integer(x), Integer(y)) => floor_div(*x, *y).map(Integer).ok_or_else(|| overflow("DIV")),
+            (BinaryOp::Mod, Integer(x), Integer(y)) => floor_mod(*x, *y).map(Integer).ok_or_else(|| overflow("MOD")),
+
+            // Mengen
+            (BinaryOp::Add, Set(x), Set(y)) => Ok(Set(x | y)),
+            (BinaryOp::Sub, Set(x), Set(y)) => Ok(Set(x & !y)),
+            (BinaryOp::Mul, Set(x), Set(y)) => Ok(Set(x & y)),
+            (BinaryOp::Div, Set(x), Set(y)) => Ok(Set(x ^ y)),
+            (BinaryOp::In, Integer(x), Set(s)) => {
+                Ok(Boolean((0..=SET_MAX_ELEMENT).contains(x) && s & (1 << *x) != 0))
+            }
+
+            // Logische Operatoren
+            (BinaryOp::And, Boolean(x), Boolean(y)) => Ok(Boolean(*x && *y)),
+            (BinaryOp::Or, Boolean(x), Boolean(y)) => Ok(Boolean(*x || *y)),
+
+            // Gleitkomma; gemischte Operanden brauchen wie im Typprüfer FLT
+            (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div, Real(x), Real(y)) => {
+                let (x, y) = (*x, *y);
+                let result = match op {
+                    BinaryOp::Add => x + y,
+                    BinaryOp::Sub => x - y,
+                    BinaryOp::Mul => x * y,
+                    _ => {
+                        if y == 0.0 {
+                            return Err(ConstError::Invalid(
+                                "Division durch Null in konstantem Ausdruck".to_string(),
+                            ));
+                        }
+                        x / y
+                    }
+                };
+                if result.is_finite() {
+                    Ok(Real(result))
+                } else {
+                    Err(overflow("REAL"))
+                }
+            }
+
+            // Vergleiche
+            (
+                BinaryOp::Equal
+                | BinaryOp::NotEqual
+                | BinaryOp::Less
+                | BinaryOp::LessEqual
+                | BinaryOp::Greater
+                | BinaryOp::GreaterEqual,
+                _,
+                _,
+            ) => self.eval_comparison(op, &left, &right),
+
+            _ => Err(ConstError::Invalid(format!(
+                "Operator {:?} nicht anwendbar auf {} und {}",
+                op,
+                left.type_name(),
+                right.type_name()
+            ))),
+        }
+    }
+
+    fn eval_comparison(
+        &self,
+        op: &BinaryOp,
+        left: &ConstValue,
+        right: &ConstValue,
+    ) -> Result<ConstValue, ConstError> {
+        use std::cmp::Ordering;
+
+        let ordering = match (left, right) {
+            (ConstValue::Integer(x), ConstValue::Integer(y)) => Some(x.cmp(y)),
+            (ConstValue::Real(x), ConstValue::Real(y)) => x.partial_cmp(y),
+            (ConstValue::String(x), ConstValue::String(y)) => Some(x.cmp(y)),
+            _ if left.as_char().is_some() && right.as_char().is_some() => {
+                Some(left.as_char().cmp(&right.as_char()))
+            }
+            (ConstValue::Boolean(x), ConstValue::Boolean(y)) if is_equality(op) => Some(x.cmp(y)),
+            (ConstValue::Set(x), ConstValue::Set(y)) if is_equality(op) => {
+                Some(if x == y { Ordering::Equal } else { Ordering::Less })
+            }
+            (ConstValue::Nil, ConstValue::Nil) if is_equality(op) => Some(Ordering::Equal),
+            _ => {
+                return Err(ConstError::Invalid(format!(
+                    "Vergleich nicht möglich für {} und {}",
+                    left.type_name(),
+                    right.type_name()
+                )))
+            }
+        };
+
+        let ordering = ordering.ok_or_else(|| {
+            ConstError::Invalid("Vergleich mit ungültigem REAL-Wert".to_string())
+        })?;
+
+        Ok(ConstValue::Boolean(match op {
+            BinaryOp::Equal => ordering == Ordering::Equal,
+            BinaryOp::NotEqual => ordering != Ordering::Equal,
+            BinaryOp::Less => ordering == Ordering::Less,
+            BinaryOp::LessEqual => ordering != Ordering::Greater,
+            BinaryOp::Greater => ordering == Ordering::Greater,
+            _ => ordering != Ordering::Less,
+        }))
+    }
+}
+
+// ============================================================================
+// Hilfsfunktionen
+// ============================================================================
+
+fn overflow(op: &str) -> ConstError {
+    ConstError::Invalid(format!("Überlauf bei '{}' in konstantem Ausdruck", op))
+}
+
+fn invalid_argument(name: &str) -> ConstError {
+    ConstError::Invalid(format!("Ungültiges Argument für {}", name))
+}
+
+fn shift_amount(name: &str, n: i64) -> Result<u32, ConstError> {
+    if (0..64).contains(&n) {
+        Ok(n as u32)
+    } else {
+        Err(ConstError::Invalid(format!("Schiebeweite {} für {} außerhalb von 0..63", n, name)))
+    }
+}
+
+fn is_equality(op: &BinaryOp) -> bool {
+    matches!(op, BinaryOp::Equal | BinaryOp::NotEqual)
+}
+
+// DIV und MOD runden in Oberon in Richtung -unendlich
+pub(crate) fn floor_div(x: i64, y: i64) -> Option<i64> {
+    let q = x.checked_div(y)?;
+    if x % y != 0 && ((x < 0) != (y < 0)) {
+        q.checked_sub(1)
+    } else {
+        Some(q)
+    }
+}
+
+pub(crate) fn floor_mod(x: i64, y: i64) -> Option<i64> {
+    let r = x.checked_rem(y)?;
+    if r != 0 && ((r < 0) != (y < 0)) {
+        Some(r + y)
+    } else {
+        Some(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Lexer;
+
+    fn eval(source: &str) -> Result<ConstValue, ConstError> {
+        let module = format!("MODULE T; CONST c = {}; END T.", source);
+        let tokens = Lexer::new(&module).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let table = SymbolTable::new();
+        ConstEvaluator::new(&table).eval(&ast.declarations.constants[0].value)
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        assert_eq!(eval("2 + 3 * 4"), Ok(ConstValue::Integer(14)));
+        assert_eq!(eval("(-7) DIV 2"), Ok(ConstValue::Integer(-4)));
+        assert_eq!(eval("(-7) MOD 2"), Ok(ConstValue::Integer(1)));
+        assert_eq!(eval("-7 DIV 2"), Ok(ConstValue::Integer(-3)));
+    }
+
+    #[test]
+    fn test_overflow_and_division_by_zero() {
+        assert!(matches!(eval("7FFFFFFFFFFFFFFFH + 1"), Err(ConstError::Invalid(_))));
+        assert!(matches!(eval("1 DIV 0"), Err(ConstError::Invalid(_))));
+        assert!(matches!(eval("1.0 / 0.0"), Err(ConstError::Invalid(_))));
+        assert!(matches!(eval("1 / 2"), Err(ConstError::Invalid(_))));
+        assert!(matches!(eval("1.5 + 1"), Err(ConstError::Invalid(_))));
+        assert_eq!(eval("1.5 + FLT(1)"), Ok(ConstValue::Real(2.5)));
+    }
+
+    #[test]
+    fn test_builtins() {
+        assert_eq!(eval("ORD(\"A\")"), Ok(ConstValue::Integer(65)));
+        assert_eq!(eval("CHR(66)"), Ok(ConstValue::Char('B')));
+        assert_eq!(eval("ABS(-5)"), Ok(ConstValue::Integer(5)));
+        assert_eq!(eval("LSL(1, 10)"), Ok(ConstValue::Integer(1024)));
+        assert_eq!(eval("FLOOR(-1.5)"), Ok(ConstValue::Integer(-2)));
+    }
+
+    #[test]
+    fn test_sets_and_booleans() {
+        assert_eq!(eval("{1, 3..5} - {4}"), Ok(ConstValue::Set(0b101010)));
+        assert_eq!(eval("3 IN {1..3}"), Ok(ConstValue::Boolean(true)));
+        assert_eq!(eval("~(1 < 2) OR (\"a\" = \"a\")"), Ok(ConstValue::Boolean(true)));
+        assert!(matches!(eval("{32}"), Err(ConstError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_not_constant() {
+        assert_eq!(eval("x + 1"), Err(ConstError::NotConstant));
+    }
+}
@@ -0,0 +1,362 @@
+use super::const_eval::ConstValue;
+use super::hir::*;
+use super::symbol_table::Builtin;
+use super::types::{TypeArena, TypeKind};
+use super::warnings::{Warning, WarningKind};
+use std::collections::{HashMap, HashSet};
+
+// Datenflussanalyse über die Anweisungsfolgen des HIR:
+// - Lesen lokaler Variablen vor der ersten Zuweisung
+// - RETURN mit möglicherweise nicht initialisiertem Ergebnis
+// - unerreichbare Anweisungen nach einem unbedingten Abbruch (ASSERT(FALSE))
+
+pub fn analyze(module: &Module) -> Vec<Warning> {
+    let mut analyzer = FlowAnalyzer {
+        types: &module.types,
+        procedure: module.name.clone(),
+        locals: HashSet::new(),
+        read_kind: WarningKind::UninitializedRead,
+        writes: HashMap::new(),
+        current_writes: HashSet::new(),
+        reported: HashSet::new(),
+        position: (0, 0),
+        warnings: Vec::new(),
+    };
+
+    for proc in &module.declarations.procedures {
+        analyzer.analyze_procedure(proc, &[]);
+    }
+
+    // Modul-Body: globale Variablen werden nicht verfolgt (Prozeduren können sie setzen)
+    analyzer.procedure = module.name.clone();
+    analyzer.locals.clear();
+    analyzer.statements(&module.body, &mut Flow::entry());
+
+    analyzer.warnings
+}
+
+// Zustand an einer Programmstelle
+#[derive(Debug, Clone)]
+struct Flow {
+    // Sicher zugewiesene lokale Variablen (qualifizierte Namen)
+    assigned: HashSet<String>,
+    // false nach einem unbedingten Abbruch
+    reachable: bool,
+}
+
+impl Flow {
+    fn entry() -> Self {
+        Flow {
+            assigned: HashSet::new(),
+            reachable: true,
+        }
+    }
+
+    // Zusammenführung von Zweigen; Zweige, die abbrechen, zählen nicht
+    fn join(branches: Vec<Flow>) -> Flow {
+        let mut reachable = branches.into_iter().filter(|branch| branch.reachable);
+        match reachable.next() {
+            Some(first) => reachable.fold(first, |joined, branch| Flow {
+                assigned: joined.assigned.intersection(&branch.assigned).cloned().collect(),
+                reachable: true,
+            }),
+            None => Flow {
+                assigned: HashSet::new(),
+                reachable: false,
+            },
+        }
+    }
+}
+
+struct FlowAnalyzer<'a> {
+    types: &'a TypeArena,
+    // Name der untersuchten Prozedur (für Meldungen)
+    procedure: String,
+    // Lokale Variablen der untersuchten Prozedur (qualifizierte Namen)
+    locals: HashSet<String>,
+    // Kategorie für gelesene, nicht zugewiesene Variablen (im RETURN-Ausdruck UninitializedResult)
+    read_kind: WarningKind,
+    // Pro Prozedur (qualifizierter Name) alle Variablen, die sie beschreibt;
+    // Aufrufe geschachtelter Prozeduren können lokale Variablen setzen
+    writes: HashMap<String, HashSet<String>>,
+    current_writes: HashSet<String>,
+    // Nur eine Warnung pro Variable und Kategorie
+    reported: HashSet<(WarningKind, String)>,
+    // Anfang der untersuchten Anweisung (bzw. Zeile des RETURN) für die Meldungen
+    position: (usize, usize),
+    warnings: Vec<Warning>,
+}
+
+impl<'a> FlowAnalyzer<'a> {
+    fn analyze_procedure(&mut self, proc: &Procedure, path: &[String]) {
+        let mut path = path.to_vec();
+        path.push(proc.name.clone());
+
+        // Geschachtelte Prozeduren zuerst, damit ihre Zuweisungen bekannt sind
+        for nested in &proc.declarations.procedures {
+            self.analyze_procedure(nested, &path);
+        }
+
+        let prefix = path.join(".");
+        self.procedure = proc.name.clone();
+        self.locals = proc
+            .declarations
+            .variables
+            .iter()
+            .map(|var| format!("{}.{}", prefix, var.name))
+            .collect();
+        self.current_writes = HashSet::new();
+
+        let mut flow = Flow::entry();
+        self.statements(&proc.body, &mut flow);
+
+        if let Some(return_expr) = &proc.return_expr {
+            if flow.reachable {
+                self.position = (proc.return_line, 0);
+                self.read_kind = WarningKind::UninitializedResult;
+                self.read(return_expr, &mut flow);
+                self.read_kind = WarningKind::UninitializedRead;
+            }
+        }
+
+        let writes = std::mem::take(&mut self.current_writes);
+        self.writes.insert(prefix, writes);
+    }
+
+    // ========================================================================
+    // Statements
+    // ========================================================================
+
+    // Nach einer geschachtelten Folge gilt wieder die Position der
+    // umgebenden Anweisung (Bedingungen von ELSIF, UNTIL usw.)
+    fn statements(&mut self, statements: &[Statement], flow: &mut Flow) {
+        let outer = self.position;
+        for stmt in statements {
+            let span = stmt.span();
+            self.position = (span.line, span.column);
+            if !flow.reachable {
+                let message = format!(
+                    "Anweisungen nach unbedingtem Abbruch in '{}' werden nie ausgeführt",
+                    self.procedure
+                );
+                self.warnings.push(Warning::new(WarningKind::Unreachable, message, self.position));
+                break;
+            }
+            self.statement(stmt, flow);
+        }
+        self.position = outer;
+    }
+
+    fn statement(&mut self, stmt: &Statement, flow: &mut Flow) {
+        match stmt {
+            Statement::Assignment { target, value, .. } => {
+                self.read(value, flow);
+                self.write(target, flow);
+            }
+            Statement::ProcedureCall { procedure, arguments, .. } => {
+                self.call(procedure, arguments, flow);
+            }
+            Statement::LibraryCall { arguments, .. } => {
+                for argument in arguments {
+                    self.read(argument, flow);
+                }
+            }
+            Statement::BuiltinCall { builtin, arguments, .. } => {
+                self.builtin(*builtin, arguments, flow);
+                if *builtin == Builtin::Assert && arguments.first().is_some_and(is_const_false) {
+                    flow.reachable = false;
+                }
+            }
+            Statement::If { branches, else_body, .. } => {
+                // Bedingungen werden nacheinander ausgewertet, bis eine zutrifft
+                let mut outcomes = Vec::new();
+                let mut current = flow.clone();
+                for (condition, body) in branches {
+                    self.read(condition, &mut current);
+                    let mut branch = current.clone();
+                    self.statements(body, &mut branch);
+                    outcomes.push(branch);
+                }
+
+                if let Some(else_body) = else_body {
+                    self.statements(else_body, &mut current);
+                }
+                outcomes.push(current);
+                *flow = Flow::join(outcomes);
+            }
+            Statement::Case { expr, clauses, else_body, .. } => {
+                self.read(expr, flow);
+
+                let mut outcomes = Vec::new();
+                for clause in clauses {
+                    let mut branch = flow.clone();
+                    self.statements(&clause.body, &mut branch);
+                    outcomes.push(branch);
+                }
+
+                // Ohne ELSE bricht ein nicht getroffener CASE mit einem Trap ab;
+                // nur die Zweige bestimmen den Zustand danach
+                if let Some(else_body) = else_body {
+                    let mut rest = flow.clone();
+                    self.statements(else_body, &mut rest);
+                    outcomes.push(rest);
+                }
+                *flow = Flow::join(outcomes);
+            }
+            Statement::While { branches, .. } => {
+                // Der Rumpf läuft eventuell nie; danach sind alle Bedingungen ausgewertet
+                for (condition, body) in branches {
+                    self.read(condition, flow);
+                    let mut branch = flow.clone();
+                    self.statements(body, &mut branch);
+                }
+            }
+            Statement::Repeat { body, condition, .. } => {
+                self.statements(body, flow);
+                if flow.reachable {
+                    self.read(condition, flow);
+                }
+            }
+            Statement::For { variable, start, end, body, .. } => {
+                self.read(start, flow);
+                self.read(end, flow);
+                self.assign(&variable.qualified_name, flow);
+
+                let mut branch = flow.clone();
+                self.statements(body, &mut branch);
+            }
+        }
+    }
+
+    fn call(&mut self, procedure: &Designator, arguments: &[Expr], flow: &mut Flow) {
+        self.read_designator(procedure, flow);
+
+        let types = self.types;
+        let params = match types.kind(procedure.ty) {
+            TypeKind::Procedure { params, .. } => params.as_slice(),
+            _ => &[],
+        };
+
+        for (i, arg) in arguments.iter().enumerate() {
+            match &arg.kind {
+                // VAR-Argumente gelten als Ausgabe der Prozedur
+                ExprKind::Designator(designator) if params.get(i).is_some_and(|param| param.is_var) => {
+                    self.write(designator, flow);
+                }
+                _ => self.read(arg, flow),
+            }
+        }
+
+        // Geschachtelte Prozeduren können Variablen der umgebenden Prozedur setzen
+        if let Some(writes) = self.writes.get(&procedure.symbol.qualified_name).cloned() {
+            for name in writes {
+                self.assign(&name, flow);
+            }
+        }
+    }
+
+    fn builtin(&mut self, builtin: Builtin, arguments: &[Expr], flow: &mut Flow) {
+        match (builtin, arguments) {
+            (Builtin::New, [Expr { kind: ExprKind::Designator(designator), .. }]) => {
+                self.write(designator, flow);
+            }
+            _ => {
+                // INC, DEC, INCL und EXCL lesen ihr erstes Argument vor dem Schreiben
+                for arg in arguments {
+                    self.read(arg, flow);
+                }
+            }
+        }
+    }
+
+    // ========================================================================
+    // Lesen und Schreiben
+    // ========================================================================
+
+    fn read(&mut self, expr: &Expr, flow: &mut Flow) {
+        match &expr.kind {
+            ExprKind::Literal(_) => {}
+            ExprKind::Designator(designator) => self.read_designator(designator, flow),
+            ExprKind::Call { procedure, arguments } => self.call(procedure, arguments, flow),
+            ExprKind::BuiltinCall { builtin, arguments } => self.builtin(*builtin, arguments, flow),
+            ExprKind::Unary { operand, .. } => self.read(operand, flow),
+            ExprKind::Binary { left, right, .. } => {
+                self.read(left, flow);
+                self.read(right, flow);
+            }
+            ExprKind::Set(elements) => {
+                for (start, end) in elements {
+                    self.read(start, flow);
+                    if let Some(end) = end {
+                        self.read(end, flow);
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_designator(&mut self, designator: &Designator, flow: &mut Flow) {
+        self.read_selectors(designator, flow);
+
+        let name = &designator.symbol.qualified_name;
+        if !self.locals.contains(name) || flow.assigned.contains(name) {
+            return;
+        }
+
+        if self.reported.insert((self.read_kind, name.clone())) {
+            let message = match self.read_kind {
+                WarningKind::UninitializedResult => format!(
+                    "RETURN von '{}' liefert möglicherweise einen nicht initialisierten Wert ('{}')",
+                    self.procedure, designator.symbol.name
+                ),
+                _ => format!(
+                    "Variable '{}' wird in '{}' möglicherweise vor der Zuweisung gelesen",
+                    designator.symbol.name, self.procedure
+                ),
+            };
+            self.warnings.push(Warning::new(self.read_kind, message, self.position));
+        }
+    }
+
+    // Zuweisung an einen Designator; auch Zuweisungen an Elemente und Felder
+    // gelten als Initialisierung. Über p^ wird p dagegen nur gelesen.
+    fn write(&mut self, designator: &Designator, flow: &mut Flow) {
+        let dereferences = designator
+            .selectors
+            .iter()
+            .any(|selector| matches!(selector, Selector::Dereference { .. }));
+
+        if dereferences {
+            self.read_designator(designator, flow);
+        } else {
+            self.read_selectors(designator, flow);
+            self.assign(&designator.symbol.qualified_name, flow);
+        }
+    }
+
+    fn read_selectors(&mut self, designator: &Designator, flow: &mut Flow) {
+        for selector in &designator.selectors {
+            if let Selector::Index { index, .. } = selector {
+                self.read(index, flow);
+            }
+        }
+    }
+
+    fn assign(&mut self, name: &str, flow: &mut Flow) {
+        self.current_writes.insert(name.to_string());
+        if self.locals.contains(name) {
+            flow.assigned.insert(name.to_string());
+        }
+    }
+}
+
+fn is_const_false(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Literal(ConstValue::Boolean(false)) => true,
+        ExprKind::Designator(designator) => {
+            designator.selectors.is_empty()
+                && designator.symbol.kind == SymbolRefKind::Constant(ConstValue::Boolean(false))
+        }
+        _ => false,
+    }
+}
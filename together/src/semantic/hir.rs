@@ -0,0 +1,307 @@
+use crate::parser::ast::{BinaryOp, ExportMark, Import, Span, UnaryOp};
+use super::const_eval::ConstValue;
+use super::library::LibraryProcedure;
+use super::symbol_table::Builtin;
+use super::types::{TypeArena, TypeId};
+
+// Typisierter, namensaufgelöster Baum (HIR) als Ergebnis der semantischen
+// Analyse. Jeder Ausdruck trägt seine TypeId, jeder Bezeichner verweist
+// auf sein Symbol. Die Code-Generatoren arbeiten nur auf dieser Darstellung.
+
+// ============================================================================
+// Module
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub imports: Vec<Import>,
+    // Alle Typen des Moduls; TypeIds im HIR verweisen hierauf
+    pub types: TypeArena,
+    pub declarations: Declarations,
+    pub body: Vec<Statement>,
+    // Exportierte Variablen der Importe mit Schnittstelle, Name als
+    // "Modul.Name" und Typ in `types`
+    pub imported_variables: Vec<VariableDeclaration>,
+}
+
+// ============================================================================
+// Deklarationen
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Declarations {
+    pub constants: Vec<ConstDeclaration>,
+    pub types: Vec<TypeDeclaration>,
+    pub variables: Vec<VariableDeclaration>,
+    pub procedures: Vec<Procedure>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstDeclaration {
+    pub name: String,
+    pub exported: ExportMark,
+    pub value: ConstValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDeclaration {
+    pub name: String,
+    pub exported: ExportMark,
+    pub type_def: TypeId,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub exported: ExportMark,
+    pub var_type: TypeId,
+    // Zeile des Namens
+    pub line: usize,
+}
+
+// ============================================================================
+// Prozeduren
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Procedure {
+    pub name: String,
+    pub exported: ExportMark,
+    pub params: Vec<Param>,
+    pub return_type: Option<TypeId>,
+    pub declarations: Declarations,
+    pub body: Vec<Statement>,
+    pub return_expr: Option<Expr>,
+    // Zeile des RETURN (0 ohne Rückgabe)
+    pub return_line: usize,
+    // Zeile des Prozedurnamens und des abschließenden END
+    pub line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub is_var: bool,
+    pub param_type: TypeId,
+    pub line: usize,
+}
+
+// ============================================================================
+// Statements
+// ============================================================================
+
+// `line` ist jeweils die Zeile, in der die Anweisung beginnt, `span` ihre
+// ganze Ausdehnung
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Assignment {
+        line: usize,
+        span: Span,
+        target: Designator,
+        value: Expr,
+    },
+    ProcedureCall {
+        line: usize,
+        span: Span,
+        procedure: Designator,
+        arguments: Vec<Expr>,
+    },
+    BuiltinCall {
+        line: usize,
+        span: Span,
+        builtin: Builtin,
+        arguments: Vec<Expr>,
+    },
+    // Aufruf einer Prozedur eines eingebauten Bibliotheksmoduls (Out)
+    LibraryCall {
+        line: usize,
+        span: Span,
+        procedure: LibraryProcedure,
+        arguments: Vec<Expr>,
+    },
+    // IF/ELSIF-Zweige in Reihenfolge
+    If {
+        line: usize,
+        span: Span,
+        branches: Vec<(Expr, Vec<Statement>)>,
+        else_body: Option<Vec<Statement>>,
+    },
+    Case {
+        line: usize,
+        span: Span,
+        expr: Expr,
+        clauses: Vec<CaseClause>,
+        else_body: Option<Vec<Statement>>,
+    },
+    // Oberon-07 WHILE mit ELSIF: Schleife läuft, solange ein Zweig zutrifft
+    While {
+        line: usize,
+        span: Span,
+        branches: Vec<(Expr, Vec<Statement>)>,
+    },
+    Repeat {
+        line: usize,
+        span: Span,
+        body: Vec<Statement>,
+        condition: Expr,
+    },
+    For {
+        line: usize,
+        span: Span,
+        variable: SymbolRef,
+        start: Expr,
+        end: Expr,
+        step: i64,
+        body: Vec<Statement>,
+    },
+}
+
+impl Statement {
+    pub fn line(&self) -> usize {
+        match self {
+            Statement::Assignment { line, .. }
+            | Statement::ProcedureCall { line, .. }
+            | Statement::BuiltinCall { line, .. }
+            | Statement::LibraryCall { line, .. }
+            | Statement::If { line, .. }
+            | Statement::Case { line, .. }
+            | Statement::While { line, .. }
+            | Statement::Repeat { line, .. }
+            | Statement::For { line, .. } => *line,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Assignment { span, .. }
+            | Statement::ProcedureCall { span, .. }
+            | Statement::BuiltinCall { span, .. }
+            | Statement::LibraryCall { span, .. }
+            | Statement::If { span, .. }
+            | Statement::Case { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Repeat { span, .. }
+            | Statement::For { span, .. } => *span,
+        }
+    }
+}
+
+// CASE-Labels als ausgewertete Ordinalbereiche (Einzelwerte: start == end)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseClause {
+    pub labels: Vec<(i64, i64)>,
+    pub body: Vec<Statement>,
+}
+
+// ============================================================================
+// Ausdrücke
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: TypeId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal(ConstValue),
+    Designator(Designator),
+    Call {
+        procedure: Designator,
+        arguments: Vec<Expr>,
+    },
+    BuiltinCall {
+        builtin: Builtin,
+        arguments: Vec<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOp,
+        right: Box<Expr>,
+    },
+    // Nicht-konstante Mengen; konstante Mengen sind bereits Literale
+    Set(Vec<(Expr, Option<Expr>)>),
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, ty: TypeId) -> Self {
+        Expr { kind, ty }
+    }
+
+    pub fn literal(value: ConstValue) -> Self {
+        let ty = value.type_id();
+        Expr::new(ExprKind::Literal(value), ty)
+    }
+}
+
+// ============================================================================
+// Designator
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Designator {
+    pub symbol: SymbolRef,
+    pub selectors: Vec<Selector>,
+    pub ty: TypeId,
+}
+
+// Jeder Selektor trägt den Typ nach seiner Anwendung;
+// mehrdimensionale Indizes a[i, j] werden zu a[i][j] aufgelöst.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Field {
+        name: String,
+        ty: TypeId,
+    },
+    Index {
+        index: Box<Expr>,
+        ty: TypeId,
+    },
+    Dereference {
+        ty: TypeId,
+    },
+    TypeGuard {
+        ty: TypeId,
+    },
+}
+
+// Verweis auf ein Symbol der Symboltabelle
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolRef {
+    pub name: String,
+    // "Name" auf Modulebene, "Proc.Name" für lokale Symbole, "Modul.Name"
+    // für Symbole importierter Module
+    pub qualified_name: String,
+    pub kind: SymbolRefKind,
+    // Modul, aus dem das Symbol importiert ist
+    pub module: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolRefKind {
+    Constant(ConstValue),
+    Variable {
+        // 0 = Modulebene, sonst Schachtelungstiefe der Prozedur
+        level: usize,
+        is_parameter: bool,
+        is_var_param: bool,
+    },
+    Procedure,
+    Type,
+    Module,
+}
+
+impl SymbolRef {
+    pub fn is_var_param(&self) -> bool {
+        matches!(self.kind, SymbolRefKind::Variable { is_var_param: true, .. })
+    }
+}
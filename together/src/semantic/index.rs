@@ -0,0 +1,153 @@
+use super::const_eval::ConstValue;
+use super::types::{TypeArena, TypeId, TypeKind};
+use crate::parser::ast::ExportMark;
+
+// Symbolindex für Editorfunktionen: alle Deklarationen mit Position und Typ,
+// alle aufgelösten Verwendungen und die Gültigkeitsbereiche als Zeilenbereiche.
+// Der Typprüfer baut ihn nebenbei auf, auch für fehlerhafte Module.
+
+// (Zeile, Spalte), 1-basiert wie im Scanner
+pub type Position = (usize, usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionKind {
+    Constant(ConstValue),
+    Type,
+    Variable,
+    Parameter { is_var: bool },
+    Procedure,
+    // Feld des Records `record`
+    Field { record: TypeId },
+    Module,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    // Typ des Werts, bei Typdeklarationen der deklarierte Typ; None bei Modulen
+    pub ty: Option<TypeId>,
+    pub position: Position,
+    pub scope: usize,
+    pub exported: ExportMark,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub position: Position,
+    // Position der Deklaration (Symbol::defined_at)
+    pub definition: Position,
+}
+
+// Modul (Index 0) oder Prozedur
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scope {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolIndex {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+    pub scopes: Vec<Scope>,
+    pub types: TypeArena,
+}
+
+impl SymbolIndex {
+    pub fn new(module_name: &str) -> Self {
+        SymbolIndex {
+            definitions: Vec::new(),
+            references: Vec::new(),
+            scopes: vec![Scope {
+                name: module_name.to_string(),
+                parent: None,
+                start_line: 1,
+                end_line: usize::MAX,
+            }],
+            types: TypeArena::new(),
+        }
+    }
+
+    pub fn definition(&self, position: Position) -> Option<&Definition> {
+        self.definitions.iter().find(|def| def.position == position)
+    }
+
+    // Deklaration zum Bezeichner, der `position` überdeckt (Deklaration oder Verwendung)
+    pub fn definition_at(&self, position: Position) -> Option<&Definition> {
+        let covers = |start: Position, name: &str| {
+            start.0 == position.0 && start.1 <= position.1 && position.1 < start.1 + name.chars().count()
+        };
+
+        if let Some(def) = self.definitions.iter().find(|def| covers(def.position, &def.name)) {
+            return Some(def);
+        }
+        self.references.iter().find_map(|reference| {
+            let def = self.definition(reference.definition)?;
+            covers(reference.position, &def.name).then_some(def)
+        })
+    }
+
+    // Alle Verwendungen einer Deklaration (ohne die Deklaration selbst)
+    pub fn references_to(&self, definition: Position) -> Vec<Position> {
+        let mut positions: Vec<Position> = self
+            .references
+            .iter()
+            .filter(|reference| reference.definition == definition)
+            .map(|reference| reference.position)
+            .collect();
+        positions.sort();
+        positions.dedup();
+        positions
+    }
+
+    // Innerster Gültigkeitsbereich, der die Zeile enthält
+    pub fn scope_at(&self, line: usize) -> usize {
+        (0..self.scopes.len())
+            .filter(|&scope| self.scopes[scope].start_line <= line && line <= self.scopes[scope].end_line)
+            .max_by_key(|&scope| self.scopes[scope].start_line)
+            .unwrap_or(0)
+    }
+
+    // In der Zeile sichtbare Deklarationen (ohne Felder); innere verdecken äußere
+    pub fn visible_at(&self, line: usize) -> Vec<&Definition> {
+        let mut visible: Vec<&Definition> = Vec::new();
+        let mut scope = Some(self.scope_at(line));
+
+        while let Some(current) = scope {
+            for def in &self.definitions {
+                let is_field = matches!(def.kind, DefinitionKind::Field { .. });
+                if def.scope == current && !is_field && !visible.iter().any(|seen| seen.name == def.name) {
+                    visible.push(def);
+                }
+            }
+            scope = self.scopes[current].parent;
+        }
+        visible
+    }
+
+    // Felder eines Records einschließlich geerbter (Basistyp zuerst)
+    pub fn fields(&self, record: TypeId) -> Vec<&Definition> {
+        let mut chain = Vec::new();
+        let mut current = Some(record);
+        while let Some(record) = current {
+            chain.push(record);
+            current = match self.types.kind(record) {
+                TypeKind::Record { base, .. } => *base,
+                _ => None,
+            };
+        }
+
+        chain
+            .into_iter()
+            .rev()
+            .flat_map(|record| {
+                self.definitions
+                    .iter()
+                    .filter(move |def| def.kind == DefinitionKind::Field { record })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::io::{self, Write};
+
+// Eingebaute Bibliotheksmodule, die ohne eigenen Quelltext importiert werden
+// können. Derzeit nur Out nach den Oakwood-Richtlinien; die Backends und der
+// Interpreter setzen die Prozeduren direkt um.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LibraryProcedure {
+    // Out.Open
+    OutOpen,
+    // Out.Char(ch: CHAR)
+    OutChar,
+    // Out.String(s: ARRAY OF CHAR), bis zum ersten 0X
+    OutString,
+    // Out.Int(i, n: INTEGER), rechtsbündig in n Zeichen
+    OutInt,
+    // Out.Real(x: REAL; n: INTEGER), rechtsbündig in n Zeichen
+    OutReal,
+    // Out.Ln
+    OutLn,
+}
+
+impl LibraryProcedure {
+    pub const ALL: [LibraryProcedure; 6] = [
+        LibraryProcedure::OutOpen,
+        LibraryProcedure::OutChar,
+        LibraryProcedure::OutString,
+        LibraryProcedure::OutInt,
+        LibraryProcedure::OutReal,
+        LibraryProcedure::OutLn,
+    ];
+
+    pub fn module(&self) -> &'static str {
+        "Out"
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LibraryProcedure::OutOpen => "Open",
+            LibraryProcedure::OutChar => "Char",
+            LibraryProcedure::OutString => "String",
+            LibraryProcedure::OutInt => "Int",
+            LibraryProcedure::OutReal => "Real",
+            LibraryProcedure::OutLn => "Ln",
+        }
+    }
+
+    // `module` ist der echte Modulname, nicht der Alias aus der IMPORT-Liste
+    pub fn lookup(module: &str, name: &str) -> Option<LibraryProcedure> {
+        Self::ALL
+            .into_iter()
+            .find(|procedure| procedure.module() == module && procedure.name() == name)
+    }
+
+    // Eingebautes Modul, das nicht geladen werden muss
+    pub fn is_library_module(module: &str) -> bool {
+        Self::ALL.iter().any(|procedure| procedure.module() == module)
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            LibraryProcedure::OutOpen | LibraryProcedure::OutLn => 0,
+            LibraryProcedure::OutChar | LibraryProcedure::OutString => 1,
+            LibraryProcedure::OutInt | LibraryProcedure::OutReal => 2,
+        }
+    }
+}
+
+// Schreibt `text` rechtsbündig in `width` Zeichen (Out.Int, Out.Real). Die
+// Auffüllung entsteht hier statt über `{:>width$}`, denn std::fmt bricht bei
+// Breiten über u16::MAX ab.
+pub fn write_padded(out: &mut dyn Write, text: &str, width: i64) -> io::Result<()> {
+    const SPACES: &str = "                                                                ";
+    let mut padding = usize::try_from(width).unwrap_or(0).saturating_sub(text.chars().count());
+    while padding > 0 {
+        let chunk = padding.min(SPACES.len());
+        out.write_all(&SPACES.as_bytes()[..chunk])?;
+        padding -= chunk;
+    }
+    out.write_all(text.as_bytes())
+}
+
+impl fmt::Display for LibraryProcedure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.module(), self.name())
+    }
+}
@@ -0,0 +1,166 @@
+use super::hir;
+use super::library::LibraryProcedure;
+use super::{TypeChecker, Warning, WarningOptions};
+use crate::parser::ast::Module;
+use crate::parser::Parser;
+use crate::scanner::Lexer;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Quelltextlader für Programme aus mehreren Modulen
+// ============================================================================
+//
+// Sucht importierte Module als <Name>.Mod in den Suchpfaden, in der
+// Reihenfolge, in der die Pfade hinzugefügt wurden, und prüft jedes Modul
+// nach allen, die es importiert, mit deren Schnittstellen
+// (TypeChecker::add_interface). Das Ergebnis ist wie bei vm::Loader die
+// Initialisierungsreihenfolge; Bibliotheksmodule wie Out brauchen keinen
+// Quelltext.
+
+pub const EXTENSION: &str = "Mod";
+
+pub struct ModuleLoader {
+    search_path: Vec<PathBuf>,
+    warnings: WarningOptions,
+}
+
+// Geprüftes Modul mit seiner Quelldatei und den Warnungen der Prüfung
+#[derive(Debug, Clone)]
+pub struct LoadedModule {
+    pub path: PathBuf,
+    pub module: hir::Module,
+    pub warnings: Vec<Warning>,
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        Self::with_warnings(WarningOptions::default())
+    }
+
+    pub fn with_warnings(warnings: WarningOptions) -> Self {
+        ModuleLoader { search_path: Vec::new(), warnings }
+    }
+
+    pub fn add_path(&mut self, dir: impl Into<PathBuf>) {
+        self.search_path.push(dir.into());
+    }
+
+    // Lädt und prüft die Datei und alle Module, die sie (mittelbar)
+    // importiert. Fehler nennen Datei und Zeile, je einer pro Zeile.
+    pub fn load_file(&self, path: &Path) -> Result<Vec<LoadedModule>, String> {
+        let module = parse(path)?;
+        let mut order = Vec::new();
+        let mut active = Vec::new();
+        self.visit(path, module, &mut order, &mut active)?;
+        Ok(order)
+    }
+
+    // Tiefensuche; `active` ist die Importkette zum aktuellen Modul
+    fn visit(
+        &self,
+        path: &Path,
+        module: Module,
+        order: &mut Vec<LoadedModule>,
+        active: &mut Vec<String>,
+    ) -> Result<(), String> {
+        active.push(module.name.clone());
+        for import in &module.imports {
+            let name = &import.module_name;
+            if LibraryProcedure::is_library_module(name) {
+                continue;
+            }
+            if active.contains(name) {
+                return Err(format!("Zyklischer Import: {} -> {}", active.join(" -> "), name));
+            }
+            if order.iter().any(|loaded| &loaded.module.name == name) {
+                continue;
+            }
+            let (imported_path, imported) = self.find(name)?;
+            self.visit(&imported_path, imported, order, active)?;
+        }
+        active.pop();
+
+        let mut type_checker = TypeChecker::with_warnings(self.warnings.clone());
+        for loaded in order.iter() {
+            type_checker.add_interface(&loaded.module);
+        }
+        match type_checker.check_module(&module) {
+            Ok(checked) => {
+                let warnings = type_checker.warnings().to_vec();
+                order.push(LoadedModule { path: path.to_path_buf(), module: checked, warnings });
+                Ok(())
+            }
+            Err(_) => {
+                let errors: Vec<String> = type_checker
+                    .located_errors()
+                    .into_iter()
+                    .map(|(line, error)| format!("{}:{}: {}", path.display(), line, error))
+                    .collect();
+                Err(errors.join("\n"))
+            }
+        }
+    }
+
+    fn find(&self, name: &str) -> Result<(PathBuf, Module), String> {
+        let file = format!("{}.{}", name, EXTENSION);
+        let path = match self.search_path.iter().map(|dir| dir.join(&file)).find(|path| path.is_file()) {
+            Some(path) => path,
+            None => {
+                let dirs: Vec<String> = self.search_path.iter().map(|dir| dir.display().to_string()).collect();
+                return Err(format!("Modul {} nicht gefunden (gesucht in: {})", name, dirs.join(", ")));
+            }
+        };
+
+        let module = parse(&path)?;
+        if module.name != name {
+            return Err(format!("{}: enthält Modul {} statt {}", path.display(), module.name, name));
+        }
+        Ok((path, module))
+    }
+}
+
+impl Default for ModuleLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse(path: &Path) -> Result<Module, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: Kann Datei nicht lesen: {}", path.display(), e))?;
+    let tokens = Lexer::new(&source).tokenize().map_err(|e| format!("{}: {}", path.display(), e))?;
+    Parser::new(tokens).parse().map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_order() {
+        let dir = std::env::temp_dir().join(format!("together-loader-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sources = [
+            ("Main", "MODULE Main; IMPORT Out, A, B; BEGIN Out.Int(A.x + B.y, 0) END Main."),
+            ("A", "MODULE A; IMPORT C; VAR x*: INTEGER; BEGIN x := C.z END A."),
+            ("B", "MODULE B; IMPORT C; VAR y*: INTEGER; END B."),
+            ("C", "MODULE C; VAR z*: INTEGER; END C."),
+            ("D", "MODULE D; IMPORT E; END D."),
+            ("E", "MODULE E; IMPORT D; END E."),
+        ];
+        for (name, source) in sources {
+            fs::write(dir.join(format!("{}.Mod", name)), source).unwrap();
+        }
+
+        let mut loader = ModuleLoader::new();
+        loader.add_path(&dir);
+        let order = loader.load_file(&dir.join("Main.Mod")).map(|modules| {
+            modules.into_iter().map(|loaded| loaded.module.name).collect::<Vec<_>>()
+        });
+        let cycle = loader.load_file(&dir.join("D.Mod")).map(|_| ()).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(order.unwrap(), ["C", "A", "B", "Main"]);
+        assert_eq!(cycle, "Zyklischer Import: D -> E -> D");
+    }
+}
@@ -1,6 +1,17 @@
-
+pub mod const_eval;
+pub mod flow;
+pub mod hir;
+pub mod index;
+pub mod library;
+pub mod loader;
 pub mod symbol_table;
 pub mod type_checker;
+pub mod types;
+pub mod warnings;
 
-pub use symbol_table::{SymbolTable, Symbol, SymbolKind, ResolvedType};
-pub use type_checker::TypeChecker;
\ No newline at end of file
+pub use const_eval::ConstValue;
+pub use loader::ModuleLoader;
+pub use symbol_table::{SymbolTable, Symbol, SymbolKind};
+pub use type_checker::TypeChecker;
+pub use types::{TypeArena, TypeId, TypeKind};
+pub use warnings::{Warning, WarningKind, WarningOptions};
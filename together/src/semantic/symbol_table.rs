@@ -1,61 +1,102 @@
 
 use crate::parser::ast::*;
-use std::collections::HashMap;
+use super::const_eval::ConstValue;
+use super::types::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SymbolKind {
     Constant {
-        value: Expression,
-        resolved_type: ResolvedType,
+        value: ConstValue,
+        resolved_type: TypeId,
     },
     Type {
-        type_def: ResolvedType,
+        type_def: TypeId,
     },
     Variable {
-        var_type: ResolvedType,
+        var_type: TypeId,
         is_parameter: bool,
         is_var_param: bool,
     },
+    // Signatur als Prozedurtyp in der Typ-Arena
     Procedure {
-        params: Vec<Parameter>,
-        return_type: Option<ResolvedType>,
+        proc_type: TypeId,
     },
+    Builtin(Builtin),
     Module,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Parameter {
-    pub name: String,
-    pub param_type: ResolvedType,
-    pub is_var: bool,
+// Vordeklarierte Prozeduren (Oberon-07 Report, Kapitel 10.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    // Funktionsprozeduren
+    Abs,
+    Odd,
+    Len,
+    Lsl,
+    Asr,
+    Ror,
+    Floor,
+    Flt,
+    Ord,
+    Chr,
+    // Eigentliche Prozeduren
+    Inc,
+    Dec,
+    Incl,
+    Excl,
+    New,
+    Assert,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum ResolvedType {
-    Integer,
-    Real,
-    Boolean,
-    Char,
-    String,
-    Nil,
-    Set,
-    Array {
-        dimensions: Vec<usize>,
-        element_type: Box<ResolvedType>,
-    },
-    Record {
-        fields: HashMap<String, ResolvedType>,
-        base_type: Option<Box<ResolvedType>>,
-    },
-    Pointer {
-        target_type: Box<ResolvedType>,
-    },
-    Procedure {
-        params: Vec<Parameter>,
-        return_type: Option<Box<ResolvedType>>,
-    },
-    Named(String),
-    // TvB Unresolved,
+impl Builtin {
+    pub const ALL: [Builtin; 16] = [
+        Builtin::Abs,
+        Builtin::Odd,
+        Builtin::Len,
+        Builtin::Lsl,
+        Builtin::Asr,
+        Builtin::Ror,
+        Builtin::Floor,
+        Builtin::Flt,
+        Builtin::Ord,
+        Builtin::Chr,
+        Builtin::Inc,
+        Builtin::Dec,
+        Builtin::Incl,
+        Builtin::Excl,
+        Builtin::New,
+        Builtin::Assert,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Abs => "ABS",
+            Builtin::Odd => "ODD",
+            Builtin::Len => "LEN",
+            Builtin::Lsl => "LSL",
+            Builtin::Asr => "ASR",
+            Builtin::Ror => "ROR",
+            Builtin::Floor => "FLOOR",
+            Builtin::Flt => "FLT",
+            Builtin::Ord => "ORD",
+            Builtin::Chr => "CHR",
+            Builtin::Inc => "INC",
+            Builtin::Dec => "DEC",
+            Builtin::Incl => "INCL",
+            Builtin::Excl => "EXCL",
+            Builtin::New => "NEW",
+            Builtin::Assert => "ASSERT",
+        }
+    }
+
+    pub fn is_function(&self) -> bool {
+        !matches!(
+            self,
+            Builtin::Inc | Builtin::Dec | Builtin::Incl | Builtin::Excl | Builtin::New | Builtin::Assert
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -69,8 +110,11 @@ pub struct Symbol {
     pub defined_at: Option<(usize, usize)>, // line, column
 }
 
+#[derive(Clone)]
 pub struct SymbolTable {
     scopes: Vec<HashMap<String, Symbol>>,
+    // Pro Scope die Namen, die per lookup gefunden wurden (für Warnungen zu unbenutzten Symbolen)
+    used: RefCell<Vec<HashSet<String>>>,
     current_procedure: Option<String>,
 }
 
@@ -78,6 +122,7 @@ impl SymbolTable {
     pub fn new() -> Self {
         let mut table = SymbolTable {
             scopes: vec![HashMap::new()],
+            used: RefCell::new(vec![HashSet::new()]),
             current_procedure: None,
         };
         table.add_predefined_types();
@@ -89,7 +134,7 @@ impl SymbolTable {
         self.define(Symbol {
             name: "INTEGER".to_string(),
             kind: SymbolKind::Type {
-                type_def: ResolvedType::Integer,
+                type_def: TypeId::INTEGER,
             },
             exported: ExportMark::None,
             defined_at: None,
@@ -98,7 +143,7 @@ impl SymbolTable {
         self.define(Symbol {
             name: "REAL".to_string(),
             kind: SymbolKind::Type {
-                type_def: ResolvedType::Real,
+                type_def: TypeId::REAL,
             },
             exported: ExportMark::None,
             defined_at: None,
@@ -107,7 +152,7 @@ impl SymbolTable {
         self.define(Symbol {
             name: "BOOLEAN".to_string(),
             kind: SymbolKind::Type {
-                type_def: ResolvedType::Boolean,
+                type_def: TypeId::BOOLEAN,
             },
             exported: ExportMark::None,
             defined_at: None,
@@ -116,7 +161,7 @@ impl SymbolTable {
         self.define(Symbol {
             name: "CHAR".to_string(),
             kind: SymbolKind::Type {
-                type_def: ResolvedType::Char,
+                type_def: TypeId::CHAR,
             },
             exported: ExportMark::None,
             defined_at: None,
@@ -125,20 +170,32 @@ impl SymbolTable {
         self.define(Symbol {
             name: "SET".to_string(),
             kind: SymbolKind::Type {
-                type_def: ResolvedType::Set,
+                type_def: TypeId::SET,
             },
             exported: ExportMark::None,
             defined_at: None,
         }).ok();
+
+        // Standardprozeduren
+        for builtin in Builtin::ALL {
+            self.define(Symbol {
+                name: builtin.name().to_string(),
+                kind: SymbolKind::Builtin(builtin),
+                exported: ExportMark::None,
+                defined_at: None,
+            }).ok();
+        }
     }
 
     pub fn enter_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.used.get_mut().push(HashSet::new());
     }
 
     pub fn exit_scope(&mut self) {
         if self.scopes.len() > 1 {
             self.scopes.pop();
+            self.used.get_mut().pop();
         }
     }
 
@@ -153,70 +210,58 @@ impl SymbolTable {
         Ok(())
     }
 
+    // Gefundene Symbole gelten als benutzt
     pub fn lookup(&self, name: &str) -> Option<&Symbol> {
-        for scope in self.scopes.iter().rev() {
-            if let Some(symbol) = scope.get(name) {
-                return Some(symbol);
-            }
-        }
-        None
+        self.lookup_with_level(name).map(|(_, symbol)| symbol)
     }
 
-    #[allow(dead_code)]
-    pub fn lookup_in_current_scope(&self, name: &str) -> Option<&Symbol> {
-        self.scopes.last().and_then(|scope| scope.get(name))
-    }
+    // Wie lookup, liefert zusätzlich die Tiefe des Scopes (0 = Modulebene)
+    pub fn lookup_with_level(&self, name: &str) -> Option<(usize, &Symbol)> {
+        let found = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(level, scope)| scope.get(name).map(|symbol| (level, symbol)));
 
-    pub fn set_current_procedure(&mut self, name: Option<String>) {
-        self.current_procedure = name;
+        if let Some((level, _)) = found {
+            self.used.borrow_mut()[level].insert(name.to_string());
+        }
+        found
     }
 
-    #[allow(dead_code)]
-    pub fn get_current_procedure(&self) -> Option<&String> {
-        self.current_procedure.as_ref()
-    }
-}
+    // Symbole des innersten Scopes, die nie nachgeschlagen wurden (nach Namen sortiert)
+    pub fn unused_in_current_scope(&self) -> Vec<&Symbol> {
+        let used = self.used.borrow();
+        let Some(used) = used.last() else {
+            return Vec::new();
+        };
 
-impl ResolvedType {
-    pub fn is_numeric(&self) -> bool {
-        matches!(self, ResolvedType::Integer | ResolvedType::Real)
+        self.current_scope_symbols()
+            .into_iter()
+            .filter(|symbol| !used.contains(&symbol.name))
+            .collect()
     }
 
-    pub fn is_integer(&self) -> bool {
-        matches!(self, ResolvedType::Integer)
+    // Alle Symbole des innersten Gültigkeitsbereichs, nach Namen sortiert
+    pub fn current_scope_symbols(&self) -> Vec<&Symbol> {
+        let mut symbols: Vec<&Symbol> = self.scopes.last().map(|scope| scope.values().collect()).unwrap_or_default();
+        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+        symbols
     }
 
-    pub fn is_boolean(&self) -> bool {
-        matches!(self, ResolvedType::Boolean)
+    // Ohne Markierung als benutzt (für Deklarationsprüfungen)
+    pub fn lookup_in_current_scope(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.last().and_then(|scope| scope.get(name))
     }
 
-    pub fn is_comparable(&self) -> bool {
-        matches!(
-            self,
-            ResolvedType::Integer
-                | ResolvedType::Real
-                | ResolvedType::Boolean
-                | ResolvedType::Char
-                | ResolvedType::String
-        )
+    pub fn set_current_procedure(&mut self, name: Option<String>) {
+        self.current_procedure = name;
     }
 
-    pub fn is_assignable_to(&self, other: &ResolvedType) -> bool {
-        if self == other {
-            return true;
-        }
-
-        // Integer kann zu Real konvertiert werden
-        if matches!(self, ResolvedType::Integer) && matches!(other, ResolvedType::Real) {
-            return true;
-        }
-
-        // NIL kann zu Pointer zugewiesen werden
-        if matches!(self, ResolvedType::Nil) && matches!(other, ResolvedType::Pointer { .. }) {
-            return true;
-        }
-
-        false
+    #[allow(dead_code)]
+    pub fn get_current_procedure(&self) -> Option<&String> {
+        self.current_procedure.as_ref()
     }
 }
 
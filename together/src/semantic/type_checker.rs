@@ -1,49 +1,246 @@
 use crate::parser::ast::*;
+use super::const_eval::{ConstError, ConstEvaluator, ConstValue};
+use super::flow;
+use super::hir;
+use super::hir::{Expr, ExprKind, SymbolRefKind};
+use super::library::LibraryProcedure;
+use super::index::{Definition, DefinitionKind, Position, Reference, Scope, SymbolIndex};
 use super::symbol_table::*;
+use super::types::{Field, Parameter, TypeArena, TypeId, TypeKind, MAX_ARRAY_LENGTH};
+use super::warnings::{Warning, WarningKind, WarningOptions};
+use std::cell::RefCell;
 use std::collections::HashMap;
 
+#[derive(Clone)]
 pub struct TypeChecker {
     symbol_table: SymbolTable,
+    types: TypeArena,
     errors: Vec<String>,
+    // Namen der umschließenden Prozeduren (für qualifizierte Namen im HIR)
+    scope_path: Vec<String>,
+    // Namen des gerade geprüften TYPE-Abschnitts; Zeiger dürfen auf die
+    // später deklarierten davon verweisen
+    section_types: Vec<String>,
+    // Platzhalter für solche Vorwärtsverweise bis zur Deklaration
+    forward_types: HashMap<String, TypeId>,
+    warning_options: WarningOptions,
+    warnings: Vec<Warning>,
+    // Deklarationen und Gültigkeitsbereiche für Editorfunktionen
+    index: SymbolIndex,
+    // Aufgelöste Verwendungen; Ausdrücke werden über &self geprüft
+    references: RefCell<Vec<Reference>>,
+    index_scope: usize,
+    // Zeile zu jedem Eintrag in `errors`, soweit bekannt
+    error_lines: Vec<usize>,
+    // Importierte Module: Alias -> Modulname
+    imports: HashMap<String, String>,
+    // Schnittstellen geprüfter Module, die importiert werden können
+    interfaces: HashMap<String, hir::Module>,
+    // Exportierte Symbole der Importe mit Schnittstelle: Alias -> Name -> Symbol
+    imported_symbols: HashMap<String, HashMap<String, Symbol>>,
+    imported_variables: Vec<hir::VariableDeclaration>,
 }
 
 impl TypeChecker {
     pub fn new() -> Self {
+        Self::with_warnings(WarningOptions::default())
+    }
+
+    pub fn with_warnings(warning_options: WarningOptions) -> Self {
         TypeChecker {
             symbol_table: SymbolTable::new(),
+            types: TypeArena::new(),
             errors: Vec::new(),
+            scope_path: Vec::new(),
+            section_types: Vec::new(),
+            forward_types: HashMap::new(),
+            warning_options,
+            warnings: Vec::new(),
+            index: SymbolIndex::new(""),
+            references: RefCell::new(Vec::new()),
+            index_scope: 0,
+            error_lines: Vec::new(),
+            imports: HashMap::new(),
+            interfaces: HashMap::new(),
+            imported_symbols: HashMap::new(),
+            imported_variables: Vec::new(),
+        }
+    }
+
+    // Schnittstelle eines bereits geprüften Moduls für spätere Importe; ohne
+    // Schnittstelle bleiben Importe außer den Bibliotheksmodulen unaufgelöst
+    pub fn add_interface(&mut self, module: &hir::Module) {
+        self.interfaces.insert(module.name.clone(), module.clone());
+    }
+
+    pub fn check_module(&mut self, module: &Module) -> Result<hir::Module, Vec<String>> {
+        self.define_module(&module.name);
+        self.define_imports(&module.imports);
+
+        // Deklarationen prüfen
+        let declarations = self.check_declarations(&module.declarations);
+
+        // Body prüfen
+        let body = match &module.body {
+            Some(body) => self.check_statement_sequence(body),
+            None => Vec::new(),
+        };
+        self.locate_errors(0);
+
+        self.warn_unused_imports(module);
+
+        if self.errors.is_empty() {
+            let module = hir::Module {
+                name: module.name.clone(),
+                imports: module.imports.clone(),
+                types: self.types.clone(),
+                declarations,
+                body,
+                imported_variables: self.imported_variables.clone(),
+            };
+            for warning in flow::analyze(&module) {
+                self.warn(warning);
+            }
+            Ok(module)
+        } else {
+            Err(self.errors.clone())
         }
     }
 
-    pub fn check_module(&mut self, module: &Module) -> Result<(), Vec<String>> {
-        // Module-Symbol hinzufügen
+    fn define_module(&mut self, name: &str) {
         self.symbol_table.define(Symbol {
-            name: module.name.clone(),
+            name: name.to_string(),
             kind: SymbolKind::Module,
             exported: ExportMark::None,
             defined_at: None,
         }).ok();
 
-        // Imports (vereinfacht - keine echte Modul-Auflösung)
-        for import in &module.imports {
-            self.symbol_table.define(Symbol {
-                name: import.alias.clone().unwrap_or(import.module_name.clone()),
+        self.index.scopes[0].name = name.to_string();
+    }
+
+    // Imports; aufgelöst werden nur Module mit Schnittstelle (add_interface).
+    // Liefert die Fehler doppelt definierter Namen.
+    fn define_imports(&mut self, imports: &[Import]) -> Vec<String> {
+        let mut errors = Vec::new();
+        for import in imports {
+            let alias = import.alias.clone().unwrap_or(import.module_name.clone());
+            self.imports.insert(alias.clone(), import.module_name.clone());
+            if let Some(interface) = self.interfaces.get(&import.module_name).cloned() {
+                self.import_interface(&alias, &interface);
+            }
+            let defined = self.define(Symbol {
+                name: alias,
                 kind: SymbolKind::Module,
                 exported: ExportMark::None,
-                defined_at: None,
-            }).ok();
+                defined_at: Some((import.line, import.column)),
+            });
+            errors.extend(defined.err().into_iter().flatten());
         }
+        errors
+    }
 
-        // Deklarationen prüfen
-        self.check_declarations(&module.declarations)?;
+    // Exportierte Deklarationen eines Imports unter seinem Alias; ihre Typen
+    // werden in die eigene Arena kopiert
+    fn import_interface(&mut self, alias: &str, interface: &hir::Module) {
+        let module = &interface.name;
+        let decls = &interface.declarations;
+        let exported = |mark: &ExportMark| *mark != ExportMark::None;
+        let mut symbols = HashMap::new();
+
+        for constant in decls.constants.iter().filter(|constant| exported(&constant.exported)) {
+            let kind = SymbolKind::Constant { value: constant.value.clone(), resolved_type: constant.value.type_id() };
+            symbols.insert(constant.name.clone(), imported_symbol(&constant.name, kind));
+        }
+        for type_decl in decls.types.iter().filter(|type_decl| exported(&type_decl.exported)) {
+            let type_def = self.types.import(module, &interface.types, type_decl.type_def);
+            symbols.insert(type_decl.name.clone(), imported_symbol(&type_decl.name, SymbolKind::Type { type_def }));
+        }
+        for variable in decls.variables.iter().filter(|variable| exported(&variable.exported)) {
+            let var_type = self.types.import(module, &interface.types, variable.var_type);
+            let name = format!("{}.{}", module, variable.name);
+            if !self.imported_variables.iter().any(|imported| imported.name == name) {
+                self.imported_variables.push(hir::VariableDeclaration {
+                    name,
+                    exported: ExportMark::None,
+                    var_type,
+                    line: 0,
+                });
+            }
+            let kind = SymbolKind::Variable { var_type, is_parameter: false, is_var_param: false };
+            symbols.insert(variable.name.clone(), imported_symbol(&variable.name, kind));
+        }
+        for procedure in decls.procedures.iter().filter(|procedure| exported(&procedure.exported)) {
+            let params = procedure
+                .params
+                .iter()
+                .map(|param| Parameter {
+                    name: param.name.clone(),
+                    param_type: self.types.import(module, &interface.types, param.param_type),
+                    is_var: param.is_var,
+                })
+                .collect();
+            let result = procedure.return_type.map(|ty| self.types.import(module, &interface.types, ty));
+            let proc_type = self.types.add(TypeKind::Procedure { params, result });
+            symbols.insert(procedure.name.clone(), imported_symbol(&procedure.name, SymbolKind::Procedure { proc_type }));
+        }
+        self.imported_symbols.insert(alias.to_string(), symbols);
+    }
 
-        // Body prüfen
-        if let Some(body) = &module.body {
-            self.check_statement_sequence(body)?;
+    // ========================================================================
+    // Schrittweise Prüfung (REPL)
+    // ========================================================================
+    //
+    // Symboltabelle und Typen bleiben zwischen den Eingaben erhalten, jede
+    // Eingabe wird auf Modulebene geprüft. Eine fehlerhafte Eingabe kann schon
+    // Symbole definiert haben; wer sie verwerfen will, prüft auf einer Kopie.
+
+    pub fn begin_session(&mut self, module_name: &str) {
+        self.define_module(module_name);
+    }
+
+    pub fn check_import_input(&mut self, imports: &[Import]) -> Result<(), Vec<String>> {
+        self.start_input();
+        let errors = self.define_imports(imports);
+        self.errors.extend(errors);
+        self.finish_input(())
+    }
+
+    pub fn check_declaration_input(&mut self, decls: &DeclSequence) -> Result<hir::Declarations, Vec<String>> {
+        self.start_input();
+        let declarations = self.check_declarations(decls);
+        self.finish_input(declarations)
+    }
+
+    pub fn check_statement_input(&mut self, statements: &[Statement]) -> Result<Vec<hir::Statement>, Vec<String>> {
+        self.start_input();
+        let statements = self.check_statement_sequence(statements);
+        self.finish_input(statements)
+    }
+
+    pub fn check_expression_input(&mut self, expr: &Expression) -> Result<Expr, Vec<String>> {
+        self.start_input();
+        let checked = self.check_expression(expr);
+        if let Err(errors) = &checked {
+            self.errors.extend(errors.iter().cloned());
+            self.locate_errors(0);
         }
+        checked
+    }
+
+    // Alle bisher angelegten Typen; TypeIds aus früheren Eingaben bleiben gültig
+    pub fn types(&self) -> &TypeArena {
+        &self.types
+    }
 
+    fn start_input(&mut self) {
+        self.errors.clear();
+        self.error_lines.clear();
+    }
+
+    fn finish_input<T>(&mut self, value: T) -> Result<T, Vec<String>> {
+        self.locate_errors(0);
         if self.errors.is_empty() {
-            Ok(())
+            Ok(value)
         } else {
             Err(self.errors.clone())
         }
@@ -53,143 +250,382 @@ impl TypeChecker {
     // Konstanten-Auswertung
     // ========================================================================
 
-    fn eval_const_expression(&self, expr: &Expression) -> Option<i64> {
-        match expr {
-            Expression::IntegerLiteral(val) => Some(*val),
-            Expression::Designator(designator) => {
-                // Konstante nachschlagen
-                if designator.selectors.is_empty() {
-                    if let Some(symbol) = self.symbol_table.lookup(&designator.base.name) {
-                        if let SymbolKind::Constant { value, .. } = &symbol.kind {
-                            return self.eval_const_expression(value);
-                        }
-                    }
-                }
-                None
+    fn eval_const(&self, expr: &Expression) -> Result<ConstValue, ConstError> {
+        ConstEvaluator::new(&self.symbol_table).eval(expr)
+    }
+
+    // Wertet einen Ausdruck aus, der konstant sein muss (`context` für die Fehlermeldung)
+    fn expect_const(&mut self, expr: &Expression, context: &str) -> Result<ConstValue, Vec<String>> {
+        match self.eval_const(expr) {
+            Ok(value) => Ok(value),
+            Err(ConstError::NotConstant) => {
+                let err = format!("{} muss ein konstanter Ausdruck sein", context);
+                self.errors.push(err.clone());
+                Err(vec![err])
             }
-            Expression::Unary { op, expr } => {
-                let val = self.eval_const_expression(expr)?;
-                match op {
-                    UnaryOp::Plus => Some(val),
-                    UnaryOp::Minus => Some(-val),
-                    UnaryOp::Not => None,
-                }
+            Err(ConstError::Invalid(msg)) => {
+                let err = format!("{}: {}", context, msg);
+                self.errors.push(err.clone());
+                Err(vec![err])
             }
-            Expression::Binary { left, op, right } => {
-                let left_val = self.eval_const_expression(left)?;
-                let right_val = self.eval_const_expression(right)?;
-                match op {
-                    BinaryOp::Add => Some(left_val + right_val),
-                    BinaryOp::Sub => Some(left_val - right_val),
-                    BinaryOp::Mul => Some(left_val * right_val),
-                    BinaryOp::IntDiv => Some(left_val / right_val),
-                    BinaryOp::Mod => Some(left_val % right_val),
-                    _ => None,
-                }
+        }
+    }
+
+    // ========================================================================
+    // Warnungen
+    // ========================================================================
+
+    fn warn(&mut self, warning: Warning) {
+        if self.warning_options.is_enabled(warning.kind) {
+            self.warnings.push(warning);
+        }
+    }
+
+    // Unbenutzte Parameter, Variablen, Konstanten und Typen der Prozedur;
+    // geschachtelte Prozeduren werden nicht gemeldet
+    fn warn_unused_locals(&mut self, proc_name: &str) {
+        let warnings: Vec<Warning> = self
+            .symbol_table
+            .unused_in_current_scope()
+            .into_iter()
+            .filter_map(|symbol| {
+                let what = match &symbol.kind {
+                    SymbolKind::Variable { is_parameter: true, .. } => "Parameter",
+                    SymbolKind::Variable { .. } => "Lokale Variable",
+                    SymbolKind::Constant { .. } => "Konstante",
+                    SymbolKind::Type { .. } => "Typ",
+                    _ => return None,
+                };
+                let message = format!("{} '{}' in '{}' wird nie benutzt", what, symbol.name, proc_name);
+                Some(Warning::new(WarningKind::Unused, message, symbol.defined_at.unwrap_or((0, 0))))
+            })
+            .collect();
+
+        for warning in warnings {
+            self.warn(warning);
+        }
+    }
+
+    fn warn_unused_imports(&mut self, module: &Module) {
+        let unused: Vec<Warning> = self
+            .symbol_table
+            .unused_in_current_scope()
+            .into_iter()
+            .filter(|symbol| {
+                symbol.kind == SymbolKind::Module
+                    && module.imports.iter().any(|import| {
+                        import.alias.as_ref().unwrap_or(&import.module_name) == &symbol.name
+                    })
+            })
+            .map(|symbol| {
+                let message = format!("Import '{}' wird nie benutzt", symbol.name);
+                Warning::new(WarningKind::Unused, message, symbol.defined_at.unwrap_or((0, 0)))
+            })
+            .collect();
+
+        for warning in unused {
+            self.warn(warning);
+        }
+    }
+
+    // ========================================================================
+    // Symbolindex und Fehlerbehandlung
+    // ========================================================================
+
+    // Definiert das Symbol und nimmt es in den Index auf; den Fehler einer
+    // Doppeldefinition meldet der Aufrufer
+    fn define(&mut self, symbol: Symbol) -> Result<(), Vec<String>> {
+        let definition = symbol.defined_at.map(|position| Definition {
+            name: symbol.name.clone(),
+            kind: definition_kind(&symbol.kind),
+            ty: symbol_type(&symbol.kind),
+            position,
+            scope: self.index_scope,
+            exported: symbol.exported.clone(),
+        });
+
+        match self.symbol_table.define(symbol) {
+            Ok(()) => {
+                self.index.definitions.extend(definition);
+                Ok(())
+            }
+            Err(e) => Err(vec![e]),
+        }
+    }
+
+    fn reference(&self, position: Position, symbol: &Symbol) {
+        if let Some(definition) = symbol.defined_at {
+            self.references.borrow_mut().push(Reference { position, definition });
+        }
+    }
+
+    // Verwendung des Felds `name` eines Records oder eines seiner Basistypen
+    fn field_reference(&self, position: Position, record: TypeId, name: &str) {
+        let mut current = Some(record);
+        while let Some(record) = current {
+            current = match self.types.kind(record) {
+                TypeKind::Record { base, .. } => *base,
+                _ => return,
+            };
+
+            let field = self.index.definitions.iter().find(|def| {
+                def.name == name && def.kind == DefinitionKind::Field { record }
+            });
+            if let Some(field) = field {
+                let definition = field.position;
+                self.references.borrow_mut().push(Reference { position, definition });
+                return;
+            }
+        }
+    }
+
+    // Übernimmt die Fehler einer abgebrochenen Deklaration oder Anweisung,
+    // soweit sie nicht schon gemeldet wurden, und ordnet sie der Zeile zu
+    fn recover(&mut self, before: usize, errors: Vec<String>, line: usize) {
+        for error in errors {
+            if !self.errors[before..].contains(&error) {
+                self.errors.push(error);
             }
-            _ => None,
         }
+        self.locate_errors(line);
+    }
+
+    fn locate_errors(&mut self, line: usize) {
+        self.error_lines.resize(self.errors.len(), line);
+    }
+
+    // Index des zuletzt geprüften Moduls, auch wenn es Fehler enthält
+    pub fn index(&self) -> SymbolIndex {
+        let mut index = self.index.clone();
+        index.references = self.references.borrow().clone();
+        index.types = self.types.clone();
+        index
+    }
+
+    // Fehler mit Zeilennummer (0, wenn die Zeile nicht bekannt ist)
+    pub fn located_errors(&self) -> Vec<(usize, String)> {
+        self.errors
+            .iter()
+            .enumerate()
+            .map(|(i, error)| (self.error_lines.get(i).copied().unwrap_or(0), error.clone()))
+            .collect()
+    }
+
+    fn qualified_name(&self, level: usize, name: &str) -> String {
+        let mut path = self.scope_path[..level.min(self.scope_path.len())].to_vec();
+        path.push(name.to_string());
+        path.join(".")
     }
 
     // ========================================================================
     // Deklarationen
     // ========================================================================
 
-    fn check_declarations(&mut self, decls: &DeclSequence) -> Result<(), Vec<String>> {
+    // Fehlerhafte Deklarationen werden gemeldet und übersprungen, die Prüfung
+    // läuft mit den übrigen weiter
+    fn check_declarations(&mut self, decls: &DeclSequence) -> hir::Declarations {
+        let mut result = hir::Declarations::default();
+
         // Constants
         for const_decl in &decls.constants {
-            let expr_type = self.infer_expression_type(&const_decl.value)?;
-
-            self.symbol_table.define(Symbol {
-                name: const_decl.name.name.clone(),
-                kind: SymbolKind::Constant {
-                    value: const_decl.value.clone(),
-                    resolved_type: expr_type,
-                },
-                exported: const_decl.name.exported.clone(),
-                defined_at: None,
-            }).map_err(|e| {
-                self.errors.push(e.clone());
-                vec![e]
-            })?;
+            let before = self.errors.len();
+            if let Err(errors) = self.check_const_declaration(const_decl, &mut result) {
+                self.recover(before, errors, const_decl.name.line);
+            }
         }
 
         // Types
+        self.section_types = decls.types.iter().map(|type_decl| type_decl.name.name.clone()).collect();
         for type_decl in &decls.types {
-            let resolved_type = self.resolve_type(&type_decl.type_def)?;
-
-            self.symbol_table.define(Symbol {
-                name: type_decl.name.name.clone(),
-                kind: SymbolKind::Type {
-                    type_def: resolved_type,
-                },
-                exported: type_decl.name.exported.clone(),
-                defined_at: None,
-            }).map_err(|e| {
-                self.errors.push(e.clone());
-                vec![e]
-            })?;
+            let before = self.errors.len();
+            if let Err(errors) = self.check_type_declaration(type_decl, &mut result) {
+                self.recover(before, errors, type_decl.name.line);
+            }
         }
+        self.section_types.clear();
+        self.forward_types.clear();
 
         // Variables
         for var_decl in &decls.variables {
-            let resolved_type = self.resolve_type(&var_decl.var_type)?;
-
-            for name in &var_decl.names {
-                self.symbol_table.define(Symbol {
-                    name: name.name.clone(),
-                    kind: SymbolKind::Variable {
-                        var_type: resolved_type.clone(),
-                        is_parameter: false,
-                        is_var_param: false,
-                    },
-                    exported: name.exported.clone(),
-                    defined_at: None,
-                }).map_err(|e| {
-                    self.errors.push(e.clone());
-                    vec![e]
-                })?;
+            let before = self.errors.len();
+            if let Err(errors) = self.check_variable_declaration(var_decl, &mut result) {
+                let line = var_decl.names.first().map_or(0, |name| name.line);
+                self.recover(before, errors, line);
             }
         }
 
         // Procedures - alle normal verarbeiten
         for proc_decl in &decls.procedures {
-            self.check_procedure(proc_decl)?;
+            let before = self.errors.len();
+            match self.check_procedure(proc_decl) {
+                Ok(Some(procedure)) => result.procedures.push(procedure),
+                Ok(None) => {}
+                Err(errors) => self.recover(before, errors, proc_decl.name.line),
+            }
+        }
+
+        result
+    }
+
+    fn check_const_declaration(
+        &mut self,
+        const_decl: &ConstDeclaration,
+        result: &mut hir::Declarations,
+    ) -> Result<(), Vec<String>> {
+        let context = format!("Wert der Konstante '{}'", const_decl.name.name);
+        let value = self.expect_const(&const_decl.value, &context)?;
+        result.constants.push(hir::ConstDeclaration {
+            name: const_decl.name.name.clone(),
+            exported: const_decl.name.exported.clone(),
+            value: value.clone(),
+        });
+
+        self.define(Symbol {
+            name: const_decl.name.name.clone(),
+            kind: SymbolKind::Constant {
+                resolved_type: value.type_id(),
+                value,
+            },
+            exported: const_decl.name.exported.clone(),
+            defined_at: Some((const_decl.name.line, const_decl.name.column)),
+        })
+    }
+
+    fn check_type_declaration(
+        &mut self,
+        type_decl: &TypeDeclaration,
+        result: &mut hir::Declarations,
+    ) -> Result<(), Vec<String>> {
+        let resolved_type = self.resolve_type(&type_decl.type_def)?;
+        // Ein Typkonstruktor erhält den Namen seiner Deklaration,
+        // TYPE B = A ist dagegen nur ein weiterer Name für A
+        if !matches!(type_decl.type_def, Type::Qualident(_)) {
+            self.types.set_name(resolved_type, &type_decl.name.name);
+        }
+        if let Some(forward) = self.forward_types.remove(&type_decl.name.name) {
+            self.check_pointer_base(resolved_type)?;
+            self.types.resolve_forward(forward, resolved_type);
         }
+        result.types.push(hir::TypeDeclaration {
+            name: type_decl.name.name.clone(),
+            exported: type_decl.name.exported.clone(),
+            type_def: resolved_type,
+            line: type_decl.name.line,
+        });
+
+        self.define(Symbol {
+            name: type_decl.name.name.clone(),
+            kind: SymbolKind::Type {
+                type_def: resolved_type,
+            },
+            exported: type_decl.name.exported.clone(),
+            defined_at: Some((type_decl.name.line, type_decl.name.column)),
+        })
+    }
 
+    fn check_variable_declaration(
+        &mut self,
+        var_decl: &VariableDeclaration,
+        result: &mut hir::Declarations,
+    ) -> Result<(), Vec<String>> {
+        let resolved_type = self.resolve_type(&var_decl.var_type)?;
+
+        for name in &var_decl.names {
+            result.variables.push(hir::VariableDeclaration {
+                name: name.name.clone(),
+                exported: name.exported.clone(),
+                var_type: resolved_type,
+                line: name.line,
+            });
+
+            self.define(Symbol {
+                name: name.name.clone(),
+                kind: SymbolKind::Variable {
+                    var_type: resolved_type,
+                    is_parameter: false,
+                    is_var_param: false,
+                },
+                exported: name.exported.clone(),
+                defined_at: Some((name.line, name.column)),
+            })?;
+        }
         Ok(())
     }
 
-    fn check_procedure(&mut self, proc: &ProcedureDeclaration) -> Result<(), Vec<String>> {
+    // Liefert None für Forward-Deklarationen (nur Prozedurkopf)
+    fn check_procedure(&mut self, proc: &ProcedureDeclaration) -> Result<Option<hir::Procedure>, Vec<String>> {
         let (params, return_type) = self.parse_procedure_parameters(proc)?;
         self.register_procedure_symbol(proc, &params, &return_type)?;
 
         if proc.is_forward {
-            return Ok(());
+            return Ok(None);
         }
 
-        self.setup_procedure_scope(&proc.name.name, &params)?;
-        self.check_declarations(&proc.declarations)?;
+        // Der Name nach END verweist auf die Prozedur
+        if let Some(symbol) = self.symbol_table.lookup_in_current_scope(&proc.name.name) {
+            self.reference((proc.end_line, proc.end_column), symbol);
+        }
+
+        self.setup_procedure_scope(proc, &params);
+        let declarations = self.check_declarations(&proc.declarations);
 
         // Built-in Funktionen können leere Bodies haben
-        if let Some(body) = &proc.body {
-            if !body.is_empty() {
-                self.check_statement_sequence(body)?;
-            }
-        }
+        let body = match &proc.body {
+            Some(body) => self.check_statement_sequence(body),
+            None => Vec::new(),
+        };
 
-        self.validate_procedure_return(proc, &return_type)?;
+        let before = self.errors.len();
+        let return_expr = match self.validate_procedure_return(proc, &return_type) {
+            Ok(return_expr) => return_expr,
+            Err(errors) => {
+                self.recover(before, errors, proc.end_line);
+                None
+            }
+        };
 
+        self.warn_unused_locals(&proc.name.name);
         self.symbol_table.set_current_procedure(None);
         self.symbol_table.exit_scope();
-
-        Ok(())
+        self.scope_path.pop();
+        self.index_scope = self.index.scopes[self.index_scope].parent.unwrap_or(0);
+
+        // Zeilen der Parameternamen in Reihenfolge der Parameter
+        let lines: Vec<usize> = proc
+            .params
+            .iter()
+            .flat_map(|formal| &formal.sections)
+            .flat_map(|section| &section.positions)
+            .map(|&(line, _)| line)
+            .collect();
+
+        Ok(Some(hir::Procedure {
+            name: proc.name.name.clone(),
+            exported: proc.name.exported.clone(),
+            params: params
+                .iter()
+                .enumerate()
+                .map(|(index, param)| hir::Param {
+                    name: param.name.clone(),
+                    is_var: param.is_var,
+                    param_type: param.param_type,
+                    line: lines.get(index).copied().unwrap_or(proc.name.line),
+                })
+                .collect(),
+            return_type,
+            declarations,
+            body,
+            return_expr,
+            return_line: proc.return_line,
+            line: proc.name.line,
+            end_line: proc.end_line,
+        }))
     }
 
     fn parse_procedure_parameters(
         &mut self,
         proc: &ProcedureDeclaration,
-    ) -> Result<(Vec<Parameter>, Option<ResolvedType>), Vec<String>> {
+    ) -> Result<(Vec<Parameter>, Option<TypeId>), Vec<String>> {
         let mut params = Vec::new();
         let mut return_type = None;
 
@@ -199,7 +635,7 @@ impl TypeChecker {
                 for param_name in &section.names {
                     params.push(Parameter {
                         name: param_name.clone(),
-                        param_type: param_type.clone(),
+                        param_type,
                         is_var: section.is_var,
                     });
                 }
@@ -217,66 +653,77 @@ impl TypeChecker {
         &mut self,
         proc: &ProcedureDeclaration,
         params: &[Parameter],
-        return_type: &Option<ResolvedType>,
+        return_type: &Option<TypeId>,
     ) -> Result<(), Vec<String>> {
-        self.symbol_table
-            .define(Symbol {
-                name: proc.name.name.clone(),
-                kind: SymbolKind::Procedure {
-                    params: params.to_vec(),
-                    return_type: return_type.clone(),
-                },
-                exported: proc.name.exported.clone(),
-                defined_at: None,
-            })
-            .map_err(|e| {
-                self.errors.push(e.clone());
-                vec![e]
-            })
+        let proc_type = self.types.add(TypeKind::Procedure {
+            params: params.to_vec(),
+            result: *return_type,
+        });
+
+        self.define(Symbol {
+            name: proc.name.name.clone(),
+            kind: SymbolKind::Procedure { proc_type },
+            exported: proc.name.exported.clone(),
+            defined_at: Some((proc.name.line, proc.name.column)),
+        })
     }
 
-    fn setup_procedure_scope(
-        &mut self,
-        proc_name: &str,
-        params: &[Parameter],
-    ) -> Result<(), Vec<String>> {
+    fn setup_procedure_scope(&mut self, proc: &ProcedureDeclaration, params: &[Parameter]) {
+        let proc_name = &proc.name.name;
         self.symbol_table.enter_scope();
         self.symbol_table.set_current_procedure(Some(proc_name.to_string()));
-
-        for param in params {
-            self.symbol_table
-                .define(Symbol {
-                    name: param.name.clone(),
-                    kind: SymbolKind::Variable {
-                        var_type: param.param_type.clone(),
-                        is_parameter: true,
-                        is_var_param: param.is_var,
-                    },
-                    exported: ExportMark::None,
-                    defined_at: None,
-                })
-                .ok();
+        self.scope_path.push(proc_name.to_string());
+
+        self.index.scopes.push(Scope {
+            name: proc_name.to_string(),
+            parent: Some(self.index_scope),
+            start_line: proc.name.line,
+            end_line: proc.end_line,
+        });
+        self.index_scope = self.index.scopes.len() - 1;
+
+        let positions = proc
+            .params
+            .iter()
+            .flat_map(|formal_params| &formal_params.sections)
+            .flat_map(|section| section.positions.iter().copied());
+        for (param, position) in params.iter().zip(positions) {
+            self.define(Symbol {
+                name: param.name.clone(),
+                kind: SymbolKind::Variable {
+                    var_type: param.param_type,
+                    is_parameter: true,
+                    is_var_param: param.is_var,
+                },
+                exported: ExportMark::None,
+                defined_at: Some(position),
+            })
+            .ok();
         }
-
-        Ok(())
     }
 
     fn validate_procedure_return(
         &mut self,
         proc: &ProcedureDeclaration,
-        return_type: &Option<ResolvedType>,
-    ) -> Result<(), Vec<String>> {
+        return_type: &Option<TypeId>,
+    ) -> Result<Option<Expr>, Vec<String>> {
         if let Some(ret_expr) = &proc.return_expr {
-            let expr_type = self.infer_expression_type(ret_expr)?;
+            let expr = self.check_expression(ret_expr)?;
 
-            if let Some(expected_type) = return_type {
-                if !expr_type.is_assignable_to(expected_type) {
-                    let err = format!(
-                        "RETURN-Typ {:?} passt nicht zu deklariertem Typ {:?} in Prozedur '{}'",
-                        expr_type, expected_type, proc.name.name
-                    );
-                    self.errors.push(err.clone());
-                    return Err(vec![err]);
+            if let Some(expected_type) = *return_type {
+                let expr_type = expr.ty;
+                match self.coerce(expr, expected_type) {
+                    Some(expr) => Ok(Some(expr)),
+                    None => {
+                        let err = format!(
+                            "RETURN-Typ {} passt nicht zu deklariertem Typ {} in Prozedur '{}'",
+                            self.types.display(expr_type),
+                            self.types.display(expected_type),
+                            proc.name.name
+                        );
+                        self.errors.push(err.clone());
+                        Err(vec![err])
+                    }
                 }
             } else {
                 let err = format!(
@@ -284,7 +731,7 @@ impl TypeChecker {
                     proc.name.name
                 );
                 self.errors.push(err.clone());
-                return Err(vec![err]);
+                Err(vec![err])
             }
         } else if return_type.is_some() {
             let err = format!(
@@ -292,109 +739,162 @@ impl TypeChecker {
                 proc.name.name
             );
             self.errors.push(err.clone());
-            return Err(vec![err]);
+            Err(vec![err])
+        } else {
+            Ok(None)
         }
-
-        Ok(())
     }
 
     // ========================================================================
     // Typ-Auflösung
     // ========================================================================
 
-    fn resolve_type(&mut self, type_def: &Type) -> Result<ResolvedType, Vec<String>> {
+    fn resolve_type(&mut self, type_def: &Type) -> Result<TypeId, Vec<String>> {
         match type_def {
             Type::Qualident(qualident) => self.resolve_qualident_type(qualident),
             Type::Array { lengths, element_type } => {
                 let mut dims = Vec::new();
                 for length_expr in lengths {
-                    // Konstanten-Auswertung
-                    if let Some(val) = self.eval_const_expression(length_expr) {
-                        dims.push(val as usize);
-                    } else {
-                        let err = format!("Array-Länge muss ein konstanter Ausdruck sein: {:?}", length_expr);
-                        self.errors.push(err.clone());
-                        return Err(vec![err]);
-                    }
+                    let err = match self.expect_const(length_expr, "Array-Länge")? {
+                        ConstValue::Integer(val) if val > 0 => match usize::try_from(val) {
+                            Ok(length) if length <= MAX_ARRAY_LENGTH => {
+                                dims.push(length);
+                                continue;
+                            }
+                            _ => format!("Array-Länge {} ist zu groß (höchstens {})", val, MAX_ARRAY_LENGTH),
+                        },
+                        ConstValue::Integer(val) => format!("Array-Länge muss positiv sein, ist {}", val),
+                        other => format!("Array-Länge muss eine INTEGER-Konstante sein, ist {}", other.type_name()),
+                    };
+                    self.errors.push(err.clone());
+                    return Err(vec![err]);
                 }
-                let elem_type = Box::new(self.resolve_type(element_type)?);
-                Ok(ResolvedType::Array {
-                    dimensions: dims,
-                    element_type: elem_type,
-                })
+
+                // ARRAY n, m OF T = ARRAY n OF ARRAY m OF T, von innen nach außen aufbauen
+                let mut array_type = self.resolve_type(element_type)?;
+                for length in dims.into_iter().rev() {
+                    array_type = self.types.add(TypeKind::Array {
+                        length,
+                        element: array_type,
+                    });
+                }
+
+                // Auch alle Dimensionen zusammen müssen in die Grenze passen
+                if self.types.element_count(array_type).is_none_or(|count| count > MAX_ARRAY_LENGTH) {
+                    let err = format!(
+                        "Array {} ist zu groß (höchstens {} Elemente)",
+                        self.types.display(array_type),
+                        MAX_ARRAY_LENGTH
+                    );
+                    self.errors.push(err.clone());
+                    return Err(vec![err]);
+                }
+                Ok(array_type)
             }
             Type::Record { base_type, fields } => {
-                let mut field_map = HashMap::new();
+                let base = match base_type {
+                    Some(base_qualident) => Some(self.resolve_record_base(base_qualident)?),
+                    None => None,
+                };
+
+                let mut record_fields: Vec<Field> = Vec::new();
                 for field_list in fields {
                     let field_type = self.resolve_type(&field_list.field_type)?;
                     for name in &field_list.names {
-                        if field_map.contains_key(&name.name) {
+                        let inherited = base.is_some_and(|base| self.types.field(base, &name.name).is_some());
+                        if inherited || record_fields.iter().any(|field| field.name == name.name) {
                             let err = format!("Feld '{}' bereits definiert", name.name);
                             self.errors.push(err.clone());
                             return Err(vec![err]);
                         }
-                        field_map.insert(name.name.clone(), field_type.clone());
+                        record_fields.push(Field {
+                            name: name.name.clone(),
+                            ty: field_type,
+                        });
                     }
                 }
 
-                let base = if let Some(base_qualident) = base_type {
-                    Some(Box::new(self.resolve_qualident_type(base_qualident)?))
-                } else {
-                    None
-                };
-
-                Ok(ResolvedType::Record {
-                    fields: field_map,
-                    base_type: base,
-                })
+                let record = self.types.add(TypeKind::Record {
+                    base,
+                    fields: record_fields,
+                });
+                for field_list in fields {
+                    for name in &field_list.names {
+                        self.index.definitions.push(Definition {
+                            name: name.name.clone(),
+                            kind: DefinitionKind::Field { record },
+                            ty: self.types.field(record, &name.name).map(|(_, field)| field.ty),
+                            position: (name.line, name.column),
+                            scope: self.index_scope,
+                            exported: name.exported.clone(),
+                        });
+                    }
+                }
+                Ok(record)
             }
             Type::Pointer { target_type } => {
-                let target = Box::new(self.resolve_type(target_type)?);
-                Ok(ResolvedType::Pointer { target_type: target })
+                let target = match target_type.as_ref() {
+                    Type::Qualident(qualident) if self.is_forward_reference(qualident) => {
+                        self.forward_type(&qualident.name)
+                    }
+                    _ => {
+                        let target = self.resolve_type(target_type)?;
+                        self.check_pointer_base(target)?;
+                        target
+                    }
+                };
+                Ok(self.types.add(TypeKind::Pointer { target }))
             }
             Type::Procedure { params } => {
+                let mut proc_params = Vec::new();
+                let mut result = None;
+
                 if let Some(formal_params) = params {
-                    let mut proc_params = Vec::new();
                     for section in &formal_params.sections {
                         let param_type = self.resolve_type(&section.param_type)?;
                         for param_name in &section.names {
                             proc_params.push(Parameter {
                                 name: param_name.clone(),
-                                param_type: param_type.clone(),
+                                param_type,
                                 is_var: section.is_var,
                             });
                         }
                     }
 
-                    let return_type = if let Some(ret_type) = &formal_params.return_type {
-                        Some(Box::new(self.resolve_qualident_type(ret_type)?))
-                    } else {
-                        None
-                    };
-
-                    Ok(ResolvedType::Procedure {
-                        params: proc_params,
-                        return_type,
-                    })
-                } else {
-                    Ok(ResolvedType::Procedure {
-                        params: Vec::new(),
-                        return_type: None,
-                    })
+                    if let Some(ret_type) = &formal_params.return_type {
+                        result = Some(self.resolve_qualident_type(ret_type)?);
+                    }
                 }
+
+                Ok(self.types.add(TypeKind::Procedure {
+                    params: proc_params,
+                    result,
+                }))
             }
         }
     }
 
-    fn resolve_qualident_type(&self, qualident: &Qualident) -> Result<ResolvedType, Vec<String>> {
-        if qualident.module.is_some() {
-            // Module-qualifizierte Typen (vereinfacht)
-            return Ok(ResolvedType::Named(format!("{}", qualident)));
+    fn resolve_qualident_type(&mut self, qualident: &Qualident) -> Result<TypeId, Vec<String>> {
+        if let Some(module) = &qualident.module {
+            match self.symbol_table.lookup(module) {
+                Some(symbol @ Symbol { kind: SymbolKind::Module, .. }) => {
+                    self.reference((qualident.line, qualident.column), symbol);
+                }
+                _ => return Err(vec![format!("Modul '{}' nicht importiert", module)]),
+            }
+            // Ohne Schnittstelle ein Platzhalter für den qualifizierten Namen
+            return match self.imported_symbols.get(module).map(|symbols| symbols.get(&qualident.name)) {
+                Some(Some(Symbol { kind: SymbolKind::Type { type_def }, .. })) => Ok(*type_def),
+                Some(Some(_)) => Err(vec![format!("'{}' ist kein Typ", qualident)]),
+                Some(None) => Err(vec![format!("Modul {} exportiert {} nicht", self.imports[module], qualident.name)]),
+                None => Ok(self.types.imported(&format!("{}", qualident))),
+            };
         }
 
         if let Some(symbol) = self.symbol_table.lookup(&qualident.name) {
+            self.reference((qualident.line, qualident.name_column), symbol);
             match &symbol.kind {
-                SymbolKind::Type { type_def } => Ok(type_def.clone()),
+                SymbolKind::Type { type_def } => Ok(*type_def),
                 _ => Err(vec![format!("'{}' ist kein Typ", qualident.name)]),
             }
         } else {
@@ -402,360 +902,1445 @@ impl TypeChecker {
         }
     }
 
+    // Verweis auf einen Typ, der erst weiter unten im selben TYPE-Abschnitt deklariert wird
+    fn is_forward_reference(&self, qualident: &Qualident) -> bool {
+        qualident.module.is_none()
+            && self.symbol_table.lookup_in_current_scope(&qualident.name).is_none()
+            && self.section_types.contains(&qualident.name)
+    }
+
+    fn forward_type(&mut self, name: &str) -> TypeId {
+        if let Some(&forward) = self.forward_types.get(name) {
+            return forward;
+        }
+        let forward = self.types.add(TypeKind::Forward(name.to_string()));
+        self.forward_types.insert(name.to_string(), forward);
+        forward
+    }
+
+    fn check_pointer_base(&mut self, target: TypeId) -> Result<(), Vec<String>> {
+        match self.types.kind(target) {
+            TypeKind::Record { .. } | TypeKind::Imported(_) => Ok(()),
+            _ => {
+                let err = format!("Zeiger-Basistyp {} ist kein RECORD", self.types.display(target));
+                self.errors.push(err.clone());
+                Err(vec![err])
+            }
+        }
+    }
+
+    // Basistyp einer Record-Erweiterung: ein Record oder ein Zeiger auf einen Record
+    fn resolve_record_base(&mut self, qualident: &Qualident) -> Result<TypeId, Vec<String>> {
+        let base = self.resolve_qualident_type(qualident)?;
+        match self.types.kind(base) {
+            TypeKind::Record { .. } | TypeKind::Imported(_) => Ok(base),
+            TypeKind::Pointer { target } if matches!(self.types.kind(*target), TypeKind::Record { .. }) => {
+                Ok(*target)
+            }
+            _ => {
+                let err = format!("Basistyp {} ist kein RECORD", self.types.display(base));
+                self.errors.push(err.clone());
+                Err(vec![err])
+            }
+        }
+    }
+
     // ========================================================================
     // Statements
     // ========================================================================
 
-    fn check_statement_sequence(&mut self, statements: &[Statement]) -> Result<(), Vec<String>> {
+    // Fehlerhafte Anweisungen werden gemeldet und ausgelassen
+    fn check_statement_sequence(&mut self, statements: &[Statement]) -> Vec<hir::Statement> {
+        let mut result = Vec::new();
         for stmt in statements {
-            self.check_statement(stmt)?;
+            let before = self.errors.len();
+            match self.check_statement(stmt) {
+                Ok(Some(checked)) => result.push(checked),
+                Ok(None) => {}
+                Err(errors) => self.recover(before, errors, stmt.line()),
+            }
         }
-        Ok(())
+        result
     }
 
-    fn check_statement(&mut self, stmt: &Statement) -> Result<(), Vec<String>> {
-        match stmt {
-            Statement::Empty => Ok(()),
-            Statement::Assignment { target, value } => {
-                let target_type = self.infer_designator_type(target)?;
-                let value_type = self.infer_expression_type(value)?;
-
-                if !value_type.is_assignable_to(&target_type) {
-                    let err = format!(
-                        "Typ-Fehler bei Zuweisung: {:?} kann nicht zu {:?} zugewiesen werden",
-                        value_type, target_type
-                    );
-                    self.errors.push(err.clone());
-                    return Err(vec![err]);
+    // Leere Anweisungen erscheinen nicht im HIR
+    fn check_statement(&mut self, stmt: &Statement) -> Result<Option<hir::Statement>, Vec<String>> {
+        let line = stmt.line();
+        let span = stmt.span();
+        let checked = match stmt {
+            Statement::Empty => return Ok(None),
+            Statement::Assignment { target, value, .. } => {
+                let target = self.check_designator(target)?;
+                let value = self.check_expression(value)?;
+                let value_type = value.ty;
+
+                match self.coerce(value, target.ty) {
+                    Some(value) => hir::Statement::Assignment { line, span, target, value },
+                    None => {
+                        let err = format!(
+                            "Typ-Fehler bei Zuweisung: {} kann nicht zu {} zugewiesen werden",
+                            self.types.display(value_type),
+                            self.types.display(target.ty)
+                        );
+                        self.errors.push(err.clone());
+                        return Err(vec![err]);
+                    }
                 }
-                Ok(())
             }
-            Statement::ProcedureCall { designator, arguments } => {
-                let proc_type = self.infer_designator_type(designator)?;
-
-                if let ResolvedType::Procedure { params, .. } = proc_type {
-                    if arguments.len() != params.len() {
+            Statement::ProcedureCall { designator, arguments, .. } => {
+                if let Some(builtin) = self.lookup_builtin(designator) {
+                    if builtin.is_function() {
                         let err = format!(
-                            "Falsche Anzahl an Argumenten für '{}': erwartet {}, gefunden {}",
-                            designator.base.name,  // NEU: Prozedurname hinzufügen
-                            params.len(),
-                            arguments.len()
+                            "Funktionsprozedur {} kann nicht als Anweisung aufgerufen werden",
+                            builtin.name()
                         );
                         self.errors.push(err.clone());
                         return Err(vec![err]);
                     }
-
-                    for (arg, param) in arguments.iter().zip(params.iter()) {
-                        let arg_type = self.infer_expression_type(arg)?;
-                        if !arg_type.is_assignable_to(&param.param_type) {
-                            let err = format!(
-                                "Argument-Typ {:?} passt nicht zu Parameter-Typ {:?}",
-                                arg_type, param.param_type
-                            );
-                            self.errors.push(err.clone());
-                            return Err(vec![err]);
+                    return match self.check_builtin_call(builtin, arguments) {
+                        Ok((arguments, _)) => Ok(Some(hir::Statement::BuiltinCall { line, span, builtin, arguments })),
+                        Err(errors) => {
+                            self.errors.extend(errors.iter().cloned());
+                            Err(errors)
                         }
-                    }
-                }
-                Ok(())
-            }
-            Statement::If { condition, then_body, elsif_parts, else_body } => {
-                let cond_type = self.infer_expression_type(condition)?;
-                if !cond_type.is_boolean() {
-                    let err = format!("IF-Bedingung muss BOOLEAN sein, ist {:?}", cond_type);
-                    self.errors.push(err.clone());
-                    return Err(vec![err]);
+                    };
                 }
 
-                self.check_statement_sequence(then_body)?;
+                if let Some(procedure) = self.lookup_library(designator) {
+                    return match self.check_library_call(procedure, arguments) {
+                        Ok(arguments) => Ok(Some(hir::Statement::LibraryCall { line, span, procedure, arguments })),
+                        Err(errors) => {
+                            self.errors.extend(errors.iter().cloned());
+                            Err(errors)
+                        }
+                    };
+                }
 
-                for (elsif_cond, elsif_body) in elsif_parts {
-                    let elsif_type = self.infer_expression_type(elsif_cond)?;
-                    if !elsif_type.is_boolean() {
-                        let err = format!("ELSIF-Bedingung muss BOOLEAN sein, ist {:?}", elsif_type);
-                        self.errors.push(err.clone());
-                        return Err(vec![err]);
+                let procedure = self.check_designator(designator)?;
+                match self.check_call_arguments(designator, procedure.ty, arguments) {
+                    Ok(arguments) => hir::Statement::ProcedureCall { line, span, procedure, arguments },
+                    Err(errors) => {
+                        self.errors.extend(errors.iter().cloned());
+                        return Err(errors);
                     }
-                    self.check_statement_sequence(elsif_body)?;
                 }
+            }
+            Statement::If { condition, then_body, elsif_parts, else_body, .. } => {
+                let mut branches = vec![(
+                    self.check_condition(condition, "IF")?,
+                    self.check_statement_sequence(then_body),
+                )];
 
-                if let Some(else_stmts) = else_body {
-                    self.check_statement_sequence(else_stmts)?;
+                for (elsif_cond, elsif_body) in elsif_parts {
+                    branches.push((
+                        self.check_condition(elsif_cond, "ELSIF")?,
+                        self.check_statement_sequence(elsif_body),
+                    ));
                 }
-                Ok(())
+
+                let else_body = else_body
+                    .as_ref()
+                    .map(|else_stmts| self.check_statement_sequence(else_stmts));
+                hir::Statement::If { line, span, branches, else_body }
             }
-            Statement::Case { expr, cases, else_body } => {
-                let expr_type = self.infer_expression_type(expr)?;
-                if !expr_type.is_integer() && !matches!(expr_type, ResolvedType::Char) {
-                    let err = format!("CASE-Ausdruck muss INTEGER oder CHAR sein, ist {:?}", expr_type);
+            Statement::Case { expr, cases, else_body, .. } => {
+                let expr = self.check_expression(expr)?;
+                if !expr.ty.is_integer() && expr.ty != TypeId::CHAR {
+                    let err = format!(
+                        "CASE-Ausdruck muss INTEGER oder CHAR sein, ist {}",
+                        self.types.display(expr.ty)
+                    );
                     self.errors.push(err.clone());
                     return Err(vec![err]);
                 }
 
+                let mut clauses = Vec::new();
+                let mut seen_ranges: Vec<(i64, i64)> = Vec::new();
                 for case in cases {
+                    let mut labels = Vec::new();
                     for label in &case.labels {
-                        let label_type = self.infer_expression_type(&label.start)?;
-                        if !label_type.is_assignable_to(&expr_type) {
-                            let err = format!("CASE-Label-Typ {:?} passt nicht zu {:?}", label_type, expr_type);
+                        let start = self.eval_case_label(&label.start, expr.ty)?;
+                        let end = match &label.end {
+                            Some(end_expr) => self.eval_case_label(end_expr, expr.ty)?,
+                            None => start,
+                        };
+
+                        if start > end {
+                            let err = format!("Leerer CASE-Bereich {}..{}", start, end);
+                            self.errors.push(err.clone());
+                            return Err(vec![err]);
+                        }
+
+                        if seen_ranges.iter().any(|&(lo, hi)| start <= hi && lo <= end) {
+                            let err = format!("CASE-Label {}..{} ist mehrfach vergeben", start, end);
                             self.errors.push(err.clone());
                             return Err(vec![err]);
                         }
+                        seen_ranges.push((start, end));
+                        labels.push((start, end));
                     }
-                    self.check_statement_sequence(&case.body)?;
+                    clauses.push(hir::CaseClause {
+                        labels,
+                        body: self.check_statement_sequence(&case.body),
+                    });
                 }
 
-                if let Some(else_stmts) = else_body {
-                    self.check_statement_sequence(else_stmts)?;
-                }
-                Ok(())
+                let else_body = else_body
+                    .as_ref()
+                    .map(|else_stmts| self.check_statement_sequence(else_stmts));
+                hir::Statement::Case { line, span, expr, clauses, else_body }
             }
-            Statement::While { condition, body, elsif_parts } => {
-                let cond_type = self.infer_expression_type(condition)?;
-                if !cond_type.is_boolean() {
-                    let err = format!("WHILE-Bedingung muss BOOLEAN sein, ist {:?}", cond_type);
-                    self.errors.push(err.clone());
-                    return Err(vec![err]);
-                }
-
-                self.check_statement_sequence(body)?;
+            Statement::While { condition, body, elsif_parts, .. } => {
+                let mut branches = vec![(
+                    self.check_condition(condition, "WHILE")?,
+                    self.check_statement_sequence(body),
+                )];
 
                 for (elsif_cond, elsif_body) in elsif_parts {
-                    let elsif_type = self.infer_expression_type(elsif_cond)?;
-                    if !elsif_type.is_boolean() {
-                        let err = format!("ELSIF-Bedingung muss BOOLEAN sein, ist {:?}", elsif_type);
-                        self.errors.push(err.clone());
-                        return Err(vec![err]);
-                    }
-                    self.check_statement_sequence(elsif_body)?;
+                    branches.push((
+                        self.check_condition(elsif_cond, "ELSIF")?,
+                        self.check_statement_sequence(elsif_body),
+                    ));
                 }
-                Ok(())
+                hir::Statement::While { line, span, branches }
             }
-            Statement::Repeat { body, condition } => {
-                self.check_statement_sequence(body)?;
-                let cond_type = self.infer_expression_type(condition)?;
-                if !cond_type.is_boolean() {
-                    let err = format!("REPEAT-Bedingung muss BOOLEAN sein, ist {:?}", cond_type);
-                    self.errors.push(err.clone());
-                    return Err(vec![err]);
-                }
-                Ok(())
+            Statement::Repeat { body, condition, .. } => {
+                let body = self.check_statement_sequence(body);
+                let condition = self.check_condition(condition, "REPEAT")?;
+                hir::Statement::Repeat { line, span, body, condition }
             }
-            Statement::For { variable, start, end, step, body } => {
+            Statement::For { line, variable, variable_column, start, end, step, body, .. } => {
                 if let Some(symbol) = self.symbol_table.lookup(variable) {
-                    if let SymbolKind::Variable { var_type, .. } = &symbol.kind {
-                        if !var_type.is_integer() {
+                    self.reference((*line, *variable_column), symbol);
+                }
+                let variable = match self.symbol_table.lookup_with_level(variable) {
+                    Some((level, symbol)) => match &symbol.kind {
+                        SymbolKind::Variable { var_type, .. } if var_type.is_integer() => {
+                            self.symbol_ref(level, symbol)
+                        }
+                        SymbolKind::Variable { .. } => {
                             let err = "FOR-Variable muss INTEGER sein".to_string();
                             self.errors.push(err.clone());
                             return Err(vec![err]);
                         }
-                    } else {
-                        let err = format!("'{}' ist keine Variable", variable);
+                        _ => {
+                            let err = format!("'{}' ist keine Variable", variable);
+                            self.errors.push(err.clone());
+                            return Err(vec![err]);
+                        }
+                    },
+                    None => {
+                        let err = format!("Unbekannte Variable: {}", variable);
                         self.errors.push(err.clone());
                         return Err(vec![err]);
                     }
-                } else {
-                    let err = format!("Unbekannte Variable: {}", variable);
-                    self.errors.push(err.clone());
-                    return Err(vec![err]);
-                }
+                };
 
-                let start_type = self.infer_expression_type(start)?;
-                let end_type = self.infer_expression_type(end)?;
+                let start = self.check_expression(start)?;
+                let end = self.check_expression(end)?;
 
-                if !start_type.is_integer() || !end_type.is_integer() {
+                if !start.ty.is_integer() || !end.ty.is_integer() {
                     let err = "FOR-Grenzen müssen INTEGER sein".to_string();
                     self.errors.push(err.clone());
                     return Err(vec![err]);
                 }
 
-                if let Some(step_expr) = step {
-                    let step_type = self.infer_expression_type(step_expr)?;
-                    if !step_type.is_integer() {
-                        let err = "FOR-Schritt muss INTEGER sein".to_string();
-                        self.errors.push(err.clone());
-                        return Err(vec![err]);
-                    }
-                }
+                let step = match step {
+                    Some(step_expr) => match self.expect_const(step_expr, "FOR-Schritt")? {
+                        ConstValue::Integer(0) => {
+                            let err = "FOR-Schritt darf nicht 0 sein".to_string();
+                            self.errors.push(err.clone());
+                            return Err(vec![err]);
+                        }
+                        ConstValue::Integer(val) => val,
+                        _ => {
+                            let err = "FOR-Schritt muss INTEGER sein".to_string();
+                            self.errors.push(err.clone());
+                            return Err(vec![err]);
+                        }
+                    },
+                    None => 1,
+                };
 
-                self.check_statement_sequence(body)?;
-                Ok(())
+                let body = self.check_statement_sequence(body);
+                hir::Statement::For { line: *line, span, variable, start, end, step, body }
+            }
+        };
+        Ok(Some(checked))
+    }
+
+    // `context` ist der Name des Konstrukts für die Fehlermeldung (IF, WHILE, ...)
+    fn check_condition(&mut self, condition: &Expression, context: &str) -> Result<Expr, Vec<String>> {
+        let condition = self.check_expression(condition)?;
+        if !condition.ty.is_boolean() {
+            let err = format!(
+                "{}-Bedingung muss BOOLEAN sein, ist {}",
+                context,
+                self.types.display(condition.ty)
+            );
+            self.errors.push(err.clone());
+            return Err(vec![err]);
+        }
+        Ok(condition)
+    }
+
+    fn check_call_arguments(
+        &self,
+        designator: &Designator,
+        proc_type: TypeId,
+        arguments: &[Expression],
+    ) -> Result<Vec<Expr>, Vec<String>> {
+        let params = match self.types.kind(proc_type) {
+            TypeKind::Procedure { params, .. } => params,
+            _ => return Err(vec![format!("'{}' ist keine Prozedur", designator.base.name)]),
+        };
+
+        if arguments.len() != params.len() {
+            return Err(vec![format!(
+                "Falsche Anzahl an Argumenten für '{}': erwartet {}, gefunden {}",
+                designator.base.name,
+                params.len(),
+                arguments.len()
+            )]);
+        }
+
+        let mut checked = Vec::new();
+        for (arg, param) in arguments.iter().zip(params.iter()) {
+            let arg = self.check_expression(arg)?;
+            let arg_type = arg.ty;
+
+            if param.is_var && !is_variable(&arg) {
+                return Err(vec![format!(
+                    "Argument für VAR-Parameter '{}' muss eine Variable sein",
+                    param.name
+                )]);
+            }
+
+            match self.coerce(arg, param.param_type) {
+                Some(arg) => checked.push(arg),
+                None => {
+                    return Err(vec![format!(
+                        "Argument-Typ {} passt nicht zu Parameter-Typ {}",
+                        self.types.display(arg_type),
+                        self.types.display(param.param_type)
+                    )])
+                }
             }
         }
+        Ok(checked)
     }
 
     // ========================================================================
     // Ausdrücke
     // ========================================================================
 
-    fn infer_expression_type(&self, expr: &Expression) -> Result<ResolvedType, Vec<String>> {
+    fn check_expression(&self, expr: &Expression) -> Result<Expr, Vec<String>> {
         match expr {
-            Expression::IntegerLiteral(_) => Ok(ResolvedType::Integer),
-            Expression::RealLiteral(_) => Ok(ResolvedType::Real),
-            Expression::StringLiteral(_) => Ok(ResolvedType::String),
-            Expression::BooleanLiteral(_) => Ok(ResolvedType::Boolean),
-            Expression::Nil => Ok(ResolvedType::Nil),
-            Expression::Set(_) => Ok(ResolvedType::Set),
-            Expression::Designator(designator) => self.infer_designator_type(designator),
-            Expression::FunctionCall { designator, arguments: _ } => {
-                let func_type = self.infer_designator_type(designator)?;
-                if let ResolvedType::Procedure { return_type, .. } = func_type {
-                    return_type.map(|t| *t).ok_or_else(|| {
-                        vec!["Prozedur hat keinen Rückgabewert".to_string()]
-                    })
-                } else {
-                    Err(vec![format!("'{:?}' ist keine Prozedur", designator)])
+            Expression::IntegerLiteral(val) => Ok(Expr::literal(ConstValue::Integer(*val))),
+            Expression::RealLiteral(val) => Ok(Expr::literal(ConstValue::Real(*val))),
+            Expression::StringLiteral(val) => Ok(Expr::literal(ConstValue::String(val.clone()))),
+            Expression::BooleanLiteral(val) => Ok(Expr::literal(ConstValue::Boolean(*val))),
+            Expression::Nil => Ok(Expr::literal(ConstValue::Nil)),
+            Expression::Set(elements) => match self.eval_const(expr) {
+                Ok(value) => Ok(Expr::literal(value)),
+                Err(ConstError::Invalid(msg)) => Err(vec![msg]),
+                Err(ConstError::NotConstant) => {
+                    let mut checked = Vec::new();
+                    for element in elements {
+                        let start = self.check_set_element(&element.start)?;
+                        let end = match &element.end {
+                            Some(end) => Some(self.check_set_element(end)?),
+                            None => None,
+                        };
+                        checked.push((start, end));
+                    }
+                    Ok(Expr::new(ExprKind::Set(checked), TypeId::SET))
+                }
+            },
+            Expression::Designator(designator) => {
+                let designator = self.check_designator(designator)?;
+                let ty = designator.ty;
+                Ok(Expr::new(ExprKind::Designator(designator), ty))
+            }
+            Expression::FunctionCall { designator, arguments } => {
+                if let Some(builtin) = self.lookup_builtin(designator) {
+                    let (arguments, result) = self.check_builtin_call(builtin, arguments)?;
+                    let ty = result.ok_or_else(|| {
+                        vec![format!("{} hat keinen Rückgabewert", builtin.name())]
+                    })?;
+                    return Ok(Expr::new(ExprKind::BuiltinCall { builtin, arguments }, ty));
+                }
+                if let Some(procedure) = self.lookup_library(designator) {
+                    return Err(vec![format!("{} hat keinen Rückgabewert", procedure)]);
+                }
+                if let Some(guarded) = self.as_type_guard(designator, arguments) {
+                    let designator = self.check_designator(&guarded)?;
+                    let ty = designator.ty;
+                    return Ok(Expr::new(ExprKind::Designator(designator), ty));
                 }
+
+                let procedure = self.check_designator(designator)?;
+                let ty = match self.types.kind(procedure.ty) {
+                    TypeKind::Procedure { result, .. } => result.ok_or_else(|| {
+                        vec!["Prozedur hat keinen Rückgabewert".to_string()]
+                    })?,
+                    _ => return Err(vec![format!("'{:?}' ist keine Prozedur", designator)]),
+                };
+                let arguments = self.check_call_arguments(designator, procedure.ty, arguments)?;
+                Ok(Expr::new(ExprKind::Call { procedure, arguments }, ty))
             }
             Expression::Unary { op, expr } => {
-                let expr_type = self.infer_expression_type(expr)?;
-                match op {
+                let operand = self.check_expression(expr)?;
+                let ty = match op {
                     UnaryOp::Plus | UnaryOp::Minus => {
-                        if expr_type.is_numeric() {
-                            Ok(expr_type)
+                        if operand.ty.is_numeric() {
+                            operand.ty
                         } else {
-                            Err(vec![format!("Unärer Operator +/- erfordert numerischen Typ, ist {:?}", expr_type)])
+                            return Err(vec![format!(
+                                "Unärer Operator +/- erfordert numerischen Typ, ist {}",
+                                self.types.display(operand.ty)
+                            )]);
                         }
                     }
                     UnaryOp::Not => {
-                        if expr_type.is_boolean() {
-                            Ok(ResolvedType::Boolean)
+                        if operand.ty.is_boolean() {
+                            TypeId::BOOLEAN
                         } else {
-                            Err(vec![format!("NOT erfordert BOOLEAN, ist {:?}", expr_type)])
+                            return Err(vec![format!(
+                                "NOT erfordert BOOLEAN, ist {}",
+                                self.types.display(operand.ty)
+                            )]);
                         }
                     }
-                }
+                };
+                Ok(Expr::new(ExprKind::Unary { op: op.clone(), operand: Box::new(operand) }, ty))
             }
             Expression::Binary { left, op, right } => {
-                let left_type = self.infer_expression_type(left)?;
-                let right_type = self.infer_expression_type(right)?;
-
-                match op {
-                    BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
-                        if left_type.is_numeric() && right_type.is_numeric() {
-                            if matches!(left_type, ResolvedType::Real) || matches!(right_type, ResolvedType::Real) {
-                                Ok(ResolvedType::Real)
-                            } else {
-                                Ok(ResolvedType::Integer)
-                            }
-                        } else {
-                            Err(vec![format!("Arithmetische Operation erfordert numerische Typen: {:?} und {:?}", left_type, right_type)])
-                        }
-                    }
-                    BinaryOp::Div => {
-                        if left_type.is_numeric() && right_type.is_numeric() {
-                            Ok(ResolvedType::Real)
-                        } else {
-                            Err(vec!["Division erfordert numerische Typen".to_string()])
-                        }
-                    }
-                    BinaryOp::IntDiv | BinaryOp::Mod => {
-                        if left_type.is_integer() && right_type.is_integer() {
-                            Ok(ResolvedType::Integer)
-                        } else {
-                            Err(vec!["DIV/MOD erfordert INTEGER-Typen".to_string()])
-                        }
-                    }
-                    BinaryOp::And | BinaryOp::Or => {
-                        if left_type.is_boolean() && right_type.is_boolean() {
-                            Ok(ResolvedType::Boolean)
-                        } else {
-                            Err(vec!["Logische Operation erfordert BOOLEAN-Typen".to_string()])
-                        }
-                    }
-                    BinaryOp::Equal | BinaryOp::NotEqual => {
-                        if left_type.is_comparable() && right_type.is_comparable() {
-                            Ok(ResolvedType::Boolean)
-                        } else {
-                            Err(vec![format!("Vergleich nicht möglich für Typen {:?} und {:?}", left_type, right_type)])
-                        }
-                    }
-                    BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
-                        if (left_type.is_numeric() && right_type.is_numeric())
-                            || (matches!(left_type, ResolvedType::Char) && matches!(right_type, ResolvedType::Char))
-                        {
-                            Ok(ResolvedType::Boolean)
-                        } else {
-                            Err(vec![format!("Vergleichsoperator nicht anwendbar auf {:?} und {:?}", left_type, right_type)])
-                        }
-                    }
-                    BinaryOp::In => {
-                        if left_type.is_integer() && matches!(right_type, ResolvedType::Set) {
-                            Ok(ResolvedType::Boolean)
-                        } else {
-                            Err(vec!["IN erfordert INTEGER und SET".to_string()])
-                        }
-                    }
-                    BinaryOp::Is => {
-                        Ok(ResolvedType::Boolean)
-                    }
-                }
+                let left = self.check_expression(left)?;
+                let right = self.check_expression(right)?;
+                self.check_binary(left, op, right)
             }
         }
     }
 
-    fn infer_designator_type(&self, designator: &Designator) -> Result<ResolvedType, Vec<String>> {
-        let base_name = &designator.base.name;
+    fn check_binary(&self, left: Expr, op: &BinaryOp, right: Expr) -> Result<Expr, Vec<String>> {
+        let (left, right) = unify_char_operands(left, right);
+        let (left_name, right_name) = (self.types.display(left.ty), self.types.display(right.ty));
+
+        // Keine impliziten Umwandlungen: beide Operanden haben denselben Typ.
+        // Zeichenketten (Arrays von CHAR und Strings) sind untereinander
+        // vergleichbar, auch bei verschiedener Länge (8.2.4)
+        let same = left.ty == right.ty;
+        let text = self.types.is_text(left.ty) && self.types.is_text(right.ty);
+        let ty = match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+                if same && (left.ty.is_numeric() || left.ty == TypeId::SET) {
+                    left.ty
+                } else {
+                    return Err(vec![format!(
+                        "Arithmetische Operation erfordert numerische Typen oder SET: {} und {}",
+                        left_name, right_name
+                    )]);
+                }
+            }
+            // Division von REAL-Werten bzw. symmetrische Differenz von Mengen
+            BinaryOp::Div => {
+                if same && (left.ty == TypeId::REAL || left.ty == TypeId::SET) {
+                    left.ty
+                } else {
+                    return Err(vec![format!(
+                        "/ erfordert REAL oder SET: {} und {}",
+                        left_name, right_name
+                    )]);
+                }
+            }
+            BinaryOp::IntDiv | BinaryOp::Mod => {
+                if left.ty.is_integer() && right.ty.is_integer() {
+                    TypeId::INTEGER
+                } else {
+                    return Err(vec!["DIV/MOD erfordert INTEGER-Typen".to_string()]);
+                }
+            }
+            BinaryOp::And | BinaryOp::Or => {
+                if left.ty.is_boolean() && right.ty.is_boolean() {
+                    TypeId::BOOLEAN
+                } else {
+                    return Err(vec!["Logische Operation erfordert BOOLEAN-Typen".to_string()]);
+                }
+            }
+            BinaryOp::Equal | BinaryOp::NotEqual => {
+                if (same && (left.ty.is_comparable() || left.ty == TypeId::SET))
+                    || text
+                    || self.types.is_reference_comparable(left.ty, right.ty)
+                {
+                    TypeId::BOOLEAN
+                } else {
+                    return Err(vec![format!(
+                        "Vergleich nicht möglich für Typen {} und {}",
+                        left_name, right_name
+                    )]);
+                }
+            }
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+                if (same && (left.ty.is_numeric() || left.ty == TypeId::CHAR)) || text {
+                    TypeId::BOOLEAN
+                } else {
+                    return Err(vec![format!(
+                        "Vergleichsoperator nicht anwendbar auf {} und {}",
+                        left_name, right_name
+                    )]);
+                }
+            }
+            BinaryOp::In => {
+                if left.ty.is_integer() && right.ty == TypeId::SET {
+                    TypeId::BOOLEAN
+                } else {
+                    return Err(vec!["IN erfordert INTEGER und SET".to_string()]);
+                }
+            }
+            BinaryOp::Is => TypeId::BOOLEAN,
+        };
 
-        let mut current_type = if let Some(symbol) = self.symbol_table.lookup(base_name) {
-            match &symbol.kind {
-                SymbolKind::Variable { var_type, .. } => var_type.clone(),
-                SymbolKind::Constant { resolved_type, .. } => resolved_type.clone(),
-                SymbolKind::Procedure { params, return_type } => {
-                    ResolvedType::Procedure {
-                        params: params.clone(),
-                        return_type: return_type.clone().map(Box::new),
-                    }
+        Ok(binary((left, right), op, ty))
+    }
+
+    fn check_set_element(&self, element: &Expression) -> Result<Expr, Vec<String>> {
+        let element = self.check_expression(element)?;
+        if !element.ty.is_integer() {
+            return Err(vec![format!(
+                "Mengenelement muss INTEGER sein, ist {}",
+                self.types.display(element.ty)
+            )]);
+        }
+        Ok(element)
+    }
+
+    fn check_designator(&self, designator: &Designator) -> Result<hir::Designator, Vec<String>> {
+        // Der Parser liest `r.f` als qualifizierten Bezeichner; ist `r` kein Modul,
+        // handelt es sich um einen Feldzugriff
+        let base = &designator.base;
+        let (base_name, base_column, field_selector, interface) = match &base.module {
+            Some(module) => match self.symbol_table.lookup(module) {
+                Some(symbol @ Symbol { kind: SymbolKind::Module, .. }) => {
+                    self.reference((base.line, base.column), symbol);
+                    (&base.name, base.name_column, None, self.imported_symbols.get(module).map(|symbols| (module, symbols)))
+                }
+                _ => {
+                    let field = Selector::Field {
+                        name: base.name.clone(),
+                        line: base.line,
+                        column: base.name_column,
+                    };
+                    (module, base.column, Some(field), None)
                 }
-                SymbolKind::Type { type_def } => type_def.clone(),
-                SymbolKind::Module => ResolvedType::Named(base_name.clone()),
+            },
+            None => (&base.name, base.name_column, None, None),
+        };
+
+        // Symbole importierter Module tragen den echten Modulnamen
+        let found = match interface {
+            Some((alias, symbols)) => {
+                let module = &self.imports[alias];
+                let symbol = symbols
+                    .get(base_name)
+                    .ok_or_else(|| vec![format!("Modul {} exportiert {} nicht", module, base_name)])?;
+                let mut symbol_ref = self.symbol_ref(0, symbol);
+                symbol_ref.qualified_name = format!("{}.{}", module, symbol.name);
+                symbol_ref.module = Some(module.clone());
+                Some((symbol_ref, symbol))
             }
+            None => self.symbol_table.lookup_with_level(base_name).map(|(level, symbol)| {
+                self.reference((base.line, base_column), symbol);
+                (self.symbol_ref(level, symbol), symbol)
+            }),
+        };
+
+        let (symbol, mut current_type) = if let Some((symbol_ref, symbol)) = found {
+            let base_type = match &symbol.kind {
+                SymbolKind::Variable { var_type, .. } => *var_type,
+                SymbolKind::Constant { resolved_type, .. } => *resolved_type,
+                SymbolKind::Procedure { proc_type } => *proc_type,
+                SymbolKind::Type { type_def } => *type_def,
+                SymbolKind::Builtin(builtin) => {
+                    return Err(vec![format!(
+                        "Standardprozedur {} kann nur aufgerufen werden",
+                        builtin.name()
+                    )]);
+                }
+                SymbolKind::Module => {
+                    return Err(vec![format!("Modul '{}' ist kein Wert", base_name)]);
+                }
+            };
+            (symbol_ref, base_type)
         } else {
             return Err(vec![format!("Unbekannter Bezeichner: {}", base_name)]);
         };
 
         // Selektoren anwenden
-        for selector in &designator.selectors {
-            current_type = match selector {
-                Selector::Field(field_name) => {
-                    if let ResolvedType::Record { fields, .. } = current_type {
-                        fields.get(field_name).cloned().ok_or_else(|| {
-                            vec![format!("Unbekanntes Feld: {}", field_name)]
-                        })?
-                    } else {
-                        return Err(vec![format!("Feld-Zugriff auf Nicht-Record-Typ: {:?}", current_type)]);
+        let mut selectors = Vec::new();
+        for selector in field_selector.iter().chain(&designator.selectors) {
+            match selector {
+                Selector::Field { name: field_name, line, column } => {
+                    // p.f steht für p^.f
+                    if let TypeKind::Pointer { target } = self.types.kind(current_type) {
+                        current_type = *target;
+                        selectors.push(hir::Selector::Dereference { ty: current_type });
                     }
+                    self.field_reference((*line, *column), current_type, field_name);
+                    current_type = match self.types.kind(current_type) {
+                        TypeKind::Record { .. } => self
+                            .types
+                            .field(current_type, field_name)
+                            .map(|(_, field)| field.ty)
+                            .ok_or_else(|| vec![format!("Unbekanntes Feld: {}", field_name)])?,
+                        _ => {
+                            return Err(vec![format!(
+                                "Feld-Zugriff auf Nicht-Record-Typ: {}",
+                                self.types.display(current_type)
+                            )]);
+                        }
+                    };
+                    selectors.push(hir::Selector::Field {
+                        name: field_name.clone(),
+                        ty: current_type,
+                    });
                 }
-                Selector::Index(_) => {
-                    if let ResolvedType::Array { element_type, .. } = current_type {
-                        *element_type
-                    } else {
-                        return Err(vec![format!("Index-Zugriff auf Nicht-Array-Typ: {:?}", current_type)]);
+                Selector::Index(indices) => {
+                    for index in indices {
+                        let index = self.check_expression(index)?;
+                        if !index.ty.is_integer() {
+                            return Err(vec![format!(
+                                "Array-Index muss INTEGER sein, ist {}",
+                                self.types.display(index.ty)
+                            )]);
+                        }
+
+                        // a[i, j] ist a[i][j]: jeder Index entfernt eine Dimension
+                        current_type = match self.types.kind(current_type) {
+                            TypeKind::Array { element, .. } => *element,
+                            _ => {
+                                return Err(vec![format!(
+                                    "Index-Zugriff auf Nicht-Array-Typ: {}",
+                                    self.types.display(current_type)
+                                )]);
+                            }
+                        };
+                        selectors.push(hir::Selector::Index {
+                            index: Box::new(index),
+                            ty: current_type,
+                        });
                     }
                 }
                 Selector::Dereference => {
-                    if let ResolvedType::Pointer { target_type } = current_type {
-                        *target_type
-                    } else {
-                        return Err(vec![format!("Dereferenzierung auf Nicht-Pointer-Typ: {:?}", current_type)]);
-                    }
+                    current_type = match self.types.kind(current_type) {
+                        TypeKind::Pointer { target } => *target,
+                        _ => {
+                            return Err(vec![format!(
+                                "Dereferenzierung auf Nicht-Pointer-Typ: {}",
+                                self.types.display(current_type)
+                            )]);
+                        }
+                    };
+                    selectors.push(hir::Selector::Dereference { ty: current_type });
+                }
+                Selector::TypeGuard(guard) => {
+                    current_type = self.check_type_guard(current_type, guard)?;
+                    selectors.push(hir::Selector::TypeGuard { ty: current_type });
                 }
-                Selector::TypeGuard(_) => {
-                    current_type
+            }
+        }
+
+        Ok(hir::Designator {
+            symbol,
+            selectors,
+            ty: current_type,
+        })
+    }
+
+    // v(T): T muss eine Erweiterung des Record- bzw. Zeigertyps von v sein
+    fn check_type_guard(&self, current_type: TypeId, guard: &Qualident) -> Result<TypeId, Vec<String>> {
+        let symbol = match &guard.module {
+            Some(module) => self.imported_symbols.get(module).and_then(|symbols| symbols.get(&guard.name)),
+            None => self.symbol_table.lookup(&guard.name),
+        };
+        let guard_type = match symbol {
+            Some(symbol @ Symbol { kind: SymbolKind::Type { type_def }, .. }) => {
+                self.reference((guard.line, guard.name_column), symbol);
+                *type_def
+            }
+            Some(_) => return Err(vec![format!("'{}' ist kein Typ", guard)]),
+            None => return Err(vec![format!("Unbekannter Typ: {}", guard)]),
+        };
+        let guarded = matches!(
+            self.types.kind(current_type),
+            TypeKind::Record { .. } | TypeKind::Pointer { .. }
+        );
+        if !guarded || !self.types.is_extension(guard_type, current_type) {
+            return Err(vec![format!(
+                "Typwächter: {} ist keine Erweiterung von {}",
+                self.types.display(guard_type),
+                self.types.display(current_type)
+            )]);
+        }
+        Ok(guard_type)
+    }
+
+    // Der Parser liest v(T) ohne folgenden Selektor als Aufruf; ist v keine
+    // Prozedur und T ein Typ, ist es ein Typwächter
+    fn as_type_guard(&self, designator: &Designator, arguments: &[Expression]) -> Option<Designator> {
+        let guard = match arguments {
+            [Expression::Designator(Designator { base, selectors })] if selectors.is_empty() => base,
+            _ => return None,
+        };
+        let is_type = match &guard.module {
+            Some(module) => self.imported_symbols.get(module).and_then(|symbols| symbols.get(&guard.name)),
+            None => self.symbol_table.lookup(&guard.name),
+        }
+        .is_some_and(|symbol| matches!(symbol.kind, SymbolKind::Type { .. }));
+        // Probe ohne Verweise: der eigentliche Durchlauf trägt sie ein
+        let references = self.references.borrow().len();
+        let is_procedure = self
+            .check_designator(designator)
+            .is_ok_and(|procedure| matches!(self.types.kind(procedure.ty), TypeKind::Procedure { .. }));
+        self.references.borrow_mut().truncate(references);
+        if !is_type || is_procedure {
+            return None;
+        }
+        let mut designator = designator.clone();
+        designator.selectors.push(Selector::TypeGuard(guard.clone()));
+        Some(designator)
+    }
+
+    fn symbol_ref(&self, level: usize, symbol: &Symbol) -> hir::SymbolRef {
+        let kind = match &symbol.kind {
+            SymbolKind::Constant { value, .. } => SymbolRefKind::Constant(value.clone()),
+            SymbolKind::Variable { is_parameter, is_var_param, .. } => SymbolRefKind::Variable {
+                level,
+                is_parameter: *is_parameter,
+                is_var_param: *is_var_param,
+            },
+            SymbolKind::Procedure { .. } | SymbolKind::Builtin(_) => SymbolRefKind::Procedure,
+            SymbolKind::Type { .. } => SymbolRefKind::Type,
+            SymbolKind::Module => SymbolRefKind::Module,
+        };
+
+        hir::SymbolRef {
+            name: symbol.name.clone(),
+            qualified_name: self.qualified_name(level, &symbol.name),
+            kind,
+            module: None,
+        }
+    }
+
+    // Zuweisungskompatibilität (Oberon-07 Report, 9.1). String-Konstanten
+    // werden zu CHAR bzw. zum Wert des Zeichen-Arrays, mit 0X aufgefüllt.
+    // None, wenn der Ausdruck nicht zuweisbar ist.
+    fn coerce(&self, expr: Expr, target_type: TypeId) -> Option<Expr> {
+        if let Some(ConstValue::String(text)) = const_value(&expr) {
+            if !self.types.is_string_assignable(text.chars().count(), target_type) {
+                return None;
+            }
+            return match self.types.kind(target_type) {
+                TypeKind::Char => text.chars().next().map(|ch| Expr::literal(ConstValue::Char(ch))),
+                TypeKind::Array { length, .. } => {
+                    let value = text.chars().chain(std::iter::repeat('\0')).take(*length).collect();
+                    Some(Expr::new(ExprKind::Literal(ConstValue::String(value)), target_type))
                 }
+                _ => Some(expr),
             };
         }
+        self.types.is_assignable(expr.ty, target_type).then_some(expr)
+    }
 
-        Ok(current_type)
+    // ========================================================================
+    // Standardprozeduren
+    // ========================================================================
+
+    fn lookup_builtin(&self, designator: &Designator) -> Option<Builtin> {
+        if !designator.selectors.is_empty() || designator.base.module.is_some() {
+            return None;
+        }
+        match self.symbol_table.lookup(&designator.base.name) {
+            Some(Symbol { kind: SymbolKind::Builtin(builtin), .. }) => Some(*builtin),
+            _ => None,
+        }
+    }
+
+    // Liefert die geprüften Argumente und den Ergebnistyp
+    // (None bei eigentlichen Prozeduren wie INC)
+    fn check_builtin_call(
+        &self,
+        builtin: Builtin,
+        arguments: &[Expression],
+    ) -> Result<(Vec<Expr>, Option<TypeId>), Vec<String>> {
+        let mut args = arguments
+            .iter()
+            .map(|arg| self.check_expression(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Erstes Argument von INC, DEC, INCL, EXCL und NEW muss eine Variable sein
+        let needs_variable = matches!(
+            builtin,
+            Builtin::Inc | Builtin::Dec | Builtin::Incl | Builtin::Excl | Builtin::New
+        );
+        if needs_variable && !args.first().is_some_and(is_variable) {
+            return Err(vec![format!("Erstes Argument von {} muss eine Variable sein", builtin.name())]);
+        }
+
+        // ORD("A"): einzeichige String-Konstante als CHAR behandeln
+        if builtin == Builtin::Ord && args.len() == 1 {
+            if let Some(arg) = self.coerce(args[0].clone(), TypeId::CHAR) {
+                args[0] = arg;
+            }
+        }
+
+        let arg_kinds: Vec<&TypeKind> = args.iter().map(|arg| self.types.kind(arg.ty)).collect();
+
+        use TypeKind::*;
+        let result = match (builtin, arg_kinds.as_slice()) {
+            (Builtin::Abs, [Integer | Real]) => Some(args[0].ty),
+            (Builtin::Odd, [Integer]) => Some(TypeId::BOOLEAN),
+            (Builtin::Len, [Array { .. } | String]) => Some(TypeId::INTEGER),
+            (Builtin::Lsl | Builtin::Asr | Builtin::Ror, [Integer, Integer]) => Some(TypeId::INTEGER),
+            (Builtin::Floor, [Real]) => Some(TypeId::INTEGER),
+            (Builtin::Flt, [Integer]) => Some(TypeId::REAL),
+            (Builtin::Ord, [Char | Boolean | Set]) => Some(TypeId::INTEGER),
+            (Builtin::Chr, [Integer]) => Some(TypeId::CHAR),
+            (Builtin::Inc | Builtin::Dec, [Integer] | [Integer, Integer]) => None,
+            (Builtin::Incl | Builtin::Excl, [Set, Integer]) => None,
+            (Builtin::New, [Pointer { .. }]) => None,
+            (Builtin::Assert, [Boolean]) => None,
+            _ => {
+                let arg_names = args.iter().map(|arg| self.types.display(arg.ty)).collect::<Vec<_>>();
+                return Err(vec![format!(
+                    "Ungültige Argumente für {}: ({})",
+                    builtin.name(),
+                    arg_names.join(", ")
+                )]);
+            }
+        };
+
+        // Konstante Argumente vorab auswerten (z.B. CHR(-1), LSL(1, 64))
+        if let Err(ConstError::Invalid(msg)) =
+            ConstEvaluator::new(&self.symbol_table).eval_builtin(builtin, arguments)
+        {
+            return Err(vec![msg]);
+        }
+
+        Ok((args, result))
+    }
+
+    // ========================================================================
+    // Eingebaute Bibliotheksmodule
+    // ========================================================================
+
+    // M.P, wenn M ein importiertes Bibliotheksmodul bezeichnet
+    fn lookup_library(&self, designator: &Designator) -> Option<LibraryProcedure> {
+        let alias = designator.base.module.as_ref()?;
+        if !designator.selectors.is_empty() {
+            return None;
+        }
+        let symbol = match self.symbol_table.lookup(alias) {
+            Some(symbol @ Symbol { kind: SymbolKind::Module, .. }) => symbol,
+            _ => return None,
+        };
+        let procedure = LibraryProcedure::lookup(self.imports.get(alias)?, &designator.base.name)?;
+        self.reference((designator.base.line, designator.base.column), symbol);
+        Some(procedure)
+    }
+
+    fn check_library_call(
+        &self,
+        procedure: LibraryProcedure,
+        arguments: &[Expression],
+    ) -> Result<Vec<Expr>, Vec<String>> {
+        let mut args = arguments
+            .iter()
+            .map(|arg| self.check_expression(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Out.Char("A") wie bei der Zuweisung umwandeln
+        if let (LibraryProcedure::OutChar, Some(first)) = (procedure, args.first()) {
+            if let Some(arg) = self.coerce(first.clone(), TypeId::CHAR) {
+                args[0] = arg;
+            }
+        }
+
+        let arg_kinds: Vec<&TypeKind> = args.iter().map(|arg| self.types.kind(arg.ty)).collect();
+
+        use TypeKind::*;
+        let valid = match (procedure, arg_kinds.as_slice()) {
+            (LibraryProcedure::OutOpen | LibraryProcedure::OutLn, []) => true,
+            (LibraryProcedure::OutChar, [Char]) => true,
+            (LibraryProcedure::OutString, [String]) => true,
+            (LibraryProcedure::OutString, [Array { element, .. }]) => *element == TypeId::CHAR,
+            (LibraryProcedure::OutInt, [Integer, Integer]) => true,
+            (LibraryProcedure::OutReal, [Real, Integer]) => true,
+            _ => false,
+        };
+
+        if !valid {
+            let arg_names = args.iter().map(|arg| self.types.display(arg.ty)).collect::<Vec<_>>();
+            return Err(vec![format!("Ungültige Argumente für {}: ({})", procedure, arg_names.join(", "))]);
+        }
+        Ok(args)
+    }
+
+    fn eval_case_label(&mut self, label: &Expression, case_type: TypeId) -> Result<i64, Vec<String>> {
+        let value = self.expect_const(label, "CASE-Label")?;
+        let ordinal = match case_type {
+            TypeId::CHAR => value.as_char().map(|ch| ch as i64),
+            _ => value.as_integer(),
+        };
+
+        ordinal.ok_or_else(|| {
+            let err = format!(
+                "CASE-Label-Typ {} passt nicht zu {}",
+                value.type_name(),
+                self.types.display(case_type)
+            );
+            self.errors.push(err.clone());
+            vec![err]
+        })
     }
 
     #[allow(dead_code)]
     pub fn get_errors(&self) -> &[String] {
         &self.errors
     }
+
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
 }
 
 impl Default for TypeChecker {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+// ============================================================================
+// Hilfsfunktionen für den Symbolindex
+// ============================================================================
+
+fn definition_kind(kind: &SymbolKind) -> DefinitionKind {
+    match kind {
+        SymbolKind::Constant { value, .. } => DefinitionKind::Constant(value.clone()),
+        SymbolKind::Variable { is_parameter: true, is_var_param, .. } => {
+            DefinitionKind::Parameter { is_var: *is_var_param }
+        }
+        SymbolKind::Variable { .. } => DefinitionKind::Variable,
+        SymbolKind::Type { .. } => DefinitionKind::Type,
+        SymbolKind::Procedure { .. } | SymbolKind::Builtin(_) => DefinitionKind::Procedure,
+        SymbolKind::Module => DefinitionKind::Module,
+    }
+}
+
+fn symbol_type(kind: &SymbolKind) -> Option<TypeId> {
+    match kind {
+        SymbolKind::Constant { resolved_type, .. } => Some(*resolved_type),
+        SymbolKind::Variable { var_type, .. } => Some(*var_type),
+        SymbolKind::Type { type_def } => Some(*type_def),
+        SymbolKind::Procedure { proc_type } => Some(*proc_type),
+        SymbolKind::Builtin(_) | SymbolKind::Module => None,
+    }
+}
+
+// ============================================================================
+// Hilfsfunktionen für das HIR
+// ============================================================================
+
+// Exportiertes Symbol eines Imports; es hat keine Position im eigenen Quelltext
+fn imported_symbol(name: &str, kind: SymbolKind) -> Symbol {
+    Symbol { name: name.to_string(), kind, exported: ExportMark::None, defined_at: None }
+}
+
+fn binary((left, right): (Expr, Expr), op: &BinaryOp, ty: TypeId) -> Expr {
+    Expr::new(
+        ExprKind::Binary {
+            left: Box::new(left),
+            op: op.clone(),
+            right: Box::new(right),
+        },
+        ty,
+    )
+}
+
+// Vergleich CHAR mit einzeichiger String-Konstante: Konstante wird CHAR
+fn unify_char_operands(left: Expr, right: Expr) -> (Expr, Expr) {
+    let as_char = |expr: &Expr| {
+        const_value(expr)
+            .and_then(|value| value.as_char())
+            .map(|ch| Expr::literal(ConstValue::Char(ch)))
+    };
+
+    match (left.ty, right.ty) {
+        (TypeId::CHAR, TypeId::STRING) => {
+            let right = as_char(&right).unwrap_or(right);
+            (left, right)
+        }
+        (TypeId::STRING, TypeId::CHAR) => {
+            let left = as_char(&left).unwrap_or(left);
+            (left, right)
+        }
+        _ => (left, right),
+    }
+}
+
+fn const_value(expr: &Expr) -> Option<&ConstValue> {
+    match &expr.kind {
+        ExprKind::Literal(value) => Some(value),
+        ExprKind::Designator(designator) if designator.selectors.is_empty() => match &designator.symbol.kind {
+            SymbolRefKind::Constant(value) => Some(value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_variable(expr: &Expr) -> bool {
+    matches!(
+        &expr.kind,
+        ExprKind::Designator(designator) if matches!(designator.symbol.kind, SymbolRefKind::Variable { .. })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{check, parse};
+
+    fn assignment_value(module: &hir::Module, index: usize) -> &Expr {
+        match &module.body[index] {
+            hir::Statement::Assignment { value, .. } => value,
+            other => panic!("Zuweisung erwartet, gefunden {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_conversions() {
+        let module = check(
+            "MODULE T; TYPE Str = ARRAY 4 OF CHAR; VAR r: REAL; c: CHAR; i: INTEGER; s: Str;
+             BEGIN r := FLT(i) / 2.0; c := \"A\"; s := \"Hi\" END T.",
+        )
+        .unwrap();
+
+        assert_eq!(assignment_value(&module, 0).ty, TypeId::REAL);
+        assert_eq!(assignment_value(&module, 1).kind, ExprKind::Literal(ConstValue::Char('A')));
+        let text = assignment_value(&module, 2);
+        assert_eq!(text.kind, ExprKind::Literal(ConstValue::String("Hi\0\0".to_string())));
+        assert_eq!(text.ty, module.declarations.types[0].type_def);
+
+        let error = |statement: &str| {
+            let source = format!(
+                "MODULE T; VAR r: REAL; c: CHAR; i: INTEGER; s: ARRAY 2 OF CHAR; BEGIN {} END T.",
+                statement
+            );
+            check(&source).unwrap_err().remove(0)
+        };
+        assert!(error("r := i").contains("INTEGER kann nicht zu REAL zugewiesen werden"));
+        assert!(error("r := r + i").contains("numerische Typen oder SET: REAL und INTEGER"));
+        assert!(error("r := i / 2").contains("/ erfordert REAL oder SET"));
+        assert!(error("c := \"AB\"").contains("STRING kann nicht zu CHAR zugewiesen werden"));
+        assert!(error("s := \"AB\"").contains("STRING kann nicht zu ARRAY 2 OF CHAR zugewiesen werden"));
+        assert!(error("IF i < r THEN END").contains("Vergleichsoperator nicht anwendbar"));
+    }
+
+    #[test]
+    fn test_set_operators() {
+        let module = check(
+            "MODULE T; VAR s, t: SET; b: BOOLEAN;
+             BEGIN s := {1} + t; s := s - t; s := s * t; s := s / t; b := s = t END T.",
+        )
+        .unwrap();
+        for index in 0..4 {
+            assert_eq!(assignment_value(&module, index).ty, TypeId::SET);
+        }
+        assert_eq!(assignment_value(&module, 4).ty, TypeId::BOOLEAN);
+
+        let errors = check("MODULE T; VAR s: SET; BEGIN s := s + 1 END T.").unwrap_err();
+        assert!(errors[0].contains("numerische Typen oder SET: SET und INTEGER"));
+    }
+
+    #[test]
+    fn test_symbol_references() {
+        let module = check(
+            "MODULE T; CONST N = 3; VAR a: ARRAY N, 4 OF INTEGER;
+             PROCEDURE P(VAR x: INTEGER); VAR i: INTEGER;
+             BEGIN i := N; x := a[i, 0] END P;
+             END T.",
+        )
+        .unwrap();
+
+        let proc = &module.declarations.procedures[0];
+        let (target, value) = match &proc.body[0] {
+            hir::Statement::Assignment { target, value, .. } => (target, value),
+            other => panic!("Zuweisung erwartet, gefunden {:?}", other),
+        };
+        assert_eq!(target.symbol.qualified_name, "P.i");
+        match &value.kind {
+            ExprKind::Designator(designator) => {
+                assert_eq!(designator.symbol.kind, SymbolRefKind::Constant(ConstValue::Integer(3)));
+            }
+            other => panic!("Konstante erwartet, gefunden {:?}", other),
+        }
+
+        match &proc.body[1] {
+            hir::Statement::Assignment { target, value, .. } => {
+                assert!(target.symbol.is_var_param());
+                match &value.kind {
+                    ExprKind::Designator(designator) => {
+                        assert_eq!(designator.symbol.qualified_name, "a");
+                        assert_eq!(designator.selectors.len(), 2);
+                        assert_eq!(designator.ty, TypeId::INTEGER);
+                    }
+                    other => panic!("Designator erwartet, gefunden {:?}", other),
+                }
+            }
+            other => panic!("Zuweisung erwartet, gefunden {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_var_argument_must_be_variable() {
+        let errors = check(
+            "MODULE T; PROCEDURE P(VAR x: INTEGER); BEGIN x := 1 END P;
+             BEGIN P(1) END T.",
+        )
+        .unwrap_err();
+        assert!(errors[0].contains("VAR-Parameter"));
+    }
+
+    #[test]
+    fn test_nominal_type_identity() {
+        let module = check(
+            "MODULE T; TYPE A = RECORD x: INTEGER END; B = RECORD x: INTEGER END; C = A;
+             VAR a: A; b: B; c: C; v, w: ARRAY 3 OF INTEGER;
+             BEGIN a := c; v := w END T.",
+        )
+        .unwrap();
+        let types = &module.declarations.types;
+        assert_eq!(types[0].type_def, types[2].type_def);
+        assert_ne!(types[0].type_def, types[1].type_def);
+        assert_eq!(module.types.name(types[2].type_def), Some("A"));
+
+        let errors = check(
+            "MODULE T; TYPE A = RECORD x: INTEGER END; B = RECORD x: INTEGER END;
+             VAR a: A; b: B;
+             BEGIN a := b END T.",
+        )
+        .unwrap_err();
+        assert!(errors[0].contains("B kann nicht zu A zugewiesen werden"));
+
+        // Arrays gleicher Länge und gleichen Elementtyps sind zuweisbar (9.1)
+        check(
+            "MODULE T; TYPE Name = ARRAY 16 OF CHAR; VAR v: ARRAY 3 OF INTEGER; w: ARRAY 3 OF INTEGER;
+             n: Name; s: ARRAY 16 OF CHAR;
+             BEGIN v := w; n := s; s := n END T.",
+        )
+        .unwrap();
+        let errors = check(
+            "MODULE T; VAR v: ARRAY 3 OF INTEGER; w: ARRAY 4 OF INTEGER;
+             BEGIN v := w END T.",
+        )
+        .unwrap_err();
+        assert!(errors[0].contains("ARRAY 4 OF INTEGER kann nicht zu ARRAY 3 OF INTEGER zugewiesen werden"));
+    }
+
+    #[test]
+    fn test_string_comparison() {
+        let module = check(
+            "MODULE T; VAR s: ARRAY 8 OF CHAR; t: ARRAY 4 OF CHAR; b: BOOLEAN;
+             BEGIN b := s = \"AB\"; b := \"AB\" # t; b := s < t; b := t >= s; b := \"A\" <= \"B\" END T.",
+        )
+        .unwrap();
+        for index in 0..5 {
+            assert_eq!(assignment_value(&module, index).ty, TypeId::BOOLEAN);
+        }
+
+        let error = |statement: &str| {
+            let source = format!(
+                "MODULE T; VAR s: ARRAY 8 OF CHAR; v: ARRAY 8 OF INTEGER; c: CHAR; b: BOOLEAN; BEGIN {} END T.",
+                statement
+            );
+            check(&source).unwrap_err().remove(0)
+        };
+        assert!(error("b := s = v").contains("Vergleich nicht möglich"));
+        assert!(error("b := v < v").contains("Vergleichsoperator nicht anwendbar"));
+        assert!(error("b := s = c").contains("Vergleich nicht möglich"));
+    }
+
+    #[test]
+    fn test_record_extension() {
+        let module = check(
+            "MODULE T; TYPE Base = RECORD x: INTEGER END; Ext = RECORD (Base) z, y: INTEGER END;
+             BaseP = POINTER TO Base; ExtP = POINTER TO Ext;
+             VAR b: Base; e: Ext; bp: BaseP; ep: ExtP;
+             BEGIN e.x := 1; b := e; bp := ep; bp := NIL END T.",
+        )
+        .unwrap();
+        let ext = module.declarations.types[1].type_def;
+        let names: Vec<&str> = module.types.fields(ext).iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, ["x", "z", "y"]);
+        assert_eq!(module.types.field(ext, "y").map(|(index, _)| index), Some(2));
+
+        let errors = check(
+            "MODULE T; TYPE Base = RECORD x: INTEGER END; Ext = RECORD (Base) y: INTEGER END;
+             VAR b: Base; e: Ext;
+             BEGIN e := b END T.",
+        )
+        .unwrap_err();
+        assert!(errors[0].contains("Base kann nicht zu Ext zugewiesen werden"));
+
+        let errors = check(
+            "MODULE T; TYPE Base = RECORD x: INTEGER END; Ext = RECORD (Base) x: INTEGER END;
+             END T.",
+        )
+        .unwrap_err();
+        assert!(errors[0].contains("Feld 'x' bereits definiert"));
+    }
+
+    #[test]
+    fn test_array_lengths() {
+        let module = check("MODULE T; CONST N = 2; VAR a: ARRAY N * 3, 2147483647 DIV 8 OF CHAR; END T.").unwrap();
+        let array = module.declarations.variables[0].var_type;
+        assert!(matches!(module.types.kind(array), TypeKind::Array { length: 6, .. }));
+
+        let error = |length: &str| {
+            let source = format!("MODULE T; VAR a: ARRAY {} OF INTEGER; END T.", length);
+            check(&source).unwrap_err().remove(0)
+        };
+        assert_eq!(error("0"), "Array-Länge muss positiv sein, ist 0");
+        assert_eq!(error("-3"), "Array-Länge muss positiv sein, ist -3");
+        assert_eq!(error("4294967297"), "Array-Länge 4294967297 ist zu groß (höchstens 2147483647)");
+        assert_eq!(error("TRUE"), "Array-Länge muss eine INTEGER-Konstante sein, ist BOOLEAN");
+        assert_eq!(
+            error("65536, 65536"),
+            "Array ARRAY 65536 OF ARRAY 65536 OF INTEGER ist zu groß (höchstens 2147483647 Elemente)"
+        );
+    }
+
+    #[test]
+    fn test_type_guards() {
+        let module = check(
+            "MODULE T; TYPE Shape = POINTER TO ShapeDesc; ShapeDesc = RECORD x: INTEGER END;
+             Circle = POINTER TO CircleDesc; CircleDesc = RECORD (ShapeDesc) r: INTEGER END;
+             VAR s: Shape; c: Circle; r: INTEGER;
+             BEGIN r := s(Circle).r; c := s(Circle); s(Circle).r := s.x END T.",
+        )
+        .unwrap();
+        let circle = module.declarations.types[2].type_def;
+        match &assignment_value(&module, 0).kind {
+            ExprKind::Designator(designator) => assert!(matches!(
+                designator.selectors.as_slice(),
+                [hir::Selector::TypeGuard { ty }, hir::Selector::Dereference { .. }, hir::Selector::Field { .. }]
+                    if *ty == circle
+            )),
+            other => panic!("Designator erwartet, gefunden {:?}", other),
+        }
+        assert_eq!(assignment_value(&module, 1).ty, circle);
+
+        let errors = check(
+            "MODULE T; TYPE Shape = POINTER TO ShapeDesc; ShapeDesc = RECORD x: INTEGER END;
+             Other = POINTER TO RECORD y: INTEGER END;
+             VAR s: Shape; y: INTEGER;
+             BEGIN y := s(Other).y END T.",
+        )
+        .unwrap_err();
+        assert!(errors[0].contains("Typwächter: Other ist keine Erweiterung von Shape"));
+    }
+
+    #[test]
+    fn test_forward_pointer_types() {
+        let module = check(
+            "MODULE T; TYPE Node = POINTER TO NodeDesc;
+               NodeDesc = RECORD value: INTEGER; next: Node; prev: POINTER TO NodeDesc END;
+             VAR n: Node;
+             BEGIN NEW(n); n^.next := n; n^.prev := NIL END T.",
+        )
+        .unwrap();
+        let node = module.declarations.types[0].type_def;
+        let node_desc = module.declarations.types[1].type_def;
+        assert_eq!(module.types.kind(node), &TypeKind::Pointer { target: node_desc });
+        let prev = module.types.field(node_desc, "prev").unwrap().1.ty;
+        assert_eq!(module.types.kind(prev), &TypeKind::Pointer { target: node_desc });
+
+        let errors = check("MODULE T; TYPE P = POINTER TO R; R = INTEGER; END T.").unwrap_err();
+        assert!(errors[0].contains("kein RECORD"));
+
+        let errors = check("MODULE T; TYPE R = RECORD s: S END; S = RECORD END; END T.").unwrap_err();
+        assert!(errors[0].contains("Unbekannter Typ: S"));
+    }
+
+    fn warnings(source: &str, options: WarningOptions) -> Vec<Warning> {
+        let mut checker = TypeChecker::with_warnings(options);
+        checker.check_module(&parse(source)).unwrap();
+        checker.warnings().to_vec()
+    }
+
+    #[test]
+    fn test_flow_warnings() {
+        let source = "MODULE T; IMPORT Out;
+             PROCEDURE F(b: BOOLEAN): INTEGER;
+             VAR x, y, z: INTEGER;
+             BEGIN
+               IF b THEN x := 1 ELSE x := 2 END;
+               x := x + y;
+               IF b THEN y := 1 END
+             RETURN y
+             END F;
+             PROCEDURE G;
+             VAR x: INTEGER;
+               PROCEDURE Set; BEGIN x := 1 END Set;
+             BEGIN Set; INC(x); ASSERT(FALSE); x := 0
+             END G;
+             BEGIN G END T.";
+
+        let kinds = |options| {
+            warnings(source, options)
+                .iter()
+                .map(|warning| (warning.kind, warning.message.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let all = kinds(WarningOptions::all());
+        assert_eq!(all.len(), 5, "{:?}", all);
+        assert!(all.contains(&(WarningKind::Unused, "Lokale Variable 'z' in 'F' wird nie benutzt".to_string())));
+        assert!(all.contains(&(WarningKind::Unused, "Import 'Out' wird nie benutzt".to_string())));
+        assert!(all.iter().any(|(kind, message)| *kind == WarningKind::UninitializedRead && message.contains("'y'")));
+        assert!(all.iter().any(|(kind, message)| *kind == WarningKind::UninitializedResult && message.contains("'y'")));
+        assert!(all.iter().any(|(kind, message)| *kind == WarningKind::Unreachable && message.contains("'G'")));
+
+        // Positionen: Deklaration bei unbenutzten Symbolen, sonst die Anweisung bzw. das RETURN
+        let lines: Vec<(WarningKind, usize)> = warnings(source, WarningOptions::all())
+            .iter()
+            .map(|warning| (warning.kind, warning.line))
+            .collect();
+        assert!(lines.contains(&(WarningKind::UninitializedRead, 6)), "{:?}", lines);
+        assert!(lines.contains(&(WarningKind::UninitializedResult, 8)), "{:?}", lines);
+        assert!(lines.contains(&(WarningKind::Unreachable, 13)), "{:?}", lines);
+        let out = warnings(source, WarningOptions::all()).into_iter().find(|warning| warning.message.contains("Out"));
+        assert_eq!(out.map(|warning| (warning.line, warning.column)), Some((1, 18)));
+
+        let mut options = WarningOptions::all();
+        options.set(WarningKind::Unused, false);
+        options.set(WarningKind::Unreachable, false);
+        let reads = kinds(options);
+        assert_eq!(reads.len(), 2, "{:?}", reads);
+        assert!(kinds(WarningOptions::none()).is_empty());
+    }
+
+    #[test]
+    fn test_case_flow() {
+        // Ein nicht getroffener CASE ohne ELSE bricht ab: Zuweisungen in
+        // allen Zweigen initialisieren, Abbrüche in allen Zweigen beenden
+        let assigned = warnings(
+            "MODULE T;
+             PROCEDURE F(i: INTEGER): INTEGER;
+             VAR x: INTEGER;
+             BEGIN CASE i OF 0: x := 1 | 1: x := 2 END
+             RETURN x
+             END F;
+             END T.",
+            WarningOptions::all(),
+        );
+        assert!(assigned.is_empty(), "{:?}", assigned);
+
+        let aborted = warnings(
+            "MODULE T;
+             PROCEDURE G(i: INTEGER);
+             VAR x: INTEGER;
+             BEGIN CASE i OF 0: ASSERT(FALSE) | 1: ASSERT(FALSE) END; x := 0
+             END G;
+             END T.",
+            WarningOptions::all(),
+        );
+        assert_eq!(aborted.len(), 1, "{:?}", aborted);
+        assert_eq!(aborted[0].kind, WarningKind::Unreachable);
+
+        let partial = warnings(
+            "MODULE T;
+             PROCEDURE H(i: INTEGER): INTEGER;
+             VAR x: INTEGER;
+             BEGIN CASE i OF 0: x := 1 | 1: ASSERT(FALSE) ELSE END
+             RETURN x
+             END H;
+             END T.",
+            WarningOptions::all(),
+        );
+        assert_eq!(partial.len(), 1, "{:?}", partial);
+        assert_eq!(partial[0].kind, WarningKind::UninitializedResult);
+    }
+
+    #[test]
+    fn test_library_module() {
+        let module = check(
+            "MODULE T; IMPORT O := Out; VAR x: REAL;
+             BEGIN O.String(\"x = \"); O.Real(1.0, 8); O.Char(\"!\"); O.Ln END T.",
+        )
+        .unwrap();
+        let procedures: Vec<LibraryProcedure> = module
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                hir::Statement::LibraryCall { procedure, .. } => Some(*procedure),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            procedures,
+            [
+                LibraryProcedure::OutString,
+                LibraryProcedure::OutReal,
+                LibraryProcedure::OutChar,
+                LibraryProcedure::OutLn
+            ]
+        );
+
+        let errors = check("MODULE T; IMPORT Out; BEGIN Out.Int(\"1\", 0) END T.").unwrap_err();
+        assert_eq!(errors[0], "Ungültige Argumente für Out.Int: (STRING, INTEGER)");
+        let errors = check("MODULE T; IMPORT Out; VAR i: INTEGER; BEGIN i := Out.Int(1, 0) END T.").unwrap_err();
+        assert_eq!(errors[0], "Out.Int hat keinen Rückgabewert");
+    }
+
+    #[test]
+    fn test_imported_interface() {
+        let shapes = check(
+            "MODULE Shapes;
+             CONST Size* = 3;
+             TYPE Point* = RECORD x*, y*: INTEGER END; Ptr* = POINTER TO Point;
+             VAR origin*: Point; hidden: INTEGER;
+             PROCEDURE Move*(VAR p: Point; dx: INTEGER); BEGIN p.x := p.x + dx END Move;
+             END Shapes.",
+        )
+        .unwrap();
+        let check_with_shapes = |source: &str| {
+            let mut checker = TypeChecker::new();
+            checker.add_interface(&shapes);
+            checker.check_module(&parse(source))
+        };
+
+        // Zeiger- und Recordtyp bleiben über beide Namen derselbe Typ
+        let module = check_with_shapes(
+            "MODULE T; IMPORT S := Shapes;
+             VAR p: S.Point; q: S.Ptr; n: INTEGER;
+             BEGIN p := S.origin; S.Move(p, S.Size); NEW(q); q^ := p; n := q^.x END T.",
+        )
+        .unwrap();
+        let origin = match &assignment_value(&module, 0).kind {
+            ExprKind::Designator(designator) => &designator.symbol,
+            other => panic!("Bezeichner erwartet, gefunden {:?}", other),
+        };
+        assert_eq!(origin.qualified_name, "Shapes.origin");
+        assert_eq!(origin.module.as_deref(), Some("Shapes"));
+        assert_eq!(module.types.display(module.declarations.variables[0].var_type), "Shapes.Point");
+        let imported: Vec<&str> = module.imported_variables.iter().map(|variable| variable.name.as_str()).collect();
+        assert_eq!(imported, ["Shapes.origin"]);
+
+        let errors = check_with_shapes("MODULE T; IMPORT Shapes; VAR n: INTEGER; BEGIN n := Shapes.hidden END T.").unwrap_err();
+        assert_eq!(errors[0], "Modul Shapes exportiert hidden nicht");
+        let errors = check_with_shapes("MODULE T; IMPORT S := Shapes; VAR v: S.Size; END T.").unwrap_err();
+        assert_eq!(errors[0], "'S.Size' ist kein Typ");
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+
+// Typ-Arena: jeder Typkonstruktor (ARRAY, RECORD, POINTER, PROCEDURE) im
+// Quelltext erzeugt einen eigenen Eintrag, Typen werden über ihre TypeId
+// verglichen (Namensäquivalenz, Oberon-07 Report, Kapitel 6).
+// Die Grundtypen liegen an festen Positionen und sind damit eindeutig.
+
+// Längste Array-Dimension und größte Elementzahl eines Arrays: Größen und
+// Indizes passen so in die 32-Bit-Rechnungen der Backends
+pub const MAX_ARRAY_LENGTH: usize = i32::MAX as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TypeId(u32);
+
+impl TypeId {
+    pub const INTEGER: TypeId = TypeId(0);
+    pub const REAL: TypeId = TypeId(1);
+    pub const BOOLEAN: TypeId = TypeId(2);
+    pub const CHAR: TypeId = TypeId(3);
+    pub const SET: TypeId = TypeId(4);
+    // Typ von String-Konstanten
+    pub const STRING: TypeId = TypeId(5);
+    pub const NIL: TypeId = TypeId(6);
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn is_numeric(self) -> bool {
+        self == TypeId::INTEGER || self == TypeId::REAL
+    }
+
+    pub fn is_integer(self) -> bool {
+        self == TypeId::INTEGER
+    }
+
+    pub fn is_boolean(self) -> bool {
+        self == TypeId::BOOLEAN
+    }
+
+    pub fn is_comparable(self) -> bool {
+        matches!(
+            self,
+            TypeId::INTEGER | TypeId::REAL | TypeId::BOOLEAN | TypeId::CHAR | TypeId::STRING
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    Integer,
+    Real,
+    Boolean,
+    Char,
+    Set,
+    String,
+    Nil,
+    // Mehrdimensionale Arrays sind geschachtelt: ARRAY 2, 3 OF T = ARRAY 2 OF ARRAY 3 OF T
+    Array {
+        length: usize,
+        element: TypeId,
+    },
+    // Felder in Deklarationsreihenfolge, ohne die des Basistyps
+    Record {
+        base: Option<TypeId>,
+        fields: Vec<Field>,
+    },
+    Pointer {
+        target: TypeId,
+    },
+    Procedure {
+        params: Vec<Parameter>,
+        result: Option<TypeId>,
+    },
+    // Typ aus einem importierten Modul ohne Schnittstelle (TypeChecker::add_interface)
+    Imported(String),
+    // Platzhalter für einen Zeiger-Basistyp, der später im selben TYPE-Abschnitt
+    // deklariert wird; nach der Deklaration verweist kein Zeiger mehr darauf
+    Forward(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub ty: TypeId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub param_type: TypeId,
+    pub is_var: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeInfo {
+    pub kind: TypeKind,
+    // Name aus der Typdeklaration, die den Typ eingeführt hat
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeArena {
+    types: Vec<TypeInfo>,
+    imported: HashMap<String, TypeId>,
+}
+
+impl TypeArena {
+    pub fn new() -> Self {
+        let mut arena = TypeArena {
+            types: Vec::new(),
+            imported: HashMap::new(),
+        };
+
+        // Reihenfolge entspricht den Konstanten in TypeId
+        for kind in [
+            TypeKind::Integer,
+            TypeKind::Real,
+            TypeKind::Boolean,
+            TypeKind::Char,
+            TypeKind::Set,
+            TypeKind::String,
+            TypeKind::Nil,
+        ] {
+            arena.add(kind);
+        }
+        arena
+    }
+
+    pub fn add(&mut self, kind: TypeKind) -> TypeId {
+        let id = TypeId(self.types.len() as u32);
+        self.types.push(TypeInfo { kind, name: None });
+        id
+    }
+
+    // Importierte Typen werden über ihren qualifizierten Namen interniert
+    pub fn imported(&mut self, qualified_name: &str) -> TypeId {
+        if let Some(&id) = self.imported.get(qualified_name) {
+            return id;
+        }
+        let id = self.add(TypeKind::Imported(qualified_name.to_string()));
+        self.imported.insert(qualified_name.to_string(), id);
+        id
+    }
+
+    // Kopiert den Typ `id` aus der Arena des Moduls `module` (Schnittstelle
+    // eines Imports). Kopien werden über den qualifizierten Namen interniert,
+    // damit derselbe Typ über verschiedene Importwege derselbe bleibt;
+    // namenlose über Modul und Position. Rekursive Records über Zeiger
+    // finden ihre Kopie, bevor sie vollständig ist.
+    pub fn import(&mut self, module: &str, source: &TypeArena, id: TypeId) -> TypeId {
+        if id.index() <= TypeId::NIL.index() {
+            return id;
+        }
+        let name = source.name(id).map(|name| match name.contains('.') {
+            true => name.to_string(),
+            false => format!("{}.{}", module, name),
+        });
+        let key = name.clone().unwrap_or_else(|| format!("{}#{}", module, id.0));
+        if let Some(&copy) = self.imported.get(&key) {
+            return copy;
+        }
+
+        let copy = self.add(TypeKind::Imported(key.clone()));
+        self.imported.insert(key, copy);
+        self.types[copy.index()].name = name;
+        let mut import = |ty: TypeId| self.import(module, source, ty);
+        let kind = match source.kind(id).clone() {
+            TypeKind::Array { length, element } => TypeKind::Array { length, element: import(element) },
+            TypeKind::Record { base, fields } => TypeKind::Record {
+                base: base.map(&mut import),
+                fields: fields.into_iter().map(|field| Field { ty: import(field.ty), ..field }).collect(),
+            },
+            TypeKind::Pointer { target } => TypeKind::Pointer { target: import(target) },
+            TypeKind::Procedure { params, result } => TypeKind::Procedure {
+                params: params.into_iter().map(|param| Parameter { param_type: import(param.param_type), ..param }).collect(),
+                result: result.map(import),
+            },
+            kind => kind,
+        };
+        self.types[copy.index()].kind = kind;
+        copy
+    }
+
+    pub fn get(&self, id: TypeId) -> &TypeInfo {
+        &self.types[id.index()]
+    }
+
+    pub fn kind(&self, id: TypeId) -> &TypeKind {
+        &self.get(id).kind
+    }
+
+    pub fn name(&self, id: TypeId) -> Option<&str> {
+        self.get(id).name.as_deref()
+    }
+
+    // Nur der erste Name zählt: bei TYPE B = A bleibt A der Name des Typs
+    pub fn set_name(&mut self, id: TypeId, name: &str) {
+        let info = &mut self.types[id.index()];
+        if info.name.is_none() {
+            info.name = Some(name.to_string());
+        }
+    }
+
+    // Lesbare Darstellung für Fehlermeldungen
+    pub fn display(&self, id: TypeId) -> String {
+        if let Some(name) = self.name(id) {
+            return name.to_string();
+        }
+
+        match self.kind(id) {
+            TypeKind::Integer => "INTEGER".to_string(),
+            TypeKind::Real => "REAL".to_string(),
+            TypeKind::Boolean => "BOOLEAN".to_string(),
+            TypeKind::Char => "CHAR".to_string(),
+            TypeKind::Set => "SET".to_string(),
+            TypeKind::String => "STRING".to_string(),
+            TypeKind::Nil => "NIL".to_string(),
+            TypeKind::Array { length, element } => {
+                format!("ARRAY {} OF {}", length, self.display(*element))
+            }
+            TypeKind::Record { .. } => "RECORD".to_string(),
+            TypeKind::Pointer { target } => format!("POINTER TO {}", self.display(*target)),
+            TypeKind::Procedure { .. } => "PROCEDURE".to_string(),
+            TypeKind::Imported(name) | TypeKind::Forward(name) => name.clone(),
+        }
+    }
+
+    // Ersetzt den Platzhalter `forward` in allen Zeigertypen durch den deklarierten Typ
+    pub fn resolve_forward(&mut self, forward: TypeId, declared: TypeId) {
+        for info in &mut self.types {
+            if let TypeKind::Pointer { target } = &mut info.kind {
+                if *target == forward {
+                    *target = declared;
+                }
+            }
+        }
+    }
+
+    // ========================================================================
+    // Arrays
+    // ========================================================================
+
+    // Anzahl der Elemente über alle Dimensionen; None bei Überlauf
+    pub fn element_count(&self, array: TypeId) -> Option<usize> {
+        match self.kind(array) {
+            TypeKind::Array { length, element } => length.checked_mul(self.element_count(*element)?),
+            _ => Some(1),
+        }
+    }
+
+    // ========================================================================
+    // Records
+    // ========================================================================
+
+    // Alle Felder eines Records einschließlich geerbter, Basistyp zuerst
+    pub fn fields(&self, record: TypeId) -> Vec<&Field> {
+        match self.kind(record) {
+            TypeKind::Record { base, fields } => {
+                let mut all = base.map(|base| self.fields(base)).unwrap_or_default();
+                all.extend(fields.iter());
+                all
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Feld samt Position in fields()
+    pub fn field(&self, record: TypeId, name: &str) -> Option<(usize, &Field)> {
+        self.fields(record)
+            .into_iter()
+            .enumerate()
+            .find(|(_, field)| field.name == name)
+    }
+
+    // ========================================================================
+    // Typregeln (Oberon-07 Report, Kapitel 6 und 9.1)
+    // ========================================================================
+
+    // Gleicher Typ: dieselbe Typbezeichnung bzw. derselbe Typkonstruktor
+    pub fn same_type(&self, a: TypeId, b: TypeId) -> bool {
+        a == b
+    }
+
+    // Gleiche Typen: gleicher Typ oder Prozedurtypen mit passenden Parameterlisten
+    pub fn equal_types(&self, a: TypeId, b: TypeId) -> bool {
+        if self.same_type(a, b) {
+            return true;
+        }
+
+        match (self.kind(a), self.kind(b)) {
+            (
+                TypeKind::Procedure { params: params_a, result: result_a },
+                TypeKind::Procedure { params: params_b, result: result_b },
+            ) => {
+                result_a == result_b
+                    && params_a.len() == params_b.len()
+                    && params_a.iter().zip(params_b).all(|(pa, pb)| {
+                        pa.is_var == pb.is_var && self.equal_types(pa.param_type, pb.param_type)
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    // Typerweiterung: Records über die Basistyp-Kette, Zeiger über ihre Zieltypen.
+    // Jeder Typ ist Erweiterung von sich selbst.
+    pub fn is_extension(&self, extension: TypeId, base: TypeId) -> bool {
+        if self.same_type(extension, base) {
+            return true;
+        }
+
+        match (self.kind(extension), self.kind(base)) {
+            (TypeKind::Record { base: Some(ext_base), .. }, TypeKind::Record { .. }) => {
+                self.is_extension(*ext_base, base)
+            }
+            (TypeKind::Pointer { target: ext_target }, TypeKind::Pointer { target: base_target }) => {
+                self.is_extension(*ext_target, *base_target)
+            }
+            _ => false,
+        }
+    }
+
+    // Zuweisungskompatibilität eines Werts vom Typ `source` an eine Variable vom
+    // Typ `target`. Numerische Typen werden nie implizit umgewandelt (FLT,
+    // FLOOR); String-Konstanten prüft is_string_assignable.
+    pub fn is_assignable(&self, source: TypeId, target: TypeId) -> bool {
+        if self.same_type(source, target) {
+            return true;
+        }
+
+        match (self.kind(source), self.kind(target)) {
+            (TypeKind::Record { .. }, TypeKind::Record { .. })
+            | (TypeKind::Pointer { .. }, TypeKind::Pointer { .. }) => self.is_extension(source, target),
+            (TypeKind::Nil, TypeKind::Pointer { .. } | TypeKind::Procedure { .. }) => true,
+            (TypeKind::Procedure { .. }, TypeKind::Procedure { .. }) => self.equal_types(source, target),
+            // Arrays gleicher Länge mit gleichem Elementtyp, auch wenn sie
+            // getrennt deklariert sind
+            (
+                TypeKind::Array { length: length_s, element: element_s },
+                TypeKind::Array { length: length_t, element: element_t },
+            ) => {
+                length_s == length_t
+                    && (self.same_type(*element_s, *element_t)
+                        || matches!(self.kind(*element_s), TypeKind::Array { .. })
+                            && self.is_assignable(*element_s, *element_t))
+            }
+            _ => false,
+        }
+    }
+
+    // Zeichenkette im Sinn von 8.2.4: String-Konstante oder ARRAY n OF CHAR
+    pub fn is_text(&self, ty: TypeId) -> bool {
+        match self.kind(ty) {
+            TypeKind::String => true,
+            TypeKind::Array { element, .. } => *element == TypeId::CHAR,
+            _ => false,
+        }
+    }
+
+    // String-Konstante mit `length` Zeichen: an CHAR, wenn sie genau ein
+    // Zeichen hat, an ARRAY n OF CHAR, wenn noch das abschließende 0X passt
+    pub fn is_string_assignable(&self, length: usize, target: TypeId) -> bool {
+        match self.kind(target) {
+            TypeKind::String => true,
+            TypeKind::Char => length == 1,
+            TypeKind::Array { length: n, element } => *element == TypeId::CHAR && length < *n,
+            _ => false,
+        }
+    }
+
+    // Vergleich mit = und #: Zeiger- und Prozedurwerte, wenn einer dem anderen
+    // zuweisbar ist (z.B. p = NIL)
+    pub fn is_reference_comparable(&self, a: TypeId, b: TypeId) -> bool {
+        let is_reference = |id: TypeId| {
+            matches!(
+                self.kind(id),
+                TypeKind::Pointer { .. } | TypeKind::Procedure { .. } | TypeKind::Nil
+            )
+        };
+        is_reference(a) && is_reference(b) && (self.is_assignable(a, b) || self.is_assignable(b, a))
+    }
+}
+
+impl Default for TypeArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array(types: &mut TypeArena, length: usize, element: TypeId) -> TypeId {
+        types.add(TypeKind::Array { length, element })
+    }
+
+    #[test]
+    fn test_string_assignment() {
+        let mut types = TypeArena::new();
+        let text = array(&mut types, 16, TypeId::CHAR);
+        let short = array(&mut types, 2, TypeId::CHAR);
+        let numbers = array(&mut types, 16, TypeId::INTEGER);
+
+        assert!(types.is_string_assignable(2, text));
+        assert!(types.is_string_assignable(1, short));
+        // Kein Platz für 0X
+        assert!(!types.is_string_assignable(2, short));
+        assert!(!types.is_string_assignable(2, numbers));
+
+        assert!(types.is_string_assignable(1, TypeId::CHAR));
+        assert!(!types.is_string_assignable(2, TypeId::CHAR));
+        assert!(!types.is_string_assignable(0, TypeId::CHAR));
+        assert!(!types.is_string_assignable(1, TypeId::INTEGER));
+    }
+
+    #[test]
+    fn test_no_numeric_conversion() {
+        let types = TypeArena::new();
+        assert!(types.is_assignable(TypeId::INTEGER, TypeId::INTEGER));
+        assert!(types.is_assignable(TypeId::REAL, TypeId::REAL));
+        assert!(!types.is_assignable(TypeId::INTEGER, TypeId::REAL));
+        assert!(!types.is_assignable(TypeId::REAL, TypeId::INTEGER));
+        assert!(!types.is_assignable(TypeId::CHAR, TypeId::INTEGER));
+    }
+
+    #[test]
+    fn test_extension_assignment() {
+        let mut types = TypeArena::new();
+        let base = types.add(TypeKind::Record { base: None, fields: Vec::new() });
+        let ext = types.add(TypeKind::Record { base: Some(base), fields: Vec::new() });
+        let base_pointer = types.add(TypeKind::Pointer { target: base });
+        let ext_pointer = types.add(TypeKind::Pointer { target: ext });
+
+        assert!(types.is_assignable(ext, base));
+        assert!(!types.is_assignable(base, ext));
+        assert!(types.is_assignable(ext_pointer, base_pointer));
+        assert!(types.is_assignable(TypeId::NIL, ext_pointer));
+        assert!(!types.is_assignable(base_pointer, ext_pointer));
+    }
+
+    #[test]
+    fn test_array_assignment() {
+        let mut types = TypeArena::new();
+        let text = array(&mut types, 16, TypeId::CHAR);
+        let other = array(&mut types, 16, TypeId::CHAR);
+        let short = array(&mut types, 8, TypeId::CHAR);
+        let matrix = array(&mut types, 2, text);
+        let other_matrix = array(&mut types, 2, other);
+
+        assert!(types.is_assignable(text, other));
+        assert!(types.is_assignable(matrix, other_matrix));
+        assert!(!types.is_assignable(short, text));
+        assert!(!types.is_assignable(text, short));
+
+        assert!(types.is_text(text));
+        assert!(types.is_text(TypeId::STRING));
+        assert!(!types.is_text(matrix));
+    }
+}
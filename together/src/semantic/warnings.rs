@@ -0,0 +1,113 @@
+use std::fmt;
+
+// Warnungen der semantischen Analyse. Anders als Fehler verhindern sie die
+// Übersetzung nicht; jede Kategorie lässt sich einzeln abschalten.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    // Lokale Variable wird möglicherweise vor der ersten Zuweisung gelesen
+    UninitializedRead,
+    // Lokale Variable, Parameter, Konstante, Typ oder Import wird nie benutzt
+    Unused,
+    // Anweisungen nach einem unbedingten Abbruch (ASSERT(FALSE))
+    Unreachable,
+    // RETURN einer Funktionsprozedur liefert möglicherweise einen nicht initialisierten Wert
+    UninitializedResult,
+}
+
+impl WarningKind {
+    pub const ALL: [WarningKind; 4] = [
+        WarningKind::UninitializedRead,
+        WarningKind::Unused,
+        WarningKind::Unreachable,
+        WarningKind::UninitializedResult,
+    ];
+
+    // Name für Schalter und Ausgabe
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningKind::UninitializedRead => "uninitialized",
+            WarningKind::Unused => "unused",
+            WarningKind::Unreachable => "unreachable",
+            WarningKind::UninitializedResult => "uninitialized-result",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<WarningKind> {
+        WarningKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+    // Position wie bei den Fehlern, 1-basiert (0, wenn nicht bekannt)
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, message: String, (line, column): (usize, usize)) -> Self {
+        Warning { kind, message, line, column }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Warnung: {} [{}]", self.message, self.kind.name())
+    }
+}
+
+// Ein Schalter pro Kategorie, standardmäßig alle eingeschaltet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarningOptions {
+    pub uninitialized_read: bool,
+    pub unused: bool,
+    pub unreachable: bool,
+    pub uninitialized_result: bool,
+}
+
+impl WarningOptions {
+    pub fn all() -> Self {
+        WarningOptions {
+            uninitialized_read: true,
+            unused: true,
+            unreachable: true,
+            uninitialized_result: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        WarningOptions {
+            uninitialized_read: false,
+            unused: false,
+            unreachable: false,
+            uninitialized_result: false,
+        }
+    }
+
+    pub fn is_enabled(&self, kind: WarningKind) -> bool {
+        match kind {
+            WarningKind::UninitializedRead => self.uninitialized_read,
+            WarningKind::Unused => self.unused,
+            WarningKind::Unreachable => self.unreachable,
+            WarningKind::UninitializedResult => self.uninitialized_result,
+        }
+    }
+
+    pub fn set(&mut self, kind: WarningKind, enabled: bool) {
+        match kind {
+            WarningKind::UninitializedRead => self.uninitialized_read = enabled,
+            WarningKind::Unused => self.unused = enabled,
+            WarningKind::Unreachable => self.unreachable = enabled,
+            WarningKind::UninitializedResult => self.uninitialized_result = enabled,
+        }
+    }
+}
+
+impl Default for WarningOptions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
@@ -0,0 +1,23 @@
+use crate::parser::ast::Module;
+use crate::parser::Parser;
+use crate::scanner::Lexer;
+use crate::semantic::hir;
+use crate::semantic::TypeChecker;
+
+// Gemeinsame Hilfsfunktionen der Tests: Scanner, Parser und Typprüfung
+// eines Quelltexts. Fehler beim Scannen und Parsen lassen den Test
+// scheitern.
+
+pub fn parse(source: &str) -> Module {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    Parser::new(tokens).parse().unwrap()
+}
+
+pub fn check(source: &str) -> Result<hir::Module, Vec<String>> {
+    TypeChecker::new().check_module(&parse(source))
+}
+
+// Geprüftes Modul eines fehlerfreien Quelltexts
+pub fn checked(source: &str) -> hir::Module {
+    check(source).unwrap()
+}
@@ -0,0 +1,96 @@
+use crate::bytecode::{self, BytecodeModule};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Modullader
+// ============================================================================
+//
+// Sucht importierte Module als <Name>.obc in den Suchpfaden, in der
+// Reihenfolge, in der die Pfade hinzugefügt wurden. Das Ergebnis ist die
+// Initialisierungsreihenfolge für Vm::load: jedes Modul nach allen, die es
+// importiert, und jedes nur einmal.
+
+pub struct Loader {
+    search_path: Vec<PathBuf>,
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Loader { search_path: Vec::new() }
+    }
+
+    pub fn add_path(&mut self, dir: impl Into<PathBuf>) {
+        self.search_path.push(dir.into());
+    }
+
+    // Lädt die Datei und alle Module, die sie (mittelbar) importiert
+    pub fn load_file(&self, path: &Path) -> Result<Vec<BytecodeModule>, String> {
+        let module = read(path)?;
+        self.resolve(module)
+    }
+
+    // Lädt das Modul `name` aus den Suchpfaden samt seiner Importe
+    pub fn load(&self, name: &str) -> Result<Vec<BytecodeModule>, String> {
+        let module = self.find(name)?;
+        self.resolve(module)
+    }
+
+    fn resolve(&self, module: BytecodeModule) -> Result<Vec<BytecodeModule>, String> {
+        let mut order = Vec::new();
+        let mut active = Vec::new();
+        self.visit(module, &mut order, &mut active)?;
+        Ok(order)
+    }
+
+    // Tiefensuche; `active` ist die Importkette zum aktuellen Modul
+    fn visit(
+        &self,
+        module: BytecodeModule,
+        order: &mut Vec<BytecodeModule>,
+        active: &mut Vec<String>,
+    ) -> Result<(), String> {
+        active.push(module.name.clone());
+        for import in &module.imports {
+            if active.contains(import) {
+                return Err(format!("Zyklischer Import: {} -> {}", active.join(" -> "), import));
+            }
+            if order.iter().any(|loaded| &loaded.name == import) {
+                continue;
+            }
+            let imported = self.find(import)?;
+            self.visit(imported, order, active)?;
+        }
+        active.pop();
+        order.push(module);
+        Ok(())
+    }
+
+    fn find(&self, name: &str) -> Result<BytecodeModule, String> {
+        let file = format!("{}.{}", name, bytecode::EXTENSION);
+        let path = match self.search_path.iter().map(|dir| dir.join(&file)).find(|path| path.is_file()) {
+            Some(path) => path,
+            None => {
+                let dirs: Vec<String> = self.search_path.iter().map(|dir| dir.display().to_string()).collect();
+                return Err(format!("Modul {} nicht gefunden (gesucht in: {})", name, dirs.join(", ")));
+            }
+        };
+
+        let module = read(&path)?;
+        if module.name != name {
+            return Err(format!("{}: enthält Modul {} statt {}", path.display(), module.name, name));
+        }
+        Ok(module)
+    }
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read(path: &Path) -> Result<BytecodeModule, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: Kann Datei nicht lesen: {}", path.display(), e))?;
+    bytecode::decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,1072 @@
+use crate::bytecode::{BytecodeModule, Function, Instruction, TrapKind, TypeDescKind};
+use crate::interpreter::Trap;
+use crate::semantic::const_eval::{floor_div, floor_mod, SET_MAX_ELEMENT};
+use crate::semantic::library::{write_padded, LibraryProcedure};
+use std::cmp::Ordering;
+use std::io::Write;
+use std::rc::Rc;
+
+mod loader;
+mod value;
+
+pub use loader::Loader;
+pub use value::{Address, Base, Value};
+use value::{assign, element, element_mut};
+
+// ============================================================================
+// Stack-VM
+// ============================================================================
+//
+// Führt Bytecode-Module aus (crate::bytecode). Module werden nacheinander
+// geladen, importierte zuerst; beim Laden läuft der Modulrumpf. Danach kann
+// der Host exportierte Prozeduren aufrufen und exportierte Variablen lesen,
+// etwa um ein Modul als Plugin zu benutzen. Verweise auf importierte Module
+// (CallExtern, ExternGlobalAddr, PushExternProc) bindet load an die geladenen
+// Module und prüft dabei, dass ihr Ziel existiert und exportiert ist.
+//
+// Aufrufe benutzen keinen Rust-Stapel: Rahmen liegen in `frames`, ihre
+// Parameter und lokalen Variablen hintereinander in `slots`. Laufzeitfehler
+// melden sich mit demselben Trap wie im Interpreter. Mit set_step_limit
+// endet auch fremder Bytecode, etwa mit verfälschten Sprungzielen, nach
+// einer festen Zahl von Befehlen mit einem Trap.
+
+// Höchste Aufruftiefe, bevor ein Stapelüberlauf gemeldet wird
+pub const MAX_FRAMES: usize = 100_000;
+
+type Exec<T> = Result<T, Trap>;
+
+struct Frame {
+    module: usize,
+    // None: Modulrumpf
+    function: Option<u32>,
+    // Nächster Befehl
+    pc: usize,
+    // Erster Platz in `slots`
+    base: usize,
+}
+
+pub struct Vm<'a> {
+    modules: Vec<Rc<BytecodeModule>>,
+    // Je geladenem Modul: Index des geladenen Moduls zu jedem Import
+    links: Vec<Vec<usize>>,
+    // Globale Variablen je geladenem Modul
+    globals: Vec<Vec<Value>>,
+    slots: Vec<Value>,
+    heap: Vec<Value>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    // Noch erlaubte Befehle über alle Ausführungen; None = unbegrenzt
+    steps: Option<u64>,
+    out: &'a mut dyn Write,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(out: &'a mut dyn Write) -> Self {
+        Vm {
+            modules: Vec::new(),
+            links: Vec::new(),
+            globals: Vec::new(),
+            slots: Vec::new(),
+            heap: Vec::new(),
+            stack: Vec::new(),
+            frames: Vec::new(),
+            steps: None,
+            out,
+        }
+    }
+
+    // Höchstens `limit` weitere Befehle; danach endet die laufende
+    // Ausführung mit einem Trap
+    pub fn set_step_limit(&mut self, limit: u64) {
+        self.steps = Some(limit);
+    }
+
+    // Lädt ein Modul, dessen Importe bereits geladen sind, und führt seinen
+    // Rumpf aus. Bei einem Trap im Rumpf bleibt das Modul ungeladen.
+    pub fn load(&mut self, module: BytecodeModule) -> Result<(), Trap> {
+        let refuse = |message: String| Trap { message, line: 0, procedure: module.name.clone() };
+        if self.module_index(&module.name).is_some() {
+            return Err(refuse(format!("Modul {} ist bereits geladen", module.name)));
+        }
+        let mut links = Vec::new();
+        for import in &module.imports {
+            match self.module_index(import) {
+                Some(index) => links.push(index),
+                None => return Err(refuse(format!("Importiertes Modul {} ist nicht geladen", import))),
+            }
+        }
+        self.link(&module, &links).map_err(refuse)?;
+
+        let globals = module
+            .globals
+            .iter()
+            .map(|global| Value::default_for(&module.types, global.ty))
+            .collect();
+        self.modules.push(Rc::new(module));
+        self.links.push(links);
+        self.globals.push(globals);
+
+        let index = self.modules.len() - 1;
+        if let Err(trap) = self.execute(index, None, Vec::new()) {
+            self.modules.pop();
+            self.links.pop();
+            self.globals.pop();
+            return Err(trap);
+        }
+        Ok(())
+    }
+
+    // Verweise auf importierte Module müssen exportierte Prozeduren und
+    // Variablen treffen; danach braucht die Ausführung keine Prüfung mehr
+    fn link(&self, module: &BytecodeModule, links: &[usize]) -> Result<(), String> {
+        for function in module.functions.iter().chain([&module.body]) {
+            for instruction in &function.code {
+                let (import, index, procedure) = match instruction {
+                    Instruction::PushExternProc { module, function } | Instruction::CallExtern { module, function } => {
+                        (*module as usize, *function as usize, true)
+                    }
+                    Instruction::ExternGlobalAddr { module, slot } => (*module as usize, *slot as usize, false),
+                    _ => continue,
+                };
+                let imported = &self.modules[links[import]];
+                let exported = match procedure {
+                    true => imported.functions.get(index).is_some_and(|function| function.exported),
+                    false => imported.globals.get(index).is_some_and(|global| global.exported),
+                };
+                if !exported {
+                    let what = if procedure { "Prozedur" } else { "Variable" };
+                    return Err(format!(
+                        "{}: Modul {} exportiert keine {} mit Index {}",
+                        function.name, imported.name, what, index
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_loaded(&self, module: &str) -> bool {
+        self.module_index(module).is_some()
+    }
+
+    // Ruft eine exportierte Prozedur eines geladenen Moduls auf; liefert den
+    // Rückgabewert bei Funktionsprozeduren
+    pub fn call(&mut self, module: &str, procedure: &str, arguments: Vec<Value>) -> Result<Option<Value>, Trap> {
+        let refuse = |message: String| Trap { message, line: 0, procedure: format!("{}.{}", module, procedure) };
+        let index = self
+            .module_index(module)
+            .ok_or_else(|| refuse(format!("Modul {} ist nicht geladen", module)))?;
+        let code = Rc::clone(&self.modules[index]);
+        let function = code
+            .functions
+            .iter()
+            .position(|function| function.exported && function.name == procedure)
+            .ok_or_else(|| refuse(format!("{}.{} ist keine exportierte Prozedur", module, procedure)))?;
+
+        let params = &code.functions[function].params;
+        if params.iter().any(|&is_var| is_var) {
+            return Err(refuse("VAR-Parameter können nicht vom Host übergeben werden".to_string()));
+        }
+        if params.len() != arguments.len() {
+            return Err(refuse(format!("{} Argumente erwartet, {} übergeben", params.len(), arguments.len())));
+        }
+        self.execute(index, Some(function as u32), arguments)
+    }
+
+    // Wert einer exportierten globalen Variablen
+    pub fn global(&self, module: &str, name: &str) -> Option<&Value> {
+        let index = self.module_index(module)?;
+        let slot = self.modules[index]
+            .globals
+            .iter()
+            .position(|global| global.exported && global.name == name)?;
+        self.globals[index].get(slot)
+    }
+
+    fn module_index(&self, name: &str) -> Option<usize> {
+        self.modules.iter().position(|module| module.name == name)
+    }
+
+    // Führt eine Prozedur bzw. einen Modulrumpf bis zu seinem RETURN aus
+    fn execute(&mut self, module: usize, function: Option<u32>, arguments: Vec<Value>) -> Exec<Option<Value>> {
+        let (depth, stack, slots) = (self.frames.len(), self.stack.len(), self.slots.len());
+        let result = self.enter(module, function, arguments).and_then(|_| self.run(depth));
+        if result.is_err() {
+            self.frames.truncate(depth);
+            self.stack.truncate(stack);
+            self.slots.truncate(slots);
+        }
+
+        let flushed = self.out.flush();
+        let value = result?;
+        flushed.map_err(|e| Trap {
+            message: format!("Ausgabe fehlgeschlagen: {}", e),
+            line: 0,
+            procedure: self.modules[module].name.clone(),
+        })?;
+        Ok(value)
+    }
+
+    // ========================================================================
+    // Traps
+    // ========================================================================
+
+    // Trap an der Stelle des zuletzt gelesenen Befehls
+    fn trap(&self, message: impl Into<String>) -> Trap {
+        let (line, procedure) = match self.frames.last() {
+            Some(frame) => {
+                let module = &self.modules[frame.module];
+                let function = function(module, frame.function);
+                let line = function.line(frame.pc.saturating_sub(1)) as usize;
+                // Prozeduren importierter Module tragen den Modulnamen
+                match self.frames.first() {
+                    Some(first) if first.module != frame.module => {
+                        (line, format!("{}.{}", module.name, function.name))
+                    }
+                    _ => (line, function.name.clone()),
+                }
+            }
+            None => (0, String::new()),
+        };
+        Trap { message: message.into(), line, procedure }
+    }
+
+    fn overflow(&self, op: &str) -> Trap {
+        self.trap(format!("Überlauf bei '{}'", op))
+    }
+
+    // Nach Typprüfung und Validierung unmöglich; deutet auf fehlerhaften Bytecode
+    fn internal(&self, what: &str) -> Trap {
+        self.trap(format!("Interner Fehler: {}", what))
+    }
+
+    // ========================================================================
+    // Stapel und Speicher
+    // ========================================================================
+
+    fn pop(&mut self) -> Exec<Value> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None => Err(self.internal("Operandenstapel leer")),
+        }
+    }
+
+    fn pop_integer(&mut self) -> Exec<i64> {
+        match self.pop()? {
+            Value::Integer(value) => Ok(value),
+            _ => Err(self.internal("INTEGER erwartet")),
+        }
+    }
+
+    fn pop_boolean(&mut self) -> Exec<bool> {
+        match self.pop()? {
+            Value::Boolean(value) => Ok(value),
+            _ => Err(self.internal("BOOLEAN erwartet")),
+        }
+    }
+
+    fn pop_address(&mut self) -> Exec<Address> {
+        match self.pop()? {
+            Value::Address(address) => Ok(address),
+            _ => Err(self.internal("Adresse erwartet")),
+        }
+    }
+
+    fn pop_set(&mut self) -> Exec<u32> {
+        match self.pop()? {
+            Value::Set(bits) => Ok(bits),
+            _ => Err(self.internal("SET erwartet")),
+        }
+    }
+
+    // Oberste `count` Werte in Stapelreihenfolge
+    fn pop_many(&mut self, count: usize) -> Exec<Vec<Value>> {
+        match self.stack.len().checked_sub(count) {
+            Some(start) => Ok(self.stack.split_off(start)),
+            None => Err(self.internal("Operandenstapel leer")),
+        }
+    }
+
+    fn value_at(&self, address: &Address) -> Exec<&Value> {
+        let root = match address.base {
+            Base::Global { module, slot } => self.globals.get(module).and_then(|globals| globals.get(slot)),
+            Base::Local(slot) => self.slots.get(slot),
+            Base::Heap(index) => self.heap.get(index),
+        };
+        match root.and_then(|root| element(root, &address.path)) {
+            Some(value) => Ok(value),
+            None => Err(self.internal("ungültige Adresse")),
+        }
+    }
+
+    fn store(&mut self, address: &Address, value: Value) -> Exec<()> {
+        let root = match address.base {
+            Base::Global { module, slot } => self.globals.get_mut(module).and_then(|globals| globals.get_mut(slot)),
+            Base::Local(slot) => self.slots.get_mut(slot),
+            Base::Heap(index) => self.heap.get_mut(index),
+        };
+        match root.and_then(|root| element_mut(root, &address.path)) {
+            Some(slot) => {
+                assign(slot, value);
+                Ok(())
+            }
+            None => Err(self.internal("ungültige Adresse")),
+        }
+    }
+
+    // ========================================================================
+    // Aufrufe
+    // ========================================================================
+
+    fn enter(&mut self, module: usize, function_index: Option<u32>, arguments: Vec<Value>) -> Exec<()> {
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(self.trap(format!("Stapelüberlauf: mehr als {} geschachtelte Aufrufe", MAX_FRAMES)));
+        }
+
+        let code = Rc::clone(&self.modules[module]);
+        let base = self.slots.len();
+        self.slots.extend(arguments);
+        for &ty in &function(&code, function_index).locals {
+            self.slots.push(Value::default_for(&code.types, ty));
+        }
+        self.frames.push(Frame { module, function: function_index, pc: 0, base });
+        Ok(())
+    }
+
+    fn call_arguments(&mut self, code: &BytecodeModule, index: u32) -> Exec<Vec<Value>> {
+        match code.functions.get(index as usize) {
+            Some(callee) => self.pop_many(callee.params.len()),
+            None => Err(self.internal("unbekannte Prozedur")),
+        }
+    }
+
+    // ========================================================================
+    // Befehle
+    // ========================================================================
+
+    // Läuft, bis der Rahmen über `depth` zurückkehrt
+    fn run(&mut self, depth: usize) -> Exec<Option<Value>> {
+        loop {
+            let top = self.frames.len() - 1;
+            let (module_index, base) = (self.frames[top].module, self.frames[top].base);
+            let code = Rc::clone(&self.modules[module_index]);
+            let function = function(&code, self.frames[top].function);
+
+            // Befehle des obersten Rahmens bis zum nächsten Aufruf oder RETURN
+            loop {
+                let pc = self.frames[top].pc;
+                let instruction = match function.code.get(pc) {
+                    Some(instruction) => instruction,
+                    None => return Err(self.internal("Befehlsindex außerhalb des Codes")),
+                };
+                self.frames[top].pc = pc + 1;
+                if let Some(steps) = &mut self.steps {
+                    match steps.checked_sub(1) {
+                        Some(remaining) => *steps = remaining,
+                        None => return Err(self.trap("Befehlsbudget erschöpft")),
+                    }
+                }
+
+                use Instruction::*;
+                match instruction {
+                    PushInt(value) => self.stack.push(Value::Integer(*value)),
+                    PushReal(value) => self.stack.push(Value::Real(*value)),
+                    PushBool(value) => self.stack.push(Value::Boolean(*value)),
+                    PushChar(ch) => self.stack.push(Value::Char(*ch)),
+                    PushSet(bits) => self.stack.push(Value::Set(*bits)),
+                    PushString(text) => self.stack.push(Value::String(text.clone())),
+                    PushNil => self.stack.push(Value::Nil),
+                    PushProc(index) => {
+                        self.stack.push(Value::Procedure { module: module_index, function: *index });
+                    }
+                    PushExternProc { module, function } => {
+                        let module = self.links[module_index][*module as usize];
+                        self.stack.push(Value::Procedure { module, function: *function });
+                    }
+
+                    GlobalAddr(slot) => {
+                        let base = Base::Global { module: module_index, slot: *slot as usize };
+                        self.stack.push(Value::Address(Address::new(base)));
+                    }
+                    LocalAddr(slot) => {
+                        self.stack.push(Value::Address(Address::new(Base::Local(base + *slot as usize))));
+                    }
+                    ExternGlobalAddr { module, slot } => {
+                        let module = self.links[module_index][*module as usize];
+                        let base = Base::Global { module, slot: *slot as usize };
+                        self.stack.push(Value::Address(Address::new(base)));
+                    }
+                    OuterAddr { function, slot } => {
+                        let outer = self
+                            .frames
+                            .iter()
+                            .rev()
+                            .find(|frame| frame.module == module_index && frame.function == Some(*function));
+                        match outer {
+                            Some(frame) => {
+                                let address = Address::new(Base::Local(frame.base + *slot as usize));
+                                self.stack.push(Value::Address(address));
+                            }
+                            None => return Err(self.internal("kein Rahmen der umschließenden Prozedur")),
+                        }
+                    }
+                    Field(index) => {
+                        let mut address = self.pop_address()?;
+                        address.path.push(*index);
+                        self.stack.push(Value::Address(address));
+                    }
+                    Index(length) => {
+                        let index = self.pop_integer()?;
+                        let mut address = self.pop_address()?;
+                        if index < 0 || index >= *length as i64 {
+                            return Err(self.trap(format!(
+                                "Index {} außerhalb von 0..{}",
+                                index,
+                                *length as i64 - 1
+                            )));
+                        }
+                        address.path.push(index as u32);
+                        self.stack.push(Value::Address(address));
+                    }
+                    Deref => {
+                        let address = self.pop_address()?;
+                        let target = match self.value_at(&address)? {
+                            Value::Pointer(index) => Address::new(Base::Heap(*index)),
+                            Value::Nil => return Err(self.trap("Dereferenzierung von NIL")),
+                            _ => return Err(self.internal("Dereferenzierung eines Nicht-Zeigers")),
+                        };
+                        self.stack.push(Value::Address(target));
+                    }
+
+                    Load => {
+                        let address = self.pop_address()?;
+                        let value = self.value_at(&address)?.clone();
+                        self.stack.push(value);
+                    }
+                    Store => {
+                        let value = self.pop()?;
+                        let address = self.pop_address()?;
+                        self.store(&address, value)?;
+                    }
+                    Dup => match self.stack.last() {
+                        Some(value) => self.stack.push(value.clone()),
+                        None => return Err(self.internal("Operandenstapel leer")),
+                    },
+                    Pop => {
+                        self.pop()?;
+                    }
+                    Project(ty) => {
+                        let value = match self.pop()? {
+                            Value::Record { mut fields, .. } => {
+                                if let TypeDescKind::Record { fields: static_fields, .. } = &code.types[*ty as usize].kind {
+                                    fields.truncate(static_fields.len());
+                                }
+                                Value::Record { ty: *ty, fields }
+                            }
+                            value => value,
+                        };
+                        self.stack.push(value);
+                    }
+                    New(ty) => {
+                        let address = self.pop_address()?;
+                        self.heap.push(Value::default_for(&code.types, *ty));
+                        let pointer = Value::Pointer(self.heap.len() - 1);
+                        self.store(&address, pointer)?;
+                    }
+
+                    Add | Sub | Mul | Div | IntDiv | Mod | Eq | Ne | Lt | Le | Gt | Ge | In => {
+                        let right = self.pop()?;
+                        let left = self.pop()?;
+                        let result = self.binary(instruction, left, right)?;
+                        self.stack.push(result);
+                    }
+                    Neg => {
+                        let result = match self.pop()? {
+                            Value::Integer(x) => Value::Integer(x.checked_neg().ok_or_else(|| self.overflow("-"))?),
+                            Value::Real(x) => Value::Real(-x),
+                            Value::Set(s) => Value::Set(!s),
+                            _ => return Err(self.internal("Operand von '-'")),
+                        };
+                        self.stack.push(result);
+                    }
+                    Not => {
+                        let value = self.pop_boolean()?;
+                        self.stack.push(Value::Boolean(!value));
+                    }
+                    ToReal => {
+                        let value = self.pop_integer()?;
+                        self.stack.push(Value::Real(value as f64));
+                    }
+                    Chars => {
+                        let chars = match self.pop()? {
+                            Value::String(text) => text.chars().map(Value::Char).collect(),
+                            _ => return Err(self.internal("Operand von Chars")),
+                        };
+                        self.stack.push(Value::Array(chars));
+                    }
+                    Is(ty) => {
+                        let dynamic = match self.pop()? {
+                            Value::Record { ty, .. } => ty,
+                            Value::Pointer(index) => match self.heap.get(index) {
+                                Some(Value::Record { ty, .. }) => *ty,
+                                _ => return Err(self.internal("IS auf Zeiger ohne Record")),
+                            },
+                            Value::Nil => return Err(self.trap("Typtest mit NIL")),
+                            _ => return Err(self.internal("Operand von IS")),
+                        };
+                        self.stack.push(Value::Boolean(is_extension(&code, dynamic, *ty)));
+                    }
+                    InRange(start, end) => {
+                        let value = self.pop_integer()?;
+                        self.stack.push(Value::Boolean(*start <= value && value <= *end));
+                    }
+
+                    Inc | Dec => {
+                        let step = self.pop_integer()?;
+                        let value = self.pop_integer()?;
+                        let result = match instruction {
+                            Inc => value.checked_add(step).ok_or_else(|| self.overflow("INC"))?,
+                            _ => value.checked_sub(step).ok_or_else(|| self.overflow("DEC"))?,
+                        };
+                        self.stack.push(Value::Integer(result));
+                    }
+                    Incl | Excl => {
+                        let bit = self.pop_integer()?;
+                        let bit = self.set_bit(bit)?;
+                        let set = self.pop_set()?;
+                        let result = if *instruction == Incl { set | 1 << bit } else { set & !(1 << bit) };
+                        self.stack.push(Value::Set(result));
+                    }
+                    InclRange => {
+                        let end = self.pop_integer()?;
+                        let end = self.set_bit(end)?;
+                        let start = self.pop_integer()?;
+                        let start = self.set_bit(start)?;
+                        let mut set = self.pop_set()?;
+                        for bit in start..=end {
+                            set |= 1 << bit;
+                        }
+                        self.stack.push(Value::Set(set));
+                    }
+
+                    Abs | Odd | Floor | Ord | Chr => {
+                        let value = self.pop()?;
+                        let result = self.builtin(instruction, value)?;
+                        self.stack.push(result);
+                    }
+                    Lsl | Asr | Ror => {
+                        let n = self.pop_integer()?;
+                        let x = self.pop_integer()?;
+                        let result = self.shift(instruction, x, n)?;
+                        self.stack.push(Value::Integer(result));
+                    }
+
+                    Jump(to) => self.frames[top].pc = *to as usize,
+                    JumpIfFalse(to) => {
+                        if !self.pop_boolean()? {
+                            self.frames[top].pc = *to as usize;
+                        }
+                    }
+                    JumpIfTrue(to) => {
+                        if self.pop_boolean()? {
+                            self.frames[top].pc = *to as usize;
+                        }
+                    }
+
+                    Call(index) => {
+                        let arguments = self.call_arguments(&code, *index)?;
+                        self.enter(module_index, Some(*index), arguments)?;
+                        break;
+                    }
+                    CallExtern { module, function } => {
+                        let module = self.links[module_index][*module as usize];
+                        let callee = Rc::clone(&self.modules[module]);
+                        let arguments = self.call_arguments(&callee, *function)?;
+                        self.enter(module, Some(*function), arguments)?;
+                        break;
+                    }
+                    CallIndirect(count) => {
+                        // Prozedurwerte können aus einem anderen Modul stammen
+                        let (module, index) = match self.pop()? {
+                            Value::Procedure { module, function } => (module, function),
+                            Value::Nil => return Err(self.trap("Aufruf einer Prozedurvariablen mit Wert NIL")),
+                            _ => return Err(self.internal("Aufruf eines Nicht-Prozedurwerts")),
+                        };
+                        let callee = Rc::clone(&self.modules[module]);
+                        let arguments = self.call_arguments(&callee, index)?;
+                        if arguments.len() != *count as usize {
+                            return Err(self.internal("Anzahl der Argumente"));
+                        }
+                        self.enter(module, Some(index), arguments)?;
+                        break;
+                    }
+                    Return | ReturnValue => {
+                        let value = match instruction {
+                            ReturnValue => Some(self.pop()?),
+                            _ => None,
+                        };
+                        if let Some(frame) = self.frames.pop() {
+                            self.slots.truncate(frame.base);
+                        }
+                        if self.frames.len() == depth {
+                            return Ok(value);
+                        }
+                        self.stack.extend(value);
+                        break;
+                    }
+
+                    Library(procedure) => {
+                        let arguments = self.pop_many(procedure.arity())?;
+                        self.library_call(*procedure, &arguments)?;
+                    }
+                    Trap(TrapKind::Assert) => return Err(self.trap("ASSERT fehlgeschlagen")),
+                    Trap(TrapKind::Case) => {
+                        let value = self.pop_integer()?;
+                        return Err(self.trap(format!("Kein CASE-Label für den Wert {}", value)));
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_bit(&self, value: i64) -> Exec<u32> {
+        if (0..=SET_MAX_ELEMENT).contains(&value) {
+            Ok(value as u32)
+        } else {
+            Err(self.trap(format!(
+                "Mengenelement {} liegt außerhalb von 0..{}",
+                value, SET_MAX_ELEMENT
+            )))
+        }
+    }
+
+    fn binary(&self, instruction: &Instruction, left: Value, right: Value) -> Exec<Value> {
+        use Instruction as I;
+        use Value::*;
+        let result = match (instruction, &left, &right) {
+            (I::Add, Integer(x), Integer(y)) => Integer(x.checked_add(*y).ok_or_else(|| self.overflow("+"))?),
+            (I::Sub, Integer(x), Integer(y)) => Integer(x.checked_sub(*y).ok_or_else(|| self.overflow("-"))?),
+            (I::Mul, Integer(x), Integer(y)) => Integer(x.checked_mul(*y).ok_or_else(|| self.overflow("*"))?),
+            (I::IntDiv | I::Mod, Integer(_), Integer(0)) => return Err(self.trap("Division durch 0")),
+            (I::IntDiv, Integer(x), Integer(y)) => Integer(floor_div(*x, *y).ok_or_else(|| self.overflow("DIV"))?),
+            (I::Mod, Integer(x), Integer(y)) => Integer(floor_mod(*x, *y).ok_or_else(|| self.overflow("MOD"))?),
+
+            (I::Add, Real(x), Real(y)) => Real(x + y),
+            (I::Sub, Real(x), Real(y)) => Real(x - y),
+            (I::Mul, Real(x), Real(y)) => Real(x * y),
+            (I::Div, Real(x), Real(y)) => Real(x / y),
+
+            (I::Add, Set(x), Set(y)) => Set(x | y),
+            (I::Sub, Set(x), Set(y)) => Set(x & !y),
+            (I::Mul, Set(x), Set(y)) => Set(x & y),
+            (I::Div, Set(x), Set(y)) => Set(x ^ y),
+            (I::In, Integer(x), Set(s)) => Boolean((0..=SET_MAX_ELEMENT).contains(x) && s & (1 << *x) != 0),
+
+            (I::Eq, Array(_) | String(_), Array(_) | String(_)) => {
+                Boolean(ordering(&left, &right) == Some(Ordering::Equal))
+            }
+            (I::Ne, Array(_) | String(_), Array(_) | String(_)) => {
+                Boolean(ordering(&left, &right) != Some(Ordering::Equal))
+            }
+            (I::Eq, _, _) => Boolean(left == right),
+            (I::Ne, _, _) => Boolean(left != right),
+            (I::Lt, _, _) => Boolean(ordering(&left, &right) == Some(Ordering::Less)),
+            (I::Le, _, _) => Boolean(matches!(ordering(&left, &right), Some(Ordering::Less | Ordering::Equal))),
+            (I::Gt, _, _) => Boolean(ordering(&left, &right) == Some(Ordering::Greater)),
+            (I::Ge, _, _) => Boolean(matches!(ordering(&left, &right), Some(Ordering::Greater | Ordering::Equal))),
+            _ => return Err(self.internal(&format!("Operanden für {:?}", instruction))),
+        };
+        Ok(result)
+    }
+
+    fn builtin(&self, instruction: &Instruction, value: Value) -> Exec<Value> {
+        use Instruction as I;
+        use Value::*;
+        let result = match (instruction, value) {
+            (I::Abs, Integer(x)) => Integer(x.checked_abs().ok_or_else(|| self.overflow("ABS"))?),
+            (I::Abs, Real(x)) => Real(x.abs()),
+            (I::Odd, Integer(x)) => Boolean(x.rem_euclid(2) == 1),
+            (I::Floor, Real(x)) => {
+                let floored = x.floor();
+                if !floored.is_finite() || floored < i64::MIN as f64 || floored >= i64::MAX as f64 {
+                    return Err(self.overflow("FLOOR"));
+                }
+                Integer(floored as i64)
+            }
+            (I::Ord, Char(ch)) => Integer(ch as i64),
+            (I::Ord, Boolean(b)) => Integer(b as i64),
+            (I::Ord, Set(s)) => Integer(s as i64),
+            (I::Chr, Integer(x)) => match u32::try_from(x).ok().and_then(char::from_u32) {
+                Some(ch) => Char(ch),
+                None => return Err(self.trap(format!("CHR({}) ist kein gültiges Zeichen", x))),
+            },
+            _ => return Err(self.internal(&format!("Argument für {:?}", instruction))),
+        };
+        Ok(result)
+    }
+
+    fn shift(&self, instruction: &Instruction, x: i64, n: i64) -> Exec<i64> {
+        let name = match instruction {
+            Instruction::Lsl => "LSL",
+            Instruction::Asr => "ASR",
+            _ => "ROR",
+        };
+        if !(0..64).contains(&n) {
+            return Err(self.trap(format!("Schiebeweite {} für {} außerhalb von 0..63", n, name)));
+        }
+        let shift = n as u32;
+        match instruction {
+            Instruction::Lsl => {
+                let result = x.wrapping_shl(shift);
+                if result.wrapping_shr(shift) != x {
+                    return Err(self.overflow(name));
+                }
+                Ok(result)
+            }
+            Instruction::Asr => Ok(x >> shift),
+            _ => Ok((x as u64).rotate_right(shift) as i64),
+        }
+    }
+
+    fn library_call(&mut self, procedure: LibraryProcedure, arguments: &[Value]) -> Exec<()> {
+        let written = match (procedure, arguments) {
+            (LibraryProcedure::OutOpen, []) => Ok(()),
+            (LibraryProcedure::OutChar, [Value::Char(ch)]) => write!(self.out, "{}", ch),
+            (LibraryProcedure::OutString, [text]) => match text.text() {
+                Some(text) => write!(self.out, "{}", text),
+                None => return Err(self.internal("Argument für Out.String")),
+            },
+            (LibraryProcedure::OutInt, [Value::Integer(x), Value::Integer(n)]) => {
+                write_padded(self.out, &x.to_string(), *n)
+            }
+            (LibraryProcedure::OutReal, [Value::Real(x), Value::Integer(n)]) => {
+                write_padded(self.out, &format!("{:.6}", x), *n)
+            }
+            (LibraryProcedure::OutLn, []) => writeln!(self.out),
+            _ => return Err(self.internal(&format!("Argumente für {}", procedure))),
+        };
+        written.map_err(|e| self.trap(format!("Ausgabe fehlgeschlagen: {}", e)))
+    }
+}
+
+fn function(module: &BytecodeModule, index: Option<u32>) -> &Function {
+    match index {
+        Some(index) => &module.functions[index as usize],
+        None => &module.body,
+    }
+}
+
+// Typerweiterung über die Basistyp-Kette der Typtabelle
+fn is_extension(module: &BytecodeModule, mut extension: u32, base: u32) -> bool {
+    loop {
+        if extension == base {
+            return true;
+        }
+        match module.types.get(extension as usize).map(|desc| &desc.kind) {
+            Some(TypeDescKind::Record { base: Some(next), .. }) => extension = *next,
+            _ => return false,
+        }
+    }
+}
+
+fn ordering(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Integer(x), Value::Integer(y)) => x.partial_cmp(y),
+        (Value::Real(x), Value::Real(y)) => x.partial_cmp(y),
+        (Value::Char(x), Value::Char(y)) => x.partial_cmp(y),
+        // Zeichenketten nur bis zum ersten 0X
+        (Value::String(_) | Value::Array(_), Value::String(_) | Value::Array(_)) => {
+            left.text().zip(right.text()).map(|(x, y)| x.cmp(&y))
+        }
+        (Value::Boolean(x), Value::Boolean(y)) => x.partial_cmp(y),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode;
+    use crate::codegen::BytecodeGenerator;
+    use crate::semantic::TypeChecker;
+    use crate::testing::{checked, parse};
+    use std::fs;
+
+    fn compile(source: &str) -> BytecodeModule {
+        BytecodeGenerator::new().generate(&checked(source)).unwrap()
+    }
+
+    // Ausgabe des Modulrumpfs bzw. der Trap; der Bytecode geht einmal durch
+    // das Binärformat
+    fn run(source: &str) -> Result<String, Trap> {
+        let module = bytecode::decode(&bytecode::encode(&compile(source))).unwrap();
+        let mut out = Vec::new();
+        Vm::new(&mut out).load(module)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_execution() {
+        let output = run("MODULE T;
+            IMPORT Out;
+            TYPE
+              Node = POINTER TO NodeDesc;
+              NodeDesc = RECORD key: INTEGER; next: Node END;
+              Point = RECORD x, y: INTEGER END;
+              Point3 = RECORD (Point) z: INTEGER END;
+              PPoint = POINTER TO Point;
+              PPoint3 = POINTER TO Point3;
+              Vec = ARRAY 3 OF INTEGER;
+            VAR list: Node; a, b: Vec; p: Point; q: Point3; i: INTEGER; s: SET; name: ARRAY 4 OF CHAR;
+              pp: PPoint; pq: PPoint3; f: PROCEDURE (n: INTEGER): INTEGER;
+
+            PROCEDURE Insert(VAR list: Node; key: INTEGER);
+              VAR n: Node;
+            BEGIN NEW(n); n^.key := key; n^.next := list; list := n
+            END Insert;
+
+            PROCEDURE Fib(n: INTEGER): INTEGER;
+              VAR r: INTEGER;
+            BEGIN IF n < 2 THEN r := n ELSE r := Fib(n - 1) + Fib(n - 2) END
+              RETURN r
+            END Fib;
+
+            PROCEDURE Sum(n: INTEGER): INTEGER;
+              VAR total: INTEGER;
+              PROCEDURE Add(k: INTEGER);
+              BEGIN total := total + k
+              END Add;
+            BEGIN total := 0; WHILE n > 0 DO Add(n); DEC(n) END
+              RETURN total
+            END Sum;
+
+            PROCEDURE Clear(v: Vec);
+              VAR i: INTEGER;
+            BEGIN FOR i := 0 TO 2 DO v[i] := 0 END
+            END Clear;
+
+            BEGIN
+              FOR i := 1 TO 3 DO Insert(list, i * i) END;
+              WHILE list # NIL DO Out.Int(list^.key, 3); list := list^.next END; Out.Ln;
+              f := Fib; Out.Int(f(15), 0); Out.Char(\" \"); Out.Int(Sum(10), 0); Out.Ln;
+              FOR i := 0 TO 2 DO a[i] := i + 1 END;
+              b := a; b[0] := 100; Clear(a);
+              Out.Int(a[0], 0); Out.Char(\" \"); Out.Int(b[0], 0); Out.Ln;
+              q.x := 1; q.y := 2; q.z := 3; p := q; Out.Int(p.x + p.y, 0); Out.Ln;
+              s := {1, 3..5}; INCL(s, 7); EXCL(s, 4);
+              FOR i := 31 TO 0 BY -1 DO IF i IN s THEN Out.Int(i, 2) END END; Out.Ln;
+              Out.Int((-7) DIV 2, 0); Out.Int((-7) MOD 2, 3); Out.Real(2.5 * 2.0, 9); Out.Ln;
+              name[0] := \"H\"; name[1] := \"i\"; name[2] := 0X; name[3] := \"!\"; Out.String(name); Out.Ln;
+              CASE ORD(name[0]) OF 0..64: Out.String(\"klein\") | 65..90: Out.String(\"gross\") END; Out.Ln;
+              NEW(pq); pp := pq; IF (pp IS PPoint3) & ~(p IS Point3) THEN Out.String(\"IS\") END; Out.Ln
+            END T.")
+        .unwrap();
+
+        assert_eq!(output, "  9  4  1\n610 55\n1 100\n3\n 7 5 3 1\n-4  1 5.000000\nHi\ngross\nIS\n");
+
+        // Breiten jenseits von u16::MAX werden aufgefüllt, negative ignoriert
+        let output = run("MODULE T; IMPORT Out; BEGIN Out.Int(1, 70000); Out.Real(0.5, 70000); Out.Int(-2, -5) END T.").unwrap();
+        assert_eq!(output, format!("{}1{}0.500000-2", " ".repeat(69999), " ".repeat(69992)));
+
+        // Zeichenketten werden bis zum ersten 0X verglichen
+        let output = run("MODULE T; IMPORT Out; VAR a: ARRAY 8 OF CHAR; b: ARRAY 4 OF CHAR;
+            BEGIN a := \"Ann\"; a[5] := \"x\"; b := \"Ann\";
+              IF (a = b) & (a = \"Ann\") & (b < \"Anna\") & ~(a > b) THEN Out.String(\"ok\") END
+            END T.")
+        .unwrap();
+        assert_eq!(output, "ok");
+    }
+
+    #[test]
+    fn test_traps() {
+        let trap = run("MODULE T;
+            VAR a: ARRAY 3 OF INTEGER; i: INTEGER;
+            PROCEDURE Set(k: INTEGER);
+            BEGIN
+              a[k] := k
+            END Set;
+            BEGIN
+              FOR i := 0 TO 3 DO Set(i) END
+            END T.")
+        .unwrap_err();
+        assert_eq!(trap.to_string(), "5: Laufzeitfehler in Set: Index 3 außerhalb von 0..2");
+
+        let message = |source: &str| run(source).unwrap_err().message;
+        assert_eq!(
+            message("MODULE T; TYPE P = POINTER TO RECORD x: INTEGER END; VAR p: P; BEGIN p^.x := 1 END T."),
+            "Dereferenzierung von NIL"
+        );
+        assert_eq!(message("MODULE T; VAR i: INTEGER; BEGIN i := 7 MOD i END T."), "Division durch 0");
+        assert_eq!(
+            message("MODULE T; VAR i: INTEGER; BEGIN i := 4; CASE i OF 1..3: i := 0 END END T."),
+            "Kein CASE-Label für den Wert 4"
+        );
+        assert_eq!(message("MODULE T; VAR i: INTEGER; BEGIN ASSERT(i > 0) END T."), "ASSERT fehlgeschlagen");
+
+        // Tiefe Rekursion braucht keinen großen Rust-Stapel
+        let trap = run("MODULE T;
+            PROCEDURE Down(n: INTEGER): INTEGER;
+            BEGIN
+              RETURN Down(n - 1)
+            END Down;
+            BEGIN ASSERT(Down(0) = 0)
+            END T.")
+        .unwrap_err();
+        assert_eq!((trap.line, trap.procedure.as_str()), (4, "Down"));
+        assert!(trap.message.starts_with("Stapelüberlauf"));
+
+        // Das Befehlsbudget beendet Endlosschleifen, auch aus verfälschten Sprüngen
+        let mut out = Vec::new();
+        let mut vm = Vm::new(&mut out);
+        vm.set_step_limit(1000);
+        let trap = vm.load(compile("MODULE T; BEGIN WHILE TRUE DO END END T.")).unwrap_err();
+        assert_eq!((trap.message.as_str(), trap.procedure.as_str()), ("Befehlsbudget erschöpft", "T"));
+
+        let mut module = compile("MODULE U; VAR i: INTEGER; BEGIN i := 1 END U.");
+        module.body.code.insert(0, Instruction::Jump(0));
+        let module = bytecode::decode(&bytecode::encode(&module)).unwrap();
+        let mut vm = Vm::new(&mut out);
+        vm.set_step_limit(1000);
+        assert_eq!(vm.load(module).unwrap_err().message, "Befehlsbudget erschöpft");
+
+        // Innerhalb des Budgets läuft das Programm normal
+        let mut vm = Vm::new(&mut out);
+        vm.set_step_limit(1000);
+        vm.load(compile("MODULE V; VAR i: INTEGER; BEGIN FOR i := 1 TO 10 DO END END V.")).unwrap();
+    }
+
+    #[test]
+    fn test_host_calls() {
+        let module = compile("MODULE Plugin;
+            VAR count*: INTEGER; secret: INTEGER;
+            PROCEDURE Square*(x: INTEGER): INTEGER;
+            BEGIN INC(count)
+              RETURN x * x
+            END Square;
+            PROCEDURE Hidden(x: INTEGER): INTEGER;
+            BEGIN RETURN x
+            END Hidden;
+            BEGIN count := 10; secret := 1
+            END Plugin.");
+
+        let mut out = Vec::new();
+        let mut vm = Vm::new(&mut out);
+        vm.load(module).unwrap();
+        assert_eq!(vm.call("Plugin", "Square", vec![Value::Integer(7)]), Ok(Some(Value::Integer(49))));
+        assert_eq!(vm.global("Plugin", "count"), Some(&Value::Integer(11)));
+        assert_eq!(vm.global("Plugin", "secret"), None);
+        assert!(vm.call("Plugin", "Hidden", vec![Value::Integer(1)]).is_err());
+        assert!(vm.call("Plugin", "Square", vec![]).is_err());
+    }
+
+    // Übersetzt die Module nacheinander, jedes mit den vorherigen als Importen
+    fn compile_all(sources: &[&str]) -> Vec<BytecodeModule> {
+        let mut checked = Vec::new();
+        let mut generator = BytecodeGenerator::new();
+        let mut modules = Vec::new();
+        for source in sources {
+            let module = parse(source);
+            let mut type_checker = TypeChecker::new();
+            for interface in &checked {
+                type_checker.add_interface(interface);
+            }
+            let module = type_checker.check_module(&module).unwrap();
+            let code = generator.generate(&module).unwrap();
+            generator.add_interface(&code);
+            checked.push(module);
+            modules.push(bytecode::decode(&bytecode::encode(&code)).unwrap());
+        }
+        modules
+    }
+
+    #[test]
+    fn test_imports() {
+        let modules = compile_all(&[
+            "MODULE Lib;
+            VAR count*: INTEGER; hidden: INTEGER;
+            PROCEDURE Max*(a, b: INTEGER): INTEGER;
+            BEGIN INC(count); IF a > b THEN b := a END
+              RETURN b
+            END Max;
+            PROCEDURE Inc*(n: INTEGER): INTEGER;
+            BEGIN RETURN n + 1
+            END Inc;
+            PROCEDURE Twice*(f: PROCEDURE (n: INTEGER): INTEGER; n: INTEGER): INTEGER;
+            BEGIN RETURN f(f(n))
+            END Twice;
+            PROCEDURE Check*(n: INTEGER);
+            BEGIN
+              ASSERT(n > 0)
+            END Check;
+            BEGIN count := 0; hidden := 1
+            END Lib.",
+            "MODULE Main;
+            IMPORT Out, L := Lib;
+            VAR f: PROCEDURE (n: INTEGER): INTEGER;
+            PROCEDURE Square(n: INTEGER): INTEGER;
+            BEGIN RETURN n * n
+            END Square;
+            BEGIN
+              Out.Int(L.Max(3, 7), 0); L.count := L.count + 10; Out.Int(L.count, 3);
+              f := L.Inc; Out.Int(f(1), 3); Out.Int(L.Twice(Square, 3), 3); Out.Int(L.Twice(L.Inc, 3), 3);
+              Out.Ln
+            END Main.",
+            "MODULE Bad; IMPORT Lib; BEGIN Lib.Check(0) END Bad.",
+        ]);
+        let (lib, main, bad) = (&modules[0], &modules[1], &modules[2]);
+        assert!(bytecode::disassemble(main).contains("CallExtern 0 0  ; Lib"));
+        assert!(bytecode::disassemble(main).contains("ExternGlobalAddr 0 0  ; Lib"));
+
+        let mut out = Vec::new();
+        let mut vm = Vm::new(&mut out);
+        vm.load(lib.clone()).unwrap();
+        vm.load(main.clone()).unwrap();
+        assert_eq!(vm.global("Lib", "count"), Some(&Value::Integer(11)));
+
+        // Traps in importierten Prozeduren nennen das Modul
+        let trap = vm.load(bad.clone()).unwrap_err();
+        assert_eq!((trap.line, trap.procedure.as_str()), (15, "Lib.Check"));
+
+        // Verweise auf nicht Exportiertes weist schon load zurück
+        let mut broken = bad.clone();
+        broken.body.code[1] = Instruction::CallExtern { module: 0, function: 9 };
+        let error = vm.load(broken).unwrap_err().message;
+        assert_eq!(error, "Bad: Modul Lib exportiert keine Prozedur mit Index 9");
+        drop(vm);
+        assert_eq!(String::from_utf8(out).unwrap(), "7 11  2 81  5\n");
+    }
+
+    #[test]
+    fn test_loader() {
+        let dir = std::env::temp_dir().join(format!("together-vm-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save = |source: &str| {
+            let module = compile(source);
+            let path = dir.join(format!("{}.{}", module.name, bytecode::EXTENSION));
+            fs::write(&path, bytecode::encode(&module)).unwrap();
+            path
+        };
+        save("MODULE A; IMPORT Out; BEGIN Out.String(\"A\") END A.");
+        save("MODULE B; IMPORT A, Out; BEGIN Out.String(\"B\") END B.");
+        let main = save("MODULE Main; IMPORT Out, X := B, A; BEGIN Out.String(\"Main\") END Main.");
+        save("MODULE C1; IMPORT C2; END C1.");
+        save("MODULE C2; IMPORT C1; END C2.");
+
+        let mut loader = Loader::new();
+        loader.add_path(&dir);
+        let modules = loader.load_file(&main).unwrap();
+        let names: Vec<&str> = modules.iter().map(|module| module.name.as_str()).collect();
+        assert_eq!(names, ["A", "B", "Main"]);
+
+        let mut out = Vec::new();
+        let mut vm = Vm::new(&mut out);
+        for module in modules {
+            vm.load(module).unwrap();
+        }
+        assert_eq!(String::from_utf8(out).unwrap(), "ABMain");
+
+        assert_eq!(loader.load("C1").unwrap_err(), "Zyklischer Import: C1 -> C2 -> C1");
+        assert!(loader.load("Missing").unwrap_err().starts_with("Modul Missing nicht gefunden"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
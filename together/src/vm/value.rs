@@ -0,0 +1,127 @@
+use crate::bytecode::{TypeDesc, TypeDescKind};
+
+// ============================================================================
+// Laufzeitwerte der VM
+// ============================================================================
+//
+// Wie im Interpreter sind Arrays und Records Werte. Typen und Prozeduren
+// werden über ihre Indizes im Bytecode-Modul bezeichnet; Adressen liegen als
+// eigene Werte auf dem Operandenstapel und in den Plätzen von VAR-Parametern.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Real(f64),
+    Boolean(bool),
+    Char(char),
+    Set(u32),
+    // String-Konstanten
+    String(String),
+    Nil,
+    // Index in den Heap
+    Pointer(usize),
+    // Geladenes Modul (Index in der VM) und Index in seinen Prozeduren
+    Procedure { module: usize, function: u32 },
+    Array(Vec<Value>),
+    // Dynamischer Typ (Index in der Typtabelle) und alle Felder
+    Record { ty: u32, fields: Vec<Value> },
+    Address(Address),
+}
+
+impl Value {
+    // Anfangswert einer Variablen: Null, NIL bzw. elementweise
+    pub fn default_for(types: &[TypeDesc], ty: u32) -> Value {
+        match &types[ty as usize].kind {
+            TypeDescKind::Integer => Value::Integer(0),
+            TypeDescKind::Real => Value::Real(0.0),
+            TypeDescKind::Boolean => Value::Boolean(false),
+            TypeDescKind::Char => Value::Char('\0'),
+            TypeDescKind::Set => Value::Set(0),
+            TypeDescKind::String => Value::String(String::new()),
+            TypeDescKind::Reference => Value::Nil,
+            TypeDescKind::Array { length, element } => {
+                Value::Array(vec![Value::default_for(types, *element); *length as usize])
+            }
+            TypeDescKind::Record { fields, .. } => Value::Record {
+                ty,
+                fields: fields.iter().map(|&field| Value::default_for(types, field)).collect(),
+            },
+        }
+    }
+
+    // Inhalt einer Zeichenkette: String-Konstante oder ARRAY OF CHAR bis 0X
+    pub fn text(&self) -> Option<String> {
+        match self {
+            Value::String(text) => Some(text.clone()),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::Char(ch) => Some(*ch),
+                    _ => None,
+                })
+                .take_while(|ch| *ch != Some('\0'))
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// Adressen
+// ============================================================================
+
+// Wurzel einer Adresse: globale Variable eines geladenen Moduls, Platz auf dem
+// Rahmenstapel oder Heap-Objekt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Base {
+    Global { module: usize, slot: usize },
+    Local(usize),
+    Heap(usize),
+}
+
+// Wurzel und Indizes in Arrays bzw. Feldpositionen in Records
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub base: Base,
+    pub path: Vec<u32>,
+}
+
+impl Address {
+    pub fn new(base: Base) -> Self {
+        Address { base, path: Vec::new() }
+    }
+}
+
+// Folgt dem Pfad ab `value`; None bei einem Pfad, der nicht zum Wert passt
+pub(super) fn element<'v>(mut value: &'v Value, path: &[u32]) -> Option<&'v Value> {
+    for &index in path {
+        value = match value {
+            Value::Array(items) | Value::Record { fields: items, .. } => items.get(index as usize)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+pub(super) fn element_mut<'v>(mut value: &'v mut Value, path: &[u32]) -> Option<&'v mut Value> {
+    for &index in path {
+        value = match value {
+            Value::Array(items) | Value::Record { fields: items, .. } => items.get_mut(index as usize)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+// Records behalten bei der Zuweisung ihren dynamischen Typ; überschrieben
+// werden nur die Felder des zugewiesenen Werts
+pub(super) fn assign(slot: &mut Value, value: Value) {
+    match (slot, value) {
+        (Value::Record { fields, .. }, Value::Record { fields: assigned, .. }) => {
+            for (field, value) in fields.iter_mut().zip(assigned) {
+                *field = value;
+            }
+        }
+        (slot, value) => *slot = value,
+    }
+}
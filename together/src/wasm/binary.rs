@@ -0,0 +1,666 @@
+use super::{
+    simple, simple_by_opcode, BlockType, Data, FuncType, Function, Global, Import, Instruction, ValType,
+    WasmModule, MEMORY_EXPORT,
+};
+
+// ============================================================================
+// Binärformat (.wasm)
+// ============================================================================
+//
+// Nach der Spezifikation: Kennung "\0asm", Version 1, danach die Sektionen in
+// der vorgeschriebenen Reihenfolge. Ganze Zahlen sind LEB128-kodiert. Der
+// Decoder liest genau das, was encode schreibt; Namen gehen dabei verloren,
+// Funktionen heißen nach ihrem Export bzw. f<Index>.
+
+const MAGIC: &[u8; 4] = b"\0asm";
+const VERSION: u32 = 1;
+
+const SECTION_TYPE: u8 = 1;
+const SECTION_IMPORT: u8 = 2;
+const SECTION_FUNCTION: u8 = 3;
+const SECTION_TABLE: u8 = 4;
+const SECTION_MEMORY: u8 = 5;
+const SECTION_GLOBAL: u8 = 6;
+const SECTION_EXPORT: u8 = 7;
+const SECTION_START: u8 = 8;
+const SECTION_ELEMENT: u8 = 9;
+const SECTION_CODE: u8 = 10;
+const SECTION_DATA: u8 = 11;
+
+const FUNCREF: u8 = 0x70;
+const FUNC_TYPE: u8 = 0x60;
+const EMPTY_BLOCK: u8 = 0x40;
+const EXPORT_FUNC: u8 = 0x00;
+const EXPORT_MEMORY: u8 = 0x02;
+// Präfix der Befehle memory.copy und memory.fill
+const PREFIX_FC: u8 = 0xFC;
+
+pub fn encode(module: &WasmModule) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+    let mut w = Writer::default();
+    w.u32(module.types.len() as u32);
+    for ty in &module.types {
+        w.byte(FUNC_TYPE);
+        w.val_types(&ty.params);
+        w.val_types(&ty.results);
+    }
+    section(&mut bytes, SECTION_TYPE, w);
+
+    if !module.imports.is_empty() {
+        let mut w = Writer::default();
+        w.u32(module.imports.len() as u32);
+        for import in &module.imports {
+            w.name(&import.module);
+            w.name(&import.name);
+            w.byte(EXPORT_FUNC);
+            w.u32(import.ty);
+        }
+        section(&mut bytes, SECTION_IMPORT, w);
+    }
+
+    let mut w = Writer::default();
+    w.u32(module.functions.len() as u32);
+    for function in &module.functions {
+        w.u32(function.ty);
+    }
+    section(&mut bytes, SECTION_FUNCTION, w);
+
+    let mut w = Writer::default();
+    w.u32(1);
+    w.byte(FUNCREF);
+    w.byte(0);
+    w.u32(module.table.len() as u32 + 1);
+    section(&mut bytes, SECTION_TABLE, w);
+
+    let mut w = Writer::default();
+    w.u32(1);
+    w.byte(0);
+    w.u32(module.memory_pages);
+    section(&mut bytes, SECTION_MEMORY, w);
+
+    if !module.globals.is_empty() {
+        let mut w = Writer::default();
+        w.u32(module.globals.len() as u32);
+        for global in &module.globals {
+            w.val_type(global.ty);
+            w.byte(global.mutable as u8);
+            w.instruction(&match global.ty {
+                ValType::I32 => Instruction::I32Const(global.init as i32),
+                ValType::I64 => Instruction::I64Const(global.init),
+                ValType::F64 => Instruction::F64Const(global.init as f64),
+            });
+            w.instruction(&Instruction::End);
+        }
+        section(&mut bytes, SECTION_GLOBAL, w);
+    }
+
+    let exports: Vec<(usize, &String)> = module
+        .functions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, function)| function.export.as_ref().map(|name| (index, name)))
+        .collect();
+    let mut w = Writer::default();
+    w.u32(exports.len() as u32 + 1);
+    w.name(MEMORY_EXPORT);
+    w.byte(EXPORT_MEMORY);
+    w.u32(0);
+    for (index, name) in exports {
+        w.name(name);
+        w.byte(EXPORT_FUNC);
+        w.u32((module.imports.len() + index) as u32);
+    }
+    section(&mut bytes, SECTION_EXPORT, w);
+
+    if let Some(start) = module.start {
+        let mut w = Writer::default();
+        w.u32(start);
+        section(&mut bytes, SECTION_START, w);
+    }
+
+    if !module.table.is_empty() {
+        let mut w = Writer::default();
+        w.u32(1);
+        w.u32(0);
+        w.instruction(&Instruction::I32Const(1));
+        w.instruction(&Instruction::End);
+        w.u32(module.table.len() as u32);
+        for &index in &module.table {
+            w.u32(index);
+        }
+        section(&mut bytes, SECTION_ELEMENT, w);
+    }
+
+    let mut w = Writer::default();
+    w.u32(module.functions.len() as u32);
+    for function in &module.functions {
+        let mut body = Writer::default();
+        // Lokale gleichen Typs in Folge werden zusammengefasst
+        let mut groups: Vec<(u32, ValType)> = Vec::new();
+        for &local in &function.locals {
+            match groups.last_mut() {
+                Some((count, ty)) if *ty == local => *count += 1,
+                _ => groups.push((1, local)),
+            }
+        }
+        body.u32(groups.len() as u32);
+        for (count, ty) in groups {
+            body.u32(count);
+            body.val_type(ty);
+        }
+        for instruction in &function.body {
+            body.instruction(instruction);
+        }
+        w.u32(body.bytes.len() as u32);
+        w.bytes.extend(body.bytes);
+    }
+    section(&mut bytes, SECTION_CODE, w);
+
+    if !module.data.is_empty() {
+        let mut w = Writer::default();
+        w.u32(module.data.len() as u32);
+        for data in &module.data {
+            w.u32(0);
+            w.instruction(&Instruction::I32Const(data.offset as i32));
+            w.instruction(&Instruction::End);
+            w.u32(data.bytes.len() as u32);
+            w.bytes.extend_from_slice(&data.bytes);
+        }
+        section(&mut bytes, SECTION_DATA, w);
+    }
+
+    bytes
+}
+
+fn section(bytes: &mut Vec<u8>, id: u8, content: Writer) {
+    bytes.push(id);
+    let mut size = Writer::default();
+    size.u32(content.bytes.len() as u32);
+    bytes.extend(size.bytes);
+    bytes.extend(content.bytes);
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    fn u32(&mut self, mut value: u32) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn i64(&mut self, mut value: i64) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+            if done {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn name(&mut self, name: &str) {
+        self.u32(name.len() as u32);
+        self.bytes.extend_from_slice(name.as_bytes());
+    }
+
+    fn val_type(&mut self, ty: ValType) {
+        self.byte(match ty {
+            ValType::I32 => 0x7F,
+            ValType::I64 => 0x7E,
+            ValType::F64 => 0x7C,
+        });
+    }
+
+    fn val_types(&mut self, types: &[ValType]) {
+        self.u32(types.len() as u32);
+        for &ty in types {
+            self.val_type(ty);
+        }
+    }
+
+    fn block_type(&mut self, ty: &BlockType) {
+        match ty {
+            BlockType::Empty => self.byte(EMPTY_BLOCK),
+            BlockType::Value(ty) => self.val_type(*ty),
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        use Instruction::*;
+        if let Some((opcode, _)) = simple(instruction) {
+            self.byte(opcode);
+            match memory_alignment(opcode) {
+                Some(align) => {
+                    self.u32(align);
+                    self.u32(0);
+                }
+                // Speicherindex von memory.size und memory.grow
+                None if matches!(instruction, MemorySize | MemoryGrow) => self.byte(0),
+                None => {}
+            }
+            return;
+        }
+
+        match instruction {
+            Block(ty) => {
+                self.byte(0x02);
+                self.block_type(ty);
+            }
+            Loop(ty) => {
+                self.byte(0x03);
+                self.block_type(ty);
+            }
+            If(ty) => {
+                self.byte(0x04);
+                self.block_type(ty);
+            }
+            Br(depth) => {
+                self.byte(0x0C);
+                self.u32(*depth);
+            }
+            BrIf(depth) => {
+                self.byte(0x0D);
+                self.u32(*depth);
+            }
+            BrTable(depths, default) => {
+                self.byte(0x0E);
+                self.u32(depths.len() as u32);
+                for depth in depths {
+                    self.u32(*depth);
+                }
+                self.u32(*default);
+            }
+            Call(index) => {
+                self.byte(0x10);
+                self.u32(*index);
+            }
+            CallIndirect(ty) => {
+                self.byte(0x11);
+                self.u32(*ty);
+                self.byte(0);
+            }
+            LocalGet(index) => {
+                self.byte(0x20);
+                self.u32(*index);
+            }
+            LocalSet(index) => {
+                self.byte(0x21);
+                self.u32(*index);
+            }
+            LocalTee(index) => {
+                self.byte(0x22);
+                self.u32(*index);
+            }
+            GlobalGet(index) => {
+                self.byte(0x23);
+                self.u32(*index);
+            }
+            GlobalSet(index) => {
+                self.byte(0x24);
+                self.u32(*index);
+            }
+            MemoryCopy => {
+                self.byte(PREFIX_FC);
+                self.u32(10);
+                self.byte(0);
+                self.byte(0);
+            }
+            MemoryFill => {
+                self.byte(PREFIX_FC);
+                self.u32(11);
+                self.byte(0);
+            }
+            I32Const(value) => {
+                self.byte(0x41);
+                self.i64(*value as i64);
+            }
+            I64Const(value) => {
+                self.byte(0x42);
+                self.i64(*value);
+            }
+            F64Const(value) => {
+                self.byte(0x44);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            other => unreachable!("{:?} fehlt in der Befehlstabelle", other),
+        }
+    }
+}
+
+// Natürliche Ausrichtung (log2) der Speicherzugriffe
+fn memory_alignment(opcode: u8) -> Option<u32> {
+    match opcode {
+        0x2D | 0x3A => Some(0),
+        0x28 | 0x36 => Some(2),
+        0x29 | 0x2B | 0x37 | 0x39 => Some(3),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Decoder
+// ============================================================================
+
+pub fn decode(bytes: &[u8]) -> Result<WasmModule, String> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(4)? != MAGIC {
+        return Err("Keine WebAssembly-Datei (Kennung \\0asm fehlt)".to_string());
+    }
+    let version = u32::from_le_bytes(r.take(4)?.try_into().unwrap());
+    if version != VERSION {
+        return Err(format!("WebAssembly-Version {} wird nicht unterstützt", version));
+    }
+
+    let mut module = WasmModule {
+        types: Vec::new(),
+        imports: Vec::new(),
+        functions: Vec::new(),
+        table: Vec::new(),
+        memory_pages: 0,
+        globals: Vec::new(),
+        data: Vec::new(),
+        start: None,
+    };
+    let mut exports = Vec::new();
+
+    while r.pos < bytes.len() {
+        let id = r.byte()?;
+        let size = r.u32()? as usize;
+        let mut s = Reader { bytes: r.take(size)?, pos: 0 };
+        match id {
+            SECTION_TYPE => {
+                module.types = s.list(|s| {
+                    if s.byte()? != FUNC_TYPE {
+                        return Err("Unbekannte Typform".to_string());
+                    }
+                    Ok(FuncType { params: s.list(Reader::val_type)?, results: s.list(Reader::val_type)? })
+                })?
+            }
+            SECTION_IMPORT => {
+                module.imports = s.list(|s| {
+                    let module = s.name()?;
+                    let name = s.name()?;
+                    if s.byte()? != EXPORT_FUNC {
+                        return Err(format!("Import {}.{} ist keine Funktion", module, name));
+                    }
+                    Ok(Import { module, name, ty: s.u32()? })
+                })?
+            }
+            SECTION_FUNCTION => {
+                let types = s.list(Reader::u32)?;
+                module.functions = types
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, ty)| Function {
+                        name: format!("f{}", index + module.imports.len()),
+                        ty,
+                        export: None,
+                        locals: Vec::new(),
+                        body: Vec::new(),
+                    })
+                    .collect();
+            }
+            SECTION_TABLE | SECTION_MEMORY => {
+                s.u32()?;
+                if id == SECTION_TABLE && s.byte()? != FUNCREF {
+                    return Err("Tabelle ist nicht funcref".to_string());
+                }
+                if s.byte()? != 0 {
+                    return Err("Grenzen mit Maximum werden nicht unterstützt".to_string());
+                }
+                let size = s.u32()?;
+                if id == SECTION_MEMORY {
+                    module.memory_pages = size;
+                }
+            }
+            SECTION_GLOBAL => {
+                module.globals = s.list(|s| {
+                    let ty = s.val_type()?;
+                    let mutable = s.byte()? != 0;
+                    let init = match s.const_expr()? {
+                        Instruction::I32Const(value) => value as i64,
+                        Instruction::I64Const(value) => value,
+                        _ => return Err("Nicht unterstützter Anfangswert".to_string()),
+                    };
+                    Ok(Global { name: String::new(), ty, mutable, init })
+                })?;
+                for (index, global) in module.globals.iter_mut().enumerate() {
+                    global.name = format!("g{}", index);
+                }
+            }
+            SECTION_EXPORT => {
+                exports = s.list(|s| Ok((s.name()?, s.byte()?, s.u32()?)))?;
+            }
+            SECTION_START => module.start = Some(s.u32()?),
+            SECTION_ELEMENT => {
+                let segments = s.list(|s| {
+                    if s.u32()? != 0 || s.const_expr()? != Instruction::I32Const(1) {
+                        return Err("Nicht unterstütztes Element-Segment".to_string());
+                    }
+                    s.list(Reader::u32)
+                })?;
+                module.table = segments.into_iter().flatten().collect();
+            }
+            SECTION_CODE => {
+                let bodies = s.list(|s| {
+                    let size = s.u32()? as usize;
+                    let mut b = Reader { bytes: s.take(size)?, pos: 0 };
+                    let mut locals = Vec::new();
+                    for _ in 0..b.u32()? {
+                        let count = b.u32()?;
+                        let ty = b.val_type()?;
+                        locals.extend(std::iter::repeat_n(ty, count as usize));
+                    }
+                    let mut body = Vec::new();
+                    while b.pos < b.bytes.len() {
+                        body.push(b.instruction()?);
+                    }
+                    Ok((locals, body))
+                })?;
+                if bodies.len() != module.functions.len() {
+                    return Err("Anzahl der Funktionsrümpfe passt nicht".to_string());
+                }
+                for (function, (locals, body)) in module.functions.iter_mut().zip(bodies) {
+                    function.locals = locals;
+                    function.body = body;
+                }
+            }
+            SECTION_DATA => {
+                module.data = s.list(|s| {
+                    if s.u32()? != 0 {
+                        return Err("Nicht unterstütztes Daten-Segment".to_string());
+                    }
+                    let offset = match s.const_expr()? {
+                        Instruction::I32Const(offset) => offset as u32,
+                        _ => return Err("Nicht unterstützte Datenadresse".to_string()),
+                    };
+                    let length = s.u32()? as usize;
+                    Ok(Data { offset, bytes: s.take(length)?.to_vec() })
+                })?
+            }
+            _ => return Err(format!("Unbekannte Sektion {}", id)),
+        }
+        if s.pos != s.bytes.len() {
+            return Err(format!("Sektion {} enthält überzählige Bytes", id));
+        }
+    }
+
+    for (name, kind, index) in exports {
+        if kind != EXPORT_FUNC {
+            continue;
+        }
+        match (index as usize).checked_sub(module.imports.len()).and_then(|index| module.functions.get_mut(index)) {
+            Some(function) => {
+                function.name = name.clone();
+                function.export = Some(name);
+            }
+            None => return Err(format!("Export {} verweist auf keine Funktion", name)),
+        }
+    }
+    Ok(module)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.pos < count {
+            return Err("Unerwartetes Dateiende".to_string());
+        }
+        let taken = &self.bytes[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            result |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return u32::try_from(result).map_err(|_| "Zahl zu groß".to_string());
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err("Zahl zu lang".to_string());
+            }
+        }
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            result |= ((byte & 0x7F) as i64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Ok(result);
+            }
+            if shift >= 70 {
+                return Err("Zahl zu lang".to_string());
+            }
+        }
+    }
+
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let count = self.u32()?;
+        (0..count).map(|_| item(self)).collect()
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| "Name ist kein UTF-8".to_string())
+    }
+
+    fn val_type(&mut self) -> Result<ValType, String> {
+        match self.byte()? {
+            0x7F => Ok(ValType::I32),
+            0x7E => Ok(ValType::I64),
+            0x7C => Ok(ValType::F64),
+            other => Err(format!("Unbekannter Werttyp {:#x}", other)),
+        }
+    }
+
+    fn block_type(&mut self) -> Result<BlockType, String> {
+        if self.bytes.get(self.pos) == Some(&EMPTY_BLOCK) {
+            self.pos += 1;
+            return Ok(BlockType::Empty);
+        }
+        Ok(BlockType::Value(self.val_type()?))
+    }
+
+    // Konstanter Ausdruck aus einem Befehl und end
+    fn const_expr(&mut self) -> Result<Instruction, String> {
+        let value = self.instruction()?;
+        if self.instruction()? != Instruction::End {
+            return Err("Konstanter Ausdruck ohne end".to_string());
+        }
+        Ok(value)
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, String> {
+        use Instruction::*;
+        let opcode = self.byte()?;
+        if let Some(instruction) = simple_by_opcode(opcode) {
+            if memory_alignment(opcode).is_some() {
+                self.u32()?;
+                if self.u32()? != 0 {
+                    return Err("Speicherzugriff mit Offset wird nicht unterstützt".to_string());
+                }
+            } else if matches!(instruction, MemorySize | MemoryGrow) {
+                self.byte()?;
+            }
+            return Ok(instruction);
+        }
+
+        Ok(match opcode {
+            0x02 => Block(self.block_type()?),
+            0x03 => Loop(self.block_type()?),
+            0x04 => If(self.block_type()?),
+            0x0C => Br(self.u32()?),
+            0x0D => BrIf(self.u32()?),
+            0x0E => {
+                let count = self.u32()?;
+                let depths = (0..count).map(|_| self.u32()).collect::<Result<Vec<_>, _>>()?;
+                BrTable(depths, self.u32()?)
+            }
+            0x10 => Call(self.u32()?),
+            0x11 => {
+                let ty = self.u32()?;
+                self.byte()?;
+                CallIndirect(ty)
+            }
+            0x20 => LocalGet(self.u32()?),
+            0x21 => LocalSet(self.u32()?),
+            0x22 => LocalTee(self.u32()?),
+            0x23 => GlobalGet(self.u32()?),
+            0x24 => GlobalSet(self.u32()?),
+            0x41 => I32Const(self.i64()? as i32),
+            0x42 => I64Const(self.i64()?),
+            0x44 => F64Const(f64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            PREFIX_FC => match self.u32()? {
+                10 => {
+                    self.take(2)?;
+                    MemoryCopy
+                }
+                11 => {
+                    self.byte()?;
+                    MemoryFill
+                }
+                other => return Err(format!("Unbekannter Befehl 0xFC {}", other)),
+            },
+            other => return Err(format!("Unbekannter Opcode {:#04x}", other)),
+        })
+    }
+}
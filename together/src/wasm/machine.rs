@@ -0,0 +1,518 @@
+use super::{BlockType, Instruction, ValType, WasmModule, PAGE_SIZE};
+use std::collections::HashMap;
+
+// ============================================================================
+// Interpreter für Tests
+// ============================================================================
+//
+// Führt Module aus, wie sie encode/decode liefern, damit sich der
+// Code-Generator ohne Browser oder externe Laufzeit testen lässt. Er kennt
+// genau die Befehle aus super::Instruction und prüft Typen nur soweit, wie es
+// für die Ausführung nötig ist. Aufrufe laufen über einen eigenen Rahmenstapel,
+// tiefe Rekursion belastet also nicht den Rust-Stapel.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Val {
+    I32(i32),
+    I64(i64),
+    F64(f64),
+}
+
+impl Val {
+    fn zero(ty: ValType) -> Val {
+        match ty {
+            ValType::I32 => Val::I32(0),
+            ValType::I64 => Val::I64(0),
+            ValType::F64 => Val::F64(0.0),
+        }
+    }
+}
+
+// Importierte Funktion: Modul, Name, Argumente, Speicher
+pub type Host<'h> = dyn FnMut(&str, &str, &[Val], &mut [u8]) -> Result<Option<Val>, String> + 'h;
+
+// Höchstgröße des Speichers in Seiten
+const MAX_PAGES: usize = 1024;
+const MAX_FRAMES: usize = 100_000;
+
+struct Label {
+    // Höhe des Wertestapels beim Eintritt
+    height: usize,
+    // Anzahl der Werte, die ein Sprung mitnimmt
+    arity: usize,
+    // Sprungziel; bei Schleifen der erste Befehl im Rumpf
+    target: usize,
+    is_loop: bool,
+}
+
+struct Frame {
+    function: usize,
+    pc: usize,
+    locals: Vec<Val>,
+    labels: Vec<Label>,
+    height: usize,
+}
+
+pub struct Machine<'m> {
+    module: &'m WasmModule,
+    pub memory: Vec<u8>,
+    globals: Vec<Val>,
+    // Je Funktion: Position von Block/Loop/If bzw. Else -> passendes End;
+    // If -> Else
+    ends: Vec<HashMap<usize, usize>>,
+    elses: Vec<HashMap<usize, usize>>,
+}
+
+impl<'m> Machine<'m> {
+    pub fn new(module: &'m WasmModule) -> Result<Self, String> {
+        let mut memory = vec![0; module.memory_pages as usize * PAGE_SIZE as usize];
+        for data in &module.data {
+            let start = data.offset as usize;
+            match memory.get_mut(start..start + data.bytes.len()) {
+                Some(target) => target.copy_from_slice(&data.bytes),
+                None => return Err("Daten-Segment außerhalb des Speichers".to_string()),
+            }
+        }
+        let globals = module
+            .globals
+            .iter()
+            .map(|global| match global.ty {
+                ValType::I32 => Val::I32(global.init as i32),
+                ValType::I64 => Val::I64(global.init),
+                ValType::F64 => Val::F64(global.init as f64),
+            })
+            .collect();
+
+        let mut ends = Vec::new();
+        let mut elses = Vec::new();
+        for function in &module.functions {
+            let (mut end, mut els) = (HashMap::new(), HashMap::new());
+            let mut open = Vec::new();
+            for (pc, instruction) in function.body.iter().enumerate() {
+                match instruction {
+                    Instruction::Block(_) | Instruction::Loop(_) | Instruction::If(_) => open.push(pc),
+                    Instruction::Else => {
+                        let start = *open.last().ok_or("else ohne if")?;
+                        els.insert(start, pc);
+                        end.insert(pc, start);
+                    }
+                    Instruction::End => {
+                        if let Some(start) = open.pop() {
+                            end.insert(start, pc);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // Else zeigt zunächst auf sein If; jetzt auf das gemeinsame End
+            let resolved: Vec<(usize, usize)> = end
+                .iter()
+                .filter(|(pc, _)| matches!(function.body[**pc], Instruction::Else))
+                .map(|(&pc, start)| (pc, end[start]))
+                .collect();
+            end.extend(resolved);
+            ends.push(end);
+            elses.push(els);
+        }
+
+        Ok(Machine { module, memory, globals, ends, elses })
+    }
+
+    pub fn run_start(&mut self, host: &mut Host) -> Result<(), String> {
+        match self.module.start {
+            Some(start) => self.call(start, Vec::new(), host).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn invoke(&mut self, export: &str, args: &[Val], host: &mut Host) -> Result<Option<Val>, String> {
+        let index = self
+            .module
+            .functions
+            .iter()
+            .position(|function| function.export.as_deref() == Some(export))
+            .ok_or_else(|| format!("Kein Export {}", export))?;
+        let result = self.call((self.module.imports.len() + index) as u32, args.to_vec(), host)?;
+        Ok(result.first().copied())
+    }
+
+    fn call(&mut self, index: u32, args: Vec<Val>, host: &mut Host) -> Result<Vec<Val>, String> {
+        let mut stack = args;
+        let mut frames = Vec::new();
+        self.enter(index, &mut stack, &mut frames, host)?;
+
+        let module = self.module;
+        while let Some(frame) = frames.last_mut() {
+            let function = &module.functions[frame.function];
+            let instruction = &function.body[frame.pc];
+            frame.pc += 1;
+            if self.step(instruction, &mut stack, &mut frames, host)? {
+                break;
+            }
+        }
+        Ok(stack)
+    }
+
+    // Betritt eine Funktion des Moduls oder ruft einen Import auf; die
+    // Argumente liegen oben auf dem Stapel
+    fn enter(&mut self, index: u32, stack: &mut Vec<Val>, frames: &mut Vec<Frame>, host: &mut Host) -> Result<(), String> {
+        let ty = self.module.function_type(index).ok_or("Funktion nicht gefunden")?;
+        let args = stack.split_off(stack.len().checked_sub(ty.params.len()).ok_or("Zu wenige Argumente")?);
+
+        if let Some(import) = self.module.imports.get(index as usize) {
+            if let Some(result) = host(&import.module, &import.name, &args, &mut self.memory)? {
+                stack.push(result);
+            }
+            return Ok(());
+        }
+
+        if frames.len() >= MAX_FRAMES {
+            return Err("Aufruftiefe überschritten".to_string());
+        }
+        let function = index as usize - self.module.imports.len();
+        let mut locals = args;
+        locals.extend(self.module.functions[function].locals.iter().map(|&ty| Val::zero(ty)));
+        frames.push(Frame {
+            function,
+            pc: 0,
+            locals,
+            labels: vec![Label { height: stack.len(), arity: ty.results.len(), target: usize::MAX, is_loop: false }],
+            height: stack.len(),
+        });
+        Ok(())
+    }
+
+    // true, wenn der äußerste Rahmen zurückgekehrt ist
+    fn step(
+        &mut self,
+        instruction: &Instruction,
+        stack: &mut Vec<Val>,
+        frames: &mut Vec<Frame>,
+        host: &mut Host,
+    ) -> Result<bool, String> {
+        use Instruction::*;
+        let frame = frames.last_mut().unwrap();
+        let pc = frame.pc - 1;
+        let function = frame.function;
+
+        macro_rules! pop {
+            (I32) => {
+                match stack.pop() {
+                    Some(Val::I32(value)) => value,
+                    other => return Err(format!("i32 erwartet, gefunden {:?}", other)),
+                }
+            };
+            (I64) => {
+                match stack.pop() {
+                    Some(Val::I64(value)) => value,
+                    other => return Err(format!("i64 erwartet, gefunden {:?}", other)),
+                }
+            };
+            (F64) => {
+                match stack.pop() {
+                    Some(Val::F64(value)) => value,
+                    other => return Err(format!("f64 erwartet, gefunden {:?}", other)),
+                }
+            };
+        }
+        macro_rules! binary {
+            ($ty:ident, $result:ident, $op:expr) => {{
+                let b = pop!($ty);
+                let a = pop!($ty);
+                let op: fn(_, _) -> _ = $op;
+                stack.push(Val::$result(op(a, b)));
+            }};
+        }
+        macro_rules! unary {
+            ($ty:ident, $result:ident, $op:expr) => {{
+                let a = pop!($ty);
+                let op: fn(_) -> _ = $op;
+                stack.push(Val::$result(op(a)));
+            }};
+        }
+
+        match instruction {
+            Unreachable => return Err("unreachable ausgeführt".to_string()),
+            Block(ty) | Loop(ty) => {
+                let is_loop = matches!(instruction, Loop(_));
+                let end = self.ends[function][&pc];
+                frame.labels.push(Label {
+                    height: stack.len(),
+                    arity: if is_loop { 0 } else { arity(ty) },
+                    target: if is_loop { pc + 1 } else { end + 1 },
+                    is_loop,
+                });
+            }
+            If(ty) => {
+                let condition = pop!(I32);
+                let end = self.ends[function][&pc];
+                let label = Label { height: stack.len(), arity: arity(ty), target: end + 1, is_loop: false };
+                if condition != 0 {
+                    frame.labels.push(label);
+                } else if let Some(&els) = self.elses[function].get(&pc) {
+                    frame.labels.push(label);
+                    frame.pc = els + 1;
+                } else {
+                    frame.pc = end + 1;
+                }
+            }
+            // Ende des then-Zweigs: weiter hinter dem End
+            Else => {
+                frame.labels.pop();
+                frame.pc = self.ends[function][&pc] + 1;
+            }
+            End => {
+                frame.labels.pop();
+                if frame.labels.is_empty() {
+                    return Ok(self.leave(stack, frames));
+                }
+            }
+            Br(depth) => return self.branch(*depth as usize, stack, frames),
+            BrIf(depth) => {
+                if pop!(I32) != 0 {
+                    return self.branch(*depth as usize, stack, frames);
+                }
+            }
+            BrTable(depths, default) => {
+                let index = pop!(I32) as u32 as usize;
+                let depth = depths.get(index).unwrap_or(default);
+                return self.branch(*depth as usize, stack, frames);
+            }
+            Return => {
+                let depth = frame.labels.len() - 1;
+                return self.branch(depth, stack, frames);
+            }
+            Call(index) => self.enter(*index, stack, frames, host)?,
+            CallIndirect(ty) => {
+                let slot = pop!(I32) as usize;
+                let index = match slot.checked_sub(1).and_then(|slot| self.module.table.get(slot)) {
+                    Some(&index) => index,
+                    None => return Err(format!("Tabelleneintrag {} ist leer", slot)),
+                };
+                if self.module.function_type(index) != self.module.types.get(*ty as usize) {
+                    return Err("call_indirect mit falscher Signatur".to_string());
+                }
+                self.enter(index, stack, frames, host)?;
+            }
+            Drop => {
+                stack.pop();
+            }
+            Select => {
+                let condition = pop!(I32);
+                let b = stack.pop().ok_or("Stapel leer")?;
+                let a = stack.pop().ok_or("Stapel leer")?;
+                stack.push(if condition != 0 { a } else { b });
+            }
+
+            LocalGet(index) => stack.push(frame.locals[*index as usize]),
+            LocalSet(index) => frame.locals[*index as usize] = stack.pop().ok_or("Stapel leer")?,
+            LocalTee(index) => frame.locals[*index as usize] = *stack.last().ok_or("Stapel leer")?,
+            GlobalGet(index) => stack.push(self.globals[*index as usize]),
+            GlobalSet(index) => self.globals[*index as usize] = stack.pop().ok_or("Stapel leer")?,
+
+            I32Load => {
+                let address = pop!(I32);
+                let bytes = self.bytes(address, 4)?;
+                stack.push(Val::I32(i32::from_le_bytes(bytes.try_into().unwrap())));
+            }
+            I64Load => {
+                let address = pop!(I32);
+                let bytes = self.bytes(address, 8)?;
+                stack.push(Val::I64(i64::from_le_bytes(bytes.try_into().unwrap())));
+            }
+            F64Load => {
+                let address = pop!(I32);
+                let bytes = self.bytes(address, 8)?;
+                stack.push(Val::F64(f64::from_le_bytes(bytes.try_into().unwrap())));
+            }
+            I32Load8U => {
+                let address = pop!(I32);
+                let bytes = self.bytes(address, 1)?;
+                stack.push(Val::I32(bytes[0] as i32));
+            }
+            I32Store => {
+                let value = pop!(I32);
+                let address = pop!(I32);
+                self.store(address, &value.to_le_bytes())?;
+            }
+            I64Store => {
+                let value = pop!(I64);
+                let address = pop!(I32);
+                self.store(address, &value.to_le_bytes())?;
+            }
+            F64Store => {
+                let value = pop!(F64);
+                let address = pop!(I32);
+                self.store(address, &value.to_le_bytes())?;
+            }
+            I32Store8 => {
+                let value = pop!(I32);
+                let address = pop!(I32);
+                self.store(address, &[value as u8])?;
+            }
+            MemorySize => stack.push(Val::I32((self.memory.len() / PAGE_SIZE as usize) as i32)),
+            MemoryGrow => {
+                let pages = pop!(I32) as usize;
+                let old = self.memory.len() / PAGE_SIZE as usize;
+                if old + pages > MAX_PAGES {
+                    stack.push(Val::I32(-1));
+                } else {
+                    self.memory.resize((old + pages) * PAGE_SIZE as usize, 0);
+                    stack.push(Val::I32(old as i32));
+                }
+            }
+            MemoryCopy => {
+                let length = pop!(I32) as u32 as usize;
+                let source = pop!(I32) as u32 as usize;
+                let target = pop!(I32) as u32 as usize;
+                if source + length > self.memory.len() || target + length > self.memory.len() {
+                    return Err("memory.copy außerhalb des Speichers".to_string());
+                }
+                self.memory.copy_within(source..source + length, target);
+            }
+            MemoryFill => {
+                let length = pop!(I32) as u32 as usize;
+                let value = pop!(I32) as u8;
+                let target = pop!(I32) as u32 as usize;
+                match self.memory.get_mut(target..target + length) {
+                    Some(range) => range.fill(value),
+                    None => return Err("memory.fill außerhalb des Speichers".to_string()),
+                }
+            }
+
+            I32Const(value) => stack.push(Val::I32(*value)),
+            I64Const(value) => stack.push(Val::I64(*value)),
+            F64Const(value) => stack.push(Val::F64(*value)),
+
+            I32Eqz => unary!(I32, I32, |a: i32| (a == 0) as i32),
+            I32Eq => binary!(I32, I32, |a: i32, b: i32| (a == b) as i32),
+            I32Ne => binary!(I32, I32, |a: i32, b: i32| (a != b) as i32),
+            I32LtS => binary!(I32, I32, |a: i32, b: i32| (a < b) as i32),
+            I32LtU => binary!(I32, I32, |a: i32, b: i32| ((a as u32) < (b as u32)) as i32),
+            I32GtS => binary!(I32, I32, |a: i32, b: i32| (a > b) as i32),
+            I32GtU => binary!(I32, I32, |a: i32, b: i32| ((a as u32) > (b as u32)) as i32),
+            I32LeS => binary!(I32, I32, |a: i32, b: i32| (a <= b) as i32),
+            I32LeU => binary!(I32, I32, |a: i32, b: i32| ((a as u32) <= (b as u32)) as i32),
+            I32GeS => binary!(I32, I32, |a: i32, b: i32| (a >= b) as i32),
+            I32GeU => binary!(I32, I32, |a: i32, b: i32| ((a as u32) >= (b as u32)) as i32),
+            I64Eqz => unary!(I64, I32, |a: i64| (a == 0) as i32),
+            I64Eq => binary!(I64, I32, |a: i64, b: i64| (a == b) as i32),
+            I64Ne => binary!(I64, I32, |a: i64, b: i64| (a != b) as i32),
+            I64LtS => binary!(I64, I32, |a: i64, b: i64| (a < b) as i32),
+            I64LtU => binary!(I64, I32, |a: i64, b: i64| ((a as u64) < (b as u64)) as i32),
+            I64GtS => binary!(I64, I32, |a: i64, b: i64| (a > b) as i32),
+            I64LeS => binary!(I64, I32, |a: i64, b: i64| (a <= b) as i32),
+            I64GeS => binary!(I64, I32, |a: i64, b: i64| (a >= b) as i32),
+            I64GeU => binary!(I64, I32, |a: i64, b: i64| ((a as u64) >= (b as u64)) as i32),
+            F64Eq => binary!(F64, I32, |a: f64, b: f64| (a == b) as i32),
+            F64Ne => binary!(F64, I32, |a: f64, b: f64| (a != b) as i32),
+            F64Lt => binary!(F64, I32, |a: f64, b: f64| (a < b) as i32),
+            F64Gt => binary!(F64, I32, |a: f64, b: f64| (a > b) as i32),
+            F64Le => binary!(F64, I32, |a: f64, b: f64| (a <= b) as i32),
+            F64Ge => binary!(F64, I32, |a: f64, b: f64| (a >= b) as i32),
+
+            I32Add => binary!(I32, I32, |a: i32, b: i32| a.wrapping_add(b)),
+            I32Sub => binary!(I32, I32, |a: i32, b: i32| a.wrapping_sub(b)),
+            I32Mul => binary!(I32, I32, |a: i32, b: i32| a.wrapping_mul(b)),
+            I32And => binary!(I32, I32, |a: i32, b: i32| a & b),
+            I32Or => binary!(I32, I32, |a: i32, b: i32| a | b),
+            I32Xor => binary!(I32, I32, |a: i32, b: i32| a ^ b),
+            I32Shl => binary!(I32, I32, |a: i32, b: i32| a.wrapping_shl(b as u32)),
+            I32ShrU => binary!(I32, I32, |a: i32, b: i32| (a as u32).wrapping_shr(b as u32) as i32),
+            I64Add => binary!(I64, I64, |a: i64, b: i64| a.wrapping_add(b)),
+            I64Sub => binary!(I64, I64, |a: i64, b: i64| a.wrapping_sub(b)),
+            I64Mul => binary!(I64, I64, |a: i64, b: i64| a.wrapping_mul(b)),
+            I64DivS | I64RemS => {
+                let b = pop!(I64);
+                let a = pop!(I64);
+                let result = if matches!(instruction, I64DivS) { a.checked_div(b) } else { a.checked_rem(b) };
+                match result {
+                    Some(result) => stack.push(Val::I64(result)),
+                    None if b == 0 => return Err("Ganzzahlige Division durch 0".to_string()),
+                    None if matches!(instruction, I64RemS) => stack.push(Val::I64(0)),
+                    None => return Err("Ganzzahlüberlauf".to_string()),
+                }
+            }
+            I64And => binary!(I64, I64, |a: i64, b: i64| a & b),
+            I64Or => binary!(I64, I64, |a: i64, b: i64| a | b),
+            I64Xor => binary!(I64, I64, |a: i64, b: i64| a ^ b),
+            I64Shl => binary!(I64, I64, |a: i64, b: i64| a.wrapping_shl(b as u32)),
+            I64ShrS => binary!(I64, I64, |a: i64, b: i64| a.wrapping_shr(b as u32)),
+            I64Rotr => binary!(I64, I64, |a: i64, b: i64| a.rotate_right((b & 63) as u32)),
+            F64Abs => unary!(F64, F64, |a: f64| a.abs()),
+            F64Neg => unary!(F64, F64, |a: f64| -a),
+            F64Floor => unary!(F64, F64, |a: f64| a.floor()),
+            F64Add => binary!(F64, F64, |a: f64, b: f64| a + b),
+            F64Sub => binary!(F64, F64, |a: f64, b: f64| a - b),
+            F64Mul => binary!(F64, F64, |a: f64, b: f64| a * b),
+            F64Div => binary!(F64, F64, |a: f64, b: f64| a / b),
+
+            I32WrapI64 => unary!(I64, I32, |a: i64| a as i32),
+            I64ExtendI32U => unary!(I32, I64, |a: i32| a as u32 as i64),
+            F64ConvertI64S => unary!(I64, F64, |a: i64| a as f64),
+            I64TruncF64S => {
+                let a = pop!(F64);
+                if a.is_nan() || a < i64::MIN as f64 || a >= i64::MAX as f64 {
+                    return Err("Ungültige Umwandlung nach i64".to_string());
+                }
+                stack.push(Val::I64(a as i64));
+            }
+        }
+        Ok(false)
+    }
+
+    // Sprung zum Label `depth` (0 = innerster Block); das äußerste Label ist
+    // der Funktionsrumpf
+    fn branch(&mut self, depth: usize, stack: &mut Vec<Val>, frames: &mut Vec<Frame>) -> Result<bool, String> {
+        let frame = frames.last_mut().unwrap();
+        let index = frame.labels.len().checked_sub(depth + 1).ok_or("Sprungziel fehlt")?;
+        if index == 0 {
+            return Ok(self.leave(stack, frames));
+        }
+
+        let label = &frame.labels[index];
+        let results = stack.split_off(stack.len() - label.arity);
+        stack.truncate(label.height);
+        stack.extend(results);
+        frame.pc = label.target;
+        let keep = if label.is_loop { index + 1 } else { index };
+        frame.labels.truncate(keep);
+        Ok(false)
+    }
+
+    // Verlässt den obersten Rahmen mit seinen Ergebnissen; true, wenn es der
+    // letzte war
+    fn leave(&mut self, stack: &mut Vec<Val>, frames: &mut Vec<Frame>) -> bool {
+        let frame = frames.pop().unwrap();
+        let arity = self.module.types[self.module.functions[frame.function].ty as usize].results.len();
+        let results = stack.split_off(stack.len() - arity);
+        stack.truncate(frame.height);
+        stack.extend(results);
+        frames.is_empty()
+    }
+
+    fn bytes(&self, address: i32, length: usize) -> Result<&[u8], String> {
+        let start = address as u32 as usize;
+        self.memory
+            .get(start..start + length)
+            .ok_or_else(|| format!("Speicherzugriff außerhalb des Speichers bei {}", start))
+    }
+
+    fn store(&mut self, address: i32, bytes: &[u8]) -> Result<(), String> {
+        let start = address as u32 as usize;
+        match self.memory.get_mut(start..start + bytes.len()) {
+            Some(target) => {
+                target.copy_from_slice(bytes);
+                Ok(())
+            }
+            None => Err(format!("Speicherzugriff außerhalb des Speichers bei {}", start)),
+        }
+    }
+}
+
+fn arity(ty: &BlockType) -> usize {
+    match ty {
+        BlockType::Empty => 0,
+        BlockType::Value(_) => 1,
+    }
+}
@@ -0,0 +1,403 @@
+mod binary;
+mod text;
+
+#[cfg(test)]
+pub(crate) mod machine;
+
+pub use binary::{decode, encode};
+pub use text::to_wat;
+
+// ============================================================================
+// WebAssembly
+// ============================================================================
+//
+// Ausschnitt aus WebAssembly 2.0, soweit ihn der Code-Generator braucht: eine
+// Funktionstabelle für Prozedurvariablen, ein linearer Speicher und
+// veränderliche Globale für Stapel- und Heapzeiger. Module lassen sich als
+// Text (.wat) und binär (.wasm) ausgeben.
+//
+// Funktionsindizes zählen wie im Standard zuerst die Importe, dann die
+// Funktionen des Moduls.
+
+// Dateiendungen übersetzter Module
+pub const EXTENSION: &str = "wasm";
+pub const TEXT_EXTENSION: &str = "wat";
+
+// Größe einer Speicherseite
+pub const PAGE_SIZE: u32 = 65536;
+
+// Name, unter dem der lineare Speicher exportiert wird
+pub const MEMORY_EXPORT: &str = "memory";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValType {
+    I32,
+    I64,
+    F64,
+}
+
+impl ValType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F64 => "f64",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WasmModule {
+    pub types: Vec<FuncType>,
+    pub imports: Vec<Import>,
+    pub functions: Vec<Function>,
+    // Tabelleneintrag i+1 ist die Funktion table[i]; Eintrag 0 bleibt leer (NIL)
+    pub table: Vec<u32>,
+    // Anfangsgröße des Speichers in Seiten
+    pub memory_pages: u32,
+    pub globals: Vec<Global>,
+    pub data: Vec<Data>,
+    // Funktion, die beim Instanziieren läuft
+    pub start: Option<u32>,
+}
+
+// Importierte Funktion des Hosts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub ty: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    // Name für den Textformat und Fehlermeldungen
+    pub name: String,
+    pub ty: u32,
+    // Exportname, falls exportiert
+    pub export: Option<String>,
+    // Lokale Variablen nach den Parametern
+    pub locals: Vec<ValType>,
+    pub body: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    pub name: String,
+    pub ty: ValType,
+    pub mutable: bool,
+    pub init: i64,
+}
+
+// Initialisierter Speicherbereich ab `offset`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Data {
+    pub offset: u32,
+    pub bytes: Vec<u8>,
+}
+
+impl WasmModule {
+    // Signatur einer Funktion nach ihrem Index (Importe zuerst)
+    pub fn function_type(&self, index: u32) -> Option<&FuncType> {
+        let index = index as usize;
+        let ty = match self.imports.get(index) {
+            Some(import) => import.ty,
+            None => self.functions.get(index - self.imports.len())?.ty,
+        };
+        self.types.get(ty as usize)
+    }
+
+    pub fn function_name(&self, index: u32) -> String {
+        let index = index as usize;
+        match self.imports.get(index) {
+            Some(import) => format!("{}.{}", import.module, import.name),
+            None => match self.functions.get(index - self.imports.len()) {
+                Some(function) => function.name.clone(),
+                None => format!("f{}", index),
+            },
+        }
+    }
+}
+
+// ============================================================================
+// Befehle
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockType {
+    Empty,
+    Value(ValType),
+}
+
+// Speicherzugriffe verwenden die natürliche Ausrichtung und keinen Offset
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Unreachable,
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    Else,
+    End,
+    Br(u32),
+    BrIf(u32),
+    // Sprungziele nach dem Wert oben auf dem Stapel, danach das Standardziel
+    BrTable(Vec<u32>, u32),
+    Return,
+    Call(u32),
+    // Typindex der erwarteten Signatur
+    CallIndirect(u32),
+    Drop,
+    Select,
+
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+
+    I32Load,
+    I64Load,
+    F64Load,
+    I32Load8U,
+    I32Store,
+    I64Store,
+    F64Store,
+    I32Store8,
+    MemorySize,
+    MemoryGrow,
+    MemoryCopy,
+    MemoryFill,
+
+    I32Const(i32),
+    I64Const(i64),
+    F64Const(f64),
+
+    I32Eqz,
+    I32Eq,
+    I32Ne,
+    I32LtS,
+    I32LtU,
+    I32GtS,
+    I32GtU,
+    I32LeS,
+    I32LeU,
+    I32GeS,
+    I32GeU,
+    I64Eqz,
+    I64Eq,
+    I64Ne,
+    I64LtS,
+    I64LtU,
+    I64GtS,
+    I64LeS,
+    I64GeS,
+    I64GeU,
+    F64Eq,
+    F64Ne,
+    F64Lt,
+    F64Gt,
+    F64Le,
+    F64Ge,
+
+    I32Add,
+    I32Sub,
+    I32Mul,
+    I32And,
+    I32Or,
+    I32Xor,
+    I32Shl,
+    I32ShrU,
+    I64Add,
+    I64Sub,
+    I64Mul,
+    I64DivS,
+    I64RemS,
+    I64And,
+    I64Or,
+    I64Xor,
+    I64Shl,
+    I64ShrS,
+    I64Rotr,
+    F64Abs,
+    F64Neg,
+    F64Floor,
+    F64Add,
+    F64Sub,
+    F64Mul,
+    F64Div,
+
+    I32WrapI64,
+    I64TruncF64S,
+    I64ExtendI32U,
+    F64ConvertI64S,
+}
+
+// Opcode und Name im Textformat der Befehle ohne Operanden; None für die
+// übrigen. Die Tabelle ist die gemeinsame Grundlage von Text, Binärformat und
+// Decoder.
+const SIMPLE: &[(Instruction, u8, &str)] = &[
+    (Instruction::Unreachable, 0x00, "unreachable"),
+    (Instruction::Else, 0x05, "else"),
+    (Instruction::End, 0x0B, "end"),
+    (Instruction::Return, 0x0F, "return"),
+    (Instruction::Drop, 0x1A, "drop"),
+    (Instruction::Select, 0x1B, "select"),
+    (Instruction::I32Load, 0x28, "i32.load"),
+    (Instruction::I64Load, 0x29, "i64.load"),
+    (Instruction::F64Load, 0x2B, "f64.load"),
+    (Instruction::I32Load8U, 0x2D, "i32.load8_u"),
+    (Instruction::I32Store, 0x36, "i32.store"),
+    (Instruction::I64Store, 0x37, "i64.store"),
+    (Instruction::F64Store, 0x39, "f64.store"),
+    (Instruction::I32Store8, 0x3A, "i32.store8"),
+    (Instruction::MemorySize, 0x3F, "memory.size"),
+    (Instruction::MemoryGrow, 0x40, "memory.grow"),
+    (Instruction::I32Eqz, 0x45, "i32.eqz"),
+    (Instruction::I32Eq, 0x46, "i32.eq"),
+    (Instruction::I32Ne, 0x47, "i32.ne"),
+    (Instruction::I32LtS, 0x48, "i32.lt_s"),
+    (Instruction::I32LtU, 0x49, "i32.lt_u"),
+    (Instruction::I32GtS, 0x4A, "i32.gt_s"),
+    (Instruction::I32GtU, 0x4B, "i32.gt_u"),
+    (Instruction::I32LeS, 0x4C, "i32.le_s"),
+    (Instruction::I32LeU, 0x4D, "i32.le_u"),
+    (Instruction::I32GeS, 0x4E, "i32.ge_s"),
+    (Instruction::I32GeU, 0x4F, "i32.ge_u"),
+    (Instruction::I64Eqz, 0x50, "i64.eqz"),
+    (Instruction::I64Eq, 0x51, "i64.eq"),
+    (Instruction::I64Ne, 0x52, "i64.ne"),
+    (Instruction::I64LtS, 0x53, "i64.lt_s"),
+    (Instruction::I64LtU, 0x54, "i64.lt_u"),
+    (Instruction::I64GtS, 0x55, "i64.gt_s"),
+    (Instruction::I64LeS, 0x57, "i64.le_s"),
+    (Instruction::I64GeS, 0x59, "i64.ge_s"),
+    (Instruction::I64GeU, 0x5A, "i64.ge_u"),
+    (Instruction::F64Eq, 0x61, "f64.eq"),
+    (Instruction::F64Ne, 0x62, "f64.ne"),
+    (Instruction::F64Lt, 0x63, "f64.lt"),
+    (Instruction::F64Gt, 0x64, "f64.gt"),
+    (Instruction::F64Le, 0x65, "f64.le"),
+    (Instruction::F64Ge, 0x66, "f64.ge"),
+    (Instruction::I32Add, 0x6A, "i32.add"),
+    (Instruction::I32Sub, 0x6B, "i32.sub"),
+    (Instruction::I32Mul, 0x6C, "i32.mul"),
+    (Instruction::I32And, 0x71, "i32.and"),
+    (Instruction::I32Or, 0x72, "i32.or"),
+    (Instruction::I32Xor, 0x73, "i32.xor"),
+    (Instruction::I32Shl, 0x74, "i32.shl"),
+    (Instruction::I32ShrU, 0x76, "i32.shr_u"),
+    (Instruction::I64Add, 0x7C, "i64.add"),
+    (Instruction::I64Sub, 0x7D, "i64.sub"),
+    (Instruction::I64Mul, 0x7E, "i64.mul"),
+    (Instruction::I64DivS, 0x7F, "i64.div_s"),
+    (Instruction::I64RemS, 0x81, "i64.rem_s"),
+    (Instruction::I64And, 0x83, "i64.and"),
+    (Instruction::I64Or, 0x84, "i64.or"),
+    (Instruction::I64Xor, 0x85, "i64.xor"),
+    (Instruction::I64Shl, 0x86, "i64.shl"),
+    (Instruction::I64ShrS, 0x87, "i64.shr_s"),
+    (Instruction::I64Rotr, 0x8A, "i64.rotr"),
+    (Instruction::F64Abs, 0x99, "f64.abs"),
+    (Instruction::F64Neg, 0x9A, "f64.neg"),
+    (Instruction::F64Floor, 0x9C, "f64.floor"),
+    (Instruction::F64Add, 0xA0, "f64.add"),
+    (Instruction::F64Sub, 0xA1, "f64.sub"),
+    (Instruction::F64Mul, 0xA2, "f64.mul"),
+    (Instruction::F64Div, 0xA3, "f64.div"),
+    (Instruction::I32WrapI64, 0xA7, "i32.wrap_i64"),
+    (Instruction::I64TruncF64S, 0xB0, "i64.trunc_f64_s"),
+    (Instruction::I64ExtendI32U, 0xAD, "i64.extend_i32_u"),
+    (Instruction::F64ConvertI64S, 0xB9, "f64.convert_i64_s"),
+];
+
+fn simple(instruction: &Instruction) -> Option<(u8, &'static str)> {
+    SIMPLE
+        .iter()
+        .find(|(simple, _, _)| simple == instruction)
+        .map(|&(_, opcode, name)| (opcode, name))
+}
+
+fn simple_by_opcode(opcode: u8) -> Option<Instruction> {
+    SIMPLE
+        .iter()
+        .find(|&&(_, simple, _)| simple == opcode)
+        .map(|(instruction, _, _)| instruction.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Kleines Modul von Hand: Fakultät rekursiv
+    fn factorial() -> WasmModule {
+        use Instruction::*;
+        WasmModule {
+            types: vec![FuncType { params: vec![ValType::I64], results: vec![ValType::I64] }],
+            imports: Vec::new(),
+            functions: vec![Function {
+                name: "Fact".to_string(),
+                ty: 0,
+                export: Some("Fact".to_string()),
+                locals: Vec::new(),
+                body: vec![
+                    LocalGet(0),
+                    I64Const(1),
+                    I64LeS,
+                    If(BlockType::Value(ValType::I64)),
+                    I64Const(1),
+                    Else,
+                    LocalGet(0),
+                    LocalGet(0),
+                    I64Const(1),
+                    I64Sub,
+                    Call(0),
+                    I64Mul,
+                    End,
+                    End,
+                ],
+            }],
+            table: vec![0],
+            memory_pages: 1,
+            globals: vec![Global { name: "sp".to_string(), ty: ValType::I32, mutable: true, init: 4096 }],
+            data: vec![Data { offset: 16, bytes: b"Hallo\0".to_vec() }],
+            start: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let module = factorial();
+        let bytes = encode(&module);
+        assert_eq!(&bytes[..8], b"\0asm\x01\0\0\0");
+        let decoded = decode(&bytes).unwrap();
+        // Namen stehen nur im Textformat
+        assert_eq!(decoded.functions[0].body, module.functions[0].body);
+        assert_eq!(decoded.functions[0].export.as_deref(), Some("Fact"));
+        assert_eq!((decoded.table.clone(), decoded.memory_pages), (vec![0], 1));
+        assert_eq!(decoded.data, module.data);
+        assert_eq!(decoded.globals[0].init, 4096);
+
+        assert!(decode(&bytes[..bytes.len() - 3]).is_err());
+        assert!(decode(b"\0asn\x01\0\0\0").is_err());
+
+        let mut machine = machine::Machine::new(&decoded).unwrap();
+        let result = machine.invoke("Fact", &[machine::Val::I64(10)], &mut |_, _, _, _| Ok(None));
+        assert_eq!(result, Ok(Some(machine::Val::I64(3628800))));
+    }
+
+    #[test]
+    fn test_wat() {
+        let wat = to_wat(&factorial());
+        assert!(wat.starts_with("(module\n  (type $t0 (func (param i64) (result i64)))\n"));
+        assert!(wat.contains("  (func $Fact (export \"Fact\") (type $t0) (param i64) (result i64)\n"));
+        assert!(wat.contains("    if (result i64)\n      i64.const 1\n    else\n"));
+        assert!(wat.contains("      call $Fact\n"));
+        assert!(wat.contains("  (data (i32.const 16) \"Hallo\\00\")\n"));
+    }
+}
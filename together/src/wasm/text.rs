@@ -0,0 +1,173 @@
+use super::{simple, BlockType, FuncType, Instruction, WasmModule, MEMORY_EXPORT};
+use std::fmt::Write;
+
+// ============================================================================
+// Textformat (.wat)
+// ============================================================================
+//
+// Befehle stehen linear, nicht gefaltet; Blöcke werden eingerückt. Funktionen
+// und Globale erhalten ihre Namen als Bezeichner ($Name), Typen $t<Index>.
+
+pub fn to_wat(module: &WasmModule) -> String {
+    let mut out = String::new();
+    writeln!(out, "(module").unwrap();
+
+    for (index, ty) in module.types.iter().enumerate() {
+        writeln!(out, "  (type $t{} (func{}))", index, signature(ty)).unwrap();
+    }
+
+    for (index, import) in module.imports.iter().enumerate() {
+        writeln!(
+            out,
+            "  (import \"{}\" \"{}\" (func {} (type $t{}){}))",
+            import.module,
+            import.name,
+            function_id(module, index as u32),
+            import.ty,
+            signature(&module.types[import.ty as usize])
+        )
+        .unwrap();
+    }
+
+    writeln!(out, "  (table {} funcref)", module.table.len() + 1).unwrap();
+    writeln!(out, "  (memory (export \"{}\") {})", MEMORY_EXPORT, module.memory_pages).unwrap();
+    for global in &module.globals {
+        let ty = if global.mutable { format!("(mut {})", global.ty.name()) } else { global.ty.name().to_string() };
+        writeln!(out, "  (global ${} {} ({}.const {}))", global.name, ty, global.ty.name(), global.init).unwrap();
+    }
+
+    if !module.table.is_empty() {
+        let functions: Vec<String> = module.table.iter().map(|&index| function_id(module, index)).collect();
+        writeln!(out, "  (elem (i32.const 1) func {})", functions.join(" ")).unwrap();
+    }
+
+    for (offset, function) in module.functions.iter().enumerate() {
+        let index = (module.imports.len() + offset) as u32;
+        let ty = &module.types[function.ty as usize];
+        write!(out, "  (func {}", function_id(module, index)).unwrap();
+        if let Some(export) = &function.export {
+            write!(out, " (export \"{}\")", export).unwrap();
+        }
+        writeln!(out, " (type $t{}){}", function.ty, signature(ty)).unwrap();
+        if !function.locals.is_empty() {
+            let locals: Vec<&str> = function.locals.iter().map(|local| local.name()).collect();
+            writeln!(out, "    (local {})", locals.join(" ")).unwrap();
+        }
+
+        // Das abschließende end des Funktionsrumpfs schließt die Klammer
+        let mut depth = 2;
+        let body = match function.body.split_last() {
+            Some((Instruction::End, body)) => body,
+            _ => &function.body[..],
+        };
+        for instruction in body {
+            if matches!(instruction, Instruction::End | Instruction::Else) {
+                depth -= 1;
+            }
+            writeln!(out, "{}{}", "  ".repeat(depth), listing(module, instruction)).unwrap();
+            if matches!(
+                instruction,
+                Instruction::Block(_) | Instruction::Loop(_) | Instruction::If(_) | Instruction::Else
+            ) {
+                depth += 1;
+            }
+        }
+        writeln!(out, "  )").unwrap();
+    }
+
+    if let Some(start) = module.start {
+        writeln!(out, "  (start {})", function_id(module, start)).unwrap();
+    }
+
+    for data in &module.data {
+        writeln!(out, "  (data (i32.const {}) \"{}\")", data.offset, escape(&data.bytes)).unwrap();
+    }
+    writeln!(out, ")").unwrap();
+    out
+}
+
+fn signature(ty: &FuncType) -> String {
+    let mut text = String::new();
+    if !ty.params.is_empty() {
+        let params: Vec<&str> = ty.params.iter().map(|param| param.name()).collect();
+        write!(text, " (param {})", params.join(" ")).unwrap();
+    }
+    if !ty.results.is_empty() {
+        let results: Vec<&str> = ty.results.iter().map(|result| result.name()).collect();
+        write!(text, " (result {})", results.join(" ")).unwrap();
+    }
+    text
+}
+
+// Bezeichner im Textformat dürfen keine Leerzeichen und Anführungszeichen
+// enthalten; qualifizierte Namen ("P.Q") sind erlaubt
+fn function_id(module: &WasmModule, index: u32) -> String {
+    format!("${}", module.function_name(index))
+}
+
+fn block_type(ty: &BlockType) -> String {
+    match ty {
+        BlockType::Empty => String::new(),
+        BlockType::Value(ty) => format!(" (result {})", ty.name()),
+    }
+}
+
+fn listing(module: &WasmModule, instruction: &Instruction) -> String {
+    use Instruction::*;
+    if let Some((_, name)) = simple(instruction) {
+        return name.to_string();
+    }
+
+    let global_name = |index: &u32| match module.globals.get(*index as usize) {
+        Some(global) => format!("${}", global.name),
+        None => index.to_string(),
+    };
+    match instruction {
+        Block(ty) => format!("block{}", block_type(ty)),
+        Loop(ty) => format!("loop{}", block_type(ty)),
+        If(ty) => format!("if{}", block_type(ty)),
+        Br(depth) => format!("br {}", depth),
+        BrIf(depth) => format!("br_if {}", depth),
+        BrTable(depths, default) => {
+            let depths: Vec<String> = depths.iter().chain([default]).map(|depth| depth.to_string()).collect();
+            format!("br_table {}", depths.join(" "))
+        }
+        Call(index) => format!("call {}", function_id(module, *index)),
+        CallIndirect(ty) => format!("call_indirect (type $t{})", ty),
+        LocalGet(index) => format!("local.get {}", index),
+        LocalSet(index) => format!("local.set {}", index),
+        LocalTee(index) => format!("local.tee {}", index),
+        GlobalGet(index) => format!("global.get {}", global_name(index)),
+        GlobalSet(index) => format!("global.set {}", global_name(index)),
+        MemoryCopy => "memory.copy".to_string(),
+        MemoryFill => "memory.fill".to_string(),
+        I32Const(value) => format!("i32.const {}", value),
+        I64Const(value) => format!("i64.const {}", value),
+        F64Const(value) => format!("f64.const {}", float(*value)),
+        other => unreachable!("{:?} fehlt in der Befehlstabelle", other),
+    }
+}
+
+// Sonderwerte heißen im Textformat inf und nan
+fn float(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+// Druckbare ASCII-Zeichen bleiben, alles andere als \hh
+fn escape(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => write!(text, "\\{}", byte as char).unwrap(),
+            0x20..=0x7E => text.push(byte as char),
+            _ => write!(text, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    text
+}